@@ -1,9 +1,11 @@
 use criterion::{criterion_group, criterion_main, Criterion};
 use gget::dependency::DependencyResolver;
+use std::fs;
 use std::hint::black_box;
+use tempfile::TempDir;
 
 fn bench_extract_dependencies(c: &mut Criterion) {
-    let mut resolver = DependencyResolver::new().unwrap();
+    let resolver = DependencyResolver::new().unwrap();
 
     // 테스트용 Go 소스 코드
     let source_code = r#"
@@ -27,7 +29,7 @@ fn bench_extract_dependencies(c: &mut Criterion) {
 }
 
 fn bench_extract_dependencies_large_file(c: &mut Criterion) {
-    let mut resolver = DependencyResolver::new().unwrap();
+    let resolver = DependencyResolver::new().unwrap();
 
     // 더 큰 테스트 파일 생성
     let mut large_source = String::from("package main\n\nimport (\n");
@@ -44,9 +46,41 @@ fn bench_extract_dependencies_large_file(c: &mut Criterion) {
     });
 }
 
+fn bench_extract_dependencies_from_directory(c: &mut Criterion) {
+    // 패키지 50개, 파일 500개짜리 디렉토리 트리
+    let temp_dir = TempDir::new().unwrap();
+    for i in 0..500 {
+        let dir = temp_dir.path().join(format!("pkg{}", i % 50));
+        fs::create_dir_all(&dir).unwrap();
+        let mut source = format!("package pkg{}\n\nimport (\n", i % 50);
+        for j in 0..20 {
+            source.push_str(&format!(
+                "    \"gno.land/p/demo/import{}\"\n",
+                (i + j) % 100
+            ));
+        }
+        source.push_str(")\n\nfunc main() {\n    // some code\n}\n");
+        fs::write(dir.join(format!("file{}.gno", i)), source).unwrap();
+    }
+
+    let resolver = DependencyResolver::new().unwrap();
+    let mut group = c.benchmark_group("extract_dependencies_from_directory");
+    group.bench_function("sequential", |b| {
+        b.iter(|| black_box(resolver.extract_dependencies_from_directory(temp_dir.path())).unwrap())
+    });
+    group.bench_function("parallel", |b| {
+        b.iter(|| {
+            black_box(resolver.extract_dependencies_from_directory_parallel(temp_dir.path()))
+                .unwrap()
+        })
+    });
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_extract_dependencies,
-    bench_extract_dependencies_large_file
+    bench_extract_dependencies_large_file,
+    bench_extract_dependencies_from_directory
 );
 criterion_main!(benches);
@@ -0,0 +1,207 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use gget::cache::{MemoryStorage, NoopCache};
+use gget::fetch::{PackageManager, PackageManagerBuilder};
+use gget::parallel::ParallelDownloadOptions;
+use gget::testing::FakeChain;
+use std::time::Duration;
+use tempfile::tempdir;
+use tokio::runtime::Runtime;
+
+const PACKAGES: usize = 50;
+const FILES_PER_PACKAGE: usize = 4;
+/// Realm importing every package of the benchmark chain
+const ROOT: &str = "gno.land/r/bench/app";
+
+/// A closure of [PACKAGES] packages of [FILES_PER_PACKAGE] files each,
+/// all imported by [ROOT]
+fn chain() -> (FakeChain, Vec<String>) {
+    let mut chain = FakeChain::new();
+    let mut paths = Vec::with_capacity(PACKAGES);
+    for i in 0..PACKAGES {
+        let path = format!("gno.land/p/bench/pkg{}", i);
+        let files: Vec<(String, String)> = (0..FILES_PER_PACKAGE)
+            .map(|f| (format!("f{}.gno", f), format!("package pkg{}\n", i)))
+            .collect();
+        let files: Vec<(&str, &str)> = files
+            .iter()
+            .map(|(name, content)| (name.as_str(), content.as_str()))
+            .collect();
+        chain = chain.with_package(&path, &files);
+        paths.push(path);
+    }
+
+    let mut root = String::from("package app\n\nimport (\n");
+    for path in &paths {
+        root.push_str(&format!("\t\"{}\"\n", path));
+    }
+    root.push_str(")\n");
+    (chain.with_package(ROOT, &[("app.gno", &root)]), paths)
+}
+
+fn options(max_concurrent: usize) -> ParallelDownloadOptions {
+    ParallelDownloadOptions {
+        max_concurrent,
+        show_progress: false,
+        ..Default::default()
+    }
+}
+
+/// Downloads every package with a fresh, empty cache, so every file is
+/// queried
+async fn download(builder: PackageManagerBuilder, url: &str, paths: &[String]) {
+    let pm = builder
+        .endpoint(url)
+        .storage(NoopCache)
+        .build()
+        .unwrap()
+        .with_quiet(true);
+    download_with(&pm, paths, options(4)).await;
+}
+
+async fn download_with(pm: &PackageManager, paths: &[String], options: ParallelDownloadOptions) {
+    let target = tempdir().unwrap();
+    let summary = pm
+        .download_packages_parallel(
+            paths.iter().map(String::as_str).collect(),
+            target.path(),
+            options,
+        )
+        .await
+        .unwrap();
+    assert!(summary.failed.is_empty());
+}
+
+/// A package manager on `url` that caches nothing
+fn uncached(url: &str) -> PackageManager {
+    PackageManager::builder()
+        .endpoint(url)
+        .storage(NoopCache)
+        .build()
+        .unwrap()
+        .with_quiet(true)
+}
+
+/// Bulk downloads opening new connections (cold) against ones reusing the
+/// kept-alive connections of a shared client (warm)
+fn bench_bulk_download(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let (chain, paths) = chain();
+    let url = runtime.block_on(async { chain.spawn() });
+
+    let mut group = c.benchmark_group("bulk_download");
+    group.sample_size(10);
+    group.bench_function("cold", |b| {
+        b.iter(|| runtime.block_on(download(PackageManager::builder(), &url, &paths)))
+    });
+
+    let client = PackageManager::builder().build_http_client().unwrap();
+    group.bench_function("warm", |b| {
+        b.iter(|| {
+            let builder = PackageManager::builder().http_client(client.clone());
+            runtime.block_on(download(builder, &url, &paths))
+        })
+    });
+    group.bench_function("warm_http2", |b| {
+        let client = PackageManager::builder()
+            .http2_prior_knowledge(true)
+            .build_http_client()
+            .unwrap();
+        b.iter(|| {
+            let builder = PackageManager::builder().http_client(client.clone());
+            runtime.block_on(download(builder, &url, &paths))
+        })
+    });
+    group.finish();
+}
+
+/// One package end to end, from listing its files to installing them
+fn bench_single_package(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let (chain, paths) = chain();
+    let url = runtime.block_on(async { chain.spawn() });
+    let pm = runtime.block_on(async { uncached(&url) });
+
+    let mut group = c.benchmark_group("single_package");
+    group.throughput(Throughput::Elements(FILES_PER_PACKAGE as u64));
+    group.bench_function("download_package", |b| {
+        b.iter(|| {
+            runtime.block_on(async {
+                let target = tempdir().unwrap();
+                pm.download_package(&paths[0], target.path()).await.unwrap();
+            })
+        })
+    });
+    group.finish();
+}
+
+/// Parallel downloads at several concurrency levels, against a node that
+/// takes a little while to answer like a remote one would
+fn bench_concurrency(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let (chain, paths) = chain();
+    chain.set_latency(Duration::from_millis(2));
+    let url = runtime.block_on(async { chain.spawn() });
+    let pm = runtime.block_on(async { uncached(&url) });
+
+    let mut group = c.benchmark_group("parallel_download");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(PACKAGES as u64));
+    for max_concurrent in [1, 4, 16] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(max_concurrent),
+            &max_concurrent,
+            |b, &max_concurrent| {
+                b.iter(|| runtime.block_on(download_with(&pm, &paths, options(max_concurrent))))
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Downloads served from a cache filled by an earlier run, so only the
+/// pipeline itself is measured, then resolving [ROOT]'s dependencies and
+/// downloading them together
+fn bench_cache_warm(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let (chain, paths) = chain();
+    let url = runtime.block_on(async { chain.spawn() });
+    let pm = runtime.block_on(async {
+        let pm = PackageManager::builder()
+            .endpoint(&url)
+            .storage(MemoryStorage::new(Duration::from_secs(3600)))
+            .build()
+            .unwrap()
+            .with_quiet(true);
+        download_with(&pm, &paths, options(4)).await;
+        pm
+    });
+
+    let mut group = c.benchmark_group("cache_warm");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(PACKAGES as u64));
+    group.bench_function("download_packages_parallel", |b| {
+        b.iter(|| runtime.block_on(download_with(&pm, &paths, options(4))))
+    });
+    group.bench_function("download_with_deps_parallel", |b| {
+        b.iter(|| {
+            runtime.block_on(async {
+                let target = tempdir().unwrap();
+                let summary = pm
+                    .download_with_deps_parallel(ROOT, target.path(), options(4))
+                    .await
+                    .unwrap();
+                assert_eq!(summary.successful, PACKAGES + 1);
+            })
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_bulk_download,
+    bench_single_package,
+    bench_concurrency,
+    bench_cache_warm
+);
+criterion_main!(benches);
@@ -0,0 +1,546 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Seek, Write};
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::cache::{CacheError, HybridCache, SeedSummary};
+use crate::dependency::{DependencyError, DependencyResolver};
+use crate::error::ErrorKind;
+use crate::fetch::{PackageManager, PackageManagerError};
+use crate::layout::Layout;
+use crate::lock::{hash_content, LockError, LockedPackage, Lockfile, LOCKFILE_NAME};
+use crate::plan::{DownloadPlan, FileAction, PlannedFile, PlannedPackage};
+use crate::report::{Report, Table};
+
+/// First bytes of a gzip stream
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum ArchiveError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Lockfile error: {0}")]
+    Lock(#[from] LockError),
+
+    #[error("Dependency error: {0}")]
+    Dependency(#[from] DependencyError),
+
+    #[error(transparent)]
+    PackageManager(#[from] PackageManagerError),
+
+    #[error(transparent)]
+    Cache(#[from] CacheError),
+
+    #[error("No {LOCKFILE_NAME} found in {}", .0.display())]
+    MissingLockfile(PathBuf),
+
+    #[error("{} has no manifest; export it with --manifest", .0.display())]
+    MissingManifest(PathBuf),
+
+    #[error("{0} isn't locked in {LOCKFILE_NAME}")]
+    NotLocked(String),
+
+    #[error("{package} was modified since it was locked: {}", .files.join(", "))]
+    Modified { package: String, files: Vec<String> },
+
+    #[error("Archive entry {0} would be written outside the target directory")]
+    UnsafePath(String),
+
+    #[error("{file} of {package} doesn't match the archive manifest")]
+    Corrupt { package: String, file: String },
+
+    #[error("The archive uses the {archive} layout but {} uses {target}", .lockfile.display())]
+    LayoutMismatch {
+        archive: Layout,
+        target: Layout,
+        lockfile: PathBuf,
+    },
+}
+
+impl ArchiveError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(_) => ErrorKind::Internal,
+            Self::Dependency(_) => ErrorKind::Validation,
+            Self::PackageManager(e) => e.kind(),
+            Self::Cache(e) => e.kind(),
+            Self::Modified { .. } | Self::UnsafePath(_) | Self::Corrupt { .. } => {
+                ErrorKind::Integrity
+            }
+            Self::Lock(_)
+            | Self::MissingLockfile(_)
+            | Self::MissingManifest(_)
+            | Self::NotLocked(_)
+            | Self::LayoutMismatch { .. } => ErrorKind::Config,
+        }
+    }
+}
+
+/// Container an export is written in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArchiveFormat {
+    Tar,
+    #[default]
+    TarGz,
+}
+
+impl ArchiveFormat {
+    pub const ALL: [ArchiveFormat; 2] = [Self::Tar, Self::TarGz];
+
+    /// File name extension, without the leading dot
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Tar => "tar",
+            Self::TarGz => "tar.gz",
+        }
+    }
+}
+
+impl fmt::Display for ArchiveFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+impl FromStr for ArchiveFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tar" => Ok(Self::Tar),
+            "tar.gz" | "tgz" => Ok(Self::TarGz),
+            other => Err(format!(
+                "unknown archive format `{}`, expected tar or tar.gz",
+                other
+            )),
+        }
+    }
+}
+
+/// Options for [export_archive]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExportOptions {
+    pub format: ArchiveFormat,
+    /// Include every locked package the exported one imports, recursively
+    pub with_deps: bool,
+    /// Include a `gget.lock` listing the exported packages, which lets
+    /// [import_archive] check them and seed the cache
+    pub manifest: bool,
+}
+
+/// Options for [import_archive]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ImportOptions {
+    /// Overwrite locked packages even if they were modified since
+    pub force: bool,
+}
+
+/// What [export_archive] or [import_archive] moved
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ArchiveSummary {
+    pub archive: PathBuf,
+    /// Packages listed in the manifest, empty without one
+    pub packages: Vec<String>,
+    pub files: usize,
+    pub bytes: u64,
+    pub manifest: bool,
+    /// Packages stored in the cache by an import
+    pub cached: usize,
+}
+
+impl Report for ArchiveSummary {
+    fn table(&self) -> Table {
+        let mut table = Table::new(&self.archive.display().to_string(), &["package"]);
+        for package in &self.packages {
+            table.push_row([package.as_str()]);
+        }
+        table
+    }
+
+    fn human(&self) -> String {
+        let mut out = format!(
+            "{}: {} files ({} bytes)",
+            self.archive.display(),
+            self.files,
+            self.bytes
+        );
+        if self.manifest {
+            out.push_str(&format!(", {} packages", self.packages.len()));
+        } else {
+            out.push_str(", no manifest");
+        }
+        if self.cached > 0 {
+            out.push_str(&format!(", {} cached", self.cached));
+        }
+        out.push('\n');
+        out
+    }
+}
+
+/// Packs the locked package `pkg_path` under `target_dir` into `archive`.
+///
+/// Entries are laid out as under `target_dir` and only hold the locked
+/// files, so the archive extracts into a tree `gget` itself could have
+/// written. Packages modified since they were locked are refused. Headers
+/// carry no timestamps or owners, so exporting the same tree twice gives
+/// the same bytes.
+pub fn export_archive(
+    pkg_path: &str,
+    target_dir: &Path,
+    archive: &Path,
+    options: ExportOptions,
+) -> Result<ArchiveSummary, ArchiveError> {
+    let lock = Lockfile::load_if_exists(&target_dir.join(LOCKFILE_NAME))?
+        .ok_or_else(|| ArchiveError::MissingLockfile(target_dir.to_path_buf()))?;
+    if lock.get(pkg_path).is_none() {
+        return Err(ArchiveError::NotLocked(pkg_path.to_string()));
+    }
+
+    let packages = if options.with_deps {
+        locked_closure(&lock, pkg_path, target_dir)?
+    } else {
+        BTreeSet::from([pkg_path.to_string()])
+    };
+
+    let mut manifest = Lockfile {
+        roots: vec![pkg_path.to_string()],
+        layout: lock.layout,
+        ..Default::default()
+    };
+    let mut entries = BTreeMap::new();
+    for path in &packages {
+        let locked = lock
+            .get(path)
+            .ok_or_else(|| ArchiveError::NotLocked(path.clone()))?;
+        let dir = lock.package_dir(target_dir, path);
+        let changed = locked.changed_files(&dir);
+        if !changed.is_empty() {
+            return Err(ArchiveError::Modified {
+                package: path.clone(),
+                files: changed,
+            });
+        }
+
+        let prefix = lock.package_dir(Path::new(""), path);
+        for name in locked.files.keys() {
+            entries.insert(entry_name(&prefix.join(name)), fs::read(dir.join(name))?);
+        }
+        manifest.insert(locked.clone());
+    }
+
+    let mut summary = ArchiveSummary {
+        archive: archive.to_path_buf(),
+        packages: Vec::new(),
+        files: entries.len(),
+        bytes: entries.values().map(|c| c.len() as u64).sum(),
+        manifest: options.manifest,
+        cached: 0,
+    };
+    if options.manifest {
+        let mut json = serde_json::to_vec_pretty(&manifest).map_err(LockError::from)?;
+        json.push(b'\n');
+        entries.insert(LOCKFILE_NAME.to_string(), json);
+        summary.packages = packages.into_iter().collect();
+    }
+
+    let file = BufWriter::new(File::create(archive)?);
+    match options.format {
+        ArchiveFormat::Tar => write_tar(file, &entries)?.flush()?,
+        ArchiveFormat::TarGz => write_tar(GzEncoder::new(file, Compression::default()), &entries)?
+            .finish()?
+            .flush()?,
+    }
+    Ok(summary)
+}
+
+/// Extracts `archive` into `target_dir`.
+///
+/// When the archive has a manifest, every packaged file is checked against
+/// it before anything is written, the manifest's packages are added to the
+/// lockfile in `target_dir`, and their files are stored in the cache of
+/// `pm` so later downloads don't need the network. Locked packages modified
+/// since they were locked are refused, unless [ImportOptions::force] is set.
+///
+/// Files are staged first and moved into place once all of them are, and
+/// other gget processes writing to `target_dir` wait for the import.
+pub async fn import_archive(
+    pm: &PackageManager,
+    archive: &Path,
+    target_dir: &Path,
+    options: ImportOptions,
+) -> Result<ArchiveSummary, ArchiveError> {
+    let mut entries = read_archive(archive)?;
+    let manifest = take_manifest(&mut entries)?;
+
+    let lock_path = target_dir.join(LOCKFILE_NAME);
+    // held from reading the lockfile to writing it, like a download
+    let _lock = pm.lock_target(&lock_path).await?;
+    let mut lock = Lockfile::load_or_default(&lock_path)?;
+    let mut packaged = Vec::new();
+    let mut package_locks = Vec::new();
+    if let Some(manifest) = &manifest {
+        if !lock.packages.is_empty() && lock.layout != manifest.layout {
+            return Err(ArchiveError::LayoutMismatch {
+                archive: manifest.layout,
+                target: lock.layout,
+                lockfile: lock_path,
+            });
+        }
+        packaged = manifest_packages(&entries, manifest)?;
+
+        for (locked, _) in &packaged {
+            let dir = manifest.package_dir(target_dir, &locked.path);
+            package_locks.push(pm.lock_target(&dir).await?);
+            let Some(existing) = lock.get(&locked.path) else {
+                continue;
+            };
+            if options.force || !dir.is_dir() {
+                continue;
+            }
+            let changed = existing.changed_files(&dir);
+            // nothing is lost if the copy already is the archived one
+            if !changed.is_empty() && !locked.is_intact(&dir) {
+                return Err(ArchiveError::Modified {
+                    package: locked.path.clone(),
+                    files: changed,
+                });
+            }
+        }
+    }
+
+    pm.install_files(target_dir, &entries)?;
+
+    let mut summary = ArchiveSummary {
+        archive: archive.to_path_buf(),
+        packages: Vec::new(),
+        files: entries.len(),
+        bytes: entries.values().map(|c| c.len() as u64).sum(),
+        manifest: manifest.is_some(),
+        cached: 0,
+    };
+    let Some(manifest) = manifest else {
+        return Ok(summary);
+    };
+
+    for (locked, files) in packaged {
+        let pm = match locked.height {
+            Some(height) => pm.clone().with_pinned_height(&locked.path, height),
+            None => pm.clone(),
+        };
+        pm.seed_cache(&locked.path, &files).await?;
+        summary.cached += 1;
+        summary.packages.push(locked.path.clone());
+        lock.insert(locked);
+    }
+    lock.layout = manifest.layout;
+    for root in manifest.roots {
+        if !lock.roots.contains(&root) {
+            lock.roots.push(root);
+        }
+    }
+    lock.save(&lock_path)?;
+    Ok(summary)
+}
+
+/// What [import_archive] would write into `target_dir`, computed without
+/// writing anything.
+///
+/// Each package of the manifest is planned in its directory; files the
+/// manifest doesn't list, all of them without one, are planned together
+/// under the name of the archive.
+pub fn plan_import(archive: &Path, target_dir: &Path) -> Result<DownloadPlan, ArchiveError> {
+    let mut entries = read_archive(archive)?;
+    let manifest = take_manifest(&mut entries)?;
+
+    let mut plan = DownloadPlan::default();
+    if let Some(manifest) = &manifest {
+        for (locked, files) in manifest_packages(&entries, manifest)? {
+            let prefix = manifest.package_dir(Path::new(""), &locked.path);
+            let dir = target_dir.join(&prefix);
+            for name in files.keys() {
+                entries.remove(&entry_name(&prefix.join(name)));
+            }
+            plan.packages.push(PlannedPackage {
+                package: locked.path,
+                files: files
+                    .iter()
+                    .map(|(name, content)| PlannedFile {
+                        name: name.clone(),
+                        bytes: content.len() as u64,
+                        action: FileAction::for_file(&dir.join(name), content),
+                    })
+                    .collect(),
+                target_dir: dir,
+            });
+        }
+    }
+    if !entries.is_empty() {
+        plan.packages.push(PlannedPackage {
+            package: archive.display().to_string(),
+            target_dir: target_dir.to_path_buf(),
+            files: entries
+                .iter()
+                .map(|(name, content)| PlannedFile {
+                    name: name.clone(),
+                    bytes: content.len() as u64,
+                    action: FileAction::for_file(
+                        &target_dir.join(name),
+                        &String::from_utf8_lossy(content),
+                    ),
+                })
+                .collect(),
+        });
+    }
+    Ok(plan)
+}
+
+/// Stores the packages listed in the manifest of `archive` in `cache`,
+/// without extracting anything
+pub async fn seed_from_archive(
+    cache: &HybridCache,
+    archive: &Path,
+) -> Result<SeedSummary, ArchiveError> {
+    let mut entries = read_archive(archive)?;
+    let manifest = take_manifest(&mut entries)?
+        .ok_or_else(|| ArchiveError::MissingManifest(archive.to_path_buf()))?;
+
+    let mut summary = SeedSummary::default();
+    for (locked, files) in manifest_packages(&entries, &manifest)? {
+        cache
+            .seed_package(&locked.path, locked.height, &files)
+            .await?;
+        summary.files += files.len();
+        summary.packages.push(locked.path);
+    }
+    Ok(summary)
+}
+
+/// Removes the manifest from the entries of an archive and parses it
+fn take_manifest(
+    entries: &mut BTreeMap<String, Vec<u8>>,
+) -> Result<Option<Lockfile>, ArchiveError> {
+    match entries.remove(LOCKFILE_NAME) {
+        Some(json) => Ok(Some(Lockfile::parse(&String::from_utf8_lossy(&json))?)),
+        None => Ok(None),
+    }
+}
+
+/// A locked package and its files by name
+type Packaged = (LockedPackage, BTreeMap<String, String>);
+
+/// The packages listed in `manifest` with their files from `entries`,
+/// each checked against its locked hash
+fn manifest_packages(
+    entries: &BTreeMap<String, Vec<u8>>,
+    manifest: &Lockfile,
+) -> Result<Vec<Packaged>, ArchiveError> {
+    let mut packages = Vec::with_capacity(manifest.packages.len());
+    for (path, locked) in &manifest.packages {
+        let prefix = manifest.package_dir(Path::new(""), path);
+        let mut files = BTreeMap::new();
+        for (name, hash) in &locked.files {
+            let content = entries
+                .get(&entry_name(&prefix.join(name)))
+                .filter(|content| &hash_content(content) == hash)
+                .ok_or_else(|| ArchiveError::Corrupt {
+                    package: path.clone(),
+                    file: name.clone(),
+                })?;
+            files.insert(name.clone(), String::from_utf8_lossy(content).into_owned());
+        }
+        packages.push((locked.clone(), files));
+    }
+    Ok(packages)
+}
+
+/// Locked packages `pkg_path` imports, directly or not, and itself
+fn locked_closure(
+    lock: &Lockfile,
+    pkg_path: &str,
+    target_dir: &Path,
+) -> Result<BTreeSet<String>, ArchiveError> {
+    let resolver = DependencyResolver::new()?;
+    let mut closure = BTreeSet::from([pkg_path.to_string()]);
+    let mut queue = VecDeque::from([pkg_path.to_string()]);
+    while let Some(path) = queue.pop_front() {
+        let Some(locked) = lock.get(&path) else {
+            continue;
+        };
+        let dir = lock.package_dir(target_dir, &path);
+        // files in subdirectories belong to other packages
+        for name in locked
+            .files
+            .keys()
+            .filter(|n| n.ends_with(".gno") && !n.contains('/'))
+        {
+            let (_, imports) =
+                resolver.extract_dependencies(&fs::read_to_string(dir.join(name))?)?;
+            for import in imports {
+                if lock.packages.contains_key(&import) && closure.insert(import.clone()) {
+                    queue.push_back(import);
+                }
+            }
+        }
+    }
+    Ok(closure)
+}
+
+/// Archive entry name of a relative path, with `/` separators
+fn entry_name(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+fn write_tar<W: Write>(writer: W, entries: &BTreeMap<String, Vec<u8>>) -> std::io::Result<W> {
+    let mut builder = tar::Builder::new(writer);
+    for (name, content) in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(0);
+        header.set_entry_type(tar::EntryType::Regular);
+        builder.append_data(&mut header, name, content.as_slice())?;
+    }
+    builder.into_inner()
+}
+
+/// Reads the regular files of a tar or gzipped tar archive, refusing any
+/// that would land outside the directory it is extracted in
+fn read_archive(archive: &Path) -> Result<BTreeMap<String, Vec<u8>>, ArchiveError> {
+    let mut file = File::open(archive)?;
+    let mut magic = [0u8; 2];
+    let gzipped = file.read_exact(&mut magic).is_ok() && magic == GZIP_MAGIC;
+    file.rewind()?;
+    let file = BufReader::new(file);
+    let reader: Box<dyn Read> = if gzipped {
+        Box::new(GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+
+    let mut entries = BTreeMap::new();
+    for entry in tar::Archive::new(reader).entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?.into_owned();
+        if !path.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(ArchiveError::UnsafePath(path.display().to_string()));
+        }
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content)?;
+        entries.insert(entry_name(&path), content);
+    }
+    Ok(entries)
+}
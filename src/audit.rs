@@ -0,0 +1,281 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::deploy::{import_cycles, package_dirs, scan_packages, DeployError};
+use crate::error::ErrorKind;
+use crate::fetch::{PackageManager, PackageManagerError};
+use crate::lock::hash_content;
+use crate::report::{Report, Table};
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum AuditError {
+    #[error("Failed to scan packages: {0}")]
+    Scan(#[from] DeployError),
+
+    #[error("Package manager error: {0}")]
+    PackageManager(#[from] PackageManagerError),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl AuditError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Scan(e) => e.kind(),
+            Self::PackageManager(e) => e.kind(),
+            Self::Io(_) => ErrorKind::Internal,
+        }
+    }
+}
+
+/// What an [Audit] warns about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingKind {
+    /// Several directories declare the same package path
+    Duplicate,
+    /// A local copy differs from the package the chain serves at its path
+    Shadowed,
+    /// The package is on an import cycle
+    Cycle,
+    /// Imported, but not in the tree
+    Missing,
+}
+
+impl FindingKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Duplicate => "duplicate",
+            Self::Shadowed => "shadowed",
+            Self::Cycle => "cycle",
+            Self::Missing => "missing",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    pub kind: FindingKind,
+    pub package: String,
+    pub detail: String,
+}
+
+/// Result of `gget audit`: problems with the packages under a directory
+/// that nothing else reports, since gget and gnokey each quietly pick one
+/// of the conflicting copies
+#[derive(Debug, Clone, Serialize)]
+pub struct Audit {
+    pub dir: PathBuf,
+    /// By kind, then by package
+    pub findings: Vec<Finding>,
+    /// Whether local packages were compared with the chain, see
+    /// [Audit::compare_with_chain]
+    pub chain_checked: bool,
+    #[serde(skip)]
+    packages: Vec<(String, PathBuf)>,
+}
+
+impl Audit {
+    /// Looks for duplicate package paths, import cycles and missing
+    /// imports among the packages under `dir`, found as
+    /// [crate::deploy::DeployPlan::from_dir] does
+    pub fn scan(dir: &Path) -> Result<Self, AuditError> {
+        let packages = package_dirs(dir)?;
+        let closure = scan_packages(dir)?;
+
+        let mut declared: BTreeMap<&str, Vec<&Path>> = BTreeMap::new();
+        for (pkg_path, pkg_dir) in &packages {
+            declared.entry(pkg_path).or_default().push(pkg_dir);
+        }
+
+        let mut findings = Vec::new();
+        for (pkg_path, dirs) in &declared {
+            if dirs.len() > 1 {
+                let dirs: Vec<String> = dirs.iter().map(|d| relative(dir, d)).collect();
+                findings.push(Finding {
+                    kind: FindingKind::Duplicate,
+                    package: pkg_path.to_string(),
+                    detail: format!("declared by {}", dirs.join(", ")),
+                });
+            }
+        }
+
+        let cyclic = import_cycles(&closure);
+        for pkg_path in &cyclic {
+            let mut through: Vec<&str> = closure.packages[pkg_path]
+                .imports
+                .iter()
+                .filter(|i| *i != pkg_path && cyclic.contains(i))
+                .map(|i| i.as_str())
+                .collect();
+            through.sort_unstable();
+            findings.push(Finding {
+                kind: FindingKind::Cycle,
+                package: pkg_path.clone(),
+                detail: format!("imports {}, which import it back", through.join(", ")),
+            });
+        }
+
+        let mut importers: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+        for (pkg_path, package) in &closure.packages {
+            for import in &package.imports {
+                if !closure.packages.contains_key(import) {
+                    importers.entry(import).or_default().insert(pkg_path);
+                }
+            }
+        }
+        for (import, by) in importers {
+            findings.push(Finding {
+                kind: FindingKind::Missing,
+                package: import.to_string(),
+                detail: format!(
+                    "imported by {}",
+                    by.into_iter().collect::<Vec<_>>().join(", ")
+                ),
+            });
+        }
+
+        let mut audit = Self {
+            dir: dir.to_path_buf(),
+            findings,
+            chain_checked: false,
+            packages,
+        };
+        audit.sort();
+        Ok(audit)
+    }
+
+    /// Downloads every package found by [Audit::scan] and warns about
+    /// local copies whose files differ from the chain's, since importing
+    /// the path on chain wouldn't get the code that was tested locally.
+    ///
+    /// Packages the chain doesn't have yet are skipped.
+    pub async fn compare_with_chain(&mut self, pm: &PackageManager) -> Result<(), AuditError> {
+        for (pkg_path, pkg_dir) in &self.packages {
+            let remote = match pm.fetch_remote_files(pkg_path).await {
+                Ok(files) => files,
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            // nested packages are compared on their own
+            let remote: BTreeMap<&str, String> = remote
+                .iter()
+                .filter(|(name, _)| !name.contains('/'))
+                .map(|(name, content)| (name.as_str(), hash_content(content.as_bytes())))
+                .collect();
+            let mut local = BTreeMap::new();
+            for entry in fs::read_dir(pkg_dir)? {
+                let path = entry?.path();
+                if !path.is_file() {
+                    continue;
+                }
+                let name = path.file_name().unwrap().to_string_lossy().to_string();
+                local.insert(name, hash_content(&fs::read(&path)?));
+            }
+
+            let names: BTreeSet<&str> = remote
+                .keys()
+                .copied()
+                .chain(local.keys().map(|n| n.as_str()))
+                .collect();
+            let differences: Vec<String> = names
+                .into_iter()
+                .filter_map(|name| match (local.get(name), remote.get(name)) {
+                    (Some(l), Some(r)) if l == r => None,
+                    (Some(_), Some(_)) => Some(format!("{} differs", name)),
+                    (Some(_), None) => Some(format!("{} is not on chain", name)),
+                    (None, _) => Some(format!("{} is only on chain", name)),
+                })
+                .collect();
+            if !differences.is_empty() {
+                self.findings.push(Finding {
+                    kind: FindingKind::Shadowed,
+                    package: pkg_path.clone(),
+                    detail: format!(
+                        "{}: {}",
+                        relative(&self.dir, pkg_dir),
+                        differences.join(", ")
+                    ),
+                });
+            }
+        }
+
+        self.chain_checked = true;
+        self.sort();
+        Ok(())
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    pub fn of_kind(&self, kind: FindingKind) -> impl Iterator<Item = &Finding> {
+        self.findings.iter().filter(move |f| f.kind == kind)
+    }
+
+    fn sort(&mut self) {
+        self.findings
+            .sort_by(|a, b| (a.kind, &a.package).cmp(&(b.kind, &b.package)));
+    }
+}
+
+impl Report for Audit {
+    fn table(&self) -> Table {
+        let mut table = Table::new(
+            &format!("Audit of {}", self.dir.display()),
+            &["kind", "package", "detail"],
+        );
+        for finding in &self.findings {
+            table.push_row([
+                finding.kind.as_str().to_string(),
+                finding.package.clone(),
+                finding.detail.clone(),
+            ]);
+        }
+        table
+    }
+
+    fn human(&self) -> String {
+        let unchecked = if self.chain_checked {
+            ""
+        } else {
+            " (not compared with the chain)"
+        };
+        if self.is_clean() {
+            return format!(
+                "No problems found under {}{}\n",
+                self.dir.display(),
+                unchecked
+            );
+        }
+
+        let mut out = format!(
+            "{} warnings under {}{}\n",
+            self.findings.len(),
+            self.dir.display(),
+            unchecked
+        );
+        for finding in &self.findings {
+            let _ = writeln!(
+                out,
+                "  warning[{}] {}: {}",
+                finding.kind.as_str(),
+                finding.package,
+                finding.detail
+            );
+        }
+        out
+    }
+}
+
+fn relative(root: &Path, dir: &Path) -> String {
+    dir.strip_prefix(root).unwrap_or(dir).display().to_string()
+}
@@ -1,17 +1,35 @@
 use std::{
-    path::PathBuf,
-    sync::Arc,
-    time::{Duration, SystemTime},
+    collections::{BTreeMap, HashMap},
+    fmt::Write as _,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime},
 };
 
 use async_trait::async_trait;
 use blake3;
 use moka::future::Cache as MemCache;
+use moka::Expiry;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tokio::{fs, sync::Mutex, time};
 
+use crate::error::ErrorKind;
+use crate::fetch::{
+    DEFAULT_CACHE_TTL, DEFAULT_FILES_TTL, DEFAULT_NEGATIVE_TTL, DEFAULT_RESOLUTION_TTL,
+};
+use crate::filelock::FileLock;
+use crate::lock::{LockError, Lockfile, LOCKFILE_NAME};
+use crate::report::{Report, Table};
+
+#[cfg(feature = "redis")]
+pub mod redis;
+
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum CacheError {
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
@@ -19,6 +37,26 @@ pub enum CacheError {
     #[error("JSON serialization/deserialization error: {0}")]
     // TODO: consider to use CBOR instead of JSON to reduce size
     Json(#[from] serde_json::Error),
+
+    #[error("Lockfile error: {0}")]
+    Lock(#[from] LockError),
+
+    #[cfg(feature = "redis")]
+    #[error("Redis error: {0}")]
+    Redis(#[from] ::redis::RedisError),
+}
+
+impl CacheError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(_) => ErrorKind::Internal,
+            // a corrupt entry
+            Self::Json(_) => ErrorKind::Integrity,
+            Self::Lock(_) => ErrorKind::Config,
+            #[cfg(feature = "redis")]
+            Self::Redis(_) => ErrorKind::Network,
+        }
+    }
 }
 
 /// Entry stored on disk
@@ -29,15 +67,76 @@ pub struct CacheEntry {
     content: String,      // raw bytes of the value
     timestamp: Timestamp, // seconds since epoch
     ttl: u64,             // TTL in seconds
+    /// blake3 digest of `content`, missing in entries written before it
+    /// was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hash: Option<String>,
 }
 
+impl CacheEntry {
+    fn new(content: &str, timestamp: Timestamp, ttl: u64) -> Self {
+        Self {
+            content: content.to_string(),
+            timestamp,
+            ttl,
+            hash: Some(blake3::hash(content.as_bytes()).to_hex().to_string()),
+        }
+    }
+
+    /// Whether `content` is still what was written
+    fn is_intact(&self) -> bool {
+        self.hash
+            .as_ref()
+            .is_none_or(|hash| *hash == blake3::hash(self.content.as_bytes()).to_hex().as_str())
+    }
+}
+
+/// Guard returned by [AsyncStorage::lock], releasing the lock when dropped
+pub type EntryLock = Box<dyn Send + Sync>;
+
+/// Persistent layer behind [HybridCache]. Implement it to keep cached RPC
+/// responses somewhere other than the local disk.
 #[async_trait]
 pub trait AsyncStorage: Send + Sync {
     async fn get(&self, key: &str) -> Result<Option<String>, CacheError>;
     async fn set(&self, key: &str, value: &str) -> Result<(), CacheError>;
     async fn cleanup(&self) -> Result<(), CacheError>;
+
+    /// Stores `value` under `key` for `ttl` instead of the storage's own
+    /// TTL, see [CacheTtls].
+    ///
+    /// Storage that can't expire entries one by one keeps them for its own.
+    async fn set_with_ttl(&self, key: &str, value: &str, _ttl: Duration) -> Result<(), CacheError> {
+        self.set(key, value).await
+    }
+
+    /// Returns the entry of `key` even if it has expired, as long as it
+    /// hasn't been cleaned up yet. Cleanup keeps expired entries for
+    /// another TTL, so they can be renewed if still current.
+    ///
+    /// Storage that drops expired entries on its own returns `None`.
+    async fn get_stale(&self, _key: &str) -> Result<Option<String>, CacheError> {
+        Ok(None)
+    }
+
+    /// Keeps other processes sharing the storage from filling `key` until
+    /// the guard is dropped, waiting for any process filling it now.
+    ///
+    /// Storage that isn't shared between processes, or can't coordinate
+    /// them, returns `None`.
+    async fn lock(&self, _key: &str) -> Result<Option<EntryLock>, CacheError> {
+        Ok(None)
+    }
 }
 
+/// Subdirectory of a [DiskStorage] holding broken entries, see
+/// [DiskStorage::quarantine]
+pub const QUARANTINE_DIR: &str = "quarantine";
+
+/// Number of the next temporary file [DiskStorage] writes an entry to, so
+/// concurrent writes of the same entry don't share one
+static NEXT_TEMP_FILE: AtomicU64 = AtomicU64::new(0);
+
 #[derive(Clone)]
 pub struct DiskStorage {
     cache_dir: PathBuf,
@@ -55,6 +154,12 @@ impl DiskStorage {
         }
     }
 
+    /// Lock file guarding the entry of `key` across processes
+    fn lock_path(&self, key: &str) -> PathBuf {
+        let hash = blake3::hash(key.as_bytes()).to_hex();
+        self.cache_dir.join("locks").join(format!("{}.lock", hash))
+    }
+
     /// Compute hash-based file path for a key
     fn entry_path(&self, key: &str) -> PathBuf {
         // to maintain search/deletion performance even when there are many files,
@@ -66,6 +171,59 @@ impl DiskStorage {
         self.cache_dir.join(subdir).join(format!("{}.json", hash))
     }
 
+    /// Reads the entry of `key`, expired or not.
+    ///
+    /// Entries that can't be read, are truncated, unparseable or don't
+    /// match their hash are quarantined and read as missing, so they are
+    /// fetched again instead of being trusted or failing the download.
+    async fn read_entry(&self, key: &str) -> Result<Option<CacheEntry>, CacheError> {
+        let path = self.entry_path(key);
+        let data = match fs::read(&path).await {
+            Ok(data) => data,
+            // another process may have cleaned it up since
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                self.quarantine(&path, &e.to_string()).await;
+                return Ok(None);
+            }
+        };
+        match serde_json::from_slice::<CacheEntry>(&data) {
+            Ok(entry) if entry.is_intact() => Ok(Some(entry)),
+            Ok(_) => {
+                self.quarantine(&path, "content doesn't match its hash")
+                    .await;
+                Ok(None)
+            }
+            Err(e) => {
+                self.quarantine(&path, &e.to_string()).await;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Moves the broken entry at `path` out of the way, into
+    /// [QUARANTINE_DIR], where it can be looked at but is never read
+    /// again. Deletes it if it can't be moved.
+    async fn quarantine(&self, path: &Path, reason: &str) {
+        let dir = self.cache_dir.join(QUARANTINE_DIR);
+        let moved = match (fs::create_dir_all(&dir).await, path.file_name()) {
+            (Ok(()), Some(name)) => fs::rename(path, dir.join(name)).await.is_ok(),
+            _ => false,
+        };
+        if !moved {
+            let _ = match fs::remove_dir_all(path).await {
+                Err(_) => fs::remove_file(path).await,
+                removed => removed,
+            };
+        }
+        tracing::warn!(
+            path = %path.display(),
+            reason,
+            quarantined = moved,
+            "broken cache entry, treating it as missing"
+        );
+    }
+
     /// Current timestamp sec since epoch
     fn now_ts() -> Timestamp {
         SystemTime::now()
@@ -78,48 +236,82 @@ impl DiskStorage {
 #[async_trait]
 impl AsyncStorage for DiskStorage {
     async fn get(&self, key: &str) -> Result<Option<String>, CacheError> {
-        let path = self.entry_path(key);
-        if !path.exists() {
+        let Some(entry) = self.read_entry(key).await? else {
             return Ok(None);
-        }
-
-        let data = fs::read_to_string(&path).await?;
-        let entry: CacheEntry = serde_json::from_str(&data)?;
-        // check TTL
+        };
+        // check TTL; expired entries are left to cleanup, see get_stale
         if Self::now_ts() >= entry.timestamp + entry.ttl {
-            let _ = fs::remove_file(&path).await?;
             return Ok(None);
         }
         Ok(Some(entry.content))
     }
 
+    async fn get_stale(&self, key: &str) -> Result<Option<String>, CacheError> {
+        Ok(self.read_entry(key).await?.map(|entry| entry.content))
+    }
+
     async fn set(&self, key: &str, value: &str) -> Result<(), CacheError> {
+        self.set_with_ttl(key, value, Duration::from_secs(self.default_ttl))
+            .await
+    }
+
+    async fn set_with_ttl(&self, key: &str, value: &str, ttl: Duration) -> Result<(), CacheError> {
         let path = self.entry_path(key);
         if let Some(dir) = path.parent() {
             fs::create_dir_all(dir).await?;
         }
-        let entry = CacheEntry {
-            content: value.to_string(),
-            timestamp: Self::now_ts(),
-            ttl: self.default_ttl,
-        };
+        let entry = CacheEntry::new(value, Self::now_ts(), ttl.as_secs());
         let json = serde_json::to_string(&entry)?;
-        fs::write(&path, json).await?;
+        // readers in other processes only ever see complete entries
+        let temp = path.with_extension(format!(
+            "{}.{}.tmp",
+            std::process::id(),
+            NEXT_TEMP_FILE.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&temp, json).await?;
+        if let Err(e) = fs::rename(&temp, &path).await {
+            let _ = fs::remove_file(&temp).await;
+            return Err(e.into());
+        }
         Ok(())
     }
 
+    async fn lock(&self, key: &str) -> Result<Option<EntryLock>, CacheError> {
+        let lock = FileLock::acquire(self.lock_path(key), || {}).await?;
+        Ok(Some(Box::new(lock)))
+    }
+
     async fn cleanup(&self) -> Result<(), CacheError> {
         // must ensure single concurrent cleanup
         let _guard = self.lock.lock().await;
         let now = Self::now_ts();
         let mut dir_entries = fs::read_dir(&self.cache_dir).await?;
         while let Some(sub) = dir_entries.next_entry().await? {
+            let quarantine = sub.file_name() == QUARANTINE_DIR;
             let mut files = fs::read_dir(sub.path()).await?;
             while let Some(file) = files.next_entry().await? {
                 let path = file.path();
+                // broken entries rarely parse, so they go by age instead,
+                // once as old as an expired entry would be
+                if quarantine {
+                    let age = file
+                        .metadata()
+                        .await
+                        .and_then(|meta| meta.modified())
+                        .ok()
+                        .and_then(|modified| modified.elapsed().ok());
+                    if age.is_some_and(|age| age.as_secs() > 2 * self.default_ttl) {
+                        let _ = match fs::remove_file(&path).await {
+                            Err(_) => fs::remove_dir_all(&path).await,
+                            removed => removed,
+                        };
+                    }
+                    continue;
+                }
                 if let Ok(data) = fs::read_to_string(&path).await {
                     if let Ok(entry) = serde_json::from_str::<CacheEntry>(&data) {
-                        if now > entry.timestamp + entry.ttl {
+                        // kept for another TTL, see AsyncStorage::get_stale
+                        if now > entry.timestamp + 2 * entry.ttl {
                             let _ = fs::remove_file(&path).await;
                         }
                     }
@@ -130,14 +322,367 @@ impl AsyncStorage for DiskStorage {
     }
 }
 
+/// Storage kept in process memory only, for read-only filesystems.
+///
+/// Unlike the in-memory layer of [HybridCache] it isn't bounded, only
+/// expired entries are dropped.
+#[derive(Clone)]
+pub struct MemoryStorage {
+    entries: Arc<Mutex<HashMap<String, MemoryEntry>>>,
+    ttl: Duration,
+}
+
+/// Content, expiry and TTL of a [MemoryStorage] entry
+type MemoryEntry = (String, Instant, Duration);
+
+impl MemoryStorage {
+    /// Creates an empty [MemoryStorage] whose entries expire after `ttl`
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            ttl,
+        }
+    }
+}
+
+#[async_trait]
+impl AsyncStorage for MemoryStorage {
+    async fn get(&self, key: &str) -> Result<Option<String>, CacheError> {
+        match self.entries.lock().await.get(key) {
+            // left to cleanup, see get_stale
+            Some((_, expires, _)) if Instant::now() >= *expires => Ok(None),
+            Some((content, _, _)) => Ok(Some(content.clone())),
+            None => Ok(None),
+        }
+    }
+
+    async fn get_stale(&self, key: &str) -> Result<Option<String>, CacheError> {
+        Ok(self
+            .entries
+            .lock()
+            .await
+            .get(key)
+            .map(|(content, _, _)| content.clone()))
+    }
+
+    async fn set(&self, key: &str, value: &str) -> Result<(), CacheError> {
+        self.set_with_ttl(key, value, self.ttl).await
+    }
+
+    async fn set_with_ttl(&self, key: &str, value: &str, ttl: Duration) -> Result<(), CacheError> {
+        self.entries.lock().await.insert(
+            key.to_string(),
+            (value.to_string(), Instant::now() + ttl, ttl),
+        );
+        Ok(())
+    }
+
+    async fn cleanup(&self) -> Result<(), CacheError> {
+        // kept for another TTL, see AsyncStorage::get_stale
+        let now = Instant::now();
+        self.entries
+            .lock()
+            .await
+            .retain(|_, (_, expires, ttl)| now < *expires + *ttl);
+        Ok(())
+    }
+}
+
+/// Storage that keeps nothing, for runs that must not write a cache
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopCache;
+
+#[async_trait]
+impl AsyncStorage for NoopCache {
+    async fn get(&self, _key: &str) -> Result<Option<String>, CacheError> {
+        Ok(None)
+    }
+
+    async fn set(&self, _key: &str, _value: &str) -> Result<(), CacheError> {
+        Ok(())
+    }
+
+    async fn cleanup(&self) -> Result<(), CacheError> {
+        Ok(())
+    }
+}
+
+/// Keeps the entries of one namespace apart from those of others sharing
+/// the same storage, e.g. per network, by prefixing their keys
+pub struct NamespacedStorage {
+    inner: Arc<dyn AsyncStorage>,
+    prefix: String,
+}
+
+impl NamespacedStorage {
+    pub fn new(inner: Arc<dyn AsyncStorage>, namespace: &str) -> Self {
+        Self {
+            inner,
+            prefix: format!("{}/", namespace),
+        }
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("{}{}", self.prefix, key)
+    }
+}
+
+#[async_trait]
+impl AsyncStorage for NamespacedStorage {
+    async fn get(&self, key: &str) -> Result<Option<String>, CacheError> {
+        self.inner.get(&self.key(key)).await
+    }
+
+    async fn set(&self, key: &str, value: &str) -> Result<(), CacheError> {
+        self.inner.set(&self.key(key), value).await
+    }
+
+    async fn cleanup(&self) -> Result<(), CacheError> {
+        self.inner.cleanup().await
+    }
+
+    async fn set_with_ttl(&self, key: &str, value: &str, ttl: Duration) -> Result<(), CacheError> {
+        self.inner.set_with_ttl(&self.key(key), value, ttl).await
+    }
+
+    async fn get_stale(&self, key: &str) -> Result<Option<String>, CacheError> {
+        self.inner.get_stale(&self.key(key)).await
+    }
+
+    async fn lock(&self, key: &str) -> Result<Option<EntryLock>, CacheError> {
+        self.inner.lock(&self.key(key)).await
+    }
+}
+
+/// Lookup known to fail until `expires_at` (seconds since epoch)
+#[derive(Serialize, Deserialize)]
+struct NegativeEntry {
+    reason: String,
+    expires_at: Timestamp,
+}
+
+/// Counters of a [HybridCache], see [HybridCache::stats]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CacheStats {
+    /// Lookups served by the in-memory layer
+    pub mem_hits: u64,
+    /// Lookups served by the [AsyncStorage] behind it, the disk by default
+    pub storage_hits: u64,
+    /// Lookups neither layer could serve
+    pub misses: u64,
+    pub writes: u64,
+    /// Entries the in-memory layer dropped because it was full or they
+    /// expired
+    pub evictions: u64,
+}
+
+impl CacheStats {
+    pub fn hits(&self) -> u64 {
+        self.mem_hits + self.storage_hits
+    }
+
+    pub fn lookups(&self) -> u64 {
+        self.hits() + self.misses
+    }
+
+    /// Share of lookups that were hits, `None` before the first lookup
+    pub fn hit_rate(&self) -> Option<f64> {
+        match self.lookups() {
+            0 => None,
+            lookups => Some(self.hits() as f64 / lookups as f64),
+        }
+    }
+
+    /// What was counted after `earlier`, a snapshot of the same cache
+    pub fn since(&self, earlier: &CacheStats) -> CacheStats {
+        CacheStats {
+            mem_hits: self.mem_hits.saturating_sub(earlier.mem_hits),
+            storage_hits: self.storage_hits.saturating_sub(earlier.storage_hits),
+            misses: self.misses.saturating_sub(earlier.misses),
+            writes: self.writes.saturating_sub(earlier.writes),
+            evictions: self.evictions.saturating_sub(earlier.evictions),
+        }
+    }
+
+    /// Adds the counts of `other`
+    pub fn merge(&mut self, other: CacheStats) {
+        self.mem_hits += other.mem_hits;
+        self.storage_hits += other.storage_hits;
+        self.misses += other.misses;
+        self.writes += other.writes;
+        self.evictions += other.evictions;
+    }
+}
+
+impl std::fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} of {} lookups hit ({} in memory, {} in storage)",
+            self.hits(),
+            self.lookups(),
+            self.mem_hits,
+            self.storage_hits
+        )?;
+        if let Some(rate) = self.hit_rate() {
+            write!(f, ", {:.0}% hit rate", rate * 100.0)?;
+        }
+        Ok(())
+    }
+}
+
+/// Live counters behind [CacheStats]
+#[derive(Default)]
+struct Counters {
+    mem_hits: AtomicU64,
+    storage_hits: AtomicU64,
+    misses: AtomicU64,
+    writes: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl Counters {
+    fn bump(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> CacheStats {
+        CacheStats {
+            mem_hits: self.mem_hits.load(Ordering::Relaxed),
+            storage_hits: self.storage_hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            writes: self.writes.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Layer a lookup was served from
+enum Layer {
+    Memory,
+    Storage,
+}
+
+/// How long each kind of cache entry stays valid.
+///
+/// File lists change whenever a package is redeployed under a new
+/// height, while what was parsed from some content is keyed by its hash
+/// and can't go stale, so each gets its own TTL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheTtls {
+    /// Package file lists, [DEFAULT_FILES_TTL] by default
+    pub files: Duration,
+    /// File contents, [DEFAULT_CACHE_TTL] by default
+    pub contents: Duration,
+    /// Packages the node reported missing, [DEFAULT_NEGATIVE_TTL] by
+    /// default
+    pub negative: Duration,
+    /// Imports found by parsing sources, [DEFAULT_RESOLUTION_TTL] by
+    /// default
+    pub resolution: Duration,
+}
+
+impl Default for CacheTtls {
+    fn default() -> Self {
+        Self {
+            files: DEFAULT_FILES_TTL,
+            contents: DEFAULT_CACHE_TTL,
+            negative: DEFAULT_NEGATIVE_TTL,
+            resolution: DEFAULT_RESOLUTION_TTL,
+        }
+    }
+}
+
+impl CacheTtls {
+    /// The same `ttl` for every kind of entry
+    pub fn uniform(ttl: Duration) -> Self {
+        Self {
+            files: ttl,
+            contents: ttl,
+            negative: ttl,
+            resolution: ttl,
+        }
+    }
+
+    /// TTL of the entry stored under `key`, by its kind. Keys of no known
+    /// kind get the content TTL.
+    pub fn for_key(&self, key: &str) -> Duration {
+        let kind = key.split(':').next().unwrap_or_default();
+        match kind {
+            "files" | "fetched" => self.files,
+            "missing" => self.negative,
+            "parse" | "imports" => self.resolution,
+            _ => self.contents,
+        }
+    }
+}
+
+/// Parses a TTL in seconds, or with an s, m, h or d suffix, e.g. `6h`
+pub fn parse_ttl(s: &str) -> Result<Duration, String> {
+    let lower = s.trim().to_ascii_lowercase();
+    let (digits, unit) = match lower.char_indices().last() {
+        Some((i, 's')) => (&lower[..i], 1),
+        Some((i, 'm')) => (&lower[..i], 60),
+        Some((i, 'h')) => (&lower[..i], 3600),
+        Some((i, 'd')) => (&lower[..i], 24 * 3600),
+        _ => (lower.as_str(), 1),
+    };
+    match digits.parse::<u64>().map(|n| n.checked_mul(unit)) {
+        Ok(Some(secs)) => Ok(Duration::from_secs(secs)),
+        _ => Err(format!("invalid TTL `{}`", s)),
+    }
+}
+
+/// Expires the entries of the in-memory layer by [CacheTtls::for_key]
+struct ExpireByKind(CacheTtls);
+
+impl Expiry<String, String> for ExpireByKind {
+    fn expire_after_create(
+        &self,
+        key: &String,
+        _value: &String,
+        _created_at: std::time::Instant,
+    ) -> Option<Duration> {
+        Some(self.0.for_key(key))
+    }
+
+    fn expire_after_update(
+        &self,
+        key: &String,
+        _value: &String,
+        _updated_at: std::time::Instant,
+        _duration_until_expiry: Option<Duration>,
+    ) -> Option<Duration> {
+        Some(self.0.for_key(key))
+    }
+}
+
+/// In-memory cache in front of an [AsyncStorage]
 pub struct HybridCache {
     mem: MemCache<String, String>,
-    storage: DiskStorage,
+    storage: Arc<dyn AsyncStorage>,
+    ttls: CacheTtls,
+    counters: Arc<Counters>,
 }
 
 impl HybridCache {
+    /// Creates a cache persisted to `cache_dir`
     pub fn new(cache_dir: PathBuf, ttl: Duration, max_in_mem: u64) -> Self {
-        let storage = DiskStorage::new(cache_dir.clone(), ttl);
+        Self::with_storage(Arc::new(DiskStorage::new(cache_dir, ttl)), ttl, max_in_mem)
+    }
+
+    /// Creates a cache persisted to `storage`, cleaned up every hour.
+    ///
+    /// `ttl` only applies to the in-memory layer; `storage` handles expiry
+    /// of what it keeps.
+    pub fn with_storage(storage: Arc<dyn AsyncStorage>, ttl: Duration, max_in_mem: u64) -> Self {
+        Self::with_ttls(storage, CacheTtls::uniform(ttl), max_in_mem)
+    }
+
+    /// Creates a cache persisted to `storage` like
+    /// [HybridCache::with_storage], whose entries expire by kind, in
+    /// memory and in `storage` alike, see [CacheTtls]
+    pub fn with_ttls(storage: Arc<dyn AsyncStorage>, ttls: CacheTtls, max_in_mem: u64) -> Self {
         let st = storage.clone();
         tokio::spawn(async move {
             let mut interval = time::interval(Duration::from_secs(3600));
@@ -147,31 +692,400 @@ impl HybridCache {
             }
         });
 
+        let counters = Arc::new(Counters::default());
+        let evictions = counters.clone();
         Self {
             mem: MemCache::builder()
-                .time_to_live(ttl)
+                .expire_after(ExpireByKind(ttls))
                 .max_capacity(max_in_mem)
+                .eviction_listener(move |_, _, cause| {
+                    if cause.was_evicted() {
+                        Counters::bump(&evictions.evictions);
+                    }
+                })
                 .build(),
             storage,
+            ttls,
+            counters,
         }
     }
 
+    /// How long each kind of entry stays valid
+    pub fn ttls(&self) -> &CacheTtls {
+        &self.ttls
+    }
+
+    /// Hits, misses, writes and evictions counted since the cache was
+    /// created
+    pub fn stats(&self) -> CacheStats {
+        self.counters.snapshot()
+    }
+
+    #[tracing::instrument(name = "cache", skip_all)]
     pub async fn get(&self, key: &str) -> Result<Option<String>, CacheError> {
+        let found = self.lookup(key).await?;
+        Counters::bump(match &found {
+            Some((_, Layer::Memory)) => &self.counters.mem_hits,
+            Some((_, Layer::Storage)) => &self.counters.storage_hits,
+            None => &self.counters.misses,
+        });
+        Ok(found.map(|(v, _)| v))
+    }
+
+    /// Looks up a `key` that [HybridCache::get] just missed, say after
+    /// taking its [HybridCache::lock]: a hit counts, the miss already did
+    pub async fn recheck(&self, key: &str) -> Result<Option<String>, CacheError> {
+        let found = self.lookup(key).await?;
+        match &found {
+            Some((_, Layer::Memory)) => Counters::bump(&self.counters.mem_hits),
+            Some((_, Layer::Storage)) => Counters::bump(&self.counters.storage_hits),
+            None => {}
+        }
+        Ok(found.map(|(v, _)| v))
+    }
+
+    /// Looks `key` up without counting it
+    async fn lookup(&self, key: &str) -> Result<Option<(String, Layer)>, CacheError> {
         if let Some(v) = self.mem.get(key).await {
-            return Ok(Some(v));
+            return Ok(Some((v, Layer::Memory)));
         }
         if let Some(v) = self.storage.get(key).await? {
             self.mem.insert(key.to_string(), v.clone()).await;
-            return Ok(Some(v));
+            return Ok(Some((v, Layer::Storage)));
         }
         Ok(None)
     }
 
+    /// Looks `key` up even if it has expired, see [AsyncStorage::get_stale],
+    /// without counting it
+    pub async fn get_stale(&self, key: &str) -> Result<Option<String>, CacheError> {
+        if let Some((v, _)) = self.lookup(key).await? {
+            return Ok(Some(v));
+        }
+        self.storage.get_stale(key).await
+    }
+
+    /// Keeps other processes sharing the storage from filling `key` until
+    /// the guard is dropped, see [AsyncStorage::lock]
+    pub async fn lock(&self, key: &str) -> Result<Option<EntryLock>, CacheError> {
+        self.storage.lock(key).await
+    }
+
+    #[tracing::instrument(name = "cache", skip_all)]
     pub async fn set(&self, key: &str, value: &str) -> Result<(), CacheError> {
-        self.storage.set(key, value).await?;
+        self.store(key, value).await?;
+        Counters::bump(&self.counters.writes);
+        Ok(())
+    }
+
+    /// Stores `value` under `key` without counting it
+    async fn store(&self, key: &str, value: &str) -> Result<(), CacheError> {
+        self.storage
+            .set_with_ttl(key, value, self.ttls.for_key(key))
+            .await?;
         self.mem.insert(key.to_string(), value.to_string()).await;
         Ok(())
     }
+
+    /// Returns the chain height the file list of `pkg_path`, pinned at
+    /// `height` or not, was last fetched at, even if it has expired
+    pub async fn fetched_at(
+        &self,
+        pkg_path: &str,
+        height: Option<u64>,
+    ) -> Result<Option<u64>, CacheError> {
+        let fetched = self.get_stale(&fetched_key(pkg_path, height)).await?;
+        Ok(fetched.and_then(|fetched| fetched.parse().ok()))
+    }
+
+    /// Records the chain height the file list of `pkg_path` was fetched
+    /// at, see [HybridCache::fetched_at]. Not counted, so the stats
+    /// describe lookups of actual content.
+    ///
+    /// An unknown height is recorded too, so that an older one can't vouch
+    /// for what was fetched since.
+    pub async fn set_fetched_at(
+        &self,
+        pkg_path: &str,
+        height: Option<u64>,
+        fetched: Option<u64>,
+    ) -> Result<(), CacheError> {
+        let fetched = fetched.map(|h| h.to_string()).unwrap_or_default();
+        self.store(&fetched_key(pkg_path, height), &fetched).await
+    }
+
+    /// Returns why the lookup of `key` failed, if it was recorded with
+    /// [HybridCache::set_negative] less than its TTL ago
+    pub async fn get_negative(&self, key: &str) -> Result<Option<String>, CacheError> {
+        // not counted, so the stats describe lookups of actual content
+        let Some((raw, _)) = self.lookup(&negative_key(key)).await? else {
+            return Ok(None);
+        };
+        let entry: NegativeEntry = serde_json::from_str(&raw)?;
+        if DiskStorage::now_ts() >= entry.expires_at {
+            return Ok(None);
+        }
+        Ok(Some(entry.reason))
+    }
+
+    /// Records that looking up `key` fails with `reason` for the next
+    /// `ttl`, independently of the cache's own TTL
+    pub async fn set_negative(
+        &self,
+        key: &str,
+        reason: &str,
+        ttl: Duration,
+    ) -> Result<(), CacheError> {
+        let entry = NegativeEntry {
+            reason: reason.to_string(),
+            expires_at: DiskStorage::now_ts() + ttl.as_secs(),
+        };
+        self.set(&negative_key(key), &serde_json::to_string(&entry)?)
+            .await
+    }
+
+    /// Stores the files of `pkg_path`, by name, as a download at `height`
+    /// would
+    pub async fn seed_package(
+        &self,
+        pkg_path: &str,
+        height: Option<u64>,
+        files: &BTreeMap<String, String>,
+    ) -> Result<(), CacheError> {
+        let names: Vec<&String> = files.keys().collect();
+        self.set(
+            &files_key(pkg_path, height),
+            &serde_json::to_string(&names)?,
+        )
+        .await?;
+        for (name, content) in files {
+            self.set(&content_key(pkg_path, name, height), content)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Stores every package of the vendor tree `dir`, so later downloads
+    /// of them don't need the network.
+    ///
+    /// With a `gget.lock` in `dir`, the locked packages are stored at their
+    /// locked heights, except those modified since. Without one, `dir` is
+    /// taken to be laid out by import path and every directory holding
+    /// `.gno` files is stored as the latest version of that package.
+    pub async fn import_dir(&self, dir: &Path) -> Result<SeedSummary, CacheError> {
+        let mut summary = SeedSummary::default();
+        let Some(lock) = Lockfile::load_if_exists(&dir.join(LOCKFILE_NAME))? else {
+            let mut packages = BTreeMap::new();
+            packages_by_import_path(dir, dir, &mut packages)?;
+            for (pkg_path, files) in packages {
+                self.seed_package(&pkg_path, None, &files).await?;
+                summary.files += files.len();
+                summary.packages.push(pkg_path);
+            }
+            return Ok(summary);
+        };
+
+        for (pkg_path, locked) in &lock.packages {
+            let pkg_dir = lock.package_dir(dir, pkg_path);
+            if !locked.is_intact(&pkg_dir) {
+                summary.skipped.push(pkg_path.clone());
+                continue;
+            }
+            let mut files = BTreeMap::new();
+            for name in locked.files.keys() {
+                let content = fs::read(pkg_dir.join(name)).await?;
+                files.insert(name.clone(), String::from_utf8_lossy(&content).into_owned());
+            }
+            self.seed_package(pkg_path, locked.height, &files).await?;
+            summary.files += files.len();
+            summary.packages.push(pkg_path.clone());
+        }
+        Ok(summary)
+    }
+}
+
+/// Cache key of a package's file list, per height for pinned packages so
+/// they never mix with the latest content
+pub fn files_key(pkg_path: &str, height: Option<u64>) -> String {
+    match height {
+        Some(height) => format!("files:{}@{}", pkg_path, height),
+        None => format!("files:{}", pkg_path),
+    }
+}
+
+/// Cache key of the chain height a package's file list was fetched at,
+/// see [files_key]
+fn fetched_key(pkg_path: &str, height: Option<u64>) -> String {
+    match height {
+        Some(height) => format!("fetched:{}@{}", pkg_path, height),
+        None => format!("fetched:{}", pkg_path),
+    }
+}
+
+/// Cache key of a file's content, see [files_key]
+pub fn content_key(pkg_path: &str, file: &str, height: Option<u64>) -> String {
+    match height {
+        Some(height) => format!("file:{}/{}@{}", pkg_path, file, height),
+        None => format!("file:{}/{}", pkg_path, file),
+    }
+}
+
+/// Cache key of what parsing some source found, by content hash. The
+/// crate version is part of it, so a newer parser never trusts an older
+/// one's results.
+pub fn parse_key(source: &str) -> String {
+    format!(
+        "parse:{}:{}",
+        env!("CARGO_PKG_VERSION"),
+        blake3::hash(source.as_bytes()).to_hex()
+    )
+}
+
+/// Cache key of what a package's sources import, by the hash of their
+/// names and contents, see [parse_key]
+pub fn imports_key(pkg_path: &str, sources: &[(String, String)]) -> String {
+    let mut hasher = blake3::Hasher::new();
+    for (name, content) in sources {
+        for part in [name, content] {
+            hasher.update(&(part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+    }
+    format!(
+        "imports:{}:{}:{}",
+        env!("CARGO_PKG_VERSION"),
+        pkg_path,
+        hasher.finalize().to_hex()
+    )
+}
+
+/// Packages stored by [HybridCache::import_dir]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SeedSummary {
+    pub packages: Vec<String>,
+    pub files: usize,
+    /// Locked packages left out because their files were modified or missing
+    pub skipped: Vec<String>,
+}
+
+impl Report for SeedSummary {
+    fn table(&self) -> Table {
+        let mut table = Table::new("Seeded packages", &["package", "status"]);
+        for package in &self.packages {
+            table.push_row([package.as_str(), "seeded"]);
+        }
+        for package in &self.skipped {
+            table.push_row([package.as_str(), "skipped"]);
+        }
+        table
+    }
+
+    fn human(&self) -> String {
+        let mut out = format!(
+            "Seeded the cache with {} files of {} packages\n",
+            self.files,
+            self.packages.len()
+        );
+        for package in &self.skipped {
+            let _ = writeln!(out, "  skipped {}: modified since it was locked", package);
+        }
+        out
+    }
+}
+
+/// Packages cached by [crate::fetch::PackageManager::prefetch]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PrefetchSummary {
+    /// Packages in the order they were cached, dependencies first
+    pub packages: Vec<PrefetchedPackage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PrefetchedPackage {
+    pub package: String,
+    pub files: usize,
+    pub bytes: u64,
+    /// File list and file content lookups that were cached already
+    pub cached: usize,
+    /// File list and file content lookups that went to the RPC endpoint
+    pub fetched: usize,
+}
+
+impl Report for PrefetchSummary {
+    fn table(&self) -> Table {
+        let mut table = Table::new(
+            "Prefetched packages",
+            &["package", "files", "bytes", "cached", "fetched"],
+        );
+        for p in &self.packages {
+            table.push_row([
+                p.package.clone(),
+                p.files.to_string(),
+                p.bytes.to_string(),
+                p.cached.to_string(),
+                p.fetched.to_string(),
+            ]);
+        }
+        table
+    }
+
+    fn human(&self) -> String {
+        let mut out = self.table().to_aligned();
+        let files: usize = self.packages.iter().map(|p| p.files).sum();
+        let bytes: u64 = self.packages.iter().map(|p| p.bytes).sum();
+        let fetched: usize = self.packages.iter().map(|p| p.fetched).sum();
+        let _ = writeln!(
+            out,
+            "\nCached {} files ({} bytes) of {} packages, {} lookups from the node; nothing was written.",
+            files,
+            bytes,
+            self.packages.len(),
+            fetched
+        );
+        out
+    }
+}
+
+fn negative_key(key: &str) -> String {
+    format!("missing:{}", key)
+}
+
+/// Packages of a tree without a lockfile, laid out by import path: every
+/// directory holding `.gno` files, with the files directly in it
+fn packages_by_import_path(
+    root: &Path,
+    dir: &Path,
+    packages: &mut BTreeMap<String, BTreeMap<String, String>>,
+) -> std::io::Result<()> {
+    let mut files = BTreeMap::new();
+    let mut is_package = false;
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(name) = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .map(str::to_string)
+        else {
+            continue;
+        };
+        if path.is_dir() {
+            packages_by_import_path(root, &path, packages)?;
+        } else if name != LOCKFILE_NAME {
+            is_package |= name.ends_with(".gno");
+            let content = String::from_utf8_lossy(&std::fs::read(&path)?).into_owned();
+            files.insert(name, content);
+        }
+    }
+
+    let pkg_path = dir
+        .strip_prefix(root)
+        .unwrap_or(dir)
+        .to_string_lossy()
+        .replace('\\', "/");
+    if is_package && !pkg_path.is_empty() {
+        packages.insert(pkg_path, files);
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -200,8 +1114,160 @@ mod tests {
         let val = "value";
         storage.set(key, val).await.unwrap();
         assert_eq!(storage.get(key).await.unwrap(), None);
-        let path = storage.entry_path(key);
+        assert_eq!(storage.get_stale(key).await.unwrap().as_deref(), Some(val));
+    }
+
+    #[tokio::test]
+    async fn test_disk_storage_drops_corrupted_entries() {
+        let dir = tempdir().unwrap();
+        let storage = DiskStorage::new(dir.path().to_path_buf(), Duration::from_secs(3600));
+        storage.set("tampered", "package avl\n").await.unwrap();
+        let path = storage.entry_path("tampered");
+        let data = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, data.replace("avl", "evil")).unwrap();
+        assert_eq!(storage.get("tampered").await.unwrap(), None);
+        assert!(!path.exists());
+        let quarantined = dir
+            .path()
+            .join(QUARANTINE_DIR)
+            .join(path.file_name().unwrap());
+        assert!(quarantined.exists());
+
+        storage.set("truncated", "package avl\n").await.unwrap();
+        let path = storage.entry_path("truncated");
+        let data = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &data[..data.len() / 2]).unwrap();
+        assert_eq!(storage.get_stale("truncated").await.unwrap(), None);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_disk_entries_are_misses() {
+        let dir = tempdir().unwrap();
+        let storage = DiskStorage::new(dir.path().to_path_buf(), Duration::from_secs(3600));
+        // reading a directory fails, whatever the permissions
+        let path = storage.entry_path("unreadable");
+        std::fs::create_dir_all(&path).unwrap();
+        assert_eq!(storage.get("unreadable").await.unwrap(), None);
         assert!(!path.exists());
+
+        storage.set("unreadable", "value").await.unwrap();
+        assert_eq!(
+            storage.get("unreadable").await.unwrap().as_deref(),
+            Some("value")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_disk_storage_reads_entries_without_a_hash() {
+        let dir = tempdir().unwrap();
+        let storage = DiskStorage::new(dir.path().to_path_buf(), Duration::from_secs(3600));
+        let path = storage.entry_path("old");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let entry = serde_json::json!({
+            "content": "package avl\n",
+            "timestamp": DiskStorage::now_ts(),
+            "ttl": 3600,
+        });
+        std::fs::write(&path, entry.to_string()).unwrap();
+        assert_eq!(
+            storage.get("old").await.unwrap().as_deref(),
+            Some("package avl\n")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_memory_storage_set_get_and_expiry() {
+        let storage = MemoryStorage::new(Duration::from_secs(3600));
+        assert_eq!(storage.get("key").await.unwrap(), None);
+        storage.set("key", "value").await.unwrap();
+        assert_eq!(storage.get("key").await.unwrap().as_deref(), Some("value"));
+
+        let expired = MemoryStorage::new(Duration::from_secs(0));
+        expired.set("key", "value").await.unwrap();
+        assert_eq!(expired.get("key").await.unwrap(), None);
+        assert_eq!(
+            expired.get_stale("key").await.unwrap().as_deref(),
+            Some("value")
+        );
+        expired.set("other", "value").await.unwrap();
+        expired.cleanup().await.unwrap();
+        assert!(expired.entries.lock().await.is_empty());
+    }
+
+    #[test]
+    fn test_parse_ttl() {
+        assert_eq!(parse_ttl("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_ttl("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_ttl("30m"), Ok(Duration::from_secs(30 * 60)));
+        assert_eq!(parse_ttl("6H"), Ok(Duration::from_secs(6 * 3600)));
+        assert_eq!(parse_ttl("7d"), Ok(Duration::from_secs(7 * 24 * 3600)));
+        assert_eq!(parse_ttl("0"), Ok(Duration::ZERO));
+        assert!(parse_ttl("1ms").is_err());
+        assert!(parse_ttl("d").is_err());
+    }
+
+    #[test]
+    fn test_ttls_by_kind_of_entry() {
+        let ttls = CacheTtls::default();
+        assert_eq!(
+            ttls.for_key(&files_key("gno.land/p/demo/avl", None)),
+            ttls.files
+        );
+        assert_eq!(
+            ttls.for_key(&content_key("gno.land/p/demo/avl", "avl.gno", Some(3))),
+            ttls.contents
+        );
+        assert_eq!(
+            ttls.for_key(&negative_key("files:gno.land/p/x")),
+            ttls.negative
+        );
+        assert_eq!(ttls.for_key(&parse_key("package avl")), ttls.resolution);
+        assert_eq!(ttls.for_key("unknown"), ttls.contents);
+    }
+
+    #[tokio::test]
+    async fn test_memory_storage_expires_entries_by_their_own_ttl() {
+        let storage = MemoryStorage::new(Duration::from_secs(3600));
+        storage
+            .set_with_ttl("short", "value", Duration::ZERO)
+            .await
+            .unwrap();
+        storage.set("long", "value").await.unwrap();
+        assert_eq!(storage.get("short").await.unwrap(), None);
+        assert_eq!(storage.get("long").await.unwrap().as_deref(), Some("value"));
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_cache_over_noop_storage() {
+        let cache = HybridCache::with_storage(Arc::new(NoopCache), Duration::from_secs(3600), 10);
+        cache.set("key", "value").await.unwrap();
+        assert_eq!(cache.get("key").await.unwrap().as_deref(), Some("value"));
+
+        let other = HybridCache::with_storage(Arc::new(NoopCache), Duration::from_secs(3600), 10);
+        assert_eq!(other.get("key").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_negative_entries_expire_on_their_own() {
+        let cache = HybridCache::with_storage(Arc::new(NoopCache), Duration::from_secs(3600), 10);
+        assert_eq!(cache.get_negative("files:a").await.unwrap(), None);
+
+        cache
+            .set_negative("files:a", "not found", Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert_eq!(
+            cache.get_negative("files:a").await.unwrap().as_deref(),
+            Some("not found")
+        );
+        assert_eq!(cache.get("files:a").await.unwrap(), None);
+
+        cache
+            .set_negative("files:b", "not found", Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(cache.get_negative("files:b").await.unwrap(), None);
     }
 
     #[tokio::test]
@@ -216,4 +1282,27 @@ mod tests {
         let cache2 = HybridCache::new(dir.path().to_path_buf(), Duration::from_secs(3600), 10);
         assert_eq!(cache2.get(key).await.unwrap().as_deref(), Some(val));
     }
+
+    #[tokio::test]
+    async fn test_hybrid_cache_counts_lookups() {
+        let dir = tempdir().unwrap();
+        let cache = HybridCache::new(dir.path().to_path_buf(), Duration::from_secs(3600), 10);
+        assert_eq!(cache.get("key").await.unwrap(), None);
+        cache.set("key", "val").await.unwrap();
+        cache.get("key").await.unwrap();
+        cache.get_negative("other").await.unwrap();
+
+        let stats = cache.stats();
+        assert_eq!((stats.mem_hits, stats.storage_hits), (1, 0));
+        assert_eq!((stats.misses, stats.writes), (1, 1));
+        assert_eq!(stats.hit_rate(), Some(0.5));
+
+        // a fresh memory layer finds it on disk, then keeps it
+        let cache2 = HybridCache::new(dir.path().to_path_buf(), Duration::from_secs(3600), 10);
+        cache2.get("key").await.unwrap();
+        cache2.get("key").await.unwrap();
+        let stats2 = cache2.stats();
+        assert_eq!((stats2.mem_hits, stats2.storage_hits), (1, 1));
+        assert_eq!(stats2.since(&stats2), CacheStats::default());
+    }
 }
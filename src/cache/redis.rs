@@ -0,0 +1,141 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use redis::aio::{ConnectionManager, ConnectionManagerConfig};
+use redis::AsyncCommands;
+
+use super::{AsyncStorage, CacheError};
+
+/// Prefix of every key unless [RedisStorageBuilder::namespace] sets another
+pub const DEFAULT_NAMESPACE: &str = "gget";
+
+/// Storage in a Redis server, so several machines (e.g. CI runners) share
+/// one cache.
+///
+/// Keys are stored as `<namespace>:<key>` and expire through Redis, so
+/// [AsyncStorage::cleanup] has nothing to do.
+pub struct RedisStorage {
+    pool: Vec<ConnectionManager>,
+    next: AtomicUsize,
+    namespace: String,
+    ttl: Duration,
+}
+
+/// Configures a [RedisStorage], see [RedisStorage::builder]
+pub struct RedisStorageBuilder {
+    url: String,
+    namespace: String,
+    ttl: Duration,
+    pool_size: usize,
+    connect_timeout: Option<Duration>,
+    response_timeout: Option<Duration>,
+}
+
+impl RedisStorage {
+    /// Starts configuring a storage on the server at `url`, e.g.
+    /// `redis://127.0.0.1:6379/0`
+    pub fn builder(url: impl Into<String>) -> RedisStorageBuilder {
+        RedisStorageBuilder {
+            url: url.into(),
+            namespace: DEFAULT_NAMESPACE.to_string(),
+            ttl: crate::fetch::DEFAULT_CACHE_TTL,
+            pool_size: 4,
+            connect_timeout: None,
+            response_timeout: None,
+        }
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("{}:{}", self.namespace, key)
+    }
+
+    /// Picks the pooled connections in turn
+    fn connection(&self) -> ConnectionManager {
+        let i = self.next.fetch_add(1, Ordering::Relaxed) % self.pool.len();
+        self.pool[i].clone()
+    }
+}
+
+impl RedisStorageBuilder {
+    /// Prefix keeping these entries apart from other users of the server
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = namespace.into();
+        self
+    }
+
+    /// Expiry set on every entry, rounded up to whole seconds
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Connections opened to the server, 4 by default. Each one carries
+    /// concurrent requests and reconnects on its own after a failure.
+    pub fn pool_size(mut self, size: usize) -> Self {
+        self.pool_size = size.max(1);
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn response_timeout(mut self, timeout: Duration) -> Self {
+        self.response_timeout = Some(timeout);
+        self
+    }
+
+    /// Opens the pooled connections
+    pub async fn connect(self) -> Result<RedisStorage, CacheError> {
+        let client = redis::Client::open(self.url.as_str())?;
+        let mut config = ConnectionManagerConfig::new();
+        if let Some(timeout) = self.connect_timeout {
+            config = config.set_connection_timeout(timeout);
+        }
+        if let Some(timeout) = self.response_timeout {
+            config = config.set_response_timeout(timeout);
+        }
+
+        let mut pool = Vec::with_capacity(self.pool_size);
+        for _ in 0..self.pool_size {
+            pool.push(
+                client
+                    .get_connection_manager_with_config(config.clone())
+                    .await?,
+            );
+        }
+        Ok(RedisStorage {
+            pool,
+            next: AtomicUsize::new(0),
+            namespace: self.namespace,
+            ttl: self.ttl,
+        })
+    }
+}
+
+#[async_trait]
+impl AsyncStorage for RedisStorage {
+    async fn get(&self, key: &str) -> Result<Option<String>, CacheError> {
+        Ok(self.connection().get(self.key(key)).await?)
+    }
+
+    async fn set(&self, key: &str, value: &str) -> Result<(), CacheError> {
+        self.set_with_ttl(key, value, self.ttl).await
+    }
+
+    async fn set_with_ttl(&self, key: &str, value: &str, ttl: Duration) -> Result<(), CacheError> {
+        // Redis refuses a zero expiry
+        let seconds = ttl.as_secs_f64().ceil().max(1.0) as u64;
+        let _: () = self
+            .connection()
+            .set_ex(self.key(key), value, seconds)
+            .await?;
+        Ok(())
+    }
+
+    async fn cleanup(&self) -> Result<(), CacheError> {
+        Ok(())
+    }
+}
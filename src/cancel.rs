@@ -0,0 +1,87 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use thiserror::Error;
+use tokio_util::sync::CancellationToken;
+
+/// Why an operation stopped before finishing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum Interrupted {
+    #[error("operation cancelled")]
+    Cancelled,
+
+    #[error("deadline exceeded")]
+    DeadlineExceeded,
+}
+
+/// Cancellation token and optional deadline bounding an operation.
+///
+/// Clones share the token, so cancelling it stops every clone at its next
+/// await point. The default never interrupts anything.
+#[derive(Debug, Clone, Default)]
+pub struct Cancellation {
+    token: CancellationToken,
+    deadline: Option<Instant>,
+}
+
+impl Cancellation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Interrupts the operation once `token` is cancelled
+    pub fn with_token(mut self, token: CancellationToken) -> Self {
+        self.token = token;
+        self
+    }
+
+    /// Interrupts the operation once `deadline` has passed
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Interrupts the operation once `timeout` from now has passed
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Returns an error if the operation should stop now
+    pub fn check(&self) -> Result<(), Interrupted> {
+        if self.token.is_cancelled() {
+            Err(Interrupted::Cancelled)
+        } else if self.deadline.is_some_and(|d| Instant::now() >= d) {
+            Err(Interrupted::DeadlineExceeded)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Runs `future` until it completes or the operation is interrupted,
+    /// whichever comes first. An interrupted future is dropped.
+    pub async fn run<F: Future>(&self, future: F) -> Result<F::Output, Interrupted> {
+        self.check()?;
+
+        let deadline = async {
+            match self.deadline {
+                Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+                None => std::future::pending().await,
+            }
+        };
+
+        tokio::select! {
+            biased;
+            _ = self.token.cancelled() => Err(Interrupted::Cancelled),
+            _ = deadline => Err(Interrupted::DeadlineExceeded),
+            output = future => Ok(output),
+        }
+    }
+}
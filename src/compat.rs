@@ -0,0 +1,191 @@
+//! Detection of Go constructs that parse fine but that Gno doesn't support.
+//!
+//! Packages using them download and validate, yet fail when deployed, so
+//! validation reports them as warnings.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tree_sitter::{Parser, Query, QueryCursor, StreamingIterator};
+
+use crate::dependency::DependencyError;
+use crate::lint::Severity;
+
+/// One pattern per unsupported construct, captured under the construct's name
+const GO_ONLY_QUERY: &str = r##"
+(go_statement) @goroutine
+(select_statement) @select
+(channel_type) @channel
+(send_statement) @channel
+(unary_expression operator: "<-") @channel
+
+(import_spec path: (_) @cgo (#match? @cgo "^[\"`]C[\"`]$"))
+(import_spec path: (_) @unsafe (#match? @unsafe "^[\"`]unsafe[\"`]$"))
+
+((comment) @cgo (#match? @cgo "#cgo\\s"))
+((comment) @directive (#match? @directive "^//go:"))"##;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GoConstruct {
+    /// `go f()`
+    Goroutine,
+    /// `select { ... }`
+    Select,
+    /// Channel types, sends and receives
+    Channel,
+    /// `import "C"` and `#cgo` directives
+    Cgo,
+    /// `import "unsafe"`
+    Unsafe,
+    /// `//go:` compiler directives such as `//go:linkname`
+    Directive,
+}
+
+impl GoConstruct {
+    fn from_capture(name: &str) -> Option<Self> {
+        match name {
+            "goroutine" => Some(Self::Goroutine),
+            "select" => Some(Self::Select),
+            "channel" => Some(Self::Channel),
+            "cgo" => Some(Self::Cgo),
+            "unsafe" => Some(Self::Unsafe),
+            "directive" => Some(Self::Directive),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for GoConstruct {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            Self::Goroutine => "goroutine",
+            Self::Select => "select statement",
+            Self::Channel => "channel",
+            Self::Cgo => "cgo",
+            Self::Unsafe => "unsafe import",
+            Self::Directive => "Go compiler directive",
+        };
+        f.write_str(description)
+    }
+}
+
+/// A Go-only construct found in a `.gno` file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CompatWarning {
+    pub file: PathBuf,
+    /// 1-based
+    pub line: usize,
+    pub construct: GoConstruct,
+}
+
+impl CompatWarning {
+    pub fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+}
+
+impl fmt::Display for CompatWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {} is not supported by Gno; the package will fail to deploy",
+            self.file.display(),
+            self.line,
+            self.construct
+        )
+    }
+}
+
+/// Finds Go-only constructs with tree-sitter queries
+pub struct CompatChecker {
+    parser: Parser,
+    query: Query,
+    cursor: QueryCursor,
+}
+
+impl CompatChecker {
+    pub fn new() -> Result<Self, DependencyError> {
+        let mut parser = Parser::new();
+        let language = tree_sitter_go::LANGUAGE;
+
+        parser
+            .set_language(&language.into())
+            .map_err(|e| DependencyError::LanguageSetup(e.to_string()))?;
+
+        let query = Query::new(&language.into(), GO_ONLY_QUERY)
+            .map_err(|e| DependencyError::QueryCreation(format!("Go-only query: {}", e)))?;
+
+        Ok(Self {
+            parser,
+            query,
+            cursor: QueryCursor::new(),
+        })
+    }
+
+    /// Returns the `(line, construct)` pairs found in `source`, sorted and
+    /// reported once per line
+    pub fn check_source(
+        &mut self,
+        source: &str,
+    ) -> Result<Vec<(usize, GoConstruct)>, DependencyError> {
+        let tree = self
+            .parser
+            .parse(source, None)
+            .ok_or(DependencyError::ParseError)?;
+
+        let mut found = Vec::new();
+        let mut matches = self
+            .cursor
+            .matches(&self.query, tree.root_node(), source.as_bytes());
+        while let Some(matched) = matches.next() {
+            for capture in matched.captures {
+                let name = self.query.capture_names()[capture.index as usize];
+                if let Some(construct) = GoConstruct::from_capture(name) {
+                    found.push((capture.node.start_position().row + 1, construct));
+                }
+            }
+        }
+
+        found.sort_unstable();
+        found.dedup();
+        Ok(found)
+    }
+
+    /// Checks every `.gno` file under `dir`, recursively
+    pub fn check_dir(&mut self, dir: &Path) -> Result<Vec<CompatWarning>, DependencyError> {
+        let mut warnings = Vec::new();
+        self.visit(dir, &mut warnings)?;
+        Ok(warnings)
+    }
+
+    fn visit(
+        &mut self,
+        dir: &Path,
+        warnings: &mut Vec<CompatWarning>,
+    ) -> Result<(), DependencyError> {
+        let entries = fs::read_dir(dir)
+            .map_err(|e| DependencyError::IoError(format!("Failed to read directory: {}", e)))?;
+        let mut paths: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+        paths.sort();
+
+        for path in paths {
+            if path.is_dir() {
+                self.visit(&path, warnings)?;
+            } else if path.extension().is_some_and(|ext| ext == "gno") {
+                let source = fs::read_to_string(&path)
+                    .map_err(|e| DependencyError::IoError(format!("Failed to read file: {}", e)))?;
+                for (line, construct) in self.check_source(&source)? {
+                    warnings.push(CompatWarning {
+                        file: path.clone(),
+                        line,
+                        construct,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
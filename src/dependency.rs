@@ -1,11 +1,21 @@
 use indexmap::IndexMap;
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt::Write as _;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use rayon::prelude::*;
+use serde::Serialize;
 
 use tree_sitter::{Parser, Query, QueryCursor, StreamingIteratorMut};
 
+use crate::error::ErrorKind;
+use crate::report::{Report, Table};
+use crate::sat::{Formula, Lit};
+
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum DependencyError {
     #[error("Failed to set language: {0}")]
     LanguageSetup(String),
@@ -27,15 +37,272 @@ pub enum DependencyError {
 
     #[error("IO error: {0}")]
     IoError(String),
+
+    #[error("No selection of packages meets the constraints: {0}")]
+    Unsatisfiable(String),
+}
+
+impl DependencyError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::LanguageSetup(_) | Self::QueryCreation(_) | Self::IoError(_) => {
+                ErrorKind::Internal
+            }
+            Self::ParseError
+            | Self::Utf8Error(_)
+            | Self::CircularDependency
+            | Self::Unsatisfiable(_) => ErrorKind::Validation,
+            Self::PackageNotFound(_) => ErrorKind::NotFound,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct PackageDependency {
     pub name: String,
     pub imports: HashSet<String>,
+    /// Imported only by test files, see [is_test_file]; never also in
+    /// `imports`
+    pub test_imports: HashSet<String>,
     pub instability: f64, // TODO: implement instability metric
 }
 
+impl PackageDependency {
+    /// Records what the file `file_name` imports. Imports of test files
+    /// go to `test_imports`, unless the package itself needs them.
+    pub fn add_imports(&mut self, file_name: &str, imports: HashSet<String>) {
+        if is_test_file(file_name) {
+            self.test_imports
+                .extend(imports.into_iter().filter(|i| !self.imports.contains(i)));
+        } else {
+            self.test_imports.retain(|i| !imports.contains(i));
+            self.imports.extend(imports);
+        }
+    }
+}
+
+/// Whether `file_name` is a `_test.gno` or `_filetest.gno` file, which only
+/// `gno test` reads
+pub fn is_test_file(file_name: &str) -> bool {
+    file_name.ends_with(TEST_FILE_SUFFIX) || file_name.ends_with(FILETEST_FILE_SUFFIX)
+}
+
+/// Whether dependency extraction reads test files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TestFiles {
+    /// Read them, recording what only they import as
+    /// [PackageDependency::test_imports]
+    #[default]
+    Include,
+    /// Skip them, leaving `test_imports` empty
+    Exclude,
+}
+
+/// A package importing another, see [DependencyClosure::dependents_of]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Dependent {
+    pub package: String,
+    /// 1 for direct importers, 2 for what imports those, and so on
+    pub depth: usize,
+    /// The package it imports on the way, the queried one if direct
+    pub via: String,
+}
+
+/// Transitive dependency closure discovered from a root package
+#[derive(Debug, Clone, Default)]
+pub struct DependencyClosure {
+    /// Root packages the closure was resolved from
+    pub roots: Vec<String>,
+    /// Every package in the closure, keyed by package path
+    pub packages: HashMap<String, PackageDependency>,
+    /// The package through which each dependency was first discovered
+    pub parents: HashMap<String, String>,
+    /// Packages a policy in warn mode left out, with why
+    pub skipped: BTreeMap<String, String>,
+}
+
+impl DependencyClosure {
+    pub fn new(root: &str) -> Self {
+        Self::from_roots(&[root])
+    }
+
+    /// Creates an empty closure shared by several roots
+    pub fn from_roots(roots: &[&str]) -> Self {
+        Self {
+            roots: roots.iter().map(|r| r.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the root through which `pkg_path` entered the closure
+    pub fn root_of<'a>(&'a self, pkg_path: &'a str) -> &'a str {
+        let mut current = pkg_path;
+        // parents form a tree, but never walk more links than there are
+        for _ in 0..self.parents.len() {
+            match self.parents.get(current) {
+                Some(parent) => current = parent,
+                None => break,
+            }
+        }
+        current
+    }
+
+    /// Returns the chain of packages that required `pkg_path`, nearest first
+    /// and ending with the root (e.g. `[A, B, root]` for `A ← B ← root`)
+    pub fn import_chain(&self, pkg_path: &str) -> Vec<String> {
+        let mut chain = Vec::new();
+        let mut current = pkg_path;
+        while let Some(parent) = self.parents.get(current) {
+            // parents form a tree, but guard against malformed input anyway
+            if chain.contains(parent) {
+                break;
+            }
+            chain.push(parent.clone());
+            current = parent;
+        }
+        chain
+    }
+
+    /// Returns the packages in the closure that directly import each
+    /// package, sorted by path
+    pub fn reverse_edges(&self) -> HashMap<&str, Vec<&str>> {
+        let mut reverse: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (pkg_path, pkg) in &self.packages {
+            for import in &pkg.imports {
+                if self.packages.contains_key(import) {
+                    reverse
+                        .entry(import.as_str())
+                        .or_default()
+                        .push(pkg_path.as_str());
+                }
+            }
+        }
+        for importers in reverse.values_mut() {
+            importers.sort_unstable();
+        }
+        reverse
+    }
+
+    /// Returns the packages of the closure importing `pkg_path`, directly
+    /// or through others, nearest first and then by path.
+    ///
+    /// Imports of test files count, and `pkg_path` needn't be in the
+    /// closure itself.
+    pub fn dependents_of(&self, pkg_path: &str) -> Vec<Dependent> {
+        let mut importers: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (path, pkg) in &self.packages {
+            for import in pkg.imports.iter().chain(&pkg.test_imports) {
+                if import != path {
+                    importers.entry(import).or_default().push(path);
+                }
+            }
+        }
+        for paths in importers.values_mut() {
+            paths.sort_unstable();
+        }
+
+        let mut dependents = Vec::new();
+        let mut seen = HashSet::from([pkg_path]);
+        let mut queue = VecDeque::from([(pkg_path, 0)]);
+        while let Some((current, depth)) = queue.pop_front() {
+            for importer in importers.get(current).into_iter().flatten() {
+                if seen.insert(importer) {
+                    dependents.push(Dependent {
+                        package: importer.to_string(),
+                        depth: depth + 1,
+                        via: current.to_string(),
+                    });
+                    queue.push_back((importer, depth + 1));
+                }
+            }
+        }
+
+        dependents.sort_by(|a, b| (a.depth, &a.package).cmp(&(b.depth, &b.package)));
+        dependents
+    }
+
+    /// Counts the packages that transitively depend on each package,
+    /// i.e. how many downloads a failure of that package would block
+    pub fn dependent_counts(&self) -> HashMap<String, usize> {
+        let reverse = self.reverse_edges();
+        let mut counts = HashMap::with_capacity(self.packages.len());
+
+        for pkg_path in self.packages.keys() {
+            let mut seen = HashSet::new();
+            let mut stack = vec![pkg_path.as_str()];
+            while let Some(current) = stack.pop() {
+                for dependent in reverse.get(current).into_iter().flatten() {
+                    if *dependent != pkg_path && seen.insert(*dependent) {
+                        stack.push(dependent);
+                    }
+                }
+            }
+            counts.insert(pkg_path.clone(), seen.len());
+        }
+
+        counts
+    }
+
+    /// Groups the closure into deployment waves: every package comes after
+    /// all of the packages it imports, so each wave can be processed
+    /// concurrently once the previous ones are done.
+    ///
+    /// Packages within a wave are sorted by path. Packages caught in an
+    /// import cycle are placed together in a final wave.
+    pub fn deployment_waves(&self) -> Vec<Vec<String>> {
+        let reverse = self.reverse_edges();
+        let mut pending: HashMap<&str, usize> = self
+            .packages
+            .iter()
+            .map(|(path, pkg)| {
+                let in_closure = pkg
+                    .imports
+                    .iter()
+                    .filter(|import| *import != path && self.packages.contains_key(*import))
+                    .count();
+                (path.as_str(), in_closure)
+            })
+            .collect();
+
+        let mut waves = Vec::new();
+        let mut current: Vec<&str> = pending
+            .iter()
+            .filter(|(_, &count)| count == 0)
+            .map(|(path, _)| *path)
+            .collect();
+
+        while !current.is_empty() {
+            current.sort_unstable();
+            for path in &current {
+                pending.remove(path);
+            }
+
+            let mut next = Vec::new();
+            for path in &current {
+                for dependent in reverse.get(path).into_iter().flatten() {
+                    if let Some(count) = pending.get_mut(dependent) {
+                        *count -= 1;
+                        if *count == 0 {
+                            next.push(*dependent);
+                        }
+                    }
+                }
+            }
+
+            waves.push(current.iter().map(|p| p.to_string()).collect());
+            current = next;
+        }
+
+        if !pending.is_empty() {
+            let mut cyclic: Vec<String> = pending.keys().map(|p| p.to_string()).collect();
+            cyclic.sort_unstable();
+            waves.push(cyclic);
+        }
+
+        waves
+    }
+}
+
 pub struct DependencyGraph {
     /// Number of incoming edges for each package
     in_degree: IndexMap<String, usize>,
@@ -43,6 +310,339 @@ pub struct DependencyGraph {
     adj: IndexMap<String, Vec<String>>,
 }
 
+impl DependencyGraph {
+    /// Builds the graph of imports between `packages`, leaving out those of
+    /// packages outside the map. Test imports are edges too with
+    /// [TestFiles::Include].
+    pub fn from_packages(
+        packages: &HashMap<String, PackageDependency>,
+        test_files: TestFiles,
+    ) -> Self {
+        let mut in_degree: IndexMap<String, usize> = IndexMap::new();
+        let mut adj: IndexMap<String, Vec<String>> = IndexMap::new();
+
+        // Initialize all packages with zero in-degree, by path so the
+        // graph doesn't depend on the map's iteration order
+        let mut names: Vec<&String> = packages.keys().collect();
+        names.sort_unstable();
+        for package_name in &names {
+            in_degree.insert(package_name.to_string(), 0);
+            adj.insert(package_name.to_string(), Vec::new());
+        }
+
+        // Build dependency relationships
+        for pkg_name in names {
+            let pkg = &packages[pkg_name];
+            let mut imports: Vec<&String> = match test_files {
+                TestFiles::Include => pkg.imports.union(&pkg.test_imports).collect(),
+                TestFiles::Exclude => pkg.imports.iter().collect(),
+            };
+            imports.sort_unstable();
+            for import in imports {
+                if packages.contains_key(import) {
+                    // Increment in-degree for the importing package
+                    *in_degree.get_mut(pkg_name).unwrap() += 1;
+                    // Add the importing package as a dependent of the imported package
+                    adj.get_mut(import).unwrap().push(pkg_name.clone());
+                }
+            }
+        }
+
+        DependencyGraph { in_degree, adj }
+    }
+
+    /// Every import chain from `from` down to `to`, both included, shortest
+    /// first and then by path. No chain visits a package twice, and at most
+    /// `limit` are returned since their number grows quickly in dense graphs.
+    pub fn import_paths(&self, from: &str, to: &str, limit: usize) -> Vec<Vec<String>> {
+        if !self.adj.contains_key(from) || !self.adj.contains_key(to) {
+            return Vec::new();
+        }
+
+        // Only packages `to` is reachable from can continue a chain
+        let mut leads: HashSet<&str> = HashSet::from([to]);
+        let mut queue = VecDeque::from([to]);
+        while let Some(pkg) = queue.pop_front() {
+            for dependent in &self.adj[pkg] {
+                if leads.insert(dependent) {
+                    queue.push_back(dependent);
+                }
+            }
+        }
+        if !leads.contains(from) {
+            return Vec::new();
+        }
+
+        let mut imports: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (pkg, dependents) in &self.adj {
+            if !leads.contains(pkg.as_str()) {
+                continue;
+            }
+            for dependent in dependents {
+                imports.entry(dependent).or_default().push(pkg);
+            }
+        }
+        for targets in imports.values_mut() {
+            targets.sort_unstable();
+        }
+
+        // Breadth first over partial chains, so they come out shortest first;
+        // sorted imports keep those of equal length in path order
+        let mut paths = Vec::new();
+        let mut queue: VecDeque<Vec<&str>> = VecDeque::from([vec![from]]);
+        while let Some(path) = queue.pop_front() {
+            if paths.len() >= limit {
+                break;
+            }
+            let last = *path.last().unwrap();
+            if last == to {
+                paths.push(path.iter().map(|pkg| pkg.to_string()).collect());
+                continue;
+            }
+            for &next in imports.get(last).into_iter().flatten() {
+                if !path.contains(&next) {
+                    let mut longer = path.clone();
+                    longer.push(next);
+                    queue.push_back(longer);
+                }
+            }
+        }
+        paths
+    }
+
+    /// Computes size, depth, fan-in/fan-out and cycle figures of the graph
+    pub fn metrics(&self) -> GraphMetrics {
+        let names: Vec<&str> = self.adj.keys().map(|p| p.as_str()).collect();
+        let index: HashMap<&str, usize> = names.iter().enumerate().map(|(i, p)| (*p, i)).collect();
+        let dependents: Vec<Vec<usize>> = self
+            .adj
+            .values()
+            .map(|ds| ds.iter().map(|d| index[d.as_str()]).collect())
+            .collect();
+        let mut imports: Vec<Vec<usize>> = vec![Vec::new(); names.len()];
+        for (pkg, ds) in dependents.iter().enumerate() {
+            for &d in ds {
+                imports[d].push(pkg);
+            }
+        }
+
+        let components = strongly_connected(&imports, &dependents);
+        let mut component_of = vec![0; names.len()];
+        for (c, members) in components.iter().enumerate() {
+            for &pkg in members {
+                component_of[pkg] = c;
+            }
+        }
+
+        // Components come out importers first, so those they import are
+        // settled by the time they're reached backwards
+        let mut component_depth = vec![0; components.len()];
+        for (c, members) in components.iter().enumerate().rev() {
+            component_depth[c] = members
+                .iter()
+                .flat_map(|&pkg| &imports[pkg])
+                .map(|&i| component_of[i])
+                .filter(|&other| other != c)
+                .map(|other| component_depth[other] + 1)
+                .max()
+                .unwrap_or(0);
+        }
+
+        let packages: Vec<PackageMetrics> = names
+            .iter()
+            .enumerate()
+            .map(|(pkg, name)| PackageMetrics {
+                package: name.to_string(),
+                fan_in: dependents[pkg].len(),
+                fan_out: imports[pkg].len(),
+                depth: component_depth[component_of[pkg]],
+            })
+            .collect();
+
+        let mut cycles: Vec<Vec<String>> = components
+            .iter()
+            .filter(|members| members.len() > 1 || imports[members[0]].contains(&members[0]))
+            .map(|members| {
+                let mut members: Vec<String> =
+                    members.iter().map(|&pkg| names[pkg].to_string()).collect();
+                members.sort_unstable();
+                members
+            })
+            .collect();
+        cycles.sort_unstable();
+
+        let mut most_depended_upon: Vec<PackageMetrics> =
+            packages.iter().filter(|p| p.fan_in > 0).cloned().collect();
+        most_depended_upon.sort_by(|a, b| b.fan_in.cmp(&a.fan_in).then(a.package.cmp(&b.package)));
+        most_depended_upon.truncate(MOST_DEPENDED_UPON);
+
+        let edges = dependents.iter().map(Vec::len).sum();
+        GraphMetrics {
+            nodes: names.len(),
+            edges,
+            max_depth: component_depth.iter().copied().max().unwrap_or(0),
+            average_fan_out: if names.is_empty() {
+                0.0
+            } else {
+                edges as f64 / names.len() as f64
+            },
+            cycles,
+            most_depended_upon,
+            packages,
+        }
+    }
+
+    /// The subgraph of the packages in `keep`
+    fn restrict(&self, keep: &HashSet<&str>) -> DependencyGraph {
+        let adj: IndexMap<String, Vec<String>> = self
+            .adj
+            .iter()
+            .filter(|(pkg, _)| keep.contains(pkg.as_str()))
+            .map(|(pkg, dependents)| {
+                let dependents = dependents
+                    .iter()
+                    .filter(|d| keep.contains(d.as_str()))
+                    .cloned()
+                    .collect();
+                (pkg.clone(), dependents)
+            })
+            .collect();
+
+        let mut in_degree: IndexMap<String, usize> =
+            adj.keys().map(|pkg| (pkg.clone(), 0)).collect();
+        for dependents in adj.values() {
+            for dependent in dependents {
+                in_degree[dependent] += 1;
+            }
+        }
+
+        DependencyGraph { in_degree, adj }
+    }
+}
+
+/// Number of packages listed in [GraphMetrics::most_depended_upon]
+pub const MOST_DEPENDED_UPON: usize = 10;
+
+/// Figures about a [DependencyGraph], see [DependencyGraph::metrics].
+///
+/// Only imports between packages of the graph count.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GraphMetrics {
+    pub nodes: usize,
+    /// Imports between packages
+    pub edges: usize,
+    /// Imports on the longest chain, counting those within a cycle as none
+    pub max_depth: usize,
+    pub average_fan_out: f64,
+    /// Strongly connected components with more than one package, or a
+    /// package importing itself: the import cycles. By path.
+    pub cycles: Vec<Vec<String>>,
+    /// Most imported first, at most [MOST_DEPENDED_UPON]
+    pub most_depended_upon: Vec<PackageMetrics>,
+    /// Every package, by path
+    pub packages: Vec<PackageMetrics>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PackageMetrics {
+    pub package: String,
+    /// Packages importing it
+    pub fan_in: usize,
+    /// Packages it imports
+    pub fan_out: usize,
+    /// Imports on its longest chain, see [GraphMetrics::max_depth]
+    pub depth: usize,
+}
+
+impl Report for GraphMetrics {
+    fn table(&self) -> Table {
+        let mut table = Table::new(
+            "Import graph metrics",
+            &["package", "fan_in", "fan_out", "depth"],
+        );
+        for pkg in &self.packages {
+            table.push_row([
+                pkg.package.clone(),
+                pkg.fan_in.to_string(),
+                pkg.fan_out.to_string(),
+                pkg.depth.to_string(),
+            ]);
+        }
+        table
+    }
+
+    fn human(&self) -> String {
+        let mut out = format!(
+            "{} packages, {} imports ({:.2} per package), {} deep\n",
+            self.nodes, self.edges, self.average_fan_out, self.max_depth
+        );
+        if !self.cycles.is_empty() {
+            out.push_str("Import cycles:\n");
+            for cycle in &self.cycles {
+                let _ = writeln!(out, "  {}", cycle.join(", "));
+            }
+        }
+        if !self.most_depended_upon.is_empty() {
+            out.push_str("Most depended upon:\n");
+            for pkg in &self.most_depended_upon {
+                let _ = writeln!(out, "  {} ({} importers)", pkg.package, pkg.fan_in);
+            }
+        }
+        out
+    }
+}
+
+/// Kosaraju's algorithm over `edges`, `reverse` holding the same edges the
+/// other way round. Components come out in topological order: none has
+/// edges to those before it.
+fn strongly_connected(edges: &[Vec<usize>], reverse: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    // first pass: order nodes by when their depth-first search finishes
+    let mut visited = vec![false; edges.len()];
+    let mut finished = Vec::with_capacity(edges.len());
+    for start in 0..edges.len() {
+        if visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        let mut stack = vec![(start, 0)];
+        while let Some((node, next)) = stack.pop() {
+            if let Some(&target) = edges[node].get(next) {
+                stack.push((node, next + 1));
+                if !visited[target] {
+                    visited[target] = true;
+                    stack.push((target, 0));
+                }
+            } else {
+                finished.push(node);
+            }
+        }
+    }
+
+    // second pass: what reaches each node, latest finished first
+    let mut component = vec![usize::MAX; edges.len()];
+    let mut components = Vec::new();
+    for &start in finished.iter().rev() {
+        if component[start] != usize::MAX {
+            continue;
+        }
+        let id = components.len();
+        component[start] = id;
+        let mut members = vec![start];
+        let mut stack = vec![start];
+        while let Some(node) = stack.pop() {
+            for &source in &reverse[node] {
+                if component[source] == usize::MAX {
+                    component[source] = id;
+                    members.push(source);
+                    stack.push(source);
+                }
+            }
+        }
+        components.push(members);
+    }
+    components
+}
+
 const PACKAGE_QUERY: &str = r#"(package_clause (package_identifier) @package)"#;
 
 const IMPORT_QUERY: &str = r#"
@@ -50,75 +650,208 @@ const IMPORT_QUERY: &str = r#"
 (import_declaration
     (import_spec_list
     (import_spec
-        name: (package_identifier)? @alias
-        path: (interpreted_string_literal) @import)))
+        name: [(package_identifier) (dot) (blank_identifier)]? @alias
+        path: [(interpreted_string_literal) (raw_string_literal)] @import)))
 
 ; Single import case
 (import_declaration
     (import_spec
-    name: (package_identifier)? @alias
-    path: (interpreted_string_literal) @import))"#;
+    name: [(package_identifier) (dot) (blank_identifier)]? @alias
+    path: [(interpreted_string_literal) (raw_string_literal)] @import))"#;
 
 const GNO_LAND_PREFIX: &str = "gno.land/";
-const GNO_FILE_EXTENSION: &str = "gno";
+pub const GNO_FILE_EXTENSION: &str = "gno";
+pub const TEST_FILE_SUFFIX: &str = "_test.gno";
+pub const FILETEST_FILE_SUFFIX: &str = "_filetest.gno";
+
+/// The string a Go string literal spells: raw strings (`` `...` ``) as
+/// written, interpreted ones (`"..."`) with their escape sequences decoded.
+///
+/// `None` for malformed literals, which tree-sitter still captures while
+/// recovering from syntax errors.
+fn string_literal_value(literal: &str) -> Option<String> {
+    if let Some(raw) = literal
+        .strip_prefix('`')
+        .and_then(|rest| rest.strip_suffix('`'))
+    {
+        // carriage returns are dropped from raw strings
+        return Some(raw.replace('\r', ""));
+    }
+    let inner = literal.strip_prefix('"')?.strip_suffix('"')?;
+    if !inner.contains('\\') {
+        return Some(inner.to_string());
+    }
+
+    let mut value = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        let escaped = match chars.next()? {
+            'a' => '\u{07}',
+            'b' => '\u{08}',
+            'f' => '\u{0c}',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'v' => '\u{0b}',
+            '\\' => '\\',
+            '"' => '"',
+            // bytes, which only make up a valid path on their own when ASCII
+            'x' => hex_escape(&mut chars, 2).filter(char::is_ascii)?,
+            'u' => hex_escape(&mut chars, 4)?,
+            'U' => hex_escape(&mut chars, 8)?,
+            first @ '0'..='7' => {
+                let digits: String = std::iter::once(first)
+                    .chain(chars.by_ref().take(2))
+                    .collect();
+                char::from(u8::from_str_radix(&digits, 8).ok().filter(u8::is_ascii)?)
+            }
+            _ => return None,
+        };
+        value.push(escaped);
+    }
+    Some(value)
+}
+
+/// Decodes the `len` hex digits of a `\x`, `\u` or `\U` escape
+fn hex_escape(chars: &mut std::str::Chars, len: usize) -> Option<char> {
+    let digits: String = chars.by_ref().take(len).collect();
+    if digits.len() != len || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    char::from_u32(u32::from_str_radix(&digits, 16).ok()?)
+}
+
+/// What one .gno file of a directory declares
+struct ParsedFile {
+    file_name: String,
+    package_name: String,
+    imports: HashSet<String>,
+}
+
+impl ParsedFile {
+    /// Adds the file's imports to its package, creating it if needed
+    fn merge_into(self, packages: &mut HashMap<String, PackageDependency>) {
+        packages
+            .entry(self.package_name.clone())
+            .or_insert_with(|| PackageDependency {
+                name: self.package_name,
+                imports: HashSet::new(),
+                test_imports: HashSet::new(),
+                instability: 0.0,
+            })
+            .add_imports(&self.file_name, self.imports);
+    }
+}
+
+/// Returns the .gno files under `dir`, recursively
+pub fn gno_files(dir: &Path) -> Result<Vec<PathBuf>, DependencyError> {
+    let mut files = Vec::new();
+    collect_gno_files(dir, &mut files)?;
+    Ok(files)
+}
+
+/// Creates a parser for the Go grammar
+fn new_parser() -> Result<Parser, DependencyError> {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&tree_sitter_go::LANGUAGE.into())
+        .map_err(|e| DependencyError::LanguageSetup(e.to_string()))?;
+    Ok(parser)
+}
+
+fn collect_gno_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), DependencyError> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+
+    let entries = fs::read_dir(dir)
+        .map_err(|e| DependencyError::IoError(format!("Failed to read directory: {}", e)))?;
+
+    for entry in entries {
+        let entry =
+            entry.map_err(|e| DependencyError::IoError(format!("Failed to read entry: {}", e)))?;
+        let path = entry.path();
 
+        if path.is_dir() {
+            collect_gno_files(&path, files)?;
+        } else if path.extension().and_then(|s| s.to_str()) == Some(GNO_FILE_EXTENSION) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Queries compiled once for the Go grammar
+struct Queries {
+    package: Query,
+    import: Query,
+}
+
+/// Extracts what Gno sources declare and orders packages by dependency.
+///
+/// Extraction takes `&self`: parsers are taken from a pool shared by
+/// clones, so one resolver can serve many threads or tasks at once.
+#[derive(Clone)]
 pub struct DependencyResolver {
-    parser: Parser,
-    package_query: Query,
-    import_query: Query,
-    cursor: QueryCursor,
+    queries: Arc<Queries>,
+    /// Parsers not in use, created on demand
+    parsers: Arc<Mutex<Vec<Parser>>>,
     /// Strategy for resolving dependencies
-    strategy: Box<dyn ResolutionStrategy>,
+    strategy: Arc<dyn ResolutionStrategy>,
+    test_files: TestFiles,
 }
 
 impl DependencyResolver {
     /// Creates a new DependencyResolver instance
     pub fn new() -> Result<Self, DependencyError> {
-        let mut parser = Parser::new();
         let language = tree_sitter_go::LANGUAGE;
 
-        parser
-            .set_language(&language.into())
-            .map_err(|e| DependencyError::LanguageSetup(e.to_string()))?;
-
-        let package_query = Query::new(&language.into(), PACKAGE_QUERY)
+        let package = Query::new(&language.into(), PACKAGE_QUERY)
             .map_err(|e| DependencyError::QueryCreation(format!("package query: {}", e)))?;
 
-        // TODO: Consider raw strings and support dot imports
-        let import_query = Query::new(&language.into(), IMPORT_QUERY)
+        let import = Query::new(&language.into(), IMPORT_QUERY)
             .map_err(|e| DependencyError::QueryCreation(format!("import query: {}", e)))?;
 
         Ok(Self {
-            parser,
-            package_query,
-            import_query,
-            cursor: QueryCursor::new(),
-            strategy: Box::new(TopoSort),
+            queries: Arc::new(Queries { package, import }),
+            parsers: Arc::new(Mutex::new(vec![new_parser()?])),
+            strategy: Arc::new(TopoSort),
+            test_files: TestFiles::default(),
         })
     }
 
     /// Extract dependencies from Gno source code
     pub fn extract_dependencies(
-        &mut self,
+        &self,
         source_code: &str,
     ) -> Result<(String, HashSet<String>), DependencyError> {
-        let tree = self
-            .parser
-            .parse(source_code, None)
-            .ok_or(DependencyError::ParseError)?;
+        let pooled = self.parsers.lock().unwrap().pop();
+        let mut parser = match pooled {
+            Some(parser) => parser,
+            None => new_parser()?,
+        };
+        let tree = parser.parse(source_code, None);
+        self.parsers.lock().unwrap().push(parser);
+        let tree = tree.ok_or(DependencyError::ParseError)?;
 
         let root_node = tree.root_node();
         let bytes = source_code.as_bytes();
+        let mut cursor = QueryCursor::new();
 
-        let package_name = self.extract_package_name(root_node, bytes)?;
-        let imports = self.extract_imports(root_node, bytes)?;
+        let package_name = self.extract_package_name(&mut cursor, root_node, bytes)?;
+        let imports = self.extract_imports(&mut cursor, root_node, bytes)?;
 
         Ok((package_name, imports))
     }
 
     /// Extract dependencies from all .gno files in a directory recursively
     pub fn extract_dependencies_from_directory(
-        &mut self,
+        &self,
         dir: &Path,
     ) -> Result<HashMap<String, PackageDependency>, DependencyError> {
         let mut packages: HashMap<String, PackageDependency> = HashMap::new();
@@ -126,34 +859,64 @@ impl DependencyResolver {
         Ok(packages)
     }
 
+    /// Like [DependencyResolver::extract_dependencies_from_directory],
+    /// parsing files on the rayon thread pool.
+    ///
+    /// Each worker takes a parser of its own from the pool. Worth it for
+    /// large vendor trees, where parsing dominates.
+    pub fn extract_dependencies_from_directory_parallel(
+        &self,
+        dir: &Path,
+    ) -> Result<HashMap<String, PackageDependency>, DependencyError> {
+        let files = gno_files(dir)?;
+
+        let parsed = files
+            .par_iter()
+            .map(|path| self.parse_gno_file(path))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut packages = HashMap::new();
+        for parsed in parsed.into_iter().flatten() {
+            parsed.merge_into(&mut packages);
+        }
+        Ok(packages)
+    }
+
     /// Generate deployment order for packages based on their dependencies
     pub fn generate_deployment_order(
         &self,
         packages: &HashMap<String, PackageDependency>,
-    ) -> Vec<String> {
+    ) -> Result<Vec<String>, DependencyError> {
         let graph = self.build_dependency_graph(packages);
         self.strategy.resolve(&graph)
     }
 
     /// Set the resolution strategy for the dependency resolver
-    #[allow(unused)]
     pub fn with_strategy<S: ResolutionStrategy + 'static>(mut self, strategy: S) -> Self {
-        self.strategy = Box::new(strategy);
+        self.strategy = Arc::new(strategy);
+        self
+    }
+
+    /// Sets whether [DependencyResolver::extract_dependencies_from_directory]
+    /// reads test files
+    pub fn with_test_files(mut self, test_files: TestFiles) -> Self {
+        self.test_files = test_files;
         self
     }
 
     /// Extract package name from the parsed tree
     fn extract_package_name(
-        &mut self,
+        &self,
+        cursor: &mut QueryCursor,
         root_node: tree_sitter::Node,
         bytes: &[u8],
     ) -> Result<String, DependencyError> {
         let mut package_name = String::new();
-        let mut matches = self.cursor.matches(&self.package_query, root_node, bytes);
+        let mut matches = cursor.matches(&self.queries.package, root_node, bytes);
 
         while let Some(matched) = matches.next_mut() {
             for capture in matched.captures {
-                if self.package_query.capture_names()[capture.index as usize] == "package" {
+                if self.queries.package.capture_names()[capture.index as usize] == "package" {
                     package_name = capture
                         .node
                         .utf8_text(bytes)
@@ -169,26 +932,28 @@ impl DependencyResolver {
 
     /// Extract imports from the parsed tree
     fn extract_imports(
-        &mut self,
+        &self,
+        cursor: &mut QueryCursor,
         root_node: tree_sitter::Node,
         bytes: &[u8],
     ) -> Result<HashSet<String>, DependencyError> {
         let mut imports = HashSet::new();
-        let mut matches = self.cursor.matches(&self.import_query, root_node, bytes);
+        let mut matches = cursor.matches(&self.queries.import, root_node, bytes);
 
         while let Some(matched) = matches.next_mut() {
             for capture in matched.captures {
-                if self.import_query.capture_names()[capture.index as usize] == "import" {
-                    let import_text = capture
+                if self.queries.import.capture_names()[capture.index as usize] == "import" {
+                    let literal = capture
                         .node
                         .utf8_text(bytes)
-                        .map_err(|e| DependencyError::Utf8Error(e.to_string()))?
-                        .trim_matches('"')
-                        .to_string();
+                        .map_err(|e| DependencyError::Utf8Error(e.to_string()))?;
 
                     // Only include gno.land imports, not standard library imports
-                    if import_text.starts_with(GNO_LAND_PREFIX) {
-                        imports.insert(import_text);
+                    match string_literal_value(literal) {
+                        Some(path) if path.starts_with(GNO_LAND_PREFIX) => {
+                            imports.insert(path);
+                        }
+                        _ => {}
                     }
                 }
             }
@@ -199,64 +964,39 @@ impl DependencyResolver {
 
     /// Recursively visit directory and process .gno files
     fn visit_directory(
-        &mut self,
+        &self,
         dir: &Path,
         packages: &mut HashMap<String, PackageDependency>,
     ) -> Result<(), DependencyError> {
-        if !dir.is_dir() {
-            return Ok(());
-        }
-
-        let entries = fs::read_dir(dir)
-            .map_err(|e| DependencyError::IoError(format!("Failed to read directory: {}", e)))?;
-
-        for entry in entries {
-            let entry = entry
-                .map_err(|e| DependencyError::IoError(format!("Failed to read entry: {}", e)))?;
-            let path = entry.path();
-
-            if path.is_dir() {
-                self.visit_directory(&path, packages)?;
-            } else if self.is_gno_file(&path) {
-                self.process_gno_file(&path, packages)?;
+        for path in gno_files(dir)? {
+            if let Some(parsed) = self.parse_gno_file(&path)? {
+                parsed.merge_into(packages);
             }
         }
-
         Ok(())
     }
 
-    /// Check if a path is a .gno file
-    fn is_gno_file(&self, path: &Path) -> bool {
-        path.extension()
-            .and_then(|s| s.to_str())
-            .map(|ext| ext == GNO_FILE_EXTENSION)
-            .unwrap_or(false)
-    }
+    /// Reads and parses a single .gno file, unless it is a test file and
+    /// those are excluded
+    fn parse_gno_file(&self, path: &Path) -> Result<Option<ParsedFile>, DependencyError> {
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default()
+            .to_string();
+        if self.test_files == TestFiles::Exclude && is_test_file(&file_name) {
+            return Ok(None);
+        }
 
-    /// Process a single .gno file and add its dependencies to the packages map
-    fn process_gno_file(
-        &mut self,
-        path: &Path,
-        packages: &mut HashMap<String, PackageDependency>,
-    ) -> Result<(), DependencyError> {
         let content = fs::read_to_string(path)
             .map_err(|e| DependencyError::IoError(format!("Failed to read file: {}", e)))?;
 
         let (package_name, imports) = self.extract_dependencies(&content)?;
-
-        packages
-            .entry(package_name.clone())
-            .and_modify(|pkg| {
-                // Merge imports if package already exists
-                pkg.imports.extend(imports.clone());
-            })
-            .or_insert(PackageDependency {
-                name: package_name,
-                imports,
-                instability: 0.0,
-            });
-
-        Ok(())
+        Ok(Some(ParsedFile {
+            file_name,
+            package_name,
+            imports,
+        }))
     }
 
     /// Build a dependency graph from packages
@@ -264,54 +1004,38 @@ impl DependencyResolver {
         &self,
         packages: &HashMap<String, PackageDependency>,
     ) -> DependencyGraph {
-        let mut in_degree: IndexMap<String, usize> = IndexMap::new();
-        let mut adj: IndexMap<String, Vec<String>> = IndexMap::new();
-
-        // Initialize all packages with zero in-degree
-        for package_name in packages.keys() {
-            in_degree.insert(package_name.clone(), 0);
-            adj.insert(package_name.clone(), Vec::new());
-        }
-
-        // Build dependency relationships
-        for (pkg_name, pkg) in packages {
-            for import in &pkg.imports {
-                if packages.contains_key(import) {
-                    // Increment in-degree for the importing package
-                    *in_degree.get_mut(pkg_name).unwrap() += 1;
-                    // Add the importing package as a dependent of the imported package
-                    adj.get_mut(import).unwrap().push(pkg_name.clone());
-                }
-            }
-        }
-
-        DependencyGraph { in_degree, adj }
+        DependencyGraph::from_packages(packages, TestFiles::Exclude)
     }
 }
 
 /// Strategy trait for dependency resolution algorithms
-pub trait ResolutionStrategy {
-    fn resolve(&self, graph: &DependencyGraph) -> Vec<String>;
+pub trait ResolutionStrategy: Send + Sync {
+    /// Orders the packages of `graph` so each comes after what it imports,
+    /// failing if the strategy's constraints can't be met
+    fn resolve(&self, graph: &DependencyGraph) -> Result<Vec<String>, DependencyError>;
 }
 
-/// Topological sort implementation for dependency resolution
+/// Topological sort implementation for dependency resolution.
+///
+/// Of the packages ready at any point, the one with the smallest path
+/// comes first, so the order only depends on the graph.
 pub struct TopoSort;
 
 impl ResolutionStrategy for TopoSort {
-    fn resolve(&self, graph: &DependencyGraph) -> Vec<String> {
+    fn resolve(&self, graph: &DependencyGraph) -> Result<Vec<String>, DependencyError> {
         let mut in_degree = graph.in_degree.clone();
-        let mut queue = VecDeque::new();
+        let mut queue = BTreeSet::new();
         let mut order = Vec::new();
 
         // Start with packages that have no dependencies (in-degree = 0)
         for (node, &degree) in &in_degree {
             if degree == 0 {
-                queue.push_back(node.clone());
+                queue.insert(node.clone());
             }
         }
 
         // Process packages in topological order
-        while let Some(current) = queue.pop_front() {
+        while let Some(current) = queue.pop_first() {
             order.push(current.clone());
 
             // Update in-degrees of dependent packages
@@ -320,29 +1044,133 @@ impl ResolutionStrategy for TopoSort {
                     let degree = in_degree.get_mut(dependent).unwrap();
                     *degree -= 1;
                     if *degree == 0 {
-                        queue.push_back(dependent.clone());
+                        queue.insert(dependent.clone());
                     }
                 }
             }
         }
 
         // Handle any remaining packages (indicates cycles)
-        for (node, &degree) in &in_degree {
-            if degree > 0 && !order.contains(node) {
-                order.push(node.clone());
+        let mut remaining: Vec<&String> = in_degree
+            .iter()
+            .filter(|(node, &degree)| degree > 0 && !order.contains(*node))
+            .map(|(node, _)| node)
+            .collect();
+        remaining.sort_unstable();
+        order.extend(remaining.into_iter().cloned());
+
+        Ok(order)
+    }
+}
+
+/// Selects packages by solving their constraints as a SAT problem, then
+/// orders the selection like [TopoSort].
+///
+/// Every package is required unless marked optional, and can only be
+/// selected along with what it imports. Optional packages are kept
+/// whenever the constraints allow, earlier ones first; see
+/// [Formula::maximize].
+#[derive(Debug, Clone, Default)]
+pub struct SatResolver {
+    optional: Vec<String>,
+    conflicts: Vec<(String, String)>,
+}
+
+impl SatResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lets `pkg_path` be left out, with the packages importing it, when
+    /// keeping it would break a constraint
+    pub fn with_optional(mut self, pkg_path: &str) -> Self {
+        self.optional.push(pkg_path.to_string());
+        self
+    }
+
+    /// Forbids selecting both `a` and `b`
+    pub fn with_conflict(mut self, a: &str, b: &str) -> Self {
+        self.conflicts.push((a.to_string(), b.to_string()));
+        self
+    }
+
+    /// Names the conflicts between packages that can't be left out: the
+    /// required ones and everything they import
+    fn explain(&self, graph: &DependencyGraph) -> String {
+        let mut imports: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (dep, dependents) in &graph.adj {
+            for dependent in dependents {
+                imports.entry(dependent).or_default().push(dep);
             }
         }
 
-        order
+        let mut needed: HashSet<&str> = HashSet::new();
+        let mut queue: VecDeque<&str> = graph
+            .in_degree
+            .keys()
+            .map(String::as_str)
+            .filter(|pkg| !self.optional.iter().any(|o| o == pkg))
+            .collect();
+        while let Some(pkg) = queue.pop_front() {
+            if needed.insert(pkg) {
+                queue.extend(imports.get(pkg).into_iter().flatten());
+            }
+        }
+
+        self.conflicts
+            .iter()
+            .filter(|(a, b)| needed.contains(a.as_str()) && needed.contains(b.as_str()))
+            .map(|(a, b)| format!("{} conflicts with {}", a, b))
+            .collect::<Vec<_>>()
+            .join("; ")
     }
 }
 
-/// SAT solver strategy for dependency resolution (placeholder for future implementation)
-#[allow(unused)]
-struct SatResolver;
-
 impl ResolutionStrategy for SatResolver {
-    fn resolve(&self, _graph: &DependencyGraph) -> Vec<String> {
-        unimplemented!("SAT solver strategy not yet implemented")
+    fn resolve(&self, graph: &DependencyGraph) -> Result<Vec<String>, DependencyError> {
+        let index: HashMap<&str, usize> = graph
+            .in_degree
+            .keys()
+            .enumerate()
+            .map(|(var, pkg)| (pkg.as_str(), var))
+            .collect();
+
+        let mut formula = Formula::new(index.len());
+        // a package brings along what it imports
+        for (dep, dependents) in &graph.adj {
+            for dependent in dependents {
+                formula.add_clause([
+                    Lit::neg(index[dependent.as_str()]),
+                    Lit::pos(index[dep.as_str()]),
+                ]);
+            }
+        }
+        for (a, b) in &self.conflicts {
+            if let (Some(&a), Some(&b)) = (index.get(a.as_str()), index.get(b.as_str())) {
+                formula.add_clause([Lit::neg(a), Lit::neg(b)]);
+            }
+        }
+        for (pkg, &var) in &index {
+            if !self.optional.iter().any(|o| o == pkg) {
+                formula.add_clause([Lit::pos(var)]);
+            }
+        }
+
+        let soft: Vec<Lit> = self
+            .optional
+            .iter()
+            .filter_map(|pkg| index.get(pkg.as_str()))
+            .map(|&var| Lit::pos(var))
+            .collect();
+        let selected = formula
+            .maximize(&soft)
+            .ok_or_else(|| DependencyError::Unsatisfiable(self.explain(graph)))?;
+
+        let keep: HashSet<&str> = index
+            .iter()
+            .filter(|(_, &var)| selected[var])
+            .map(|(pkg, _)| *pkg)
+            .collect();
+        TopoSort.resolve(&graph.restrict(&keep))
     }
 }
@@ -0,0 +1,443 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::dependency::{
+    is_test_file, DependencyClosure, DependencyError, DependencyResolver, PackageDependency,
+    ResolutionStrategy,
+};
+use crate::error::ErrorKind;
+use crate::gnomod::{GnoMod, GnoModError, GNOMOD_NAME};
+use crate::report::{Report, Table};
+
+#[cfg(feature = "deploy")]
+mod amino;
+#[cfg(feature = "deploy")]
+pub mod broadcast;
+#[cfg(feature = "deploy")]
+pub mod key;
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum DeployError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Dependency error: {0}")]
+    Dependency(#[from] DependencyError),
+
+    #[error("gno.mod error: {0}")]
+    GnoMod(#[from] GnoModError),
+
+    #[error("Import cycle between {}", .0.join(", "))]
+    ImportCycle(Vec<String>),
+}
+
+impl DeployError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(_) => ErrorKind::Internal,
+            Self::Dependency(e) => e.kind(),
+            Self::GnoMod(e) => e.kind(),
+            Self::ImportCycle(_) => ErrorKind::Validation,
+        }
+    }
+}
+
+/// Transaction settings passed to every `gnokey maketx addpkg` call.
+///
+/// Defaults target a local `gnodev` devnet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeployOptions {
+    /// Name of the gnokey key signing the transactions
+    pub key: String,
+    pub chain_id: String,
+    /// Node RPC address
+    pub remote: String,
+    pub gas_fee: String,
+    pub gas_wanted: u64,
+}
+
+impl Default for DeployOptions {
+    fn default() -> Self {
+        Self {
+            key: "test1".to_string(),
+            chain_id: "dev".to_string(),
+            remote: "127.0.0.1:26657".to_string(),
+            gas_fee: "1000000ugnot".to_string(),
+            gas_wanted: 10_000_000,
+        }
+    }
+}
+
+impl DeployOptions {
+    pub fn with_key(mut self, key: &str) -> Self {
+        self.key = key.to_string();
+        self
+    }
+
+    pub fn with_chain_id(mut self, chain_id: &str) -> Self {
+        self.chain_id = chain_id.to_string();
+        self
+    }
+
+    pub fn with_remote(mut self, remote: &str) -> Self {
+        self.remote = remote.to_string();
+        self
+    }
+
+    pub fn with_gas_fee(mut self, gas_fee: &str) -> Self {
+        self.gas_fee = gas_fee.to_string();
+        self
+    }
+
+    pub fn with_gas_wanted(mut self, gas_wanted: u64) -> Self {
+        self.gas_wanted = gas_wanted;
+        self
+    }
+}
+
+/// A single `addpkg` transaction of a [DeployPlan]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeployStep {
+    pub pkg_path: String,
+    /// Name from the package clause of the package's sources
+    pub pkg_name: String,
+    pub pkg_dir: PathBuf,
+    /// Full `gnokey` command line, program name included
+    pub args: Vec<String>,
+}
+
+/// `gnokey maketx addpkg` invocations deploying a directory of packages,
+/// ordered so every package is deployed after the packages it imports.
+///
+/// Imports of packages outside the directory are assumed to be on chain
+/// already.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DeployPlan {
+    pub steps: Vec<DeployStep>,
+}
+
+impl DeployPlan {
+    /// Plans the deployment of every package found under `dir`.
+    ///
+    /// A directory holding `.gno` files is a package. Its path comes from
+    /// the `module` line of its gno.mod, or else from its location relative
+    /// to `dir` (as laid out by gget, e.g. `dir/gno.land/p/demo/avl`).
+    pub fn from_dir(dir: &Path, options: &DeployOptions) -> Result<Self, DeployError> {
+        let found = FoundPackages::scan(dir)?;
+        let waves = found.closure.deployment_waves();
+        check_acyclic(&found.closure, &waves)?;
+
+        Ok(found.plan(waves.into_iter().flatten(), options))
+    }
+
+    /// Like [DeployPlan::from_dir], ordering packages with `strategy`
+    /// instead, which may leave some out; see
+    /// [crate::dependency::SatResolver]
+    pub fn from_dir_with_strategy<S: ResolutionStrategy + 'static>(
+        dir: &Path,
+        options: &DeployOptions,
+        strategy: S,
+    ) -> Result<Self, DeployError> {
+        let found = FoundPackages::scan(dir)?;
+        let waves = found.closure.deployment_waves();
+        check_acyclic(&found.closure, &waves)?;
+
+        let order = DependencyResolver::new()?
+            .with_strategy(strategy)
+            .generate_deployment_order(&found.closure.packages)?;
+        Ok(found.plan(order, options))
+    }
+
+    /// Plans the deployment of one package whose path is already known
+    pub fn single(
+        pkg_path: &str,
+        pkg_dir: &Path,
+        options: &DeployOptions,
+    ) -> Result<Self, DeployError> {
+        let resolver = DependencyResolver::new()?;
+        let package = extract_package(&resolver, pkg_dir)?;
+        Ok(Self {
+            steps: vec![DeployStep {
+                pkg_path: pkg_path.to_string(),
+                pkg_name: package.name,
+                pkg_dir: pkg_dir.to_path_buf(),
+                args: addpkg_args(pkg_path, pkg_dir, options),
+            }],
+        })
+    }
+
+    /// Renders the plan as a POSIX shell script that stops at the first failure
+    pub fn to_script(&self) -> String {
+        let mut script = String::from("#!/bin/sh\nset -e\n");
+        script.push_str(&format!(
+            "# Deploys {} packages in dependency order\n",
+            self.steps.len()
+        ));
+        for step in &self.steps {
+            script.push('\n');
+            let args: Vec<String> = step.args.iter().map(|a| shell_quote(a)).collect();
+            script.push_str(&args.join(" "));
+            script.push('\n');
+        }
+        script
+    }
+}
+
+impl Report for DeployPlan {
+    fn table(&self) -> Table {
+        let mut table = Table::new("Deployment order", &["step", "pkg_path", "pkg_dir"]);
+        for (idx, step) in self.steps.iter().enumerate() {
+            table.push_row([
+                (idx + 1).to_string(),
+                step.pkg_path.clone(),
+                step.pkg_dir.display().to_string(),
+            ]);
+        }
+        table
+    }
+
+    fn human(&self) -> String {
+        self.to_script()
+    }
+}
+
+/// Finds the packages under `dir` like [DeployPlan::from_dir] and what
+/// they import
+pub fn scan_packages(dir: &Path) -> Result<DependencyClosure, DeployError> {
+    Ok(FoundPackages::scan(dir)?.closure)
+}
+
+/// Every package directory under `dir` with its package path, in path
+/// order of the directories. Several directories may declare the same
+/// package path through their gno.mod.
+pub fn package_dirs(dir: &Path) -> Result<Vec<(String, PathBuf)>, DeployError> {
+    let mut found = Vec::new();
+    find_package_dirs(dir, &mut found)?;
+    found
+        .into_iter()
+        .map(|pkg_dir| Ok((package_path(dir, &pkg_dir)?, pkg_dir)))
+        .collect()
+}
+
+/// The packages under a directory, see [DeployPlan::from_dir]
+struct FoundPackages {
+    closure: DependencyClosure,
+    dirs: HashMap<String, PathBuf>,
+    /// Package names, by package path
+    names: HashMap<String, String>,
+}
+
+impl FoundPackages {
+    fn scan(dir: &Path) -> Result<Self, DeployError> {
+        let resolver = DependencyResolver::new()?;
+
+        let mut closure = DependencyClosure::default();
+        let mut dirs = HashMap::new();
+        let mut names = HashMap::new();
+        for (pkg_path, pkg_dir) in package_dirs(dir)? {
+            let package = extract_package(&resolver, &pkg_dir)?;
+            names.insert(pkg_path.clone(), package.name.clone());
+            closure
+                .packages
+                .entry(pkg_path.clone())
+                .and_modify(|pkg: &mut PackageDependency| {
+                    pkg.imports.extend(package.imports.clone())
+                })
+                .or_insert(package);
+            dirs.insert(pkg_path, pkg_dir);
+        }
+
+        Ok(Self {
+            closure,
+            dirs,
+            names,
+        })
+    }
+
+    /// Deploys the packages in `order`
+    fn plan(
+        mut self,
+        order: impl IntoIterator<Item = String>,
+        options: &DeployOptions,
+    ) -> DeployPlan {
+        let steps = order
+            .into_iter()
+            .map(|pkg_path| {
+                let pkg_dir = self.dirs.remove(&pkg_path).unwrap_or_default();
+                DeployStep {
+                    args: addpkg_args(&pkg_path, &pkg_dir, options),
+                    pkg_name: self.names.remove(&pkg_path).unwrap_or_default(),
+                    pkg_path,
+                    pkg_dir,
+                }
+            })
+            .collect();
+
+        DeployPlan { steps }
+    }
+}
+
+fn find_package_dirs(dir: &Path, found: &mut Vec<PathBuf>) -> Result<(), DeployError> {
+    let mut has_sources = false;
+    let mut subdirs = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            subdirs.push(path);
+        } else if is_gno_file(&path) {
+            has_sources = true;
+        }
+    }
+
+    if has_sources {
+        found.push(dir.to_path_buf());
+    }
+    subdirs.sort();
+    for subdir in subdirs {
+        find_package_dirs(&subdir, found)?;
+    }
+    Ok(())
+}
+
+fn package_path(root: &Path, pkg_dir: &Path) -> Result<String, DeployError> {
+    let gnomod = pkg_dir.join(GNOMOD_NAME);
+    if gnomod.is_file() {
+        if let Some(module) = GnoMod::load(&gnomod)?.module {
+            return Ok(module);
+        }
+    }
+
+    let relative = pkg_dir.strip_prefix(root).unwrap_or(pkg_dir);
+    Ok(relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/"))
+}
+
+/// Merges the imports of every `.gno` file directly inside `pkg_dir`
+fn extract_package(
+    resolver: &DependencyResolver,
+    pkg_dir: &Path,
+) -> Result<PackageDependency, DeployError> {
+    let mut package = PackageDependency {
+        name: String::new(),
+        imports: Default::default(),
+        test_imports: Default::default(),
+        instability: 0.0,
+    };
+
+    for entry in fs::read_dir(pkg_dir)? {
+        let path = entry?.path();
+        if !path.is_file() || !is_gno_file(&path) {
+            continue;
+        }
+        let (name, imports) = resolver.extract_dependencies(&fs::read_to_string(&path)?)?;
+        // external tests may declare `package foo_test`
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default();
+        if package.name.is_empty() || !is_test_file(file_name) {
+            package.name = name;
+        }
+        package.imports.extend(imports);
+    }
+
+    Ok(package)
+}
+
+/// Fails if the closure can't be ordered, naming the packages on import cycles
+fn check_acyclic(closure: &DependencyClosure, waves: &[Vec<String>]) -> Result<(), DeployError> {
+    let cyclic = cyclic_packages(closure, waves);
+    if cyclic.is_empty() {
+        Ok(())
+    } else {
+        Err(DeployError::ImportCycle(cyclic))
+    }
+}
+
+/// The packages of the closure on import cycles, in deployment order
+pub fn import_cycles(closure: &DependencyClosure) -> Vec<String> {
+    cyclic_packages(closure, &closure.deployment_waves())
+}
+
+/// [DependencyClosure::deployment_waves] puts cyclic packages (and whatever
+/// imports them) in a final wave, so only that wave needs checking.
+fn cyclic_packages(closure: &DependencyClosure, waves: &[Vec<String>]) -> Vec<String> {
+    let Some(last) = waves.last() else {
+        return Vec::new();
+    };
+    let in_wave: HashSet<&str> = last.iter().map(|p| p.as_str()).collect();
+    let imports_in_wave = |pkg: &str| -> Vec<&str> {
+        closure.packages[pkg]
+            .imports
+            .iter()
+            .map(|i| i.as_str())
+            .filter(|i| *i != pkg && in_wave.contains(i))
+            .collect()
+    };
+
+    let on_cycle = |start: &str| {
+        let mut seen = HashSet::new();
+        let mut stack = imports_in_wave(start);
+        while let Some(pkg) = stack.pop() {
+            if pkg == start {
+                return true;
+            }
+            if seen.insert(pkg) {
+                stack.extend(imports_in_wave(pkg));
+            }
+        }
+        false
+    };
+
+    last.iter().filter(|p| on_cycle(p)).cloned().collect()
+}
+
+fn addpkg_args(pkg_path: &str, pkg_dir: &Path, options: &DeployOptions) -> Vec<String> {
+    [
+        "gnokey",
+        "maketx",
+        "addpkg",
+        "-pkgpath",
+        pkg_path,
+        "-pkgdir",
+        &pkg_dir.display().to_string(),
+        "-gas-fee",
+        &options.gas_fee,
+        "-gas-wanted",
+        &options.gas_wanted.to_string(),
+        "-broadcast",
+        "-chainid",
+        &options.chain_id,
+        "-remote",
+        &options.remote,
+        &options.key,
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()
+}
+
+fn is_gno_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "gno")
+}
+
+/// Quotes a word for the shell unless it is obviously safe
+fn shell_quote(word: &str) -> String {
+    let safe = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c));
+    if safe {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
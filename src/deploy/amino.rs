@@ -0,0 +1,124 @@
+//! Just enough of tm2's amino encoding to build an addpkg transaction.
+//!
+//! Amino's binary format is protobuf3-compatible: fields in declaration
+//! order, zero values omitted, interfaces wrapped in `google.protobuf.Any`.
+//! Its JSON format, once keys are sorted, is what transactions sign.
+
+use serde_json::{Map, Value};
+
+/// Protobuf wire type of varint fields
+const VARINT: u64 = 0;
+/// Protobuf wire type of strings, bytes and embedded messages
+const LENGTH_DELIMITED: u64 = 2;
+
+/// A binary message built field by field
+#[derive(Debug, Default)]
+pub struct Message(Vec<u8>);
+
+impl Message {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn uvarint(mut self, field: u64, value: u64) -> Self {
+        if value != 0 {
+            self.key(field, VARINT);
+            write_uvarint(&mut self.0, value);
+        }
+        self
+    }
+
+    pub fn bytes(mut self, field: u64, value: &[u8]) -> Self {
+        if !value.is_empty() {
+            self.key(field, LENGTH_DELIMITED);
+            write_uvarint(&mut self.0, value.len() as u64);
+            self.0.extend_from_slice(value);
+        }
+        self
+    }
+
+    pub fn string(self, field: u64, value: &str) -> Self {
+        self.bytes(field, value.as_bytes())
+    }
+
+    /// Writes an embedded message, even an empty one (as repeated fields need)
+    pub fn message(mut self, field: u64, value: Message) -> Self {
+        self.key(field, LENGTH_DELIMITED);
+        write_uvarint(&mut self.0, value.0.len() as u64);
+        self.0.extend(value.0);
+        self
+    }
+
+    /// Writes an interface value as `google.protobuf.Any`
+    pub fn any(self, field: u64, type_url: &str, value: Message) -> Self {
+        let any = Message::new().string(1, type_url).bytes(2, &value.0);
+        self.message(field, any)
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+
+    fn key(&mut self, field: u64, wire_type: u64) {
+        write_uvarint(&mut self.0, (field << 3) | wire_type);
+    }
+}
+
+fn write_uvarint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+/// Serializes JSON the way tm2 computes sign bytes: keys sorted, no
+/// whitespace, and HTML-sensitive characters escaped like Go's encoder
+pub fn sorted_json(value: &Value) -> String {
+    let json = serde_json::to_string(&sort_keys(value)).expect("JSON values always serialize");
+    json.replace('<', "\\u003c")
+        .replace('>', "\\u003e")
+        .replace('&', "\\u0026")
+        .replace('\u{2028}', "\\u2028")
+        .replace('\u{2029}', "\\u2029")
+}
+
+fn sort_keys(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            let sorted: Map<String, Value> = keys
+                .into_iter()
+                .map(|k| (k.clone(), sort_keys(&map[k])))
+                .collect();
+            Value::Object(sorted)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(sort_keys).collect()),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protobuf_layout() {
+        let bytes = Message::new()
+            .uvarint(1, 300)
+            .string(2, "hi")
+            .uvarint(3, 0)
+            .into_bytes();
+        assert_eq!(bytes, vec![0x08, 0xac, 0x02, 0x12, 0x02, b'h', b'i']);
+    }
+
+    #[test]
+    fn test_sorted_json_escapes_like_go() {
+        let value = serde_json::json!({ "b": "a < b && c", "a": { "z": 1, "y": [] } });
+        assert_eq!(
+            sorted_json(&value),
+            r#"{"a":{"y":[],"z":1},"b":"a \u003c b \u0026\u0026 c"}"#
+        );
+    }
+}
@@ -0,0 +1,327 @@
+use std::fs;
+
+use base64::{engine::general_purpose, Engine as _};
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::{json, Value};
+use thiserror::Error;
+
+use super::amino::{sorted_json, Message};
+use super::key::DeployKey;
+use super::{DeployOptions, DeployPlan, DeployStep};
+use crate::error::ErrorKind;
+
+/// Amino type URL of `vm.MsgAddPackage`
+const MSG_ADD_PACKAGE: &str = "/vm.m_addpkg";
+/// Amino type URL of secp256k1 public keys
+const PUBKEY_SECP256K1: &str = "/tm.PubKeySecp256k1";
+/// Non-source files uploaded along with a package, like gnokey does
+const EXTRA_PACKAGE_FILES: &[&str] = &["gno.mod", "gnomod.toml", "LICENSE", "README.md"];
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum BroadcastError {
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON serialization/deserialization error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Base64 decoding error: {0}")]
+    Base64(#[from] base64::DecodeError),
+
+    #[error("RPC error: {0}")]
+    Rpc(String),
+
+    #[error("Account {0} doesn't exist on chain; fund it first")]
+    UnknownAccount(String),
+
+    #[error("Deploying {pkg_path} failed: {log}")]
+    Rejected { pkg_path: String, log: String },
+}
+
+impl BroadcastError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Http(_) | Self::Rpc(_) => ErrorKind::Network,
+            // the node answered, but refused the transaction
+            Self::Rejected { .. } => ErrorKind::Network,
+            Self::Io(_) => ErrorKind::Internal,
+            // a malformed response
+            Self::Json(_) | Self::Base64(_) => ErrorKind::Integrity,
+            Self::UnknownAccount(_) => ErrorKind::Config,
+        }
+    }
+}
+
+/// A file uploaded by an addpkg transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemFile {
+    pub name: String,
+    pub body: String,
+}
+
+/// Package contents as carried by an addpkg transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemPackage {
+    pub name: String,
+    pub path: String,
+    /// Sorted by name
+    pub files: Vec<MemFile>,
+}
+
+impl MemPackage {
+    /// Reads the `.gno` sources and package metadata files of a plan step
+    pub fn from_step(step: &DeployStep) -> Result<Self, BroadcastError> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&step.pkg_dir)? {
+            let path = entry?.path();
+            let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            if path.is_file() && (name.ends_with(".gno") || EXTRA_PACKAGE_FILES.contains(&name)) {
+                files.push(MemFile {
+                    name: name.to_string(),
+                    body: fs::read_to_string(&path)?,
+                });
+            }
+        }
+        files.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(Self {
+            name: step.pkg_name.clone(),
+            path: step.pkg_path.clone(),
+            files,
+        })
+    }
+
+    fn to_amino(&self) -> Message {
+        self.files.iter().fold(
+            Message::new().string(1, &self.name).string(2, &self.path),
+            |msg, file| {
+                msg.message(
+                    3,
+                    Message::new().string(1, &file.name).string(2, &file.body),
+                )
+            },
+        )
+    }
+
+    fn to_json(&self) -> Value {
+        let files: Vec<Value> = self
+            .files
+            .iter()
+            .map(|f| json!({ "name": f.name, "body": f.body }))
+            .collect();
+        json!({ "name": self.name, "path": self.path, "files": files })
+    }
+}
+
+/// What happened to one package of a deployed plan
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum DeployStatus {
+    Deployed {
+        height: u64,
+        /// Transaction hash as reported by the node
+        hash: String,
+    },
+    /// The package path was already taken, so it was left alone
+    AlreadyOnChain,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeployedPackage {
+    pub pkg_path: String,
+    #[serde(flatten)]
+    pub status: DeployStatus,
+}
+
+/// Signs addpkg transactions and broadcasts them through a node's RPC
+/// endpoint (`options.remote`, e.g. `http://localhost:26657`).
+///
+/// The encoding follows the tm2 transaction format served by gnodev.
+pub struct Deployer {
+    http_client: Client,
+    key: DeployKey,
+    options: DeployOptions,
+}
+
+impl Deployer {
+    pub fn new(key: DeployKey, options: DeployOptions) -> Self {
+        Self {
+            http_client: Client::new(),
+            key,
+            options,
+        }
+    }
+
+    /// Deploys every package of a plan in order, one transaction each.
+    ///
+    /// Packages whose path already exists on chain are skipped. The first
+    /// rejected transaction stops the deployment.
+    pub async fn deploy(&self, plan: &DeployPlan) -> Result<Vec<DeployedPackage>, BroadcastError> {
+        let (account_number, mut sequence) = self.account().await?;
+        let mut deployed = Vec::with_capacity(plan.steps.len());
+
+        for step in &plan.steps {
+            if self.package_exists(&step.pkg_path).await? {
+                deployed.push(DeployedPackage {
+                    pkg_path: step.pkg_path.clone(),
+                    status: DeployStatus::AlreadyOnChain,
+                });
+                continue;
+            }
+
+            let package = MemPackage::from_step(step)?;
+            let tx = self.signed_tx(&package, account_number, sequence);
+            let status = self.broadcast(&step.pkg_path, &tx).await?;
+            sequence += 1;
+
+            deployed.push(DeployedPackage {
+                pkg_path: step.pkg_path.clone(),
+                status,
+            });
+        }
+
+        Ok(deployed)
+    }
+
+    /// Returns the account number and next sequence of the deploying key
+    pub async fn account(&self) -> Result<(u64, u64), BroadcastError> {
+        let address = self.key.address();
+        let base = self
+            .abci_query(&format!("auth/accounts/{}", address), "")
+            .await?;
+        let data = base.get("Data").and_then(Value::as_str).unwrap_or_default();
+        let account: Value = match general_purpose::STANDARD.decode(data)? {
+            raw if raw.is_empty() => Value::Null,
+            raw => serde_json::from_slice(&raw)?,
+        };
+
+        let Some(fields) = account.get("BaseAccount") else {
+            return Err(BroadcastError::UnknownAccount(address));
+        };
+        Ok((
+            json_u64(&fields["account_number"]),
+            json_u64(&fields["sequence"]),
+        ))
+    }
+
+    /// Returns true if a package is already deployed at `pkg_path`
+    pub async fn package_exists(&self, pkg_path: &str) -> Result<bool, BroadcastError> {
+        let data = general_purpose::STANDARD.encode(pkg_path.as_bytes());
+        let base = self.abci_query("vm/qfile", &data).await?;
+        Ok(base.get("Error").is_none_or(Value::is_null))
+    }
+
+    /// Returns the bytes a transaction deploying `package` signs
+    pub fn sign_bytes(&self, package: &MemPackage, account_number: u64, sequence: u64) -> String {
+        let doc = json!({
+            "chain_id": self.options.chain_id,
+            "account_number": account_number.to_string(),
+            "sequence": sequence.to_string(),
+            "fee": {
+                "gas_wanted": self.options.gas_wanted.to_string(),
+                "gas_fee": self.options.gas_fee,
+            },
+            "msgs": [{
+                "@type": MSG_ADD_PACKAGE,
+                "creator": self.key.address(),
+                "package": package.to_json(),
+                "send": "",
+                "max_deposit": "",
+            }],
+            "memo": "",
+        });
+        sorted_json(&doc)
+    }
+
+    /// Builds the amino-encoded, signed transaction deploying `package`
+    pub fn signed_tx(&self, package: &MemPackage, account_number: u64, sequence: u64) -> Vec<u8> {
+        let signature = self.key.sign(
+            self.sign_bytes(package, account_number, sequence)
+                .as_bytes(),
+        );
+
+        let msg = Message::new()
+            .bytes(1, &self.key.address_bytes())
+            .message(2, package.to_amino());
+        let fee = Message::new()
+            .uvarint(1, self.options.gas_wanted)
+            .string(2, &self.options.gas_fee);
+        let signature = Message::new()
+            .any(
+                1,
+                PUBKEY_SECP256K1,
+                Message::new().bytes(1, &self.key.public_key()),
+            )
+            .bytes(2, &signature);
+
+        Message::new()
+            .any(1, MSG_ADD_PACKAGE, msg)
+            .message(2, fee)
+            .message(3, signature)
+            .into_bytes()
+    }
+
+    async fn broadcast(&self, pkg_path: &str, tx: &[u8]) -> Result<DeployStatus, BroadcastError> {
+        let result = self
+            .call(
+                "broadcast_tx_commit",
+                json!({ "tx": general_purpose::STANDARD.encode(tx) }),
+            )
+            .await?;
+
+        for phase in ["check_tx", "deliver_tx"] {
+            let base = &result[phase]["ResponseBase"];
+            if !base["Error"].is_null() {
+                return Err(BroadcastError::Rejected {
+                    pkg_path: pkg_path.to_string(),
+                    log: base["Log"].as_str().unwrap_or_default().trim().to_string(),
+                });
+            }
+        }
+
+        Ok(DeployStatus::Deployed {
+            height: json_u64(&result["height"]),
+            hash: result["hash"].as_str().unwrap_or_default().to_string(),
+        })
+    }
+
+    /// Runs an ABCI query, returning its `ResponseBase`
+    async fn abci_query(&self, path: &str, data: &str) -> Result<Value, BroadcastError> {
+        let mut result = self
+            .call("abci_query", json!({ "path": path, "data": data }))
+            .await?;
+        Ok(result["response"]["ResponseBase"].take())
+    }
+
+    async fn call(&self, method: &str, params: Value) -> Result<Value, BroadcastError> {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let mut response: Value = self
+            .http_client
+            .post(&self.options.remote)
+            .json(&request)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        if let Some(error) = response.get("error").filter(|e| !e.is_null()) {
+            return Err(BroadcastError::Rpc(error.to_string()));
+        }
+        Ok(response["result"].take())
+    }
+}
+
+/// Reads an integer amino encodes as a JSON string
+fn json_u64(value: &Value) -> u64 {
+    match value {
+        Value::String(s) => s.parse().unwrap_or_default(),
+        other => other.as_u64().unwrap_or_default(),
+    }
+}
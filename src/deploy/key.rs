@@ -0,0 +1,170 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use k256::ecdsa::signature::Signer;
+use k256::ecdsa::{Signature, SigningKey};
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::error::ErrorKind;
+
+/// Bech32 prefix of gno.land addresses
+pub const ADDRESS_PREFIX: &str = "g";
+
+/// Environment variable holding a hex-encoded private key
+pub const KEY_ENV: &str = "GGET_DEPLOY_KEY";
+
+const BECH32_CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum KeyError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("private key must be 64 hex characters")]
+    InvalidHex,
+
+    #[error("not a valid secp256k1 private key")]
+    InvalidKey,
+
+    #[error("no deploy key: pass a keyfile or set {}", KEY_ENV)]
+    Missing,
+}
+
+impl KeyError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(_) => ErrorKind::Internal,
+            Self::InvalidHex | Self::InvalidKey | Self::Missing => ErrorKind::Config,
+        }
+    }
+}
+
+/// secp256k1 key signing deploy transactions, given as a raw 32-byte
+/// private key written in hex
+#[derive(Clone)]
+pub struct DeployKey {
+    signing_key: SigningKey,
+}
+
+impl DeployKey {
+    /// Parses a hex-encoded private key, ignoring surrounding whitespace
+    pub fn from_hex(hex: &str) -> Result<Self, KeyError> {
+        let hex = hex.trim();
+        let hex = hex.strip_prefix("0x").unwrap_or(hex);
+        if hex.len() != 64 {
+            return Err(KeyError::InvalidHex);
+        }
+
+        let mut bytes = [0u8; 32];
+        for (byte, pair) in bytes.iter_mut().zip(hex.as_bytes().chunks(2)) {
+            let pair = std::str::from_utf8(pair).map_err(|_| KeyError::InvalidHex)?;
+            *byte = u8::from_str_radix(pair, 16).map_err(|_| KeyError::InvalidHex)?;
+        }
+
+        let signing_key = SigningKey::from_slice(&bytes).map_err(|_| KeyError::InvalidKey)?;
+        Ok(Self { signing_key })
+    }
+
+    /// Reads a hex-encoded private key from a file
+    pub fn load(path: &Path) -> Result<Self, KeyError> {
+        Self::from_hex(&fs::read_to_string(path)?)
+    }
+
+    /// Reads a hex-encoded private key from [KEY_ENV]
+    pub fn from_env() -> Result<Self, KeyError> {
+        match std::env::var(KEY_ENV) {
+            Ok(hex) => Self::from_hex(&hex),
+            Err(_) => Err(KeyError::Missing),
+        }
+    }
+
+    /// Compressed SEC1 public key
+    pub fn public_key(&self) -> [u8; 33] {
+        let point = self.signing_key.verifying_key().to_encoded_point(true);
+        let mut key = [0u8; 33];
+        key.copy_from_slice(point.as_bytes());
+        key
+    }
+
+    /// Raw account address: `RIPEMD160(SHA256(public key))`
+    pub fn address_bytes(&self) -> [u8; 20] {
+        let sha = Sha256::digest(self.public_key());
+        Ripemd160::digest(sha).into()
+    }
+
+    /// Bech32 account address (e.g. `g1...`)
+    pub fn address(&self) -> String {
+        bech32_encode(ADDRESS_PREFIX, &self.address_bytes())
+    }
+
+    /// Signs `SHA256(message)`, returning the 64-byte `r || s` signature
+    pub fn sign(&self, message: &[u8]) -> [u8; 64] {
+        let signature: Signature = self.signing_key.sign(message);
+        // tm2 rejects high-S signatures
+        let signature = signature.normalize_s().unwrap_or(signature);
+        signature.to_bytes().into()
+    }
+}
+
+impl fmt::Debug for DeployKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeployKey")
+            .field("address", &self.address())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Encodes bytes as a BIP-173 bech32 string
+fn bech32_encode(hrp: &str, data: &[u8]) -> String {
+    // regroup 8-bit bytes into 5-bit words
+    let mut words = Vec::with_capacity(data.len() * 8 / 5 + 1);
+    let (mut acc, mut bits) = (0u32, 0u32);
+    for byte in data {
+        // only the unconsumed low bits matter
+        acc = ((acc << 8) | u32::from(*byte)) & 0xfff;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            words.push(((acc >> bits) & 31) as u8);
+        }
+    }
+    if bits > 0 {
+        words.push(((acc << (5 - bits)) & 31) as u8);
+    }
+
+    let mut values: Vec<u8> = hrp.bytes().map(|b| b >> 5).collect();
+    values.push(0);
+    values.extend(hrp.bytes().map(|b| b & 31));
+    values.extend(&words);
+    values.extend([0; 6]);
+    let checksum = bech32_polymod(&values) ^ 1;
+
+    let mut encoded = format!("{}1", hrp);
+    for word in &words {
+        encoded.push(BECH32_CHARSET[*word as usize] as char);
+    }
+    for i in 0..6 {
+        let word = (checksum >> (5 * (5 - i))) & 31;
+        encoded.push(BECH32_CHARSET[word as usize] as char);
+    }
+    encoded
+}
+
+fn bech32_polymod(values: &[u8]) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    let mut chk = 1u32;
+    for value in values {
+        let top = chk >> 25;
+        chk = ((chk & 0x1ffffff) << 5) ^ u32::from(*value);
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= generator;
+            }
+        }
+    }
+    chk
+}
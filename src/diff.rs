@@ -0,0 +1,342 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::error::ErrorKind;
+use crate::fetch::{PackageManager, PackageManagerError};
+use crate::lock::{LockError, Lockfile, LOCKFILE_NAME};
+use crate::report::{Report, Table};
+
+/// Lines of unchanged context around each change in a unified diff
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum DiffError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    PackageManager(#[from] PackageManagerError),
+
+    #[error("Lockfile error: {0}")]
+    Lock(#[from] LockError),
+
+    #[error("{0} isn't locked at a height in {LOCKFILE_NAME}")]
+    NotLocked(String),
+}
+
+impl DiffError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(_) => ErrorKind::Internal,
+            Self::PackageManager(e) => e.kind(),
+            Self::Lock(_) | Self::NotLocked(_) => ErrorKind::Config,
+        }
+    }
+}
+
+/// Version of a package the local copy is compared with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Against {
+    /// What the chain serves now
+    #[default]
+    Latest,
+    /// What the chain served at a block height
+    Height(u64),
+    /// What the chain served at the height locked in `gget.lock`
+    Lockfile,
+}
+
+impl FromStr for Against {
+    type Err = String;
+
+    /// Parses `latest`, `lockfile` or a block height
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "latest" => Ok(Self::Latest),
+            "lockfile" => Ok(Self::Lockfile),
+            height => height.parse().map(Self::Height).map_err(|_| {
+                format!(
+                    "expected latest, lockfile or a block height, got `{}`",
+                    height
+                )
+            }),
+        }
+    }
+}
+
+/// A file whose local content differs from the chain's
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileDiff {
+    pub name: String,
+    /// Unified diff from the local file to the chain's
+    pub unified: String,
+}
+
+/// Differences between the local copy of a package and the chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PackageDiff {
+    pub package: String,
+    pub dir: PathBuf,
+    /// Height compared with, `None` for the latest
+    pub height: Option<u64>,
+    /// Files on chain that are missing locally
+    pub added: Vec<String>,
+    /// Local files that aren't on chain
+    pub removed: Vec<String>,
+    pub modified: Vec<FileDiff>,
+}
+
+impl PackageDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+impl Report for PackageDiff {
+    fn table(&self) -> Table {
+        let mut table = Table::new(&format!("Changes in {}", self.package), &["file", "status"]);
+        for name in &self.added {
+            table.push_row([name.as_str(), "added"]);
+        }
+        for name in &self.removed {
+            table.push_row([name.as_str(), "removed"]);
+        }
+        for file in &self.modified {
+            table.push_row([file.name.as_str(), "modified"]);
+        }
+        table
+    }
+
+    fn human(&self) -> String {
+        let mut out = String::new();
+        for file in &self.modified {
+            out.push_str(&file.unified);
+        }
+        for name in &self.added {
+            let _ = writeln!(out, "Only on chain: {}", name);
+        }
+        for name in &self.removed {
+            let _ = writeln!(out, "Only in {}: {}", self.dir.display(), name);
+        }
+        if self.is_empty() {
+            let _ = writeln!(out, "{} matches the chain", self.dir.display());
+        }
+        out
+    }
+}
+
+/// Compares the copy of `pkg_path` in `dir` with the chain.
+///
+/// `output` is where `gget.lock` lives, for [Against::Lockfile] and to
+/// leave subpackages nested in `dir` out of the comparison.
+pub async fn diff_package(
+    pm: &PackageManager,
+    pkg_path: &str,
+    dir: &Path,
+    output: &Path,
+    against: Against,
+) -> Result<PackageDiff, DiffError> {
+    let lock = Lockfile::load_if_exists(&output.join(LOCKFILE_NAME))?;
+    let height = match against {
+        Against::Latest => None,
+        Against::Height(height) => Some(height),
+        Against::Lockfile => Some(
+            lock.as_ref()
+                .and_then(|lock| lock.get(pkg_path))
+                .and_then(|locked| locked.height)
+                .ok_or_else(|| DiffError::NotLocked(pkg_path.to_string()))?,
+        ),
+    };
+    let nested = lock
+        .map(|lock| lock.nested_dirs(pkg_path, output))
+        .unwrap_or_default();
+
+    let pm = match height {
+        Some(height) => pm.clone().with_pinned_height(pkg_path, height),
+        None => pm.clone(),
+    };
+    let remote = pm.fetch_remote_files(pkg_path).await?;
+    let mut local = BTreeMap::new();
+    if dir.is_dir() {
+        collect_files(dir, dir, &nested, &mut local)?;
+    }
+
+    let mut diff = PackageDiff {
+        package: pkg_path.to_string(),
+        dir: dir.to_path_buf(),
+        height,
+        added: Vec::new(),
+        removed: Vec::new(),
+        modified: Vec::new(),
+    };
+    for (name, content) in &remote {
+        match local.get(name) {
+            None => diff.added.push(name.clone()),
+            Some(ours) if ours != content => diff.modified.push(FileDiff {
+                name: name.clone(),
+                unified: unified_diff(
+                    &format!("a/{}", name),
+                    &format!("b/{}", name),
+                    ours,
+                    content,
+                ),
+            }),
+            Some(_) => {}
+        }
+    }
+    diff.removed = local
+        .into_keys()
+        .filter(|name| !remote.contains_key(name))
+        .collect();
+    Ok(diff)
+}
+
+/// Reads every file under `dir` but the lockfile and the `excluded`
+/// subdirectories, by path relative to `root`
+fn collect_files(
+    root: &Path,
+    dir: &Path,
+    excluded: &[PathBuf],
+    files: &mut BTreeMap<String, String>,
+) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if !excluded.contains(&path) {
+                collect_files(root, &path, excluded, files)?;
+            }
+        } else if path.file_name().and_then(|s| s.to_str()) != Some(LOCKFILE_NAME) {
+            let relative = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            let content = String::from_utf8_lossy(&fs::read(&path)?).into_owned();
+            files.insert(relative, content);
+        }
+    }
+    Ok(())
+}
+
+/// One line of an edit script
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit<'a> {
+    Keep(&'a str),
+    Remove(&'a str),
+    Add(&'a str),
+}
+
+/// Line edits turning `old` into `new`, from their longest common
+/// subsequence
+fn edit_script<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Edit<'a>> {
+    // lengths of the common subsequences of every pair of suffixes
+    let mut lcs = vec![vec![0u32; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut edits = Vec::with_capacity(old.len().max(new.len()));
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            edits.push(Edit::Keep(old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            // removals first, like diff(1)
+            edits.push(Edit::Remove(old[i]));
+            i += 1;
+        } else {
+            edits.push(Edit::Add(new[j]));
+            j += 1;
+        }
+    }
+    edits
+}
+
+/// Renders the changes from `old` to `new` as a unified diff with
+/// [CONTEXT_LINES] of context, empty if they are equal
+pub fn unified_diff(old_name: &str, new_name: &str, old: &str, new: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let edits = edit_script(&old_lines, &new_lines);
+
+    let changed: Vec<usize> = edits
+        .iter()
+        .enumerate()
+        .filter(|(_, edit)| !matches!(edit, Edit::Keep(_)))
+        .map(|(idx, _)| idx)
+        .collect();
+    if changed.is_empty() {
+        return String::new();
+    }
+
+    // group changes whose context overlaps into hunks of edit indices
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for idx in changed {
+        let start = idx.saturating_sub(CONTEXT_LINES);
+        let end = (idx + CONTEXT_LINES + 1).min(edits.len());
+        match hunks.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", old_name, new_name);
+    for (start, end) in hunks {
+        // line numbers where the hunk starts, counting the edits before it
+        let old_start = edits[..start]
+            .iter()
+            .filter(|e| !matches!(e, Edit::Add(_)))
+            .count();
+        let new_start = edits[..start]
+            .iter()
+            .filter(|e| !matches!(e, Edit::Remove(_)))
+            .count();
+        let hunk = &edits[start..end];
+        let old_len = hunk.iter().filter(|e| !matches!(e, Edit::Add(_))).count();
+        let new_len = hunk
+            .iter()
+            .filter(|e| !matches!(e, Edit::Remove(_)))
+            .count();
+
+        let _ = writeln!(
+            out,
+            "@@ -{} +{} @@",
+            hunk_range(old_start, old_len),
+            hunk_range(new_start, new_len)
+        );
+        for edit in hunk {
+            let _ = match edit {
+                Edit::Keep(line) => writeln!(out, " {}", line),
+                Edit::Remove(line) => writeln!(out, "-{}", line),
+                Edit::Add(line) => writeln!(out, "+{}", line),
+            };
+        }
+    }
+    out
+}
+
+/// `start,len` of a hunk, 1-based; an empty range points at the line
+/// before it
+fn hunk_range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, len),
+    }
+}
@@ -0,0 +1,284 @@
+use std::fmt;
+
+use reqwest::Client;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::error::ErrorKind;
+use crate::query::{next_request_id, AbciInfoResponse, StatusResponse, StatusResult};
+use crate::report::{Report, Table};
+
+/// Default number of blocks an endpoint may trail the best one before it's stale
+pub const DEFAULT_MAX_HEIGHT_LAG: u64 = 10;
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum EndpointError {
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Invalid block height `{0}`")]
+    InvalidHeight(String),
+
+    #[error("The node didn't report its chain id")]
+    MissingChainId,
+}
+
+impl EndpointError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Http(_) | Self::MissingChainId => ErrorKind::Network,
+            Self::InvalidHeight(_) => ErrorKind::Config,
+        }
+    }
+}
+
+/// Latest block height reported by one endpoint, relative to the others
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EndpointStatus {
+    pub url: String,
+    /// `None` if the endpoint couldn't be queried
+    pub height: Option<u64>,
+    /// Blocks behind the highest endpoint
+    pub lag: u64,
+    /// Lags by more than the allowed threshold, so it may serve outdated packages
+    pub stale: bool,
+    pub error: Option<String>,
+}
+
+impl EndpointStatus {
+    /// Reachable and not stale
+    pub fn is_healthy(&self) -> bool {
+        self.height.is_some() && !self.stale
+    }
+}
+
+impl fmt::Display for EndpointStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.height, &self.error) {
+            (Some(height), _) => {
+                write!(f, "{}: height {}", self.url, height)?;
+                if self.lag > 0 {
+                    write!(f, " ({} behind)", self.lag)?;
+                }
+                if self.stale {
+                    write!(f, " [stale]")?;
+                }
+                Ok(())
+            }
+            (None, Some(error)) => write!(f, "{}: unreachable ({})", self.url, error),
+            (None, None) => write!(f, "{}: unreachable", self.url),
+        }
+    }
+}
+
+/// Calls an RPC method without parameters
+async fn call<T: DeserializeOwned>(
+    client: &Client,
+    url: &str,
+    method: &str,
+) -> Result<T, EndpointError> {
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": next_request_id(),
+        "method": method,
+        "params": {},
+    });
+    Ok(client.post(url).json(&request).send().await?.json().await?)
+}
+
+/// Queries an endpoint's `status` RPC method
+async fn query_status(client: &Client, url: &str) -> Result<StatusResult, EndpointError> {
+    let response: StatusResponse = call(client, url, "status").await?;
+    Ok(response.result)
+}
+
+/// Queries the latest block height of an endpoint via the `status` RPC method
+pub async fn query_height(client: &Client, url: &str) -> Result<u64, EndpointError> {
+    let height = query_status(client, url)
+        .await?
+        .sync_info
+        .latest_block_height;
+    height
+        .parse()
+        .map_err(|_| EndpointError::InvalidHeight(height))
+}
+
+/// Queries the id of the chain an endpoint serves via the `status` RPC
+/// method
+pub async fn query_chain_id(client: &Client, url: &str) -> Result<String, EndpointError> {
+    let node_info = query_status(client, url).await?.node_info;
+    node_info
+        .ok_or(EndpointError::MissingChainId)
+        .map(|info| info.network)
+}
+
+/// Compares the latest block height across endpoints.
+///
+/// Endpoints trailing the highest one by more than `max_lag` blocks are
+/// marked stale. Results keep the order of `urls`.
+pub async fn check_endpoints(
+    client: &Client,
+    urls: &[String],
+    max_lag: u64,
+) -> Vec<EndpointStatus> {
+    let heights = futures::future::join_all(urls.iter().map(|url| query_height(client, url))).await;
+    let best = heights
+        .iter()
+        .filter_map(|h| h.as_ref().ok())
+        .max()
+        .copied()
+        .unwrap_or(0);
+
+    urls.iter()
+        .zip(heights)
+        .map(|(url, height)| match height {
+            Ok(height) => EndpointStatus {
+                url: url.clone(),
+                height: Some(height),
+                lag: best - height,
+                stale: best - height > max_lag,
+                error: None,
+            },
+            Err(e) => EndpointStatus {
+                url: url.clone(),
+                height: None,
+                lag: 0,
+                stale: false,
+                error: Some(e.to_string()),
+            },
+        })
+        .collect()
+}
+
+/// Picks the first healthy endpoint in configured order, demoting stale and
+/// unreachable ones
+pub fn select_endpoint(statuses: &[EndpointStatus]) -> Option<&EndpointStatus> {
+    statuses.iter().find(|s| s.is_healthy())
+}
+
+impl Report for [EndpointStatus] {
+    fn table(&self) -> Table {
+        let mut table = Table::new("RPC endpoints", &["url", "height", "lag", "stale", "error"]);
+        for status in self {
+            table.push_row([
+                status.url.clone(),
+                status.height.map(|h| h.to_string()).unwrap_or_default(),
+                status.lag.to_string(),
+                status.stale.to_string(),
+                status.error.clone().unwrap_or_default(),
+            ]);
+        }
+        table
+    }
+
+    fn human(&self) -> String {
+        let mut out = String::from("RPC endpoints:\n");
+        for status in self {
+            out.push_str(&format!("  {}\n", status));
+        }
+        out
+    }
+}
+
+/// What a node reports about itself, for `gget status`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NodeStatus {
+    pub url: String,
+    pub chain_id: Option<String>,
+    pub moniker: Option<String>,
+    /// Version of the node software
+    pub version: Option<String>,
+    /// Version of the application, from `abci_info`
+    pub app_version: Option<String>,
+    pub latest_block_height: u64,
+    pub latest_block_time: Option<String>,
+    /// Still syncing, so it may not serve the latest packages yet
+    pub catching_up: bool,
+    /// Why `abci_info` couldn't be queried; the rest comes from `status`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub abci_error: Option<String>,
+}
+
+/// Queries the `status` and `abci_info` RPC methods of an endpoint.
+///
+/// Only `status` has to answer; what `abci_info` would add is left out if
+/// it fails.
+pub async fn query_node_status(client: &Client, url: &str) -> Result<NodeStatus, EndpointError> {
+    let status = query_status(client, url).await?;
+    let height = status.sync_info.latest_block_height;
+    let node_info = status.node_info;
+    let non_empty = |s: String| (!s.is_empty()).then_some(s);
+
+    let (app_version, abci_error) = match call::<AbciInfoResponse>(client, url, "abci_info").await {
+        Ok(info) => (non_empty(info.result.response.app_version), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    Ok(NodeStatus {
+        url: url.to_string(),
+        latest_block_height: height
+            .parse()
+            .map_err(|_| EndpointError::InvalidHeight(height))?,
+        latest_block_time: non_empty(status.sync_info.latest_block_time),
+        catching_up: status.sync_info.catching_up,
+        chain_id: node_info.as_ref().map(|info| info.network.clone()),
+        moniker: node_info
+            .as_ref()
+            .and_then(|info| non_empty(info.moniker.clone())),
+        version: node_info.and_then(|info| non_empty(info.version)),
+        app_version,
+        abci_error,
+    })
+}
+
+impl Report for NodeStatus {
+    fn table(&self) -> Table {
+        let mut table = Table::new("Node status", &["field", "value"]);
+        let unknown = |value: &Option<String>| value.clone().unwrap_or_default();
+        table.push_row(["url".to_string(), self.url.clone()]);
+        table.push_row(["chain_id".to_string(), unknown(&self.chain_id)]);
+        table.push_row(["moniker".to_string(), unknown(&self.moniker)]);
+        table.push_row(["version".to_string(), unknown(&self.version)]);
+        table.push_row(["app_version".to_string(), unknown(&self.app_version)]);
+        table.push_row([
+            "latest_block_height".to_string(),
+            self.latest_block_height.to_string(),
+        ]);
+        table.push_row([
+            "latest_block_time".to_string(),
+            unknown(&self.latest_block_time),
+        ]);
+        table.push_row(["catching_up".to_string(), self.catching_up.to_string()]);
+        table
+    }
+
+    fn human(&self) -> String {
+        let unknown =
+            |value: &Option<String>| value.clone().unwrap_or_else(|| "unknown".to_string());
+        let mut out = format!("Node:         {}\n", self.url);
+        out.push_str(&format!("Chain:        {}\n", unknown(&self.chain_id)));
+        if let Some(moniker) = &self.moniker {
+            out.push_str(&format!("Moniker:      {}\n", moniker));
+        }
+        out.push_str(&format!("Version:      {}\n", unknown(&self.version)));
+        out.push_str(&format!("App version:  {}\n", unknown(&self.app_version)));
+        out.push_str(&format!("Latest block: {}", self.latest_block_height));
+        if let Some(time) = &self.latest_block_time {
+            out.push_str(&format!(" at {}", time));
+        }
+        out.push('\n');
+        out.push_str(&format!(
+            "Catching up:  {}\n",
+            if self.catching_up {
+                "yes, it may not serve the latest packages yet"
+            } else {
+                "no"
+            }
+        ));
+        if let Some(error) = &self.abci_error {
+            out.push_str(&format!("Warning: abci_info failed: {}\n", error));
+        }
+        out
+    }
+}
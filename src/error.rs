@@ -0,0 +1,49 @@
+use serde::Serialize;
+
+/// Broad category of an error, for library consumers that react to kinds
+/// of failures rather than to specific variants.
+///
+/// Every public error enum of this crate is `#[non_exhaustive]` and has a
+/// `kind()` accessor, so new variants can be added in minor releases
+/// without breaking a `match` on the kind. Kinds may be added as well, so
+/// such a `match` needs a wildcard arm too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The node couldn't be reached, timed out or answered with an error
+    Network,
+    /// The package, or a file of it, doesn't exist
+    NotFound,
+    /// Content doesn't match a hash, or isn't well-formed
+    Integrity,
+    /// A downloaded package failed validation or dependency analysis
+    Validation,
+    /// Settings or input files are invalid, or a policy refused a package
+    Config,
+    /// Local I/O failed, or something went wrong inside gget
+    Internal,
+    /// The run was cancelled or hit its deadline
+    Cancelled,
+}
+
+impl ErrorKind {
+    /// Stable, machine-readable name of the kind
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Network => "network",
+            Self::NotFound => "not_found",
+            Self::Integrity => "integrity",
+            Self::Validation => "validation",
+            Self::Config => "config",
+            Self::Internal => "internal",
+            Self::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -1,24 +1,80 @@
 use base64::{engine::general_purpose, Engine as _};
+use futures::stream::{self, StreamExt};
 use reqwest::{Client, Error as ReqwestError};
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::fs;
+use std::future::Future;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use thiserror::Error;
+use tokio::sync::Mutex;
 
-use crate::cache::{CacheError, HybridCache};
-use crate::dependency::{DependencyError, DependencyResolver, PackageDependency};
+use crate::cache::{
+    content_key, files_key, imports_key, parse_key, AsyncStorage, CacheError, CacheStats,
+    CacheTtls, DiskStorage, HybridCache, NamespacedStorage, PrefetchSummary, PrefetchedPackage,
+};
+use crate::cancel::{Cancellation, Interrupted};
+use crate::compat::CompatChecker;
+use crate::dependency::{
+    gno_files, is_test_file, DependencyClosure, DependencyError, DependencyResolver,
+    PackageDependency, TestFiles,
+};
+use crate::error::ErrorKind;
+use crate::filelock::FileLock;
+use crate::gnomod::GNOMOD_NAME;
+use crate::install::{install, write_file};
+use crate::layout::{Layout, LayoutError};
+use crate::limits::{DownloadLimits, LimitExceeded, TotalSize};
+use crate::lint::{Diagnostic, SyntaxChecker, ValidationReport};
+use crate::lock::{
+    hash_content, hash_files, nested_dirs, LockError, LockedPackage, Lockfile, Selection,
+    LOCKFILE_NAME,
+};
 use crate::parallel::{
-    DownloadError, DownloadManager, DownloadSummary, DownloadTask, ParallelDownloadOptions,
+    DownloadError, DownloadManager, DownloadSummary, DownloadTask, PackageStats,
+    ParallelDownloadOptions,
 };
-use crate::query::{RpcParams, RpcRequest, RpcResponse};
-use crate::DEFAULT_RPC_ENDPOINT;
-
-const MAX_ENTRIES: u64 = 1_000;
-const TTL: u64 = 24 * 3600;
+use crate::paths::{self, check_relative, safe_join, UnsafePath};
+use crate::plan::{DownloadPlan, FileAction, PlannedFile, PlannedPackage};
+use crate::policy::{Policy, PolicyError, PolicyMode};
+use crate::query::{
+    next_request_id, FuncSignature, RpcErrorCode, RpcErrorObject, RpcParams, RpcRequest,
+    RpcResponse, QEVAL_PATH, QFILE_PATH, QFUNCS_PATH, QRENDER_PATH,
+};
+use crate::ratelimit::{RateLimit, RateLimiter};
+use crate::rpc::{client_with, FailoverRpcClient, HttpRpcClient, RpcClient, RpcClientError};
+use crate::session::LoginHook;
+use crate::singleflight::SingleFlight;
+use crate::source::{PackageSource, SourceError, GIT_CHECKOUTS_DIR};
+use crate::trash::{Trash, TrashError};
+use crate::verify::{Verifier, VerifyError};
+use crate::{CancellationToken, DEFAULT_RPC_ENDPOINT};
+
+/// Number of cache entries kept in memory unless configured otherwise
+pub const DEFAULT_MAX_CACHE_ENTRIES: u64 = 1_000;
+/// How long cached RPC responses, file contents among them, stay valid
+/// unless configured otherwise
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(24 * 3600);
+/// How long cached package file lists stay valid unless configured
+/// otherwise. Expired lists are cheap to revalidate, see
+/// [crate::cache::HybridCache::fetched_at].
+pub const DEFAULT_FILES_TTL: Duration = Duration::from_secs(3600);
+/// How long what parsing a package's sources found stays cached unless
+/// configured otherwise. It is keyed by content, so it can't go stale.
+pub const DEFAULT_RESOLUTION_TTL: Duration = Duration::from_secs(30 * 24 * 3600);
+/// Cache directory used when no platform cache directory can be found,
+/// see [crate::paths::cache_dir]
+pub const DEFAULT_CACHE_DIR: &str = "cache";
+/// How long a package the node reported missing is remembered as such
+/// unless configured otherwise
+pub const DEFAULT_NEGATIVE_TTL: Duration = Duration::from_secs(5 * 60);
+/// How often idle connections are probed to keep them open unless
+/// configured otherwise
+pub const DEFAULT_KEEP_ALIVE: Duration = Duration::from_secs(30);
 
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum PackageManagerError {
     #[error("HTTP request failed: {0}")]
     Http(#[from] ReqwestError),
@@ -32,277 +88,2298 @@ pub enum PackageManagerError {
     #[error("Base64 decoding error: {0}")]
     Base64(#[from] base64::DecodeError),
 
+    /// Error reported by the node itself
     #[error("RPC error: {0}")]
     Rpc(String),
 
+    /// The node rejected the JSON-RPC request itself
+    #[error(
+        "JSON-RPC {code} for request {request_id}: {message}{}",
+        .data.as_ref().map(|d| format!(": {}", d)).unwrap_or_default()
+    )]
+    RpcProtocol {
+        request_id: u32,
+        code: RpcErrorCode,
+        message: String,
+        data: Option<String>,
+    },
+
+    /// The response carries another request's id
+    #[error("Response to JSON-RPC request {expected} has id {got}")]
+    RpcIdMismatch {
+        expected: u32,
+        got: serde_json::Value,
+    },
+
+    #[error("RPC transport error: {0}")]
+    Transport(RpcClientError),
+
+    #[error("Not in the cache, and network access is disabled in offline mode")]
+    Offline,
+
     #[error("Failed to create target directory: {0}")]
     DirectoryCreation(String),
 
-    #[error("Failed to get package files: {0}")]
-    PackageFiles(String),
-
-    #[error("Failed to get file content for {file}: {error}")]
-    FileContent { file: String, error: String },
+    /// Fetching the file list (`file` is `None`) or a file of a package
+    /// failed
+    #[error(
+        "Failed to download {package}{}: {source}",
+        .file.as_ref().map(|f| format!("/{}", f)).unwrap_or_default()
+    )]
+    Download {
+        package: String,
+        file: Option<String>,
+        #[source]
+        source: Box<PackageManagerError>,
+    },
+
+    /// The node's file list can't be the whole package
+    #[error("Incomplete file list for {package}: {reason}")]
+    IncompleteListing { package: String, reason: String },
+
+    /// A file name would put the file outside the package's directory
+    #[error("Refusing to write a file of {package}: {source}")]
+    UnsafePath {
+        package: String,
+        #[source]
+        source: UnsafePath,
+    },
+
+    /// The package is larger than the manager's [DownloadLimits] allow
+    #[error("Refusing to download {package}: {source}")]
+    LimitExceeded {
+        package: String,
+        #[source]
+        source: LimitExceeded,
+    },
+
+    #[error("Download queue failed: {0}")]
+    Queue(#[source] Box<DownloadError>),
+
+    #[error("Failed to resolve the dependencies of {package}: {source}")]
+    Resolution {
+        package: String,
+        #[source]
+        source: Box<PackageManagerError>,
+    },
+
+    #[error("Validation of {} failed: {source}", .dir.display())]
+    Validation {
+        dir: PathBuf,
+        #[source]
+        source: ValidationError,
+    },
 
     #[error("Cache error: {0}")]
     Cache(#[from] CacheError),
 
     #[error("Dependency error: {0}")]
     Dependency(#[from] DependencyError),
+
+    #[error("Verification failed: {0}")]
+    Verification(#[from] VerifyError),
+
+    #[error("Policy violation: {0}")]
+    Policy(#[from] PolicyError),
+
+    #[error("Lockfile error: {0}")]
+    Lock(#[from] LockError),
+
+    #[error("Trash error: {0}")]
+    Trash(#[from] TrashError),
+
+    #[error("Layout error: {0}")]
+    Layout(#[from] LayoutError),
+
+    #[error("Cancelled: {0}")]
+    Cancelled(#[from] Interrupted),
+
+    #[error("Source error: {0}")]
+    Source(#[from] SourceError),
+
+    #[error(transparent)]
+    Coalesced(CoalescedError),
+}
+
+impl PackageManagerError {
+    /// Stable, machine-readable identifier of the error kind
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Http(_) => "http",
+            Self::Io(_) => "io",
+            Self::Json(_) => "json",
+            Self::Base64(_) => "base64",
+            Self::Rpc(_) => "rpc",
+            Self::RpcProtocol { .. } => "rpc_protocol",
+            Self::RpcIdMismatch { .. } => "rpc_id_mismatch",
+            Self::Transport(_) => "transport",
+            Self::Offline => "offline",
+            Self::DirectoryCreation(_) => "directory_creation",
+            Self::Download { .. } => "download",
+            Self::IncompleteListing { .. } => "incomplete_listing",
+            Self::UnsafePath { .. } => "unsafe_path",
+            Self::LimitExceeded { .. } => "limit_exceeded",
+            Self::Queue(_) => "queue",
+            Self::Resolution { .. } => "resolution",
+            Self::Validation { .. } => "validation",
+            Self::Cache(_) => "cache",
+            Self::Dependency(_) => "dependency",
+            Self::Verification(_) => "verification",
+            Self::Policy(_) => "policy",
+            Self::Lock(_) => "lock",
+            Self::Trash(_) => "trash",
+            Self::Layout(_) => "layout",
+            Self::Cancelled(_) => "cancelled",
+            Self::Source(_) => "source",
+            Self::Coalesced(e) => e.code,
+        }
+    }
+
+    /// Broad category of the error; wrapping variants report the kind of
+    /// the error they wrap
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Http(e) if e.status() == Some(reqwest::StatusCode::NOT_FOUND) => {
+                ErrorKind::NotFound
+            }
+            Self::Http(_) | Self::Offline => ErrorKind::Network,
+            // the node reports unknown packages as errors
+            Self::Rpc(message) if message.to_lowercase().contains("not found") => {
+                ErrorKind::NotFound
+            }
+            Self::Rpc(_) => ErrorKind::Network,
+            // gget sent something the node can't read
+            Self::RpcProtocol {
+                code: RpcErrorCode::ParseError | RpcErrorCode::InvalidRequest,
+                ..
+            } => ErrorKind::Internal,
+            // not a tm2 node, or the query doesn't suit it
+            Self::RpcProtocol {
+                code: RpcErrorCode::MethodNotFound | RpcErrorCode::InvalidParams,
+                ..
+            } => ErrorKind::Config,
+            Self::RpcProtocol { .. } => ErrorKind::Network,
+            Self::RpcIdMismatch { .. }
+            | Self::IncompleteListing { .. }
+            | Self::UnsafePath { .. } => ErrorKind::Integrity,
+            Self::Transport(e) => e.kind(),
+            Self::Io(_) | Self::DirectoryCreation(_) | Self::Trash(_) => ErrorKind::Internal,
+            // a malformed response
+            Self::Json(_) | Self::Base64(_) => ErrorKind::Integrity,
+            Self::Download { source, .. } | Self::Resolution { source, .. } => source.kind(),
+            Self::Queue(e) => e.kind(),
+            Self::Validation { .. } => ErrorKind::Validation,
+            Self::Cache(e) => e.kind(),
+            Self::Dependency(e) => e.kind(),
+            Self::Verification(_) => ErrorKind::Integrity,
+            Self::Policy(_) | Self::LimitExceeded { .. } => ErrorKind::Config,
+            Self::Layout(e) => e.kind(),
+            Self::Lock(LockError::HashConflict { .. }) => ErrorKind::Integrity,
+            Self::Lock(LockError::Json(_)) => ErrorKind::Config,
+            Self::Lock(LockError::Io(_)) => ErrorKind::Internal,
+            Self::Lock(LockError::UnsafePath(_)) => ErrorKind::Integrity,
+            Self::Cancelled(_) => ErrorKind::Cancelled,
+            Self::Source(e) => e.kind(),
+            Self::Coalesced(e) => e.kind,
+        }
+    }
+
+    /// Wraps a failure to download `package`, leaving cancellations as they are
+    fn download(package: &str, file: Option<&str>, error: Self) -> Self {
+        match error {
+            e @ Self::Cancelled(_) => e,
+            e => Self::Download {
+                package: package.to_string(),
+                file: file.map(str::to_string),
+                source: Box::new(e),
+            },
+        }
+    }
+}
+
+impl PackageManagerError {
+    /// The error the node answered request `request_id` with
+    pub fn rpc_protocol(error: RpcErrorObject, request_id: u32) -> Self {
+        Self::RpcProtocol {
+            request_id,
+            code: error.code.into(),
+            data: error.data_text(),
+            message: error.message,
+        }
+    }
+}
+
+/// Parses a `vm/qfile` directory listing: one entry per line, relative to
+/// the directory, subdirectories ending with `/`.
+///
+/// The node sends the whole list in one response, as `vm/qfile` has no
+/// continuation. A list cut short on the way, by a proxy or a response
+/// size limit, would download part of a package as if it were all of it,
+/// so lists that can't be complete are rejected: ones with an entry that
+/// isn't a clean relative path, and ones with repeated entries. See
+/// [check_file_list] for what the whole package must look like. A name
+/// cut short fails later, when the node doesn't know the file.
+pub fn parse_file_listing(
+    pkg_path: &str,
+    listing: &str,
+) -> Result<Vec<String>, PackageManagerError> {
+    let mut entries: Vec<String> = Vec::new();
+    let mut seen = HashSet::new();
+    for entry in listing.lines().map(str::trim).filter(|s| !s.is_empty()) {
+        let path = entry.strip_suffix('/').unwrap_or(entry);
+        if let Err(e) = check_relative(path) {
+            return Err(incomplete_listing(
+                pkg_path,
+                format!("`{}` is not a file path: {}", entry, e.reason),
+            ));
+        }
+        if !seen.insert(path) {
+            return Err(incomplete_listing(
+                pkg_path,
+                format!("`{}` is listed twice", entry),
+            ));
+        }
+        entries.push(entry.to_string());
+    }
+    Ok(entries)
+}
+
+/// Files of the package in the local directory `dir`, relative to it and
+/// sorted, listed like the chain lists a package: subdirectories holding a
+/// gno.mod are other packages and left out, and so are hidden entries
+fn local_package_files(dir: &Path) -> std::io::Result<Vec<String>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![PathBuf::new()];
+    while let Some(relative) = dirs.pop() {
+        for entry in fs::read_dir(dir.join(&relative))? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with('.') {
+                continue;
+            }
+            let path = relative.join(&name);
+            if entry.file_type()?.is_dir() {
+                if !entry.path().join(GNOMOD_NAME).exists() {
+                    dirs.push(path);
+                }
+            } else {
+                files.push(path.to_string_lossy().replace('\\', "/"));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Lists the files of `pkg_path` kept in the local directory `dir`, see
+/// [local_package_files]
+fn list_local_package(
+    pkg_path: &str,
+    dir: &Path,
+) -> Result<(Vec<String>, Option<u64>), PackageManagerError> {
+    let files = local_package_files(dir)?;
+    check_file_list(pkg_path, &files)?;
+    Ok((files, None))
+}
+
+/// Checks that `files`, every file listed for a package, can be the whole
+/// package: gno packages have at least one `.gno` file.
+pub fn check_file_list(pkg_path: &str, files: &[String]) -> Result<(), PackageManagerError> {
+    if files.is_empty() {
+        return Err(incomplete_listing(
+            pkg_path,
+            "the node listed no files".to_string(),
+        ));
+    }
+    if !files.iter().any(|file| file.ends_with(".gno")) {
+        return Err(incomplete_listing(
+            pkg_path,
+            format!("none of the {} listed files is a .gno file", files.len()),
+        ));
+    }
+    Ok(())
+}
+
+fn unsafe_path(pkg_path: &str, source: UnsafePath) -> PackageManagerError {
+    PackageManagerError::UnsafePath {
+        package: pkg_path.to_string(),
+        source,
+    }
+}
+
+fn limit_exceeded(pkg_path: &str, source: LimitExceeded) -> PackageManagerError {
+    PackageManagerError::LimitExceeded {
+        package: pkg_path.to_string(),
+        source,
+    }
+}
+
+fn incomplete_listing(pkg_path: &str, reason: String) -> PackageManagerError {
+    PackageManagerError::IncompleteListing {
+        package: pkg_path.to_string(),
+        reason,
+    }
+}
+
+/// Unique temporary directory a download of `target_dir` is staged in,
+/// under `parent` or next to the target.
+///
+/// As a sibling of the target, renaming it over the target never crosses
+/// filesystems.
+fn staging_dir(parent: Option<&Path>, target_dir: &Path) -> PathBuf {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let name = format!(
+        "{}_tmp_{}",
+        target_dir
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or("package"),
+        timestamp,
+    );
+    match parent.or(target_dir.parent()) {
+        Some(parent) => parent.join(name),
+        None => PathBuf::from(name),
+    }
+}
+
+/// Removes a staging directory on drop, so it doesn't outlive a failed
+/// download
+struct TempDirGuard(PathBuf);
+
+impl Drop for TempDirGuard {
+    fn drop(&mut self) {
+        if self.0.exists() {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+}
+
+/// Paths of the files under `dir`, relative to it
+fn relative_files(dir: &Path) -> std::io::Result<Vec<String>> {
+    fn walk(dir: &Path, prefix: &str, files: &mut Vec<String>) -> std::io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+            if entry.file_type()?.is_dir() {
+                walk(&entry.path(), &format!("{}/", name), files)?;
+            } else {
+                files.push(name);
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = Vec::new();
+    walk(dir, "", &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+/// Failure of an RPC query another task was already making, as reported to
+/// the tasks that waited for it instead of querying the node themselves
+#[derive(Error, Debug, Clone)]
+#[error("{message}")]
+pub struct CoalescedError {
+    code: &'static str,
+    kind: ErrorKind,
+    message: String,
+}
+
+impl CoalescedError {
+    /// Code of the original error, see [PackageManagerError::code]
+    pub fn code(&self) -> &'static str {
+        self.code
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+}
+
+impl From<&PackageManagerError> for CoalescedError {
+    fn from(error: &PackageManagerError) -> Self {
+        Self {
+            code: error.code(),
+            kind: error.kind(),
+            message: error.to_string(),
+        }
+    }
+}
+
+/// Why a downloaded package failed validation
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum ValidationError {
+    #[error("no .gno files found")]
+    NoSourceFiles,
+
+    #[error(transparent)]
+    Parse(#[from] DependencyError),
+
+    /// Strict validation found syntax errors
+    #[error(
+        "{} syntax error{}:\n{}",
+        .0.len(),
+        if .0.len() == 1 { "" } else { "s" },
+        .0.iter().map(|e| format!("  {}", e)).collect::<Vec<_>>().join("\n")
+    )]
+    Syntax(Vec<Diagnostic>),
+}
+
+impl ValidationError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::NoSourceFiles | Self::Parse(_) | Self::Syntax(_) => ErrorKind::Validation,
+        }
+    }
+}
+
+impl From<RpcClientError> for PackageManagerError {
+    fn from(error: RpcClientError) -> Self {
+        match error {
+            // keep HTTP failures distinguishable, as before transports existed
+            RpcClientError::Http(e) => Self::Http(e),
+            other => Self::Transport(other),
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct PackageManager {
     rpc_endpoint: String,
-    http_client: Client,
+    rpc_client: Arc<dyn RpcClient>,
     cache: Arc<HybridCache>,
+    verifier: Option<Arc<dyn Verifier>>,
+    policy: Policy,
+    limits: DownloadLimits,
+    /// What this manager and its clones downloaded, see
+    /// [DownloadLimits::max_total_size]
+    downloaded: TotalSize,
+    /// Whether resolution follows what only test files import
+    test_files: TestFiles,
+    quiet: bool,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    cancellation: Cancellation,
+    /// Block heights packages are queried at, by package path
+    pinned_heights: Arc<HashMap<String, u64>>,
+    /// Heights picked among several roots' pins, reported in plans and
+    /// lockfiles
+    selections: Arc<Vec<Selection>>,
+    /// Where packages are served from instead of the chain, by package path
+    sources: Arc<HashMap<String, PackageSource>>,
+    /// Where git sources are checked out
+    checkouts_dir: PathBuf,
+    /// Package directories of the git sources checked out so far, by
+    /// package path
+    checked_out: Arc<Mutex<HashMap<String, PathBuf>>>,
+    /// Directories laid out by import path that packages are also found
+    /// in, such as a checkout of the gno repository's examples
+    registries: Arc<Vec<PathBuf>>,
+    /// Serve packages found in a registry from it rather than the chain
+    prefer_local: bool,
+    /// Packages the chain couldn't serve, read from a registry instead
+    registry_fallbacks: Arc<std::sync::Mutex<HashSet<String>>>,
+    /// Serve from the cache only, failing instead of querying the node
+    offline: bool,
+    /// Fail validation on syntax errors instead of reporting them
+    strict: bool,
+    /// How long missing packages are remembered, zero to not remember them
+    negative_ttl: Duration,
+    /// Ask the node again about packages remembered as missing
+    refresh: bool,
+    /// Write downloads straight into their target instead of swapping
+    /// them in atomically
+    direct: bool,
+    /// Where downloads are staged, next to their target if `None`
+    staging_dir: Option<PathBuf>,
+    /// Whether downloaded files are synced to disk as they are written
+    fsync: bool,
+    /// Where each package of a multi-package download goes
+    layout: Layout,
+    /// Where replaced packages go instead of being deleted
+    trash: Option<Trash>,
+    /// Modules whose packages are available locally, never resolved or
+    /// downloaded
+    local_modules: Vec<String>,
+    /// RPC queries in flight, shared by concurrent identical queries
+    inflight: Arc<SingleFlight<Result<Answer, CoalescedError>>>,
+}
+
+/// Data of an ABCI query response, and the height it was answered at
+#[derive(Clone)]
+struct Answer {
+    data: String,
+    height: Option<u64>,
+}
+
+/// Configures a [PackageManager], see [PackageManager::builder]
+#[derive(Default)]
+pub struct PackageManagerBuilder {
+    endpoints: Vec<String>,
+    cache_dir: Option<PathBuf>,
+    storage: Option<Arc<dyn AsyncStorage>>,
+    cache_namespace: Option<String>,
+    cache_ttls: CacheTtls,
+    max_cache_entries: Option<u64>,
+    http_client: Option<Client>,
+    user_agent: Option<String>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    cookies: bool,
+    proxy: Option<String>,
+    root_certificates: Vec<PathBuf>,
+    pool_max_idle_per_host: Option<usize>,
+    pool_idle_timeout: Option<Duration>,
+    gzip: bool,
+    keep_alive: Option<Option<Duration>>,
+    http2_prior_knowledge: bool,
+    login_command: Option<String>,
+    offline: bool,
+    verifier: Option<Arc<dyn Verifier>>,
+}
+
+impl PackageManagerBuilder {
+    /// Adds an RPC endpoint. Defaults to [DEFAULT_RPC_ENDPOINT] if none is
+    /// added.
+    pub fn endpoint(mut self, url: impl Into<String>) -> Self {
+        self.endpoints.push(url.into());
+        self
+    }
+
+    /// Adds several RPC endpoints. Requests go to the first one and fail
+    /// over to the next when an endpoint can't be reached.
+    pub fn endpoints<I, S>(mut self, urls: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.endpoints.extend(urls.into_iter().map(Into::into));
+        self
+    }
+
+    /// Directory of the on-disk cache, [crate::paths::cache_dir] by default.
+    /// Ignored when a [PackageManagerBuilder::storage] is set.
+    pub fn cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(dir.into());
+        self
+    }
+
+    /// Persists the cache to `storage` instead of the cache directory, e.g.
+    /// [crate::cache::MemoryStorage] to keep it in memory only, or
+    /// [crate::cache::NoopCache] to keep nothing beyond the in-memory layer
+    pub fn storage<S: AsyncStorage + 'static>(mut self, storage: S) -> Self {
+        self.storage = Some(Arc::new(storage));
+        self
+    }
+
+    /// Keeps the cached responses of this package manager apart from
+    /// those cached under other namespaces, or none, in the same storage.
+    /// Useful to not mix up packages of different networks.
+    pub fn cache_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.cache_namespace = Some(namespace.into());
+        self
+    }
+
+    /// How long cached responses of every kind but missing packages stay
+    /// valid, see [PackageManagerBuilder::cache_ttls]
+    pub fn cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttls = CacheTtls {
+            negative: self.cache_ttls.negative,
+            ..CacheTtls::uniform(ttl)
+        };
+        self
+    }
+
+    /// How long each kind of cached response stays valid, see
+    /// [CacheTtls] for the defaults
+    pub fn cache_ttls(mut self, ttls: CacheTtls) -> Self {
+        self.cache_ttls = ttls;
+        self
+    }
+
+    /// How long a package the node reported missing is remembered, so
+    /// bogus imports don't query it again on every run.
+    /// [DEFAULT_NEGATIVE_TTL] by default, zero to not remember them.
+    pub fn negative_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttls.negative = ttl;
+        self
+    }
+
+    /// Entries kept in the in-memory cache, [DEFAULT_MAX_CACHE_ENTRIES] by
+    /// default
+    pub fn max_cache_entries(mut self, entries: u64) -> Self {
+        self.max_cache_entries = Some(entries);
+        self
+    }
+
+    /// Sends HTTP requests through `client`. The HTTP settings of this
+    /// builder, from [PackageManagerBuilder::user_agent] to
+    /// [PackageManagerBuilder::gzip], are ignored in favor of the client's
+    /// own.
+    pub fn http_client(mut self, client: Client) -> Self {
+        self.http_client = Some(client);
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Limit on a whole HTTP request, from connecting to reading the body
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Keeps cookies set by HTTP endpoints and sends them back, for
+    /// gateways that hold sessions in cookies. Each host gets its own.
+    pub fn cookies(mut self, enabled: bool) -> Self {
+        self.cookies = enabled;
+        self
+    }
+
+    /// Sends HTTP requests through the proxy at `url`, e.g.
+    /// `http://proxy.corp:3128`.
+    ///
+    /// Without one, the `HTTPS_PROXY`, `HTTP_PROXY`, `ALL_PROXY` and
+    /// `NO_PROXY` environment variables apply.
+    pub fn proxy(mut self, url: impl Into<String>) -> Self {
+        self.proxy = Some(url.into());
+        self
+    }
+
+    /// Trusts the CA certificates in the PEM file at `path` on top of the
+    /// system ones, for endpoints behind TLS-intercepting gateways
+    pub fn root_certificate(mut self, path: impl Into<PathBuf>) -> Self {
+        self.root_certificates.push(path.into());
+        self
+    }
+
+    /// Idle connections kept open to each host for reuse
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.pool_max_idle_per_host = Some(max);
+        self
+    }
+
+    /// How long an idle connection is kept open for reuse
+    pub fn pool_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Asks for gzip-compressed responses and decompresses them
+    pub fn gzip(mut self, enabled: bool) -> Self {
+        self.gzip = enabled;
+        self
+    }
+
+    /// How often idle connections are probed, with TCP keep-alives and
+    /// HTTP/2 pings, so the connections of a long run stay open between
+    /// bursts of requests. [DEFAULT_KEEP_ALIVE] by default, `None` to not
+    /// probe them.
+    pub fn keep_alive(mut self, interval: Option<Duration>) -> Self {
+        self.keep_alive = Some(interval);
+        self
+    }
+
+    /// Speaks HTTP/2 to plain `http://` endpoints without upgrading first,
+    /// for nodes known to support it. HTTPS endpoints negotiate HTTP/2 on
+    /// their own.
+    pub fn http2_prior_knowledge(mut self, enabled: bool) -> Self {
+        self.http2_prior_knowledge = enabled;
+        self
+    }
+
+    /// Runs `command` before the first request to each HTTP endpoint and
+    /// sends the headers it prints, see [LoginHook]
+    pub fn login_command(mut self, command: impl Into<String>) -> Self {
+        self.login_command = Some(command.into());
+        self
+    }
+
+    /// Serves packages from the cache only. Anything not cached fails with
+    /// [PackageManagerError::Offline].
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Sets a verifier that must accept every atomically downloaded package
+    pub fn verifier<V: Verifier + 'static>(mut self, verifier: V) -> Self {
+        self.verifier = Some(Arc::new(verifier));
+        self
+    }
+
+    /// The HTTP client the package manager will send requests through,
+    /// built from the HTTP settings unless one was given with
+    /// [PackageManagerBuilder::http_client].
+    ///
+    /// Fails if the proxy URL is invalid or a certificate can't be read.
+    pub fn build_http_client(&self) -> Result<Client, PackageManagerError> {
+        if let Some(client) = &self.http_client {
+            return Ok(client.clone());
+        }
+
+        // concurrent requests share HTTP/2 connections, whose flow control
+        // window grows with the bandwidth they see
+        let mut builder = Client::builder()
+            .cookie_store(self.cookies)
+            .gzip(self.gzip)
+            .http2_adaptive_window(true);
+        if let Some(interval) = self.keep_alive.unwrap_or(Some(DEFAULT_KEEP_ALIVE)) {
+            builder = builder
+                .tcp_keepalive(interval)
+                .http2_keep_alive_interval(interval)
+                .http2_keep_alive_while_idle(true);
+        }
+        if self.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        if let Some(user_agent) = &self.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+        }
+        for path in &self.root_certificates {
+            let certificates = reqwest::Certificate::from_pem_bundle(&fs::read(path)?)?;
+            if certificates.is_empty() {
+                return Err(PackageManagerError::Io(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("no PEM certificates in {}", path.display()),
+                )));
+            }
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+        if let Some(max) = self.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max);
+        }
+        if let Some(timeout) = self.pool_idle_timeout {
+            builder = builder.pool_idle_timeout(timeout);
+        }
+        Ok(builder.build()?)
+    }
+
+    /// Creates the package manager. Fails if the HTTP client can't be
+    /// built from the given settings, see
+    /// [PackageManagerBuilder::build_http_client].
+    pub fn build(self) -> Result<PackageManager, PackageManagerError> {
+        let http_client = self.build_http_client()?;
+
+        let mut endpoints = self.endpoints;
+        if endpoints.is_empty() {
+            endpoints.push(DEFAULT_RPC_ENDPOINT.to_string());
+        }
+        let transport = |endpoint: &str| -> Arc<dyn RpcClient> {
+            match &self.login_command {
+                Some(command) if endpoint.starts_with("http") => Arc::new(
+                    HttpRpcClient::with_client(endpoint, http_client.clone())
+                        .with_login_hook(LoginHook::new(command, endpoint)),
+                ),
+                _ => client_with(endpoint, http_client.clone()),
+            }
+        };
+        let rpc_client = match endpoints.as_slice() {
+            [endpoint] => transport(endpoint),
+            _ => Arc::new(FailoverRpcClient::new(
+                endpoints
+                    .iter()
+                    .map(|endpoint| transport(endpoint))
+                    .collect(),
+            )),
+        };
+        let limits = DownloadLimits::default();
+        let rpc_client = rpc_client
+            .with_max_response_size(limits.max_response_size())
+            .unwrap_or(rpc_client);
+
+        let ttls = self.cache_ttls;
+        let cache_dir = self.cache_dir.unwrap_or_else(paths::cache_dir);
+        let checkouts_dir = cache_dir.join(GIT_CHECKOUTS_DIR);
+        let mut storage = self
+            .storage
+            .unwrap_or_else(|| Arc::new(DiskStorage::new(cache_dir, ttls.contents)));
+        if let Some(namespace) = &self.cache_namespace {
+            storage = Arc::new(NamespacedStorage::new(storage, namespace));
+        }
+        let cache = HybridCache::with_ttls(
+            storage,
+            ttls,
+            self.max_cache_entries.unwrap_or(DEFAULT_MAX_CACHE_ENTRIES),
+        );
+
+        Ok(PackageManager {
+            rpc_endpoint: endpoints.swap_remove(0),
+            rpc_client,
+            cache: Arc::new(cache),
+            verifier: self.verifier,
+            policy: Policy::default(),
+            limits,
+            downloaded: TotalSize::default(),
+            test_files: TestFiles::default(),
+            strict: false,
+            quiet: false,
+            rate_limiter: None,
+            cancellation: Cancellation::default(),
+            pinned_heights: Arc::default(),
+            selections: Arc::default(),
+            sources: Arc::default(),
+            checkouts_dir,
+            checked_out: Arc::default(),
+            registries: Arc::default(),
+            prefer_local: false,
+            registry_fallbacks: Arc::default(),
+            offline: self.offline,
+            negative_ttl: ttls.negative,
+            refresh: false,
+            direct: false,
+            staging_dir: None,
+            fsync: false,
+            layout: Layout::default(),
+            trash: None,
+            local_modules: Vec::new(),
+            inflight: Arc::default(),
+        })
+    }
 }
 
 impl PackageManager {
-    /// Creates a new PackageManager instance
+    /// Creates a new PackageManager instance with the default settings, see
+    /// [PackageManager::builder] for the rest
     pub fn new(rpc_endpoint: Option<String>, cache_dir: PathBuf) -> Self {
-        let endpoint = rpc_endpoint.unwrap_or_else(|| DEFAULT_RPC_ENDPOINT.to_string());
-        let http_client = Client::new();
-        let cache = HybridCache::new(cache_dir, Duration::from_secs(TTL), MAX_ENTRIES);
+        Self::builder()
+            .endpoints(rpc_endpoint)
+            .cache_dir(cache_dir)
+            .build()
+            .expect("the default HTTP client always builds")
+    }
+
+    /// Starts configuring a package manager
+    pub fn builder() -> PackageManagerBuilder {
+        PackageManagerBuilder::default()
+    }
+
+    /// Sends RPC requests through `client` instead of the transport picked
+    /// from the endpoint's scheme
+    pub fn with_rpc_client<C: RpcClient + 'static>(mut self, client: C) -> Self {
+        let client: Arc<dyn RpcClient> = Arc::new(client);
+        self.rpc_client = client
+            .with_max_response_size(self.limits.max_response_size())
+            .unwrap_or(client);
+        self
+    }
+
+    /// Queries the node again for packages it recently reported missing
+    /// instead of failing from the cache, and parses packages again instead
+    /// of reusing the imports cached for them
+    pub fn with_refresh(mut self, refresh: bool) -> Self {
+        self.refresh = refresh;
+        self
+    }
+
+    /// Writes downloaded files straight into their target directory instead
+    /// of swapping a complete copy in, which is what
+    /// [PackageManager::download_package] does by default.
+    ///
+    /// A failed or interrupted download then leaves a partial tree behind.
+    /// Deprecated escape hatch for targets that can't be replaced as a
+    /// whole; ignored when a verifier is set.
+    pub fn with_unsafe_direct(mut self, direct: bool) -> Self {
+        self.direct = direct;
+        self
+    }
+
+    /// Lays out the packages of multi-package downloads under their target
+    /// directory with `layout`, [Layout::ByImportPath] by default
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Stages downloads in `dir` instead of next to their target.
+    ///
+    /// On another filesystem than the target, staged packages are copied
+    /// and synced before they are swapped in, instead of being renamed.
+    pub fn with_staging_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.staging_dir = Some(dir.into());
+        self
+    }
+
+    /// Syncs every downloaded file to disk before it is moved into place,
+    /// so a crash right after a download can't leave truncated files
+    /// behind the rename.
+    ///
+    /// Off by default, as it slows large downloads down; the lockfile and
+    /// the directories files are renamed into are synced either way.
+    pub fn with_fsync(mut self, fsync: bool) -> Self {
+        self.fsync = fsync;
+        self
+    }
+
+    /// Suppresses the per-file progress lines printed while downloading
+    pub fn with_quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Throttles every RPC call made through this manager and its clones
+    pub fn with_rate_limit(mut self, limit: RateLimit) -> Self {
+        self.rate_limiter = Some(Arc::new(RateLimiter::new(limit)));
+        self
+    }
+
+    /// Sets a verifier that must accept every atomically downloaded package
+    pub fn with_verifier<V: Verifier + 'static>(mut self, verifier: V) -> Self {
+        self.verifier = Some(Arc::new(verifier));
+        self
+    }
+
+    /// Stops every operation of this manager and its clones once `token`
+    /// is cancelled.
+    ///
+    /// Interrupted operations return [PackageManagerError::Cancelled]
+    /// promptly: atomic downloads remove their temporary directory and
+    /// leave the target untouched, and no lockfile is written for an
+    /// interrupted run.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = self.cancellation.with_token(token);
+        self
+    }
+
+    /// Stops every operation of this manager and its clones once `deadline`
+    /// has passed, the same way as [PackageManager::with_cancellation]
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.cancellation = self.cancellation.with_deadline(deadline);
+        self
+    }
+
+    /// Moves the packages that atomic downloads replace to `trash` instead
+    /// of deleting them, and saves files there before they are overwritten
+    /// in place
+    pub fn with_trash(mut self, trash: Trash) -> Self {
+        self.trash = Some(trash);
+        self
+    }
+
+    /// Treats the packages of `modules` and their subpackages as available
+    /// locally, such as the other members of a workspace, so dependency
+    /// resolution stops at them instead of fetching them
+    pub fn with_local_modules<I, S>(mut self, modules: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.local_modules = modules.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Whether `pkg_path` belongs to one of the local modules
+    fn is_local(&self, pkg_path: &str) -> bool {
+        self.local_modules.iter().any(|module| {
+            pkg_path
+                .strip_prefix(module.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+    }
+
+    /// Where replaced packages go, if anywhere, see [PackageManager::with_trash]
+    pub fn trash(&self) -> Option<&Trash> {
+        self.trash.as_ref()
+    }
+
+    /// Sets the allow/deny policy enforced during dependency resolution
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Caps what downloads may write, [DownloadLimits::default] unless set.
+    ///
+    /// The total size counts every package this manager and its clones
+    /// download, or each parallel run on its own. Responses from the node
+    /// are capped to what the largest file allowed needs, see
+    /// [DownloadLimits::max_response_size].
+    pub fn with_limits(mut self, limits: DownloadLimits) -> Self {
+        self.limits = limits;
+        if let Some(client) = self
+            .rpc_client
+            .with_max_response_size(limits.max_response_size())
+        {
+            self.rpc_client = client;
+        }
+        self
+    }
+
+    /// Sets whether dependency resolution reads `_test.gno` and
+    /// `_filetest.gno` files and follows what only they import.
+    ///
+    /// Test files are downloaded either way; excluding them keeps the
+    /// closure to what the packages need to run.
+    pub fn with_test_files(mut self, test_files: TestFiles) -> Self {
+        self.test_files = test_files;
+        self
+    }
+
+    /// Sets whether [PackageManager::validate_package] fails on syntax
+    /// errors rather than only reporting them
+    pub fn with_strict_validation(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Queries `pkg_path` at block `height` instead of the latest block.
+    ///
+    /// Only the package itself is pinned; its dependencies still resolve
+    /// at the latest height.
+    pub fn with_pinned_height(mut self, pkg_path: &str, height: u64) -> Self {
+        Arc::make_mut(&mut self.pinned_heights).insert(pkg_path.to_string(), height);
+        self
+    }
+
+    /// Pins each selected package at the height picked for it, see
+    /// [crate::lock::select_heights]. Plans and lockfiles of downloads
+    /// including a selected package report its selection.
+    pub fn with_selections(mut self, selections: Vec<Selection>) -> Self {
+        for selection in &selections {
+            self = self.with_pinned_height(&selection.path, selection.height);
+        }
+        Arc::make_mut(&mut self.selections).extend(selections);
+        self
+    }
+
+    /// The selections among `packages`
+    fn selections_of(&self, mut packages: impl FnMut(&str) -> bool) -> Vec<Selection> {
+        self.selections
+            .iter()
+            .filter(|selection| packages(&selection.path))
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the height `pkg_path` is pinned at, if any
+    pub fn pinned_height(&self, pkg_path: &str) -> Option<u64> {
+        self.pinned_heights.get(pkg_path).copied()
+    }
+
+    /// Serves `pkg_path` from the local directory `dir` instead of the
+    /// chain, like a gno.mod `replace` directive.
+    ///
+    /// Its files are read from `dir` whenever they're needed, never cached,
+    /// so edits show up on the next run. Its imports are resolved as usual.
+    pub fn with_replacement(self, pkg_path: &str, dir: impl Into<PathBuf>) -> Self {
+        self.with_source(pkg_path, PackageSource::Local(dir.into()))
+    }
+
+    /// Applies [PackageManager::with_replacement] to each `(package, dir)`,
+    /// such as [crate::gnomod::GnoMod::replacements]
+    pub fn with_replacements<I>(self, replacements: I) -> Self
+    where
+        I: IntoIterator<Item = (String, PathBuf)>,
+    {
+        replacements.into_iter().fold(self, |pm, (pkg_path, dir)| {
+            pm.with_replacement(&pkg_path, dir)
+        })
+    }
+
+    /// Serves `pkg_path` from `source` instead of the chain.
+    ///
+    /// Git sources are checked out under the cache directory the first
+    /// time one of their files is needed, then read like local directories.
+    /// Their files go through the same installation as the chain's.
+    pub fn with_source(mut self, pkg_path: &str, source: PackageSource) -> Self {
+        Arc::make_mut(&mut self.sources).insert(pkg_path.to_string(), source);
+        self
+    }
+
+    /// Returns where `pkg_path` is served from instead of the chain, if
+    /// anywhere
+    pub fn source(&self, pkg_path: &str) -> Option<&PackageSource> {
+        self.sources.get(pkg_path)
+    }
+
+    /// Also looks for packages in `dir`, laid out by import path like the
+    /// `examples` directory of the gno repository.
+    ///
+    /// Packages the node doesn't have, or that can't be fetched offline,
+    /// are read from the first registry holding them. With
+    /// [PackageManager::with_prefer_local], registries come first.
+    pub fn with_local_registry(mut self, dir: impl Into<PathBuf>) -> Self {
+        Arc::make_mut(&mut self.registries).push(dir.into());
+        self
+    }
+
+    /// Serves packages found in a local registry from it without asking
+    /// the node, see [PackageManager::with_local_registry]
+    pub fn with_prefer_local(mut self, prefer_local: bool) -> Self {
+        self.prefer_local = prefer_local;
+        self
+    }
+
+    /// Directory of `pkg_path` in the first local registry holding it, one
+    /// with `.gno` files of its own
+    pub fn registry_package(&self, pkg_path: &str) -> Option<PathBuf> {
+        self.registries.iter().find_map(|registry| {
+            let dir = safe_join(registry, pkg_path).ok()?;
+            let is_package = fs::read_dir(&dir).ok()?.flatten().any(|entry| {
+                entry.file_name().to_string_lossy().ends_with(".gno")
+                    && entry.file_type().is_ok_and(|t| t.is_file())
+            });
+            is_package.then_some(dir)
+        })
+    }
+
+    /// Directory of `pkg_path` in a local registry, if it's read from there
+    fn registry_dir(&self, pkg_path: &str) -> Option<PathBuf> {
+        let fell_back = || {
+            self.registry_fallbacks
+                .lock()
+                .expect("registry fallbacks poisoned")
+                .contains(pkg_path)
+        };
+        if !self.prefer_local && !fell_back() {
+            return None;
+        }
+        self.registry_package(pkg_path)
+    }
+
+    /// Directory of `pkg_path` in a local registry, if one makes up for
+    /// the chain failing to serve it with `error`; the registry serves it
+    /// from then on
+    fn fall_back_to_registry(
+        &self,
+        pkg_path: &str,
+        error: &PackageManagerError,
+    ) -> Option<PathBuf> {
+        let unavailable = matches!(error.kind(), ErrorKind::NotFound)
+            || matches!(error, PackageManagerError::Offline)
+            || matches!(error, PackageManagerError::Download { source, .. }
+                if matches!(**source, PackageManagerError::Offline));
+        if !unavailable {
+            return None;
+        }
+        let dir = self.registry_package(pkg_path)?;
+        tracing::debug!(package = pkg_path, "read from a local registry");
+        self.registry_fallbacks
+            .lock()
+            .expect("registry fallbacks poisoned")
+            .insert(pkg_path.to_string());
+        Some(dir)
+    }
+
+    /// Whether `pkg_path` is read from somewhere other than the chain
+    fn is_sourced(&self, pkg_path: &str) -> bool {
+        self.source(pkg_path).is_some() || self.registry_dir(pkg_path).is_some()
+    }
+
+    /// Directory the files of `pkg_path` are read from if it has a source
+    /// or is read from a local registry, checking git sources out on first
+    /// use
+    async fn source_dir(&self, pkg_path: &str) -> Result<Option<PathBuf>, PackageManagerError> {
+        let git = match self.source(pkg_path) {
+            None => return Ok(self.registry_dir(pkg_path)),
+            Some(PackageSource::Local(dir)) => return Ok(Some(dir.clone())),
+            Some(PackageSource::Git(git)) => git,
+        };
+        // held while checking out, so concurrent tasks don't clone twice
+        let mut checked_out = self.checked_out.lock().await;
+        if let Some(dir) = checked_out.get(pkg_path) {
+            return Ok(Some(dir.clone()));
+        }
+        self.cancellation.check()?;
+        let dir = git.checkout(&self.checkouts_dir).await?;
+        checked_out.insert(pkg_path.to_string(), dir.clone());
+        Ok(Some(dir))
+    }
+
+    /// Cached value of `key`, one of `pkg_path`'s entries; never found for
+    /// packages read from elsewhere, whose entries could be left from the
+    /// chain
+    async fn cached_entry(
+        &self,
+        pkg_path: &str,
+        key: &str,
+    ) -> Result<Option<String>, PackageManagerError> {
+        if self.is_sourced(pkg_path) {
+            return Ok(None);
+        }
+        Ok(self.cache.get(key).await?)
+    }
+
+    /// Cache key of a package's file list, see [files_key]
+    fn files_key(&self, pkg_path: &str) -> String {
+        files_key(pkg_path, self.pinned_height(pkg_path))
+    }
+
+    /// Cache key of a file's content, see [content_key]
+    fn content_key(&self, pkg_path: &str, file: &str) -> String {
+        content_key(pkg_path, file, self.pinned_height(pkg_path))
+    }
+
+    /// Checks a package against the policy.
+    ///
+    /// Returns `Ok(false)` when the package should be skipped in warn mode,
+    /// recording why in `closure` for the caller to report.
+    fn check_policy(
+        &self,
+        pkg_path: &str,
+        closure: &mut DependencyClosure,
+    ) -> Result<bool, PackageManagerError> {
+        match self.policy.check(pkg_path) {
+            Ok(()) => Ok(true),
+            Err(e) if self.policy.mode == PolicyMode::Warn => {
+                closure.skipped.insert(pkg_path.to_string(), e.to_string());
+                Ok(false)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Directory a download of `target_dir` is staged in
+    fn staging_dir(&self, target_dir: &Path) -> PathBuf {
+        staging_dir(self.staging_dir.as_deref(), target_dir)
+    }
+
+    /// Returns the RPC endpoint, the first one if there are several
+    pub fn rpc_endpoint(&self) -> &str {
+        &self.rpc_endpoint
+    }
+
+    /// Downloads a package and its files to the target directory.
+    ///
+    /// Every file is fetched into a staging directory next to the target
+    /// first, so a failed download leaves the target untouched. A new
+    /// target is then renamed into place as a whole; the files of an
+    /// existing one are replaced one rename at a time, keeping anything
+    /// else in it. Writes in place instead when
+    /// [PackageManager::with_unsafe_direct] is set.
+    ///
+    /// Other gget processes downloading into `target_dir` are waited for
+    /// rather than raced with.
+    pub async fn download_package(
+        &self,
+        pkg_path: &str,
+        target_dir: &Path,
+    ) -> Result<PackageStats, PackageManagerError> {
+        let _lock = self.lock_target(target_dir).await?;
+        if self.direct && self.verifier.is_none() {
+            return self
+                .download_package_direct(pkg_path, target_dir, target_dir)
+                .await;
+        }
+        if !target_dir.exists() {
+            return self.replace_package(pkg_path, target_dir).await;
+        }
+
+        let temp_dir = self.staging_dir(target_dir);
+        let _guard = TempDirGuard(temp_dir.clone());
+        let stats = self
+            .download_package_direct(pkg_path, &temp_dir, target_dir)
+            .await?;
+        if let Some(verifier) = &self.verifier {
+            verifier.verify(pkg_path, &temp_dir)?;
+        }
+
+        let files = relative_files(&temp_dir)?;
+        if let Some(trash) = &self.trash {
+            trash.save_files(pkg_path, target_dir, &files)?;
+        }
+        for file in &files {
+            let target = target_dir.join(file);
+            if let Some(p) = target.parent() {
+                fs::create_dir_all(p)?;
+            }
+            install(&temp_dir.join(file), &target)?;
+        }
+
+        Ok(stats)
+    }
+
+    /// Writes `files`, by path relative to `target_dir`, into it through a
+    /// staging directory, the way [PackageManager::download_package]
+    /// updates an existing target: nothing is written until every file is
+    /// staged, then each one is [install]ed over its local copy
+    pub(crate) fn install_files(
+        &self,
+        target_dir: &Path,
+        files: &BTreeMap<String, Vec<u8>>,
+    ) -> Result<(), PackageManagerError> {
+        let temp_dir = self.staging_dir(target_dir);
+        let _guard = TempDirGuard(temp_dir.clone());
+        for (name, content) in files {
+            let staged = temp_dir.join(name);
+            if let Some(p) = staged.parent() {
+                fs::create_dir_all(p)?;
+            }
+            write_file(&staged, content, self.fsync)?;
+        }
+        for name in files.keys() {
+            let target = target_dir.join(name);
+            if let Some(p) = target.parent() {
+                fs::create_dir_all(p)?;
+            }
+            install(&temp_dir.join(name), &target)?;
+        }
+        Ok(())
+    }
+
+    /// Takes the [FileLock] of `target_dir`, telling the user when another
+    /// process holds it
+    pub(crate) async fn lock_target(
+        &self,
+        target_dir: &Path,
+    ) -> Result<FileLock, PackageManagerError> {
+        let quiet = self.quiet;
+        let lock = FileLock::for_target(target_dir, || {
+            if !quiet {
+                println!(
+                    "Waiting for another gget process to finish with {}...",
+                    target_dir.display()
+                );
+            }
+        });
+        Ok(self.cancellation.run(lock).await??)
+    }
+
+    /// Returns the cached value of `key`, or fills it with `fetch`, along
+    /// with whether it was cached.
+    ///
+    /// Other processes sharing the cache that miss `key` at the same time
+    /// wait for this one to fill it instead of fetching it as well.
+    async fn cached_or_fetch<F>(
+        &self,
+        key: &str,
+        fetch: F,
+    ) -> Result<(String, bool), PackageManagerError>
+    where
+        F: Future<Output = Result<String, PackageManagerError>>,
+    {
+        if let Some(raw) = self.cache.get(key).await? {
+            return Ok((raw, true));
+        }
+        let _lock = self.cancellation.run(self.cache.lock(key)).await??;
+        if let Some(raw) = self.cache.recheck(key).await? {
+            return Ok((raw, true));
+        }
+        let value = fetch.await?;
+        self.cache.set(key, &value).await?;
+        Ok((value, false))
+    }
+
+    /// Fetches the file list of `pkg_path` once its cache entry is gone,
+    /// renewing the expired entries of its files if the package can't
+    /// have changed since they were fetched, so they aren't fetched again.
+    ///
+    /// The node has no query telling when a path last changed, so the
+    /// height the list is served at is compared with the one recorded
+    /// when the entries were fetched: if no block was committed since,
+    /// nothing changed. Pinned packages never change, so their entries
+    /// are renewed without a query.
+    async fn revalidate(&self, pkg_path: &str) -> Result<String, PackageManagerError> {
+        let files_key = self.files_key(pkg_path);
+        let stale = self.cache.get_stale(&files_key).await?;
+        if let (Some(stale), Some(_)) = (&stale, self.pinned_height(pkg_path)) {
+            self.renew(pkg_path, stale).await?;
+            return Ok(stale.clone());
+        }
+
+        let (files, height) = self.list_chain_package(pkg_path).await?;
+        let list = serde_json::to_string(&files)?;
+        let fetched_at = self
+            .cache
+            .fetched_at(pkg_path, self.pinned_height(pkg_path))
+            .await?;
+        if let (Some(stale), Some(height)) = (&stale, height) {
+            if *stale == list && fetched_at == Some(height) {
+                tracing::debug!(package = pkg_path, height, "unchanged since last fetched");
+                self.renew(pkg_path, stale).await?;
+            }
+        }
+        self.record_fetched(pkg_path, height).await?;
+        Ok(list)
+    }
+
+    /// Renews the cache entries of the files listed in `list`, if they're
+    /// still there
+    async fn renew(&self, pkg_path: &str, list: &str) -> Result<(), PackageManagerError> {
+        let files: Vec<String> = serde_json::from_str(list)?;
+        for file in files {
+            let key = self.content_key(pkg_path, file.trim());
+            if let Some(content) = self.cache.get_stale(&key).await? {
+                self.cache.set(&key, &content).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Records the height the file list of `pkg_path` was fetched at, see
+    /// [PackageManager::revalidate]
+    async fn record_fetched(
+        &self,
+        pkg_path: &str,
+        height: Option<u64>,
+    ) -> Result<(), PackageManagerError> {
+        Ok(self
+            .cache
+            .set_fetched_at(pkg_path, self.pinned_height(pkg_path), height)
+            .await?)
+    }
+
+    /// Returns the file list of `pkg_path` from the cache, or fetches and
+    /// caches it, counting where it came from in `stats`
+    async fn cached_file_list(
+        &self,
+        pkg_path: &str,
+        stats: &mut PackageStats,
+    ) -> Result<Vec<String>, PackageManagerError> {
+        let listed = match self.is_sourced(pkg_path) {
+            true => None,
+            false => {
+                let fetched = self
+                    .cached_or_fetch(&self.files_key(pkg_path), async {
+                        self.revalidate(pkg_path)
+                            .await
+                            .map_err(|e| PackageManagerError::download(pkg_path, None, e))
+                    })
+                    .await;
+                match fetched {
+                    Err(e) if self.fall_back_to_registry(pkg_path, &e).is_some() => None,
+                    fetched => Some(fetched?),
+                }
+            }
+        };
+        let files: Vec<String> = match listed {
+            Some((raw, cached)) => {
+                if cached {
+                    stats.cache_hits += 1;
+                } else {
+                    stats.network_fetches += 1;
+                }
+                serde_json::from_str(&raw)?
+            }
+            None => {
+                self.list_package(pkg_path)
+                    .await
+                    .map_err(|e| PackageManagerError::download(pkg_path, None, e))?
+                    .0
+            }
+        };
+        // the list may come from a cache written by another version, or
+        // tampered with, so it's checked here and not only when listed
+        let mut listed = 0;
+        for file in &files {
+            let trimmed = file.trim();
+            if !trimmed.is_empty() {
+                check_relative(trimmed).map_err(|e| unsafe_path(pkg_path, e))?;
+                listed += 1;
+            }
+        }
+        self.limits
+            .check_files(listed)
+            .map_err(|e| limit_exceeded(pkg_path, e))?;
+        Ok(files)
+    }
+
+    /// Returns the content of a file of `pkg_path` from the cache, or
+    /// fetches and caches it, counting where it came from in `stats`
+    async fn cached_file(
+        &self,
+        pkg_path: &str,
+        file: &str,
+        stats: &mut PackageStats,
+    ) -> Result<String, PackageManagerError> {
+        if self.is_sourced(pkg_path) {
+            return self
+                .get_file_content(pkg_path, file)
+                .await
+                .map_err(|e| PackageManagerError::download(pkg_path, Some(file), e));
+        }
+        let (content, cached) = self
+            .cached_or_fetch(&self.content_key(pkg_path, file), async {
+                self.get_file_content(pkg_path, file)
+                    .await
+                    .map_err(|e| PackageManagerError::download(pkg_path, Some(file), e))
+            })
+            .await?;
+        if cached {
+            stats.cache_hits += 1;
+        } else {
+            stats.network_fetches += 1;
+        }
+        Ok(content)
+    }
+
+    /// Downloads a package's files into `target_dir` in place, leaving
+    /// whatever was fetched so far behind on failure. Progress lines show
+    /// the files under `shown_dir`.
+    async fn download_package_direct(
+        &self,
+        pkg_path: &str,
+        target_dir: &Path,
+        shown_dir: &Path,
+    ) -> Result<PackageStats, PackageManagerError> {
+        let mut stats = PackageStats::default();
+
+        // Create target directory if it doesn't exist
+        if !target_dir.exists() {
+            fs::create_dir_all(target_dir)
+                .map_err(|e| PackageManagerError::DirectoryCreation(e.to_string()))?;
+        }
+
+        let files = self.cached_file_list(pkg_path, &mut stats).await?;
+        let mut charge = self.downloaded.charge(self.limits.max_total_size);
+
+        // keep what is about to be overwritten
+        if let Some(trash) = &self.trash {
+            let files: Vec<String> = files.iter().map(|f| f.trim().to_string()).collect();
+            trash.save_files(pkg_path, target_dir, &files)?;
+        }
+
+        // for each file, fetch content via cache or RPC
+        for file in files {
+            self.cancellation.check()?;
+            let trimmed = file.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            let content = self.cached_file(pkg_path, trimmed, &mut stats).await?;
+
+            let size = content.len() as u64;
+            self.limits
+                .check_file(trimmed, size, stats.bytes + size)
+                .and_then(|()| charge.add(trimmed, size))
+                .map_err(|e| limit_exceeded(pkg_path, e))?;
+
+            // write to disk
+            let target = safe_join(target_dir, trimmed).map_err(|e| unsafe_path(pkg_path, e))?;
+            tracing::info_span!("write").in_scope(|| -> std::io::Result<()> {
+                if let Some(p) = target.parent() {
+                    fs::create_dir_all(p)?;
+                }
+                write_file(&target, content.as_bytes(), self.fsync)
+            })?;
+            stats.files += 1;
+            stats.bytes += size;
+            if !self.quiet {
+                println!("Downloaded: {}", shown_dir.join(trimmed).display());
+            }
+        }
+
+        charge.keep();
+        Ok(stats)
+    }
+
+    /// Stores the files of `pkg_path` in the cache as if they had just been
+    /// downloaded, so later runs find them without the network
+    pub async fn seed_cache(
+        &self,
+        pkg_path: &str,
+        files: &BTreeMap<String, String>,
+    ) -> Result<(), PackageManagerError> {
+        Ok(self
+            .cache
+            .seed_package(pkg_path, self.pinned_height(pkg_path), files)
+            .await?)
+    }
+
+    /// The cache in front of the RPC endpoint
+    pub fn cache(&self) -> &HybridCache {
+        &self.cache
+    }
+
+    /// Returns true if `dir` already holds an unmodified copy of `pkg_path`.
+    ///
+    /// Files are compared against the locked hashes when an entry is given,
+    /// which must also be locked at the height `pkg_path` is pinned at,
+    /// otherwise against the cached file contents. Never touches the network:
+    /// without a lock entry or a warm cache the package counts as changed.
+    pub async fn is_unchanged(
+        &self,
+        pkg_path: &str,
+        dir: &Path,
+        locked: Option<&LockedPackage>,
+    ) -> Result<bool, PackageManagerError> {
+        if !dir.is_dir() {
+            return Ok(false);
+        }
+
+        // the lock and the cache can't tell whether the source was edited
+        if let Some(source) = self.source_dir(pkg_path).await? {
+            let source = source.as_path();
+            for file in local_package_files(source)? {
+                let copied = fs::read(dir.join(&file)).ok();
+                if copied.is_none() || copied != fs::read(source.join(&file)).ok() {
+                    return Ok(false);
+                }
+            }
+            return Ok(true);
+        }
+
+        if let Some(locked) = locked {
+            // a copy of another height isn't the one asked for, however intact
+            return Ok(locked.height == self.pinned_height(pkg_path) && locked.is_intact(dir));
+        }
+
+        let Some(raw) = self
+            .cached_entry(pkg_path, &self.files_key(pkg_path))
+            .await?
+        else {
+            return Ok(false);
+        };
+        let files: Vec<String> = serde_json::from_str(&raw)?;
+
+        for file in files {
+            let trimmed = file.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            let content_key = self.content_key(pkg_path, trimmed);
+            let Some(cached) = self.cache.get(&content_key).await? else {
+                return Ok(false);
+            };
+            let Ok(path) = safe_join(dir, trimmed) else {
+                return Ok(false);
+            };
+            match fs::read(path) {
+                Ok(on_disk) if hash_content(&on_disk) == hash_content(cached.as_bytes()) => {}
+                _ => return Ok(false),
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Fetches the current files of a package from the RPC endpoint and
+    /// returns its package hash (see [hash_files]).
+    ///
+    /// The cache is bypassed, then refreshed with what was fetched, so a
+    /// following download writes exactly the hashed content.
+    pub async fn fetch_remote_hash(&self, pkg_path: &str) -> Result<String, PackageManagerError> {
+        let hashes = self
+            .fetch_remote_files(pkg_path)
+            .await?
+            .iter()
+            .map(|(file, content)| (file.clone(), hash_content(content.as_bytes())))
+            .collect();
+        Ok(hash_files(&hashes))
+    }
+
+    /// Fetches the current content of every file of a package, by file
+    /// name, bypassing and then refreshing the cache like
+    /// [PackageManager::fetch_remote_hash]
+    pub async fn fetch_remote_files(
+        &self,
+        pkg_path: &str,
+    ) -> Result<BTreeMap<String, String>, PackageManagerError> {
+        let (files, height) = self.list_package(pkg_path).await?;
+        if self.is_sourced(pkg_path) {
+            let mut contents = BTreeMap::new();
+            for file in files {
+                let content = self.get_file_content(pkg_path, &file).await?;
+                contents.insert(file, content);
+            }
+            return Ok(contents);
+        }
+        let mut contents = BTreeMap::new();
+        for file in &files {
+            self.cancellation.check()?;
+            let content = self.get_file_content(pkg_path, file).await?;
+            self.cache
+                .set(&self.content_key(pkg_path, file), &content)
+                .await?;
+            contents.insert(file.clone(), content);
+        }
+        self.cache
+            .set(&self.files_key(pkg_path), &serde_json::to_string(&files)?)
+            .await?;
+        self.record_fetched(pkg_path, height).await?;
+        Ok(contents)
+    }
+
+    /// Computes what downloading `roots` under `target_dir` would write,
+    /// with their dependencies when `resolve_deps` is set, without touching
+    /// the target.
+    ///
+    /// Packages are laid out like real downloads and listed dependencies
+    /// first. Each file's size comes from its content, read from the cache
+    /// or queried.
+    #[tracing::instrument(name = "plan", skip(self))]
+    pub async fn plan_download(
+        &self,
+        roots: &[&str],
+        target_dir: &Path,
+        resolve_deps: bool,
+    ) -> Result<DownloadPlan, PackageManagerError> {
+        let packages: Vec<String> = if resolve_deps {
+            let closure = self.resolve_dependencies(roots).await?;
+            closure.deployment_waves().into_iter().flatten().collect()
+        } else {
+            roots.iter().map(|root| root.to_string()).collect()
+        };
+        self.layout
+            .check(target_dir, packages.iter().map(String::as_str))?;
+
+        let mut plan = DownloadPlan::default();
+        for pkg_path in packages {
+            self.cancellation.check()?;
+            let dir = self.layout.package_dir(target_dir, &pkg_path);
+            let files = match self
+                .cached_entry(&pkg_path, &self.files_key(&pkg_path))
+                .await?
+            {
+                Some(raw) => serde_json::from_str(&raw)?,
+                None => self
+                    .get_package_files(&pkg_path)
+                    .await
+                    .map_err(|e| PackageManagerError::download(&pkg_path, None, e))?,
+            };
 
-        Self {
-            rpc_endpoint: endpoint,
-            http_client,
-            cache: Arc::new(cache),
+            let mut planned = Vec::with_capacity(files.len());
+            for file in files {
+                let name = file.trim().to_string();
+                if name.is_empty() {
+                    continue;
+                }
+                let path = safe_join(&dir, &name).map_err(|e| unsafe_path(&pkg_path, e))?;
+                let content = match self
+                    .cached_entry(&pkg_path, &self.content_key(&pkg_path, &name))
+                    .await?
+                {
+                    Some(content) => content,
+                    None => self
+                        .get_file_content(&pkg_path, &name)
+                        .await
+                        .map_err(|e| PackageManagerError::download(&pkg_path, Some(&name), e))?,
+                };
+                planned.push(PlannedFile {
+                    action: FileAction::for_file(&path, &content),
+                    bytes: content.len() as u64,
+                    name,
+                });
+            }
+            plan.packages.push(PlannedPackage {
+                package: pkg_path,
+                target_dir: dir,
+                files: planned,
+            });
         }
+        plan.selections =
+            self.selections_of(|path| plan.packages.iter().any(|p| p.package == path));
+        Ok(plan)
     }
 
-    /// Returns the RPC endpoint
-    pub fn rpc_endpoint(&self) -> &str {
-        &self.rpc_endpoint
+    /// Finds `roots` and everything they import, analyzing up to the
+    /// default parallelism of packages at once
+    pub async fn resolve_dependencies(
+        &self,
+        roots: &[&str],
+    ) -> Result<DependencyClosure, PackageManagerError> {
+        let max_concurrent = ParallelDownloadOptions::default().max_concurrent;
+        self.resolve_all_dependencies(roots, max_concurrent, |_, _| {})
+            .await
     }
 
-    /// Downloads a package and its files to the target directory
-    pub async fn download_package(
-        &self,
-        pkg_path: &str,
-        target_dir: &Path,
-    ) -> Result<(), PackageManagerError> {
-        // Create target directory if it doesn't exist
-        if !target_dir.exists() {
-            fs::create_dir_all(target_dir)
-                .map_err(|e| PackageManagerError::DirectoryCreation(e.to_string()))?;
+    /// Fills the cache with the files of `roots` and everything they
+    /// import, without writing any, so later downloads of them are served
+    /// from the cache, even offline ones.
+    ///
+    /// Packages are fetched up to the default parallelism at once and
+    /// listed dependencies first.
+    #[tracing::instrument(name = "prefetch", skip(self))]
+    pub async fn prefetch(&self, roots: &[&str]) -> Result<PrefetchSummary, PackageManagerError> {
+        let max_concurrent = ParallelDownloadOptions::default().max_concurrent;
+        let closure = self.resolve_dependencies(roots).await?;
+        let packages: Vec<String> = closure.deployment_waves().into_iter().flatten().collect();
+
+        let mut results = stream::iter(packages)
+            .map(|pkg_path| async move {
+                let result = self.prefetch_package(&pkg_path).await;
+                (pkg_path, result)
+            })
+            .buffered(max_concurrent.max(1));
+        let mut summary = PrefetchSummary::default();
+        while let Some((package, result)) = results.next().await {
+            let stats = result?;
+            summary.packages.push(PrefetchedPackage {
+                package,
+                files: stats.files,
+                bytes: stats.bytes,
+                cached: stats.cache_hits,
+                fetched: stats.network_fetches,
+            });
         }
+        Ok(summary)
+    }
 
-        let files_key = format!("files:{}", pkg_path);
-        let files: Vec<String> = if let Some(raw) = self.cache.get(&files_key).await? {
-            serde_json::from_str(&raw)?
-        } else {
-            let list = self
-                .get_package_files(pkg_path)
-                .await
-                .map_err(|e| PackageManagerError::PackageFiles(e.to_string()))?;
-            let serialized = serde_json::to_string(&list)?;
-            self.cache.set(&files_key, &serialized).await?;
-            list
-        };
-
-        // for each file, fetch content via cache or RPC
-        for file in files {
+    /// Caches the file list and files of `pkg_path` like
+    /// [PackageManager::download_package_direct] does, writing nothing
+    async fn prefetch_package(&self, pkg_path: &str) -> Result<PackageStats, PackageManagerError> {
+        let mut stats = PackageStats::default();
+        for file in self.cached_file_list(pkg_path, &mut stats).await? {
+            self.cancellation.check()?;
             let trimmed = file.trim();
             if trimmed.is_empty() {
                 continue;
             }
-            let file_path = format!("{}/{}", pkg_path, trimmed);
-            let content_key = format!("file:{}", file_path);
-            let content = if let Some(raw) = self.cache.get(&content_key).await? {
-                raw
-            } else {
-                let cnt = self.get_file_content(&file_path).await.map_err(|e| {
-                    PackageManagerError::FileContent {
-                        file: file.clone(),
-                        error: e.to_string(),
-                    }
-                })?;
-                self.cache.set(&content_key, &cnt).await?;
-                cnt
-            };
-
-            // write to disk
-            let target = target_dir.join(&file);
-            if let Some(p) = target.parent() {
-                fs::create_dir_all(p)?;
-            }
-            fs::write(&target, &content)?;
-            println!("Downloaded: {}", target.display());
+            let content = self.cached_file(pkg_path, trimmed, &mut stats).await?;
+            stats.files += 1;
+            stats.bytes += content.len() as u64;
         }
-
-        Ok(())
+        Ok(stats)
     }
 
-    /// Downloads a package atomically to prevent partial downloads
+    /// Downloads a package atomically to prevent partial downloads.
+    ///
+    /// Files go to a temporary directory next to the target, on the same
+    /// filesystem. The current copy is then moved aside, to the trash if
+    /// there is one, and the new one renamed into place, so the target is
+    /// only missing between two renames; if the new copy can't be moved in,
+    /// the old one is put back. Other gget processes writing to
+    /// `target_dir` wait for this one to finish.
     pub async fn download_package_atomic(
         &self,
         pkg_path: &str,
         target_dir: &Path,
-    ) -> Result<(), PackageManagerError> {
-        use std::time::{SystemTime, UNIX_EPOCH};
-
-        // create a unique temp dir name
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_nanos();
-        let temp_dir_name = format!(
-            "{}_tmp_{}",
-            target_dir
-                .file_name()
-                .and_then(|s| s.to_str())
-                .unwrap_or("package"),
-            timestamp,
-        );
+    ) -> Result<PackageStats, PackageManagerError> {
+        let _lock = self.lock_target(target_dir).await?;
+        self.replace_package(pkg_path, target_dir).await
+    }
 
-        let temp_dir = if let Some(parent) = target_dir.parent() {
-            parent.join(temp_dir_name)
-        } else {
-            PathBuf::from(temp_dir_name)
-        };
+    /// [PackageManager::download_package_atomic], for callers that already
+    /// hold the lock of `target_dir`
+    async fn replace_package(
+        &self,
+        pkg_path: &str,
+        target_dir: &Path,
+    ) -> Result<PackageStats, PackageManagerError> {
+        let temp_dir = self.staging_dir(target_dir);
 
         // ensure cleanup happens even if download fails
-        // automatically remove temp dir on drop with RAII pattern
-        struct TempDirGuard(PathBuf);
-        impl Drop for TempDirGuard {
-            fn drop(&mut self) {
-                if self.0.exists() {
-                    let _ = std::fs::remove_dir_all(&self.0);
-                }
-            }
-        }
-
         let _guard = TempDirGuard(temp_dir.clone());
 
         // download to temp dir first
-        self.download_package(pkg_path, &temp_dir).await?;
+        let stats = self
+            .download_package_direct(pkg_path, &temp_dir, target_dir)
+            .await?;
 
-        // if target dir exists, remove it
-        if target_dir.exists() {
-            std::fs::remove_dir_all(target_dir).map_err(PackageManagerError::Io)?;
+        // refuse tampered content before it can replace the current target
+        if let Some(verifier) = &self.verifier {
+            verifier.verify(pkg_path, &temp_dir)?;
         }
 
-        // create parent dir if it doesn't exist
-        if let Some(p) = target_dir.parent() {
-            if !p.exists() {
-                std::fs::create_dir_all(p)
-                    .map_err(|e| PackageManagerError::DirectoryCreation(e.to_string()))?;
+        // move the current copy out of the way, keeping it until the new
+        // one is in place
+        let mut trashed = false;
+        let aside = match &self.trash {
+            Some(trash) => {
+                trashed = trash.discard(pkg_path, target_dir)?.is_some();
+                None
             }
-        }
+            None if target_dir.exists() => {
+                let aside = staging_dir(None, target_dir).with_extension("old");
+                fs::rename(target_dir, &aside)?;
+                Some(aside)
+            }
+            None => None,
+        };
 
-        // atomically move from temp to final destination
-        std::fs::rename(&temp_dir, target_dir).map_err(PackageManagerError::Io)?;
+        if let Err(e) = install(&temp_dir, target_dir) {
+            if let Some(aside) = &aside {
+                fs::rename(aside, target_dir)?;
+            } else if let Some(trash) = self.trash.as_ref().filter(|_| trashed) {
+                trash.restore(pkg_path, Some(target_dir))?;
+            }
+            return Err(PackageManagerError::Io(e));
+        }
+        if let Some(aside) = aside {
+            fs::remove_dir_all(aside)?;
+        }
 
-        Ok(())
+        Ok(stats)
     }
 
-    #[allow(dead_code)]
+    /// Finds `roots` and everything they import, breadth first.
+    ///
+    /// Up to `max_concurrent` packages of each level are analyzed at once,
+    /// sharing one [DependencyResolver]; results are taken in queue order,
+    /// so who pulled in whom doesn't depend on which query answers first.
+    /// `found` is called with each package as soon as it is analyzed, so
+    /// callers can act on it while the rest is still being resolved.
+    #[tracing::instrument(name = "resolve", skip(self, found))]
     async fn resolve_all_dependencies(
         &self,
-        root_pkg: &str,
-    ) -> Result<HashMap<String, String>, PackageManagerError> {
-        let mut all_deps = HashMap::new();
-        let mut to_analyze = VecDeque::new();
+        roots: &[&str],
+        max_concurrent: usize,
+        mut found: impl FnMut(&DependencyClosure, &str),
+    ) -> Result<DependencyClosure, PackageManagerError> {
+        let resolver = DependencyResolver::new()?;
+        let mut closure = DependencyClosure::from_roots(roots);
+        let mut level: Vec<String> = roots.iter().map(|r| r.to_string()).collect();
         let mut analyzed = HashSet::new();
 
-        to_analyze.push_back(root_pkg.to_string());
+        while !level.is_empty() {
+            self.cancellation.check()?;
 
-        while let Some(pkg_path) = to_analyze.pop_front() {
-            if analyzed.contains(&pkg_path) {
-                continue;
+            let mut pending = Vec::new();
+            for pkg_path in level {
+                if !analyzed.insert(pkg_path.clone()) {
+                    continue;
+                }
+                if !self.is_local(&pkg_path) && self.check_policy(&pkg_path, &mut closure)? {
+                    pending.push(pkg_path);
+                }
             }
 
-            let package_dep = self.analyze_package_dependencies(&pkg_path).await?;
-
-            // add new deps to analysis queue
-            for import in &package_dep.imports {
-                if !analyzed.contains(import) && !to_analyze.contains(import) {
-                    to_analyze.push_back(import.clone());
+            let mut results = stream::iter(pending)
+                .map(|pkg_path| {
+                    let resolver = &resolver;
+                    async move {
+                        let result = self.analyze_package_dependencies(resolver, &pkg_path).await;
+                        (pkg_path, result)
+                    }
+                })
+                .buffered(max_concurrent.max(1));
+
+            let mut next = Vec::new();
+            while let Some((pkg_path, result)) = results.next().await {
+                let package_dep = match result {
+                    Ok(dep) => dep,
+                    Err(e @ PackageManagerError::Cancelled(_)) => return Err(e),
+                    Err(e) => {
+                        return Err(PackageManagerError::Resolution {
+                            package: pkg_path,
+                            source: Box::new(e),
+                        })
+                    }
+                };
+
+                // add new deps to the next level, remembering who pulled them in
+                for import in package_dep.imports.iter().chain(&package_dep.test_imports) {
+                    if !analyzed.contains(import) && !next.contains(import) {
+                        next.push(import.clone());
+                        closure
+                            .parents
+                            .entry(import.clone())
+                            .or_insert_with(|| pkg_path.clone());
+                    }
                 }
-            }
 
-            // add to result map
-            all_deps.insert(pkg_path.clone(), package_dep.name);
-            analyzed.insert(pkg_path);
+                // add to result map
+                closure.packages.insert(pkg_path.clone(), package_dep);
+                found(&closure, &pkg_path);
+            }
+            level = next;
         }
 
-        Ok(all_deps)
+        Ok(closure)
     }
 
-    #[allow(dead_code)]
+    /// Finds what the files of a package import.
+    ///
+    /// The result is cached by the hash of the files, so a package that
+    /// hasn't changed since an earlier run isn't parsed again, unless
+    /// refreshing.
     async fn analyze_package_dependencies(
         &self,
+        resolver: &DependencyResolver,
         pkg_path: &str,
     ) -> Result<PackageDependency, PackageManagerError> {
-        let files = self.get_package_files(pkg_path).await?;
-        let mut all_imports = HashSet::new();
-
-        let mut resolver = DependencyResolver::new()?;
-
+        // offline, the sources can only come from the cache, e.g. filled
+        // by PackageManager::prefetch
+        let mut cached = PackageStats::default();
+        let files = match self.offline {
+            true => self.cached_file_list(pkg_path, &mut cached).await?,
+            false => self.get_package_files(pkg_path).await?,
+        };
+        let mut sources = Vec::new();
         for file in files {
             let trimmed = file.trim();
             if trimmed.is_empty() || !trimmed.ends_with(".gno") {
                 continue;
             }
+            if self.test_files == TestFiles::Exclude && is_test_file(trimmed) {
+                continue;
+            }
 
-            let file_path = format!("{}/{}", pkg_path, trimmed);
-            let content = self.get_file_content(&file_path).await?;
-
-            // reuse the same resolver instance for all files in the same package
-            let (_, imports) = resolver.extract_dependencies(&content)?;
-            all_imports.extend(imports);
+            let content = match self.offline {
+                true => self.cached_file(pkg_path, trimmed, &mut cached).await?,
+                false => self.get_file_content(pkg_path, trimmed).await?,
+            };
+            sources.push((trimmed.to_string(), content));
         }
 
-        Ok(PackageDependency {
+        let mut package = PackageDependency {
             name: pkg_path.to_string(),
-            imports: all_imports,
+            imports: HashSet::new(),
+            test_imports: HashSet::new(),
             instability: 0.0,
-        })
+        };
+
+        let key = imports_key(pkg_path, &sources);
+        if !self.refresh {
+            let cached = self
+                .cache
+                .get(&key)
+                .await?
+                .and_then(|value| serde_json::from_str(&value).ok());
+            if let Some((imports, test_imports)) = cached {
+                package.imports = imports;
+                package.test_imports = test_imports;
+                return Ok(package);
+            }
+        }
+
+        for (name, content) in &sources {
+            let (_, imports) = resolver.extract_dependencies(content)?;
+            package.add_imports(name, imports);
+        }
+        let value = serde_json::to_string(&(&package.imports, &package.test_imports))?;
+        self.cache.set(&key, &value).await?;
+
+        Ok(package)
     }
 
-    pub async fn validate_package(&self, target_dir: &Path) -> Result<(), PackageManagerError> {
-        // when users deploy packages to the chain, the `gnokey` only recognizes and deploys
-        // `gno.mod` and `*.gno` files. Therefore, this check is actually meaningless.
-        let mut resolver = DependencyResolver::new()?;
+    /// Checks every `.gno` file under `target_dir` for syntax errors and
+    /// for Go-only constructs that would fail on deploy, and package
+    /// directories for a gno.mod and for files gnokey wouldn't deploy.
+    ///
+    /// Only unreadable or undecodable files fail; what else is wrong is
+    /// in the report, see [ValidationReport::into_result]. What each file
+    /// declares is cached by content hash, so files that haven't changed
+    /// since an earlier run are only read, not parsed, to find its package.
+    #[tracing::instrument(name = "validate", skip(self))]
+    pub async fn validate_package(
+        &self,
+        target_dir: &Path,
+    ) -> Result<ValidationReport, PackageManagerError> {
+        let invalid = |source: ValidationError| PackageManagerError::Validation {
+            dir: target_dir.to_path_buf(),
+            source,
+        };
 
-        // Use the new directory-based method to validate all .gno files recursively
-        let packages = resolver.extract_dependencies_from_directory(target_dir)?;
+        self.extract_dependencies_from_directory(target_dir)
+            .await
+            .map_err(|e| match e {
+                PackageManagerError::Dependency(e) => invalid(e.into()),
+                other => other,
+            })?;
+
+        let mut report = ValidationReport::scan(target_dir, self.strict)?;
+        let syntax_errors = SyntaxChecker::new()?
+            .check_dir(target_dir)
+            .map_err(|e| invalid(e.into()))?;
+        let warnings = CompatChecker::new()?
+            .check_dir(target_dir)
+            .map_err(|e| invalid(e.into()))?;
+        report.diagnostics = syntax_errors
+            .into_iter()
+            .map(Diagnostic::from)
+            .chain(warnings.into_iter().map(Diagnostic::from))
+            .collect();
+        report
+            .diagnostics
+            .sort_by(|a, b| (&a.file, a.line, a.column).cmp(&(&b.file, b.line, b.column)));
+        Ok(report)
+    }
 
-        if packages.is_empty() {
-            return Err(PackageManagerError::PackageFiles(
-                "No .gno files found".to_string(),
-            ));
+    /// Like [DependencyResolver::extract_dependencies_from_directory],
+    /// reusing what earlier parses of the same content found
+    pub async fn extract_dependencies_from_directory(
+        &self,
+        dir: &Path,
+    ) -> Result<HashMap<String, PackageDependency>, PackageManagerError> {
+        let mut resolver = None;
+        let mut packages: HashMap<String, PackageDependency> = HashMap::new();
+        for path in gno_files(dir)? {
+            let file_name = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or_default()
+                .to_string();
+            if self.test_files == TestFiles::Exclude && is_test_file(&file_name) {
+                continue;
+            }
+            let content = fs::read_to_string(&path)
+                .map_err(|e| DependencyError::IoError(format!("Failed to read file: {}", e)))?;
+
+            let key = parse_key(&content);
+            let cached = self
+                .cache
+                .get(&key)
+                .await?
+                .and_then(|value| serde_json::from_str(&value).ok());
+            let (package_name, imports): (String, HashSet<String>) = match cached {
+                Some(parsed) => parsed,
+                None => {
+                    let resolver = match &mut resolver {
+                        Some(resolver) => resolver,
+                        None => resolver.insert(DependencyResolver::new()?),
+                    };
+                    let parsed = resolver.extract_dependencies(&content)?;
+                    self.cache
+                        .set(&key, &serde_json::to_string(&parsed)?)
+                        .await?;
+                    parsed
+                }
+            };
+
+            packages
+                .entry(package_name.clone())
+                .or_insert_with(|| PackageDependency {
+                    name: package_name,
+                    imports: HashSet::new(),
+                    test_imports: HashSet::new(),
+                    instability: 0.0,
+                })
+                .add_imports(&file_name, imports);
         }
 
-        // All files were successfully parsed if we got here
-        Ok(())
+        Ok(packages)
     }
 
-    /// Retrieves the list of files in a package
+    /// Retrieves the list of files in a package, with the files of its
+    /// subdirectories as paths relative to the package root.
+    ///
+    /// Subdirectories holding a [GNOMOD_NAME] are packages of their own
+    /// and are left to be downloaded as such.
+    ///
+    /// Packages the node reports missing are remembered for the negative
+    /// TTL and fail without a query meanwhile, unless refreshing.
     async fn get_package_files(&self, pkg_path: &str) -> Result<Vec<String>, PackageManagerError> {
-        let encoded_path = general_purpose::STANDARD.encode(pkg_path.as_bytes());
-        let data = self.query_rpc(&encoded_path).await?;
+        Ok(self.list_package(pkg_path).await?.0)
+    }
 
-        // Decode the response data
-        let decoded_data = general_purpose::STANDARD.decode(&data)?;
-        let files_list = String::from_utf8_lossy(&decoded_data);
+    /// Lists the files of a package like [PackageManager::get_package_files],
+    /// along with the height its root directory was listed at
+    async fn list_package(
+        &self,
+        pkg_path: &str,
+    ) -> Result<(Vec<String>, Option<u64>), PackageManagerError> {
+        if let Some(dir) = self.source_dir(pkg_path).await? {
+            return list_local_package(pkg_path, &dir);
+        }
+        match self.list_chain_package(pkg_path).await {
+            Err(e) => match self.fall_back_to_registry(pkg_path, &e) {
+                Some(dir) => list_local_package(pkg_path, &dir),
+                None => Err(e),
+            },
+            listed => listed,
+        }
+    }
 
-        // Split the file list and filter out empty strings
-        let files: Vec<String> = files_list
-            .lines()
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-            .collect();
+    /// Lists the files of a package on the chain, see
+    /// [PackageManager::list_package]
+    async fn list_chain_package(
+        &self,
+        pkg_path: &str,
+    ) -> Result<(Vec<String>, Option<u64>), PackageManagerError> {
+        let files_key = self.files_key(pkg_path);
+        if !self.refresh && !self.negative_ttl.is_zero() {
+            if let Some(reason) = self.cache.get_negative(&files_key).await? {
+                return Err(PackageManagerError::Rpc(reason));
+            }
+        }
 
-        Ok(files)
+        let (entries, height) = match self.list_dir(pkg_path, "").await {
+            Ok(listed) => listed,
+            Err(error) => {
+                if let PackageManagerError::Rpc(reason) = &error {
+                    if error.kind() == ErrorKind::NotFound && !self.negative_ttl.is_zero() {
+                        self.cache
+                            .set_negative(&files_key, reason, self.negative_ttl)
+                            .await?;
+                    }
+                }
+                return Err(error);
+            }
+        };
+
+        let mut files = Vec::new();
+        let mut listed = VecDeque::from([entries]);
+        while let Some(entries) = listed.pop_front() {
+            for entry in entries {
+                if !entry.ends_with('/') {
+                    files.push(entry);
+                    continue;
+                }
+                self.cancellation.check()?;
+                let (subdir, _) = self.list_dir(pkg_path, &entry).await?;
+                if !subdir.contains(&format!("{}{}", entry, GNOMOD_NAME)) {
+                    listed.push_back(subdir);
+                }
+            }
+        }
+
+        check_file_list(pkg_path, &files)?;
+        Ok((files, height))
+    }
+
+    /// Lists the directory `dir` of a package (`""` for its root, else
+    /// ending with `/`), see [parse_file_listing], along with the height
+    /// it was listed at. Entries are relative to the package root.
+    async fn list_dir(
+        &self,
+        pkg_path: &str,
+        dir: &str,
+    ) -> Result<(Vec<String>, Option<u64>), PackageManagerError> {
+        let target = match dir.strip_suffix('/') {
+            Some(dir) => format!("{}/{}", pkg_path, dir),
+            None => pkg_path.to_string(),
+        };
+        let encoded_path = general_purpose::STANDARD.encode(target.as_bytes());
+        let answer = self
+            .query_rpc(QFILE_PATH, &encoded_path, self.pinned_height(pkg_path))
+            .await?;
+
+        // Decode the response data
+        let decoded_data = general_purpose::STANDARD.decode(&answer.data)?;
+        let entries = parse_file_listing(pkg_path, &String::from_utf8_lossy(&decoded_data))?;
+        let entries = entries
+            .into_iter()
+            .map(|entry| format!("{}{}", dir, entry))
+            .collect();
+        Ok((entries, answer.height))
     }
 
     /// Retrieves the content of a specific file
-    async fn get_file_content(&self, file_path: &str) -> Result<String, PackageManagerError> {
+    async fn get_file_content(
+        &self,
+        pkg_path: &str,
+        file: &str,
+    ) -> Result<String, PackageManagerError> {
+        if let Some(dir) = self.source_dir(pkg_path).await? {
+            let path = safe_join(&dir, file).map_err(|e| unsafe_path(pkg_path, e))?;
+            return Ok(fs::read_to_string(path)?);
+        }
+
+        let file_path = format!("{}/{}", pkg_path, file);
         let encoded_path = general_purpose::STANDARD.encode(file_path.as_bytes());
-        let data = self.query_rpc(&encoded_path).await?;
+        let data = self
+            .query_rpc(QFILE_PATH, &encoded_path, self.pinned_height(pkg_path))
+            .await?
+            .data;
 
         // Decode the response data
         let decoded_data = general_purpose::STANDARD.decode(&data)?;
@@ -311,32 +2388,133 @@ impl PackageManager {
         Ok(content)
     }
 
-    /// Sends a query to the RPC endpoint (core function)
-    async fn query_rpc(&self, data: &str) -> Result<String, PackageManagerError> {
+    /// Returns what `realm`'s `Render` function outputs for `path`, the
+    /// markdown gnoweb shows at `realm:path`.
+    ///
+    /// Rendered pages follow the realm's state, so they are never cached.
+    pub async fn render(&self, realm: &str, path: &str) -> Result<String, PackageManagerError> {
+        let target = format!("{}:{}", realm, path);
+        let encoded_target = general_purpose::STANDARD.encode(target.as_bytes());
+        let data = self
+            .query_rpc(QRENDER_PATH, &encoded_target, self.pinned_height(realm))
+            .await?
+            .data;
+
+        let decoded_data = general_purpose::STANDARD.decode(&data)?;
+        Ok(String::from_utf8_lossy(&decoded_data).into_owned())
+    }
+
+    /// Evaluates `expr` in `realm`, say `GetBoardIDFromName("gnolang")`,
+    /// returning the result as the node prints it, e.g. `(1 uint64)`.
+    ///
+    /// The node runs it read-only: whatever it changes is thrown away.
+    pub async fn eval(&self, realm: &str, expr: &str) -> Result<String, PackageManagerError> {
+        let target = format!("{}.{}", realm, expr);
+        let encoded_target = general_purpose::STANDARD.encode(target.as_bytes());
+        let data = self
+            .query_rpc(QEVAL_PATH, &encoded_target, self.pinned_height(realm))
+            .await?
+            .data;
+
+        let decoded_data = general_purpose::STANDARD.decode(&data)?;
+        Ok(String::from_utf8_lossy(&decoded_data).into_owned())
+    }
+
+    /// Lists the exported functions of `pkg_path` with their signatures
+    pub async fn funcs(&self, pkg_path: &str) -> Result<Vec<FuncSignature>, PackageManagerError> {
+        let encoded_path = general_purpose::STANDARD.encode(pkg_path.as_bytes());
+        let data = self
+            .query_rpc(QFUNCS_PATH, &encoded_path, self.pinned_height(pkg_path))
+            .await?
+            .data;
+
+        let decoded_data = general_purpose::STANDARD.decode(&data)?;
+        Ok(serde_json::from_slice(&decoded_data)?)
+    }
+
+    /// Sends a query to the RPC endpoint (core function).
+    ///
+    /// Concurrent identical queries are sent once, the others wait for it
+    /// and share its response.
+    #[tracing::instrument(name = "rpc", skip_all, fields(request_id = tracing::field::Empty))]
+    async fn query_rpc(
+        &self,
+        path: &str,
+        data: &str,
+        height: Option<u64>,
+    ) -> Result<Answer, PackageManagerError> {
+        if self.offline {
+            return Err(PackageManagerError::Offline);
+        }
+
+        let key = match height {
+            Some(height) => format!("{} {}@{}", path, data, height),
+            None => format!("{} {}", path, data),
+        };
+        self.cancellation
+            .run(self.inflight.run(
+                &key,
+                self.send_query(path, data, height),
+                |result| result.as_ref().cloned().map_err(CoalescedError::from),
+                |shared| shared.map_err(PackageManagerError::Coalesced),
+            ))
+            .await?
+    }
+
+    /// Sends a query to the RPC endpoint and unwraps its response data
+    async fn send_query(
+        &self,
+        path: &str,
+        data: &str,
+        height: Option<u64>,
+    ) -> Result<Answer, PackageManagerError> {
+        let id = next_request_id();
+        tracing::Span::current().record("request_id", id);
         let request = RpcRequest {
             jsonrpc: "2.0".to_string(),
-            id: 1,
+            id,
             method: "abci_query".to_string(),
             params: RpcParams {
-                path: "vm/qfile".to_string(),
+                path: path.to_string(),
                 data: data.to_string(),
+                height: height.map(|h| h.to_string()),
             },
         };
 
-        let response = self
-            .http_client
-            .post(&self.rpc_endpoint)
-            .json(&request)
-            .send()
-            .await?;
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire_request().await;
+        }
 
-        let rpc_response: RpcResponse = response.json().await?;
+        let body = self.rpc_client.send(&request).await?;
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.record_bytes(body.len()).await;
+        }
+        let rpc_response: RpcResponse = serde_json::from_slice(&body)?;
+        if let Some(error) = rpc_response.error {
+            return Err(PackageManagerError::rpc_protocol(error, id));
+        }
+        if !rpc_response.answers(id) {
+            return Err(PackageManagerError::RpcIdMismatch {
+                expected: id,
+                got: rpc_response.id,
+            });
+        }
+        let Some(result) = rpc_response.result else {
+            let missing: serde_json::Error = serde::de::Error::missing_field("result");
+            return Err(missing.into());
+        };
 
-        if let Some(error) = rpc_response.result.response.response_base.error {
-            return Err(PackageManagerError::Rpc(format!("RPC error: {}", error)));
+        if let Some(error) = result.response.response_base.error {
+            return Err(PackageManagerError::Rpc(format!(
+                "RPC error: {} (request {})",
+                error, id
+            )));
         }
 
-        Ok(rpc_response.result.response.response_base.data)
+        Ok(Answer {
+            data: result.response.response_base.data,
+            height: result.response.height.parse().ok(),
+        })
     }
 
     /// Download multiple packages concurrently
@@ -347,39 +2525,200 @@ impl PackageManager {
         target_dir: &Path,
         options: ParallelDownloadOptions,
     ) -> Result<DownloadSummary, PackageManagerError> {
-        let download_manager = DownloadManager::new(options.max_concurrent);
-
-        // Queue all packages
-        for (idx, package) in packages.iter().enumerate() {
-            let task = DownloadTask {
+        self.layout.check(target_dir, packages.iter().copied())?;
+        let depths = self
+            .dependency_depths(&packages, options.max_concurrent)
+            .await?;
+        let tasks = packages
+            .iter()
+            .map(|package| DownloadTask {
                 package_id: package.to_string(),
                 package_path: package.to_string(),
-                target_dir: target_dir.join(package),
-                priority: (packages.len() - idx) as u8, // Earlier packages have higher priority
+                target_dir: self.layout.package_dir(target_dir, package),
+                // leaves first, so dependencies land before what imports them
+                priority: u8::MAX.saturating_sub(depths[*package].min(u8::MAX as usize) as u8),
                 retry_config: options.retry_config.clone(),
+                ..Default::default()
+            })
+            .collect();
+
+        self.rate_limited(&options)
+            .download_waves_parallel(vec![tasks], target_dir, options)
+            .await
+    }
+
+    /// Depth of each of `packages` in the imports between them: 0 for
+    /// those importing none of the others, else one more than the deepest
+    /// package they import.
+    ///
+    /// Packages that can't be analyzed count as leaves; their download
+    /// reports why.
+    async fn dependency_depths(
+        &self,
+        packages: &[&str],
+        max_concurrent: usize,
+    ) -> Result<HashMap<String, usize>, PackageManagerError> {
+        if packages.len() < 2 {
+            return Ok(packages.iter().map(|p| (p.to_string(), 0)).collect());
+        }
+
+        let resolver = DependencyResolver::new()?;
+        let results: Vec<_> = stream::iter(packages)
+            .map(|pkg_path| self.analyze_package_dependencies(&resolver, pkg_path))
+            .buffered(max_concurrent.max(1))
+            .collect()
+            .await;
+
+        let mut closure = DependencyClosure::from_roots(packages);
+        for (pkg_path, result) in packages.iter().zip(results) {
+            let package = match result {
+                Ok(package) => package,
+                Err(e @ PackageManagerError::Cancelled(_)) => return Err(e),
+                Err(_) => PackageDependency {
+                    name: pkg_path.to_string(),
+                    imports: HashSet::new(),
+                    test_imports: HashSet::new(),
+                    instability: 0.0,
+                },
             };
-            download_manager
-                .queue_download(task)
-                .await
-                .map_err(|e| PackageManagerError::Rpc(e.to_string()))?;
+            closure.packages.insert(pkg_path.to_string(), package);
+        }
+
+        Ok(closure
+            .deployment_waves()
+            .into_iter()
+            .enumerate()
+            .flat_map(|(depth, wave)| wave.into_iter().map(move |p| (p, depth)))
+            .collect())
+    }
+
+    /// Runs prepared download tasks through a [DownloadManager], one wave
+    /// after another (see [DownloadManager::process_waves]).
+    ///
+    /// `target_dir` is the directory the tasks' packages are laid out under.
+    async fn download_waves_parallel(
+        &self,
+        waves: Vec<Vec<DownloadTask>>,
+        target_dir: &Path,
+        options: ParallelDownloadOptions,
+    ) -> Result<DownloadSummary, PackageManagerError> {
+        let download_manager = self.download_manager(&options);
+        let mut up_to_date = Vec::new();
+        let cache_before = self.cache.stats();
+        let lock = self.previous_lock(target_dir, &options);
+
+        let mut pending = Vec::with_capacity(waves.len());
+        for wave in waves {
+            let mut tasks = Vec::with_capacity(wave.len());
+            for task in wave {
+                if self.is_up_to_date(&task, lock.as_ref(), &options).await? {
+                    up_to_date.push(task.package_path);
+                    continue;
+                }
+                tasks.push(task);
+            }
+            pending.push(tasks);
+        }
+
+        // Process queue with progress tracking
+        let result = download_manager
+            .process_waves(pending, self.download_fn())
+            .await;
+        self.finish_summary(result, up_to_date, &cache_before, &options)
+    }
+
+    /// A [DownloadManager] configured for a run with `options`
+    fn download_manager(&self, options: &ParallelDownloadOptions) -> DownloadManager {
+        let download_manager = DownloadManager::new(options.max_concurrent)
+            .with_timeout(options.timeout)
+            .with_cancellation(self.cancellation.clone());
+        match options.seed {
+            Some(seed) => download_manager.with_seed(seed),
+            None => download_manager,
         }
+    }
+
+    /// The lockfile of an earlier run into `target_dir`, if one can be
+    /// read and the run isn't forced
+    fn previous_lock(
+        &self,
+        target_dir: &Path,
+        options: &ParallelDownloadOptions,
+    ) -> Option<Lockfile> {
+        // an unreadable lockfile just means we can't skip anything
+        if options.force {
+            None
+        } else {
+            Lockfile::load_if_exists(&target_dir.join(LOCKFILE_NAME)).unwrap_or(None)
+        }
+    }
+
+    /// Whether a task's package is already on disk as `lock` recorded it
+    async fn is_up_to_date(
+        &self,
+        task: &DownloadTask,
+        lock: Option<&Lockfile>,
+        options: &ParallelDownloadOptions,
+    ) -> Result<bool, PackageManagerError> {
+        if options.force {
+            return Ok(false);
+        }
+        let locked = lock.and_then(|l| l.get(&task.package_path));
+        self.is_unchanged(&task.package_path, &task.target_dir, locked)
+            .await
+    }
 
-        // Create a closure that captures self for downloading
-        let self_clone = self.clone();
-        let download_fn = move |task: DownloadTask| {
+    /// Downloads a task's package for a [DownloadManager], counting the
+    /// run's total size from zero
+    fn download_fn(
+        &self,
+    ) -> impl Fn(
+        DownloadTask,
+    ) -> futures::future::BoxFuture<'static, Result<PackageStats, DownloadError>>
+           + Send
+           + Sync
+           + 'static {
+        let self_clone = Self {
+            downloaded: TotalSize::default(),
+            ..self.clone()
+        };
+        move |task: DownloadTask| {
             let pm = self_clone.clone();
             Box::pin(async move {
                 pm.download_package(&task.package_path, &task.target_dir)
                     .await
-                    .map_err(|e| DownloadError::PackageManager(e))
-            }) as futures::future::BoxFuture<'static, Result<(), DownloadError>>
-        };
+                    .map_err(|e| match e {
+                        PackageManagerError::Cancelled(_) => DownloadError::Cancelled,
+                        e => DownloadError::PackageManager(e),
+                    })
+            })
+                as futures::future::BoxFuture<'static, Result<PackageStats, DownloadError>>
+        }
+    }
 
-        // Process queue with progress tracking
-        let summary = download_manager
-            .process_queue(download_fn)
-            .await
-            .map_err(|e| PackageManagerError::Rpc(e.to_string()))?;
+    /// Turns what a [DownloadManager] returned into the run's summary,
+    /// printing it if progress is shown
+    fn finish_summary(
+        &self,
+        result: Result<DownloadSummary, DownloadError>,
+        up_to_date: Vec<String>,
+        cache_before: &CacheStats,
+        options: &ParallelDownloadOptions,
+    ) -> Result<DownloadSummary, PackageManagerError> {
+        let mut summary = result.map_err(|e| match e {
+            DownloadError::Cancelled => self
+                .cancellation
+                .check()
+                .err()
+                .unwrap_or(Interrupted::Cancelled)
+                .into(),
+            e => PackageManagerError::Queue(Box::new(e)),
+        })?;
+        // partial results of an interrupted run aren't reported as a summary
+        self.cancellation.check()?;
+        summary.total_packages += up_to_date.len();
+        summary.up_to_date = up_to_date;
+        summary.cache = self.cache.stats().since(cache_before);
 
         // Print summary if progress is enabled
         if options.show_progress {
@@ -396,21 +2735,189 @@ impl PackageManager {
         target_dir: &Path,
         options: ParallelDownloadOptions,
     ) -> Result<DownloadSummary, PackageManagerError> {
-        println!("Analyzing dependencies for {}...", package);
+        self.download_roots_with_deps_parallel(&[package], target_dir, options)
+            .await
+    }
+
+    /// Downloads several root packages and the union of their dependencies,
+    /// recording all of them as roots in a single lockfile.
+    ///
+    /// Other gget processes downloading into `target_dir` with their
+    /// dependencies wait for this one to finish.
+    pub async fn download_roots_with_deps_parallel(
+        &self,
+        roots: &[&str],
+        target_dir: &Path,
+        options: ParallelDownloadOptions,
+    ) -> Result<DownloadSummary, PackageManagerError> {
+        // a disallowed root can't be skipped, so it always fails the run
+        for root in roots {
+            self.policy.check(root)?;
+        }
+
+        // runs into the same directory take turns, from reading the
+        // lockfile to writing it
+        let _lock = self.lock_target(&target_dir.join(LOCKFILE_NAME)).await?;
+        let pm = self.rate_limited(&options);
+
+        if !self.quiet {
+            println!("Analyzing dependencies for {}...", roots.join(", "));
+        }
 
-        // First, analyze all dependencies
-        let all_deps = self.resolve_all_dependencies(package).await?;
+        let download_manager = pm.download_manager(&options);
+        let lock = pm.previous_lock(target_dir, &options);
+        let cache_before = self.cache.stats();
+        // taken before any worker runs, so none stops while resolving
+        let hold = download_manager.hold();
+        let (found_tx, mut found_rx) = tokio::sync::mpsc::unbounded_channel();
+
+        // packages are queued as they are analyzed, while resolution
+        // continues; a task waits for its dependencies to download
+        let resolve = async {
+            let found_tx = found_tx;
+            let closure = pm
+                .resolve_all_dependencies(roots, options.max_concurrent, |closure, pkg| {
+                    let _ = found_tx.send(pm.dependency_task(closure, pkg, target_dir, &options));
+                })
+                .await?;
+            if !self.quiet {
+                println!(
+                    "Found {} packages to download in {} waves",
+                    closure.packages.len(),
+                    closure.deployment_waves().len()
+                );
+            }
+            Ok::<_, PackageManagerError>(closure)
+        };
+        let feed = async {
+            let mut dirs: HashMap<PathBuf, String> = HashMap::new();
+            let mut up_to_date = Vec::new();
+            while let Some(task) = found_rx.recv().await {
+                if let Some(other) = dirs.insert(task.target_dir.clone(), task.package_path.clone())
+                {
+                    self.layout
+                        .check(target_dir, [other.as_str(), task.package_path.as_str()])?;
+                }
+                if pm.is_up_to_date(&task, lock.as_ref(), &options).await? {
+                    download_manager.mark_done(&task.package_path);
+                    up_to_date.push(task.package_path);
+                    continue;
+                }
+                download_manager
+                    .queue_download(task)
+                    .await
+                    .map_err(|e| PackageManagerError::Queue(Box::new(e)))?;
+            }
+            Ok(up_to_date)
+        };
+        let produce = async {
+            let _hold = hold;
+            let produced = tokio::try_join!(resolve, feed);
+            // nothing more is started once resolution has failed
+            if produced.is_err() {
+                download_manager.clear_queue().await;
+            }
+            produced
+        };
 
-        // Convert to package list
-        let mut packages: Vec<&str> = all_deps.keys().map(|s| s.as_str()).collect();
+        let (produced, result) =
+            tokio::join!(produce, download_manager.process_queue(pm.download_fn()));
+        let (closure, mut up_to_date) = produced?;
+        up_to_date.sort();
+        let mut summary = pm.finish_summary(result, up_to_date, &cache_before, &options)?;
+        summary.skipped = closure.skipped.clone();
 
-        // Sort packages for consistent ordering
-        packages.sort();
+        // record what landed on disk so an identical re-run can be skipped
+        if summary.failed.is_empty() {
+            self.write_lockfile(&closure, target_dir)?;
+        }
 
-        println!("Found {} packages to download", packages.len());
+        Ok(summary)
+    }
 
-        // Download all packages in parallel
-        self.download_packages_parallel(packages, target_dir, options)
-            .await
+    /// The download task for `pkg`, just analyzed in `closure`.
+    ///
+    /// It depends on every import resolution will follow, and packages
+    /// already known to import it make it more urgent.
+    fn dependency_task(
+        &self,
+        closure: &DependencyClosure,
+        pkg: &str,
+        target_dir: &Path,
+        options: &ParallelDownloadOptions,
+    ) -> DownloadTask {
+        let dependents = closure
+            .packages
+            .values()
+            .filter(|p| p.imports.contains(pkg))
+            .count();
+        let mut depends_on: Vec<String> = closure.packages[pkg]
+            .imports
+            .iter()
+            .filter(|import| {
+                *import != pkg && !self.is_local(import) && self.policy.check(import).is_ok()
+            })
+            .cloned()
+            .collect();
+        depends_on.sort();
+
+        DownloadTask {
+            package_id: pkg.to_string(),
+            package_path: pkg.to_string(),
+            target_dir: self.layout.package_dir(target_dir, pkg),
+            // blockers first
+            priority: dependents.min(u8::MAX as usize) as u8,
+            retry_config: options.retry_config.for_blocker(dependents),
+            required_by: closure.import_chain(pkg),
+            depends_on,
+            ..Default::default()
+        }
+    }
+
+    /// Returns a manager throttled by the run's rate limit and endpoint
+    /// concurrency, if set
+    fn rate_limited(&self, options: &ParallelDownloadOptions) -> Self {
+        let mut pm = match options.rate_limit {
+            Some(limit) => self.clone().with_rate_limit(limit),
+            None => self.clone(),
+        };
+        if let Some(max) = options.endpoint_concurrency {
+            if let Some(client) = pm.rpc_client.with_max_in_flight(max) {
+                pm.rpc_client = client;
+            }
+        }
+        pm
+    }
+
+    /// Writes `gget.lock` for a closure downloaded under `target_dir`
+    #[tracing::instrument(name = "write", skip_all)]
+    fn write_lockfile(
+        &self,
+        closure: &DependencyClosure,
+        target_dir: &Path,
+    ) -> Result<(), PackageManagerError> {
+        let mut lock = Lockfile {
+            roots: closure.roots.clone(),
+            layout: self.layout,
+            ..Default::default()
+        };
+
+        for pkg_path in closure.packages.keys() {
+            // subpackages live inside their parent's directory but are locked separately
+            let nested = nested_dirs(self.layout, pkg_path, closure.packages.keys(), target_dir);
+
+            let mut entry = LockedPackage::from_dir(
+                pkg_path,
+                &self.layout.package_dir(target_dir, pkg_path),
+                &nested,
+            )?;
+            entry.required_by = vec![closure.root_of(pkg_path).to_string()];
+            entry.height = self.pinned_height(pkg_path);
+            lock.insert(entry);
+        }
+        lock.selections = self.selections_of(|path| closure.packages.contains_key(path));
+
+        lock.save(&target_dir.join(LOCKFILE_NAME))?;
+        Ok(())
     }
 }
@@ -0,0 +1,65 @@
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::paths;
+
+/// Advisory lock on a file, shared by every gget process on the machine
+/// and released when dropped.
+///
+/// Locks only coordinate gget processes with each other; nothing stops
+/// other programs from touching what they guard.
+#[derive(Debug)]
+pub struct FileLock {
+    _file: File,
+    path: PathBuf,
+}
+
+impl FileLock {
+    /// Takes the exclusive lock on `path`, creating the file if needed.
+    ///
+    /// Blocks until the process holding it lets go, calling `on_wait`
+    /// first if it has to.
+    pub async fn acquire(path: PathBuf, on_wait: impl FnOnce()) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)?;
+        match file.try_lock() {
+            Ok(()) => return Ok(Self { _file: file, path }),
+            Err(TryLockError::WouldBlock) => on_wait(),
+            Err(TryLockError::Error(e)) => return Err(e),
+        }
+
+        tokio::task::spawn_blocking(move || {
+            file.lock()?;
+            Ok(Self { _file: file, path })
+        })
+        .await
+        .map_err(io::Error::other)?
+    }
+
+    /// Takes the lock guarding writes to `target_dir`, see
+    /// [FileLock::target_path]
+    pub async fn for_target(target_dir: &Path, on_wait: impl FnOnce()) -> io::Result<Self> {
+        Self::acquire(Self::target_path(target_dir)?, on_wait).await
+    }
+
+    /// Lock file guarding writes to `target_dir`.
+    ///
+    /// It lives in [paths::lock_dir], named after the absolute path of the
+    /// target, so it never shows up among the downloaded files.
+    pub fn target_path(target_dir: &Path) -> io::Result<PathBuf> {
+        let absolute = std::path::absolute(target_dir)?;
+        let hash = blake3::hash(absolute.as_os_str().as_encoded_bytes()).to_hex();
+        Ok(paths::lock_dir().join(format!("{}.lock", hash)))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
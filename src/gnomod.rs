@@ -0,0 +1,457 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::dependency::{DependencyError, DependencyResolver, GNO_FILE_EXTENSION};
+use crate::error::ErrorKind;
+use crate::install::write_durably;
+
+/// Default manifest name used by upstream gno tooling
+pub const GNOMOD_NAME: &str = "gno.mod";
+
+/// Version of the requirements gget adds, gno packages being unversioned
+pub const LATEST_VERSION: &str = "v0.0.0-latest";
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum GnoModError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("gno.mod line {line}: {reason}")]
+    Parse { line: usize, reason: String },
+
+    #[error("Failed to parse the module's sources: {0}")]
+    Dependency(#[from] DependencyError),
+}
+
+impl GnoModError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(_) => ErrorKind::Internal,
+            Self::Parse { .. } => ErrorKind::Config,
+            Self::Dependency(e) => e.kind(),
+        }
+    }
+}
+
+/// A single `require` directive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Require {
+    /// Package path (e.g., "gno.land/p/demo/avl")
+    pub path: String,
+    /// Version string as written (e.g., "v0.0.0-latest")
+    pub version: String,
+}
+
+/// A `replace` directive substituting a local directory for a package
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replace {
+    /// Package path replaced (e.g., "gno.land/p/me/foo")
+    pub path: String,
+    /// Directory used instead, as written, relative to the gno.mod
+    pub dir: PathBuf,
+}
+
+/// The parts of a gno.mod file gget cares about.
+///
+/// Supports the go.mod-style subset used by gno projects:
+///
+/// ```text
+/// module gno.land/r/demo/foo
+///
+/// require (
+///     gno.land/p/demo/avl v0.0.0-latest
+/// )
+///
+/// replace gno.land/p/me/foo => ../foo
+/// ```
+///
+/// Only local directories can replace packages. Directives other than
+/// `module`, `require` and `replace` are accepted and ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GnoMod {
+    pub module: Option<String>,
+    pub requires: Vec<Require>,
+    pub replaces: Vec<Replace>,
+}
+
+impl GnoMod {
+    /// Reads and parses a gno.mod file
+    pub fn load(path: &Path) -> Result<Self, GnoModError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parses gno.mod content
+    pub fn parse(content: &str) -> Result<Self, GnoModError> {
+        let mut gnomod = GnoMod::default();
+        // directive of the `( ... )` block currently open, if any
+        let mut block: Option<String> = None;
+
+        for (idx, raw) in content.lines().enumerate() {
+            let line_no = idx + 1;
+            let line = strip_comment(raw).trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(directive) = &block {
+                if line == ")" {
+                    block = None;
+                } else if directive == "require" {
+                    gnomod.requires.push(parse_require(line, line_no)?);
+                } else if directive == "replace" {
+                    gnomod.replaces.push(parse_replace(line, line_no)?);
+                }
+                continue;
+            }
+
+            let (directive, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let rest = rest.trim();
+
+            if rest == "(" {
+                block = Some(directive.to_string());
+                continue;
+            }
+
+            match directive {
+                "module" => {
+                    if rest.is_empty() {
+                        return Err(GnoModError::Parse {
+                            line: line_no,
+                            reason: "missing module path".to_string(),
+                        });
+                    }
+                    gnomod.module = Some(unquote(rest).to_string());
+                }
+                "require" => gnomod.requires.push(parse_require(rest, line_no)?),
+                "replace" => gnomod.replaces.push(parse_replace(rest, line_no)?),
+                _ => {}
+            }
+        }
+
+        if block.is_some() {
+            return Err(GnoModError::Parse {
+                line: content.lines().count(),
+                reason: "unterminated block".to_string(),
+            });
+        }
+
+        Ok(gnomod)
+    }
+
+    /// The replaced packages and their directories, resolved against `dir`,
+    /// the directory of the gno.mod
+    pub fn replacements(&self, dir: &Path) -> Vec<(String, PathBuf)> {
+        self.replaces
+            .iter()
+            .map(|replace| (replace.path.clone(), dir.join(&replace.dir)))
+            .collect()
+    }
+
+    /// Returns the required package paths in declaration order
+    pub fn require_paths(&self) -> Vec<&str> {
+        self.requires.iter().map(|r| r.path.as_str()).collect()
+    }
+
+    /// Requirements that none of `imports` refer to, in declaration order
+    pub fn unused_requires<I, S>(&self, imports: I) -> Vec<&Require>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let imports: BTreeSet<String> = imports
+            .into_iter()
+            .map(|import| import.as_ref().to_string())
+            .collect();
+        self.requires
+            .iter()
+            .filter(|require| !imports.contains(&require.path))
+            .collect()
+    }
+}
+
+/// Packages imported by the `.gno` files of the module rooted at `dir`,
+/// test files included.
+///
+/// Nested modules, directories with a gno.mod of their own, are left out,
+/// and so is `exclude`, e.g. the directory dependencies are vendored in.
+pub fn module_imports(dir: &Path, exclude: &Path) -> Result<BTreeSet<String>, GnoModError> {
+    let resolver = DependencyResolver::new()?;
+    let exclude = exclude.canonicalize().ok();
+    let mut imports = BTreeSet::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                let hidden = path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+                let nested = path.join(GNOMOD_NAME).exists();
+                let excluded = exclude.is_some() && path.canonicalize().ok() == exclude;
+                if !hidden && !nested && !excluded {
+                    dirs.push(path);
+                }
+            } else if path
+                .extension()
+                .is_some_and(|ext| ext == GNO_FILE_EXTENSION)
+            {
+                let (_, found) = resolver.extract_dependencies(&fs::read_to_string(&path)?)?;
+                imports.extend(found);
+            }
+        }
+    }
+    Ok(imports)
+}
+
+/// Adds requirements on the `direct` packages and, marked `// indirect`
+/// like Go does, on the `indirect` ones to the gno.mod `content`,
+/// returning the new content and the paths added.
+///
+/// Everything else is kept as written. New entries go at the end of the
+/// first `require` block, indented like its entries, or into a new block
+/// at the end of the file. Packages already required are left alone,
+/// except that direct ones lose their `// indirect` marker.
+pub fn add_requires(
+    content: &str,
+    direct: &[&str],
+    indirect: &[&str],
+) -> Result<(String, Vec<String>), GnoModError> {
+    let gnomod = GnoMod::parse(content)?;
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let layout = RequireLines::scan(&lines);
+
+    let indent = layout.indent.unwrap_or_else(|| "\t".to_string());
+    let wanted = direct
+        .iter()
+        .map(|path| (*path, false))
+        .chain(indirect.iter().map(|path| (*path, true)));
+    let mut added = Vec::new();
+    let mut entries = Vec::new();
+    for (path, is_indirect) in wanted {
+        if gnomod.module.as_deref() == Some(path) || added.iter().any(|a| a == path) {
+            continue;
+        }
+        match layout.entries.get(path) {
+            Some(&i) if !is_indirect => lines[i] = without_indirect(&lines[i]),
+            Some(_) => {}
+            None => {
+                let marker = if is_indirect { " // indirect" } else { "" };
+                entries.push(format!("{}{} {}{}", indent, path, LATEST_VERSION, marker));
+                added.push(path.to_string());
+            }
+        }
+    }
+
+    if !entries.is_empty() {
+        match layout.blocks.first() {
+            Some(&(_, end)) => {
+                lines.splice(end..end, entries);
+            }
+            None => {
+                if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+                    lines.push(String::new());
+                }
+                lines.push("require (".to_string());
+                lines.extend(entries);
+                lines.push(")".to_string());
+            }
+        }
+    }
+    Ok((join_lines(&lines), added))
+}
+
+/// Removes the requirements on `paths` from the gno.mod `content`,
+/// returning the new content and the paths removed.
+///
+/// Everything else is kept as written, except `require` blocks left empty,
+/// which go too.
+pub fn remove_requires(
+    content: &str,
+    paths: &[&str],
+) -> Result<(String, Vec<String>), GnoModError> {
+    GnoMod::parse(content)?;
+    let lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let layout = RequireLines::scan(&lines);
+
+    let mut removed = Vec::new();
+    let mut dropped = BTreeSet::new();
+    for path in paths {
+        if let Some(&i) = layout.entries.get(*path) {
+            if dropped.insert(i) {
+                removed.push(path.to_string());
+            }
+        }
+    }
+    for &(start, end) in &layout.blocks {
+        let emptied = layout
+            .entries
+            .values()
+            .filter(|&&i| start < i && i < end)
+            .all(|i| dropped.contains(i));
+        if emptied {
+            dropped.insert(start);
+            dropped.insert(end);
+        }
+    }
+
+    let kept: Vec<String> = lines
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| !dropped.contains(i))
+        .map(|(_, line)| line)
+        .collect();
+    Ok((join_lines(&kept), removed))
+}
+
+/// Like [add_requires], for the gno.mod file at `path`, which is only
+/// rewritten if it changes
+pub fn add_requires_to_file(
+    path: &Path,
+    direct: &[&str],
+    indirect: &[&str],
+) -> Result<Vec<String>, GnoModError> {
+    let content = fs::read_to_string(path)?;
+    let (updated, added) = add_requires(&content, direct, indirect)?;
+    if updated != content {
+        write_durably(path, updated.as_bytes())?;
+    }
+    Ok(added)
+}
+
+/// Where the requirements of a gno.mod are written
+#[derive(Debug, Default)]
+struct RequireLines {
+    /// Line of each requirement, keyed by path
+    entries: HashMap<String, usize>,
+    /// Lines opening and closing each `require ( ... )` block
+    blocks: Vec<(usize, usize)>,
+    /// Indentation of the first entry of a block
+    indent: Option<String>,
+}
+
+impl RequireLines {
+    /// Scans the lines of a gno.mod that parses
+    fn scan(lines: &[String]) -> Self {
+        let mut found = Self::default();
+        // directive and first line of the block currently open, if any
+        let mut block: Option<(&str, usize)> = None;
+        for (i, raw) in lines.iter().enumerate() {
+            let line = strip_comment(raw).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some((directive, start)) = block {
+                if line == ")" {
+                    if directive == "require" {
+                        found.blocks.push((start, i));
+                    }
+                    block = None;
+                } else if directive == "require" {
+                    found.indent.get_or_insert_with(|| {
+                        raw[..raw.len() - raw.trim_start().len()].to_string()
+                    });
+                    found.entries.insert(require_path(line), i);
+                }
+                continue;
+            }
+            let (directive, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            match rest.trim() {
+                "(" => {
+                    block = Some((
+                        if directive == "require" {
+                            "require"
+                        } else {
+                            ""
+                        },
+                        i,
+                    ))
+                }
+                rest if directive == "require" => {
+                    found.entries.insert(require_path(rest), i);
+                }
+                _ => {}
+            }
+        }
+        found
+    }
+}
+
+/// `lines` as file content, ending with a newline unless empty
+fn join_lines(lines: &[String]) -> String {
+    let mut content = lines.join("\n");
+    if !content.is_empty() {
+        content.push('\n');
+    }
+    content
+}
+
+/// Path of a `<path> <version>` requirement
+fn require_path(spec: &str) -> String {
+    unquote(spec.split_whitespace().next().unwrap_or_default()).to_string()
+}
+
+/// `line` without its `// indirect` comment, if it has one
+fn without_indirect(line: &str) -> String {
+    match line.split_once("//") {
+        Some((code, comment)) if comment.trim() == "indirect" => code.trim_end().to_string(),
+        _ => line.to_string(),
+    }
+}
+
+fn parse_require(spec: &str, line: usize) -> Result<Require, GnoModError> {
+    let mut parts = spec.split_whitespace();
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(path), Some(version), None) => Ok(Require {
+            path: unquote(path).to_string(),
+            version: version.to_string(),
+        }),
+        _ => Err(GnoModError::Parse {
+            line,
+            reason: format!("expected `<path> <version>`, got `{}`", spec),
+        }),
+    }
+}
+
+/// Parses `<path> [version] => <dir>`, where `dir` must be a local path
+fn parse_replace(spec: &str, line: usize) -> Result<Replace, GnoModError> {
+    let error = |reason: String| GnoModError::Parse { line, reason };
+    let (old, new) = spec
+        .split_once("=>")
+        .ok_or_else(|| error(format!("expected `<path> => <dir>`, got `{}`", spec)))?;
+    let mut old = old.split_whitespace();
+    let mut new = new.split_whitespace();
+    let (Some(path), _, None, Some(dir), None) =
+        (old.next(), old.next(), old.next(), new.next(), new.next())
+    else {
+        return Err(error(format!("expected `<path> => <dir>`, got `{}`", spec)));
+    };
+    let dir = unquote(dir);
+    let local = matches!(dir, "." | "..")
+        || dir.starts_with("./")
+        || dir.starts_with("../")
+        || Path::new(dir).is_absolute();
+    if !local {
+        return Err(error(format!(
+            "{} must be replaced by a local directory, starting with ./ or ../",
+            unquote(path)
+        )));
+    }
+    Ok(Replace {
+        path: unquote(path).to_string(),
+        dir: PathBuf::from(dir),
+    })
+}
+
+pub(crate) fn strip_comment(line: &str) -> &str {
+    line.split_once("//").map_or(line, |(code, _)| code)
+}
+
+pub(crate) fn unquote(s: &str) -> &str {
+    s.strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or(s)
+}
@@ -0,0 +1,215 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Permissions of every file gget writes on unix, whatever the umask or
+/// the mode of the file it replaces
+pub const FILE_MODE: u32 = 0o644;
+
+/// Moves the staged file or directory `staged` to `target`. A directory
+/// target must not exist; a file target is replaced.
+///
+/// A rename when both are on the same filesystem. Otherwise (`EXDEV`) the
+/// tree is copied next to `target` and synced to disk first, then renamed
+/// into place, so `target` never holds a partial copy. Either way the
+/// parent of `target` is synced, so the swap itself survives a crash.
+pub fn install(staged: &Path, target: &Path) -> io::Result<()> {
+    match fs::rename(staged, target) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => copy_and_swap(staged, target),
+        Err(e) => Err(e),
+        Ok(()) => match target.parent() {
+            Some(parent) => sync_dir(parent),
+            None => Ok(()),
+        },
+    }
+}
+
+/// Writes `contents` to `path` with [FILE_MODE], syncing it to disk
+/// before returning when `sync` is set
+pub fn write_file(path: &Path, contents: &[u8], sync: bool) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(FILE_MODE))?;
+    }
+    file.write_all(contents)?;
+    if sync {
+        file.sync_all()?;
+    }
+    Ok(())
+}
+
+/// Replaces `path` with `contents` so that a crash leaves either the old
+/// or the new file, complete: the contents are written and synced next
+/// to it, then [install]ed
+pub fn write_durably(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let staged = sibling(path);
+    let result = write_file(&staged, contents, true).and_then(|()| install(&staged, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&staged);
+    }
+    result
+}
+
+/// Copies `staged` next to `target`, syncs the copy, renames it over
+/// `target` and removes `staged`
+fn copy_and_swap(staged: &Path, target: &Path) -> io::Result<()> {
+    let copy = sibling(target);
+    let result = if staged.is_dir() {
+        copy_dir_synced(staged, &copy)
+    } else {
+        copy_file_synced(staged, &copy)
+    }
+    .and_then(|()| fs::rename(&copy, target));
+    if result.is_err() {
+        let _ = fs::remove_dir_all(&copy).or_else(|_| fs::remove_file(&copy));
+        return result;
+    }
+    if let Some(parent) = target.parent() {
+        sync_dir(parent)?;
+    }
+
+    if staged.is_dir() {
+        fs::remove_dir_all(staged)
+    } else {
+        fs::remove_file(staged)
+    }
+}
+
+/// Unused path next to `path`, on the same filesystem
+fn sibling(path: &Path) -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let name = format!(
+        ".{}_copy_{}",
+        path.file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or("package"),
+        timestamp,
+    );
+    match path.parent() {
+        Some(parent) => parent.join(name),
+        None => PathBuf::from(name),
+    }
+}
+
+fn copy_file_synced(from: &Path, to: &Path) -> io::Result<()> {
+    fs::copy(from, to)?;
+    File::open(to)?.sync_all()
+}
+
+fn copy_dir_synced(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir_synced(&entry.path(), &target)?;
+        } else {
+            copy_file_synced(&entry.path(), &target)?;
+        }
+    }
+    sync_dir(to)
+}
+
+/// Persists the entries of `dir`. Directories can't be opened for syncing
+/// on every platform, where this does nothing.
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        // the parent of a bare file name
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        File::open(dir)?.sync_all()?;
+    }
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_copy_and_swap_dir() {
+        let root = tempdir().unwrap();
+        let staged = root.path().join("staged");
+        fs::create_dir_all(staged.join("sub")).unwrap();
+        fs::write(staged.join("a.gno"), "package a").unwrap();
+        fs::write(staged.join("sub/b.gno"), "package b").unwrap();
+
+        let target = root.path().join("a");
+        copy_and_swap(&staged, &target).unwrap();
+        assert!(!staged.exists());
+        assert_eq!(
+            fs::read_to_string(target.join("a.gno")).unwrap(),
+            "package a"
+        );
+        assert_eq!(
+            fs::read_to_string(target.join("sub/b.gno")).unwrap(),
+            "package b"
+        );
+        // nothing but the target is left next to it
+        assert_eq!(fs::read_dir(root.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_copy_and_swap_file_replaces_target() {
+        let root = tempdir().unwrap();
+        let staged = root.path().join("staged.gno");
+        let target = root.path().join("a.gno");
+        fs::write(&staged, "package a // v2").unwrap();
+        fs::write(&target, "package a // v1").unwrap();
+
+        copy_and_swap(&staged, &target).unwrap();
+        assert!(!staged.exists());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "package a // v2");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_written_files_get_the_file_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = tempdir().unwrap();
+        let path = root.path().join("gget.lock");
+        fs::write(&path, "{}").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+        write_durably(&path, b"{\"roots\": []}").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"roots\": []}");
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, FILE_MODE);
+        assert_eq!(fs::read_dir(root.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_sync_dir_of_a_bare_file_name() {
+        // Path::new("gno.mod").parent()
+        sync_dir(Path::new("")).unwrap();
+    }
+
+    #[test]
+    fn test_failed_copy_leaves_target_and_no_leftovers() {
+        let root = tempdir().unwrap();
+        let target = root.path().join("a");
+        fs::create_dir_all(&target).unwrap();
+        fs::write(target.join("a.gno"), "package a").unwrap();
+
+        copy_and_swap(&root.path().join("missing"), &target).unwrap_err();
+        assert_eq!(
+            fs::read_to_string(target.join("a.gno")).unwrap(),
+            "package a"
+        );
+        assert_eq!(fs::read_dir(root.path()).unwrap().count(), 1);
+    }
+}
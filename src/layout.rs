@@ -0,0 +1,109 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::error::ErrorKind;
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum LayoutError {
+    #[error("{} would all be written to {} with the {layout} layout", .packages.join(", "), .dir.display())]
+    Collision {
+        layout: Layout,
+        dir: PathBuf,
+        packages: Vec<String>,
+    },
+}
+
+impl LayoutError {
+    pub fn kind(&self) -> ErrorKind {
+        ErrorKind::Config
+    }
+}
+
+/// Where each package goes under the output directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Layout {
+    /// Straight into the output directory; holds a single package
+    Flat,
+    /// In a directory named after the last element of the package path,
+    /// e.g. `avl` for `gno.land/p/demo/avl`
+    ByName,
+    /// In the directories of the package path, e.g. `gno.land/p/demo/avl`
+    #[default]
+    ByImportPath,
+}
+
+impl Layout {
+    pub const ALL: [Layout; 3] = [Self::Flat, Self::ByName, Self::ByImportPath];
+
+    /// Directory `pkg_path` is written to under `output`
+    pub fn package_dir(self, output: &Path, pkg_path: &str) -> PathBuf {
+        match self {
+            Self::Flat => output.to_path_buf(),
+            Self::ByName => output.join(pkg_path.rsplit('/').next().unwrap_or(pkg_path)),
+            Self::ByImportPath => output.join(pkg_path),
+        }
+    }
+
+    /// Checks that no two of `packages` would be written to the same
+    /// directory under `output`
+    pub fn check<'a>(
+        self,
+        output: &Path,
+        packages: impl IntoIterator<Item = &'a str>,
+    ) -> Result<(), LayoutError> {
+        let mut dirs: BTreeMap<PathBuf, Vec<String>> = BTreeMap::new();
+        for pkg_path in packages {
+            dirs.entry(self.package_dir(output, pkg_path))
+                .or_default()
+                .push(pkg_path.to_string());
+        }
+        match dirs.into_iter().find(|(_, packages)| packages.len() > 1) {
+            Some((dir, mut packages)) => {
+                packages.sort();
+                Err(LayoutError::Collision {
+                    layout: self,
+                    dir,
+                    packages,
+                })
+            }
+            None => Ok(()),
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Flat => "flat",
+            Self::ByName => "by-name",
+            Self::ByImportPath => "by-import-path",
+        })
+    }
+}
+
+impl FromStr for Layout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|layout| layout.to_string() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown layout `{}`, expected flat, by-name or by-import-path",
+                    s
+                )
+            })
+    }
+}
@@ -1,7 +1,48 @@
+pub mod archive;
+pub mod audit;
 pub mod cache;
+pub mod cancel;
+pub mod compat;
 pub mod dependency;
+pub mod deploy;
+pub mod diff;
+pub mod endpoint;
+pub mod error;
 pub mod fetch;
+pub mod filelock;
+pub mod gnomod;
+pub mod install;
+pub mod layout;
+pub mod limits;
+pub mod lint;
+pub mod lock;
+pub mod network;
 pub mod parallel;
+pub mod paths;
+pub mod plan;
+pub mod policy;
 pub mod query;
+pub mod ratelimit;
+pub mod rdeps;
+pub mod report;
+pub mod repro;
+pub mod requirements;
+pub mod review;
+pub mod rpc;
+pub mod sat;
+pub mod session;
+pub mod singleflight;
+pub mod source;
+#[cfg(feature = "test-util")]
+pub mod testing;
+pub mod tidy;
+pub mod timings;
+pub mod trash;
+pub mod verify;
+pub mod watch;
+pub mod workspace;
+
+pub use error::ErrorKind;
+pub use tokio_util::sync::CancellationToken;
 
 pub const DEFAULT_RPC_ENDPOINT: &str = "https://rpc.gno.land:443";
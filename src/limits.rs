@@ -0,0 +1,232 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use thiserror::Error;
+
+/// Files a package may have unless configured otherwise
+pub const DEFAULT_MAX_FILES: u64 = 10_000;
+/// Size a single file may have unless configured otherwise
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 16 * 1024 * 1024;
+/// Size a whole package may have unless configured otherwise
+pub const DEFAULT_MAX_PACKAGE_SIZE: u64 = 64 * 1024 * 1024;
+/// Room a response has besides the file it carries, for the JSON around
+/// it and for file listings
+const RESPONSE_OVERHEAD: u64 = 1024 * 1024;
+
+/// What [DownloadLimits] caps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// Files in one package
+    Files,
+    /// Bytes of one file
+    FileSize,
+    /// Bytes of one package
+    PackageSize,
+    /// Bytes of everything a run downloads
+    TotalSize,
+}
+
+impl Limit {
+    /// The command line flag setting the limit
+    pub fn flag(self) -> &'static str {
+        match self {
+            Self::Files => "--max-files",
+            Self::FileSize => "--max-file-size",
+            Self::PackageSize => "--max-package-size",
+            Self::TotalSize => "--max-total-size",
+        }
+    }
+}
+
+/// A download went over one of its [DownloadLimits]
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+#[error("{}, more than the {max} allowed by {}", self.measured(), limit.flag())]
+pub struct LimitExceeded {
+    pub limit: Limit,
+    /// The file the limit was hit on, if any
+    pub file: Option<String>,
+    pub actual: u64,
+    pub max: u64,
+}
+
+impl LimitExceeded {
+    fn measured(&self) -> String {
+        let file = self.file.as_deref().unwrap_or_default();
+        match self.limit {
+            Limit::Files => format!("{} files", self.actual),
+            Limit::FileSize => format!("{} has {} bytes", file, self.actual),
+            Limit::PackageSize => format!("{} bytes up to {}", self.actual, file),
+            Limit::TotalSize => format!("{} bytes in total up to {}", self.actual, file),
+        }
+    }
+}
+
+/// Caps on what downloads may write, so that a malicious or runaway
+/// package fails its download instead of filling the disk.
+///
+/// Files and packages are capped by default, see [DEFAULT_MAX_FILES],
+/// [DEFAULT_MAX_FILE_SIZE] and [DEFAULT_MAX_PACKAGE_SIZE]; the total size
+/// of a run is only capped when set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DownloadLimits {
+    pub max_files: Option<u64>,
+    pub max_file_size: Option<u64>,
+    pub max_package_size: Option<u64>,
+    pub max_total_size: Option<u64>,
+}
+
+impl Default for DownloadLimits {
+    fn default() -> Self {
+        Self {
+            max_files: Some(DEFAULT_MAX_FILES),
+            max_file_size: Some(DEFAULT_MAX_FILE_SIZE),
+            max_package_size: Some(DEFAULT_MAX_PACKAGE_SIZE),
+            max_total_size: None,
+        }
+    }
+}
+
+impl DownloadLimits {
+    /// No caps at all
+    pub fn unlimited() -> Self {
+        Self {
+            max_files: None,
+            max_file_size: None,
+            max_package_size: None,
+            max_total_size: None,
+        }
+    }
+
+    pub fn with_max_files(mut self, max: u64) -> Self {
+        self.max_files = Some(max);
+        self
+    }
+
+    pub fn with_max_file_size(mut self, max: u64) -> Self {
+        self.max_file_size = Some(max);
+        self
+    }
+
+    pub fn with_max_package_size(mut self, max: u64) -> Self {
+        self.max_package_size = Some(max);
+        self
+    }
+
+    pub fn with_max_total_size(mut self, max: u64) -> Self {
+        self.max_total_size = Some(max);
+        self
+    }
+
+    /// Bytes an RPC response may have to carry a file of the largest size
+    /// allowed, base64 encoded, or `None` if file sizes aren't capped
+    pub fn max_response_size(&self) -> Option<u64> {
+        self.max_file_size.map(|max| {
+            max.div_ceil(3)
+                .saturating_mul(4)
+                .saturating_add(RESPONSE_OVERHEAD)
+        })
+    }
+
+    /// Checks the number of files listed for a package
+    pub fn check_files(&self, files: u64) -> Result<(), LimitExceeded> {
+        check(Limit::Files, None, files, self.max_files)
+    }
+
+    /// Checks a file's size, and the size of its package so far
+    pub fn check_file(
+        &self,
+        file: &str,
+        size: u64,
+        package_size: u64,
+    ) -> Result<(), LimitExceeded> {
+        check(Limit::FileSize, Some(file), size, self.max_file_size)?;
+        check(
+            Limit::PackageSize,
+            Some(file),
+            package_size,
+            self.max_package_size,
+        )
+    }
+}
+
+fn check(
+    limit: Limit,
+    file: Option<&str>,
+    actual: u64,
+    max: Option<u64>,
+) -> Result<(), LimitExceeded> {
+    match max {
+        Some(max) if actual > max => Err(LimitExceeded {
+            limit,
+            file: file.map(str::to_string),
+            actual,
+            max,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Bytes downloaded by a run so far, shared by the clones of a package
+/// manager and checked against [DownloadLimits::max_total_size]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TotalSize(Arc<AtomicU64>);
+
+impl TotalSize {
+    /// Starts counting the bytes of one package download
+    pub(crate) fn charge(&self, max: Option<u64>) -> Charge<'_> {
+        Charge {
+            total: self,
+            max,
+            bytes: 0,
+        }
+    }
+}
+
+/// Bytes one package download added to a [TotalSize].
+///
+/// They are given back when the charge is dropped without
+/// [Charge::keep], so failed attempts don't count against later retries.
+pub(crate) struct Charge<'a> {
+    total: &'a TotalSize,
+    max: Option<u64>,
+    bytes: u64,
+}
+
+impl Charge<'_> {
+    pub(crate) fn add(&mut self, file: &str, bytes: u64) -> Result<(), LimitExceeded> {
+        self.bytes += bytes;
+        let total = self.total.0.fetch_add(bytes, Ordering::SeqCst) + bytes;
+        check(Limit::TotalSize, Some(file), total, self.max)
+    }
+
+    /// Leaves the bytes counted
+    pub(crate) fn keep(mut self) {
+        self.bytes = 0;
+    }
+}
+
+impl Drop for Charge<'_> {
+    fn drop(&mut self) {
+        self.total.0.fetch_sub(self.bytes, Ordering::SeqCst);
+    }
+}
+
+/// A size in bytes, with an optional `k`, `m` or `g` suffix (powers of
+/// 1024), e.g. `512k` or `2g`
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let lower = s.trim().to_ascii_lowercase();
+    let (digits, multiplier) = if let Some(n) = lower.strip_suffix('k') {
+        (n, 1 << 10)
+    } else if let Some(n) = lower.strip_suffix('m') {
+        (n, 1 << 20)
+    } else if let Some(n) = lower.strip_suffix('g') {
+        (n, 1 << 30)
+    } else {
+        (lower.as_str(), 1)
+    };
+
+    match digits.parse::<u64>().map(|n| n.checked_mul(multiplier)) {
+        Ok(Some(n)) if n > 0 => Ok(n),
+        _ => Err(format!("invalid size `{}`", s)),
+    }
+}
@@ -0,0 +1,409 @@
+//! Validation of downloaded packages: syntax errors in `.gno` files and
+//! what else keeps a package from deploying as is.
+//!
+//! tree-sitter parses anything: it wraps what it can't make sense of in
+//! ERROR nodes and inserts MISSING nodes for tokens it expected, so a file
+//! producing a tree proves little. Validation walks the tree for those.
+
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tree_sitter::{Node, Parser};
+
+use crate::compat::CompatWarning;
+use crate::dependency::DependencyError;
+use crate::fetch::{PackageManagerError, ValidationError};
+use crate::gnomod::GNOMOD_NAME;
+use crate::report::{Report, Table};
+
+/// Longest snippet quoted in a [SyntaxError], in characters
+const SNIPPET_LEN: usize = 60;
+
+/// How serious a validation finding is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Deploys, or might, but likely not as intended
+    Warning,
+    /// Won't compile
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Warning => "warning",
+            Self::Error => "error",
+        })
+    }
+}
+
+/// An ERROR or MISSING node of a `.gno` file's syntax tree
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SyntaxError {
+    pub file: PathBuf,
+    /// 1-based
+    pub line: usize,
+    /// 1-based, in bytes
+    pub column: usize,
+    /// The token the parser expected, for MISSING nodes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub missing: Option<String>,
+    /// The start of the offending line, trimmed
+    pub snippet: String,
+}
+
+impl SyntaxError {
+    pub fn severity(&self) -> Severity {
+        Severity::Error
+    }
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: syntax error",
+            self.file.display(),
+            self.line,
+            self.column
+        )?;
+        if let Some(missing) = &self.missing {
+            write!(f, ", missing `{}`", missing)?;
+        }
+        write!(f, ": {}", self.snippet)
+    }
+}
+
+/// What a [Diagnostic] is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticKind {
+    /// See [SyntaxError]
+    Syntax,
+    /// A Go construct Gno doesn't support, see [crate::compat]
+    GoOnly,
+}
+
+impl DiagnosticKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Syntax => "syntax",
+            Self::GoOnly => "go_only",
+        }
+    }
+}
+
+/// A finding about one line of a `.gno` file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub file: PathBuf,
+    /// 1-based
+    pub line: usize,
+    /// 1-based, when known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+    pub kind: DiagnosticKind,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file.display(), self.line)?;
+        if let Some(column) = self.column {
+            write!(f, ":{}", column)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl From<SyntaxError> for Diagnostic {
+    fn from(error: SyntaxError) -> Self {
+        let message = match &error.missing {
+            Some(missing) => format!("syntax error, missing `{}`: {}", missing, error.snippet),
+            None => format!("syntax error: {}", error.snippet),
+        };
+        Self {
+            severity: error.severity(),
+            file: error.file,
+            line: error.line,
+            column: Some(error.column),
+            kind: DiagnosticKind::Syntax,
+            message,
+        }
+    }
+}
+
+impl From<CompatWarning> for Diagnostic {
+    fn from(warning: CompatWarning) -> Self {
+        Self {
+            severity: warning.severity(),
+            message: format!(
+                "{} is not supported by Gno; the package will fail to deploy",
+                warning.construct
+            ),
+            file: warning.file,
+            line: warning.line,
+            column: None,
+            kind: DiagnosticKind::GoOnly,
+        }
+    }
+}
+
+/// Result of [crate::fetch::PackageManager::validate_package]
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ValidationReport {
+    pub dir: PathBuf,
+    /// `.gno` files read
+    pub files_checked: usize,
+    /// By file, then by line
+    pub diagnostics: Vec<Diagnostic>,
+    /// Package directories without a gno.mod
+    pub missing_gnomod: Vec<PathBuf>,
+    /// Files in package directories that gnokey doesn't deploy, being
+    /// neither `.gno` files nor gno.mod
+    pub unexpected_files: Vec<PathBuf>,
+    /// Whether syntax errors fail [ValidationReport::into_result]
+    pub strict: bool,
+}
+
+impl ValidationReport {
+    /// Finds the `.gno` files, package directories without gno.mod and
+    /// unexpected files under `dir`, leaving diagnostics to the caller
+    pub fn scan(dir: &Path, strict: bool) -> std::io::Result<Self> {
+        let mut report = Self {
+            dir: dir.to_path_buf(),
+            strict,
+            ..Default::default()
+        };
+        report.visit(dir)?;
+        Ok(report)
+    }
+
+    fn visit(&mut self, dir: &Path) -> std::io::Result<()> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<_, _>>()?;
+        paths.sort();
+
+        let (subdirs, files): (Vec<PathBuf>, Vec<PathBuf>) =
+            paths.into_iter().partition(|p| p.is_dir());
+        let is_gno = |p: &PathBuf| p.extension().is_some_and(|ext| ext == "gno");
+        let sources = files.iter().filter(|p| is_gno(p)).count();
+        if sources > 0 {
+            self.files_checked += sources;
+            if !dir.join(GNOMOD_NAME).is_file() {
+                self.missing_gnomod.push(dir.to_path_buf());
+            }
+            self.unexpected_files.extend(
+                files
+                    .into_iter()
+                    .filter(|p| !is_gno(p) && !p.ends_with(GNOMOD_NAME)),
+            );
+        }
+
+        for subdir in subdirs {
+            self.visit(&subdir)?;
+        }
+        Ok(())
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+    }
+
+    /// Warnings among the diagnostics, plus missing gno.mod files and
+    /// unexpected files
+    pub fn warning_count(&self) -> usize {
+        let diagnostics = self
+            .diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Warning)
+            .count();
+        diagnostics + self.missing_gnomod.len() + self.unexpected_files.len()
+    }
+
+    /// Worst severity found, `None` if nothing was
+    pub fn severity(&self) -> Option<Severity> {
+        let layout = (self.warning_count() > 0).then_some(Severity::Warning);
+        self.diagnostics
+            .iter()
+            .map(|d| d.severity)
+            .chain(layout)
+            .max()
+    }
+
+    /// Fails like validation did before it returned reports: without any
+    /// `.gno` file, or on syntax errors when strict
+    pub fn into_result(self) -> Result<Self, PackageManagerError> {
+        let invalid = |source: ValidationError| PackageManagerError::Validation {
+            dir: self.dir.clone(),
+            source,
+        };
+        if self.files_checked == 0 {
+            return Err(invalid(ValidationError::NoSourceFiles));
+        }
+        let errors: Vec<Diagnostic> = self.errors().cloned().collect();
+        if self.strict && !errors.is_empty() {
+            return Err(invalid(ValidationError::Syntax(errors)));
+        }
+        Ok(self)
+    }
+}
+
+impl Report for ValidationReport {
+    fn table(&self) -> Table {
+        let mut table = Table::new(
+            &format!("Validation of {}", self.dir.display()),
+            &["file", "line", "severity", "kind", "message"],
+        );
+        for diagnostic in &self.diagnostics {
+            table.push_row([
+                diagnostic.file.display().to_string(),
+                diagnostic.line.to_string(),
+                diagnostic.severity.to_string(),
+                diagnostic.kind.as_str().to_string(),
+                diagnostic.message.clone(),
+            ]);
+        }
+        for dir in &self.missing_gnomod {
+            table.push_row([
+                dir.display().to_string(),
+                String::new(),
+                Severity::Warning.to_string(),
+                "missing_gnomod".to_string(),
+                format!("no {}", GNOMOD_NAME),
+            ]);
+        }
+        for file in &self.unexpected_files {
+            table.push_row([
+                file.display().to_string(),
+                String::new(),
+                Severity::Warning.to_string(),
+                "unexpected_file".to_string(),
+                "not deployed by gnokey".to_string(),
+            ]);
+        }
+        table
+    }
+
+    fn human(&self) -> String {
+        let mut out = String::new();
+        for diagnostic in &self.diagnostics {
+            let _ = writeln!(out, "{}: {}", diagnostic.severity, diagnostic);
+        }
+        for dir in &self.missing_gnomod {
+            let _ = writeln!(out, "warning: {} has no {}", dir.display(), GNOMOD_NAME);
+        }
+        for file in &self.unexpected_files {
+            let _ = writeln!(out, "warning: {} is not deployed by gnokey", file.display());
+        }
+
+        let errors = self.errors().count();
+        let warnings = self.warning_count();
+        if errors == 0 && warnings == 0 {
+            let _ = writeln!(out, "All {} files are valid", self.files_checked);
+        } else {
+            let _ = write!(
+                out,
+                "Checked {} files: {} errors, {} warnings",
+                self.files_checked, errors, warnings
+            );
+            if errors > 0 && !self.strict {
+                out.push_str(" (use --strict to fail on errors)");
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Finds syntax errors with tree-sitter
+pub struct SyntaxChecker {
+    parser: Parser,
+}
+
+impl SyntaxChecker {
+    pub fn new() -> Result<Self, DependencyError> {
+        let mut parser = Parser::new();
+        parser
+            .set_language(&tree_sitter_go::LANGUAGE.into())
+            .map_err(|e| DependencyError::LanguageSetup(e.to_string()))?;
+        Ok(Self { parser })
+    }
+
+    /// Returns the syntax errors of `source`, read from `file`, in source
+    /// order. An ERROR node nested in another is only reported once.
+    pub fn check_source(
+        &mut self,
+        file: &Path,
+        source: &str,
+    ) -> Result<Vec<SyntaxError>, DependencyError> {
+        let tree = self
+            .parser
+            .parse(source, None)
+            .ok_or(DependencyError::ParseError)?;
+
+        let mut found = Vec::new();
+        let mut stack = vec![tree.root_node()];
+        while let Some(node) = stack.pop() {
+            if node.is_error() || node.is_missing() {
+                found.push(syntax_error(file, source, node));
+                continue;
+            }
+            if node.has_error() {
+                let mut cursor = node.walk();
+                let children: Vec<Node> = node.children(&mut cursor).collect();
+                stack.extend(children.into_iter().rev());
+            }
+        }
+        Ok(found)
+    }
+
+    /// Checks every `.gno` file under `dir`, recursively
+    pub fn check_dir(&mut self, dir: &Path) -> Result<Vec<SyntaxError>, DependencyError> {
+        let mut errors = Vec::new();
+        self.visit(dir, &mut errors)?;
+        Ok(errors)
+    }
+
+    fn visit(&mut self, dir: &Path, errors: &mut Vec<SyntaxError>) -> Result<(), DependencyError> {
+        let entries = fs::read_dir(dir)
+            .map_err(|e| DependencyError::IoError(format!("Failed to read directory: {}", e)))?;
+        let mut paths: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+        paths.sort();
+
+        for path in paths {
+            if path.is_dir() {
+                self.visit(&path, errors)?;
+            } else if path.extension().is_some_and(|ext| ext == "gno") {
+                let source = fs::read_to_string(&path)
+                    .map_err(|e| DependencyError::IoError(format!("Failed to read file: {}", e)))?;
+                errors.extend(self.check_source(&path, &source)?);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn syntax_error(file: &Path, source: &str, node: Node) -> SyntaxError {
+    let start = node.start_position();
+    let line = source.lines().nth(start.row).unwrap_or_default().trim();
+    let mut snippet: String = line.chars().take(SNIPPET_LEN).collect();
+    if line.chars().count() > SNIPPET_LEN {
+        snippet.push('…');
+    }
+
+    SyntaxError {
+        file: file.to_path_buf(),
+        line: start.row + 1,
+        column: start.column + 1,
+        missing: node.is_missing().then(|| node.kind().to_string()),
+        snippet,
+    }
+}
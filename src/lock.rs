@@ -0,0 +1,702 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::error::ErrorKind;
+use crate::install::write_durably;
+use crate::layout::Layout;
+use crate::paths::{check_relative, UnsafePath};
+
+/// Default lockfile name written next to downloaded packages
+pub const LOCKFILE_NAME: &str = "gget.lock";
+
+/// Current lockfile format version
+const LOCKFILE_VERSION: u32 = 1;
+
+/// Minimum share of identical files, in percent, for a removed and an added
+/// package to count as one moved package
+pub const MOVE_SIMILARITY: u8 = 50;
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum LockError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON serialization/deserialization error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Conflicting hashes for {path} at height {height}: {}", format_pins(.pins))]
+    HashConflict {
+        path: String,
+        height: u64,
+        /// `(root, hash)` pairs that disagree at the selected height
+        pins: Vec<(String, String)>,
+    },
+
+    /// A package path or file name would reach outside the target
+    /// directory
+    #[error("Unsafe path in lockfile: {0}")]
+    UnsafePath(#[from] UnsafePath),
+}
+
+impl LockError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(_) => ErrorKind::Internal,
+            Self::Json(_) => ErrorKind::Config,
+            Self::HashConflict { .. } | Self::UnsafePath(_) => ErrorKind::Integrity,
+        }
+    }
+}
+
+/// Directories under `target_dir` of the `packages` laid out inside the
+/// directory of `pkg_path`, such as subpackages by import path
+pub fn nested_dirs<'a>(
+    layout: Layout,
+    pkg_path: &str,
+    packages: impl IntoIterator<Item = &'a String>,
+    target_dir: &Path,
+) -> Vec<PathBuf> {
+    let dir = layout.package_dir(target_dir, pkg_path);
+    packages
+        .into_iter()
+        .map(|other| layout.package_dir(target_dir, other))
+        .filter(|other| other != &dir && other.starts_with(&dir))
+        .collect()
+}
+
+fn format_pins(pins: &[(String, String)]) -> String {
+    pins.iter()
+        .map(|(root, hash)| format!("{} pins {}", root, hash))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// A single locked package entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedPackage {
+    /// Package path (e.g., "gno.land/p/demo/avl")
+    pub path: String,
+    /// Block height the package was fetched or pinned at, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u64>,
+    /// Content hash of the whole package (see [hash_files])
+    pub hash: String,
+    /// Per-file content hashes keyed by path relative to the package root
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub files: BTreeMap<String, String>,
+    /// Roots whose closure requires this package
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required_by: Vec<String>,
+}
+
+impl LockedPackage {
+    /// Builds an entry from the package files currently in `dir`.
+    ///
+    /// Subdirectories listed in `nested` belong to other packages and are
+    /// left out of the hash.
+    pub fn from_dir(pkg_path: &str, dir: &Path, nested: &[PathBuf]) -> Result<Self, LockError> {
+        let (hash, files) = hash_dir_excluding(dir, nested)?;
+        Ok(Self {
+            path: pkg_path.to_string(),
+            height: None,
+            hash,
+            files,
+            required_by: Vec::new(),
+        })
+    }
+
+    /// Returns the locked files that are missing or modified under `dir`
+    pub fn changed_files(&self, dir: &Path) -> Vec<String> {
+        self.files
+            .iter()
+            .filter(|(name, hash)| match fs::read(dir.join(name)) {
+                Ok(content) => &hash_content(&content) != *hash,
+                Err(_) => true,
+            })
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Returns true if every locked file is present and unmodified under `dir`
+    pub fn is_intact(&self, dir: &Path) -> bool {
+        dir.is_dir() && self.changed_files(dir).is_empty()
+    }
+}
+
+/// On-disk lockfile recording the exact package set of a download
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    pub version: u32,
+    /// Root packages this lockfile was resolved from
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roots: Vec<String>,
+    /// How the packages are laid out next to the lockfile
+    #[serde(default, skip_serializing_if = "Layout::is_default")]
+    pub layout: Layout,
+    pub packages: BTreeMap<String, LockedPackage>,
+    /// Heights picked for packages the roots pinned at different heights,
+    /// see [select_heights]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub selections: Vec<Selection>,
+}
+
+impl Default for Lockfile {
+    fn default() -> Self {
+        Self {
+            version: LOCKFILE_VERSION,
+            roots: Vec::new(),
+            layout: Layout::default(),
+            packages: BTreeMap::new(),
+            selections: Vec::new(),
+        }
+    }
+}
+
+impl Lockfile {
+    /// Reads a lockfile from disk
+    pub fn load(path: &Path) -> Result<Self, LockError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parses lockfile JSON, rejecting package paths and file names that
+    /// would reach outside the directories they're joined to, see
+    /// [check_relative]
+    pub fn parse(data: &str) -> Result<Self, LockError> {
+        let lock: Self = serde_json::from_str(data)?;
+        for (pkg_path, locked) in &lock.packages {
+            check_relative(pkg_path)?;
+            for file in locked.files.keys() {
+                check_relative(file)?;
+            }
+        }
+        Ok(lock)
+    }
+
+    /// Reads a lockfile that may be missing or empty, as git passes for the
+    /// base of a merge without a common ancestor
+    pub fn load_or_default(path: &Path) -> Result<Self, LockError> {
+        match fs::read_to_string(path) {
+            Ok(data) if data.trim().is_empty() => Ok(Self::default()),
+            Ok(data) => Self::parse(&data),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Reads a lockfile from disk, returning `None` if it doesn't exist
+    pub fn load_if_exists(path: &Path) -> Result<Option<Self>, LockError> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        Self::load(path).map(Some)
+    }
+
+    /// Writes the lockfile to disk as pretty-printed JSON, so that a crash
+    /// leaves the previous lockfile or this one, see [write_durably]
+    pub fn save(&self, path: &Path) -> Result<(), LockError> {
+        let mut json = serde_json::to_string_pretty(self)?;
+        json.push('\n');
+        write_durably(path, json.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn get(&self, pkg_path: &str) -> Option<&LockedPackage> {
+        self.packages.get(pkg_path)
+    }
+
+    pub fn insert(&mut self, package: LockedPackage) {
+        self.packages.insert(package.path.clone(), package);
+    }
+
+    /// Directory of `pkg_path` under `target_dir`, following the layout
+    pub fn package_dir(&self, target_dir: &Path, pkg_path: &str) -> PathBuf {
+        self.layout.package_dir(target_dir, pkg_path)
+    }
+
+    /// Directories under `target_dir` of the locked packages nested inside
+    /// `pkg_path`, which [LockedPackage::from_dir] must leave out
+    pub fn nested_dirs(&self, pkg_path: &str, target_dir: &Path) -> Vec<PathBuf> {
+        nested_dirs(self.layout, pkg_path, self.packages.keys(), target_dir)
+    }
+
+    /// Returns true if `root` was locked here and every locked package is
+    /// still intact under `target_dir`, meaning a re-run would have nothing
+    /// to do.
+    pub fn is_up_to_date(&self, root: &str, target_dir: &Path) -> bool {
+        self.roots.iter().any(|r| r == root)
+            && self
+                .packages
+                .values()
+                .all(|pkg| pkg.is_intact(&self.package_dir(target_dir, &pkg.path)))
+    }
+
+    /// Merges the lockfiles of several roots into a single closure.
+    ///
+    /// Each package keeps the highest height pinned by any root. Roots that
+    /// pinned the same package at that height must agree on its hash,
+    /// otherwise [LockError::HashConflict] is returned.
+    pub fn merge_roots(roots: &[(String, Lockfile)]) -> Result<(Self, Vec<Selection>), LockError> {
+        let mut pins = Vec::new();
+        for (root, lock) in roots {
+            for pkg in lock.packages.values() {
+                pins.push(Pin {
+                    root: root.clone(),
+                    path: pkg.path.clone(),
+                    height: pkg.height.unwrap_or(0),
+                    hash: pkg.hash.clone(),
+                });
+            }
+        }
+
+        let selections = select_heights(&pins)?;
+        let mut merged = Lockfile {
+            roots: roots.iter().map(|(root, _)| root.clone()).collect(),
+            ..Default::default()
+        };
+
+        for selection in &selections {
+            // take the file list from whichever root pinned the winning height
+            let files = roots
+                .iter()
+                .filter_map(|(_, lock)| lock.get(&selection.path))
+                .find(|pkg| pkg.height.unwrap_or(0) == selection.height)
+                .map(|pkg| pkg.files.clone())
+                .unwrap_or_default();
+
+            merged.insert(LockedPackage {
+                path: selection.path.clone(),
+                height: Some(selection.height).filter(|h| *h > 0),
+                hash: selection.hash.clone(),
+                files,
+                required_by: selection.roots.clone(),
+            });
+        }
+
+        merged.selections = selections.clone();
+        Ok((merged, selections))
+    }
+
+    /// Three-way merges two lockfiles that diverged from `base`.
+    ///
+    /// Works per package rather than per line: a change made on one side is
+    /// kept, packages added on either side are all kept, and both sides
+    /// locking the same hash is never a conflict. Only a package whose hash
+    /// diverged on both sides conflicts; the merged result keeps our entry
+    /// for it.
+    pub fn merge3(
+        base: &Lockfile,
+        ours: &Lockfile,
+        theirs: &Lockfile,
+    ) -> (Self, Vec<MergeConflict>) {
+        let mut merged = Lockfile {
+            version: ours.version.max(theirs.version),
+            roots: merge_roots_list(&base.roots, &ours.roots, &theirs.roots),
+            layout: ours.layout,
+            packages: BTreeMap::new(),
+            selections: if ours.selections == base.selections {
+                theirs.selections.clone()
+            } else {
+                ours.selections.clone()
+            },
+        };
+        let mut conflicts = Vec::new();
+
+        let paths: BTreeSet<&String> = ours.packages.keys().chain(theirs.packages.keys()).collect();
+        for path in paths {
+            let b = base.get(path);
+            let o = ours.get(path);
+            let t = theirs.get(path);
+
+            let picked = if o == t || t == b {
+                o.cloned()
+            } else if o == b {
+                t.cloned()
+            } else {
+                match (o, t) {
+                    (Some(o), Some(t)) if o.hash == t.hash => Some(merge_entries(o, t)),
+                    // removed on one side but changed on the other: keep the package
+                    (Some(kept), None) | (None, Some(kept)) => Some(kept.clone()),
+                    (Some(o), Some(t)) => {
+                        conflicts.push(MergeConflict {
+                            path: path.clone(),
+                            ours: o.hash.clone(),
+                            theirs: t.hash.clone(),
+                        });
+                        Some(o.clone())
+                    }
+                    (None, None) => None,
+                }
+            };
+
+            if let Some(entry) = picked {
+                merged.insert(entry);
+            }
+        }
+
+        (merged, conflicts)
+    }
+
+    /// Lists the packages added, removed, moved or changed going from `self`
+    /// to `newer`.
+    ///
+    /// A removed package and an added one sharing at least [MOVE_SIMILARITY]
+    /// percent of their file contents are reported as a move, and files of a
+    /// changed package that only changed names as renames.
+    pub fn diff(&self, newer: &Lockfile) -> LockDiff {
+        let mut diff = LockDiff::default();
+        let paths: BTreeSet<&String> = self.packages.keys().chain(newer.packages.keys()).collect();
+        for path in paths {
+            match (self.get(path), newer.get(path)) {
+                (None, Some(_)) => diff.added.push(path.clone()),
+                (Some(_), None) => diff.removed.push(path.clone()),
+                (Some(old), Some(new)) if old.hash != new.hash || old.height != new.height => {
+                    diff.changed.push(ChangedPackage {
+                        path: path.clone(),
+                        old_hash: old.hash.clone(),
+                        new_hash: new.hash.clone(),
+                        old_height: old.height,
+                        new_height: new.height,
+                        renamed_files: renamed_files(&old.files, &new.files),
+                    })
+                }
+                _ => {}
+            }
+        }
+        self.detect_moves(newer, &mut diff);
+        diff
+    }
+
+    /// Pairs removed packages with the added packages most similar to them,
+    /// best matches first
+    fn detect_moves(&self, newer: &Lockfile, diff: &mut LockDiff) {
+        // content hash -> added packages holding a file with that content
+        let mut index: HashMap<&str, BTreeSet<&str>> = HashMap::new();
+        for path in &diff.added {
+            for hash in newer.packages[path].files.values() {
+                index
+                    .entry(hash.as_str())
+                    .or_default()
+                    .insert(path.as_str());
+            }
+        }
+
+        let mut candidates = Vec::new();
+        for from in &diff.removed {
+            let old = &self.packages[from];
+            let mut shared: BTreeMap<&str, usize> = BTreeMap::new();
+            let hashes: BTreeSet<&String> = old.files.values().collect();
+            for hash in hashes {
+                for to in index.get(hash.as_str()).into_iter().flatten() {
+                    *shared.entry(*to).or_default() += 1;
+                }
+            }
+            // entries locked without per-file hashes can only match exactly
+            for to in &diff.added {
+                let new = &newer.packages[to];
+                if old.files.is_empty() && new.files.is_empty() && old.hash == new.hash {
+                    candidates.push((100, from.as_str(), to.as_str()));
+                }
+            }
+            for (to, count) in shared {
+                let files = old.files.len().max(newer.packages[to].files.len());
+                let similarity = (count * 100 / files) as u8;
+                if similarity >= MOVE_SIMILARITY {
+                    candidates.push((similarity, from.as_str(), to));
+                }
+            }
+        }
+        candidates.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(b.1)).then(a.2.cmp(b.2)));
+
+        let (mut moved_from, mut moved_to) = (BTreeSet::new(), BTreeSet::new());
+        for (similarity, from, to) in candidates {
+            if moved_from.contains(from) || moved_to.contains(to) {
+                continue;
+            }
+            moved_from.insert(from.to_string());
+            moved_to.insert(to.to_string());
+            let (old, new) = (&self.packages[from], &newer.packages[to]);
+            diff.moved.push(MovedPackage {
+                from: from.to_string(),
+                to: to.to_string(),
+                old_hash: old.hash.clone(),
+                new_hash: new.hash.clone(),
+                similarity,
+                renamed_files: renamed_files(&old.files, &new.files),
+            });
+        }
+
+        diff.moved.sort_by(|a, b| a.from.cmp(&b.from));
+        diff.removed.retain(|path| !moved_from.contains(path));
+        diff.added.retain(|path| !moved_to.contains(path));
+    }
+}
+
+/// Package-level difference between two lockfiles
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LockDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub moved: Vec<MovedPackage>,
+    pub changed: Vec<ChangedPackage>,
+}
+
+impl LockDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.moved.is_empty()
+            && self.changed.is_empty()
+    }
+}
+
+/// A package removed from one path and added under another with mostly the
+/// same files
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MovedPackage {
+    pub from: String,
+    pub to: String,
+    pub old_hash: String,
+    pub new_hash: String,
+    /// Percentage of files with identical content (100 for a pure move)
+    pub similarity: u8,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub renamed_files: Vec<RenamedFile>,
+}
+
+/// A file whose content is unchanged but whose name is not
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RenamedFile {
+    pub from: String,
+    pub to: String,
+}
+
+/// A package locked on both sides of a [LockDiff] with a different hash or height
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChangedPackage {
+    pub path: String,
+    pub old_hash: String,
+    pub new_hash: String,
+    pub old_height: Option<u64>,
+    pub new_height: Option<u64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub renamed_files: Vec<RenamedFile>,
+}
+
+/// Pairs files only present on one side that have the same content
+fn renamed_files(
+    old: &BTreeMap<String, String>,
+    new: &BTreeMap<String, String>,
+) -> Vec<RenamedFile> {
+    let mut added: Vec<(&String, &String)> = new
+        .iter()
+        .filter(|(name, _)| !old.contains_key(*name))
+        .collect();
+    let mut renamed = Vec::new();
+    for (name, hash) in old.iter().filter(|(name, _)| !new.contains_key(*name)) {
+        if let Some(idx) = added.iter().position(|(_, h)| *h == hash) {
+            let (to, _) = added.remove(idx);
+            renamed.push(RenamedFile {
+                from: name.clone(),
+                to: to.clone(),
+            });
+        }
+    }
+    renamed
+}
+
+/// Merges root lists, dropping roots that either side removed from the base
+fn merge_roots_list(base: &[String], ours: &[String], theirs: &[String]) -> Vec<String> {
+    let mut roots = Vec::new();
+    for root in ours.iter().chain(theirs) {
+        let removed = base.contains(root) && (!ours.contains(root) || !theirs.contains(root));
+        if !removed && !roots.contains(root) {
+            roots.push(root.clone());
+        }
+    }
+    roots
+}
+
+/// Combines two entries that locked the same content
+fn merge_entries(ours: &LockedPackage, theirs: &LockedPackage) -> LockedPackage {
+    let mut merged = ours.clone();
+    merged.height = ours.height.max(theirs.height);
+    for root in &theirs.required_by {
+        if !merged.required_by.contains(root) {
+            merged.required_by.push(root.clone());
+        }
+    }
+    merged.required_by.sort();
+    merged
+}
+
+/// A package whose hash diverged on both sides of a merge
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeConflict {
+    pub path: String,
+    pub ours: String,
+    pub theirs: String,
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: ours locks {}, theirs locks {}",
+            self.path, self.ours, self.theirs
+        )
+    }
+}
+
+/// A package height pinned by one root of a workspace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pin {
+    pub root: String,
+    pub path: String,
+    pub height: u64,
+    pub hash: String,
+}
+
+/// Outcome of height selection for a single package
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Selection {
+    pub path: String,
+    pub height: u64,
+    pub hash: String,
+    /// Roots that pinned the selected height
+    pub roots: Vec<String>,
+    /// Lower `(root, height)` pins that were overridden
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overridden: Vec<(String, u64)>,
+}
+
+impl fmt::Display for Selection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} @ {} (pinned by {})",
+            self.path,
+            self.height,
+            self.roots.join(", ")
+        )?;
+        if !self.overridden.is_empty() {
+            let overridden: Vec<String> = self
+                .overridden
+                .iter()
+                .map(|(root, height)| format!("{} from {}", height, root))
+                .collect();
+            write!(f, "; overrides {}", overridden.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Selects one height per package across all pins.
+///
+/// The highest pinned height wins. Output is sorted by package path so the
+/// result doesn't depend on the order roots were visited in.
+pub fn select_heights(pins: &[Pin]) -> Result<Vec<Selection>, LockError> {
+    let mut by_path: BTreeMap<&str, Vec<&Pin>> = BTreeMap::new();
+    for pin in pins {
+        by_path.entry(pin.path.as_str()).or_default().push(pin);
+    }
+
+    let mut selections = Vec::with_capacity(by_path.len());
+
+    for (path, mut pkg_pins) in by_path {
+        // stable tie-breaking so reports list roots in a fixed order
+        pkg_pins.sort_by(|a, b| b.height.cmp(&a.height).then(a.root.cmp(&b.root)));
+        let height = pkg_pins[0].height;
+
+        let (winners, losers): (Vec<&Pin>, Vec<&Pin>) =
+            pkg_pins.into_iter().partition(|p| p.height == height);
+
+        let hash = &winners[0].hash;
+        if winners.iter().any(|p| &p.hash != hash) {
+            return Err(LockError::HashConflict {
+                path: path.to_string(),
+                height,
+                pins: winners
+                    .iter()
+                    .map(|p| (p.root.clone(), p.hash.clone()))
+                    .collect(),
+            });
+        }
+
+        let mut roots: Vec<String> = winners.iter().map(|p| p.root.clone()).collect();
+        roots.dedup();
+
+        selections.push(Selection {
+            path: path.to_string(),
+            height,
+            hash: hash.clone(),
+            roots,
+            overridden: losers.iter().map(|p| (p.root.clone(), p.height)).collect(),
+        });
+    }
+
+    Ok(selections)
+}
+
+/// Returns the hex-encoded blake3 hash of some content
+pub fn hash_content(content: &[u8]) -> String {
+    blake3::hash(content).to_hex().to_string()
+}
+
+/// Computes a package hash from its per-file hashes.
+///
+/// File names are part of the digest, so renaming a file changes the hash.
+pub fn hash_files(files: &BTreeMap<String, String>) -> String {
+    let mut hasher = blake3::Hasher::new();
+    for (name, hash) in files {
+        hasher.update(name.as_bytes());
+        hasher.update(b"\0");
+        hasher.update(hash.as_bytes());
+        hasher.update(b"\n");
+    }
+    hasher.finalize().to_hex().to_string()
+}
+
+/// Hashes every file under `dir`, returning the package hash and per-file hashes
+pub fn hash_dir(dir: &Path) -> Result<(String, BTreeMap<String, String>), LockError> {
+    hash_dir_excluding(dir, &[])
+}
+
+/// Like [hash_dir], but skips the given subdirectories entirely
+pub fn hash_dir_excluding(
+    dir: &Path,
+    excluded: &[PathBuf],
+) -> Result<(String, BTreeMap<String, String>), LockError> {
+    let mut files = BTreeMap::new();
+    collect_file_hashes(dir, dir, excluded, &mut files)?;
+    Ok((hash_files(&files), files))
+}
+
+fn collect_file_hashes(
+    root: &Path,
+    dir: &Path,
+    excluded: &[PathBuf],
+    files: &mut BTreeMap<String, String>,
+) -> Result<(), LockError> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if !excluded.contains(&path) {
+                collect_file_hashes(root, &path, excluded, files)?;
+            }
+        } else if path.file_name().and_then(|s| s.to_str()) != Some(LOCKFILE_NAME) {
+            let relative = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            files.insert(relative, hash_content(&fs::read(&path)?));
+        }
+    }
+    Ok(())
+}
@@ -1,12 +1,48 @@
+use clap::parser::ValueSource;
 use clap::{Arg, Command};
-use gget::fetch::PackageManager;
-use gget::parallel::ParallelDownloadOptions;
+use gget::archive::{
+    export_archive, import_archive, plan_import, seed_from_archive, ArchiveFormat, ExportOptions,
+    ImportOptions,
+};
+use gget::audit::Audit;
+use gget::cache::{parse_ttl, CacheTtls, MemoryStorage};
+use gget::dependency::{DependencyGraph, SatResolver, TestFiles};
+use gget::deploy::{scan_packages, DeployOptions, DeployPlan};
+use gget::diff::{diff_package, Against};
+use gget::endpoint::{check_endpoints, query_height, query_node_status, select_endpoint};
+use gget::fetch::{PackageManager, PackageManagerBuilder, DEFAULT_CACHE_TTL};
+use gget::gnomod::{add_requires_to_file, module_imports, GnoMod, GNOMOD_NAME};
+use gget::layout::Layout;
+use gget::limits::{
+    parse_size, DownloadLimits, DEFAULT_MAX_FILES, DEFAULT_MAX_FILE_SIZE, DEFAULT_MAX_PACKAGE_SIZE,
+};
+use gget::lint::ValidationReport;
+use gget::lock::{Lockfile, LOCKFILE_NAME};
+use gget::network::Network;
+use gget::parallel::{DownloadSummary, PackageReport, ParallelDownloadOptions};
+use gget::paths::CACHE_DIR_ENV;
+use gget::policy::{Policy, PolicyMode};
+use gget::ratelimit::RateLimit;
+use gget::rdeps::{ImportPaths, ReverseDependencies};
+use gget::report::{render, ReportFormat};
+use gget::repro::repro_check;
+use gget::requirements::Requirements;
+use gget::review::{committed_lockfile, DependencyReview};
+use gget::source::{parse_git_source, GitSource, PackageSource};
+use gget::tidy::{tidy, TidyOptions};
+use gget::timings::Timings;
+use gget::trash::{Trash, TRASH_DIR};
+use gget::verify::{verify_tree, ChecksumManifest};
+use gget::watch::{WatchError, WatchUpdate, Watcher};
+use gget::workspace::{find_modules, init_workspace, Workspace, WORKSPACE_NAME};
 use gget::DEFAULT_RPC_ENDPOINT;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing_subscriber::layer::SubscriberExt;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let matches = Command::new("gget")
+    let command = Command::new("gget")
         .version("0.1.0")
         .arg(
             Arg::new("add")
@@ -19,15 +55,47 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .short('o')
                 .long("output")
                 .value_name("DIR")
-                .help("Output directory for downloaded files.\nDefault: ./gno")
-                .default_value("."),
+                .help("Output directory for downloaded packages, laid out by --layout.\nDefault: ./gno")
+                .default_value("gno")
+                .global(true),
+        )
+        .arg(
+            Arg::new("layout")
+                .long("layout")
+                .value_name("LAYOUT")
+                .help("Where each package goes in the output directory: flat (straight into it, a single package only),\nby-name (e.g. gno/avl) or by-import-path (e.g. gno/gno.land/p/demo/avl).\nDefault: by-import-path")
+                .value_parser(clap::value_parser!(Layout))
+                .default_value("by-import-path")
+                .global(true),
         )
         .arg(
             Arg::new("rpc-endpoint")
                 .long("rpc-endpoint")
                 .value_name("URL")
-                .help("RPC endpoint URL.\nDefault: https://rpc.gno.land:443")
-                .default_value(DEFAULT_RPC_ENDPOINT),
+                .help("RPC endpoint URL (repeatable; stale endpoints are skipped).\nws:// and wss:// URLs (e.g. ws://localhost:26657/websocket) use one WebSocket connection.\nDefault: https://rpc.gno.land:443")
+                .default_value(DEFAULT_RPC_ENDPOINT)
+                .action(clap::ArgAction::Append)
+                .global(true),
+        )
+        .arg(
+            Arg::new("network")
+                .long("network")
+                .value_name("NAME")
+                .help(format!(
+                    "Network to download from: {}.\nUses its RPC endpoint unless --rpc-endpoint is given, checks the node runs its chain, and caches its packages apart from other networks'",
+                    Network::names().join(", ")
+                ))
+                .value_parser(clap::builder::PossibleValuesParser::new(Network::names()))
+                .global(true),
+        )
+        .arg(
+            Arg::new("max-height-lag")
+                .long("max-height-lag")
+                .value_name("BLOCKS")
+                .help("Blocks an endpoint may trail the others before it's considered stale")
+                .value_parser(clap::value_parser!(u64))
+                .default_value("10")
+                .global(true),
         )
         .arg(
             Arg::new("resolve-deps")
@@ -39,13 +107,57 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             Arg::new("validate")
                 .long("validate")
                 .help("Validate downloaded packages")
-                .action(clap::ArgAction::SetTrue),
+                .action(clap::ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            Arg::new("fsync")
+                .long("fsync")
+                .help("Sync every downloaded file to disk before moving it into place")
+                .action(clap::ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            Arg::new("strict")
+                .long("strict")
+                .help("Fail --validate on syntax errors instead of only reporting them")
+                .action(clap::ArgAction::SetTrue)
+                .global(true),
         )
         .arg(
             Arg::new("force")
                 .long("force")
                 .help("Force download even if package already exists")
-                .action(clap::ArgAction::SetTrue),
+                .action(clap::ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            Arg::new("refresh")
+                .long("refresh")
+                .help("Re-resolve dependencies even if the lockfile says everything is up to date,\nwithout the imports cached from earlier runs, and ask the node again about\npackages it recently reported missing")
+                .action(clap::ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            Arg::new("skip-test-deps")
+                .long("skip-test-deps")
+                .help("Don't resolve what only _test.gno and _filetest.gno files import")
+                .action(clap::ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .help("Print which files would be created or overwritten, and their sizes, without writing anything.\nSupported by downloads, install, tidy, work sync and import")
+                .action(clap::ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            Arg::new("unsafe-direct")
+                .long("unsafe-direct")
+                .help("Write files straight into the output directory instead of swapping in a complete copy.\nA failed download can leave a partial package behind (deprecated)")
+                .action(clap::ArgAction::SetTrue)
+                .global(true),
         )
         .arg(
             Arg::new("parallel")
@@ -58,51 +170,865 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .long("max-concurrent")
                 .value_name("N")
                 .help("Maximum number of concurrent downloads")
-                .default_value("4"),
+                .default_value("4")
+                .global(true),
+        )
+        .arg(
+            Arg::new("rate-limit")
+                .long("rate-limit")
+                .value_name("RPS[,BYTES]")
+                .help("Limit RPC requests per second, optionally with a byte rate (e.g. 10 or 10,512k)")
+                .value_parser(clap::value_parser!(RateLimit))
+                .global(true),
+        )
+        .arg(
+            Arg::new("endpoint-concurrency")
+                .long("endpoint-concurrency")
+                .value_name("N")
+                .help("Maximum RPC requests in flight to each endpoint when several are configured;\nan endpoint answering 429 or timing out gets fewer until it recovers")
+                .value_parser(clap::value_parser!(usize))
+                .global(true),
+        )
+        .arg(
+            Arg::new("max-files")
+                .long("max-files")
+                .value_name("N")
+                .help(format!("Refuse packages with more files than this [default: {}]", DEFAULT_MAX_FILES))
+                .value_parser(clap::value_parser!(u64))
+                .global(true),
+        )
+        .arg(
+            Arg::new("max-file-size")
+                .long("max-file-size")
+                .value_name("SIZE")
+                .help(format!(
+                    "Refuse packages with a file larger than this, in bytes or with a k, m or g suffix [default: {}m]",
+                    DEFAULT_MAX_FILE_SIZE >> 20
+                ))
+                .value_parser(parse_size)
+                .global(true),
+        )
+        .arg(
+            Arg::new("max-package-size")
+                .long("max-package-size")
+                .value_name("SIZE")
+                .help(format!(
+                    "Refuse packages larger than this in total [default: {}m]",
+                    DEFAULT_MAX_PACKAGE_SIZE >> 20
+                ))
+                .value_parser(parse_size)
+                .global(true),
+        )
+        .arg(
+            Arg::new("max-total-size")
+                .long("max-total-size")
+                .value_name("SIZE")
+                .help("Stop downloading once a run has fetched more than this, e.g. 500m")
+                .value_parser(parse_size)
+                .global(true),
+        )
+        .arg(
+            Arg::new("cache")
+                .long("cache")
+                .value_name("BACKEND")
+                .help("Where to cache RPC responses: disk, or memory to write nothing (e.g. on a read-only filesystem)")
+                .value_parser(CACHE_BACKENDS)
+                .default_value("disk")
+                .global(true),
+        )
+        .arg(
+            Arg::new("cache-dir")
+                .long("cache-dir")
+                .value_name("DIR")
+                .help(format!(
+                    "Directory of the disk cache, shared by every working directory.\n\
+                     Default: ${}, or gget/packages under the platform cache directory \
+                     ($XDG_CACHE_HOME or ~/.cache, ~/Library/Caches, %LOCALAPPDATA%)",
+                    CACHE_DIR_ENV
+                ))
+                .global(true),
+        )
+        .args(cache_ttl_args())
+        .arg(
+            Arg::new("cookies")
+                .long("cookies")
+                .help("Keep cookies set by HTTP endpoints, for gateways that hold sessions in cookies")
+                .action(clap::ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            Arg::new("proxy")
+                .long("proxy")
+                .value_name("URL")
+                .help("Send HTTP requests through this proxy. Default: $HTTPS_PROXY, $HTTP_PROXY or $ALL_PROXY, minus the hosts in $NO_PROXY")
+                .global(true),
+        )
+        .arg(
+            Arg::new("http2-prior-knowledge")
+                .long("http2-prior-knowledge")
+                .help("Speak HTTP/2 to http:// endpoints without upgrading first; https:// endpoints negotiate it on their own")
+                .action(clap::ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            Arg::new("ca-cert")
+                .long("ca-cert")
+                .value_name("FILE")
+                .help("Also trust the CA certificates in this PEM file, for TLS-intercepting gateways. Repeatable")
+                .action(clap::ArgAction::Append)
+                .global(true),
+        )
+        .arg(
+            Arg::new("login-command")
+                .long("login-command")
+                .value_name("CMD")
+                .help("Shell command printing 'Name: value' headers to send to each HTTP endpoint.\nRuns before the first request, with GGET_ENDPOINT set, and again on 401/403")
+                .global(true),
+        )
+        .arg(
+            Arg::new("git-source")
+                .long("git-source")
+                .value_name("PKG=URL@REV[#SUBDIR]")
+                .help("Fetch PKG from a git repository checked out at REV instead of the chain,\ne.g. gno.land/p/me/draft=https://github.com/me/draft@v0.1.0#pkg. Repeatable")
+                .value_parser(parse_git_source)
+                .action(clap::ArgAction::Append)
+                .global(true),
+        )
+        .arg(
+            Arg::new("local-registry")
+                .long("local-registry")
+                .value_name("DIR")
+                .help("Also look for packages in DIR, laid out by import path (e.g. a checkout of the gno repository's examples/).\nUsed for packages the node doesn't have or can't serve offline. Repeatable")
+                .action(clap::ArgAction::Append)
+                .global(true),
+        )
+        .arg(
+            Arg::new("prefer-local")
+                .long("prefer-local")
+                .help("Read packages found in a --local-registry from it instead of querying the node")
+                .action(clap::ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            Arg::new("trusted-checksums")
+                .long("trusted-checksums")
+                .value_name("FILE")
+                .help("Refuse packages whose hash doesn't match this checksum manifest")
+                .global(true),
+        )
+        .arg(
+            Arg::new("policy")
+                .long("policy")
+                .value_name("FILE")
+                .help("JSON file with allow/deny rules for resolved dependencies")
+                .global(true),
+        )
+        .arg(
+            Arg::new("allow")
+                .long("allow")
+                .value_name("PATTERN")
+                .help("Only allow dependencies matching this pattern (repeatable)")
+                .action(clap::ArgAction::Append)
+                .global(true),
+        )
+        .arg(
+            Arg::new("deny")
+                .long("deny")
+                .value_name("PATTERN")
+                .help("Deny dependencies matching this pattern, e.g. 'gno.land/r/*' (repeatable)")
+                .action(clap::ArgAction::Append)
+                .global(true),
+        )
+        .arg(
+            Arg::new("policy-mode")
+                .long("policy-mode")
+                .value_name("MODE")
+                .help("What to do with disallowed dependencies: fail or warn")
+                .value_parser(["fail", "warn"])
+                .global(true),
+        )
+        .arg(
+            Arg::new("seed")
+                .long("seed")
+                .value_name("N")
+                .help("Seed retry jitter so backoff delays are reproducible")
+                .value_parser(clap::value_parser!(u64))
+                .global(true),
+        )
+        .arg(
+            Arg::new("timings")
+                .long("timings")
+                .help("Print where the run spent its time (resolution, RPC, cache, writes, validation)")
+                .action(clap::ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_name("FORMAT")
+                .help("Output format for summaries and reports")
+                .value_parser(clap::builder::PossibleValuesParser::new(ReportFormat::NAMES))
+                .default_value("human")
+                .global(true),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .help("Shorthand for --format json")
+                .action(clap::ArgAction::SetTrue)
+                .global(true),
+        )
+        .subcommand(
+            Command::new("install")
+                .about("Download the packages required by a gno.mod, plus their dependencies")
+                .arg(
+                    Arg::new("file")
+                        .value_name("GNO_MOD")
+                        .help("Path to the gno.mod file")
+                        .default_value(GNOMOD_NAME),
+                )
+                .arg(
+                    Arg::new("requirements")
+                        .short('r')
+                        .long("requirements")
+                        .value_name("FILE")
+                        .help(
+                            "Install the packages listed in a requirements file instead, \
+                             one path per line with optional `@height=N`, `pin=true` and \
+                             `layout=import-path` options",
+                        )
+                        .conflicts_with("file"),
+                ),
+        )
+        .subcommand(
+            Command::new("tidy")
+                .about(
+                    "Make gno.mod require what the module imports, download missing \
+                     packages, prune vendored ones nothing imports and update gget.lock",
+                )
+                .arg(
+                    Arg::new("file")
+                        .value_name("GNO_MOD")
+                        .help("Path to the gno.mod file")
+                        .default_value(GNOMOD_NAME),
+                )
+                .arg(
+                    Arg::new("check")
+                        .long("check")
+                        .help("Change nothing, exiting with an error if anything is out of sync")
+                        .action(clap::ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("work")
+                .about("Manage a gget.work workspace of several modules")
+                .subcommand_required(true)
+                .arg(
+                    Arg::new("file")
+                        .long("file")
+                        .value_name("GGET_WORK")
+                        .help("Path to the workspace file")
+                        .default_value(WORKSPACE_NAME)
+                        .global(true),
+                )
+                .subcommand(
+                    Command::new("init")
+                        .about(
+                            "Create a workspace file listing module directories, every \
+                             directory with a gno.mod by default",
+                        )
+                        .arg(
+                            Arg::new("dirs")
+                                .value_name("DIR")
+                                .help("Module directories, relative to the workspace file")
+                                .num_args(0..),
+                        ),
+                )
+                .subcommand(
+                    Command::new("sync")
+                        .about(
+                            "Download what the members import from the chain into --output, \
+                             with one lockfile, resolving imports between members locally",
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("lock")
+                .about("Work with gget.lock files")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("merge")
+                        .about("Three-way merge two lockfiles and print the result")
+                        .arg(Arg::new("ours").value_name("OURS").required(true))
+                        .arg(Arg::new("theirs").value_name("THEIRS").required(true))
+                        .arg(Arg::new("base").value_name("BASE").required(true)),
+                )
+                .subcommand(
+                    Command::new("merge-driver")
+                        .about(
+                            "Git merge driver writing the merge into OURS.\n\
+                             Configure with:\n  \
+                             git config merge.gget-lock.driver 'gget lock merge-driver %O %A %B'\n  \
+                             echo 'gget.lock merge=gget-lock' >> .gitattributes",
+                        )
+                        .arg(Arg::new("base").value_name("BASE").required(true))
+                        .arg(Arg::new("ours").value_name("OURS").required(true))
+                        .arg(Arg::new("theirs").value_name("THEIRS").required(true)),
+                ),
+        )
+        .subcommand(
+            Command::new("report")
+                .about("Generate reports about downloaded packages")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("deps").about(
+                        "Summarize the packages locked under --output for code review: \
+                         sizes, hashes, heights, the dependency tree, policy and integrity \
+                         findings, and lockfile changes since the last commit.\n\
+                         Use --format markdown for a PR-ready document.",
+                    ),
+                ),
+        )
+        .subcommand(
+            Command::new("deploy-plan")
+                .about(
+                    "Print the gnokey commands deploying the packages under DIR in dependency order.\n\
+                     Prints a shell script by default; use --format json for a machine-readable plan.",
+                )
+                .arg(
+                    Arg::new("dir")
+                        .value_name("DIR")
+                        .help("Directory containing the packages to deploy")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("key")
+                        .long("key")
+                        .value_name("NAME")
+                        .help("gnokey key signing the transactions")
+                        .default_value("test1"),
+                )
+                .arg(
+                    Arg::new("chain-id")
+                        .long("chain-id")
+                        .value_name("ID")
+                        .default_value("dev"),
+                )
+                .arg(
+                    Arg::new("remote")
+                        .long("remote")
+                        .value_name("ADDR")
+                        .help("Node RPC address")
+                        .default_value("127.0.0.1:26657"),
+                )
+                .arg(
+                    Arg::new("gas-fee")
+                        .long("gas-fee")
+                        .value_name("FEE")
+                        .default_value("1000000ugnot"),
+                )
+                .arg(
+                    Arg::new("gas-wanted")
+                        .long("gas-wanted")
+                        .value_name("GAS")
+                        .value_parser(clap::value_parser!(u64))
+                        .default_value("10000000"),
+                )
+                .arg(
+                    Arg::new("resolver")
+                        .long("resolver")
+                        .value_name("STRATEGY")
+                        .help("How packages are ordered: by topological sort, or by solving their constraints as a SAT problem")
+                        .value_parser(["topo", "sat"])
+                        .default_value("topo"),
+                )
+                .arg(
+                    Arg::new("optional")
+                        .long("optional")
+                        .value_name("PKG")
+                        .help("With --resolver sat, leave PKG and what imports it out when a --conflict requires (repeatable)")
+                        .action(clap::ArgAction::Append),
+                )
+                .arg(
+                    Arg::new("conflict")
+                        .long("conflict")
+                        .value_names(["PKG", "OTHER"])
+                        .help("With --resolver sat, never deploy both PKG and OTHER (repeatable)")
+                        .num_args(2)
+                        .action(clap::ArgAction::Append),
+                ),
+        )
+        .subcommand(
+            Command::new("watch")
+                .about(
+                    "Keep a package under --output in sync with the chain, re-downloading it \
+                     whenever its content hash changes",
+                )
+                .arg(
+                    Arg::new("package")
+                        .value_name("PKG")
+                        .help("Package path to watch")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("interval")
+                        .long("interval")
+                        .value_name("SECONDS")
+                        .help("Seconds between two checks")
+                        .value_parser(clap::value_parser!(u64).range(1..))
+                        .default_value("30"),
+                )
+                .arg(
+                    Arg::new("exec")
+                        .long("exec")
+                        .value_name("COMMAND")
+                        .help(
+                            "Shell command run after each update, with GGET_PACKAGE, GGET_DIR, \
+                             GGET_OLD_HASH and GGET_NEW_HASH set",
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("diff")
+                .about(
+                    "Compare the copy of a package under --output with the chain, printing a \
+                     unified diff per file; exits non-zero when they differ",
+                )
+                .arg(
+                    Arg::new("package")
+                        .value_name("PKG")
+                        .help("Package path to compare")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("against")
+                        .long("against")
+                        .value_name("VERSION")
+                        .help("What to compare with: latest, a block height, or lockfile for the height locked in gget.lock")
+                        .value_parser(clap::value_parser!(Against))
+                        .default_value("latest"),
+                ),
         )
-        .get_matches();
+        .subcommand(
+            Command::new("render")
+                .about("Print what a realm renders, as gnoweb shows it")
+                .arg(
+                    Arg::new("target")
+                        .value_name("REALM[:PATH]")
+                        .help("Realm path, followed by the path passed to its Render function, e.g. gno.land/r/demo/boards:gnolang")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("eval")
+                .about("Evaluate a read-only expression in a realm and print the result")
+                .arg(
+                    Arg::new("realm")
+                        .value_name("REALM")
+                        .help("Realm path, e.g. gno.land/r/demo/boards")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("expr")
+                        .value_name("EXPR")
+                        .help("Expression to evaluate in the realm, e.g. 'GetBoardIDFromName(\"gnolang\")'")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("funcs")
+                .about("List the exported functions of a package with their signatures")
+                .arg(
+                    Arg::new("package")
+                        .value_name("PKG")
+                        .help("Package path to inspect")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("prefetch")
+                .about(
+                    "Fill the cache with packages and everything they import without \
+                     writing any files, e.g. to warm a CI image ahead of offline builds",
+                )
+                .arg(
+                    Arg::new("packages")
+                        .value_name("PKG")
+                        .help("Package paths to prefetch")
+                        .num_args(1..)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("export")
+                .about(
+                    "Pack a package locked under --output into an archive for offline \
+                     transfer; unpack it elsewhere with gget import",
+                )
+                .arg(
+                    Arg::new("package")
+                        .value_name("PKG")
+                        .help("Package path to export")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("archive-format")
+                        .long("archive-format")
+                        .value_name("FORMAT")
+                        .help("Archive format: tar or tar.gz")
+                        .value_parser(clap::value_parser!(ArchiveFormat))
+                        .default_value("tar.gz"),
+                )
+                .arg(
+                    Arg::new("to")
+                        .long("to")
+                        .value_name("FILE")
+                        .help("Archive to write. Default: the package name with the format's extension"),
+                )
+                .arg(
+                    Arg::new("with-deps")
+                        .long("with-deps")
+                        .help("Include every locked package it imports, recursively")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("manifest")
+                        .long("manifest")
+                        .help("Include a gget.lock listing the exported packages, so gget import can check them and seed the cache")
+                        .action(clap::ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("import")
+                .about(
+                    "Unpack an archive written by gget export into --output; with a manifest, \
+                     check the files, add them to gget.lock and seed the cache. Locked \
+                     packages modified since are only overwritten with --force",
+                )
+                .arg(
+                    Arg::new("archive")
+                        .value_name("ARCHIVE")
+                        .help("tar or tar.gz archive to unpack")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("cache")
+                .about("Manage the RPC cache")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("seed")
+                        .about(
+                            "Fill the cache from a vendor tree or an archive written by \
+                             gget export --manifest, so later downloads of its packages \
+                             need no network",
+                        )
+                        .arg(
+                            Arg::new("path")
+                                .value_name("PATH")
+                                .help("Vendor directory or archive to read")
+                                .required(true),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("rdeps")
+                .about(
+                    "List the packages under --output importing PACKAGE, directly or through \
+                     others: what removing or upgrading it could break. Reads gget.lock, or \
+                     scans the tree when there is none",
+                )
+                .arg(
+                    Arg::new("package")
+                        .value_name("PACKAGE")
+                        .help("Package path, e.g. gno.land/p/demo/ufmt")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("why")
+                .about(
+                    "Print every import chain from ROOT to DEPENDENCY among the packages \
+                     under --output, shortest first, to see why DEPENDENCY is in the tree",
+                )
+                .arg(
+                    Arg::new("root")
+                        .value_name("ROOT")
+                        .help("Package the chains start from, e.g. gno.land/r/demo/app")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("dependency")
+                        .value_name("DEPENDENCY")
+                        .help("Package the chains end at, e.g. gno.land/p/demo/ufmt")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("max-paths")
+                        .long("max-paths")
+                        .value_name("N")
+                        .help("Stop after the N shortest chains")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("100"),
+                ),
+        )
+        .subcommand(
+            Command::new("metrics")
+                .about(
+                    "Summarize the import graph of the packages under a directory: size, \
+                     depth, fan-in and fan-out, import cycles and the most imported packages",
+                )
+                .arg(
+                    Arg::new("dir")
+                        .long("dir")
+                        .value_name("DIR")
+                        .help("Directory containing the packages")
+                        .default_value("."),
+                ),
+        )
+        .subcommand(
+            Command::new("audit")
+                .about(
+                    "Warn about packages under --output declared by several directories, \
+                     differing from what the chain serves at their path, on import cycles, \
+                     or imported without being in the tree",
+                )
+                .arg(
+                    Arg::new("no-chain")
+                        .long("no-chain")
+                        .help("Skip downloading packages to compare them with the chain")
+                        .action(clap::ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(Command::new("verify").about(
+            "Re-hash every file under --output and compare it with gget.lock, reporting \
+             modified, missing and extraneous files; exits non-zero when any are found",
+        ))
+        .subcommand(Command::new("repro-check").about(
+            "Download the packages locked under --output afresh and check that the vendor \
+             tree, gget.lock and the chain agree file by file; exits non-zero otherwise",
+        ))
+        .subcommand(
+            Command::new("restore")
+                .about(format!(
+                    "Bring back the version of a package that the last update replaced, \
+                     from {}",
+                    TRASH_DIR
+                ))
+                .arg(
+                    Arg::new("package")
+                        .value_name("PKG")
+                        .help("Package path to restore")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("to")
+                        .long("to")
+                        .value_name("DIR")
+                        .help("Restore into DIR instead of where the package was"),
+                ),
+        )
+        .subcommand(
+            Command::new("doctor").about("Check that the configured RPC endpoints are reachable and in sync"),
+        )
+        .subcommand(Command::new("status").about(
+            "Show the node version, chain id, latest block and whether the node is still \
+             catching up",
+        ))
+        .subcommand_negates_reqs(true);
+    #[cfg(feature = "deploy")]
+    let command = command.args(deploy_args());
+    #[cfg(feature = "redis")]
+    let command = command.args(redis_args());
+    let mut command = command;
+    let matches = command.get_matches_mut();
+    // the bare package form only stands on its own
+    if let (Some(name), Some(pkg_path)) =
+        (matches.subcommand_name(), matches.get_one::<String>("add"))
+    {
+        command
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                format!(
+                    "the package {} can't be given together with `{}`",
+                    pkg_path, name
+                ),
+            )
+            .exit();
+    }
+    // only these honor --dry-run, anything else would write regardless
+    let ignores_dry_run = match matches.subcommand() {
+        None | Some(("install" | "tidy" | "import", _)) => None,
+        Some(("work", sub)) if sub.subcommand_name() == Some("sync") => None,
+        Some((name, sub)) => Some(match sub.subcommand_name() {
+            Some(inner) => format!("{} {}", name, inner),
+            None => name.to_string(),
+        }),
+    };
+    if let Some(name) = ignores_dry_run.filter(|_| matches.get_flag("dry-run")) {
+        command
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                format!("`{}` doesn't support --dry-run", name),
+            )
+            .exit();
+    }
+
+    let timings = matches.get_flag("timings").then(|| {
+        let timings = Timings::new();
+        let subscriber = tracing_subscriber::registry().with(timings.layer());
+        tracing::subscriber::set_global_default(subscriber)
+            .expect("no other tracing subscriber is installed");
+        timings
+    });
+    let started = Instant::now();
+
+    let result = match matches.subcommand() {
+        Some(("install", sub)) => install(sub).await,
+        Some(("tidy", sub)) => tidy_command(sub).await,
+        Some(("work", sub)) => work_command(sub).await,
+        Some(("lock", sub)) => lock_command(sub),
+        Some(("report", sub)) => report_command(sub),
+        Some(("deploy-plan", sub)) => deploy_plan(sub),
+        Some(("watch", sub)) => watch(sub).await,
+        Some(("restore", sub)) => restore(sub),
+        Some(("export", sub)) => export_command(sub),
+        Some(("import", sub)) => import_command(sub).await,
+        Some(("cache", sub)) => cache_command(sub).await,
+        Some(("prefetch", sub)) => prefetch_command(sub).await,
+        Some(("audit", sub)) => audit_command(sub).await,
+        Some(("metrics", sub)) => metrics_command(sub),
+        Some(("rdeps", sub)) => rdeps_command(sub),
+        Some(("why", sub)) => why_command(sub),
+        Some(("verify", sub)) => verify_command(sub),
+        Some(("repro-check", sub)) => repro_check_command(sub).await,
+        Some(("diff", sub)) => diff_command(sub).await,
+        Some(("render", sub)) => render_command(sub).await,
+        Some(("eval", sub)) => eval_command(sub).await,
+        Some(("funcs", sub)) => funcs_command(sub).await,
+        Some(("doctor", sub)) => doctor(sub).await,
+        Some(("status", sub)) => status_command(sub).await,
+        _ => run(&matches).await,
+    };
+    #[cfg(feature = "deploy")]
+    let result = match result {
+        Ok(()) => deploy_downloaded(&matches).await,
+        err => err,
+    };
+
+    // stderr keeps `--format` output parseable
+    if let Some(timings) = timings {
+        eprintln!("\n{}", timings.report(started.elapsed()));
+    }
+
+    result
+}
 
+async fn run(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
     // essential arguments
     let pkg_path = matches.get_one::<String>("add").unwrap();
     let output_dir = matches.get_one::<String>("output").unwrap();
-    let rpc_endpoint = matches.get_one::<String>("rpc-endpoint").unwrap();
+    let rpc_endpoints = rpc_endpoints(matches);
     let target_path = PathBuf::from(output_dir);
+    let package_dir = layout(matches).package_dir(&target_path, pkg_path);
 
     // dependency resolution
     let resolve_deps = matches.get_flag("resolve-deps");
     let validate = matches.get_flag("validate");
     let force = matches.get_flag("force");
+    let refresh = matches.get_flag("refresh");
     let use_parallel = matches.get_flag("parallel");
+    let format = report_format(matches);
+    let quiet = format != ReportFormat::Human;
     let max_concurrent: usize = matches
         .get_one::<String>("max-concurrent")
         .unwrap()
         .parse()
         .unwrap_or(4);
 
-    println!("Downloading package: {}", pkg_path);
-    println!("Output directory: {}", output_dir);
-    println!("RPC endpoint: {}", rpc_endpoint);
+    if !quiet {
+        println!("Downloading package: {}", pkg_path);
+        println!("Output directory: {}", output_dir);
+        println!("RPC endpoint: {}", rpc_endpoints.join(", "));
+    }
+
+    // packages the project's gno.mod replaces with local directories
+    let replacements = match GnoMod::load(Path::new(GNOMOD_NAME)) {
+        Ok(gnomod) => gnomod.replacements(Path::new(".")),
+        Err(_) => Vec::new(),
+    };
+
+    // nothing to do if the lockfile matches what's already on disk, unless
+    // a package comes from a source the lockfile can't tell changed
+    let sourced = !replacements.is_empty() || reads_local_sources(matches);
+    if resolve_deps && !refresh && !force && !sourced {
+        let lock_path = target_path.join(LOCKFILE_NAME);
+        if let Ok(Some(lock)) = Lockfile::load_if_exists(&lock_path) {
+            if lock.is_up_to_date(pkg_path, &target_path) {
+                if quiet {
+                    let summary = up_to_date_summary(lock.packages.keys().cloned().collect());
+                    print!("{}", render(&summary, format)?);
+                } else {
+                    println!("Already up to date");
+                }
+                return Ok(());
+            }
+        }
+    }
 
-    if target_path.exists() && !force {
+    let pm = package_manager(matches)
+        .await?
+        .with_replacements(replacements);
+
+    if matches.get_flag("dry-run") {
+        let plan = pm
+            .plan_download(&[pkg_path], &target_path, use_parallel && resolve_deps)
+            .await?;
+        print!("{}", render(&plan, format)?);
+        return Ok(());
+    }
+
+    // an existing copy is only replaced if it's an unmodified download,
+    // anything else takes --force
+    let locked = Lockfile::load_if_exists(&target_path.join(LOCKFILE_NAME))
+        .ok()
+        .flatten()
+        .and_then(|lock| lock.get(pkg_path).cloned());
+    let mut unchanged = !force
+        && pm
+            .is_unchanged(pkg_path, &package_dir, locked.as_ref())
+            .await?;
+    // without a lock entry that answer comes from the cache, which may
+    // just have expired, so ask the chain before calling the copy modified
+    if !force && !unchanged && locked.is_none() && package_dir.is_dir() {
+        unchanged = diff_package(&pm, pkg_path, &package_dir, &target_path, Against::Latest)
+            .await?
+            .is_empty();
+    }
+    if !force && !unchanged && package_dir.exists() {
         eprintln!(
-            "Package already exists at {}. Use --force to overwrite.",
-            target_path.display()
+            "{} already exists and differs from the downloaded {}. Use --force to overwrite.",
+            package_dir.display(),
+            pkg_path
         );
         std::process::exit(1);
     }
 
-    let pm = PackageManager::new(Some(rpc_endpoint.to_string()), PathBuf::from("cache"));
-
     // Use parallel download if requested and dependencies are being resolved
     if use_parallel && resolve_deps {
-        println!(
-            "Using parallel download with {} concurrent downloads",
-            max_concurrent
-        );
+        if !quiet {
+            println!(
+                "Using parallel download with {} concurrent downloads",
+                max_concurrent
+            );
+        }
 
         let options = ParallelDownloadOptions {
             max_concurrent,
-            show_progress: true,
+            show_progress: !quiet,
+            force,
+            seed: matches.get_one::<u64>("seed").copied(),
+            endpoint_concurrency: matches.get_one::<usize>("endpoint-concurrency").copied(),
             ..Default::default()
         };
 
@@ -111,18 +1037,27 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             .await
         {
             Ok(summary) => {
-                println!("\nDownload complete!");
-                println!("{}", summary);
+                if quiet {
+                    print!("{}", render(&summary, format)?);
+                } else {
+                    println!("\nDownload complete!");
+                    println!("{}", summary);
+                }
+
+                let indirect: Vec<&str> = summary
+                    .packages
+                    .iter()
+                    .map(|report| report.package.as_str())
+                    .chain(summary.up_to_date.iter().map(String::as_str))
+                    .filter(|package| package != pkg_path)
+                    .collect();
+                record_requires(pkg_path, &indirect, quiet);
 
                 if validate {
-                    println!("\nValidating packages...");
-                    match pm.validate_package(&target_path).await {
-                        Ok(()) => println!("All packages are valid!"),
-                        Err(e) => {
-                            eprintln!("Validation failed: {}", e);
-                            std::process::exit(1);
-                        }
+                    if !quiet {
+                        println!("\nValidating packages...");
                     }
+                    validate_dir(&pm, &target_path, format).await?;
                 }
             }
             Err(e) => {
@@ -131,20 +1066,54 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         }
     } else {
-        // Use regular download
-        match pm.download_package(pkg_path, &target_path).await {
-            Ok(()) => {
-                println!("Download complete!");
+        if unchanged {
+            if quiet {
+                let summary = up_to_date_summary(vec![pkg_path.to_string()]);
+                print!("{}", render(&summary, format)?);
+            } else {
+                println!(
+                    "{} is already up to date at {}. Use --force to re-download.",
+                    pkg_path,
+                    package_dir.display()
+                );
+            }
+            return Ok(());
+        }
+
+        let started = Instant::now();
+        let cache_before = pm.cache().stats();
+
+        match pm.download_package(pkg_path, &package_dir).await {
+            Ok(stats) => {
+                if quiet {
+                    let duration = started.elapsed();
+                    let summary = DownloadSummary {
+                        total_packages: 1,
+                        successful: 1,
+                        failed: Vec::new(),
+                        up_to_date: Vec::new(),
+                        skipped: Default::default(),
+                        packages: vec![PackageReport {
+                            package: pkg_path.to_string(),
+                            duration,
+                            stats,
+                            retries: 0,
+                        }],
+                        duration,
+                        cache: pm.cache().stats().since(&cache_before),
+                    };
+                    print!("{}", render(&summary, format)?);
+                } else {
+                    println!("Download complete!");
+                }
+
+                record_requires(pkg_path, &[], quiet);
 
                 if validate {
-                    println!("Validating package...");
-                    match pm.validate_package(&target_path).await {
-                        Ok(()) => println!("Package is valid!"),
-                        Err(e) => {
-                            eprintln!("Validation failed: {}", e);
-                            std::process::exit(1);
-                        }
+                    if !quiet {
+                        println!("Validating package...");
                     }
+                    validate_dir(&pm, &package_dir, format).await?;
                 }
             }
             Err(e) => {
@@ -156,3 +1125,1147 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+/// Handles `gget install [gno.mod]`
+async fn install(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let gnomod_path = PathBuf::from(matches.get_one::<String>("file").unwrap());
+    let target_path = PathBuf::from(matches.get_one::<String>("output").unwrap());
+    let format = report_format(matches);
+    let quiet = format != ReportFormat::Human;
+    let force = matches.get_flag("force");
+    let max_concurrent: usize = matches
+        .get_one::<String>("max-concurrent")
+        .unwrap()
+        .parse()
+        .unwrap_or(4);
+
+    let requirements = matches
+        .get_one::<String>("requirements")
+        .map(|file| load_requirements(&PathBuf::from(file)));
+    let mut replacements = Vec::new();
+    let (source, roots): (PathBuf, Vec<String>) = match &requirements {
+        Some(requirements) => (
+            PathBuf::from(matches.get_one::<String>("requirements").unwrap()),
+            requirements
+                .entries
+                .iter()
+                .map(|r| r.path.clone())
+                .collect(),
+        ),
+        None => {
+            let gnomod = GnoMod::load(&gnomod_path).unwrap_or_else(|e| {
+                eprintln!("Failed to read {}: {}", gnomod_path.display(), e);
+                std::process::exit(1);
+            });
+            warn_unused_requires(&gnomod, &gnomod_path, &target_path);
+            replacements = gnomod.replacements(parent_dir(&gnomod_path));
+            let roots = gnomod.requires.into_iter().map(|r| r.path).collect();
+            (gnomod_path, roots)
+        }
+    };
+    if roots.is_empty() {
+        if !quiet {
+            println!("{} has no requirements", source.display());
+        }
+        return Ok(());
+    }
+
+    let lock = Lockfile::load_if_exists(&target_path.join(LOCKFILE_NAME))
+        .ok()
+        .flatten();
+    // explicit heights must match the lockfile for it to count as up to date
+    let heights_locked = requirements.iter().flat_map(|r| &r.entries).all(|r| {
+        r.height.is_none()
+            || lock
+                .as_ref()
+                .and_then(|lock| lock.packages.get(&r.path))
+                .is_some_and(|pkg| pkg.height == r.height)
+    });
+
+    // nothing to do if the lockfile already covers every requirement,
+    // unless a package comes from a source that may have changed
+    let sourced = !replacements.is_empty()
+        || reads_local_sources(matches)
+        || requirements
+            .iter()
+            .flat_map(|r| &r.entries)
+            .any(|r| r.source.is_some());
+    if !matches.get_flag("refresh") && !force && heights_locked && !sourced {
+        if let Some(lock) = &lock {
+            if roots
+                .iter()
+                .all(|root| lock.is_up_to_date(root, &target_path))
+            {
+                if quiet {
+                    let summary = up_to_date_summary(lock.packages.keys().cloned().collect());
+                    print!("{}", render(&summary, format)?);
+                } else {
+                    println!("Already up to date");
+                }
+                return Ok(());
+            }
+        }
+    }
+
+    if !quiet {
+        println!(
+            "Installing {} requirements from {}",
+            roots.len(),
+            source.display()
+        );
+    }
+
+    let mut pm = package_manager(matches)
+        .await?
+        .with_replacements(replacements);
+    for requirement in requirements.iter().flat_map(|r| &r.entries) {
+        if let Some(source) = &requirement.source {
+            pm = pm.with_source(&requirement.path, PackageSource::Git(source.clone()));
+            continue;
+        }
+        let locked = lock
+            .as_ref()
+            .and_then(|lock| lock.packages.get(&requirement.path))
+            .and_then(|pkg| pkg.height);
+        let height = match (requirement.height, requirement.pin) {
+            (Some(height), _) => height,
+            // keep an existing pin, otherwise pin at the current height
+            (None, true) => match locked {
+                Some(height) => height,
+                None => {
+                    let client = http_settings(matches).build_http_client()?;
+                    query_height(&client, pm.rpc_endpoint()).await?
+                }
+            },
+            (None, false) => continue,
+        };
+        pm = pm.with_pinned_height(&requirement.path, height);
+    }
+    let roots: Vec<&str> = roots.iter().map(String::as_str).collect();
+    if matches.get_flag("dry-run") {
+        let plan = pm.plan_download(&roots, &target_path, true).await?;
+        print!("{}", render(&plan, format)?);
+        return Ok(());
+    }
+
+    let options = ParallelDownloadOptions {
+        max_concurrent,
+        show_progress: !quiet,
+        force,
+        seed: matches.get_one::<u64>("seed").copied(),
+        endpoint_concurrency: matches.get_one::<usize>("endpoint-concurrency").copied(),
+        ..Default::default()
+    };
+
+    match pm
+        .download_roots_with_deps_parallel(&roots, &target_path, options)
+        .await
+    {
+        Ok(summary) => {
+            if quiet {
+                print!("{}", render(&summary, format)?);
+            } else {
+                println!("\nInstall complete!");
+            }
+
+            if matches.get_flag("validate") {
+                if !quiet {
+                    println!("\nValidating packages...");
+                }
+                validate_dir(&pm, &target_path, format).await?;
+            }
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    Ok(())
+}
+
+/// Handles `gget tidy [gno.mod]`
+async fn tidy_command(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let gnomod_path = PathBuf::from(matches.get_one::<String>("file").unwrap());
+    let target_path = PathBuf::from(matches.get_one::<String>("output").unwrap());
+    let check = matches.get_flag("check");
+    let dry_run = matches.get_flag("dry-run");
+    let max_concurrent: usize = matches
+        .get_one::<String>("max-concurrent")
+        .unwrap()
+        .parse()
+        .unwrap_or(4);
+    let pm = package_manager(matches).await?.with_quiet(true);
+
+    let options = TidyOptions {
+        // a dry run is a check that doesn't fail on what it finds
+        check: check || dry_run,
+        download: ParallelDownloadOptions {
+            max_concurrent,
+            seed: matches.get_one::<u64>("seed").copied(),
+            endpoint_concurrency: matches.get_one::<usize>("endpoint-concurrency").copied(),
+            ..Default::default()
+        },
+    };
+    let report = tidy(&pm, &gnomod_path, &target_path, options).await?;
+    print!("{}", render(&report, report_format(matches))?);
+    if check && !report.is_tidy() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Adds the package `gget add` fetched, and the `indirect` ones it pulled
+/// in, to the gno.mod of the current directory if there is one
+fn record_requires(pkg_path: &str, indirect: &[&str], quiet: bool) {
+    let path = Path::new(GNOMOD_NAME);
+    if !path.is_file() {
+        return;
+    }
+    match add_requires_to_file(path, &[pkg_path], indirect) {
+        Ok(added) => {
+            if !quiet {
+                for package in added {
+                    println!("Added {} to {}", package, path.display());
+                }
+            }
+        }
+        Err(e) => eprintln!("Warning: could not update {}: {}", path.display(), e),
+    }
+}
+
+/// Directory of the file at `path`, `.` for a bare file name
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+/// Warns about the requirements of the gno.mod at `path` that none of its
+/// module's files import
+fn warn_unused_requires(gnomod: &GnoMod, path: &Path, vendor_dir: &Path) {
+    match module_imports(parent_dir(path), vendor_dir) {
+        Ok(imports) => {
+            for require in gnomod.unused_requires(&imports) {
+                eprintln!(
+                    "Warning: {} is required by {} but not imported",
+                    require.path,
+                    path.display()
+                );
+            }
+        }
+        Err(e) => eprintln!(
+            "Warning: could not check which requirements are imported: {}",
+            e
+        ),
+    }
+}
+
+/// Reads a requirements file, exiting on errors and on options gget can't
+/// honor yet
+fn load_requirements(path: &Path) -> Requirements {
+    let requirements = Requirements::load(path).unwrap_or_else(|e| {
+        eprintln!("Failed to read {}: {}", path.display(), e);
+        std::process::exit(1);
+    });
+    for requirement in &requirements.entries {
+        if let Some(layout) = requirement.layout.as_deref() {
+            if layout != "import-path" {
+                eprintln!(
+                    "{}: unsupported layout `{}` for {}; only `import-path` is supported",
+                    path.display(),
+                    layout,
+                    requirement.path
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+    requirements
+}
+
+/// Handles `gget work init` and `gget work sync`
+async fn work_command(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let (name, sub) = matches.subcommand().expect("work requires a subcommand");
+    let path = PathBuf::from(sub.get_one::<String>("file").unwrap());
+    let target_path = PathBuf::from(sub.get_one::<String>("output").unwrap());
+    let root = parent_dir(&path).to_path_buf();
+
+    if name == "init" {
+        let dirs: Vec<PathBuf> = match sub.get_many::<String>("dirs") {
+            Some(dirs) => dirs.map(PathBuf::from).collect(),
+            None => find_modules(&root, &target_path)?,
+        };
+        // fails on directories without a usable gno.mod
+        let workspace = Workspace::from_dirs(root, &dirs)?;
+        init_workspace(&path, &dirs)?;
+        println!(
+            "Created {} with {} modules",
+            path.display(),
+            workspace.members.len()
+        );
+        return Ok(());
+    }
+
+    let workspace = Workspace::load(&path)?;
+    let format = report_format(sub);
+    let quiet = format != ReportFormat::Human;
+    let max_concurrent: usize = sub
+        .get_one::<String>("max-concurrent")
+        .unwrap()
+        .parse()
+        .unwrap_or(4);
+    let pm = package_manager(sub).await?.with_quiet(true);
+    if sub.get_flag("dry-run") {
+        let plan = workspace.plan(&pm, &target_path).await?;
+        print!("{}", render(&plan, format)?);
+        return Ok(());
+    }
+    let options = ParallelDownloadOptions {
+        max_concurrent,
+        show_progress: !quiet,
+        force: sub.get_flag("force"),
+        seed: sub.get_one::<u64>("seed").copied(),
+        endpoint_concurrency: sub.get_one::<usize>("endpoint-concurrency").copied(),
+        ..Default::default()
+    };
+
+    let (imports, summary) = workspace.sync(&pm, &target_path, options).await?;
+    if quiet {
+        print!("{}", render(&summary, format)?);
+    } else {
+        for (member, locals) in &imports.local {
+            for local in locals {
+                println!("{} uses {} from the workspace", member, local);
+            }
+        }
+        if let Some(lock) = Lockfile::load_if_exists(&target_path.join(LOCKFILE_NAME))? {
+            for selection in &lock.selections {
+                println!("Selected {}", selection);
+            }
+        }
+        println!(
+            "Synced {} modules, {} imports from the chain",
+            workspace.members.len(),
+            imports.external.len()
+        );
+    }
+    if !summary.failed.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Handles `gget lock <merge|merge-driver>`
+fn lock_command(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let (name, sub) = matches.subcommand().expect("lock requires a subcommand");
+    let path = |id: &str| PathBuf::from(sub.get_one::<String>(id).unwrap());
+
+    let base = Lockfile::load_or_default(&path("base"))?;
+    let ours = Lockfile::load_or_default(&path("ours"))?;
+    let theirs = Lockfile::load_or_default(&path("theirs"))?;
+    let (merged, conflicts) = Lockfile::merge3(&base, &ours, &theirs);
+
+    if name == "merge-driver" {
+        // git expects the result in place of our version
+        merged.save(&path("ours"))?;
+    } else {
+        println!("{}", serde_json::to_string_pretty(&merged)?);
+    }
+
+    if !conflicts.is_empty() {
+        eprintln!("Lockfile merge conflicts (kept ours):");
+        for conflict in &conflicts {
+            eprintln!("  {}", conflict);
+        }
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Handles `gget report deps`
+fn report_command(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let (_, sub) = matches.subcommand().expect("report requires a subcommand");
+    let target_dir = PathBuf::from(sub.get_one::<String>("output").unwrap());
+
+    let mut review = DependencyReview::from_dir(&target_dir, &load_policy(sub)?)?;
+    if let Some(committed) = committed_lockfile(&target_dir.join(LOCKFILE_NAME)) {
+        let current = Lockfile::load(&target_dir.join(LOCKFILE_NAME))?;
+        review = review.with_lock_diff(committed.diff(&current));
+    }
+
+    print!("{}", render(&review, report_format(sub))?);
+    Ok(())
+}
+
+/// Handles `gget deploy-plan`
+fn deploy_plan(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let value = |id: &str| matches.get_one::<String>(id).unwrap().as_str();
+    let options = DeployOptions::default()
+        .with_key(value("key"))
+        .with_chain_id(value("chain-id"))
+        .with_remote(value("remote"))
+        .with_gas_fee(value("gas-fee"))
+        .with_gas_wanted(*matches.get_one::<u64>("gas-wanted").unwrap());
+
+    let optional: Vec<&String> = matches.get_many("optional").into_iter().flatten().collect();
+    let conflicts: Vec<&String> = matches.get_many("conflict").into_iter().flatten().collect();
+    let dir = PathBuf::from(value("dir"));
+    let plan = match value("resolver") {
+        "sat" => {
+            let strategy = optional.iter().fold(SatResolver::new(), |strategy, pkg| {
+                strategy.with_optional(pkg)
+            });
+            let strategy = conflicts.chunks(2).fold(strategy, |strategy, pair| {
+                strategy.with_conflict(pair[0], pair[1])
+            });
+            DeployPlan::from_dir_with_strategy(&dir, &options, strategy)?
+        }
+        _ if !optional.is_empty() || !conflicts.is_empty() => {
+            return Err("--optional and --conflict are constraints for --resolver sat".into());
+        }
+        _ => DeployPlan::from_dir(&dir, &options)?,
+    };
+    print!("{}", render(&plan, report_format(matches))?);
+    Ok(())
+}
+
+/// Values of `--cache`
+#[cfg(not(feature = "redis"))]
+const CACHE_BACKENDS: [&str; 2] = ["disk", "memory"];
+#[cfg(feature = "redis")]
+const CACHE_BACKENDS: [&str; 3] = ["disk", "memory", "redis"];
+
+/// Global arguments configuring `--cache redis`
+#[cfg(feature = "redis")]
+fn redis_args() -> [Arg; 3] {
+    [
+        Arg::new("redis-url")
+            .long("redis-url")
+            .value_name("URL")
+            .help("Redis server shared by --cache redis.\nDefault: the REDIS_URL environment variable")
+            .global(true),
+        Arg::new("redis-namespace")
+            .long("redis-namespace")
+            .value_name("PREFIX")
+            .help("Prefix of the keys gget stores in Redis")
+            .default_value(gget::cache::redis::DEFAULT_NAMESPACE)
+            .global(true),
+        Arg::new("redis-pool-size")
+            .long("redis-pool-size")
+            .value_name("N")
+            .help("Connections opened to the Redis server")
+            .value_parser(clap::value_parser!(usize))
+            .default_value("4")
+            .global(true),
+    ]
+}
+
+/// Global arguments setting how long each kind of cache entry stays valid
+fn cache_ttl_args() -> [Arg; 4] {
+    let defaults = CacheTtls::default();
+    let ttl = |name: &'static str, what: &str, default: Duration| {
+        Arg::new(name)
+            .long(name)
+            .value_name("TTL")
+            .help(format!(
+                "How long {} stay cached, in seconds or with an s, m, h or d suffix [default: {}]",
+                what,
+                format_ttl(default)
+            ))
+            .value_parser(parse_ttl)
+            .global(true)
+    };
+    [
+        ttl("files-ttl", "package file lists", defaults.files),
+        ttl("contents-ttl", "file contents", defaults.contents),
+        ttl(
+            "negative-ttl",
+            "packages the node reported missing",
+            defaults.negative,
+        ),
+        ttl(
+            "resolution-ttl",
+            "the imports found in sources",
+            defaults.resolution,
+        ),
+    ]
+}
+
+/// Formats `ttl` in the largest unit [parse_ttl] reads that fits it
+fn format_ttl(ttl: Duration) -> String {
+    let secs = ttl.as_secs();
+    [(24 * 3600, "d"), (3600, "h"), (60, "m")]
+        .into_iter()
+        .find(|(unit, _)| secs > 0 && secs.is_multiple_of(*unit))
+        .map(|(unit, suffix)| format!("{}{}", secs / unit, suffix))
+        .unwrap_or_else(|| format!("{}s", secs))
+}
+
+/// TTLs from the `--*-ttl` flags, the defaults for those not given
+fn cache_ttls(matches: &clap::ArgMatches) -> CacheTtls {
+    let ttl = |name: &str, default: Duration| {
+        matches
+            .get_one::<Duration>(name)
+            .copied()
+            .unwrap_or(default)
+    };
+    let defaults = CacheTtls::default();
+    CacheTtls {
+        files: ttl("files-ttl", defaults.files),
+        contents: ttl("contents-ttl", defaults.contents),
+        negative: ttl("negative-ttl", defaults.negative),
+        resolution: ttl("resolution-ttl", defaults.resolution),
+    }
+}
+
+/// Connects to the Redis server configured for `--cache redis`
+#[cfg(feature = "redis")]
+async fn redis_storage(
+    matches: &clap::ArgMatches,
+) -> Result<gget::cache::redis::RedisStorage, Box<dyn std::error::Error>> {
+    let url = match matches.get_one::<String>("redis-url") {
+        Some(url) => url.clone(),
+        None => std::env::var("REDIS_URL")
+            .map_err(|_| "--cache redis needs --redis-url or REDIS_URL")?,
+    };
+    Ok(gget::cache::redis::RedisStorage::builder(url)
+        .namespace(matches.get_one::<String>("redis-namespace").unwrap())
+        .pool_size(*matches.get_one::<usize>("redis-pool-size").unwrap())
+        .ttl(DEFAULT_CACHE_TTL)
+        .connect_timeout(Duration::from_secs(10))
+        .connect()
+        .await?)
+}
+
+/// Global arguments pushing downloaded packages to a node
+#[cfg(feature = "deploy")]
+fn deploy_args() -> [Arg; 3] {
+    [
+        Arg::new("deploy-to")
+            .long("deploy-to")
+            .value_name("URL")
+            .help(
+                "After downloading, deploy the packages to the node at URL with addpkg \
+                 transactions.\nExample: --deploy-to http://localhost:26657",
+            )
+            .global(true),
+        Arg::new("deploy-key")
+            .long("deploy-key")
+            .value_name("FILE")
+            .help(format!(
+                "File holding the hex-encoded private key signing deploy transactions.\n\
+                 Default: the {} environment variable",
+                gget::deploy::key::KEY_ENV
+            ))
+            .global(true),
+        Arg::new("deploy-chain-id")
+            .long("deploy-chain-id")
+            .value_name("ID")
+            .help("Chain ID of the node given with --deploy-to")
+            .default_value("dev")
+            .global(true),
+    ]
+}
+
+/// Deploys what `gget add` or `gget install` downloaded, if `--deploy-to` is set
+#[cfg(feature = "deploy")]
+async fn deploy_downloaded(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    use gget::deploy::broadcast::{DeployStatus, Deployer};
+    use gget::deploy::key::DeployKey;
+
+    let (installing, sub) = match matches.subcommand() {
+        Some(("install", sub)) => (true, sub),
+        Some(_) => return Ok(()),
+        None => (false, matches),
+    };
+    let Some(remote) = sub.get_one::<String>("deploy-to") else {
+        return Ok(());
+    };
+    // nothing was downloaded
+    if sub.get_flag("dry-run") {
+        return Ok(());
+    }
+    let key = match sub.get_one::<String>("deploy-key") {
+        Some(path) => DeployKey::load(&PathBuf::from(path))?,
+        None => DeployKey::from_env()?,
+    };
+    let options = DeployOptions::default()
+        .with_remote(remote)
+        .with_chain_id(sub.get_one::<String>("deploy-chain-id").unwrap());
+
+    let target_path = PathBuf::from(sub.get_one::<String>("output").unwrap());
+    let layout = layout(sub);
+    let plan = if installing || (sub.get_flag("resolve-deps") && sub.get_flag("parallel")) {
+        // package paths are read back from the directories they were written to
+        if layout != Layout::ByImportPath {
+            return Err(format!(
+                "deploying dependencies needs --layout by-import-path, not {}",
+                layout
+            )
+            .into());
+        }
+        DeployPlan::from_dir(&target_path, &options)?
+    } else {
+        let pkg_path = sub.get_one::<String>("add").unwrap();
+        DeployPlan::single(
+            pkg_path,
+            &layout.package_dir(&target_path, pkg_path),
+            &options,
+        )?
+    };
+
+    let quiet = report_format(sub) != ReportFormat::Human;
+    if !quiet {
+        println!(
+            "\nDeploying {} packages to {} as {}",
+            plan.steps.len(),
+            remote,
+            key.address()
+        );
+    }
+    let deployed = Deployer::new(key, options).deploy(&plan).await?;
+    if !quiet {
+        for package in &deployed {
+            match &package.status {
+                DeployStatus::Deployed { height, .. } => {
+                    println!("  deployed {} at height {}", package.pkg_path, height)
+                }
+                DeployStatus::AlreadyOnChain => {
+                    println!("  skipped {} (already on chain)", package.pkg_path)
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Builds the allow/deny policy from `--policy`, `--allow`, `--deny` and `--policy-mode`
+fn load_policy(matches: &clap::ArgMatches) -> Result<Policy, Box<dyn std::error::Error>> {
+    let mut policy = match matches.get_one::<String>("policy") {
+        Some(path) => Policy::load(&PathBuf::from(path)).unwrap_or_else(|e| {
+            eprintln!("Failed to load policy: {}", e);
+            std::process::exit(1);
+        }),
+        None => Policy::default(),
+    };
+    if let Some(patterns) = matches.get_many::<String>("allow") {
+        policy.allow.extend(patterns.cloned());
+    }
+    if let Some(patterns) = matches.get_many::<String>("deny") {
+        policy.deny.extend(patterns.cloned());
+    }
+    if let Some(mode) = matches.get_one::<String>("policy-mode") {
+        policy.mode = mode.parse::<PolicyMode>()?;
+    }
+    Ok(policy)
+}
+
+/// Handles `gget doctor`
+async fn doctor(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let endpoints = rpc_endpoints(matches);
+    let max_lag = *matches.get_one::<u64>("max-height-lag").unwrap();
+
+    let format = report_format(matches);
+    let client = http_settings(matches).build_http_client()?;
+
+    let statuses = check_endpoints(&client, &endpoints, max_lag).await;
+    print!("{}", render(statuses.as_slice(), format)?);
+    match select_endpoint(&statuses) {
+        Some(selected) => {
+            if format == ReportFormat::Human {
+                println!("Using {}", selected.url);
+            }
+        }
+        None => {
+            eprintln!("No healthy RPC endpoint");
+            std::process::exit(1);
+        }
+    }
+    Ok(())
+}
+
+/// Handles `gget status`
+async fn status_command(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let client = http_settings(matches).build_http_client()?;
+    let url = choose_endpoint(matches, &client).await;
+
+    let status = query_node_status(&client, &url).await?;
+    print!("{}", render(&status, report_format(matches))?);
+    Ok(())
+}
+
+/// Handles `gget watch`, until interrupted
+async fn watch(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let pkg_path = matches.get_one::<String>("package").unwrap();
+    let output = PathBuf::from(matches.get_one::<String>("output").unwrap());
+    let target_path = layout(matches).package_dir(&output, pkg_path);
+    let interval = Duration::from_secs(*matches.get_one::<u64>("interval").unwrap());
+    let human = report_format(matches) == ReportFormat::Human;
+
+    let pm = package_manager(matches).await?.with_quiet(true);
+    let mut watcher = Watcher::new(pm, pkg_path, &target_path).with_interval(interval);
+    if let Some(command) = matches.get_one::<String>("exec") {
+        watcher = watcher.with_hook(command);
+    }
+
+    if human {
+        println!(
+            "Watching {} every {}s (Ctrl-C to stop)",
+            pkg_path,
+            interval.as_secs()
+        );
+    }
+    let on_update = |update: &WatchUpdate| {
+        if human {
+            match &update.old_hash {
+                Some(old) => println!(
+                    "{} changed: {} -> {}, {} files updated",
+                    update.package, old, update.new_hash, update.stats.files
+                ),
+                None => println!(
+                    "{} downloaded: {}, {} files",
+                    update.package, update.new_hash, update.stats.files
+                ),
+            }
+        } else if let Ok(line) = serde_json::to_string(update) {
+            println!("{}", line);
+        }
+    };
+    let on_error = |e: &WatchError| eprintln!("Warning: {}", e);
+
+    tokio::select! {
+        result = watcher.run(on_update, on_error) => result?,
+        _ = tokio::signal::ctrl_c() => {}
+    }
+    Ok(())
+}
+
+/// Handles `gget repro-check`
+async fn repro_check_command(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let target_path = PathBuf::from(matches.get_one::<String>("output").unwrap());
+    let pm = package_manager(matches).await?.with_quiet(true);
+
+    let attestation = repro_check(&pm, &target_path).await?;
+    print!("{}", render(&attestation, report_format(matches))?);
+    if !attestation.passed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Handles `gget export`
+fn export_command(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let pkg_path = matches.get_one::<String>("package").unwrap();
+    let output = PathBuf::from(matches.get_one::<String>("output").unwrap());
+    let options = ExportOptions {
+        format: *matches.get_one::<ArchiveFormat>("archive-format").unwrap(),
+        with_deps: matches.get_flag("with-deps"),
+        manifest: matches.get_flag("manifest"),
+    };
+    let archive = match matches.get_one::<String>("to") {
+        Some(to) => PathBuf::from(to),
+        None => PathBuf::from(format!(
+            "{}.{}",
+            pkg_path.rsplit('/').next().unwrap_or(pkg_path),
+            options.format.extension()
+        )),
+    };
+
+    let summary = export_archive(pkg_path, &output, &archive, options)?;
+    print!("{}", render(&summary, report_format(matches))?);
+    Ok(())
+}
+
+/// Handles `gget import`
+async fn import_command(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let archive = PathBuf::from(matches.get_one::<String>("archive").unwrap());
+    let output = PathBuf::from(matches.get_one::<String>("output").unwrap());
+    let format = report_format(matches);
+    if matches.get_flag("dry-run") {
+        let plan = plan_import(&archive, &output)?;
+        print!("{}", render(&plan, format)?);
+        return Ok(());
+    }
+    let pm = package_manager(matches).await?.with_quiet(true);
+
+    let options = ImportOptions {
+        force: matches.get_flag("force"),
+    };
+    let summary = import_archive(&pm, &archive, &output, options).await?;
+    print!("{}", render(&summary, format)?);
+    Ok(())
+}
+
+/// Handles `gget cache`
+async fn cache_command(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let (_, sub) = matches.subcommand().expect("cache requires a subcommand");
+    let path = PathBuf::from(sub.get_one::<String>("path").unwrap());
+    let pm = package_manager(sub).await?;
+
+    let summary = if path.is_dir() {
+        pm.cache().import_dir(&path).await?
+    } else {
+        seed_from_archive(pm.cache(), &path).await?
+    };
+    print!("{}", render(&summary, report_format(sub))?);
+    Ok(())
+}
+
+/// Handles `gget prefetch`
+async fn prefetch_command(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let packages: Vec<&str> = matches
+        .get_many::<String>("packages")
+        .unwrap()
+        .map(String::as_str)
+        .collect();
+    let pm = package_manager(matches).await?.with_quiet(true);
+
+    let summary = pm.prefetch(&packages).await?;
+    print!("{}", render(&summary, report_format(matches))?);
+    Ok(())
+}
+
+/// Handles `gget metrics`
+fn metrics_command(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = PathBuf::from(matches.get_one::<String>("dir").unwrap());
+
+    let closure = scan_packages(&dir)?;
+    let metrics = DependencyGraph::from_packages(&closure.packages, TestFiles::Include).metrics();
+    print!("{}", render(&metrics, report_format(matches))?);
+    Ok(())
+}
+
+/// Handles `gget audit`
+async fn audit_command(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let target_path = PathBuf::from(matches.get_one::<String>("output").unwrap());
+
+    let mut audit = Audit::scan(&target_path)?;
+    if !matches.get_flag("no-chain") {
+        let pm = package_manager(matches).await?.with_quiet(true);
+        audit.compare_with_chain(&pm).await?;
+    }
+    print!("{}", render(&audit, report_format(matches))?);
+    Ok(())
+}
+
+/// Handles `gget rdeps`
+fn rdeps_command(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let target_path = PathBuf::from(matches.get_one::<String>("output").unwrap());
+    let pkg_path = matches.get_one::<String>("package").unwrap();
+
+    let rdeps = ReverseDependencies::from_dir(&target_path, pkg_path)?;
+    print!("{}", render(&rdeps, report_format(matches))?);
+    Ok(())
+}
+
+/// Handles `gget why`
+fn why_command(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let target_path = PathBuf::from(matches.get_one::<String>("output").unwrap());
+    let root = matches.get_one::<String>("root").unwrap();
+    let dependency = matches.get_one::<String>("dependency").unwrap();
+    let max_paths = *matches.get_one::<usize>("max-paths").unwrap();
+
+    let why = ImportPaths::from_dir(&target_path, root, dependency, max_paths)?;
+    print!("{}", render(&why, report_format(matches))?);
+    Ok(())
+}
+
+/// Handles `gget verify`
+fn verify_command(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let target_path = PathBuf::from(matches.get_one::<String>("output").unwrap());
+
+    let verification = verify_tree(&target_path)?;
+    print!("{}", render(&verification, report_format(matches))?);
+    if !verification.passed() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Handles `gget diff`
+async fn diff_command(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let pkg_path = matches.get_one::<String>("package").unwrap();
+    let output = PathBuf::from(matches.get_one::<String>("output").unwrap());
+    let against = *matches.get_one::<Against>("against").unwrap();
+    let pm = package_manager(matches).await?.with_quiet(true);
+
+    let dir = layout(matches).package_dir(&output, pkg_path);
+    let diff = diff_package(&pm, pkg_path, &dir, &output, against).await?;
+    print!("{}", render(&diff, report_format(matches))?);
+    if !diff.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Handles `gget render`
+async fn render_command(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let target = matches.get_one::<String>("target").unwrap();
+    let (realm, path) = target.split_once(':').unwrap_or((target, ""));
+    let pm = package_manager(matches).await?.with_quiet(true);
+
+    let output = pm.render(realm, path).await?;
+    print!("{}", output);
+    if !output.is_empty() && !output.ends_with('\n') {
+        println!();
+    }
+    Ok(())
+}
+
+/// Handles `gget eval`
+async fn eval_command(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let realm = matches.get_one::<String>("realm").unwrap();
+    let expr = matches.get_one::<String>("expr").unwrap();
+    let pm = package_manager(matches).await?.with_quiet(true);
+
+    let result = pm.eval(realm, expr).await?;
+    println!("{}", result.trim_end());
+    Ok(())
+}
+
+/// Handles `gget funcs`
+async fn funcs_command(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let pkg_path = matches.get_one::<String>("package").unwrap();
+    let pm = package_manager(matches).await?.with_quiet(true);
+
+    let funcs = pm.funcs(pkg_path).await?;
+    print!("{}", render(funcs.as_slice(), report_format(matches))?);
+    Ok(())
+}
+
+/// Handles `gget restore`
+fn restore(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let pkg_path = matches.get_one::<String>("package").unwrap();
+    let to = matches.get_one::<String>("to").map(PathBuf::from);
+
+    let entry = Trash::new(TRASH_DIR).restore(pkg_path, to.as_deref())?;
+    let target = to.unwrap_or(entry.original.clone());
+    if report_format(matches) == ReportFormat::Human {
+        println!("Restored {} to {}", pkg_path, target.display());
+    } else {
+        println!("{}", serde_json::to_string(&entry)?);
+    }
+    Ok(())
+}
+
+/// The network picked with --network, if any
+fn network(matches: &clap::ArgMatches) -> Option<&'static Network> {
+    matches
+        .get_one::<String>("network")
+        .map(|name| Network::find(name).expect("clap only accepts known networks"))
+}
+
+/// The configured RPC endpoints: those given with --rpc-endpoint, else the
+/// --network's, else the default
+fn rpc_endpoints(matches: &clap::ArgMatches) -> Vec<String> {
+    let defaulted = matches.value_source("rpc-endpoint") == Some(ValueSource::DefaultValue);
+    match network(matches) {
+        Some(network) if defaulted => vec![network.rpc_endpoint.to_string()],
+        _ => matches
+            .get_many::<String>("rpc-endpoint")
+            .unwrap()
+            .cloned()
+            .collect(),
+    }
+}
+
+/// Picks the endpoint to use, comparing block heights when several are configured
+async fn choose_endpoint(matches: &clap::ArgMatches, client: &reqwest::Client) -> String {
+    let endpoints = rpc_endpoints(matches);
+    if endpoints.len() == 1 {
+        return endpoints[0].clone();
+    }
+
+    let max_lag = *matches.get_one::<u64>("max-height-lag").unwrap();
+    let statuses = check_endpoints(client, &endpoints, max_lag).await;
+    for status in statuses.iter().filter(|s| !s.is_healthy()) {
+        eprintln!("Warning: skipping endpoint {}", status);
+    }
+
+    match select_endpoint(&statuses) {
+        Some(selected) => selected.url.clone(),
+        None => {
+            eprintln!("Warning: no endpoint looks healthy, using {}", endpoints[0]);
+            endpoints[0].clone()
+        }
+    }
+}
+
+/// Package manager builder with the HTTP settings shared by all commands
+fn http_settings(matches: &clap::ArgMatches) -> PackageManagerBuilder {
+    let mut builder =
+        PackageManager::builder().user_agent(concat!("gget/", env!("CARGO_PKG_VERSION")));
+    if matches.get_flag("cookies") {
+        builder = builder.cookies(true);
+    }
+    if let Some(proxy) = matches.get_one::<String>("proxy") {
+        builder = builder.proxy(proxy);
+    }
+    for cert in matches.get_many::<String>("ca-cert").into_iter().flatten() {
+        builder = builder.root_certificate(cert);
+    }
+    if matches.get_flag("http2-prior-knowledge") {
+        builder = builder.http2_prior_knowledge(true);
+    }
+    builder
+}
+
+/// Creates the package manager shared by all commands
+async fn package_manager(
+    matches: &clap::ArgMatches,
+) -> Result<PackageManager, Box<dyn std::error::Error>> {
+    let mut builder = http_settings(matches);
+    let http_client = builder.build_http_client()?;
+    let rpc_endpoint = choose_endpoint(matches, &http_client).await;
+    if let Some(network) = network(matches) {
+        // nodes only report their status over HTTP
+        if rpc_endpoint.starts_with("http") {
+            network.check(&http_client, &rpc_endpoint).await?;
+        }
+        builder = builder.cache_namespace(network.name);
+    }
+    builder = builder.http_client(http_client).endpoint(rpc_endpoint);
+    if let Some(command) = matches.get_one::<String>("login-command") {
+        builder = builder.login_command(command);
+    }
+    if let Some(dir) = matches.get_one::<String>("cache-dir") {
+        builder = builder.cache_dir(dir);
+    }
+    builder = builder.cache_ttls(cache_ttls(matches));
+    match matches.get_one::<String>("cache").map(String::as_str) {
+        Some("memory") => builder = builder.storage(MemoryStorage::new(DEFAULT_CACHE_TTL)),
+        #[cfg(feature = "redis")]
+        Some("redis") => builder = builder.storage(redis_storage(matches).await?),
+        _ => {}
+    }
+    let mut pm = builder
+        .build()?
+        .with_trash(Trash::new(TRASH_DIR))
+        .with_policy(load_policy(matches)?)
+        .with_refresh(matches.get_flag("refresh"))
+        .with_test_files(if matches.get_flag("skip-test-deps") {
+            TestFiles::Exclude
+        } else {
+            TestFiles::Include
+        })
+        .with_unsafe_direct(matches.get_flag("unsafe-direct"))
+        .with_strict_validation(matches.get_flag("strict"))
+        .with_fsync(matches.get_flag("fsync"))
+        .with_layout(layout(matches))
+        .with_quiet(report_format(matches) != ReportFormat::Human);
+    if let Some(limit) = matches.get_one::<RateLimit>("rate-limit") {
+        pm = pm.with_rate_limit(*limit);
+    }
+    pm = pm.with_limits(download_limits(matches));
+    if let Some(manifest) = matches.get_one::<String>("trusted-checksums") {
+        match ChecksumManifest::load(&PathBuf::from(manifest)) {
+            Ok(manifest) => pm = pm.with_verifier(manifest),
+            Err(e) => {
+                eprintln!("Failed to load checksum manifest: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    for (pkg_path, source) in git_sources(matches) {
+        pm = pm.with_source(pkg_path, PackageSource::Git(source.clone()));
+    }
+    for dir in matches
+        .get_many::<String>("local-registry")
+        .into_iter()
+        .flatten()
+    {
+        pm = pm.with_local_registry(dir);
+    }
+    Ok(pm.with_prefer_local(matches.get_flag("prefer-local")))
+}
+
+/// Whether packages may be read from directories whose changes the
+/// lockfile can't tell about: git sources, or preferred local registries
+fn reads_local_sources(matches: &clap::ArgMatches) -> bool {
+    git_sources(matches).next().is_some()
+        || (matches.get_flag("prefer-local") && matches.contains_id("local-registry"))
+}
+
+/// Packages mapped to git repositories by `--git-source`
+fn git_sources(matches: &clap::ArgMatches) -> impl Iterator<Item = &(String, GitSource)> {
+    matches
+        .get_many::<(String, GitSource)>("git-source")
+        .into_iter()
+        .flatten()
+}
+
+/// Download limits from `--max-files` and the `--max-*-size` flags, the
+/// defaults for those not given
+fn download_limits(matches: &clap::ArgMatches) -> DownloadLimits {
+    let mut limits = DownloadLimits::default();
+    if let Some(max) = matches.get_one::<u64>("max-files") {
+        limits = limits.with_max_files(*max);
+    }
+    if let Some(max) = matches.get_one::<u64>("max-file-size") {
+        limits = limits.with_max_file_size(*max);
+    }
+    if let Some(max) = matches.get_one::<u64>("max-package-size") {
+        limits = limits.with_max_package_size(*max);
+    }
+    if let Some(max) = matches.get_one::<u64>("max-total-size") {
+        limits = limits.with_max_total_size(*max);
+    }
+    limits
+}
+
+/// Package layout from `--layout`
+fn layout(matches: &clap::ArgMatches) -> Layout {
+    matches
+        .get_one::<Layout>("layout")
+        .copied()
+        .unwrap_or_default()
+}
+
+/// Output format from `--format`, with `--json` as a shorthand
+fn report_format(matches: &clap::ArgMatches) -> ReportFormat {
+    if matches.get_flag("json") {
+        return ReportFormat::Json;
+    }
+    matches
+        .get_one::<String>("format")
+        .and_then(|f| f.parse().ok())
+        .unwrap_or_default()
+}
+
+/// Validates `dir` for `--validate`, exiting when it fails
+async fn validate_dir(
+    pm: &PackageManager,
+    dir: &Path,
+    format: ReportFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match pm
+        .validate_package(dir)
+        .await
+        .and_then(ValidationReport::into_result)
+    {
+        Ok(report) => {
+            print!("{}", render(&report, format)?);
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("Validation failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Summary reported when there was nothing to download
+fn up_to_date_summary(up_to_date: Vec<String>) -> DownloadSummary {
+    DownloadSummary {
+        total_packages: up_to_date.len(),
+        successful: 0,
+        failed: Vec::new(),
+        up_to_date,
+        skipped: Default::default(),
+        packages: Vec::new(),
+        duration: Default::default(),
+        cache: Default::default(),
+    }
+}
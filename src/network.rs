@@ -0,0 +1,118 @@
+use std::fmt;
+
+use reqwest::Client;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::endpoint::{query_chain_id, EndpointError};
+use crate::error::ErrorKind;
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum NetworkError {
+    #[error("Unknown network `{0}`, expected one of: {names}", names = Network::names().join(", "))]
+    Unknown(String),
+
+    #[error("Couldn't check the chain id of {url}: {source}")]
+    Status {
+        url: String,
+        #[source]
+        source: EndpointError,
+    },
+
+    #[error("{url} serves chain `{actual}`, not `{expected}` as the {network} network should")]
+    ChainIdMismatch {
+        network: String,
+        url: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+impl NetworkError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Unknown(_) | Self::ChainIdMismatch { .. } => ErrorKind::Config,
+            Self::Status { .. } => ErrorKind::Network,
+        }
+    }
+}
+
+/// A gno.land network: where to reach it and which chain it runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Network {
+    pub name: &'static str,
+    pub rpc_endpoint: &'static str,
+    /// What the `status` RPC reports as `node_info.network`
+    pub chain_id: &'static str,
+}
+
+/// Networks that can be picked by name
+pub const NETWORKS: &[Network] = &[
+    Network {
+        name: "portal-loop",
+        rpc_endpoint: "https://rpc.gno.land:443",
+        chain_id: "portal-loop",
+    },
+    Network {
+        name: "test5",
+        rpc_endpoint: "https://rpc.test5.gno.land:443",
+        chain_id: "test5",
+    },
+    Network {
+        name: "staging",
+        rpc_endpoint: "https://rpc.staging.gno.land:443",
+        chain_id: "staging",
+    },
+    // `gnodev` and a default `gnoland start`
+    Network {
+        name: "local",
+        rpc_endpoint: "http://127.0.0.1:26657",
+        chain_id: "dev",
+    },
+];
+
+impl Network {
+    /// The network called `name`
+    pub fn find(name: &str) -> Result<&'static Network, NetworkError> {
+        NETWORKS
+            .iter()
+            .find(|network| network.name == name)
+            .ok_or_else(|| NetworkError::Unknown(name.to_string()))
+    }
+
+    /// Names of [NETWORKS], in order
+    pub fn names() -> Vec<&'static str> {
+        NETWORKS.iter().map(|network| network.name).collect()
+    }
+
+    /// Checks that the node at `url` runs this network's chain, so
+    /// packages from one network never pass for another's
+    pub async fn check(&self, client: &Client, url: &str) -> Result<(), NetworkError> {
+        let actual = query_chain_id(client, url)
+            .await
+            .map_err(|source| NetworkError::Status {
+                url: url.to_string(),
+                source,
+            })?;
+        if actual != self.chain_id {
+            return Err(NetworkError::ChainIdMismatch {
+                network: self.name.to_string(),
+                url: url.to_string(),
+                expected: self.chain_id.to_string(),
+                actual,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}, chain {})",
+            self.name, self.rpc_endpoint, self.chain_id
+        )
+    }
+}
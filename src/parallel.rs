@@ -1,13 +1,24 @@
-use std::collections::{HashMap, VecDeque};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use tokio::sync::{mpsc, Mutex, Semaphore};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Serialize, Serializer};
+use tokio::sync::{broadcast, watch, Mutex, Notify};
 
+use crate::cache::CacheStats;
+use crate::cancel::Cancellation;
+use crate::error::ErrorKind;
 use crate::fetch::PackageManagerError;
+use crate::ratelimit::RateLimit;
+use crate::report::{Report, Table};
 
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum DownloadError {
     #[error("Network error: {0}")]
     Network(String),
@@ -24,14 +35,49 @@ pub enum DownloadError {
     #[error("Download cancelled")]
     Cancelled,
 
-    #[error("Max retries exceeded")]
-    MaxRetriesExceeded,
+    /// Every attempt failed; the last attempt's error says why
+    #[error("Max retries exceeded: {0}")]
+    MaxRetriesExceeded(#[source] Box<DownloadError>),
+
+    #[error("Dependency {0} failed")]
+    DependencyFailed(String),
 
     #[error("Package manager error: {0}")]
     PackageManager(#[from] PackageManagerError),
 }
 
-#[derive(Debug, Clone)]
+impl DownloadError {
+    /// Stable, machine-readable identifier of the error kind; package
+    /// manager errors keep their own code
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Network(_) => "network",
+            Self::Io(_) => "io",
+            Self::Timeout(_) => "timeout",
+            Self::ChecksumMismatch => "checksum_mismatch",
+            Self::Cancelled => "cancelled",
+            Self::MaxRetriesExceeded(_) => "max_retries_exceeded",
+            Self::DependencyFailed(_) => "dependency_failed",
+            Self::PackageManager(e) => e.code(),
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Network(_) | Self::Timeout(_) => ErrorKind::Network,
+            Self::MaxRetriesExceeded(last) => last.kind(),
+            Self::Io(_) => ErrorKind::Internal,
+            Self::ChecksumMismatch => ErrorKind::Integrity,
+            Self::Cancelled => ErrorKind::Cancelled,
+            // the dependency couldn't be obtained; its own failure,
+            // reported separately, carries the root cause
+            Self::DependencyFailed(_) => ErrorKind::NotFound,
+            Self::PackageManager(e) => e.kind(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct DownloadTask {
     /// Package identifier
     pub package_id: String,
@@ -43,6 +89,12 @@ pub struct DownloadTask {
     pub priority: u8,
     /// Retry configuration
     pub retry_config: RetryConfig,
+    /// Packages that required this one, nearest first and ending with the root
+    pub required_by: Vec<String>,
+    /// Per-attempt timeout overriding the manager's default
+    pub timeout: Option<Duration>,
+    /// Package paths that must download successfully before this one starts
+    pub depends_on: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -55,6 +107,44 @@ pub struct RetryConfig {
     pub max_backoff: Duration,
     /// Backoff multiplier
     pub multiplier: f64,
+    /// Fraction of each backoff randomly shaved off, between 0 (no jitter) and 1
+    pub jitter: f64,
+}
+
+/// Upper bound on extra attempts granted to packages that block others
+const MAX_BLOCKER_BOOST: u32 = 3;
+
+impl RetryConfig {
+    /// Adjusts the config for a package that `dependents` other packages wait on.
+    ///
+    /// Blockers get a few extra attempts (growing logarithmically with the
+    /// number of dependents) and a shorter initial backoff, so a flaky
+    /// network hurts the whole closure less. Leaves keep the base config.
+    pub fn for_blocker(&self, dependents: usize) -> Self {
+        if dependents == 0 {
+            return self.clone();
+        }
+
+        // floor(log2(dependents)) + 1
+        let boost = (usize::BITS - dependents.leading_zeros()).min(MAX_BLOCKER_BOOST);
+
+        Self {
+            max_attempts: self.max_attempts + boost,
+            initial_backoff: self.initial_backoff / (boost + 1),
+            ..self.clone()
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Applies jitter to a backoff, returning a delay in `[(1 - jitter) * backoff, backoff]`
+    pub fn jittered<R: Rng>(&self, backoff: Duration, rng: &mut R) -> Duration {
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return backoff;
+        }
+        backoff.mul_f64(1.0 - jitter * rng.gen::<f64>())
+    }
 }
 
 impl Default for RetryConfig {
@@ -64,6 +154,7 @@ impl Default for RetryConfig {
             initial_backoff: Duration::from_secs(1),
             max_backoff: Duration::from_secs(30),
             multiplier: 2.0,
+            jitter: 0.25,
         }
     }
 }
@@ -85,31 +176,152 @@ pub enum DownloadState {
     Cancelled,
 }
 
-#[derive(Debug)]
+/// Counters collected while downloading a single package
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PackageStats {
+    /// Number of files written
+    pub files: usize,
+    /// Total bytes written
+    pub bytes: u64,
+    /// File list and file content lookups served from the cache
+    pub cache_hits: usize,
+    /// File list and file content lookups that went to the RPC endpoint
+    pub network_fetches: usize,
+}
+
+/// Per-package entry of a [DownloadSummary]
+#[derive(Debug, Clone, Serialize)]
+pub struct PackageReport {
+    pub package: String,
+    /// Wall time of the successful attempt plus any retries
+    #[serde(rename = "duration_ms", serialize_with = "serialize_millis")]
+    pub duration: Duration,
+    #[serde(flatten)]
+    pub stats: PackageStats,
+    /// Attempts needed beyond the first
+    pub retries: u32,
+}
+
+#[derive(Debug, Default, Serialize)]
 pub struct DownloadSummary {
     pub total_packages: usize,
     pub successful: usize,
     pub failed: Vec<FailedDownload>,
+    /// Packages skipped because an unchanged copy was already on disk
+    pub up_to_date: Vec<String>,
+    /// Packages a policy in warn mode left out, with why
+    pub skipped: BTreeMap<String, String>,
+    /// Breakdown of every successful download
+    pub packages: Vec<PackageReport>,
+    #[serde(rename = "duration_ms", serialize_with = "serialize_millis")]
     pub duration: Duration,
+    /// Cache lookups made during the run
+    pub cache: CacheStats,
 }
 
-#[derive(Debug)]
+impl Report for DownloadSummary {
+    fn table(&self) -> Table {
+        let mut table = Table::new(
+            "Download summary",
+            &[
+                "package",
+                "status",
+                "duration_ms",
+                "files",
+                "bytes",
+                "cache_hits",
+                "network_fetches",
+                "retries",
+                "error",
+            ],
+        );
+        for p in &self.packages {
+            table.push_row([
+                p.package.clone(),
+                "downloaded".to_string(),
+                p.duration.as_millis().to_string(),
+                p.stats.files.to_string(),
+                p.stats.bytes.to_string(),
+                p.stats.cache_hits.to_string(),
+                p.stats.network_fetches.to_string(),
+                p.retries.to_string(),
+                String::new(),
+            ]);
+        }
+        for package in &self.up_to_date {
+            let mut row = vec![package.clone(), "up to date".to_string()];
+            row.resize(table.columns.len(), String::new());
+            table.push_row(row);
+        }
+        for (package, reason) in &self.skipped {
+            let mut row = vec![package.clone(), "skipped".to_string()];
+            row.resize(table.columns.len() - 1, String::new());
+            row.push(reason.clone());
+            table.push_row(row);
+        }
+        for f in &self.failed {
+            let mut row = vec![f.package.clone(), "failed".to_string()];
+            row.resize(table.columns.len() - 2, String::new());
+            row.push(f.retry_count.to_string());
+            row.push(f.error.to_string());
+            table.push_row(row);
+        }
+        table
+    }
+
+    fn human(&self) -> String {
+        format!("{}\n", self)
+    }
+}
+
+#[derive(Debug, Serialize)]
 pub struct FailedDownload {
     pub package: String,
+    #[serde(serialize_with = "serialize_display")]
     pub error: DownloadError,
+    /// [DownloadError::code] of `error`
+    pub error_code: &'static str,
+    /// [DownloadError::kind] of `error`
+    pub error_kind: ErrorKind,
     pub retry_count: u32,
+    /// Import chain that required the package, nearest first
+    pub required_by: Vec<String>,
+}
+
+impl std::fmt::Display for FailedDownload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.package, self.error)?;
+        if self.retry_count > 0 {
+            write!(f, " after {} retries", self.retry_count)?;
+        }
+        if !self.required_by.is_empty() {
+            write!(f, " (needed by {})", self.required_by.join(" ← "))?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct ParallelDownloadOptions {
-    /// Maximum concurrent downloads
+    /// Maximum concurrent downloads, and packages analyzed at once while
+    /// resolving dependencies
     pub max_concurrent: usize,
     /// Enable progress display
     pub show_progress: bool,
     /// Retry configuration
     pub retry_config: RetryConfig,
-    /// Timeout per download
+    /// Timeout per download attempt; a timed out attempt is retried like any other failure
     pub timeout: Duration,
+    /// Re-download packages even if an unchanged copy is already on disk
+    pub force: bool,
+    /// Throttle for all RPC calls made during the run, including resolution
+    pub rate_limit: Option<RateLimit>,
+    /// Seed for retry jitter, making backoff delays reproducible; random if unset
+    pub seed: Option<u64>,
+    /// RPC requests each endpoint may have in flight when several are
+    /// configured; one that answers 429 or times out gets a smaller share
+    /// until it recovers. Unbounded if unset.
+    pub endpoint_concurrency: Option<usize>,
 }
 
 impl Default for ParallelDownloadOptions {
@@ -119,72 +331,396 @@ impl Default for ParallelDownloadOptions {
             show_progress: true,
             retry_config: RetryConfig::default(),
             timeout: Duration::from_secs(300), // 5 minutes
+            force: false,
+            rate_limit: None,
+            seed: None,
+            endpoint_concurrency: None,
         }
     }
 }
 
+/// Updates kept for a subscriber that isn't keeping up, see
+/// [ProgressTracker::get_update_receiver]
+pub const PROGRESS_BUFFER: usize = 1024;
+
 pub struct ProgressTracker {
     /// Progress for each package
     package_progress: Arc<Mutex<HashMap<String, PackageProgress>>>,
     /// Update channel for progress events
-    update_tx: mpsc::Sender<ProgressUpdate>,
-    update_rx: Arc<Mutex<mpsc::Receiver<ProgressUpdate>>>,
+    update_tx: broadcast::Sender<ProgressUpdate>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ProgressUpdate {
-    Started { package_id: String },
-    Progress { package_id: String, percent: f32 },
-    Completed { package_id: String },
-    Failed { package_id: String, error: String },
+    Started {
+        package_id: String,
+    },
+    Progress {
+        package_id: String,
+        percent: f32,
+    },
+    Completed {
+        package_id: String,
+    },
+    Failed {
+        package_id: String,
+        error: String,
+    },
+    /// A failed attempt is about to be retried after `backoff`
+    Retrying {
+        package_id: String,
+        /// The attempt that just failed, starting at 1
+        attempt: u32,
+        /// Delay before the next attempt, jitter included
+        backoff: Duration,
+    },
 }
 
 impl ProgressTracker {
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
-        let (tx, rx) = mpsc::channel(100);
+        let (tx, _) = broadcast::channel(PROGRESS_BUFFER);
         Self {
             package_progress: Arc::new(Mutex::new(HashMap::new())),
             update_tx: tx,
-            update_rx: Arc::new(Mutex::new(rx)),
         }
     }
 
+    /// Records an update in the progress of its package and publishes it
+    /// to the current subscribers, without waiting for them to read it
     pub async fn update(&self, update: ProgressUpdate) {
-        let _ = self.update_tx.send(update).await;
+        let mut packages = self.package_progress.lock().await;
+        match &update {
+            ProgressUpdate::Started { package_id } => {
+                packages.insert(
+                    package_id.clone(),
+                    PackageProgress {
+                        package_id: package_id.clone(),
+                        state: DownloadState::Downloading { percent: 0.0 },
+                        started_at: Instant::now(),
+                        eta: None,
+                    },
+                );
+            }
+            ProgressUpdate::Progress {
+                package_id,
+                percent,
+            } => {
+                if let Some(package) = packages.get_mut(package_id) {
+                    package.state = DownloadState::Downloading { percent: *percent };
+                    package.eta = (*percent > 0.0).then(|| {
+                        package
+                            .started_at
+                            .elapsed()
+                            .mul_f32((100.0 - percent.min(100.0)) / percent)
+                    });
+                }
+            }
+            ProgressUpdate::Completed { package_id } => {
+                if let Some(package) = packages.get_mut(package_id) {
+                    package.state = DownloadState::Completed;
+                    package.eta = None;
+                }
+            }
+            ProgressUpdate::Failed { package_id, error } => {
+                if let Some(package) = packages.get_mut(package_id) {
+                    package.state = DownloadState::Failed {
+                        error: error.clone(),
+                    };
+                    package.eta = None;
+                }
+            }
+            // the next attempt starts over
+            ProgressUpdate::Retrying { package_id, .. } => {
+                if let Some(package) = packages.get_mut(package_id) {
+                    package.state = DownloadState::Downloading { percent: 0.0 };
+                    package.eta = None;
+                }
+            }
+        }
+        drop(packages);
+
+        // nobody may be subscribed, which is fine
+        let _ = self.update_tx.send(update);
     }
 
+    /// Where every package that started stands, however late this is asked
     pub async fn get_progress(&self) -> HashMap<String, PackageProgress> {
         self.package_progress.lock().await.clone()
     }
 
-    pub fn get_update_receiver(&self) -> Arc<Mutex<mpsc::Receiver<ProgressUpdate>>> {
-        Arc::clone(&self.update_rx)
+    /// Subscribes to the updates published from now on.
+    ///
+    /// Each subscriber keeps up to [PROGRESS_BUFFER] unread updates; one
+    /// that falls further behind loses the oldest and is told so with
+    /// [broadcast::error::RecvError::Lagged]. [ProgressTracker::get_progress]
+    /// still has the final state of every package.
+    pub fn get_update_receiver(&self) -> broadcast::Receiver<ProgressUpdate> {
+        self.update_tx.subscribe()
     }
 }
 
 pub struct DownloadManager {
-    /// Semaphore for concurrency control
-    semaphore: Arc<Semaphore>,
+    /// Number of workers downloading at once
+    workers: usize,
     /// Progress tracking
     progress: Arc<ProgressTracker>,
     /// Download queue
     queue: Arc<Mutex<VecDeque<DownloadTask>>>,
+    /// Default per-attempt timeout for tasks that don't set their own
+    timeout: Option<Duration>,
+    /// Outcome channels of queued and in-flight downloads, keyed by package path
+    waiters: Arc<Mutex<HashMap<String, watch::Sender<Option<DownloadOutcome>>>>>,
+    /// Stops queued and in-flight downloads when interrupted
+    cancellation: Cancellation,
+    /// Seed every task's jitter RNG is derived from
+    seed: u64,
+    /// Target directories being written to
+    targets: Arc<TargetLocks>,
+    /// Tasks taken by a worker and not finished yet
+    in_flight: Arc<AtomicUsize>,
+    /// Wakes idle workers when a task is queued or finishes
+    wake: Arc<Notify>,
+    /// Whether each finished package succeeded, keyed by package path
+    done: Arc<std::sync::Mutex<HashMap<String, bool>>>,
+    /// Outstanding [QueueHold]s
+    holds: Arc<AtomicUsize>,
+}
+
+/// Keeps [DownloadManager::process_queue] waiting for more tasks, even
+/// with nothing left to download, until dropped
+pub struct QueueHold {
+    holds: Arc<AtomicUsize>,
+    wake: Arc<Notify>,
+}
+
+impl Drop for QueueHold {
+    fn drop(&mut self) {
+        self.holds.fetch_sub(1, Ordering::SeqCst);
+        self.wake.notify_waiters();
+    }
+}
+
+/// Target directories of in-flight downloads.
+///
+/// A package and its subpackage have nested targets, so two tasks may
+/// only write at once if neither target contains the other.
+#[derive(Debug, Default)]
+struct TargetLocks {
+    busy: std::sync::Mutex<Vec<PathBuf>>,
+}
+
+impl TargetLocks {
+    /// Claims `target` until the guard is dropped, unless an in-flight
+    /// download's target overlaps it. Tasks without a target never wait.
+    fn try_lock(self: &Arc<Self>, target: &Path) -> Option<TargetGuard> {
+        let guard = TargetGuard {
+            locks: Arc::clone(self),
+            target: target.to_path_buf(),
+        };
+        if target.as_os_str().is_empty() {
+            return Some(guard);
+        }
+        let mut busy = self.busy.lock().expect("target locks poisoned");
+        if busy
+            .iter()
+            .any(|b| b.starts_with(target) || target.starts_with(b))
+        {
+            return None;
+        }
+        busy.push(target.to_path_buf());
+        Some(guard)
+    }
+}
+
+struct TargetGuard {
+    locks: Arc<TargetLocks>,
+    target: PathBuf,
+}
+
+impl Drop for TargetGuard {
+    fn drop(&mut self) {
+        let mut busy = self.locks.busy.lock().expect("target locks poisoned");
+        if let Some(idx) = busy.iter().position(|b| *b == self.target) {
+            busy.swap_remove(idx);
+        }
+    }
+}
+
+/// What a worker hands back for each task it took
+type Finished = (
+    String,
+    Vec<String>,
+    Result<Result<PackageReport, (DownloadError, u32)>, tokio::task::JoinError>,
+);
+
+/// Final outcome of a download, shared by every request coalesced into it
+#[derive(Debug, Clone)]
+pub enum DownloadOutcome {
+    Completed(PackageReport),
+    Failed(String),
+}
+
+/// Resolves once the download a [DownloadManager::queue_download] call was
+/// coalesced into has finished
+#[derive(Debug)]
+pub struct DownloadWaiter(watch::Receiver<Option<DownloadOutcome>>);
+
+impl DownloadWaiter {
+    /// Waits for the outcome, or `None` if the manager was dropped before
+    /// the download ran
+    pub async fn wait(mut self) -> Option<DownloadOutcome> {
+        match self.0.wait_for(|outcome| outcome.is_some()).await {
+            Ok(outcome) => outcome.clone(),
+            Err(_) => None,
+        }
+    }
 }
 
 impl DownloadManager {
     pub fn new(max_concurrent: usize) -> Self {
         Self {
-            semaphore: Arc::new(Semaphore::new(max_concurrent)),
+            workers: max_concurrent.max(1),
             progress: Arc::new(ProgressTracker::new()),
             queue: Arc::new(Mutex::new(VecDeque::new())),
+            timeout: None,
+            waiters: Arc::new(Mutex::new(HashMap::new())),
+            cancellation: Cancellation::default(),
+            seed: rand::random(),
+            targets: Arc::default(),
+            in_flight: Arc::default(),
+            wake: Arc::default(),
+            done: Arc::default(),
+            holds: Arc::default(),
+        }
+    }
+
+    /// Seeds the RNG behind retry jitter.
+    ///
+    /// Each task draws from its own generator derived from the seed and its
+    /// package path, so delays are reproducible however tasks get scheduled.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Limits how long a single download attempt may take
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Interrupts downloads once `cancellation` fires.
+    ///
+    /// Tasks not started yet fail with [DownloadError::Cancelled], and
+    /// in-flight attempts are dropped at their next await point.
+    pub fn with_cancellation(mut self, cancellation: Cancellation) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// Queue a package for download.
+    ///
+    /// A task whose package path is already queued or downloading is
+    /// coalesced into the existing one, which keeps the higher of the two
+    /// priorities; every caller's waiter then sees the same outcome.
+    pub async fn queue_download(
+        &self,
+        task: DownloadTask,
+    ) -> Result<DownloadWaiter, DownloadError> {
+        let mut waiters = self.waiters.lock().await;
+        let mut queue = self.queue.lock().await;
+
+        if let Some(sender) = waiters.get(&task.package_path) {
+            let waiter = DownloadWaiter(sender.subscribe());
+            // still queued: bump the existing task if the new one is more urgent
+            if let Some(idx) = queue
+                .iter()
+                .position(|t| t.package_path == task.package_path)
+            {
+                if queue[idx].priority < task.priority {
+                    let mut existing = queue.remove(idx).expect("index is in bounds");
+                    existing.priority = task.priority;
+                    Self::insert_by_priority(&mut queue, existing);
+                }
+            }
+            return Ok(waiter);
         }
+
+        let (sender, receiver) = watch::channel(None);
+        waiters.insert(task.package_path.clone(), sender);
+        Self::insert_by_priority(&mut queue, task);
+        self.wake.notify_waiters();
+        Ok(DownloadWaiter(receiver))
+    }
+
+    /// Number of tasks queued and not taken by a worker yet
+    pub async fn queue_depth(&self) -> usize {
+        self.queue.lock().await.len()
     }
 
-    /// Queue a package for download
-    pub async fn queue_download(&self, task: DownloadTask) -> Result<(), DownloadError> {
+    /// Number of tasks a worker is downloading
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Keeps [DownloadManager::process_queue] running until the hold is
+    /// dropped, for a producer still queueing tasks as it finds them
+    pub fn hold(&self) -> QueueHold {
+        self.holds.fetch_add(1, Ordering::SeqCst);
+        QueueHold {
+            holds: Arc::clone(&self.holds),
+            wake: Arc::clone(&self.wake),
+        }
+    }
+
+    /// Records a package as present without downloading it, e.g. because
+    /// it is already up to date, so tasks depending on it may start
+    pub fn mark_done(&self, package_path: &str) {
+        self.done
+            .lock()
+            .expect("finished downloads poisoned")
+            .insert(package_path.to_string(), true);
+        self.wake.notify_waiters();
+    }
+
+    /// Drops every task no worker has taken yet, returning how many.
+    /// Their waiters see no outcome.
+    pub async fn clear_queue(&self) -> usize {
+        let mut waiters = self.waiters.lock().await;
         let mut queue = self.queue.lock().await;
+        for task in queue.iter() {
+            waiters.remove(&task.package_path);
+        }
+        let dropped = queue.len();
+        queue.clear();
+        self.wake.notify_waiters();
+        dropped
+    }
+
+    /// Whether `task` may start: `Err` names a dependency that failed,
+    /// `Ok(false)` means one is still queued, in flight or, while the
+    /// queue is held, may yet be queued. Dependencies never queued don't
+    /// hold a task back once nothing else can be queued.
+    fn readiness(
+        task: &DownloadTask,
+        done: &HashMap<String, bool>,
+        waiters: &HashMap<String, watch::Sender<Option<DownloadOutcome>>>,
+        held: bool,
+    ) -> Result<bool, String> {
+        let mut ready = true;
+        for dep in task.depends_on.iter().filter(|d| **d != task.package_path) {
+            match done.get(dep) {
+                Some(true) => {}
+                Some(false) => return Err(dep.clone()),
+                None if held || waiters.contains_key(dep) => ready = false,
+                None => {}
+            }
+        }
+        Ok(ready)
+    }
 
+    fn insert_by_priority(queue: &mut VecDeque<DownloadTask>, task: DownloadTask) {
         // Insert based on priority (higher priority first)
         let position = queue
             .iter()
@@ -192,96 +728,71 @@ impl DownloadManager {
             .unwrap_or(queue.len());
 
         queue.insert(position, task);
-        Ok(())
     }
 
-    /// Process all queued downloads
+    /// Downloads queued tasks with a fixed pool of workers, each taking
+    /// the most urgent task whose target no in-flight download overlaps
+    /// and whose `depends_on` have all downloaded.
+    ///
+    /// A task depending on a package that failed is not started and is
+    /// reported as failed instead. Tasks queued while processing, e.g. by
+    /// a download that finds more packages, are picked up too. Returns
+    /// once the queue is empty, no download is left in flight and no
+    /// [QueueHold] is outstanding.
     pub async fn process_queue<F>(&self, download_fn: F) -> Result<DownloadSummary, DownloadError>
     where
-        F: Fn(DownloadTask) -> futures::future::BoxFuture<'static, Result<(), DownloadError>>
+        F: Fn(
+                DownloadTask,
+            )
+                -> futures::future::BoxFuture<'static, Result<PackageStats, DownloadError>>
             + Send
             + Sync
             + 'static,
     {
         let start_time = Instant::now();
         let download_fn = Arc::new(download_fn);
-        let mut handles = Vec::new();
-        let mut total_packages = 0;
-
-        // Process queue
-        loop {
-            let task = {
-                let mut queue = self.queue.lock().await;
-                queue.pop_front()
-            };
-
-            let Some(task) = task else {
-                break;
-            };
-
-            total_packages += 1;
-            let package_id = task.package_id.clone();
-            let package_id_for_handle = package_id.clone();
-
-            // Update progress
-            self.progress
-                .update(ProgressUpdate::Started {
-                    package_id: package_id.clone(),
-                })
-                .await;
-
-            // Acquire semaphore permit
-            let permit = Arc::clone(&self.semaphore);
-            let progress = Arc::clone(&self.progress);
-            let download_fn = Arc::clone(&download_fn);
 
-            let handle = tokio::spawn(async move {
-                let _permit = permit.acquire().await.unwrap();
-                let result = Self::download_with_retry(task, download_fn.as_ref(), &progress).await;
-
-                match &result {
-                    Ok(_) => {
-                        progress
-                            .update(ProgressUpdate::Completed {
-                                package_id: package_id.clone(),
-                            })
-                            .await;
-                    }
-                    Err(e) => {
-                        progress
-                            .update(ProgressUpdate::Failed {
-                                package_id: package_id.clone(),
-                                error: e.to_string(),
-                            })
-                            .await;
-                    }
-                }
-
-                result
-            });
-
-            handles.push((package_id_for_handle, handle));
+        let workers: Vec<_> = (0..self.workers)
+            .map(|_| tokio::spawn(self.worker(Arc::clone(&download_fn))))
+            .collect();
+        let mut finished = Vec::new();
+        for worker in workers {
+            match worker.await {
+                Ok(done) => finished.extend(done),
+                Err(e) => return Err(DownloadError::Network(format!("Worker panic: {}", e))),
+            }
         }
 
-        // Wait for all downloads to complete
+        let total_packages = finished.len();
         let mut successful = 0;
         let mut failed = Vec::new();
+        let mut packages = Vec::new();
 
-        for (package_id, handle) in handles {
-            match handle.await {
-                Ok(Ok(_)) => successful += 1,
-                Ok(Err(e)) => {
+        for (package_id, required_by, result) in finished {
+            match result {
+                Ok(Ok(report)) => {
+                    successful += 1;
+                    packages.push(report);
+                }
+                Ok(Err((e, retry_count))) => {
                     failed.push(FailedDownload {
                         package: package_id,
+                        error_code: e.code(),
+                        error_kind: e.kind(),
                         error: e,
-                        retry_count: 0, // Will be updated by retry logic
+                        retry_count,
+                        required_by,
                     });
                 }
                 Err(e) => {
+                    let error = DownloadError::Network(format!("Task panic: {}", e));
                     failed.push(FailedDownload {
                         package: package_id,
-                        error: DownloadError::Network(format!("Task panic: {}", e)),
+                        error_code: error.code(),
+                        error_kind: error.kind(),
+                        error,
                         retry_count: 0,
+                        required_by,
                     });
                 }
             }
@@ -289,22 +800,262 @@ impl DownloadManager {
 
         let duration = start_time.elapsed();
 
+        // listed by path, not by which download finished first
+        packages.sort_by(|a, b| a.package.cmp(&b.package));
+        failed.sort_by(|a, b| a.package.cmp(&b.package));
+
         Ok(DownloadSummary {
             total_packages,
             successful,
             failed,
+            up_to_date: Vec::new(),
+            skipped: BTreeMap::new(),
+            packages,
             duration,
+            cache: CacheStats::default(),
         })
     }
 
-    /// Download with retry logic
+    /// A worker of [DownloadManager::process_queue]: takes tasks until
+    /// there are none left and none in flight or held back that could
+    /// queue more
+    fn worker<F>(&self, download_fn: Arc<F>) -> impl Future<Output = Vec<Finished>> + 'static
+    where
+        F: Fn(
+                DownloadTask,
+            )
+                -> futures::future::BoxFuture<'static, Result<PackageStats, DownloadError>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        let queue = Arc::clone(&self.queue);
+        let targets = Arc::clone(&self.targets);
+        let in_flight = Arc::clone(&self.in_flight);
+        let wake = Arc::clone(&self.wake);
+        let done = Arc::clone(&self.done);
+        let holds = Arc::clone(&self.holds);
+        let progress = Arc::clone(&self.progress);
+        let waiters = Arc::clone(&self.waiters);
+        let cancellation = self.cancellation.clone();
+        let default_timeout = self.timeout;
+        let seed = self.seed;
+
+        async move {
+            let mut finished = Vec::new();
+            loop {
+                // registered before looking, so a wake-up in between isn't missed
+                let woken = wake.notified();
+                let next = {
+                    let waiters = waiters.lock().await;
+                    let mut queue = queue.lock().await;
+                    let held = holds.load(Ordering::SeqCst) > 0;
+                    let idle = in_flight.load(Ordering::SeqCst) == 0;
+                    let next = {
+                        let done = done.lock().expect("finished downloads poisoned");
+                        let lock = |(idx, task): (usize, &DownloadTask)| {
+                            targets
+                                .try_lock(&task.target_dir)
+                                .map(|guard| (idx, Some(guard), None))
+                        };
+                        queue
+                            .iter()
+                            .enumerate()
+                            .find_map(|(idx, task)| {
+                                match Self::readiness(task, &done, &waiters, held) {
+                                    Ok(true) => lock((idx, task)),
+                                    Ok(false) => None,
+                                    Err(dep) => Some((idx, None, Some(dep))),
+                                }
+                            })
+                            // only waiting on each other: an import cycle
+                            .or_else(|| match idle && !held {
+                                true => queue.iter().enumerate().find_map(lock),
+                                false => None,
+                            })
+                    };
+                    match next {
+                        Some((idx, guard, blocker)) => {
+                            in_flight.fetch_add(1, Ordering::SeqCst);
+                            let task = queue.remove(idx).expect("index is in bounds");
+                            Some((task, guard, blocker))
+                        }
+                        None if queue.is_empty() && idle && !held => {
+                            // let the other idle workers see it too
+                            wake.notify_waiters();
+                            return finished;
+                        }
+                        None => None,
+                    }
+                };
+                let Some((task, guard, blocker)) = next else {
+                    woken.await;
+                    continue;
+                };
+
+                let package_id = task.package_id.clone();
+                let package_path = task.package_path.clone();
+                let required_by = task.required_by.clone();
+                if let Some(blocker) = blocker {
+                    let error = DownloadError::DependencyFailed(blocker);
+                    Self::record(&done, &package_path, false);
+                    if let Some(sender) = waiters.lock().await.remove(&package_path) {
+                        sender.send_replace(Some(DownloadOutcome::Failed(error.to_string())));
+                    }
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    wake.notify_waiters();
+                    finished.push((package_id, required_by, Ok(Err((error, 0)))));
+                    continue;
+                }
+                progress
+                    .update(ProgressUpdate::Started {
+                        package_id: package_id.clone(),
+                    })
+                    .await;
+
+                // spawned, so that a panicking download only fails its package
+                let download = tokio::spawn(Self::download(
+                    task,
+                    Arc::clone(&download_fn),
+                    default_timeout,
+                    cancellation.clone(),
+                    seed,
+                    Arc::clone(&progress),
+                ));
+                let result = download.await;
+                let outcome = match &result {
+                    Ok(Ok(report)) => DownloadOutcome::Completed(report.clone()),
+                    Ok(Err((e, _))) => DownloadOutcome::Failed(e.to_string()),
+                    Err(e) => DownloadOutcome::Failed(format!("Task panic: {}", e)),
+                };
+                // recorded before the waiter goes, so dependents never see neither
+                Self::record(&done, &package_path, matches!(result, Ok(Ok(_))));
+                if let Some(sender) = waiters.lock().await.remove(&package_path) {
+                    sender.send_replace(Some(outcome));
+                }
+                drop(guard);
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                wake.notify_waiters();
+
+                finished.push((package_id, required_by, result));
+            }
+        }
+    }
+
+    /// Downloads one task with retries, reporting its progress
+    async fn download<F>(
+        task: DownloadTask,
+        download_fn: Arc<F>,
+        default_timeout: Option<Duration>,
+        cancellation: Cancellation,
+        seed: u64,
+        progress: Arc<ProgressTracker>,
+    ) -> Result<PackageReport, (DownloadError, u32)>
+    where
+        F: Fn(
+            DownloadTask,
+        ) -> futures::future::BoxFuture<'static, Result<PackageStats, DownloadError>>,
+    {
+        let package_id = task.package_id.clone();
+        let timeout = task.timeout.or(default_timeout);
+        let mut rng = StdRng::seed_from_u64(task_seed(seed, &task.package_path));
+        let started = Instant::now();
+        let (result, attempts) = Self::download_with_retry(
+            task,
+            download_fn.as_ref(),
+            timeout,
+            &cancellation,
+            &mut rng,
+            &progress,
+        )
+        .await;
+
+        match &result {
+            Ok(_) => {
+                progress
+                    .update(ProgressUpdate::Completed {
+                        package_id: package_id.clone(),
+                    })
+                    .await;
+            }
+            Err(e) => {
+                progress
+                    .update(ProgressUpdate::Failed {
+                        package_id: package_id.clone(),
+                        error: e.to_string(),
+                    })
+                    .await;
+            }
+        }
+
+        let retries = attempts.saturating_sub(1);
+        result
+            .map(|stats| PackageReport {
+                package: package_id,
+                duration: started.elapsed(),
+                stats,
+                retries,
+            })
+            .map_err(|e| (e, retries))
+    }
+
+    fn record(done: &std::sync::Mutex<HashMap<String, bool>>, package_path: &str, ok: bool) {
+        done.lock()
+            .expect("finished downloads poisoned")
+            .insert(package_path.to_string(), ok);
+    }
+
+    /// Processes tasks one wave at a time, waiting for each wave to finish
+    /// before starting the next one.
+    ///
+    /// Tasks within a wave run concurrently. A task whose `depends_on`
+    /// names a package that failed in an earlier wave is not started and
+    /// is reported as failed instead.
+    pub async fn process_waves<F>(
+        &self,
+        waves: Vec<Vec<DownloadTask>>,
+        download_fn: F,
+    ) -> Result<DownloadSummary, DownloadError>
+    where
+        F: Fn(
+                DownloadTask,
+            )
+                -> futures::future::BoxFuture<'static, Result<PackageStats, DownloadError>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        let start_time = Instant::now();
+        let download_fn = Arc::new(download_fn);
+        let mut summary = DownloadSummary::default();
+
+        for wave in waves {
+            for task in wave {
+                self.queue_download(task).await?;
+            }
+
+            let download_fn = Arc::clone(&download_fn);
+            let wave_summary = self.process_queue(move |task| download_fn(task)).await?;
+            summary.merge(wave_summary);
+        }
+
+        summary.duration = start_time.elapsed();
+        Ok(summary)
+    }
+
+    /// Download with retry logic, returning the result and the number of attempts made
     async fn download_with_retry<F>(
         task: DownloadTask,
         download_fn: &F,
-        _progress: &ProgressTracker,
-    ) -> Result<(), DownloadError>
+        timeout: Option<Duration>,
+        cancellation: &Cancellation,
+        rng: &mut StdRng,
+        progress: &ProgressTracker,
+    ) -> (Result<PackageStats, DownloadError>, u32)
     where
-        F: Fn(DownloadTask) -> futures::future::BoxFuture<'static, Result<(), DownloadError>>,
+        F: Fn(
+            DownloadTask,
+        ) -> futures::future::BoxFuture<'static, Result<PackageStats, DownloadError>>,
     {
         let mut attempts = 0;
         let mut backoff = task.retry_config.initial_backoff;
@@ -312,20 +1063,54 @@ impl DownloadManager {
         loop {
             attempts += 1;
 
-            match download_fn(task.clone()).await {
-                Ok(_) => return Ok(()),
-                Err(_e) if attempts >= task.retry_config.max_attempts => {
-                    return Err(DownloadError::MaxRetriesExceeded);
+            // built lazily, so an interrupted run never starts the download
+            let attempt = async {
+                let attempt = download_fn(task.clone());
+                match timeout {
+                    Some(limit) => tokio::time::timeout(limit, attempt)
+                        .await
+                        .unwrap_or(Err(DownloadError::Timeout(limit))),
+                    None => attempt.await,
+                }
+            };
+            let result = cancellation
+                .run(attempt)
+                .await
+                .unwrap_or(Err(DownloadError::Cancelled));
+
+            match result {
+                Ok(stats) => return (Ok(stats), attempts),
+                // retrying can't help once the whole run was interrupted
+                Err(e @ DownloadError::Cancelled) => return (Err(e), attempts),
+                Err(e) if attempts >= task.retry_config.max_attempts => {
+                    return (
+                        Err(DownloadError::MaxRetriesExceeded(Box::new(e))),
+                        attempts,
+                    );
                 }
                 Err(e) => {
-                    // Log retry attempt
-                    eprintln!(
-                        "Download failed for {}: {}. Retrying in {:?} (attempt {}/{})",
-                        task.package_id, e, backoff, attempts, task.retry_config.max_attempts
+                    let delay = task.retry_config.jittered(backoff, rng);
+                    tracing::warn!(
+                        package = %task.package_id,
+                        error = %e,
+                        ?delay,
+                        attempt = attempts,
+                        max_attempts = task.retry_config.max_attempts,
+                        "download failed, retrying"
                     );
 
+                    progress
+                        .update(ProgressUpdate::Retrying {
+                            package_id: task.package_id.clone(),
+                            attempt: attempts,
+                            backoff: delay,
+                        })
+                        .await;
+
                     // Wait before retry
-                    tokio::time::sleep(backoff).await;
+                    if cancellation.run(tokio::time::sleep(delay)).await.is_err() {
+                        return (Err(DownloadError::Cancelled), attempts);
+                    }
 
                     // Update backoff
                     backoff = std::cmp::min(
@@ -343,15 +1128,59 @@ impl DownloadManager {
     }
 }
 
+impl DownloadSummary {
+    /// Folds another summary into this one, keeping the longer duration
+    pub fn merge(&mut self, other: DownloadSummary) {
+        self.total_packages += other.total_packages;
+        self.successful += other.successful;
+        self.failed.extend(other.failed);
+        self.up_to_date.extend(other.up_to_date);
+        self.skipped.extend(other.skipped);
+        self.packages.extend(other.packages);
+        self.duration = self.duration.max(other.duration);
+        self.cache.merge(other.cache);
+    }
+}
+
 impl std::fmt::Display for DownloadSummary {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Downloaded {} packages in {:?} ({} successful, {} failed)",
+            "Downloaded {} packages in {:?} ({} successful, {} up to date, {} failed)",
             self.total_packages,
             self.duration,
             self.successful,
+            self.up_to_date.len(),
             self.failed.len()
-        )
+        )?;
+        if self.cache.lookups() > 0 {
+            write!(f, "\n  cache: {}", self.cache)?;
+        }
+        for reason in self.skipped.values() {
+            write!(f, "\n  skipped: {}", reason)?;
+        }
+        for failure in &self.failed {
+            write!(f, "\n  failed: {}", failure)?;
+        }
+        Ok(())
     }
 }
+
+/// Derives a task's jitter seed from the manager seed and its package path
+fn task_seed(seed: u64, package_path: &str) -> u64 {
+    let digest = blake3::hash(package_path.as_bytes());
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&digest.as_bytes()[..8]);
+    seed ^ u64::from_le_bytes(bytes)
+}
+
+fn serialize_millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
+
+fn serialize_display<T: std::fmt::Display, S: Serializer>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
@@ -0,0 +1,129 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::fetch::DEFAULT_CACHE_DIR;
+
+/// Environment variable overriding the cache directory
+pub const CACHE_DIR_ENV: &str = "GGET_CACHE_DIR";
+
+/// Directory gget caches RPC responses in unless configured otherwise.
+///
+/// [CACHE_DIR_ENV] wins when set; otherwise it is `gget/packages` under the
+/// platform cache directory:
+///
+/// - Linux and other Unix: `$XDG_CACHE_HOME`, or `~/.cache`
+/// - macOS: `~/Library/Caches`
+/// - Windows: `%LOCALAPPDATA%`
+///
+/// Falls back to [DEFAULT_CACHE_DIR] in the working directory when none of
+/// those can be found.
+pub fn cache_dir() -> PathBuf {
+    cache_dir_from(|name| std::env::var_os(name))
+}
+
+/// Like [cache_dir], reading environment variables through `env`
+pub fn cache_dir_from(env: impl Fn(&str) -> Option<OsString>) -> PathBuf {
+    if let Some(dir) = absolute_dir(&env, CACHE_DIR_ENV) {
+        return dir;
+    }
+    match platform_cache_dir(&env) {
+        Some(base) => base.join("gget").join("packages"),
+        None => PathBuf::from(DEFAULT_CACHE_DIR),
+    }
+}
+
+/// The platform cache directory, see [cache_dir]
+fn platform_cache_dir(env: &impl Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+    let dir = |name: &str| absolute_dir(env, name);
+    if cfg!(windows) {
+        dir("LOCALAPPDATA")
+    } else if cfg!(target_os = "macos") {
+        dir("HOME").map(|home| home.join("Library").join("Caches"))
+    } else {
+        dir("XDG_CACHE_HOME").or_else(|| dir("HOME").map(|home| home.join(".cache")))
+    }
+}
+
+/// Directory named by the environment variable `name`.
+///
+/// Relative paths are ignored, as the XDG spec asks, so directories don't
+/// move with the working directory.
+fn absolute_dir(env: &impl Fn(&str) -> Option<OsString>, name: &str) -> Option<PathBuf> {
+    env(name)
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+}
+
+/// Directory of the lock files gget processes coordinate through, see
+/// [crate::filelock::FileLock]: `gget/locks` under the platform cache
+/// directory, or under the system temporary directory without one
+pub fn lock_dir() -> PathBuf {
+    lock_dir_from(|name| std::env::var_os(name))
+}
+
+/// Like [lock_dir], reading environment variables through `env`
+pub fn lock_dir_from(env: impl Fn(&str) -> Option<OsString>) -> PathBuf {
+    match platform_cache_dir(&env) {
+        Some(base) => base.join("gget").join("locks"),
+        None => std::env::temp_dir().join("gget-locks"),
+    }
+}
+
+/// A file name that could be written outside the directory it's joined to
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("`{name}` is not a safe relative path: {reason}")]
+pub struct UnsafePath {
+    pub name: String,
+    pub reason: &'static str,
+}
+
+/// Checks that `name`, a `/`-separated file name from the node, the cache
+/// or a lockfile, stays inside any directory it's joined to, on every
+/// platform.
+///
+/// Names are rejected rather than normalized: a node sending one is
+/// broken or hostile, and quietly writing elsewhere would hide that.
+pub fn check_relative(name: &str) -> Result<(), UnsafePath> {
+    let unsafe_path = |reason| {
+        Err(UnsafePath {
+            name: name.to_string(),
+            reason,
+        })
+    };
+    let bytes = name.as_bytes();
+
+    if name.is_empty() {
+        return unsafe_path("it is empty");
+    }
+    if name.starts_with('/') {
+        return unsafe_path("it is absolute");
+    }
+    if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        return unsafe_path("it starts with a drive letter");
+    }
+    if name.contains('\\') {
+        return unsafe_path("it contains a backslash");
+    }
+    // alternate data streams on NTFS
+    if name.contains(':') {
+        return unsafe_path("it contains a colon");
+    }
+    if name.chars().any(char::is_control) {
+        return unsafe_path("it contains a control character");
+    }
+    if name
+        .split('/')
+        .any(|part| part.is_empty() || part == "." || part == "..")
+    {
+        return unsafe_path("it has an empty, `.` or `..` component");
+    }
+    Ok(())
+}
+
+/// Joins `name` to `root` once [check_relative] accepts it
+pub fn safe_join(root: &Path, name: &str) -> Result<PathBuf, UnsafePath> {
+    check_relative(name)?;
+    Ok(root.join(name))
+}
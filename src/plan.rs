@@ -0,0 +1,134 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::lock::Selection;
+use crate::report::{Report, Table};
+
+/// What downloading a file would do to its local copy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileAction {
+    /// The file doesn't exist locally yet
+    Create,
+    /// The local file differs and would be replaced
+    Overwrite,
+    /// The local file already has the remote content
+    Unchanged,
+}
+
+impl FileAction {
+    /// Compares the local file at `path` with the remote `content`
+    pub fn for_file(path: &Path, content: &str) -> Self {
+        match fs::read(path) {
+            Ok(local) if local == content.as_bytes() => Self::Unchanged,
+            Ok(_) => Self::Overwrite,
+            Err(_) => Self::Create,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlannedFile {
+    /// Path relative to the package directory
+    pub name: String,
+    pub bytes: u64,
+    pub action: FileAction,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlannedPackage {
+    pub package: String,
+    pub target_dir: PathBuf,
+    pub files: Vec<PlannedFile>,
+}
+
+impl PlannedPackage {
+    /// Number of files that would get `action`
+    pub fn count(&self, action: FileAction) -> usize {
+        self.files.iter().filter(|f| f.action == action).count()
+    }
+
+    /// Bytes that would be written
+    pub fn bytes_to_write(&self) -> u64 {
+        self.files
+            .iter()
+            .filter(|f| f.action != FileAction::Unchanged)
+            .map(|f| f.bytes)
+            .sum()
+    }
+}
+
+/// What a download would write, computed without writing anything, see
+/// [crate::fetch::PackageManager::plan_download]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DownloadPlan {
+    /// Packages in download order, dependencies first
+    pub packages: Vec<PlannedPackage>,
+    /// Heights picked for packages pinned by several roots
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub selections: Vec<Selection>,
+}
+
+impl DownloadPlan {
+    /// Number of files that would get `action`, across every package
+    pub fn count(&self, action: FileAction) -> usize {
+        self.packages.iter().map(|p| p.count(action)).sum()
+    }
+
+    /// Bytes that would be written, across every package
+    pub fn bytes_to_write(&self) -> u64 {
+        self.packages
+            .iter()
+            .map(PlannedPackage::bytes_to_write)
+            .sum()
+    }
+}
+
+impl Report for DownloadPlan {
+    fn table(&self) -> Table {
+        let mut table = Table::new(
+            "Download plan",
+            &[
+                "package",
+                "target_dir",
+                "create",
+                "overwrite",
+                "unchanged",
+                "bytes",
+            ],
+        );
+        for p in &self.packages {
+            table.push_row([
+                p.package.clone(),
+                p.target_dir.display().to_string(),
+                p.count(FileAction::Create).to_string(),
+                p.count(FileAction::Overwrite).to_string(),
+                p.count(FileAction::Unchanged).to_string(),
+                p.bytes_to_write().to_string(),
+            ]);
+        }
+        table
+    }
+
+    fn human(&self) -> String {
+        let mut out = self.table().to_aligned();
+        if !self.selections.is_empty() {
+            out.push_str("\nSelected heights:\n");
+            for selection in &self.selections {
+                let _ = writeln!(out, "  {}", selection);
+            }
+        }
+        let _ = writeln!(
+            out,
+            "\nWould create {} and overwrite {} files ({} bytes) in {} packages; nothing was written.",
+            self.count(FileAction::Create),
+            self.count(FileAction::Overwrite),
+            self.bytes_to_write(),
+            self.packages.len()
+        );
+        out
+    }
+}
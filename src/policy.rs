@@ -0,0 +1,152 @@
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::error::ErrorKind;
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum PolicyError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON serialization/deserialization error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Package {package} is not allowed: {reason}")]
+    Disallowed { package: String, reason: String },
+}
+
+impl PolicyError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(_) => ErrorKind::Internal,
+            Self::Json(_) | Self::Disallowed { .. } => ErrorKind::Config,
+        }
+    }
+}
+
+/// What to do when resolution reaches a disallowed package
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyMode {
+    /// Abort the whole run
+    #[default]
+    Fail,
+    /// Skip the package (and everything only it requires) with a warning
+    Warn,
+}
+
+impl std::str::FromStr for PolicyMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fail" => Ok(Self::Fail),
+            "warn" => Ok(Self::Warn),
+            other => Err(format!("unknown policy mode `{}`", other)),
+        }
+    }
+}
+
+/// Allowlist/denylist governing which packages may enter a dependency closure.
+///
+/// Patterns match full package paths; `*` matches any run of characters,
+/// so `gno.land/r/*` covers every realm. Deny rules take precedence, and a
+/// non-empty allowlist rejects everything it doesn't match.
+///
+/// Policies can be loaded from a JSON file:
+///
+/// ```json
+/// { "allow": ["gno.land/p/demo/*"], "deny": ["gno.land/r/*"], "mode": "warn" }
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Policy {
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+    #[serde(default)]
+    pub mode: PolicyMode,
+}
+
+impl Policy {
+    /// Loads a policy from a JSON file
+    pub fn load(path: &Path) -> Result<Self, PolicyError> {
+        let data = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    /// Returns true if the policy has no rules at all
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// Checks a package path against the policy rules
+    pub fn check(&self, pkg_path: &str) -> Result<(), PolicyError> {
+        if let Some(pattern) = self.deny.iter().find(|p| matches_pattern(p, pkg_path)) {
+            return Err(PolicyError::Disallowed {
+                package: pkg_path.to_string(),
+                reason: format!("denied by `{}`", pattern),
+            });
+        }
+
+        if !self.allow.is_empty() && !self.allow.iter().any(|p| matches_pattern(p, pkg_path)) {
+            return Err(PolicyError::Disallowed {
+                package: pkg_path.to_string(),
+                reason: "not matched by any allow rule".to_string(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Matches a path against a pattern where `*` stands for any run of characters
+fn matches_pattern(pattern: &str, path: &str) -> bool {
+    let mut parts = pattern.split('*');
+    // split always yields at least one item
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // no wildcard in the pattern
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("gno.land/r/*", "gno.land/r/demo/boards"));
+        assert!(!matches_pattern("gno.land/r/*", "gno.land/p/demo/avl"));
+        assert!(matches_pattern(
+            "gno.land/p/demo/avl",
+            "gno.land/p/demo/avl"
+        ));
+        assert!(!matches_pattern(
+            "gno.land/p/demo/avl",
+            "gno.land/p/demo/avl/pager"
+        ));
+        assert!(matches_pattern("gno.land/*/demo/*", "gno.land/p/demo/ufmt"));
+        assert!(matches_pattern("*/ufmt", "gno.land/p/demo/ufmt"));
+        assert!(!matches_pattern("*/ufmt", "gno.land/p/demo/ufmt/v2"));
+    }
+}
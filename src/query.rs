@@ -1,4 +1,30 @@
-use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::report::{Report, Table};
+
+/// ABCI query path listing the files of a package, or reading one of them
+pub const QFILE_PATH: &str = "vm/qfile";
+
+/// ABCI query path returning what a realm's `Render` function outputs
+pub const QRENDER_PATH: &str = "vm/qrender";
+
+/// ABCI query path evaluating an expression in a realm
+pub const QEVAL_PATH: &str = "vm/qeval";
+
+/// ABCI query path listing the exported functions of a package
+pub const QFUNCS_PATH: &str = "vm/qfuncs";
+
+/// Id of the next JSON-RPC request, see [next_request_id]
+static NEXT_REQUEST_ID: AtomicU32 = AtomicU32::new(1);
+
+/// A fresh JSON-RPC request id. Ids increase over the life of the process,
+/// so responses, and log lines, can be matched with their request.
+pub fn next_request_id() -> u32 {
+    NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed)
+}
 
 #[derive(Serialize, Debug)]
 pub struct RpcRequest {
@@ -12,13 +38,115 @@ pub struct RpcRequest {
 pub struct RpcParams {
     pub path: String,
     pub data: String,
+    /// Block height to query at, the latest one if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<String>,
 }
 
+/// Reply to an [RpcRequest]: `result` when the node ran it, `error` when
+/// it rejected the request itself
 #[derive(Deserialize, Debug)]
 pub struct RpcResponse {
     pub jsonrpc: String,
-    pub id: u32,
-    pub result: RpcResult,
+    /// The request's, or empty when the node couldn't read it
+    #[serde(default)]
+    pub id: serde_json::Value,
+    #[serde(default)]
+    pub result: Option<RpcResult>,
+    #[serde(default)]
+    pub error: Option<RpcErrorObject>,
+}
+
+impl RpcResponse {
+    /// Whether this is the response to request `id`
+    pub fn answers(&self, id: u32) -> bool {
+        self.id.as_u64() == Some(id.into())
+    }
+}
+
+/// Error object of a JSON-RPC response
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct RpcErrorObject {
+    pub code: i64,
+    pub message: String,
+    /// Details, a string from tm2 nodes
+    #[serde(default)]
+    pub data: Option<serde_json::Value>,
+}
+
+impl RpcErrorObject {
+    /// The details as text, if there are any
+    pub fn data_text(&self) -> Option<String> {
+        match self.data.as_ref()? {
+            serde_json::Value::Null => None,
+            serde_json::Value::String(data) if data.is_empty() => None,
+            serde_json::Value::String(data) => Some(data.clone()),
+            other => Some(other.to_string()),
+        }
+    }
+}
+
+/// Error code of an [RpcErrorObject], per the JSON-RPC 2.0 spec
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RpcErrorCode {
+    /// The node couldn't parse the request as JSON
+    ParseError,
+    /// The request isn't a valid JSON-RPC request
+    InvalidRequest,
+    /// The node has no such method
+    MethodNotFound,
+    /// The method doesn't take these parameters
+    InvalidParams,
+    /// The node failed to run the method
+    InternalError,
+    /// One of the codes reserved for server errors, -32099 to -32000
+    ServerError(i64),
+    /// Any other code
+    Other(i64),
+}
+
+impl RpcErrorCode {
+    /// The numeric code
+    pub fn code(&self) -> i64 {
+        match self {
+            Self::ParseError => -32700,
+            Self::InvalidRequest => -32600,
+            Self::MethodNotFound => -32601,
+            Self::InvalidParams => -32602,
+            Self::InternalError => -32603,
+            Self::ServerError(code) | Self::Other(code) => *code,
+        }
+    }
+}
+
+impl From<i64> for RpcErrorCode {
+    fn from(code: i64) -> Self {
+        match code {
+            -32700 => Self::ParseError,
+            -32600 => Self::InvalidRequest,
+            -32601 => Self::MethodNotFound,
+            -32602 => Self::InvalidParams,
+            -32603 => Self::InternalError,
+            -32099..=-32000 => Self::ServerError(code),
+            code => Self::Other(code),
+        }
+    }
+}
+
+impl fmt::Display for RpcErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::ParseError => "parse error",
+            Self::InvalidRequest => "invalid request",
+            Self::MethodNotFound => "method not found",
+            Self::InvalidParams => "invalid params",
+            Self::InternalError => "internal error",
+            Self::ServerError(_) => "server error",
+            Self::Other(_) => "error",
+        };
+        write!(f, "{} ({})", name, self.code())
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -30,14 +158,151 @@ pub struct RpcResult {
 pub struct Response {
     #[serde(rename = "ResponseBase")]
     pub response_base: ResponseBase,
+    /// Height of the state the query ran against, empty if the node
+    /// didn't say
+    #[serde(rename = "Height", default, deserialize_with = "null_as_default")]
+    pub height: String,
 }
 
 #[derive(Deserialize, Debug)]
 pub struct ResponseBase {
     #[serde(rename = "Error")]
     pub error: Option<serde_json::Value>,
-    #[serde(rename = "Data")]
+    /// Base64; `null` when empty
+    #[serde(rename = "Data", default, deserialize_with = "null_as_default")]
     pub data: String,
-    #[serde(rename = "Log")]
+    #[serde(rename = "Log", default, deserialize_with = "null_as_default")]
     pub log: String,
 }
+
+#[derive(Deserialize, Debug)]
+pub struct StatusResponse {
+    pub result: StatusResult,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct StatusResult {
+    /// Left out by some gateways
+    #[serde(default)]
+    pub node_info: Option<NodeInfo>,
+    pub sync_info: SyncInfo,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct NodeInfo {
+    /// Id of the chain the node runs
+    pub network: String,
+    /// Version of the node software
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub moniker: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SyncInfo {
+    /// Encoded as a decimal string by tendermint
+    pub latest_block_height: String,
+    /// RFC 3339
+    #[serde(default)]
+    pub latest_block_time: String,
+    /// Whether the node is still syncing with the network
+    #[serde(default)]
+    pub catching_up: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AbciInfoResponse {
+    pub result: AbciInfoResult,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AbciInfoResult {
+    pub response: AbciInfo,
+}
+
+/// What the application on top of the node reports about itself
+#[derive(Deserialize, Debug)]
+pub struct AbciInfo {
+    #[serde(rename = "ABCIVersion", default)]
+    pub abci_version: String,
+    #[serde(rename = "AppVersion", default)]
+    pub app_version: String,
+    /// Encoded as a decimal string by tendermint
+    #[serde(rename = "LastBlockHeight", default)]
+    pub last_block_height: String,
+}
+
+/// An exported function of a package, as `vm/qfuncs` describes it
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct FuncSignature {
+    #[serde(rename(deserialize = "FuncName"))]
+    pub name: String,
+    #[serde(rename(deserialize = "Params"), deserialize_with = "null_as_default")]
+    pub params: Vec<NamedType>,
+    #[serde(rename(deserialize = "Results"), deserialize_with = "null_as_default")]
+    pub results: Vec<NamedType>,
+}
+
+/// A parameter or result of a [FuncSignature]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct NamedType {
+    /// Empty or `_` for unnamed results
+    #[serde(rename(deserialize = "Name"))]
+    pub name: String,
+    #[serde(rename(deserialize = "Type"))]
+    pub r#type: String,
+}
+
+impl fmt::Display for NamedType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name.as_str() {
+            "" | "_" => write!(f, "{}", self.r#type),
+            name => write!(f, "{} {}", name, self.r#type),
+        }
+    }
+}
+
+impl fmt::Display for FuncSignature {
+    /// Formats it the way it is declared, e.g. `func Render(path string) string`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |types: &[NamedType]| {
+            types
+                .iter()
+                .map(|t| t.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        write!(f, "func {}({})", self.name, list(&self.params))?;
+        match self.results.as_slice() {
+            [] => Ok(()),
+            [result] if matches!(result.name.as_str(), "" | "_") => {
+                write!(f, " {}", result.r#type)
+            }
+            results => write!(f, " ({})", list(results)),
+        }
+    }
+}
+
+impl Report for [FuncSignature] {
+    fn table(&self) -> Table {
+        let mut table = Table::new("Exported functions", &["name", "signature"]);
+        for func in self {
+            table.push_row([func.name.clone(), func.to_string()]);
+        }
+        table
+    }
+
+    fn human(&self) -> String {
+        self.iter().map(|func| format!("{}\n", func)).collect()
+    }
+}
+
+/// Go encodes empty slices as `null`
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    Ok(Option::deserialize(deserializer)?.unwrap_or_default())
+}
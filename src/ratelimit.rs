@@ -0,0 +1,155 @@
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+
+use crate::limits::parse_size;
+
+/// Limits applied to RPC traffic
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    /// Maximum RPC requests per second
+    pub requests_per_second: f64,
+    /// Maximum response bytes per second, if throttled
+    pub bytes_per_second: Option<u64>,
+}
+
+impl RateLimit {
+    pub fn requests_per_second(requests_per_second: f64) -> Self {
+        Self {
+            requests_per_second,
+            bytes_per_second: None,
+        }
+    }
+
+    pub fn with_bytes_per_second(mut self, bytes_per_second: u64) -> Self {
+        self.bytes_per_second = Some(bytes_per_second);
+        self
+    }
+}
+
+impl FromStr for RateLimit {
+    type Err = String;
+
+    /// Parses `<requests>[,<bytes>]`, where bytes accept a `k`, `m` or `g` suffix
+    /// (e.g. `10` or `10,512k`)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (requests, bytes) = match s.split_once(',') {
+            Some((requests, bytes)) => (requests, Some(bytes)),
+            None => (s, None),
+        };
+
+        let requests: f64 = requests
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid request rate `{}`", requests))?;
+        if requests <= 0.0 || !requests.is_finite() {
+            return Err(format!("request rate must be positive, got `{}`", requests));
+        }
+
+        let mut limit = Self::requests_per_second(requests);
+        if let Some(bytes) = bytes {
+            let bytes = parse_size(bytes).map_err(|_| format!("invalid byte rate `{}`", bytes))?;
+            limit = limit.with_bytes_per_second(bytes);
+        }
+        Ok(limit)
+    }
+}
+
+/// A token bucket refilled continuously at `rate` tokens per second.
+///
+/// The bucket holds at most one second worth of tokens, so idle time buys
+/// a short burst but never more.
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64) -> Self {
+        let capacity = rate.max(1.0);
+        Self {
+            rate,
+            capacity,
+            state: Mutex::new(BucketState {
+                tokens: capacity,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    fn refill(&self, state: &mut BucketState) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(state.refilled_at).as_secs_f64();
+        state.tokens = (state.tokens + elapsed * self.rate).min(self.capacity);
+        state.refilled_at = now;
+    }
+
+    /// Waits until `amount` tokens are available and takes them
+    async fn acquire(&self, amount: f64) {
+        loop {
+            let wait = {
+                let mut state = self.state.lock().await;
+                self.refill(&mut state);
+                if state.tokens >= amount {
+                    state.tokens -= amount;
+                    return;
+                }
+                Duration::from_secs_f64((amount - state.tokens) / self.rate)
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Takes `amount` tokens immediately, then waits until the bucket is out of debt.
+    ///
+    /// Used for byte counts, which are only known after a response arrived.
+    async fn charge(&self, amount: f64) {
+        let wait = {
+            let mut state = self.state.lock().await;
+            self.refill(&mut state);
+            state.tokens -= amount;
+            if state.tokens >= 0.0 {
+                return;
+            }
+            Duration::from_secs_f64(-state.tokens / self.rate)
+        };
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// Rate limiter shared by every RPC call of a package manager
+#[derive(Debug)]
+pub struct RateLimiter {
+    requests: TokenBucket,
+    bytes: Option<TokenBucket>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            requests: TokenBucket::new(limit.requests_per_second),
+            bytes: limit.bytes_per_second.map(|b| TokenBucket::new(b as f64)),
+        }
+    }
+
+    /// Waits for permission to send one request
+    pub async fn acquire_request(&self) {
+        self.requests.acquire(1.0).await;
+    }
+
+    /// Accounts for a received response body, throttling if over the byte budget
+    pub async fn record_bytes(&self, bytes: usize) {
+        if let Some(bucket) = &self.bytes {
+            bucket.charge(bytes as f64).await;
+        }
+    }
+}
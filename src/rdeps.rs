@@ -0,0 +1,220 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::dependency::{
+    DependencyClosure, DependencyError, DependencyGraph, DependencyResolver, Dependent,
+    PackageDependency, TestFiles,
+};
+use crate::deploy::{scan_packages, DeployError};
+use crate::error::ErrorKind;
+use crate::lock::{LockError, Lockfile, LOCKFILE_NAME};
+use crate::report::{Report, Table};
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum RdepsError {
+    #[error("Lockfile error: {0}")]
+    Lock(#[from] LockError),
+
+    #[error("Dependency error: {0}")]
+    Dependency(#[from] DependencyError),
+
+    #[error("Failed to scan packages: {0}")]
+    Scan(#[from] DeployError),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl RdepsError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Lock(e) => e.kind(),
+            Self::Dependency(e) => e.kind(),
+            Self::Scan(e) => e.kind(),
+            Self::Io(_) => ErrorKind::Internal,
+        }
+    }
+}
+
+/// The packages of a vendor tree that would break without `package`,
+/// answering `gget rdeps`
+#[derive(Debug, Clone, Serialize)]
+pub struct ReverseDependencies {
+    pub package: String,
+    /// Nearest first, see [DependencyClosure::dependents_of]
+    pub dependents: Vec<Dependent>,
+}
+
+impl ReverseDependencies {
+    /// Finds what imports `pkg_path` among the packages locked in
+    /// `target_dir/gget.lock`, or every package under `target_dir` if there
+    /// is no lockfile
+    pub fn from_dir(target_dir: &Path, pkg_path: &str) -> Result<Self, RdepsError> {
+        Ok(Self::new(&vendored_closure(target_dir)?, pkg_path))
+    }
+
+    pub fn new(closure: &DependencyClosure, pkg_path: &str) -> Self {
+        Self {
+            package: pkg_path.to_string(),
+            dependents: closure.dependents_of(pkg_path),
+        }
+    }
+
+    /// Packages importing the package themselves
+    pub fn direct(&self) -> impl Iterator<Item = &Dependent> {
+        self.dependents.iter().filter(|d| d.depth == 1)
+    }
+}
+
+/// Import chains from one package of a vendor tree to another, answering
+/// `gget why`
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportPaths {
+    pub root: String,
+    pub dependency: String,
+    /// Each from `root` to `dependency`, shortest first
+    pub paths: Vec<Vec<String>>,
+}
+
+impl ImportPaths {
+    /// Finds how `root` comes to import `dependency` among the packages
+    /// under `target_dir`, read as [ReverseDependencies::from_dir] does.
+    /// At most `limit` chains are kept.
+    pub fn from_dir(
+        target_dir: &Path,
+        root: &str,
+        dependency: &str,
+        limit: usize,
+    ) -> Result<Self, RdepsError> {
+        Ok(Self::new(
+            &vendored_closure(target_dir)?,
+            root,
+            dependency,
+            limit,
+        ))
+    }
+
+    pub fn new(closure: &DependencyClosure, root: &str, dependency: &str, limit: usize) -> Self {
+        let graph = DependencyGraph::from_packages(&closure.packages, TestFiles::Include);
+        Self {
+            root: root.to_string(),
+            dependency: dependency.to_string(),
+            paths: graph.import_paths(root, dependency, limit),
+        }
+    }
+}
+
+/// The locked packages of `target_dir`, or all of them without a lockfile
+fn vendored_closure(target_dir: &Path) -> Result<DependencyClosure, RdepsError> {
+    Ok(
+        match Lockfile::load_if_exists(&target_dir.join(LOCKFILE_NAME))? {
+            Some(lock) => locked_closure(&lock, target_dir)?,
+            None => scan_packages(target_dir)?,
+        },
+    )
+}
+
+/// The locked packages and what their sources import
+fn locked_closure(lock: &Lockfile, target_dir: &Path) -> Result<DependencyClosure, RdepsError> {
+    let resolver = DependencyResolver::new()?;
+    let mut closure = DependencyClosure {
+        roots: lock.roots.clone(),
+        ..Default::default()
+    };
+
+    for (path, entry) in &lock.packages {
+        let dir = lock.package_dir(target_dir, path);
+        let mut package = PackageDependency {
+            name: path.clone(),
+            imports: Default::default(),
+            test_imports: Default::default(),
+            instability: 0.0,
+        };
+        for name in entry.files.keys() {
+            // files in subdirectories aren't part of this package's source
+            if !name.ends_with(".gno") || name.contains('/') {
+                continue;
+            }
+            let (_, imports) =
+                resolver.extract_dependencies(&fs::read_to_string(dir.join(name))?)?;
+            package.add_imports(name, imports);
+        }
+        closure.packages.insert(path.clone(), package);
+    }
+
+    Ok(closure)
+}
+
+impl Report for ReverseDependencies {
+    fn table(&self) -> Table {
+        let mut table = Table::new(
+            &format!("Reverse dependencies of {}", self.package),
+            &["package", "depth", "via"],
+        );
+        for dependent in &self.dependents {
+            table.push_row([
+                dependent.package.clone(),
+                dependent.depth.to_string(),
+                dependent.via.clone(),
+            ]);
+        }
+        table
+    }
+
+    fn human(&self) -> String {
+        if self.dependents.is_empty() {
+            return format!("Nothing imports {}\n", self.package);
+        }
+
+        let mut out = format!(
+            "{} packages import {} ({} directly)\n",
+            self.dependents.len(),
+            self.package,
+            self.direct().count()
+        );
+        for dependent in &self.dependents {
+            if dependent.depth == 1 {
+                let _ = writeln!(out, "  {}", dependent.package);
+            } else {
+                let _ = writeln!(out, "  {} (via {})", dependent.package, dependent.via);
+            }
+        }
+        out
+    }
+}
+
+impl Report for ImportPaths {
+    fn table(&self) -> Table {
+        let mut table = Table::new(
+            &format!("Why {} depends on {}", self.root, self.dependency),
+            &["length", "path"],
+        );
+        for path in &self.paths {
+            table.push_row([(path.len() - 1).to_string(), path.join(" -> ")]);
+        }
+        table
+    }
+
+    fn human(&self) -> String {
+        if self.paths.is_empty() {
+            return format!("{} does not import {}\n", self.root, self.dependency);
+        }
+
+        let mut out = format!(
+            "{} reaches {} through {} import chain{}\n",
+            self.root,
+            self.dependency,
+            self.paths.len(),
+            if self.paths.len() == 1 { "" } else { "s" }
+        );
+        for path in &self.paths {
+            let _ = writeln!(out, "  {}", path.join(" -> "));
+        }
+        out
+    }
+}
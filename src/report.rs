@@ -0,0 +1,279 @@
+use std::fmt::Write as _;
+use std::str::FromStr;
+
+use serde::Serialize;
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::error::ErrorKind;
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum ReportError {
+    #[error("JSON serialization/deserialization error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+impl ReportError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            // gget failed to serialize its own output
+            Self::Json(_) => ErrorKind::Internal,
+        }
+    }
+}
+
+/// Output format shared by every informational command
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// Aligned text meant for a terminal
+    #[default]
+    Human,
+    Json,
+    Yaml,
+    Csv,
+    Markdown,
+}
+
+impl ReportFormat {
+    pub const NAMES: &'static [&'static str] = &["human", "json", "yaml", "csv", "markdown"];
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(Self::Human),
+            "json" => Ok(Self::Json),
+            "yaml" => Ok(Self::Yaml),
+            "csv" => Ok(Self::Csv),
+            "markdown" => Ok(Self::Markdown),
+            other => Err(format!("unknown report format `{}`", other)),
+        }
+    }
+}
+
+/// Rows and columns behind the human, CSV and markdown renderings
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Table {
+    pub title: String,
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(title: &str, columns: &[&str]) -> Self {
+        Self {
+            title: title.to_string(),
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn push_row<I, S>(&mut self, row: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: ToString,
+    {
+        self.rows
+            .push(row.into_iter().map(|c| c.to_string()).collect());
+    }
+
+    /// Renders the table with space-aligned columns
+    pub fn to_aligned(&self) -> String {
+        let mut widths: Vec<usize> = self.columns.iter().map(|c| c.chars().count()).collect();
+        for row in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        let line = |cells: &[String]| {
+            let padded: Vec<String> = cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect();
+            padded.join("  ").trim_end().to_string()
+        };
+
+        let mut out = String::new();
+        if !self.title.is_empty() {
+            let _ = writeln!(out, "{}", self.title);
+        }
+        let _ = writeln!(out, "{}", line(&self.columns));
+        for row in &self.rows {
+            let _ = writeln!(out, "{}", line(row));
+        }
+        out
+    }
+
+    /// Renders the table as RFC 4180 CSV with a header row
+    pub fn to_csv(&self) -> String {
+        let line = |cells: &[String]| {
+            cells
+                .iter()
+                .map(|c| csv_field(c))
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+
+        let mut out = String::new();
+        let _ = writeln!(out, "{}", line(&self.columns));
+        for row in &self.rows {
+            let _ = writeln!(out, "{}", line(row));
+        }
+        out
+    }
+
+    /// Renders the table as a GitHub-flavored markdown table
+    pub fn to_markdown(&self) -> String {
+        let line = |cells: &[String]| {
+            let escaped: Vec<String> = cells.iter().map(|c| c.replace('|', "\\|")).collect();
+            format!("| {} |", escaped.join(" | "))
+        };
+
+        let mut out = String::new();
+        if !self.title.is_empty() {
+            let _ = writeln!(out, "### {}\n", self.title);
+        }
+        let _ = writeln!(out, "{}", line(&self.columns));
+        let _ = writeln!(out, "|{}|", vec!["---"; self.columns.len()].join("|"));
+        for row in &self.rows {
+            let _ = writeln!(out, "{}", line(row));
+        }
+        out
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Something a command reports, renderable in every [ReportFormat].
+///
+/// JSON and YAML come from the `Serialize` impl, CSV from [Report::table],
+/// and the human and markdown formats default to renderings of that table.
+pub trait Report: Serialize {
+    fn table(&self) -> Table;
+
+    fn human(&self) -> String {
+        self.table().to_aligned()
+    }
+
+    fn markdown(&self) -> String {
+        self.table().to_markdown()
+    }
+}
+
+/// Renders a report in the requested format
+pub fn render<R: Report + ?Sized>(report: &R, format: ReportFormat) -> Result<String, ReportError> {
+    Ok(match format {
+        ReportFormat::Human => report.human(),
+        ReportFormat::Json => serde_json::to_string_pretty(report)? + "\n",
+        ReportFormat::Yaml => to_yaml(&serde_json::to_value(report)?),
+        ReportFormat::Csv => report.table().to_csv(),
+        ReportFormat::Markdown => report.markdown(),
+    })
+}
+
+/// Emits a JSON value as block-style YAML
+pub fn to_yaml(value: &Value) -> String {
+    let mut out = String::new();
+    match value {
+        Value::Object(map) if !map.is_empty() => write_yaml_map(&mut out, map, 0),
+        Value::Array(items) if !items.is_empty() => write_yaml_seq(&mut out, items, 0),
+        scalar => {
+            let _ = writeln!(out, "{}", yaml_scalar(scalar));
+        }
+    }
+    out
+}
+
+fn write_yaml_map(out: &mut String, map: &serde_json::Map<String, Value>, indent: usize) {
+    for (key, value) in map {
+        let _ = write!(out, "{:indent$}{}:", "", yaml_string(key), indent = indent);
+        write_yaml_child(out, value, indent);
+    }
+}
+
+fn write_yaml_seq(out: &mut String, items: &[Value], indent: usize) {
+    for item in items {
+        let _ = write!(out, "{:indent$}-", "", indent = indent);
+        match item {
+            // nested maps start on the dash line, like most YAML emitters
+            Value::Object(map) if !map.is_empty() => {
+                let mut first = true;
+                for (key, value) in map {
+                    if first {
+                        let _ = write!(out, " {}:", yaml_string(key));
+                        first = false;
+                    } else {
+                        let _ = write!(
+                            out,
+                            "{:indent$}{}:",
+                            "",
+                            yaml_string(key),
+                            indent = indent + 2
+                        );
+                    }
+                    write_yaml_child(out, value, indent + 2);
+                }
+            }
+            other => write_yaml_child(out, other, indent),
+        }
+    }
+}
+
+/// Writes the value following a `key:` or `-`, which is already on the line
+fn write_yaml_child(out: &mut String, value: &Value, indent: usize) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            out.push('\n');
+            write_yaml_map(out, map, indent + 2);
+        }
+        Value::Array(items) if !items.is_empty() => {
+            out.push('\n');
+            write_yaml_seq(out, items, indent + 2);
+        }
+        scalar => {
+            let _ = writeln!(out, " {}", yaml_scalar(scalar));
+        }
+    }
+}
+
+fn yaml_scalar(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => yaml_string(s),
+        Value::Array(_) => "[]".to_string(),
+        Value::Object(_) => "{}".to_string(),
+    }
+}
+
+/// Quotes strings that plain YAML would misread
+fn yaml_string(s: &str) -> String {
+    let ambiguous = matches!(
+        s.to_ascii_lowercase().as_str(),
+        "" | "null" | "~" | "true" | "false" | "yes" | "no" | "on" | "off"
+    ) || s.parse::<f64>().is_ok();
+    let special = s.starts_with(|c: char| "-?:,[]{}#&*!|>'\"%@` ".contains(c))
+        || s.ends_with(' ')
+        || s.contains(": ")
+        || s.contains(" #")
+        || s.contains(['\n', '\t', '\\']);
+
+    if ambiguous || special {
+        // JSON string syntax is valid double-quoted YAML
+        serde_json::to_string(s).unwrap_or_default()
+    } else {
+        s.to_string()
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::error::ErrorKind;
+use crate::fetch::{PackageManager, PackageManagerError};
+use crate::lock::{hash_content, LockError, LockedPackage, Lockfile, LOCKFILE_NAME};
+use crate::report::{Report, Table};
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum ReproError {
+    #[error("Package manager error: {0}")]
+    PackageManager(#[from] PackageManagerError),
+
+    #[error("Lockfile error: {0}")]
+    Lock(#[from] LockError),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl ReproError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::PackageManager(e) => e.kind(),
+            Self::Lock(e) => e.kind(),
+            Self::Io(_) => ErrorKind::Internal,
+        }
+    }
+}
+
+/// A file whose hash isn't the same in the lockfile, the vendor tree and a
+/// fresh download. `None` means the file is absent from that source.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DivergentFile {
+    pub file: String,
+    pub locked: Option<String>,
+    pub installed: Option<String>,
+    pub fetched: Option<String>,
+}
+
+/// Outcome of the check for one locked package
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PackageCheck {
+    pub package: String,
+    pub height: Option<u64>,
+    pub locked: String,
+    /// Hash of the vendor copy, `None` if it's missing
+    pub installed: Option<String>,
+    /// Hash of the fresh download, `None` if it failed
+    pub fetched: Option<String>,
+    pub passed: bool,
+    pub divergent: Vec<DivergentFile>,
+    /// Why the fresh download failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Result of `gget repro-check`: whether the vendor tree is exactly what
+/// its lockfile declares and what the chain serves for it
+#[derive(Debug, Clone, Serialize)]
+pub struct Attestation {
+    pub passed: bool,
+    pub lockfile: PathBuf,
+    /// Hash of the lockfile's bytes, pinning the inputs that were checked
+    pub lockfile_hash: String,
+    pub endpoint: String,
+    /// Seconds since the Unix epoch
+    pub checked_at: u64,
+    pub packages: Vec<PackageCheck>,
+}
+
+impl Report for Attestation {
+    fn table(&self) -> Table {
+        let mut table = Table::new(
+            "Reproducibility check",
+            &[
+                "package",
+                "status",
+                "locked",
+                "installed",
+                "fetched",
+                "divergent",
+            ],
+        );
+        for check in &self.packages {
+            table.push_row([
+                check.package.clone(),
+                if check.passed { "pass" } else { "fail" }.to_string(),
+                check.locked.clone(),
+                check.installed.clone().unwrap_or_default(),
+                check.fetched.clone().unwrap_or_default(),
+                check
+                    .divergent
+                    .iter()
+                    .map(|d| d.file.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ]);
+        }
+        table
+    }
+
+    fn human(&self) -> String {
+        let failed: Vec<&PackageCheck> = self.packages.iter().filter(|p| !p.passed).collect();
+        let mut out = if self.passed {
+            format!(
+                "PASS: {} packages match {} ({}) and {}\n",
+                self.packages.len(),
+                self.lockfile.display(),
+                self.lockfile_hash,
+                self.endpoint
+            )
+        } else {
+            format!(
+                "FAIL: {} of {} packages diverge from {} ({}) or {}\n",
+                failed.len(),
+                self.packages.len(),
+                self.lockfile.display(),
+                self.lockfile_hash,
+                self.endpoint
+            )
+        };
+
+        let short = |hash: &Option<String>| match hash {
+            Some(hash) => hash.chars().take(12).collect(),
+            None => "missing".to_string(),
+        };
+        for check in failed {
+            out.push_str(&format!("  {}\n", check.package));
+            if let Some(error) = &check.error {
+                out.push_str(&format!("    download failed: {}\n", error));
+            }
+            for file in &check.divergent {
+                out.push_str(&format!(
+                    "    {}: locked {}, installed {}, fetched {}\n",
+                    file.file,
+                    short(&file.locked),
+                    short(&file.installed),
+                    short(&file.fetched)
+                ));
+            }
+        }
+        out
+    }
+}
+
+/// Downloads every package locked under `target_dir` afresh into a
+/// temporary directory and compares the lockfile, the vendor tree and the
+/// download file by file.
+///
+/// Packages locked at a height are fetched at that height. The cache is
+/// bypassed, so the check reflects what the chain serves now.
+pub async fn repro_check(
+    pm: &PackageManager,
+    target_dir: &Path,
+) -> Result<Attestation, ReproError> {
+    let lock_path = target_dir.join(LOCKFILE_NAME);
+    let lock_bytes = fs::read(&lock_path).map_err(LockError::from)?;
+    let lock = Lockfile::load(&lock_path)?;
+
+    let scratch = ScratchDir::new()?;
+    let mut packages = Vec::with_capacity(lock.packages.len());
+    for locked in lock.packages.values() {
+        let pm = match locked.height {
+            Some(height) => pm.clone().with_pinned_height(&locked.path, height),
+            None => pm.clone(),
+        };
+
+        let installed_dir = lock.package_dir(target_dir, &locked.path);
+        let installed = if installed_dir.is_dir() {
+            Some(LockedPackage::from_dir(
+                &locked.path,
+                &installed_dir,
+                &lock.nested_dirs(&locked.path, target_dir),
+            )?)
+        } else {
+            None
+        };
+
+        let fetched = match pm.fetch_remote_files(&locked.path).await {
+            Ok(files) => {
+                let dir = lock.package_dir(&scratch.0, &locked.path);
+                fs::create_dir_all(&dir)?;
+                for (name, content) in &files {
+                    let path = dir.join(name);
+                    if let Some(parent) = path.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::write(path, content)?;
+                }
+                let nested = lock.nested_dirs(&locked.path, &scratch.0);
+                Ok(LockedPackage::from_dir(&locked.path, &dir, &nested)?)
+            }
+            Err(e @ PackageManagerError::Cancelled(_)) => return Err(e.into()),
+            Err(e) => Err(e.to_string()),
+        };
+
+        packages.push(compare(locked, installed.as_ref(), fetched));
+    }
+
+    Ok(Attestation {
+        passed: packages.iter().all(|p| p.passed),
+        lockfile: lock_path,
+        lockfile_hash: hash_content(&lock_bytes),
+        endpoint: pm.rpc_endpoint().to_string(),
+        checked_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        packages,
+    })
+}
+
+fn compare(
+    locked: &LockedPackage,
+    installed: Option<&LockedPackage>,
+    fetched: Result<LockedPackage, String>,
+) -> PackageCheck {
+    let (fetched, error) = match fetched {
+        Ok(fetched) => (Some(fetched), None),
+        Err(e) => (None, Some(e)),
+    };
+
+    // old lockfiles only carry package hashes, so files can't be told apart
+    let mut names: BTreeSet<&String> = locked.files.keys().collect();
+    if !locked.files.is_empty() {
+        for package in installed.into_iter().chain(fetched.as_ref()) {
+            names.extend(package.files.keys());
+        }
+    }
+    let divergent: Vec<DivergentFile> = names
+        .into_iter()
+        .filter_map(|name| {
+            let file = DivergentFile {
+                file: name.clone(),
+                locked: locked.files.get(name).cloned(),
+                installed: installed.and_then(|p| p.files.get(name).cloned()),
+                fetched: fetched.as_ref().and_then(|p| p.files.get(name).cloned()),
+            };
+            let same = file.locked == file.installed && file.locked == file.fetched;
+            (!same).then_some(file)
+        })
+        .collect();
+
+    let installed = installed.map(|p| p.hash.clone());
+    let fetched = fetched.map(|p| p.hash.clone());
+    let passed = error.is_none()
+        && installed.as_ref() == Some(&locked.hash)
+        && fetched.as_ref() == Some(&locked.hash)
+        && divergent.is_empty();
+
+    PackageCheck {
+        package: locked.path.clone(),
+        height: locked.height,
+        locked: locked.hash.clone(),
+        installed,
+        fetched,
+        passed,
+        divergent,
+        error,
+    }
+}
+
+/// Temporary directory removed on drop
+struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    fn new() -> std::io::Result<Self> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!("gget-repro-{}-{}", std::process::id(), nanos));
+        fs::create_dir_all(&dir)?;
+        Ok(Self(dir))
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,173 @@
+use std::fs;
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::error::ErrorKind;
+use crate::source::GitSource;
+
+/// Conventional name of a requirements file
+pub const REQUIREMENTS_NAME: &str = "gget-requirements.txt";
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum RequirementsError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("requirements line {line}: {reason}")]
+    Parse { line: usize, reason: String },
+}
+
+impl RequirementsError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(_) => ErrorKind::Internal,
+            Self::Parse { .. } => ErrorKind::Config,
+        }
+    }
+}
+
+/// A package to install, with its per-package options
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Requirement {
+    /// Package path (e.g., "gno.land/p/demo/avl")
+    pub path: String,
+    /// Block height to fetch the package at (`@height=N`)
+    pub height: Option<u64>,
+    /// Pin the package at the current height when no height is given
+    /// (`pin=true`)
+    pub pin: bool,
+    /// Directory layout to install the package with (`layout=...`)
+    pub layout: Option<String>,
+    /// Git repository to fetch the package from instead of the chain
+    /// (`git=URL rev=REV`, plus `dir=SUBDIR` if it isn't at the root)
+    pub source: Option<GitSource>,
+}
+
+/// A list of packages to install, one per line:
+///
+/// ```text
+/// # shared helpers
+/// gno.land/p/demo/avl @height=123 pin=true layout=import-path
+/// gno.land/p/demo/ufmt
+/// gno.land/p/me/draft git=https://github.com/me/draft rev=v0.1.0 dir=pkg
+/// ```
+///
+/// Options follow the path, separated by whitespace. Everything after a `#`
+/// is a comment.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Requirements {
+    pub entries: Vec<Requirement>,
+}
+
+impl Requirements {
+    /// Reads and parses a requirements file
+    pub fn load(path: &Path) -> Result<Self, RequirementsError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parses requirements content
+    pub fn parse(content: &str) -> Result<Self, RequirementsError> {
+        let mut requirements = Requirements::default();
+
+        for (idx, raw) in content.lines().enumerate() {
+            let line_no = idx + 1;
+            let line = raw.split_once('#').map_or(raw, |(line, _)| line).trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let requirement = parse_requirement(line, line_no)?;
+            if requirements
+                .entries
+                .iter()
+                .any(|other| other.path == requirement.path)
+            {
+                return Err(parse_error(
+                    line_no,
+                    format!("{} is listed more than once", requirement.path),
+                ));
+            }
+            requirements.entries.push(requirement);
+        }
+
+        Ok(requirements)
+    }
+}
+
+fn parse_requirement(line: &str, line_no: usize) -> Result<Requirement, RequirementsError> {
+    let mut parts = line.split_whitespace();
+    let path = parts.next().unwrap_or_default();
+    if path.contains('=') {
+        return Err(parse_error(line_no, "expected a package path first"));
+    }
+
+    let mut requirement = Requirement {
+        path: path.to_string(),
+        height: None,
+        pin: false,
+        layout: None,
+        source: None,
+    };
+    let (mut git, mut rev, mut dir) = (None, None, None);
+
+    for option in parts {
+        let Some((key, value)) = option.split_once('=') else {
+            return Err(parse_error(
+                line_no,
+                format!("expected key=value, got `{}`", option),
+            ));
+        };
+        match key {
+            "@height" => {
+                let height = value
+                    .parse()
+                    .map_err(|_| parse_error(line_no, format!("invalid height `{}`", value)))?;
+                requirement.height = Some(height);
+            }
+            "pin" => {
+                requirement.pin = value.parse().map_err(|_| {
+                    parse_error(
+                        line_no,
+                        format!("pin must be true or false, got `{}`", value),
+                    )
+                })?;
+            }
+            "layout" if !value.is_empty() => requirement.layout = Some(value.to_string()),
+            "layout" => return Err(parse_error(line_no, "layout must not be empty")),
+            "git" => git = Some(value),
+            "rev" => rev = Some(value),
+            "dir" => dir = Some(value),
+            _ => return Err(parse_error(line_no, format!("unknown option `{}`", key))),
+        }
+    }
+
+    match (git, rev) {
+        (Some(url), Some(rev)) => {
+            let source =
+                GitSource::new(url, rev, dir).map_err(|e| parse_error(line_no, e.to_string()))?;
+            requirement.source = Some(source);
+        }
+        (Some(_), None) => return Err(parse_error(line_no, "git needs a rev to pin")),
+        (None, _) if rev.is_some() || dir.is_some() => {
+            return Err(parse_error(line_no, "rev and dir need a git repository"));
+        }
+        (None, _) => {}
+    }
+    if requirement.source.is_some() && (requirement.height.is_some() || requirement.pin) {
+        return Err(parse_error(
+            line_no,
+            "heights don't apply to packages fetched from git",
+        ));
+    }
+
+    Ok(requirement)
+}
+
+fn parse_error(line: usize, reason: impl Into<String>) -> RequirementsError {
+    RequirementsError::Parse {
+        line,
+        reason: reason.into(),
+    }
+}
@@ -0,0 +1,418 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::dependency::{DependencyError, DependencyResolver};
+use crate::error::ErrorKind;
+use crate::lock::{LockDiff, LockError, Lockfile, RenamedFile, LOCKFILE_NAME};
+use crate::policy::Policy;
+use crate::report::{Report, Table};
+
+/// Hash prefix length shown where full hashes would clutter the output
+const SHORT_HASH_LEN: usize = 12;
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum ReviewError {
+    #[error("Lockfile error: {0}")]
+    Lock(#[from] LockError),
+
+    #[error("Dependency error: {0}")]
+    Dependency(#[from] DependencyError),
+
+    #[error("No gget.lock found in {0}")]
+    MissingLockfile(String),
+}
+
+impl ReviewError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Lock(e) => e.kind(),
+            Self::Dependency(e) => e.kind(),
+            Self::MissingLockfile(_) => ErrorKind::Config,
+        }
+    }
+}
+
+/// A locked package as listed in a [DependencyReview]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReviewedPackage {
+    pub path: String,
+    pub height: Option<u64>,
+    pub hash: String,
+    pub files: usize,
+    /// Total size of the locked files, if all of them are on disk
+    pub bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FindingKind {
+    /// The package is rejected by the allow/deny policy
+    Policy,
+    /// The files on disk don't match the lockfile
+    Integrity,
+}
+
+/// Something a reviewer should look at before accepting the lockfile
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    pub package: String,
+    pub kind: FindingKind,
+    pub message: String,
+}
+
+/// Review of the dependencies locked in a download directory.
+///
+/// Rendered as markdown it is meant to be pasted into a pull request, so
+/// dependency updates document themselves: what is locked, how the packages
+/// import each other, what looks wrong, and what changed since the last
+/// commit.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DependencyReview {
+    pub roots: Vec<String>,
+    pub packages: Vec<ReviewedPackage>,
+    /// Locked packages imported by each locked package
+    pub dependencies: BTreeMap<String, Vec<String>>,
+    pub findings: Vec<Finding>,
+    /// Changes against the committed lockfile, if there is one
+    pub lock_diff: Option<LockDiff>,
+}
+
+impl DependencyReview {
+    /// Reviews `target_dir/gget.lock` against the files next to it
+    pub fn from_dir(target_dir: &Path, policy: &Policy) -> Result<Self, ReviewError> {
+        let lock = Lockfile::load_if_exists(&target_dir.join(LOCKFILE_NAME))?
+            .ok_or_else(|| ReviewError::MissingLockfile(target_dir.display().to_string()))?;
+        Self::new(&lock, target_dir, policy)
+    }
+
+    /// Reviews a lockfile whose packages live under `target_dir`
+    pub fn new(lock: &Lockfile, target_dir: &Path, policy: &Policy) -> Result<Self, ReviewError> {
+        let resolver = DependencyResolver::new()?;
+        let mut review = Self {
+            roots: lock.roots.clone(),
+            ..Default::default()
+        };
+
+        for (path, entry) in &lock.packages {
+            let dir = lock.package_dir(target_dir, path);
+            let mut bytes = Some(0);
+            let mut imports = BTreeSet::new();
+
+            for name in entry.files.keys() {
+                let file = dir.join(name);
+                bytes = match (bytes, fs::metadata(&file)) {
+                    (Some(total), Ok(meta)) => Some(total + meta.len()),
+                    _ => None,
+                };
+
+                // files in subdirectories aren't part of this package's source
+                if !name.ends_with(".gno") || name.contains('/') {
+                    continue;
+                }
+                // unparsable sources are reported by `--validate`, not here
+                let Ok(source) = fs::read_to_string(&file) else {
+                    continue;
+                };
+                if let Ok((_, found)) = resolver.extract_dependencies(&source) {
+                    imports.extend(
+                        found
+                            .into_iter()
+                            .filter(|i| i != path && lock.packages.contains_key(i)),
+                    );
+                }
+            }
+
+            if let Err(e) = policy.check(path) {
+                review.findings.push(Finding {
+                    package: path.clone(),
+                    kind: FindingKind::Policy,
+                    message: e.to_string(),
+                });
+            }
+            let changed = entry.changed_files(&dir);
+            if !changed.is_empty() {
+                review.findings.push(Finding {
+                    package: path.clone(),
+                    kind: FindingKind::Integrity,
+                    message: format!("missing or modified: {}", changed.join(", ")),
+                });
+            }
+
+            review.packages.push(ReviewedPackage {
+                path: path.clone(),
+                height: entry.height,
+                hash: entry.hash.clone(),
+                files: entry.files.len(),
+                bytes,
+            });
+            review
+                .dependencies
+                .insert(path.clone(), imports.into_iter().collect());
+        }
+
+        Ok(review)
+    }
+
+    pub fn with_lock_diff(mut self, diff: LockDiff) -> Self {
+        self.lock_diff = Some(diff);
+        self
+    }
+
+    /// Flattens the dependency tree into `(depth, package, repeated)` lines.
+    ///
+    /// Trees start at the roots, followed by any package nothing else imports.
+    /// A package already shown is marked as repeated and not expanded again.
+    pub fn tree(&self) -> Vec<(usize, &str, bool)> {
+        let imported: HashSet<&str> = self
+            .dependencies
+            .values()
+            .flatten()
+            .map(|s| s.as_str())
+            .collect();
+        let starts = self
+            .roots
+            .iter()
+            .filter(|r| self.dependencies.contains_key(*r))
+            .chain(
+                self.dependencies
+                    .keys()
+                    .filter(|p| !imported.contains(p.as_str())),
+            )
+            // packages only reachable through a cycle
+            .chain(self.dependencies.keys());
+
+        let mut lines = Vec::new();
+        let mut seen = HashSet::new();
+        for start in starts {
+            if !seen.contains(start.as_str()) {
+                self.walk_tree(start, 0, &mut seen, &mut lines);
+            }
+        }
+        lines
+    }
+
+    fn walk_tree<'a>(
+        &'a self,
+        pkg: &'a str,
+        depth: usize,
+        seen: &mut HashSet<&'a str>,
+        lines: &mut Vec<(usize, &'a str, bool)>,
+    ) {
+        if !seen.insert(pkg) {
+            lines.push((depth, pkg, true));
+            return;
+        }
+        lines.push((depth, pkg, false));
+        for dep in self.dependencies.get(pkg).into_iter().flatten() {
+            self.walk_tree(dep, depth + 1, seen, lines);
+        }
+    }
+
+    fn package_table(&self, hash: impl Fn(&str) -> String) -> Table {
+        let mut table = Table::new("Packages", &["package", "height", "hash", "files", "bytes"]);
+        for pkg in &self.packages {
+            table.push_row([
+                pkg.path.clone(),
+                or_dash(pkg.height),
+                hash(&pkg.hash),
+                pkg.files.to_string(),
+                or_dash(pkg.bytes),
+            ]);
+        }
+        table
+    }
+}
+
+impl Report for DependencyReview {
+    fn table(&self) -> Table {
+        self.package_table(|h| h.to_string())
+    }
+
+    fn human(&self) -> String {
+        let mut out = self
+            .package_table(|h| short_hash(h).to_string())
+            .to_aligned();
+
+        let _ = writeln!(out, "\nDependency tree:");
+        for (depth, pkg, repeated) in self.tree() {
+            let marker = if repeated { " (*)" } else { "" };
+            let _ = writeln!(out, "  {:indent$}{}{}", "", pkg, marker, indent = depth * 2);
+        }
+
+        let _ = writeln!(out, "\nFindings:");
+        if self.findings.is_empty() {
+            let _ = writeln!(out, "  none");
+        }
+        for finding in &self.findings {
+            let _ = writeln!(
+                out,
+                "  [{}] {}: {}",
+                finding_label(finding.kind),
+                finding.package,
+                finding.message
+            );
+        }
+
+        let _ = writeln!(out, "\nLockfile changes since last commit:");
+        match &self.lock_diff {
+            None => {
+                let _ = writeln!(out, "  no committed lockfile to compare against");
+            }
+            Some(diff) if diff.is_empty() => {
+                let _ = writeln!(out, "  none");
+            }
+            Some(diff) => {
+                for (change, pkg, before, after) in diff_rows(diff) {
+                    let _ = writeln!(out, "  {:<8} {} {} -> {}", change, pkg, before, after);
+                }
+            }
+        }
+        out
+    }
+
+    fn markdown(&self) -> String {
+        let mut out = String::from("## Dependency review\n\n");
+        let roots: Vec<String> = self.roots.iter().map(|r| format!("`{}`", r)).collect();
+        let _ = write!(out, "{} packages locked", self.packages.len());
+        if !roots.is_empty() {
+            let _ = write!(out, " for {}", roots.join(", "));
+        }
+        let _ = writeln!(out, ", {} findings.\n", self.findings.len());
+
+        out.push_str(
+            &self
+                .package_table(|h| format!("`{}`", short_hash(h)))
+                .to_markdown(),
+        );
+
+        out.push_str("\n### Dependency tree\n\n");
+        for (depth, pkg, repeated) in self.tree() {
+            let marker = if repeated { " (see above)" } else { "" };
+            let _ = writeln!(
+                out,
+                "{:indent$}- `{}`{}",
+                "",
+                pkg,
+                marker,
+                indent = depth * 2
+            );
+        }
+
+        out.push_str("\n### Findings\n\n");
+        if self.findings.is_empty() {
+            out.push_str("No findings.\n");
+        }
+        for finding in &self.findings {
+            let _ = writeln!(
+                out,
+                "- **{}** `{}`: {}",
+                finding_label(finding.kind),
+                finding.package,
+                finding.message
+            );
+        }
+
+        out.push_str("\n### Lockfile changes\n\n");
+        match &self.lock_diff {
+            None => out.push_str("No committed lockfile to compare against.\n"),
+            Some(diff) if diff.is_empty() => out.push_str("No changes since the last commit.\n"),
+            Some(diff) => {
+                let mut table = Table::new("", &["change", "package", "before", "after"]);
+                for (change, pkg, before, after) in diff_rows(diff) {
+                    table.push_row([change.to_string(), format!("`{}`", pkg), before, after]);
+                }
+                out.push_str(&table.to_markdown());
+            }
+        }
+        out
+    }
+}
+
+/// Reads the version of `lock_path` committed at git `HEAD`, if there is one
+pub fn committed_lockfile(lock_path: &Path) -> Option<Lockfile> {
+    let dir = match lock_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let name = lock_path.file_name()?.to_str()?;
+
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("show")
+        .arg(format!("HEAD:./{}", name))
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    serde_json::from_slice(&output.stdout).ok()
+}
+
+fn diff_rows(diff: &LockDiff) -> Vec<(&'static str, &str, String, String)> {
+    let mut rows = Vec::new();
+    for pkg in &diff.added {
+        rows.push(("added", pkg.as_str(), "-".to_string(), "-".to_string()));
+    }
+    for pkg in &diff.removed {
+        rows.push(("removed", pkg.as_str(), "-".to_string(), "-".to_string()));
+    }
+    for moved in &diff.moved {
+        rows.push((
+            "moved",
+            moved.to.as_str(),
+            moved.from.clone(),
+            format!("{}% identical", moved.similarity),
+        ));
+        rows.extend(renamed_rows(&moved.to, &moved.renamed_files));
+    }
+    for change in &diff.changed {
+        rows.push((
+            "changed",
+            change.path.as_str(),
+            locked_version(&change.old_hash, change.old_height),
+            locked_version(&change.new_hash, change.new_height),
+        ));
+        rows.extend(renamed_rows(&change.path, &change.renamed_files));
+    }
+    rows
+}
+
+fn renamed_rows<'a>(
+    pkg: &'a str,
+    renamed: &[RenamedFile],
+) -> Vec<(&'static str, &'a str, String, String)> {
+    renamed
+        .iter()
+        .map(|file| ("renamed", pkg, file.from.clone(), file.to.clone()))
+        .collect()
+}
+
+fn locked_version(hash: &str, height: Option<u64>) -> String {
+    match height {
+        Some(height) => format!("{} @ {}", short_hash(hash), height),
+        None => short_hash(hash).to_string(),
+    }
+}
+
+fn finding_label(kind: FindingKind) -> &'static str {
+    match kind {
+        FindingKind::Policy => "policy",
+        FindingKind::Integrity => "integrity",
+    }
+}
+
+fn short_hash(hash: &str) -> &str {
+    hash.get(..SHORT_HASH_LEN).unwrap_or(hash)
+}
+
+fn or_dash<T: ToString>(value: Option<T>) -> String {
+    value.map_or_else(|| "-".to_string(), |v| v.to_string())
+}
@@ -0,0 +1,494 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+
+use async_trait::async_trait;
+use futures::{SinkExt, StreamExt};
+use reqwest::header::HeaderMap;
+use reqwest::{Client, StatusCode};
+use thiserror::Error;
+use tokio::sync::{mpsc, oneshot, Mutex, Notify};
+use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::error::ErrorKind;
+use crate::query::RpcRequest;
+use crate::session::{LoginHook, SessionError};
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum RpcClientError {
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("WebSocket error: {0}")]
+    WebSocket(#[from] Box<tokio_tungstenite::tungstenite::Error>),
+
+    #[error("JSON serialization/deserialization error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("WebSocket connection closed before the response arrived")]
+    ConnectionClosed,
+
+    #[error("Login failed: {0}")]
+    Login(#[from] SessionError),
+
+    #[error("Endpoint is overloaded (HTTP 429 Too Many Requests)")]
+    TooManyRequests,
+
+    #[error("Response is larger than the {max} bytes allowed, raise --max-file-size to accept it")]
+    ResponseTooLarge { max: u64 },
+}
+
+impl RpcClientError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Http(e) if e.status() == Some(StatusCode::NOT_FOUND) => ErrorKind::NotFound,
+            Self::Http(_) | Self::WebSocket(_) | Self::ConnectionClosed | Self::TooManyRequests => {
+                ErrorKind::Network
+            }
+            Self::Json(_) => ErrorKind::Integrity,
+            Self::Login(_) | Self::ResponseTooLarge { .. } => ErrorKind::Config,
+        }
+    }
+
+    /// Whether the endpoint is shedding load or too slow to answer, as
+    /// opposed to unreachable
+    pub fn is_overload(&self) -> bool {
+        match self {
+            Self::TooManyRequests => true,
+            Self::Http(e) => e.is_timeout(),
+            _ => false,
+        }
+    }
+}
+
+/// Transport carrying JSON-RPC requests to a tm2 node
+#[async_trait]
+pub trait RpcClient: Send + Sync {
+    /// Sends a request, returning the raw JSON response body
+    async fn send(&self, request: &RpcRequest) -> Result<Vec<u8>, RpcClientError>;
+
+    /// A client over the same endpoints allowing each at most `max`
+    /// requests in flight, or `None` if this client has only one endpoint
+    fn with_max_in_flight(&self, _max: usize) -> Option<Arc<dyn RpcClient>> {
+        None
+    }
+
+    /// A client over the same endpoints refusing responses larger than
+    /// `max` bytes while reading them, or `None` if this client can't cap
+    /// them
+    fn with_max_response_size(&self, _max: Option<u64>) -> Option<Arc<dyn RpcClient>> {
+        None
+    }
+}
+
+/// Picks the transport matching the endpoint's scheme: WebSocket for
+/// `ws://` and `wss://`, HTTP otherwise
+pub fn client_for(endpoint: &str) -> Arc<dyn RpcClient> {
+    client_with(endpoint, Client::new())
+}
+
+/// Like [client_for], sending HTTP requests through `client`
+pub fn client_with(endpoint: &str, client: Client) -> Arc<dyn RpcClient> {
+    if endpoint.starts_with("ws://") || endpoint.starts_with("wss://") {
+        Arc::new(WsRpcClient::new(endpoint))
+    } else {
+        Arc::new(HttpRpcClient::with_client(endpoint, client))
+    }
+}
+
+/// One HTTP POST per request
+pub struct HttpRpcClient {
+    client: Client,
+    url: String,
+    login: Option<Arc<LoginHook>>,
+    /// Bytes a response body may have, unbounded unless set
+    max_response_size: Option<u64>,
+}
+
+impl HttpRpcClient {
+    pub fn new(url: &str) -> Self {
+        Self::with_client(url, Client::new())
+    }
+
+    /// Uses a preconfigured client, e.g. with a user agent or timeouts
+    pub fn with_client(url: &str, client: Client) -> Self {
+        Self {
+            client,
+            url: url.to_string(),
+            login: None,
+            max_response_size: None,
+        }
+    }
+
+    /// Sends the headers printed by `login` with every request, for
+    /// endpoints behind a session-authenticated gateway
+    pub fn with_login_hook(mut self, login: LoginHook) -> Self {
+        self.login = Some(Arc::new(login));
+        self
+    }
+
+    async fn post(
+        &self,
+        request: &RpcRequest,
+        headers: HeaderMap,
+    ) -> Result<reqwest::Response, RpcClientError> {
+        Ok(self
+            .client
+            .post(&self.url)
+            .headers(headers)
+            .json(request)
+            .send()
+            .await?)
+    }
+}
+
+#[async_trait]
+impl RpcClient for HttpRpcClient {
+    async fn send(&self, request: &RpcRequest) -> Result<Vec<u8>, RpcClientError> {
+        let Some(login) = &self.login else {
+            let response = self.post(request, HeaderMap::new()).await?;
+            return body(response, self.max_response_size).await;
+        };
+
+        let headers = login.headers().await?;
+        let mut response = self.post(request, headers.clone()).await?;
+        if matches!(
+            response.status(),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+        ) {
+            let headers = login.refresh(&headers).await?;
+            response = self.post(request, headers).await?;
+        }
+        body(response, self.max_response_size).await
+    }
+
+    fn with_max_response_size(&self, max: Option<u64>) -> Option<Arc<dyn RpcClient>> {
+        Some(Arc::new(Self {
+            client: self.client.clone(),
+            url: self.url.clone(),
+            login: self.login.clone(),
+            max_response_size: max,
+        }))
+    }
+}
+
+/// Reads a response's body, stopping as soon as it grows past `max`
+/// instead of buffering all of it first
+async fn body(
+    mut response: reqwest::Response,
+    max: Option<u64>,
+) -> Result<Vec<u8>, RpcClientError> {
+    if response.status() == StatusCode::TOO_MANY_REQUESTS {
+        return Err(RpcClientError::TooManyRequests);
+    }
+    let Some(max) = max else {
+        return Ok(response.bytes().await?.to_vec());
+    };
+    if response.content_length().is_some_and(|len| len > max) {
+        return Err(RpcClientError::ResponseTooLarge { max });
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if (body.len() + chunk.len()) as u64 > max {
+            return Err(RpcClientError::ResponseTooLarge { max });
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+/// Sends each request to the first endpoint that can be reached.
+///
+/// Only transport failures move on to the next endpoint; a response is
+/// returned as is, even if the node reported an error in it.
+///
+/// Requests in flight are tracked per endpoint. An endpoint answering
+/// 429 or timing out has its share halved, so requests overflow to the
+/// next one, and gets it back one request at a time as it succeeds again.
+pub struct FailoverRpcClient {
+    clients: Vec<Arc<dyn RpcClient>>,
+    /// Requests each endpoint may have in flight, unbounded unless set
+    max_in_flight: usize,
+    loads: StdMutex<Vec<Load>>,
+    freed: Notify,
+}
+
+/// What one endpoint of a [FailoverRpcClient] is doing
+#[derive(Debug, Clone, Copy)]
+struct Load {
+    in_flight: usize,
+    /// Requests it may have in flight now, at most the client's maximum
+    share: usize,
+}
+
+impl FailoverRpcClient {
+    /// `clients` are tried in order. Panics if there are none.
+    pub fn new(clients: Vec<Arc<dyn RpcClient>>) -> Self {
+        Self::with_limit(clients, usize::MAX)
+    }
+
+    fn with_limit(clients: Vec<Arc<dyn RpcClient>>, max_in_flight: usize) -> Self {
+        assert!(!clients.is_empty(), "failover needs at least one endpoint");
+        let load = Load {
+            in_flight: 0,
+            share: max_in_flight,
+        };
+        Self {
+            loads: StdMutex::new(vec![load; clients.len()]),
+            clients,
+            max_in_flight,
+            freed: Notify::new(),
+        }
+    }
+
+    /// Takes a slot on the first endpoint not `tried` yet that has room,
+    /// waiting for one to free up if all of them are busy. `None` once
+    /// every endpoint has been tried.
+    async fn acquire(&self, tried: &mut [bool]) -> Option<Slot<'_>> {
+        loop {
+            // registered before looking, so a slot freed in between isn't missed
+            let freed = self.freed.notified();
+            {
+                let mut loads = self.loads.lock().expect("endpoint loads poisoned");
+                let untried = (0..loads.len()).filter(|idx| !tried[*idx]);
+                let mut full = Vec::new();
+                for idx in untried {
+                    let load = &mut loads[idx];
+                    if load.in_flight >= load.share {
+                        full.push(idx);
+                        continue;
+                    }
+                    if !full.is_empty() {
+                        tracing::debug!(endpoint = idx, busy = ?full, "endpoints at their share, sending elsewhere");
+                    }
+                    load.in_flight += 1;
+                    tried[idx] = true;
+                    return Some(Slot { client: self, idx });
+                }
+                if full.is_empty() {
+                    return None;
+                }
+            }
+            freed.await;
+        }
+    }
+
+    /// Shrinks or grows an endpoint's share after a request to it
+    fn adapt(&self, idx: usize, result: &Result<Vec<u8>, RpcClientError>) {
+        let mut loads = self.loads.lock().expect("endpoint loads poisoned");
+        let load = &mut loads[idx];
+        match result {
+            Err(e) if e.is_overload() => {
+                let share = (load.share.min(load.in_flight) / 2).max(1);
+                if share < load.share {
+                    tracing::warn!(endpoint = idx, share, error = %e, "endpoint overloaded, lowering its share");
+                    load.share = share;
+                }
+            }
+            Ok(_) if load.share < self.max_in_flight => {
+                load.share += 1;
+                if load.share == self.max_in_flight {
+                    tracing::debug!(endpoint = idx, "endpoint recovered its full share");
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A request in flight to one endpoint of a [FailoverRpcClient], given
+/// back when dropped
+struct Slot<'a> {
+    client: &'a FailoverRpcClient,
+    idx: usize,
+}
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        let mut loads = self.client.loads.lock().expect("endpoint loads poisoned");
+        loads[self.idx].in_flight -= 1;
+        self.client.freed.notify_waiters();
+    }
+}
+
+#[async_trait]
+impl RpcClient for FailoverRpcClient {
+    async fn send(&self, request: &RpcRequest) -> Result<Vec<u8>, RpcClientError> {
+        let mut tried = vec![false; self.clients.len()];
+        let mut last_error = None;
+        while let Some(slot) = self.acquire(&mut tried).await {
+            let result = self.clients[slot.idx].send(request).await;
+            self.adapt(slot.idx, &result);
+            match result {
+                Ok(body) => return Ok(body),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.expect("at least one endpoint"))
+    }
+
+    fn with_max_in_flight(&self, max: usize) -> Option<Arc<dyn RpcClient>> {
+        Some(Arc::new(Self::with_limit(self.clients.clone(), max.max(1))))
+    }
+
+    fn with_max_response_size(&self, max: Option<u64>) -> Option<Arc<dyn RpcClient>> {
+        let clients = self
+            .clients
+            .iter()
+            .map(|client| {
+                client
+                    .with_max_response_size(max)
+                    .unwrap_or_else(|| client.clone())
+            })
+            .collect();
+        Some(Arc::new(Self::with_limit(clients, self.max_in_flight)))
+    }
+}
+
+/// Requests waiting for a response, by request id
+type Pending = Arc<StdMutex<HashMap<u32, oneshot::Sender<Vec<u8>>>>>;
+
+/// Multiplexes requests over a single WebSocket connection (tm2 serves it
+/// at `/websocket`, e.g. `ws://localhost:26657/websocket`).
+///
+/// Every request gets a fresh id on the connection so responses can arrive
+/// in any order; responses come back with the caller's id. The connection
+/// is opened on first use and reopened after it drops.
+///
+/// A message over the response size cap drops the connection, failing the
+/// requests waiting on it.
+pub struct WsRpcClient {
+    url: String,
+    /// Bytes a message may have, tungstenite's default unless set
+    max_message_size: Option<usize>,
+    next_id: AtomicU32,
+    connection: Mutex<Option<WsConnection>>,
+}
+
+#[derive(Clone)]
+struct WsConnection {
+    outgoing: mpsc::UnboundedSender<Message>,
+    pending: Pending,
+    closed: Arc<AtomicBool>,
+}
+
+impl WsRpcClient {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            max_message_size: None,
+            next_id: AtomicU32::new(1),
+            connection: Mutex::new(None),
+        }
+    }
+
+    /// Returns the open connection, connecting if there is none
+    async fn connection(&self) -> Result<WsConnection, RpcClientError> {
+        let mut current = self.connection.lock().await;
+        if let Some(connection) = current.as_ref() {
+            if !connection.closed.load(Ordering::SeqCst) {
+                return Ok(connection.clone());
+            }
+        }
+
+        let config = self.max_message_size.map(|max| WebSocketConfig {
+            max_message_size: Some(max),
+            max_frame_size: Some(max),
+            ..Default::default()
+        });
+        let (stream, _) =
+            tokio_tungstenite::connect_async_with_config(self.url.as_str(), config, false)
+                .await
+                .map_err(Box::new)?;
+        let (mut sink, mut source) = stream.split();
+        let (outgoing, mut queue) = mpsc::unbounded_channel::<Message>();
+        let connection = WsConnection {
+            outgoing,
+            pending: Pending::default(),
+            closed: Arc::new(AtomicBool::new(false)),
+        };
+
+        let closed = connection.closed.clone();
+        tokio::spawn(async move {
+            while let Some(message) = queue.recv().await {
+                if sink.send(message).await.is_err() {
+                    closed.store(true, Ordering::SeqCst);
+                    break;
+                }
+            }
+        });
+
+        let (pending, closed) = (connection.pending.clone(), connection.closed.clone());
+        tokio::spawn(async move {
+            while let Some(Ok(message)) = source.next().await {
+                let body = match message {
+                    Message::Text(text) => text.into_bytes(),
+                    Message::Binary(bytes) => bytes,
+                    Message::Close(_) => break,
+                    _ => continue,
+                };
+                // event notifications and unknown ids have no one waiting
+                if let Some(id) = response_id(&body) {
+                    if let Some(waiting) = pending.lock().unwrap().remove(&id) {
+                        let _ = waiting.send(body);
+                    }
+                }
+            }
+            // mark closed before failing the waiting requests, so none is
+            // registered on this connection afterwards
+            closed.store(true, Ordering::SeqCst);
+            pending.lock().unwrap().clear();
+        });
+
+        *current = Some(connection.clone());
+        Ok(connection)
+    }
+}
+
+#[async_trait]
+impl RpcClient for WsRpcClient {
+    async fn send(&self, request: &RpcRequest) -> Result<Vec<u8>, RpcClientError> {
+        let connection = self.connection().await?;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut body = serde_json::to_value(request)?;
+        body["id"] = id.into();
+
+        let (sender, response) = oneshot::channel();
+        connection.pending.lock().unwrap().insert(id, sender);
+        if connection.closed.load(Ordering::SeqCst)
+            || connection
+                .outgoing
+                .send(Message::Text(body.to_string()))
+                .is_err()
+        {
+            connection.pending.lock().unwrap().remove(&id);
+            return Err(RpcClientError::ConnectionClosed);
+        }
+
+        let body = response
+            .await
+            .map_err(|_| RpcClientError::ConnectionClosed)?;
+        // ids on the connection are its own, the caller expects its id back
+        let mut response: serde_json::Value = serde_json::from_slice(&body)?;
+        response["id"] = request.id.into();
+        Ok(serde_json::to_vec(&response)?)
+    }
+
+    fn with_max_response_size(&self, max: Option<u64>) -> Option<Arc<dyn RpcClient>> {
+        Some(Arc::new(Self {
+            max_message_size: max.map(|max| usize::try_from(max).unwrap_or(usize::MAX)),
+            ..Self::new(&self.url)
+        }))
+    }
+}
+
+fn response_id(body: &[u8]) -> Option<u32> {
+    #[derive(serde::Deserialize)]
+    struct Envelope {
+        id: serde_json::Value,
+    }
+    let envelope: Envelope = serde_json::from_slice(body).ok()?;
+    envelope.id.as_u64().and_then(|id| u32::try_from(id).ok())
+}
@@ -0,0 +1,180 @@
+use std::ops::Not;
+
+/// A variable of a [Formula], or its negation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Lit {
+    var: usize,
+    positive: bool,
+}
+
+impl Lit {
+    /// The variable `var` being true
+    pub fn pos(var: usize) -> Self {
+        Self {
+            var,
+            positive: true,
+        }
+    }
+
+    /// The variable `var` being false
+    pub fn neg(var: usize) -> Self {
+        Self {
+            var,
+            positive: false,
+        }
+    }
+
+    pub fn var(&self) -> usize {
+        self.var
+    }
+
+    pub fn is_positive(&self) -> bool {
+        self.positive
+    }
+
+    fn value(&self, assignment: &[Option<bool>]) -> Option<bool> {
+        assignment[self.var].map(|value| value == self.positive)
+    }
+}
+
+impl Not for Lit {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self {
+            var: self.var,
+            positive: !self.positive,
+        }
+    }
+}
+
+/// A boolean formula in conjunctive normal form, solved by DPLL: unit
+/// propagation and chronological backtracking, without clause learning.
+///
+/// That's plenty for dependency graphs, whose clauses are mostly binary
+/// implications that propagation settles on its own.
+#[derive(Debug, Clone, Default)]
+pub struct Formula {
+    vars: usize,
+    clauses: Vec<Vec<Lit>>,
+}
+
+impl Formula {
+    /// Creates an empty formula over `vars` variables, numbered from zero
+    pub fn new(vars: usize) -> Self {
+        Self {
+            vars,
+            clauses: Vec::new(),
+        }
+    }
+
+    /// Requires at least one of `lits` to hold; an empty clause never does
+    pub fn add_clause(&mut self, lits: impl IntoIterator<Item = Lit>) {
+        let clause: Vec<Lit> = lits.into_iter().collect();
+        debug_assert!(clause.iter().all(|lit| lit.var < self.vars));
+        self.clauses.push(clause);
+    }
+
+    /// Returns a value for every variable satisfying all clauses, if any
+    pub fn solve(&self) -> Option<Vec<bool>> {
+        self.solve_with(&[])
+    }
+
+    /// Like [Formula::solve], with `assumptions` holding as well
+    pub fn solve_with(&self, assumptions: &[Lit]) -> Option<Vec<bool>> {
+        let mut assignment = vec![None; self.vars];
+        for lit in assumptions {
+            match lit.value(&assignment) {
+                Some(false) => return None,
+                Some(true) => {}
+                None => assignment[lit.var] = Some(lit.positive),
+            }
+        }
+
+        if !self.search(&mut assignment) {
+            return None;
+        }
+        Some(
+            assignment
+                .into_iter()
+                .map(|value| value.unwrap_or(false))
+                .collect(),
+        )
+    }
+
+    /// Satisfies the clauses and as many of `soft` as possible, giving each
+    /// soft literal priority over those after it: the result is the
+    /// lexicographically greatest choice, not necessarily the largest.
+    ///
+    /// `None` if the clauses alone can't be satisfied.
+    pub fn maximize(&self, soft: &[Lit]) -> Option<Vec<bool>> {
+        let mut kept = Vec::new();
+        self.solve_with(&kept)?;
+        for lit in soft {
+            kept.push(*lit);
+            if self.solve_with(&kept).is_none() {
+                kept.pop();
+            }
+        }
+        self.solve_with(&kept)
+    }
+
+    fn search(&self, assignment: &mut Vec<Option<bool>>) -> bool {
+        if !self.propagate(assignment) {
+            return false;
+        }
+        let Some(var) = assignment.iter().position(Option::is_none) else {
+            return true;
+        };
+
+        for value in [true, false] {
+            let mut branch = assignment.clone();
+            branch[var] = Some(value);
+            if self.search(&mut branch) {
+                *assignment = branch;
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Assigns the last literal of every clause whose others are all false,
+    /// until none is left. `false` on a clause with every literal false.
+    fn propagate(&self, assignment: &mut [Option<bool>]) -> bool {
+        loop {
+            let mut changed = false;
+            for clause in &self.clauses {
+                let mut unassigned = None;
+                let mut open = 0;
+                let mut satisfied = false;
+                for lit in clause {
+                    match lit.value(assignment) {
+                        Some(true) => {
+                            satisfied = true;
+                            break;
+                        }
+                        Some(false) => {}
+                        None => {
+                            open += 1;
+                            unassigned = Some(*lit);
+                        }
+                    }
+                }
+                if satisfied {
+                    continue;
+                }
+                match (open, unassigned) {
+                    (0, _) => return false,
+                    (1, Some(lit)) => {
+                        assignment[lit.var] = Some(lit.positive);
+                        changed = true;
+                    }
+                    _ => {}
+                }
+            }
+            if !changed {
+                return true;
+            }
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use std::process::Stdio;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use thiserror::Error;
+use tokio::process::Command;
+use tokio::sync::Mutex;
+
+use crate::error::ErrorKind;
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum SessionError {
+    #[error("Failed to run login command: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Login command `{command}` exited with {status}: {stderr}")]
+    Failed {
+        command: String,
+        status: String,
+        stderr: String,
+    },
+
+    #[error("Login command printed an invalid header: {0:?}")]
+    InvalidHeader(String),
+}
+
+impl SessionError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            // the login command is part of the settings
+            Self::Io(_) | Self::Failed { .. } | Self::InvalidHeader(_) => ErrorKind::Config,
+        }
+    }
+}
+
+/// Command run before the first request to an endpoint, printing the
+/// headers that authenticate requests to it, one `Name: value` per line.
+///
+/// The command runs through the shell with `GGET_ENDPOINT` set to the
+/// endpoint URL, so one command can serve several gateways. It runs again
+/// when the endpoint answers 401 or 403, e.g. once a session expired.
+pub struct LoginHook {
+    command: String,
+    endpoint: String,
+    headers: Mutex<Option<HeaderMap>>,
+}
+
+impl LoginHook {
+    pub fn new(command: impl Into<String>, endpoint: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+            endpoint: endpoint.into(),
+            headers: Mutex::new(None),
+        }
+    }
+
+    /// Headers of the current session, logging in if there is none yet
+    pub async fn headers(&self) -> Result<HeaderMap, SessionError> {
+        let mut current = self.headers.lock().await;
+        if let Some(headers) = current.as_ref() {
+            return Ok(headers.clone());
+        }
+        let headers = self.login().await?;
+        *current = Some(headers.clone());
+        Ok(headers)
+    }
+
+    /// Logs in again after `rejected` were refused. Requests rejected
+    /// together share a single login.
+    pub async fn refresh(&self, rejected: &HeaderMap) -> Result<HeaderMap, SessionError> {
+        let mut current = self.headers.lock().await;
+        if let Some(headers) = current.as_ref() {
+            if headers != rejected {
+                return Ok(headers.clone());
+            }
+        }
+        let headers = self.login().await?;
+        *current = Some(headers.clone());
+        Ok(headers)
+    }
+
+    async fn login(&self) -> Result<HeaderMap, SessionError> {
+        let output = shell(&self.command)
+            .env("GGET_ENDPOINT", &self.endpoint)
+            .stdin(Stdio::null())
+            .output()
+            .await?;
+        if !output.status.success() {
+            return Err(SessionError::Failed {
+                command: self.command.clone(),
+                status: output.status.to_string(),
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+        parse_headers(&String::from_utf8_lossy(&output.stdout))
+    }
+}
+
+/// Parses `Name: value` lines, skipping blank lines and `#` comments
+pub fn parse_headers(output: &str) -> Result<HeaderMap, SessionError> {
+    let mut headers = HeaderMap::new();
+    for line in output.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = || SessionError::InvalidHeader(line.to_string());
+        let (name, value) = line.split_once(':').ok_or_else(invalid)?;
+        let name = HeaderName::from_bytes(name.trim().as_bytes()).map_err(|_| invalid())?;
+        let value = HeaderValue::from_str(value.trim()).map_err(|_| invalid())?;
+        headers.append(name, value);
+    }
+    Ok(headers)
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+
+use tokio::sync::watch;
+
+/// Coalesces concurrent calls made with the same key.
+///
+/// The first caller for a key runs its future; callers arriving while it is
+/// in flight wait for it and get a clone of its output instead of running
+/// their own. If the first caller is dropped before finishing, the waiting
+/// callers run their futures themselves.
+pub struct SingleFlight<V> {
+    inflight: Mutex<HashMap<String, watch::Receiver<Option<V>>>>,
+}
+
+impl<V> Default for SingleFlight<V> {
+    fn default() -> Self {
+        Self {
+            inflight: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+/// Takes the key out of the in-flight map when the call finishes or is
+/// dropped, so later calls run again
+struct Leader<'a, V> {
+    flight: &'a SingleFlight<V>,
+    key: &'a str,
+}
+
+impl<V> Drop for Leader<'_, V> {
+    fn drop(&mut self) {
+        self.flight.inflight.lock().unwrap().remove(self.key);
+    }
+}
+
+/// How a call joined the flight for its key
+enum Role<V> {
+    Leader(watch::Sender<Option<V>>),
+    Follower(watch::Receiver<Option<V>>),
+}
+
+impl<V: Clone> SingleFlight<V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs `future`, or waits for the call already in flight for `key`.
+    ///
+    /// Outputs that can't be cloned as they are go through `share`, which
+    /// turns the running call's output into the value handed to waiting
+    /// callers, and `join`, which turns that value back into their output.
+    pub async fn run<F>(
+        &self,
+        key: &str,
+        future: F,
+        share: impl FnOnce(&F::Output) -> V,
+        join: impl FnOnce(V) -> F::Output,
+    ) -> F::Output
+    where
+        F: Future,
+    {
+        let role = {
+            let mut inflight = self.inflight.lock().unwrap();
+            match inflight.get(key) {
+                Some(receiver) => Role::Follower(receiver.clone()),
+                None => {
+                    let (sender, receiver) = watch::channel(None);
+                    inflight.insert(key.to_string(), receiver);
+                    Role::Leader(sender)
+                }
+            }
+        };
+
+        match role {
+            Role::Leader(sender) => {
+                let _leader = Leader { flight: self, key };
+                let output = future.await;
+                sender.send_replace(Some(share(&output)));
+                output
+            }
+            Role::Follower(mut receiver) => {
+                let shared = receiver
+                    .wait_for(Option::is_some)
+                    .await
+                    .ok()
+                    .and_then(|value| value.clone());
+                match shared {
+                    Some(value) => join(value),
+                    // the leader was dropped before finishing
+                    None => future.await,
+                }
+            }
+        }
+    }
+
+    /// Number of keys currently in flight
+    pub fn len(&self) -> usize {
+        self.inflight.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
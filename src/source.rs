@@ -0,0 +1,301 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use thiserror::Error;
+use tokio::process::Command;
+
+use crate::error::ErrorKind;
+
+/// Subdirectory of the cache directory holding git checkouts
+pub const GIT_CHECKOUTS_DIR: &str = "git";
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum SourceError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid git source `{spec}`: {reason}")]
+    InvalidSpec { spec: String, reason: String },
+
+    #[error("`git {command}` failed: {stderr}")]
+    Git { command: String, stderr: String },
+
+    #[error("{repository} has no directory {subdir}")]
+    MissingSubdir { repository: String, subdir: String },
+}
+
+impl SourceError {
+    /// Stable, machine-readable identifier of the error kind
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Io(_) => "io",
+            Self::InvalidSpec { .. } => "invalid_source",
+            Self::Git { .. } => "git",
+            Self::MissingSubdir { .. } => "missing_subdir",
+        }
+    }
+
+    /// Broad category of the error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(_) => ErrorKind::Internal,
+            Self::InvalidSpec { .. } => ErrorKind::Config,
+            // mostly an unreachable remote or an unknown revision
+            Self::Git { .. } => ErrorKind::Network,
+            Self::MissingSubdir { .. } => ErrorKind::NotFound,
+        }
+    }
+}
+
+/// Where a package comes from instead of the chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PackageSource {
+    /// A local directory, e.g. from a gno.mod `replace` directive
+    Local(PathBuf),
+    /// A directory of a git repository at a pinned revision
+    Git(GitSource),
+}
+
+impl fmt::Display for PackageSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Local(dir) => write!(f, "{}", dir.display()),
+            Self::Git(git) => write!(f, "{}", git),
+        }
+    }
+}
+
+/// A package kept in a git repository, written `URL@REV` or
+/// `URL@REV#SUBDIR` when the package isn't at the root of the repository
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitSource {
+    /// Anything `git clone` accepts
+    pub url: String,
+    /// Commit, tag or branch to check out
+    pub rev: String,
+    /// Directory of the package inside the repository, its root if `None`
+    pub subdir: Option<String>,
+}
+
+impl fmt::Display for GitSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.url, self.rev)?;
+        if let Some(subdir) = &self.subdir {
+            write!(f, "#{}", subdir)?;
+        }
+        Ok(())
+    }
+}
+
+impl GitSource {
+    /// Parses `URL@REV[#SUBDIR]`. The revision follows the last `@`, so
+    /// scp-like URLs such as `git@github.com:me/foo.git` work.
+    pub fn parse(spec: &str) -> Result<Self, SourceError> {
+        let (location, subdir) = match spec.split_once('#') {
+            Some((location, subdir)) => (location, Some(subdir)),
+            None => (spec, None),
+        };
+        let (url, rev) = location.rsplit_once('@').unwrap_or((location, ""));
+        Self::new(url, rev, subdir)
+    }
+
+    /// Checks and assembles a source from its parts
+    pub fn new(url: &str, rev: &str, subdir: Option<&str>) -> Result<Self, SourceError> {
+        let subdir = subdir.map(|subdir| subdir.trim_matches('/'));
+        let invalid = |reason: &str| {
+            let mut spec = format!("{}@{}", url, rev);
+            if let Some(subdir) = subdir {
+                spec = format!("{}#{}", spec, subdir);
+            }
+            SourceError::InvalidSpec {
+                spec,
+                reason: reason.to_string(),
+            }
+        };
+        if url.is_empty() {
+            return Err(invalid("missing repository URL"));
+        }
+        // git would take them for options, such as `--upload-pack=...`
+        if url.starts_with('-') || rev.starts_with('-') {
+            return Err(invalid("the URL and revision must not start with `-`"));
+        }
+        // git refuses `:` in revision names, so it's part of an scp-like
+        // URL given without a revision
+        if rev.is_empty() || rev.contains(':') {
+            return Err(invalid("expected URL@REV, a revision to pin"));
+        }
+        if subdir.is_some_and(|subdir| subdir.split('/').any(|part| part == "..")) {
+            return Err(invalid("the directory must stay inside the repository"));
+        }
+        Ok(Self {
+            url: url.to_string(),
+            rev: rev.to_string(),
+            subdir: subdir.filter(|s| !s.is_empty()).map(str::to_string),
+        })
+    }
+
+    /// Directory under `checkouts` the repository is checked out to at
+    /// `commit`, one per URL and commit
+    pub fn checkout_dir(&self, checkouts: &Path, commit: &str) -> PathBuf {
+        let hash = blake3::hash(format!("{}\n{}", self.url, commit).as_bytes());
+        checkouts.join(&hash.to_hex()[..16])
+    }
+
+    /// Checks the repository out at the revision under `checkouts`, unless
+    /// an earlier run checked out the commit it names, and returns the
+    /// package directory in it.
+    ///
+    /// Checkouts are kept per commit, so a branch or tag is resolved
+    /// again on every run and fetched anew once it moves. The clone is
+    /// made next to its final place and renamed into it once checked
+    /// out, so an interrupted clone is never used.
+    pub async fn checkout(&self, checkouts: &Path) -> Result<PathBuf, SourceError> {
+        let commit = self.remote_commit().await?;
+        if let Some(commit) = &commit {
+            let dir = self.checkout_dir(checkouts, commit);
+            if dir.is_dir() {
+                return self.package_dir(dir);
+            }
+        }
+
+        tokio::fs::create_dir_all(checkouts).await?;
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let staging = checkouts.join(format!(".tmp-{}-{}", std::process::id(), nanos));
+        let result = self.clone_into(&staging, checkouts, commit).await;
+        let _ = tokio::fs::remove_dir_all(&staging).await;
+        self.package_dir(result?)
+    }
+
+    /// The commit the revision names on the remote, without cloning it.
+    ///
+    /// `None` for revisions only a clone can resolve, such as abbreviated
+    /// commit hashes.
+    async fn remote_commit(&self) -> Result<Option<String>, SourceError> {
+        let is_hex = self.rev.chars().all(|c| c.is_ascii_hexdigit());
+        if is_hex && matches!(self.rev.len(), 40 | 64) {
+            return Ok(Some(self.rev.to_ascii_lowercase()));
+        }
+        let listed = git(&["ls-remote", "--", &self.url, &self.rev]).await?;
+        let refs: Vec<(&str, &str)> = listed
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .collect();
+        // the order `git rev-parse` looks names up in, annotated tags
+        // peeled to their commit
+        let wanted = [
+            self.rev.clone(),
+            format!("refs/tags/{}^{{}}", self.rev),
+            format!("refs/tags/{}", self.rev),
+            format!("refs/heads/{}", self.rev),
+        ];
+        Ok(wanted.iter().find_map(|name| {
+            refs.iter()
+                .find(|(_, reference)| reference == name)
+                .map(|(commit, _)| commit.to_string())
+        }))
+    }
+
+    /// Clones the repository into `staging`, then moves it into its
+    /// checkout directory under `checkouts` once checked out at the
+    /// revision's commit, unless one is already there
+    async fn clone_into(
+        &self,
+        staging: &Path,
+        checkouts: &Path,
+        commit: Option<String>,
+    ) -> Result<PathBuf, SourceError> {
+        let staging_str = staging.to_string_lossy();
+        git(&[
+            "clone",
+            "--quiet",
+            "--no-checkout",
+            "--",
+            &self.url,
+            &staging_str,
+        ])
+        .await?;
+        let commit = match commit {
+            Some(commit) => commit,
+            None => {
+                let rev = format!("{}^{{commit}}", self.rev);
+                git(&["-C", &staging_str, "rev-parse", "--verify", &rev])
+                    .await?
+                    .trim()
+                    .to_string()
+            }
+        };
+        let dir = self.checkout_dir(checkouts, &commit);
+        if dir.is_dir() {
+            return Ok(dir);
+        }
+        git(&[
+            "-C",
+            &staging_str,
+            "-c",
+            "advice.detachedHead=false",
+            "checkout",
+            "--quiet",
+            "--detach",
+            &commit,
+        ])
+        .await?;
+        match tokio::fs::rename(staging, &dir).await {
+            // another process may have won the race
+            Err(_) if dir.is_dir() => Ok(dir),
+            other => other.map(|()| dir).map_err(SourceError::from),
+        }
+    }
+
+    /// The package's directory in the checkout `dir`
+    fn package_dir(&self, dir: PathBuf) -> Result<PathBuf, SourceError> {
+        match &self.subdir {
+            Some(subdir) => {
+                let package = dir.join(subdir);
+                if !package.is_dir() {
+                    return Err(SourceError::MissingSubdir {
+                        repository: format!("{}@{}", self.url, self.rev),
+                        subdir: subdir.clone(),
+                    });
+                }
+                Ok(package)
+            }
+            None => Ok(dir),
+        }
+    }
+}
+
+/// Parses `PKG=URL@REV[#SUBDIR]`, a package and the git source it's
+/// fetched from
+pub fn parse_git_source(s: &str) -> Result<(String, GitSource), String> {
+    let (pkg_path, spec) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected PKG=URL@REV[#SUBDIR], got `{}`", s))?;
+    if pkg_path.is_empty() {
+        return Err("missing package path".to_string());
+    }
+    let source = GitSource::parse(spec).map_err(|e| e.to_string())?;
+    Ok((pkg_path.to_string(), source))
+}
+
+/// Runs git without prompting for credentials, returning what it printed
+async fn git(args: &[&str]) -> Result<String, SourceError> {
+    let output = Command::new("git")
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .output()
+        .await?;
+    if output.status.success() {
+        return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+    }
+    Err(SourceError::Git {
+        command: args.join(" "),
+        stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+    })
+}
@@ -0,0 +1,505 @@
+//! Scripted fake chain to test against gget without network access.
+//!
+//! [FakeChain] serves a package universe over the tm2 JSON-RPC API gget
+//! talks to: `abci_query` on `vm/qfile`, `vm/qpaths`, `vm/qrender`,
+//! `vm/qeval` and `vm/qfuncs`, `status` and `abci_info`.
+//! Packages can change from one height to the next, and failures and
+//! latency can be scripted, so the whole download, resolve, update and
+//! verify pipeline runs without network access.
+//!
+//! Only built with the `test-util` feature:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! gget = { version = "0.1", features = ["test-util"] }
+//! ```
+//!
+//! ```no_run
+//! # async fn example() {
+//! use gget::fetch::PackageManager;
+//! use gget::testing::FakeChain;
+//!
+//! let chain = FakeChain::new().with_package("gno.land/p/demo/avl", &[("avl.gno", "package avl\n")]);
+//! let cache = std::env::temp_dir().join("gget-example-cache");
+//! let pm = PackageManager::new(Some(chain.spawn()), cache);
+//! pm.download_package("gno.land/p/demo/avl", "avl".as_ref()).await.unwrap();
+//! # }
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::{json, Value};
+use warp::http::StatusCode;
+use warp::Filter;
+
+use crate::parallel::{ParallelDownloadOptions, RetryConfig};
+
+/// Files of a package, by name
+pub type Files = BTreeMap<String, String>;
+
+/// How a scripted request fails
+#[derive(Debug, Clone)]
+pub enum Failure {
+    /// Answers with this HTTP status and an empty body
+    Status(u16),
+    /// Answers with an error in the ABCI response, as the node does
+    Rpc(String),
+    /// Answers with a body that isn't JSON
+    Malformed,
+}
+
+/// A query the chain received
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+    /// JSON-RPC request id
+    pub id: u64,
+    pub method: String,
+    /// ABCI path, e.g. `vm/qfile`; empty for other methods
+    pub path: String,
+    /// Decoded query data, e.g. a package or file path
+    pub data: String,
+    pub height: Option<u64>,
+}
+
+struct Scripted {
+    /// Only queries whose data starts with this fail, any if `None`
+    target: Option<String>,
+    failure: Failure,
+    remaining: usize,
+}
+
+#[derive(Default)]
+struct State {
+    height: u64,
+    /// Reported by `status`, if set
+    chain_id: Option<String>,
+    /// Reported by `status`
+    catching_up: bool,
+    /// Versions of each package by the height they were published at;
+    /// `None` once removed
+    packages: BTreeMap<String, BTreeMap<u64, Option<Files>>>,
+    /// What realms render, by `realm:path`
+    renders: BTreeMap<String, String>,
+    /// Results of expressions, by `realm.expr`
+    evals: BTreeMap<String, String>,
+    /// `vm/qfuncs` answers, by package
+    funcs: BTreeMap<String, Value>,
+    failures: VecDeque<Scripted>,
+    latency: Duration,
+    queries: Vec<Query>,
+}
+
+impl State {
+    fn package_at(&self, path: &str, height: u64) -> Option<&Files> {
+        self.packages
+            .get(path)?
+            .range(..=height)
+            .next_back()
+            .and_then(|(_, files)| files.as_ref())
+    }
+
+    fn current(&self, path: &str) -> Files {
+        self.package_at(path, self.height)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn take_failure(&mut self, data: &str) -> Option<Failure> {
+        let i = self.failures.iter().position(|scripted| {
+            scripted
+                .target
+                .as_deref()
+                .is_none_or(|target| data.starts_with(target))
+        })?;
+        let scripted = &mut self.failures[i];
+        let failure = scripted.failure.clone();
+        scripted.remaining -= 1;
+        if scripted.remaining == 0 {
+            self.failures.remove(i);
+        }
+        Some(failure)
+    }
+}
+
+/// In-process fake of a gno.land node. Clones share the same chain.
+#[derive(Clone)]
+pub struct FakeChain {
+    state: Arc<Mutex<State>>,
+}
+
+impl Default for FakeChain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FakeChain {
+    /// An empty chain at height 1
+    pub fn new() -> Self {
+        let state = State {
+            height: 1,
+            ..Default::default()
+        };
+        Self {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// A chain where `gno.land/r/demo/app` imports `gno.land/p/demo/avl`,
+    /// which has two files
+    pub fn demo() -> Self {
+        Self::new()
+            .with_package(
+                "gno.land/p/demo/avl",
+                &[("avl.gno", "package avl\n"), ("node.gno", "package avl\n")],
+            )
+            .with_package(
+                "gno.land/r/demo/app",
+                &[("app.gno", "package app\n\nimport \"gno.land/p/demo/avl\"\n")],
+            )
+    }
+
+    /// Publishes `path` with `files` at the current height
+    pub fn with_package(self, path: &str, files: &[(&str, &str)]) -> Self {
+        self.publish(path, files);
+        self
+    }
+
+    /// Publishes `path` with exactly `files` at the current height
+    pub fn publish(&self, path: &str, files: &[(&str, &str)]) {
+        let files = files
+            .iter()
+            .map(|(name, content)| (name.to_string(), content.to_string()))
+            .collect();
+        let mut state = self.state.lock().unwrap();
+        let height = state.height;
+        state
+            .packages
+            .entry(path.to_string())
+            .or_default()
+            .insert(height, Some(files));
+    }
+
+    /// Makes `status` report `chain_id` as the chain the node runs
+    pub fn with_chain_id(self, chain_id: &str) -> Self {
+        self.state.lock().unwrap().chain_id = Some(chain_id.to_string());
+        self
+    }
+
+    /// Makes `status` report the node as still syncing
+    pub fn with_catching_up(self) -> Self {
+        self.state.lock().unwrap().catching_up = true;
+        self
+    }
+
+    /// Makes `realm` render `output` for `path`
+    pub fn with_render(self, realm: &str, path: &str, output: &str) -> Self {
+        self.state
+            .lock()
+            .unwrap()
+            .renders
+            .insert(format!("{}:{}", realm, path), output.to_string());
+        self
+    }
+
+    /// Makes `expr` evaluate to `result` in `realm`
+    pub fn with_eval(self, realm: &str, expr: &str, result: &str) -> Self {
+        self.state
+            .lock()
+            .unwrap()
+            .evals
+            .insert(format!("{}.{}", realm, expr), result.to_string());
+        self
+    }
+
+    /// Makes `vm/qfuncs` describe the functions of `path` with `funcs`
+    pub fn with_funcs(self, path: &str, funcs: Value) -> Self {
+        self.state
+            .lock()
+            .unwrap()
+            .funcs
+            .insert(path.to_string(), funcs);
+        self
+    }
+
+    /// Adds or replaces one file of `path` at the current height
+    pub fn set_file(&self, path: &str, file: &str, content: &str) {
+        let mut state = self.state.lock().unwrap();
+        let height = state.height;
+        let mut files = state.current(path);
+        files.insert(file.to_string(), content.to_string());
+        state
+            .packages
+            .entry(path.to_string())
+            .or_default()
+            .insert(height, Some(files));
+    }
+
+    /// Removes one file of `path` at the current height
+    pub fn remove_file(&self, path: &str, file: &str) {
+        let mut state = self.state.lock().unwrap();
+        let height = state.height;
+        let mut files = state.current(path);
+        files.remove(file);
+        state
+            .packages
+            .entry(path.to_string())
+            .or_default()
+            .insert(height, Some(files));
+    }
+
+    /// Removes `path` at the current height
+    pub fn remove_package(&self, path: &str) {
+        let mut state = self.state.lock().unwrap();
+        let height = state.height;
+        state
+            .packages
+            .entry(path.to_string())
+            .or_default()
+            .insert(height, None);
+    }
+
+    /// Moves to the next height, returning it. Changes made afterwards
+    /// aren't visible to queries at earlier heights.
+    pub fn advance(&self) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        state.height += 1;
+        state.height
+    }
+
+    pub fn height(&self) -> u64 {
+        self.state.lock().unwrap().height
+    }
+
+    /// Makes the next `times` queries fail
+    pub fn fail_next(&self, times: usize, failure: Failure) {
+        self.script(None, times, failure);
+    }
+
+    /// Makes the next `times` queries for `path` or its files fail
+    pub fn fail_package(&self, path: &str, times: usize, failure: Failure) {
+        self.script(Some(path.to_string()), times, failure);
+    }
+
+    fn script(&self, target: Option<String>, times: usize, failure: Failure) {
+        if times == 0 {
+            return;
+        }
+        self.state.lock().unwrap().failures.push_back(Scripted {
+            target,
+            failure,
+            remaining: times,
+        });
+    }
+
+    /// Delays every response by `latency`
+    pub fn set_latency(&self, latency: Duration) {
+        self.state.lock().unwrap().latency = latency;
+    }
+
+    /// Queries received so far, in order
+    pub fn queries(&self) -> Vec<Query> {
+        self.state.lock().unwrap().queries.clone()
+    }
+
+    /// Number of `vm/qfile` queries received so far
+    pub fn file_queries(&self) -> usize {
+        self.queries()
+            .iter()
+            .filter(|query| query.path == "vm/qfile")
+            .count()
+    }
+
+    /// Starts serving the chain on an ephemeral port, returning its URL
+    pub fn spawn(&self) -> String {
+        let chain = self.clone();
+        let route =
+            warp::post()
+                .and(warp::body::bytes())
+                .then(move |body: warp::hyper::body::Bytes| {
+                    let chain = chain.clone();
+                    async move { chain.handle(&body).await }
+                });
+        let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        format!("http://{}", addr)
+    }
+
+    async fn handle(&self, body: &[u8]) -> warp::reply::Response {
+        use warp::Reply;
+
+        let Ok(request) = serde_json::from_slice::<Value>(body) else {
+            return StatusCode::BAD_REQUEST.into_response();
+        };
+        let id = request["id"].clone();
+        let query = Query {
+            id: id.as_u64().unwrap_or_default(),
+            method: request["method"].as_str().unwrap_or_default().to_string(),
+            path: request["params"]["path"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            data: request["params"]["data"]
+                .as_str()
+                .and_then(|data| general_purpose::STANDARD.decode(data).ok())
+                .map(|data| String::from_utf8_lossy(&data).into_owned())
+                .unwrap_or_default(),
+            height: request["params"]["height"]
+                .as_str()
+                .and_then(|height| height.parse().ok()),
+        };
+
+        let (latency, failure) = {
+            let mut state = self.state.lock().unwrap();
+            state.queries.push(query.clone());
+            (state.latency, state.take_failure(&query.data))
+        };
+        if !latency.is_zero() {
+            tokio::time::sleep(latency).await;
+        }
+
+        match failure {
+            Some(Failure::Status(status)) => {
+                return StatusCode::from_u16(status)
+                    .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+                    .into_response()
+            }
+            Some(Failure::Malformed) => return "not json".into_response(),
+            Some(Failure::Rpc(message)) => return abci_reply(id, Err(message), 0).into_response(),
+            None => {}
+        }
+
+        let state = self.state.lock().unwrap();
+        if query.method == "status" {
+            let mut result = json!({ "sync_info": {
+                "latest_block_height": state.height.to_string(),
+                "latest_block_time": "2024-01-01T00:00:00Z",
+                "catching_up": state.catching_up,
+            } });
+            if let Some(chain_id) = &state.chain_id {
+                result["node_info"] = json!({ "network": chain_id, "version": "v0.1.0" });
+            }
+            return warp::reply::json(&json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": result,
+            }))
+            .into_response();
+        }
+
+        if query.method == "abci_info" {
+            return warp::reply::json(&json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": { "response": {
+                    "AppVersion": "fake",
+                    "LastBlockHeight": state.height.to_string(),
+                } },
+            }))
+            .into_response();
+        }
+
+        let height = query.height.unwrap_or(state.height);
+        let result = match query.path.as_str() {
+            "vm/qfile" => qfile(&state, &query.data, height),
+            "vm/qpaths" => Ok(state
+                .packages
+                .keys()
+                .filter(|path| path.starts_with(&query.data))
+                .filter(|path| state.package_at(path, height).is_some())
+                .cloned()
+                .collect::<Vec<_>>()
+                .join("\n")),
+            "vm/qrender" => state
+                .renders
+                .get(&query.data)
+                .cloned()
+                .ok_or_else(|| format!("realm not found: {}", query.data)),
+            "vm/qeval" => state
+                .evals
+                .get(&query.data)
+                .cloned()
+                .ok_or_else(|| format!("cannot evaluate {}", query.data)),
+            "vm/qfuncs" => state
+                .funcs
+                .get(&query.data)
+                .map(|funcs| funcs.to_string())
+                .ok_or_else(|| format!("package not found: {}", query.data)),
+            other => Err(format!("unknown query path {}", other)),
+        };
+        abci_reply(id, result, height).into_response()
+    }
+}
+
+/// Lists a directory of a package like the node does, subdirectories
+/// ending with `/`, or returns the content of a file
+fn qfile(state: &State, target: &str, height: u64) -> Result<String, String> {
+    let not_found = || format!("package not found: {}", target);
+    let (files, rel) = locate(state, target, height).ok_or_else(not_found)?;
+    if let Some(content) = files.get(rel) {
+        return Ok(content.clone());
+    }
+
+    let prefix = if rel.is_empty() {
+        String::new()
+    } else {
+        format!("{}/", rel)
+    };
+    let entries: BTreeSet<String> = files
+        .keys()
+        .filter_map(|name| name.strip_prefix(&prefix))
+        .map(|rest| match rest.split_once('/') {
+            Some((dir, _)) => format!("{}/", dir),
+            None => rest.to_string(),
+        })
+        .collect();
+    if entries.is_empty() {
+        return Err(not_found());
+    }
+    Ok(entries.into_iter().collect::<Vec<_>>().join("\n"))
+}
+
+/// The files of the package `target` is in, the innermost one, and the
+/// path of `target` inside it
+fn locate<'a>(state: &'a State, target: &'a str, height: u64) -> Option<(&'a Files, &'a str)> {
+    let mut package = target;
+    loop {
+        if let Some(files) = state.package_at(package, height) {
+            return Some((files, target[package.len()..].trim_start_matches('/')));
+        }
+        package = package.rsplit_once('/')?.0;
+    }
+}
+
+fn abci_reply(id: Value, result: Result<String, String>, height: u64) -> warp::reply::Json {
+    let (error, data) = match result {
+        Ok(data) => (Value::Null, general_purpose::STANDARD.encode(data)),
+        Err(message) => (json!({ "msg": message }), String::new()),
+    };
+    warp::reply::json(&json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "result": { "response": { "ResponseBase": {
+            "Error": error,
+            "Data": data,
+            "Log": "",
+        }, "Height": height.to_string() } },
+    }))
+}
+
+/// Options for downloading from a [FakeChain]: no progress output, and
+/// retries backing off for milliseconds rather than seconds
+pub fn download_options() -> ParallelDownloadOptions {
+    ParallelDownloadOptions {
+        show_progress: false,
+        retry_config: RetryConfig {
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
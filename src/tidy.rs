@@ -0,0 +1,387 @@
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::dependency::DependencyClosure;
+use crate::error::ErrorKind;
+use crate::fetch::{PackageManager, PackageManagerError};
+use crate::gnomod::{add_requires, module_imports, remove_requires, GnoMod, GnoModError};
+use crate::install::write_durably;
+use crate::lock::{LockError, Lockfile, LOCKFILE_NAME};
+use crate::parallel::ParallelDownloadOptions;
+use crate::paths::{check_relative, safe_join};
+use crate::report::{Report, Table};
+use crate::trash::TrashError;
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum TidyError {
+    #[error("gno.mod error: {0}")]
+    GnoMod(#[from] GnoModError),
+
+    #[error("Package manager error: {0}")]
+    PackageManager(#[from] PackageManagerError),
+
+    #[error("Lockfile error: {0}")]
+    Lock(#[from] LockError),
+
+    #[error("Trash error: {0}")]
+    Trash(#[from] TrashError),
+
+    #[error("Failed to download {}", .0.join(", "))]
+    Download(Vec<String>),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl TidyError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::GnoMod(e) => e.kind(),
+            Self::PackageManager(e) => e.kind(),
+            Self::Lock(e) => e.kind(),
+            Self::Trash(e) => e.kind(),
+            // why each package failed was reported with the download
+            Self::Download(_) => ErrorKind::Network,
+            Self::Io(_) => ErrorKind::Internal,
+        }
+    }
+}
+
+/// What `gget tidy` does, or would do, to a package
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TidyAction {
+    /// Imported, directly or not, but not required by gno.mod
+    Require,
+    /// Required by gno.mod, but nothing imports it
+    Unrequire,
+    /// Imported, but missing or modified under the target directory
+    Download,
+    /// Vendored, but nothing imports it
+    Prune,
+}
+
+impl TidyAction {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Require => "require",
+            Self::Unrequire => "unrequire",
+            Self::Download => "download",
+            Self::Prune => "prune",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TidyChange {
+    pub action: TidyAction,
+    pub package: String,
+}
+
+/// Options for [tidy]
+#[derive(Debug, Clone, Default)]
+pub struct TidyOptions {
+    /// Only report what is out of sync, changing nothing
+    pub check: bool,
+    /// How missing packages are downloaded
+    pub download: ParallelDownloadOptions,
+}
+
+/// Result of `gget tidy`: how a module's gno.mod, lockfile and vendored
+/// packages differ from what its sources import
+#[derive(Debug, Clone, Serialize)]
+pub struct TidyReport {
+    pub gnomod: PathBuf,
+    pub target_dir: PathBuf,
+    /// By action, then by package
+    pub changes: Vec<TidyChange>,
+    /// Whether gno.mod has to be rewritten, which includes dropping the
+    /// `// indirect` marker of requirements imported directly
+    pub gnomod_stale: bool,
+    /// Whether gget.lock doesn't record the packages imported
+    pub lockfile_stale: bool,
+    /// Whether the changes were made, rather than only checked
+    pub applied: bool,
+}
+
+impl TidyReport {
+    /// Returns true if the three agree with the sources
+    pub fn is_tidy(&self) -> bool {
+        self.changes.is_empty() && !self.gnomod_stale && !self.lockfile_stale
+    }
+
+    /// Packages the report has `action` for
+    pub fn packages(&self, action: TidyAction) -> impl Iterator<Item = &str> {
+        self.changes
+            .iter()
+            .filter(move |change| change.action == action)
+            .map(|change| change.package.as_str())
+    }
+}
+
+impl Report for TidyReport {
+    fn table(&self) -> Table {
+        let mut table = Table::new(
+            &format!("Tidy of {}", self.gnomod.display()),
+            &["action", "package"],
+        );
+        for change in &self.changes {
+            table.push_row([change.action.as_str().to_string(), change.package.clone()]);
+        }
+        table
+    }
+
+    fn human(&self) -> String {
+        let lockfile = self.target_dir.join(LOCKFILE_NAME);
+        if self.is_tidy() {
+            return format!(
+                "{}, {} and {} are tidy\n",
+                self.gnomod.display(),
+                lockfile.display(),
+                self.target_dir.display()
+            );
+        }
+
+        let mut out = String::new();
+        for change in &self.changes {
+            let _ = match change.action {
+                TidyAction::Require => {
+                    writeln!(out, "  require {}", change.package)
+                }
+                TidyAction::Unrequire => {
+                    writeln!(out, "  unrequire {}, nothing imports it", change.package)
+                }
+                TidyAction::Download => writeln!(out, "  download {}", change.package),
+                TidyAction::Prune => {
+                    writeln!(out, "  prune {}, nothing imports it", change.package)
+                }
+            };
+        }
+        if self.gnomod_stale && self.changes.is_empty() {
+            let _ = writeln!(out, "  rewrite {}", self.gnomod.display());
+        }
+        if self.lockfile_stale {
+            let _ = writeln!(out, "  rewrite {}", lockfile.display());
+        }
+        if self.applied {
+            out.insert_str(0, "Tidied:\n");
+        } else {
+            out.insert_str(0, "Out of sync, `gget tidy` would:\n");
+        }
+        out
+    }
+}
+
+/// Brings the gno.mod at `gnomod_path`, the packages vendored under
+/// `target_dir` and their lockfile in line with what the module imports.
+///
+/// The module's sources, skipping `target_dir`, are parsed for imports,
+/// which are resolved through `pm` into the packages they need. gno.mod
+/// then requires the imports and, marked `// indirect`, the rest, and
+/// nothing else. Needed packages that are missing or modified are
+/// downloaded, locked packages nothing needs are removed, saving their
+/// files to the manager's trash if it has one, and the lockfile is
+/// rewritten to match. Packages gno.mod replaces are read from their
+/// directories. With [TidyOptions::check], only the report is made.
+pub async fn tidy(
+    pm: &PackageManager,
+    gnomod_path: &Path,
+    target_dir: &Path,
+    options: TidyOptions,
+) -> Result<TidyReport, TidyError> {
+    let content = fs::read_to_string(gnomod_path)?;
+    let gnomod = GnoMod::parse(&content)?;
+    let module_dir = match gnomod_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let pm = &pm
+        .clone()
+        .with_replacements(gnomod.replacements(module_dir));
+
+    // the module's own packages are never fetched
+    let own = |import: &str| {
+        gnomod.module.as_deref().is_some_and(|module| {
+            import == module
+                || import
+                    .strip_prefix(module)
+                    .is_some_and(|rest| rest.starts_with('/'))
+        })
+    };
+    let imports: Vec<String> = module_imports(module_dir, target_dir)?
+        .into_iter()
+        .filter(|import| !own(import))
+        .collect();
+    let roots: Vec<&str> = imports.iter().map(String::as_str).collect();
+    let closure = if roots.is_empty() {
+        DependencyClosure::default()
+    } else {
+        pm.resolve_dependencies(&roots).await?
+    };
+    let needed: BTreeSet<&str> = roots
+        .iter()
+        .copied()
+        .chain(closure.packages.keys().map(String::as_str))
+        .collect();
+    let indirect: Vec<&str> = needed
+        .iter()
+        .copied()
+        .filter(|pkg| !roots.contains(pkg))
+        .collect();
+
+    let mut changes = Vec::new();
+
+    // gno.mod
+    let unused: Vec<&str> = gnomod
+        .requires
+        .iter()
+        .map(|require| require.path.as_str())
+        .filter(|path| !needed.contains(path))
+        .collect();
+    let (tidied, removed) = remove_requires(&content, &unused)?;
+    let (tidied, added) = add_requires(&tidied, &roots, &indirect)?;
+    changes.extend(added.into_iter().map(|package| TidyChange {
+        action: TidyAction::Require,
+        package,
+    }));
+    changes.extend(removed.into_iter().map(|package| TidyChange {
+        action: TidyAction::Unrequire,
+        package,
+    }));
+
+    // vendored packages and their lockfile
+    let lock_path = target_dir.join(LOCKFILE_NAME);
+    let lock = Lockfile::load_if_exists(&lock_path)?;
+    let layout = pm.layout();
+    for pkg in &needed {
+        let intact = lock
+            .as_ref()
+            .and_then(|lock| lock.get(pkg))
+            .is_some_and(|locked| locked.is_intact(&layout.package_dir(target_dir, pkg)));
+        if !intact {
+            changes.push(TidyChange {
+                action: TidyAction::Download,
+                package: pkg.to_string(),
+            });
+        }
+    }
+    let unneeded: Vec<String> = lock
+        .iter()
+        .flat_map(|lock| lock.packages.keys())
+        .filter(|pkg| !needed.contains(pkg.as_str()))
+        .cloned()
+        .collect();
+    changes.extend(unneeded.iter().map(|package| TidyChange {
+        action: TidyAction::Prune,
+        package: package.clone(),
+    }));
+    let lockfile_stale = match &lock {
+        Some(lock) => {
+            lock.layout != layout
+                || lock
+                    .roots
+                    .iter()
+                    .map(String::as_str)
+                    .collect::<BTreeSet<_>>()
+                    != roots.iter().copied().collect()
+                || changes
+                    .iter()
+                    .any(|change| matches!(change.action, TidyAction::Download | TidyAction::Prune))
+        }
+        None => !roots.is_empty(),
+    };
+    changes.sort_by(|a, b| (a.action, &a.package).cmp(&(b.action, &b.package)));
+
+    let mut report = TidyReport {
+        gnomod: gnomod_path.to_path_buf(),
+        target_dir: target_dir.to_path_buf(),
+        changes,
+        gnomod_stale: tidied != content,
+        lockfile_stale,
+        applied: false,
+    };
+    if options.check || report.is_tidy() {
+        return Ok(report);
+    }
+
+    if report.gnomod_stale {
+        write_durably(gnomod_path, tidied.as_bytes())?;
+    }
+    if let Some(lock) = &lock {
+        for pkg in &unneeded {
+            prune(pm, lock, pkg, target_dir)?;
+        }
+    }
+    if report.lockfile_stale {
+        if roots.is_empty() {
+            // nothing left to lock
+            Lockfile {
+                layout,
+                ..Default::default()
+            }
+            .save(&lock_path)?;
+        } else {
+            let summary = pm
+                .download_roots_with_deps_parallel(&roots, target_dir, options.download)
+                .await?;
+            if !summary.failed.is_empty() {
+                return Err(TidyError::Download(
+                    summary.failed.into_iter().map(|f| f.package).collect(),
+                ));
+            }
+        }
+    }
+    report.applied = true;
+    Ok(report)
+}
+
+/// Removes the files `lock` records for `pkg` under `target_dir`, then
+/// the directories that leaves empty.
+///
+/// Packages nested inside its directory aren't among its files, so they
+/// stay.
+fn prune(
+    pm: &PackageManager,
+    lock: &Lockfile,
+    pkg: &str,
+    target_dir: &Path,
+) -> Result<(), TidyError> {
+    let Some(locked) = lock.get(pkg) else {
+        return Ok(());
+    };
+    // a lockfile built in memory isn't checked like a loaded one
+    check_relative(pkg).map_err(LockError::from)?;
+    let dir = lock.package_dir(target_dir, pkg);
+    let files: Vec<String> = locked.files.keys().cloned().collect();
+    let paths = files
+        .iter()
+        .map(|file| safe_join(&dir, file))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(LockError::from)?;
+    if let Some(trash) = pm.trash() {
+        trash.save_files(pkg, &dir, &files)?;
+    }
+
+    for path in paths {
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+        // stops at the first directory something else is left in
+        for parent in path.ancestors().skip(1) {
+            if parent == target_dir || !parent.starts_with(target_dir) {
+                break;
+            }
+            if fs::remove_dir(parent).is_err() {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tracing::span::{Attributes, Id};
+use tracing::Subscriber;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Span names recorded as phases, in report order
+pub const PHASES: &[&str] = &["resolve", "rpc", "cache", "write", "validate"];
+
+/// Accumulated time for a single phase
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PhaseTiming {
+    /// Time spent inside the phase's spans, excluding nested phases
+    pub busy: Duration,
+    /// Number of spans closed
+    pub count: usize,
+}
+
+/// Per-phase timing collected from tracing spans.
+///
+/// Install [Timings::layer] on a subscriber, then read the totals once the
+/// command is done. Busy time is summed across concurrent tasks, so phases
+/// can add up to more than the wall time of a parallel run.
+#[derive(Debug, Clone, Default)]
+pub struct Timings {
+    phases: Arc<Mutex<BTreeMap<&'static str, PhaseTiming>>>,
+}
+
+impl Timings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a layer feeding this collector
+    pub fn layer(&self) -> TimingsLayer {
+        TimingsLayer {
+            phases: Arc::clone(&self.phases),
+        }
+    }
+
+    /// Returns the timing of a phase, if any of its spans were entered
+    pub fn get(&self, phase: &str) -> Option<PhaseTiming> {
+        self.phases.lock().unwrap().get(phase).copied()
+    }
+
+    /// Builds a printable report against the total wall time of the command
+    pub fn report(&self, total: Duration) -> TimingsReport {
+        let phases = self.phases.lock().unwrap();
+        TimingsReport {
+            total,
+            phases: PHASES
+                .iter()
+                .map(|name| (*name, phases.get(name).copied().unwrap_or_default()))
+                .collect(),
+        }
+    }
+}
+
+/// Snapshot of [Timings] printed at the end of a run
+#[derive(Debug, Clone)]
+pub struct TimingsReport {
+    pub total: Duration,
+    pub phases: Vec<(&'static str, PhaseTiming)>,
+}
+
+impl fmt::Display for TimingsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Timings (total {:.2?}):", self.total)?;
+        for (name, timing) in &self.phases {
+            write!(
+                f,
+                "\n  {:<10} {:>10.2?}  ({} spans)",
+                name, timing.busy, timing.count
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Busy-time bookkeeping stored in each phase span's extensions
+struct Busy {
+    entered: Option<Instant>,
+    /// Time spent in nested phases during the current entry
+    nested: Duration,
+}
+
+/// Tracing layer aggregating the busy time of [PHASES] spans
+pub struct TimingsLayer {
+    phases: Arc<Mutex<BTreeMap<&'static str, PhaseTiming>>>,
+}
+
+fn phase_name(name: &'static str) -> Option<&'static str> {
+    PHASES.iter().find(|p| **p == name).copied()
+}
+
+impl<S> Layer<S> for TimingsLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if phase_name(attrs.metadata().name()).is_none() {
+            return;
+        }
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(Busy {
+                entered: None,
+                nested: Duration::ZERO,
+            });
+        }
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let mut ext = span.extensions_mut();
+        if let Some(busy) = ext.get_mut::<Busy>() {
+            busy.entered = Some(Instant::now());
+            busy.nested = Duration::ZERO;
+        }
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let Some(name) = phase_name(span.name()) else {
+            return;
+        };
+
+        let elapsed = {
+            let mut ext = span.extensions_mut();
+            let Some(busy) = ext.get_mut::<Busy>() else {
+                return;
+            };
+            let Some(entered) = busy.entered.take() else {
+                return;
+            };
+            let elapsed = entered.elapsed();
+            self.phases.lock().unwrap().entry(name).or_default().busy +=
+                elapsed.saturating_sub(busy.nested);
+            elapsed
+        };
+
+        // charge this time to the enclosing phase as nested, so it isn't counted twice
+        for ancestor in span.scope().skip(1) {
+            let mut ext = ancestor.extensions_mut();
+            if let Some(busy) = ext.get_mut::<Busy>() {
+                if busy.entered.is_some() {
+                    busy.nested += elapsed;
+                    break;
+                }
+            }
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else { return };
+        if let Some(name) = phase_name(span.name()) {
+            self.phases.lock().unwrap().entry(name).or_default().count += 1;
+        }
+    }
+}
@@ -0,0 +1,353 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::error::ErrorKind;
+use crate::paths::{safe_join, UnsafePath};
+
+/// Default trash location, relative to the working directory
+pub const TRASH_DIR: &str = ".gget-trash";
+
+/// Default cap on the total size of trashed packages
+pub const DEFAULT_TRASH_LIMIT: u64 = 100 * 1024 * 1024;
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum TrashError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("JSON serialization/deserialization error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("No trashed version of {0}")]
+    NotFound(String),
+
+    #[error("Can't trash {}, it contains the trash directory", .0.display())]
+    ContainsTrash(PathBuf),
+
+    #[error("Refusing to trash a file: {0}")]
+    UnsafePath(#[from] UnsafePath),
+}
+
+impl TrashError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(_) => ErrorKind::Internal,
+            // corrupt trash metadata
+            Self::Json(_) | Self::UnsafePath(_) => ErrorKind::Integrity,
+            Self::NotFound(_) => ErrorKind::NotFound,
+            Self::ContainsTrash(_) => ErrorKind::Config,
+        }
+    }
+}
+
+/// A package version moved to the trash.
+///
+/// Stored as `<name>.json` next to the trashed directory `<name>`, where
+/// `name` is `<pkg>-<timestamp>` with `/` escaped as `%2F`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrashEntry {
+    pub package: String,
+    /// Where the package was before it was trashed
+    pub original: PathBuf,
+    /// Milliseconds since the Unix epoch
+    pub trashed_at: u128,
+    /// Total size of the trashed files, in bytes
+    pub size: u64,
+    /// Files saved before being overwritten in place, relative to
+    /// `original`; `None` when the whole directory was trashed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub files: Option<Vec<String>>,
+    #[serde(skip)]
+    name: String,
+}
+
+/// Size-capped holding area for package versions that an update replaced,
+/// so they can be brought back with `gget restore`.
+///
+/// The oldest entries are pruned once the total size exceeds the limit;
+/// the newest one is always kept.
+#[derive(Debug, Clone)]
+pub struct Trash {
+    dir: PathBuf,
+    limit: u64,
+}
+
+impl Trash {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            limit: DEFAULT_TRASH_LIMIT,
+        }
+    }
+
+    pub fn with_limit(mut self, bytes: u64) -> Self {
+        self.limit = bytes;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Moves `dir`, the current copy of `package`, to the trash and prunes
+    /// old entries. Does nothing if `dir` doesn't exist.
+    pub fn discard(&self, package: &str, dir: &Path) -> Result<Option<TrashEntry>, TrashError> {
+        if !dir.exists() {
+            return Ok(None);
+        }
+
+        let entry = self.new_entry(package, dir, dir_size(dir)?, None)?;
+        if fs::canonicalize(&self.dir)?.starts_with(&entry.original) {
+            return Err(TrashError::ContainsTrash(dir.to_path_buf()));
+        }
+        move_dir(dir, &self.dir.join(&entry.name))?;
+        self.commit(&entry)?;
+        Ok(Some(entry))
+    }
+
+    /// Copies the `files` of `dir` that exist to the trash before they are
+    /// overwritten in place, then prunes old entries. Does nothing if none
+    /// of them exist.
+    pub fn save_files(
+        &self,
+        package: &str,
+        dir: &Path,
+        files: &[String],
+    ) -> Result<Option<TrashEntry>, TrashError> {
+        let mut existing = Vec::new();
+        for file in files {
+            if safe_join(dir, file)?.is_file() {
+                existing.push(file.clone());
+            }
+        }
+        if existing.is_empty() {
+            return Ok(None);
+        }
+
+        let mut size = 0;
+        for file in &existing {
+            size += fs::metadata(dir.join(file))?.len();
+        }
+        let entry = self.new_entry(package, dir, size, Some(existing))?;
+        let saved = self.dir.join(&entry.name);
+        for file in entry.files.iter().flatten() {
+            copy_file(&dir.join(file), &saved.join(file))?;
+        }
+        self.commit(&entry)?;
+        Ok(Some(entry))
+    }
+
+    fn new_entry(
+        &self,
+        package: &str,
+        dir: &Path,
+        size: u64,
+        files: Option<Vec<String>>,
+    ) -> Result<TrashEntry, TrashError> {
+        fs::create_dir_all(&self.dir)?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        // entries within the same millisecond must neither collide nor
+        // sort before an earlier one
+        let after_latest = self.entries()?.last().map_or(0, |e| e.trashed_at + 1);
+        let trashed_at = now.max(after_latest);
+
+        Ok(TrashEntry {
+            package: package.to_string(),
+            original: fs::canonicalize(dir)?,
+            trashed_at,
+            size,
+            files,
+            name: entry_name(package, trashed_at),
+        })
+    }
+
+    /// Records a filled entry, then prunes
+    fn commit(&self, entry: &TrashEntry) -> Result<(), TrashError> {
+        fs::write(
+            self.metadata_path(&entry.name),
+            serde_json::to_string_pretty(entry)?,
+        )?;
+        self.prune()?;
+        Ok(())
+    }
+
+    /// Lists trashed versions, oldest first
+    pub fn entries(&self) -> Result<Vec<TrashEntry>, TrashError> {
+        if !self.dir.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut entries = Vec::new();
+        for file in fs::read_dir(&self.dir)? {
+            let path = file?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            // skip entries pruned or written concurrently, e.g. by parallel downloads
+            let Ok(raw) = fs::read_to_string(&path) else {
+                continue;
+            };
+            let Ok(mut entry) = serde_json::from_str::<TrashEntry>(&raw) else {
+                continue;
+            };
+            entry.name = name.to_string();
+            entries.push(entry);
+        }
+        entries.sort_by(|a, b| a.trashed_at.cmp(&b.trashed_at).then(a.name.cmp(&b.name)));
+        Ok(entries)
+    }
+
+    /// Puts the most recently trashed version of `package` back where it
+    /// was, or at `to` if given. Whatever it replaces is trashed in turn,
+    /// so a restore can itself be undone.
+    pub fn restore(&self, package: &str, to: Option<&Path>) -> Result<TrashEntry, TrashError> {
+        let entry = self
+            .entries()?
+            .into_iter()
+            .rev()
+            .find(|entry| entry.package == package)
+            .ok_or_else(|| TrashError::NotFound(package.to_string()))?;
+        let target = to.unwrap_or(&entry.original);
+
+        // move the entry out first so pruning can't drop it, and put it
+        // back as it was if it can't be restored
+        let staged = self.dir.join(format!("{}.restoring", entry.name));
+        fs::rename(self.dir.join(&entry.name), &staged)?;
+        let restored = fs::remove_file(self.metadata_path(&entry.name))
+            .map_err(TrashError::from)
+            .and_then(|()| self.put_in_place(&entry, &staged, target));
+        if let Err(e) = restored {
+            fs::rename(&staged, self.dir.join(&entry.name))?;
+            fs::write(
+                self.metadata_path(&entry.name),
+                serde_json::to_string_pretty(&entry)?,
+            )?;
+            return Err(e);
+        }
+
+        // saved files were copied, a whole directory was moved
+        if entry.files.is_some() {
+            fs::remove_dir_all(&staged)?;
+        }
+        Ok(entry)
+    }
+
+    /// Moves or copies the files of `entry`, staged in `staged`, to
+    /// `target`, trashing what they replace
+    fn put_in_place(
+        &self,
+        entry: &TrashEntry,
+        staged: &Path,
+        target: &Path,
+    ) -> Result<(), TrashError> {
+        match &entry.files {
+            Some(files) => {
+                self.save_files(&entry.package, target, files)?;
+                for file in files {
+                    copy_file(&staged.join(file), &target.join(file))?;
+                }
+            }
+            None => {
+                self.discard(&entry.package, target)?;
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                move_dir(staged, target)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the oldest entries until the trash fits its size limit
+    pub fn prune(&self) -> Result<Vec<TrashEntry>, TrashError> {
+        let entries = self.entries()?;
+        let mut total: u64 = entries.iter().map(|entry| entry.size).sum();
+        let mut pruned = Vec::new();
+
+        for entry in entries.iter().take(entries.len().saturating_sub(1)) {
+            if total <= self.limit {
+                break;
+            }
+            ignore_missing(fs::remove_file(self.metadata_path(&entry.name)))?;
+            ignore_missing(fs::remove_dir_all(self.dir.join(&entry.name)))?;
+            total -= entry.size;
+            pruned.push(entry.clone());
+        }
+        Ok(pruned)
+    }
+
+    fn metadata_path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", name))
+    }
+}
+
+/// Treats a file that is already gone as removed
+fn ignore_missing(result: io::Result<()>) -> io::Result<()> {
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        other => other,
+    }
+}
+
+fn entry_name(package: &str, trashed_at: u128) -> String {
+    format!("{}-{}", package.replace('/', "%2F"), trashed_at)
+}
+
+fn dir_size(dir: &Path) -> io::Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(size)
+}
+
+/// Renames `from` to `to`, copying then removing when they are on
+/// different filesystems
+fn move_dir(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            copy_dir(from, to)?;
+            fs::remove_dir_all(from)
+        }
+        other => other,
+    }
+}
+
+fn copy_file(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(from, to).map(|_| ())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,331 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::error::ErrorKind;
+use crate::lock::{hash_dir, hash_dir_excluding, LockError, Lockfile, LOCKFILE_NAME};
+use crate::report::{Report, Table};
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum VerifyError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to hash package: {0}")]
+    Hash(#[from] LockError),
+
+    #[error("Malformed checksum manifest at line {line}: {reason}")]
+    Manifest { line: usize, reason: String },
+
+    #[error("No trusted checksum for {0}")]
+    Untrusted(String),
+
+    #[error("Checksum mismatch for {package}: expected {expected}, got {actual}")]
+    Mismatch {
+        package: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("No {LOCKFILE_NAME} found in {}", .0.display())]
+    MissingLockfile(PathBuf),
+}
+
+impl VerifyError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(_) => ErrorKind::Internal,
+            Self::Hash(e) => e.kind(),
+            Self::Untrusted(_) | Self::Mismatch { .. } => ErrorKind::Integrity,
+            Self::Manifest { .. } | Self::MissingLockfile(_) => ErrorKind::Config,
+        }
+    }
+}
+
+/// Hook consulted before a downloaded package is moved into place.
+///
+/// Implementations receive the temporary directory holding the complete
+/// package and may reject it, in which case the target is left untouched.
+pub trait Verifier: Send + Sync {
+    fn verify(&self, pkg_path: &str, dir: &Path) -> Result<(), VerifyError>;
+}
+
+/// Verifies packages against a trusted checksum manifest.
+///
+/// The manifest uses the familiar `sha256sum` layout with the package hash
+/// from [crate::lock::hash_dir] in the first column:
+///
+/// ```text
+/// # comments and blank lines are ignored
+/// 3f1c...e9a0  gno.land/p/demo/avl
+/// ```
+pub struct ChecksumManifest {
+    checksums: HashMap<String, String>,
+    /// Reject packages that have no entry in the manifest
+    strict: bool,
+}
+
+impl ChecksumManifest {
+    /// Loads a manifest from disk
+    pub fn load(path: &Path) -> Result<Self, VerifyError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parses manifest contents
+    pub fn parse(contents: &str) -> Result<Self, VerifyError> {
+        let mut checksums = HashMap::new();
+
+        for (idx, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.split_whitespace();
+            let (Some(hash), Some(pkg), None) = (parts.next(), parts.next(), parts.next()) else {
+                return Err(VerifyError::Manifest {
+                    line: idx + 1,
+                    reason: "expected `<hash> <package path>`".to_string(),
+                });
+            };
+
+            if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(VerifyError::Manifest {
+                    line: idx + 1,
+                    reason: format!("invalid hash `{}`", hash),
+                });
+            }
+
+            checksums.insert(pkg.to_string(), hash.to_ascii_lowercase());
+        }
+
+        Ok(Self {
+            checksums,
+            strict: true,
+        })
+    }
+
+    /// Allow packages missing from the manifest instead of rejecting them
+    pub fn allow_unlisted(mut self) -> Self {
+        self.strict = false;
+        self
+    }
+}
+
+impl Verifier for ChecksumManifest {
+    fn verify(&self, pkg_path: &str, dir: &Path) -> Result<(), VerifyError> {
+        let Some(expected) = self.checksums.get(pkg_path) else {
+            if self.strict {
+                return Err(VerifyError::Untrusted(pkg_path.to_string()));
+            }
+            return Ok(());
+        };
+
+        let (actual, _) = hash_dir(dir)?;
+        if &actual != expected {
+            return Err(VerifyError::Mismatch {
+                package: pkg_path.to_string(),
+                expected: expected.clone(),
+                actual,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// How a file on disk disagrees with `gget.lock`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    /// Locked, but its content hash differs
+    Modified,
+    /// Locked, but not on disk
+    Missing,
+    /// On disk, but not locked
+    Extraneous,
+}
+
+impl FileStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Modified => "modified",
+            Self::Missing => "missing",
+            Self::Extraneous => "extraneous",
+        }
+    }
+}
+
+/// A file that doesn't match the lockfile
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TreeFinding {
+    /// Package the file belongs to, `None` for files outside every package
+    pub package: Option<String>,
+    /// Path relative to the package directory, or to the output directory
+    /// for files outside every package
+    pub file: String,
+    pub status: FileStatus,
+}
+
+/// Result of `gget verify`: every file under an output directory re-hashed
+/// and compared with its lockfile
+#[derive(Debug, Clone, Serialize)]
+pub struct TreeVerification {
+    pub lockfile: PathBuf,
+    pub packages: usize,
+    pub files: usize,
+    pub findings: Vec<TreeFinding>,
+}
+
+impl TreeVerification {
+    pub fn passed(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    /// Number of findings with `status`
+    pub fn count(&self, status: FileStatus) -> usize {
+        self.findings.iter().filter(|f| f.status == status).count()
+    }
+}
+
+impl Report for TreeVerification {
+    fn table(&self) -> Table {
+        let mut table = Table::new("Verification", &["package", "file", "status"]);
+        for finding in &self.findings {
+            table.push_row([
+                finding.package.as_deref().unwrap_or(""),
+                finding.file.as_str(),
+                finding.status.as_str(),
+            ]);
+        }
+        table
+    }
+
+    fn human(&self) -> String {
+        let mut out = if self.passed() {
+            format!(
+                "OK: {} files in {} packages match {}\n",
+                self.files,
+                self.packages,
+                self.lockfile.display()
+            )
+        } else {
+            format!(
+                "FAIL: {} modified, {} missing and {} extraneous files against {}\n",
+                self.count(FileStatus::Modified),
+                self.count(FileStatus::Missing),
+                self.count(FileStatus::Extraneous),
+                self.lockfile.display()
+            )
+        };
+        for finding in &self.findings {
+            let _ = writeln!(
+                out,
+                "  {:<10} {}{}",
+                finding.status.as_str(),
+                finding
+                    .package
+                    .as_ref()
+                    .map(|p| format!("{}: ", p))
+                    .unwrap_or_default(),
+                finding.file
+            );
+        }
+        out
+    }
+}
+
+/// Re-hashes every file under `target_dir` and compares it with the
+/// lockfile there.
+///
+/// Files are matched to the locked package whose directory holds them,
+/// following the lockfile's layout. Files outside every package directory
+/// are reported as extraneous, as are unlocked files inside one.
+pub fn verify_tree(target_dir: &Path) -> Result<TreeVerification, VerifyError> {
+    let lock_path = target_dir.join(LOCKFILE_NAME);
+    let lock = Lockfile::load_if_exists(&lock_path)?
+        .ok_or_else(|| VerifyError::MissingLockfile(target_dir.to_path_buf()))?;
+
+    let mut verification = TreeVerification {
+        lockfile: lock_path,
+        packages: lock.packages.len(),
+        files: 0,
+        findings: Vec::new(),
+    };
+    let mut package_dirs = Vec::with_capacity(lock.packages.len());
+    for (path, locked) in &lock.packages {
+        let dir = lock.package_dir(target_dir, path);
+        let on_disk = if dir.is_dir() {
+            hash_dir_excluding(&dir, &lock.nested_dirs(path, target_dir))?.1
+        } else {
+            BTreeMap::new()
+        };
+        verification.files += on_disk.len();
+
+        let mut finding = |file: &str, status| {
+            verification.findings.push(TreeFinding {
+                package: Some(path.clone()),
+                file: file.to_string(),
+                status,
+            })
+        };
+        for (name, hash) in &locked.files {
+            match on_disk.get(name) {
+                None => finding(name, FileStatus::Missing),
+                Some(actual) if actual != hash => finding(name, FileStatus::Modified),
+                Some(_) => {}
+            }
+        }
+        for name in on_disk.keys().filter(|n| !locked.files.contains_key(*n)) {
+            finding(name, FileStatus::Extraneous);
+        }
+        package_dirs.push(dir);
+    }
+
+    let mut stray = Vec::new();
+    collect_stray_files(target_dir, target_dir, &package_dirs, &mut stray)?;
+    verification.files += stray.len();
+    verification
+        .findings
+        .extend(stray.into_iter().map(|file| TreeFinding {
+            package: None,
+            file,
+            status: FileStatus::Extraneous,
+        }));
+    Ok(verification)
+}
+
+/// Lists the files under `dir` outside every directory in `package_dirs`,
+/// by path relative to `root`
+fn collect_stray_files(
+    root: &Path,
+    dir: &Path,
+    package_dirs: &[PathBuf],
+    files: &mut Vec<String>,
+) -> std::io::Result<()> {
+    if package_dirs.iter().any(|p| p == dir) {
+        return Ok(());
+    }
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            collect_stray_files(root, &path, package_dirs, files)?;
+        } else if path != root.join(LOCKFILE_NAME) {
+            files.push(
+                path.strip_prefix(root)
+                    .unwrap_or(&path)
+                    .to_string_lossy()
+                    .replace('\\', "/"),
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,163 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::error::ErrorKind;
+use crate::fetch::{PackageManager, PackageManagerError};
+use crate::lock::{hash_dir, LockError};
+use crate::parallel::PackageStats;
+
+/// Default time between two checks of `gget watch`
+pub const DEFAULT_WATCH_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum WatchError {
+    #[error("Package manager error: {0}")]
+    PackageManager(#[from] PackageManagerError),
+
+    #[error("Lockfile error: {0}")]
+    Lock(#[from] LockError),
+
+    #[error("Hook `{command}` failed: {reason}")]
+    Hook { command: String, reason: String },
+}
+
+impl WatchError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::PackageManager(e) => e.kind(),
+            Self::Lock(e) => e.kind(),
+            Self::Hook { .. } => ErrorKind::Config,
+        }
+    }
+}
+
+/// A re-download triggered by a change of the package on chain
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchUpdate {
+    pub package: String,
+    /// Hash of the previous copy, `None` if there was none on disk
+    pub old_hash: Option<String>,
+    pub new_hash: String,
+    pub stats: PackageStats,
+}
+
+/// Keeps a local copy of a package in sync with the chain by polling its
+/// content hash, re-downloading it when the hash changes.
+///
+/// An optional hook command runs through `sh -c` after every update, with
+/// `GGET_PACKAGE`, `GGET_DIR`, `GGET_OLD_HASH` and `GGET_NEW_HASH` set.
+pub struct Watcher {
+    pm: PackageManager,
+    pkg_path: String,
+    target_dir: PathBuf,
+    interval: Duration,
+    hook: Option<String>,
+    /// Hash of the copy on disk, as of the last check
+    current: Option<String>,
+}
+
+impl Watcher {
+    pub fn new(pm: PackageManager, pkg_path: &str, target_dir: &Path) -> Self {
+        Self {
+            pm,
+            pkg_path: pkg_path.to_string(),
+            target_dir: target_dir.to_path_buf(),
+            interval: DEFAULT_WATCH_INTERVAL,
+            hook: None,
+            current: None,
+        }
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn with_hook(mut self, command: &str) -> Self {
+        self.hook = Some(command.to_string());
+        self
+    }
+
+    /// Checks the package once, re-downloading it if it changed.
+    ///
+    /// The first check compares against whatever is already on disk; later
+    /// checks against the last hash seen.
+    pub async fn check(&mut self) -> Result<Option<WatchUpdate>, WatchError> {
+        if self.current.is_none() && self.target_dir.is_dir() {
+            self.current = Some(hash_dir(&self.target_dir)?.0);
+        }
+
+        let new_hash = self.pm.fetch_remote_hash(&self.pkg_path).await?;
+        if self.current.as_ref() == Some(&new_hash) {
+            return Ok(None);
+        }
+
+        let stats = self
+            .pm
+            .download_package_atomic(&self.pkg_path, &self.target_dir)
+            .await?;
+        let update = WatchUpdate {
+            package: self.pkg_path.clone(),
+            old_hash: self.current.replace(new_hash.clone()),
+            new_hash,
+            stats,
+        };
+        self.run_hook(&update)?;
+        Ok(Some(update))
+    }
+
+    /// Checks the package every interval until interrupted, passing each
+    /// update to `on_update`.
+    ///
+    /// Failed checks (an unreachable node, a failing hook) don't stop the
+    /// watch; they are passed to `on_error` and retried at the next interval.
+    pub async fn run(
+        &mut self,
+        mut on_update: impl FnMut(&WatchUpdate),
+        mut on_error: impl FnMut(&WatchError),
+    ) -> Result<(), WatchError> {
+        loop {
+            match self.check().await {
+                Ok(Some(update)) => on_update(&update),
+                Ok(None) => {}
+                Err(WatchError::PackageManager(e @ PackageManagerError::Cancelled(_))) => {
+                    return Err(e.into())
+                }
+                Err(e) => on_error(&e),
+            }
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+
+    fn run_hook(&self, update: &WatchUpdate) -> Result<(), WatchError> {
+        let Some(command) = &self.hook else {
+            return Ok(());
+        };
+        let failed = |reason: String| WatchError::Hook {
+            command: command.clone(),
+            reason,
+        };
+
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .env("GGET_PACKAGE", &update.package)
+            .env("GGET_DIR", &self.target_dir)
+            .env(
+                "GGET_OLD_HASH",
+                update.old_hash.as_deref().unwrap_or_default(),
+            )
+            .env("GGET_NEW_HASH", &update.new_hash)
+            .status()
+            .map_err(|e| failed(e.to_string()))?;
+        if !status.success() {
+            return Err(failed(status.to_string()));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,386 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::error::ErrorKind;
+use crate::fetch::{PackageManager, PackageManagerError};
+use crate::gnomod::{module_imports, strip_comment, unquote, GnoMod, GnoModError, GNOMOD_NAME};
+use crate::install::write_durably;
+use crate::lock::{LockError, Lockfile, Selection, LOCKFILE_NAME};
+use crate::parallel::{DownloadSummary, ParallelDownloadOptions};
+use crate::plan::DownloadPlan;
+
+/// Default workspace file name, looked for in the working directory
+pub const WORKSPACE_NAME: &str = "gget.work";
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum WorkspaceError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("gget.work line {line}: {reason}")]
+    Parse { line: usize, reason: String },
+
+    #[error("{}: {source}", .path.display())]
+    GnoMod {
+        path: PathBuf,
+        #[source]
+        source: GnoModError,
+    },
+
+    #[error("{} has no module directive", .0.display())]
+    MissingModule(PathBuf),
+
+    #[error("{module} is declared by both {} and {}", .first.display(), .second.display())]
+    DuplicateModule {
+        module: String,
+        first: PathBuf,
+        second: PathBuf,
+    },
+
+    #[error("Package manager error: {0}")]
+    PackageManager(#[from] PackageManagerError),
+
+    #[error("Lockfile error: {0}")]
+    Lock(#[from] LockError),
+}
+
+impl WorkspaceError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(_) => ErrorKind::Internal,
+            Self::Parse { .. } | Self::MissingModule(_) | Self::DuplicateModule { .. } => {
+                ErrorKind::Config
+            }
+            Self::GnoMod { source, .. } => source.kind(),
+            Self::PackageManager(e) => e.kind(),
+            Self::Lock(e) => e.kind(),
+        }
+    }
+}
+
+/// A module of a [Workspace]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceMember {
+    /// Directory holding the module's gno.mod
+    pub dir: PathBuf,
+    /// Module path declared by its gno.mod
+    pub module: String,
+    pub gnomod: GnoMod,
+}
+
+impl WorkspaceMember {
+    /// Whether `pkg_path` is the module or one of its subpackages
+    pub fn provides(&self, pkg_path: &str) -> bool {
+        pkg_path
+            .strip_prefix(&self.module)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+}
+
+/// Several modules of one repository managed together, listed by a
+/// go.work-style `gget.work` file:
+///
+/// ```text
+/// use (
+///     ./r/app
+///     ./p/util
+/// )
+/// ```
+///
+/// Imports between members are served by the members' sources; only the
+/// rest comes from the chain, into a single vendor directory and lockfile.
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    /// Directory of the gget.work file, which member paths are relative to
+    pub root: PathBuf,
+    pub members: Vec<WorkspaceMember>,
+}
+
+/// What the members of a [Workspace] import
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorkspaceImports {
+    /// Imports no member provides, to be fetched from the chain
+    pub external: BTreeSet<String>,
+    /// Imports of other members, keyed by the importing member's module
+    pub local: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl Workspace {
+    /// Reads a gget.work file and the gno.mod of each member it lists
+    pub fn load(path: &Path) -> Result<Self, WorkspaceError> {
+        let root = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let dirs = parse_workspace(&fs::read_to_string(path)?)?;
+        Self::from_dirs(root, &dirs)
+    }
+
+    /// Builds a workspace of the modules in `dirs`, relative to `root`
+    pub fn from_dirs(root: PathBuf, dirs: &[PathBuf]) -> Result<Self, WorkspaceError> {
+        let mut members: Vec<WorkspaceMember> = Vec::new();
+        for dir in dirs {
+            let dir = root.join(dir);
+            let path = dir.join(GNOMOD_NAME);
+            let gnomod = GnoMod::load(&path).map_err(|source| WorkspaceError::GnoMod {
+                path: path.clone(),
+                source,
+            })?;
+            let module = gnomod
+                .module
+                .clone()
+                .ok_or_else(|| WorkspaceError::MissingModule(path.clone()))?;
+            if let Some(other) = members.iter().find(|m| m.module == module) {
+                return Err(WorkspaceError::DuplicateModule {
+                    module,
+                    first: other.dir.clone(),
+                    second: dir,
+                });
+            }
+            members.push(WorkspaceMember {
+                dir,
+                module,
+                gnomod,
+            });
+        }
+        Ok(Self { root, members })
+    }
+
+    /// The member providing `pkg_path`, the one with the longest module
+    /// path if they nest
+    pub fn member_of(&self, pkg_path: &str) -> Option<&WorkspaceMember> {
+        self.members
+            .iter()
+            .filter(|member| member.provides(pkg_path))
+            .max_by_key(|member| member.module.len())
+    }
+
+    /// Parses the sources of every member, skipping `exclude`, and splits
+    /// their imports into those the workspace provides and the rest
+    pub fn imports(&self, exclude: &Path) -> Result<WorkspaceImports, WorkspaceError> {
+        let mut imports = WorkspaceImports::default();
+        for member in &self.members {
+            let found =
+                module_imports(&member.dir, exclude).map_err(|source| WorkspaceError::GnoMod {
+                    path: member.dir.join(GNOMOD_NAME),
+                    source,
+                })?;
+            for import in found {
+                match self.member_of(&import) {
+                    // a module's own subpackages aren't dependencies
+                    Some(other) if other.module == member.module => {}
+                    Some(_) => {
+                        imports
+                            .local
+                            .entry(member.module.clone())
+                            .or_default()
+                            .insert(import);
+                    }
+                    None => {
+                        imports.external.insert(import);
+                    }
+                }
+            }
+        }
+        Ok(imports)
+    }
+
+    /// Heights to fetch packages at, picked across the members' own
+    /// lockfiles: where members pinned a package at different heights the
+    /// highest one wins, see [Lockfile::merge_roots]. The lockfile in
+    /// `target_dir` is the workspace's own and isn't a member's.
+    pub fn select_heights(&self, target_dir: &Path) -> Result<Vec<Selection>, WorkspaceError> {
+        let own = target_dir.join(LOCKFILE_NAME).canonicalize().ok();
+        let mut roots = Vec::new();
+        for member in &self.members {
+            let path = member.dir.join(LOCKFILE_NAME);
+            if own.is_some() && path.canonicalize().ok() == own {
+                continue;
+            }
+            if let Some(mut lock) = Lockfile::load_if_exists(&path)? {
+                // unpinned packages were fetched at whatever height was current
+                lock.packages.retain(|_, pkg| pkg.height.is_some());
+                roots.push((member.module.clone(), lock));
+            }
+        }
+        let (_, selections) = Lockfile::merge_roots(&roots)?;
+        Ok(selections)
+    }
+
+    /// The imports to fetch, and `pm` set up to resolve them within the
+    /// workspace at the heights selected for them
+    fn prepare(
+        &self,
+        pm: &PackageManager,
+        target_dir: &Path,
+    ) -> Result<(WorkspaceImports, PackageManager), WorkspaceError> {
+        let imports = self.imports(target_dir)?;
+        let pm = self
+            .members
+            .iter()
+            .fold(pm.clone(), |pm, member| {
+                pm.with_replacements(member.gnomod.replacements(&member.dir))
+            })
+            .with_local_modules(self.members.iter().map(|m| m.module.clone()))
+            .with_selections(self.select_heights(target_dir)?);
+        Ok((imports, pm))
+    }
+
+    /// What [Workspace::sync] would download under `target_dir` and the
+    /// heights it would select, without writing anything
+    pub async fn plan(
+        &self,
+        pm: &PackageManager,
+        target_dir: &Path,
+    ) -> Result<DownloadPlan, WorkspaceError> {
+        let (imports, pm) = self.prepare(pm, target_dir)?;
+        let roots: Vec<&str> = imports.external.iter().map(String::as_str).collect();
+        if roots.is_empty() {
+            return Ok(DownloadPlan::default());
+        }
+        Ok(pm.plan_download(&roots, target_dir, true).await?)
+    }
+
+    /// Downloads what the members import from the chain, and everything
+    /// that imports, under `target_dir`, recording all of it in one
+    /// lockfile there.
+    ///
+    /// Resolution stops at packages of the members, even when chain
+    /// packages import them, and packages the members' gno.mod files
+    /// replace are read from their directories. Packages the members'
+    /// lockfiles pin are fetched at the heights [Workspace::select_heights]
+    /// picks, which the lockfile records.
+    pub async fn sync(
+        &self,
+        pm: &PackageManager,
+        target_dir: &Path,
+        options: ParallelDownloadOptions,
+    ) -> Result<(WorkspaceImports, DownloadSummary), WorkspaceError> {
+        let (imports, pm) = self.prepare(pm, target_dir)?;
+        let roots: Vec<&str> = imports.external.iter().map(String::as_str).collect();
+        let summary = if roots.is_empty() {
+            fs::create_dir_all(target_dir)?;
+            Lockfile {
+                layout: pm.layout(),
+                ..Default::default()
+            }
+            .save(&target_dir.join(LOCKFILE_NAME))?;
+            DownloadSummary::default()
+        } else {
+            pm.download_roots_with_deps_parallel(&roots, target_dir, options)
+                .await?
+        };
+        Ok((imports, summary))
+    }
+}
+
+/// Parses gget.work content into the member directories it lists, in
+/// order.
+///
+/// Each `use` directive takes one directory or a `( ... )` block of them.
+/// `//` comments and other directives are ignored.
+pub fn parse_workspace(content: &str) -> Result<Vec<PathBuf>, WorkspaceError> {
+    let mut dirs = Vec::new();
+    // directive of the `( ... )` block currently open, if any
+    let mut block: Option<String> = None;
+
+    for (idx, raw) in content.lines().enumerate() {
+        let line = strip_comment(raw).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(directive) = &block {
+            if line == ")" {
+                block = None;
+            } else if directive == "use" {
+                dirs.push(PathBuf::from(unquote(line)));
+            }
+            continue;
+        }
+
+        let (directive, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        if rest == "(" {
+            block = Some(directive.to_string());
+        } else if directive == "use" {
+            if rest.is_empty() {
+                return Err(WorkspaceError::Parse {
+                    line: idx + 1,
+                    reason: "missing directory".to_string(),
+                });
+            }
+            dirs.push(PathBuf::from(unquote(rest)));
+        }
+    }
+
+    if block.is_some() {
+        return Err(WorkspaceError::Parse {
+            line: content.lines().count(),
+            reason: "unterminated block".to_string(),
+        });
+    }
+    Ok(dirs)
+}
+
+/// gget.work content listing `dirs`
+pub fn format_workspace(dirs: &[PathBuf]) -> String {
+    let mut out = String::from("use (\n");
+    for dir in dirs {
+        let dir = dir.to_string_lossy().replace('\\', "/");
+        let dir = match dir.as_str() {
+            "" | "." => ".".to_string(),
+            dir if dir.starts_with("./") || dir.starts_with("../") || dir.starts_with('/') => {
+                dir.to_string()
+            }
+            dir => format!("./{}", dir),
+        };
+        let _ = writeln!(out, "\t{}", dir);
+    }
+    out.push_str(")\n");
+    out
+}
+
+/// Directories under `root` holding a gno.mod, relative to it and sorted,
+/// skipping hidden directories and `exclude`
+pub fn find_modules(root: &Path, exclude: &Path) -> Result<Vec<PathBuf>, WorkspaceError> {
+    let exclude = exclude.canonicalize().ok();
+    let mut modules = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        if dir.join(GNOMOD_NAME).is_file() {
+            modules.push(dir.strip_prefix(root).unwrap_or(&dir).to_path_buf());
+        }
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let hidden = path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+            let excluded = exclude.is_some() && path.canonicalize().ok() == exclude;
+            if path.is_dir() && !hidden && !excluded {
+                dirs.push(path);
+            }
+        }
+    }
+    modules.sort();
+    Ok(modules)
+}
+
+/// Writes a gget.work at `path` listing `dirs`, refusing to replace an
+/// existing one
+pub fn init_workspace(path: &Path, dirs: &[PathBuf]) -> Result<(), WorkspaceError> {
+    if path.exists() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            format!("{} already exists", path.display()),
+        )
+        .into());
+    }
+    write_durably(path, format_workspace(dirs).as_bytes())?;
+    Ok(())
+}
@@ -0,0 +1,282 @@
+use gget::archive::{
+    export_archive, import_archive, plan_import, seed_from_archive, ArchiveError, ArchiveFormat,
+    ExportOptions, ImportOptions,
+};
+use gget::cache::HybridCache;
+use gget::fetch::PackageManager;
+use gget::lock::{Lockfile, LOCKFILE_NAME};
+use gget::plan::FileAction;
+use gget::testing::{download_options, FakeChain};
+use gget::verify::verify_tree;
+use gget::ErrorKind;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tempfile::tempdir;
+
+fn chain() -> FakeChain {
+    FakeChain::demo().with_package("gno.land/p/demo/ufmt", &[("ufmt.gno", "package ufmt\n")])
+}
+
+/// Downloads `app` and `ufmt` with their dependencies into `output`
+async fn download(url: String, cache: &Path, output: &Path) {
+    let pm = PackageManager::new(Some(url), cache.to_path_buf()).with_quiet(true);
+    let options = download_options();
+    let summary = pm
+        .download_roots_with_deps_parallel(
+            &["gno.land/r/demo/app", "gno.land/p/demo/ufmt"],
+            output,
+            options,
+        )
+        .await
+        .unwrap();
+    assert!(summary.failed.is_empty(), "{}", summary);
+}
+
+#[tokio::test]
+async fn test_export_and_import_with_manifest() {
+    let chain = chain();
+    let url = chain.spawn();
+    let output = tempdir().unwrap();
+    download(url.clone(), tempdir().unwrap().path(), output.path()).await;
+
+    let scratch = tempdir().unwrap();
+    let archive = scratch.path().join("app.tar.gz");
+    let options = ExportOptions {
+        with_deps: true,
+        manifest: true,
+        ..Default::default()
+    };
+    let exported = export_archive("gno.land/r/demo/app", output.path(), &archive, options).unwrap();
+    assert_eq!(
+        exported.packages,
+        ["gno.land/p/demo/avl", "gno.land/r/demo/app"]
+    );
+    assert_eq!(exported.files, 3);
+
+    // same tree, same bytes
+    let again = archive.with_file_name("again.tar.gz");
+    export_archive("gno.land/r/demo/app", output.path(), &again, options).unwrap();
+    assert_eq!(fs::read(&archive).unwrap(), fs::read(&again).unwrap());
+
+    let cache = tempdir().unwrap();
+    let imported_dir = tempdir().unwrap();
+    let offline = PackageManager::builder()
+        .endpoint(&url)
+        .cache_dir(cache.path())
+        .offline(true)
+        .build()
+        .unwrap()
+        .with_quiet(true);
+    let imported = import_archive(
+        &offline,
+        &archive,
+        imported_dir.path(),
+        ImportOptions::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(imported.cached, 2);
+
+    // the tree is complete, locked, and the cache serves it offline
+    let lock = Lockfile::load(&imported_dir.path().join(LOCKFILE_NAME)).unwrap();
+    assert_eq!(lock.roots, ["gno.land/r/demo/app"]);
+    assert_eq!(lock.packages.len(), 2);
+    assert!(verify_tree(imported_dir.path()).unwrap().passed());
+    let target = tempdir().unwrap();
+    offline
+        .download_package("gno.land/p/demo/avl", target.path())
+        .await
+        .unwrap();
+    assert!(target.path().join("node.gno").exists());
+}
+
+#[tokio::test]
+async fn test_export_single_package_without_manifest() {
+    let chain = chain();
+    let url = chain.spawn();
+    let output = tempdir().unwrap();
+    download(url.clone(), tempdir().unwrap().path(), output.path()).await;
+
+    let scratch = tempdir().unwrap();
+    let archive = scratch.path().join("app.tar");
+    let options = ExportOptions {
+        format: ArchiveFormat::Tar,
+        ..Default::default()
+    };
+    let exported = export_archive("gno.land/r/demo/app", output.path(), &archive, options).unwrap();
+    assert!(exported.packages.is_empty());
+    assert_eq!(exported.files, 1);
+
+    let cache = tempdir().unwrap();
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf());
+    let imported_dir = tempdir().unwrap();
+    let imported = import_archive(&pm, &archive, imported_dir.path(), ImportOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(imported.cached, 0);
+    assert!(imported_dir
+        .path()
+        .join("gno.land/r/demo/app/app.gno")
+        .exists());
+    assert!(!imported_dir.path().join("gno.land/p").exists());
+    assert!(!imported_dir.path().join(LOCKFILE_NAME).exists());
+}
+
+#[tokio::test]
+async fn test_export_refuses_modified_packages() {
+    let chain = chain();
+    let url = chain.spawn();
+    let output = tempdir().unwrap();
+    download(url, tempdir().unwrap().path(), output.path()).await;
+    fs::write(
+        output.path().join("gno.land/p/demo/avl/node.gno"),
+        "package avl // edited\n",
+    )
+    .unwrap();
+
+    let scratch = tempdir().unwrap();
+    let archive = scratch.path().join("app.tar.gz");
+    let options = ExportOptions {
+        with_deps: true,
+        ..Default::default()
+    };
+    let err = export_archive("gno.land/r/demo/app", output.path(), &archive, options).unwrap_err();
+    assert!(
+        matches!(&err, ArchiveError::Modified { package, files }
+            if package == "gno.land/p/demo/avl" && files == &["node.gno"]),
+        "{}",
+        err
+    );
+    assert_eq!(err.kind(), ErrorKind::Integrity);
+    assert!(!archive.exists());
+}
+
+#[tokio::test]
+async fn test_import_refuses_modified_packages_unless_forced() {
+    let chain = chain();
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let output = tempdir().unwrap();
+    download(url.clone(), cache.path(), output.path()).await;
+
+    let scratch = tempdir().unwrap();
+    let archive = scratch.path().join("app.tar.gz");
+    let options = ExportOptions {
+        with_deps: true,
+        manifest: true,
+        ..Default::default()
+    };
+    export_archive("gno.land/r/demo/app", output.path(), &archive, options).unwrap();
+
+    let node = output.path().join("gno.land/p/demo/avl/node.gno");
+    fs::write(&node, "package avl // edited\n").unwrap();
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+    let err = import_archive(&pm, &archive, output.path(), ImportOptions::default())
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, ArchiveError::Modified { package, files }
+            if package == "gno.land/p/demo/avl" && files == &["node.gno"]),
+        "{}",
+        err
+    );
+    assert_eq!(
+        fs::read_to_string(&node).unwrap(),
+        "package avl // edited\n"
+    );
+
+    let options = ImportOptions { force: true };
+    import_archive(&pm, &archive, output.path(), options)
+        .await
+        .unwrap();
+    assert_ne!(
+        fs::read_to_string(&node).unwrap(),
+        "package avl // edited\n"
+    );
+    verify_tree(output.path()).unwrap();
+}
+
+#[tokio::test]
+async fn test_import_plan_writes_nothing() {
+    let chain = chain();
+    let url = chain.spawn();
+    let output = tempdir().unwrap();
+    download(url, tempdir().unwrap().path(), output.path()).await;
+
+    let scratch = tempdir().unwrap();
+    let archive = scratch.path().join("app.tar.gz");
+    let options = ExportOptions {
+        with_deps: true,
+        manifest: true,
+        ..Default::default()
+    };
+    export_archive("gno.land/r/demo/app", output.path(), &archive, options).unwrap();
+
+    let plan = plan_import(&archive, output.path()).unwrap();
+    assert!(plan
+        .packages
+        .iter()
+        .any(|p| p.package == "gno.land/p/demo/avl"));
+    assert_eq!(plan.count(FileAction::Create), 0);
+    assert_eq!(plan.count(FileAction::Overwrite), 0);
+
+    let empty = tempdir().unwrap();
+    let plan = plan_import(&archive, empty.path()).unwrap();
+    assert!(plan.count(FileAction::Create) > 0);
+    assert_eq!(fs::read_dir(empty.path()).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn test_seed_cache_from_archive() {
+    let chain = chain();
+    let url = chain.spawn();
+    let output = tempdir().unwrap();
+    download(url, tempdir().unwrap().path(), output.path()).await;
+
+    let scratch = tempdir().unwrap();
+    let with_manifest = scratch.path().join("app.tar.gz");
+    let options = ExportOptions {
+        with_deps: true,
+        manifest: true,
+        ..Default::default()
+    };
+    export_archive(
+        "gno.land/r/demo/app",
+        output.path(),
+        &with_manifest,
+        options,
+    )
+    .unwrap();
+
+    let cache = tempdir().unwrap();
+    let cache = HybridCache::new(cache.path().to_path_buf(), Duration::from_secs(3600), 10);
+    let seeded = seed_from_archive(&cache, &with_manifest).await.unwrap();
+    assert_eq!(
+        seeded.packages,
+        ["gno.land/p/demo/avl", "gno.land/r/demo/app"]
+    );
+    assert_eq!(seeded.files, 3);
+    assert_eq!(
+        cache
+            .get("file:gno.land/p/demo/avl/node.gno")
+            .await
+            .unwrap()
+            .as_deref(),
+        Some("package avl\n")
+    );
+
+    // without a manifest there is no telling where packages start
+    let without = scratch.path().join("bare.tar.gz");
+    export_archive(
+        "gno.land/r/demo/app",
+        output.path(),
+        &without,
+        ExportOptions::default(),
+    )
+    .unwrap();
+    assert!(matches!(
+        seed_from_archive(&cache, &without).await,
+        Err(ArchiveError::MissingManifest(_))
+    ));
+}
@@ -1,126 +1,14 @@
-use std::collections::HashMap;
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
-use std::{io, time};
+use std::sync::Arc;
+use std::time::Duration;
 use tempfile::TempDir;
 use tokio::fs;
 
-use gget::cache::HybridCache;
-use gget::fetch::PackageManagerError;
+use gget::fetch::PackageManager;
+use gget::filelock::FileLock;
+use gget::testing::{Failure, FakeChain};
+use gget::ErrorKind;
 
-struct MockRpcServer {
-    responses: Arc<Mutex<HashMap<String, String>>>,
-    should_fail: Arc<Mutex<bool>>,
-    call_count: Arc<Mutex<usize>>,
-}
-
-impl MockRpcServer {
-    fn new() -> Self {
-        Self {
-            responses: Arc::new(Mutex::new(HashMap::new())),
-            should_fail: Arc::new(Mutex::new(false)),
-            call_count: Arc::new(Mutex::new(0)),
-        }
-    }
-
-    #[allow(dead_code)]
-    fn set_rsps(&self, encoded_path: &str, response: &str) {
-        let mut rsps = self.responses.lock().unwrap();
-        rsps.insert(encoded_path.to_string(), response.to_string());
-    }
-
-    fn set_should_fail(&self, should_fail: bool) {
-        *self.should_fail.lock().unwrap() = should_fail;
-    }
-
-    #[allow(dead_code)]
-    fn get_call_count(&self) -> usize {
-        *self.call_count.lock().unwrap()
-    }
-
-    #[allow(dead_code)]
-    fn reset_call_count(&self) {
-        *self.call_count.lock().unwrap() = 0;
-    }
-}
-
-#[allow(dead_code)]
-struct MockPackageManager {
-    cache: HybridCache,
-    mock_server: MockRpcServer,
-    rpc_endpoint: String,
-}
-
-impl MockPackageManager {
-    fn new(cache_dir: PathBuf, mock_server: MockRpcServer) -> Self {
-        let cache = HybridCache::new(cache_dir, time::Duration::from_secs(3600), 100);
-        Self {
-            cache,
-            mock_server,
-            rpc_endpoint: "http://mock.test".to_string(),
-        }
-    }
-
-    // TODO: abstract this into a trait
-    async fn download_package_atomic(
-        &self,
-        pkg_path: &str,
-        target_dir: &std::path::Path,
-    ) -> Result<(), PackageManagerError> {
-        use std::time::{SystemTime, UNIX_EPOCH};
-
-        // increment call count
-        {
-            let mut count = self.mock_server.call_count.lock().unwrap();
-            *count += 1;
-        }
-
-        // check if should fail
-        if *self.mock_server.should_fail.lock().unwrap() {
-            return Err(PackageManagerError::Io(io::Error::new(
-                io::ErrorKind::ConnectionRefused,
-                "Mock server failed",
-            )));
-        }
-
-        // create temp dir
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_nanos();
-        let temp_dir_name = format!(
-            "{}_tmp_{}",
-            target_dir
-                .file_name()
-                .and_then(|n| n.to_str())
-                .unwrap_or("package"),
-            timestamp
-        );
-
-        let temp_dir = if let Some(parent) = target_dir.parent() {
-            parent.join(temp_dir_name)
-        } else {
-            PathBuf::from(temp_dir_name)
-        };
-
-        // Ensure cleanup happens even if download fails
-        struct TempDirGuard(PathBuf);
-        impl Drop for TempDirGuard {
-            fn drop(&mut self) {
-                if self.0.exists() {
-                    let _ = std::fs::remove_dir_all(&self.0);
-                }
-            }
-        }
-        let _guard = TempDirGuard(temp_dir.clone());
-
-        // Mock download to temporary directory
-        std::fs::create_dir_all(&temp_dir).map_err(PackageManagerError::Io)?;
-
-        // Create mock files based on package path
-        match pkg_path {
-            "gno.land/p/demo/avl" => {
-                let avl_content = r#"package avl
+const NODE_GNO: &str = r#"package avl
 
 type Node struct {
     key   string
@@ -133,113 +21,43 @@ func NewTree() *Tree {
     return &Tree{}
 }
 "#;
-                fs::write(temp_dir.join("node.gno"), avl_content)
-                    .await
-                    .map_err(PackageManagerError::Io)?;
-                fs::write(
-                    temp_dir.join("tree.gno"),
-                    "package avl\n\ntype Tree struct{}\n",
-                )
-                .await
-                .map_err(PackageManagerError::Io)?;
-            }
-            "gno.land/p/demo/ufmt" => {
-                let ufmt_content = r#"package ufmt
 
-func Sprintf(format string, args ...any) string {
-    return ""
+/// `avl` and `ufmt`, and a package without a single `.gno` file
+fn chain() -> FakeChain {
+    FakeChain::new()
+        .with_package(
+            "gno.land/p/demo/avl",
+            &[
+                ("node.gno", NODE_GNO),
+                ("tree.gno", "package avl\n\ntype Tree struct{}\n"),
+            ],
+        )
+        .with_package(
+            "gno.land/p/demo/ufmt",
+            &[("ufmt.gno", "package ufmt\n\nfunc Println(args ...any) {}\n")],
+        )
+        .with_package(
+            "gno.land/p/demo/invalid",
+            &[("README.md", "Not a gno file")],
+        )
 }
 
-func Println(args ...any) {
-    // implementation
-}
-"#;
-                fs::write(temp_dir.join("ufmt.gno"), ufmt_content)
-                    .await
-                    .map_err(PackageManagerError::Io)?;
-            }
-            _ => {
-                // Default mock package
-                let content = format!(
-                    "package {}\n\nfunc Hello() string {{\n    return \"Hello from {}\"\n}}",
-                    pkg_path.split('/').last().unwrap_or("unknown"),
-                    pkg_path
-                );
-                fs::write(temp_dir.join("main.gno"), content)
-                    .await
-                    .map_err(PackageManagerError::Io)?;
-            }
-        }
-
-        // Validate the package (basic check)
-        self.validate_package(&temp_dir).await?;
-
-        // If target directory exists, remove it first
-        if target_dir.exists() {
-            std::fs::remove_dir_all(target_dir).map_err(PackageManagerError::Io)?;
-        }
-
-        // Create parent directory if needed
-        if let Some(parent) = target_dir.parent() {
-            if !parent.exists() {
-                std::fs::create_dir_all(parent)
-                    .map_err(|e| PackageManagerError::DirectoryCreation(e.to_string()))?;
-            }
-        }
-
-        // Atomically move from temp to final location
-        std::fs::rename(&temp_dir, target_dir).map_err(PackageManagerError::Io)?;
-
-        Ok(())
-    }
-
-    // TODO: abstract this into a trait
-    async fn validate_package(
-        &self,
-        target_dir: &std::path::Path,
-    ) -> Result<(), PackageManagerError> {
-        let mut has_gno_files = false;
-
-        if let Ok(entries) = std::fs::read_dir(target_dir) {
-            for entry in entries.flatten() {
-                if let Some(ext) = entry.path().extension() {
-                    if ext == "gno" {
-                        has_gno_files = true;
-
-                        // Basic validation - check if file is readable
-                        let _content = std::fs::read_to_string(entry.path())
-                            .map_err(PackageManagerError::Io)?;
-                    }
-                }
-            }
-        }
-
-        if !has_gno_files {
-            return Err(PackageManagerError::Rpc(
-                "No valid .gno files found in package".to_string(),
-            ));
-        }
-
-        Ok(())
-    }
+fn package_manager(chain: &FakeChain, cache: &TempDir) -> PackageManager {
+    PackageManager::new(Some(chain.spawn()), cache.path().to_path_buf()).with_quiet(true)
 }
 
 #[tokio::test]
 async fn test_atomic_download_success() {
     let temp_dir = TempDir::new().unwrap();
-    let cache_dir = temp_dir.path().join("cache");
+    let cache = TempDir::new().unwrap();
     let target_dir = temp_dir.path().join("avl");
+    let package_manager = package_manager(&chain(), &cache);
 
-    let mock_server = MockRpcServer::new();
-    let package_manager = MockPackageManager::new(cache_dir, mock_server);
-
-    // Test successful download
     let result = package_manager
         .download_package_atomic("gno.land/p/demo/avl", &target_dir)
         .await;
 
-    assert!(result.is_ok(), "Download should succeed");
-    assert!(target_dir.exists(), "Target directory should exist");
+    assert!(result.is_ok(), "Download should succeed: {:?}", result);
     assert!(
         target_dir.join("node.gno").exists(),
         "node.gno should exist"
@@ -248,27 +66,22 @@ async fn test_atomic_download_success() {
         target_dir.join("tree.gno").exists(),
         "tree.gno should exist"
     );
-
-    // Verify content
     let content = fs::read_to_string(target_dir.join("node.gno"))
         .await
         .unwrap();
-    assert!(content.contains("package avl"));
-    assert!(content.contains("type Node struct"));
-    assert!(content.contains("func NewTree() *Tree {"));
+    assert_eq!(content, NODE_GNO);
+    assert_eq!(leftover_staging_dirs(temp_dir.path()), 0);
 }
 
 #[tokio::test]
 async fn test_atomic_download_failure_cleanup() {
     let temp_dir = TempDir::new().unwrap();
-    let cache_dir = temp_dir.path().join("cache");
+    let cache = TempDir::new().unwrap();
     let target_dir = temp_dir.path().join("failed_package");
+    let chain = chain();
+    chain.fail_next(10, Failure::Status(500));
+    let package_manager = package_manager(&chain, &cache);
 
-    let mock_server = MockRpcServer::new();
-    mock_server.set_should_fail(true); // Force failure
-    let package_manager = MockPackageManager::new(cache_dir, mock_server);
-
-    // Test failed download
     let result = package_manager
         .download_package_atomic("gno.land/p/demo/avl", &target_dir)
         .await;
@@ -278,55 +91,32 @@ async fn test_atomic_download_failure_cleanup() {
         !target_dir.exists(),
         "Target directory should not exist after failure"
     );
-
-    // Check that no temporary directories are left behind
-    let parent_dir = target_dir.parent().unwrap();
-    let entries: Vec<_> = std::fs::read_dir(parent_dir)
-        .unwrap()
-        .filter_map(Result::ok)
-        .collect();
-
-    let temp_dirs: Vec<_> = entries
-        .iter()
-        .filter(|entry| entry.file_name().to_string_lossy().contains("_tmp_"))
-        .collect();
-
-    assert_eq!(temp_dirs.len(), 0, "No temporary directories should remain");
+    assert_eq!(
+        leftover_staging_dirs(temp_dir.path()),
+        0,
+        "No temporary directories should remain"
+    );
 }
 
 #[tokio::test]
 async fn test_atomic_download_preserves_existing_on_failure() {
     let temp_dir = TempDir::new().unwrap();
-    let cache_dir = temp_dir.path().join("cache");
+    let cache = TempDir::new().unwrap();
     let target_dir = temp_dir.path().join("existing_package");
+    let chain = chain();
+    let package_manager = package_manager(&chain, &cache);
 
-    // Create existing package
-    std::fs::create_dir_all(&target_dir).unwrap();
-    std::fs::write(target_dir.join("existing.gno"), "package existing\n").unwrap();
-
-    let _original_content = std::fs::read_to_string(target_dir.join("existing.gno")).unwrap();
-
-    let mock_server = MockRpcServer::new();
-    let package_manager = MockPackageManager::new(cache_dir, mock_server);
-
-    // First download should succeed and replace existing
-    let result = package_manager
+    package_manager
         .download_package_atomic("gno.land/p/demo/avl", &target_dir)
-        .await;
-    assert!(result.is_ok());
-
-    // Now set up for failure
-    package_manager.mock_server.set_should_fail(true);
+        .await
+        .unwrap();
 
-    // Attempted download should fail and preserve what was just downloaded
+    chain.fail_next(10, Failure::Status(500));
     let result = package_manager
         .download_package_atomic("gno.land/p/demo/ufmt", &target_dir)
         .await;
 
     assert!(result.is_err(), "Second download should fail");
-    assert!(target_dir.exists(), "Target directory should still exist");
-
-    // Should still contain the avl package files (from successful download)
     assert!(
         target_dir.join("node.gno").exists(),
         "Previous successful download should be preserved"
@@ -340,46 +130,64 @@ async fn test_atomic_download_preserves_existing_on_failure() {
 #[tokio::test]
 async fn test_atomic_download_overwrites_existing_on_success() {
     let temp_dir = TempDir::new().unwrap();
-    let cache_dir = temp_dir.path().join("cache");
+    let cache = TempDir::new().unwrap();
     let target_dir = temp_dir.path().join("overwrite_test");
-
-    // Create existing package
     std::fs::create_dir_all(&target_dir).unwrap();
-    std::fs::write(target_dir.join("old.gno"), "package old\n").unwrap();
+    std::fs::write(target_dir.join("node.gno"), "package old\n").unwrap();
+    let package_manager = package_manager(&chain(), &cache);
 
-    let mock_server = MockRpcServer::new();
-    let package_manager = MockPackageManager::new(cache_dir, mock_server);
-
-    // Download new package should overwrite existing
     let result = package_manager
         .download_package_atomic("gno.land/p/demo/avl", &target_dir)
         .await;
 
-    assert!(result.is_ok(), "Download should succeed");
-    assert!(target_dir.exists(), "Target directory should exist");
-    assert!(
-        !target_dir.join("old.gno").exists(),
-        "Old file should be removed"
-    );
-    assert!(
-        target_dir.join("node.gno").exists(),
-        "New file should exist"
+    assert!(result.is_ok(), "Download should succeed: {:?}", result);
+    assert_eq!(
+        std::fs::read_to_string(target_dir.join("node.gno")).unwrap(),
+        NODE_GNO,
+        "Old content should be replaced"
     );
     assert!(
         target_dir.join("tree.gno").exists(),
         "New file should exist"
     );
+    assert_eq!(
+        leftover_staging_dirs(temp_dir.path()),
+        0,
+        "The replaced copy should be removed"
+    );
 }
 
 #[tokio::test]
-async fn test_concurrent_atomic_downloads() {
+async fn test_atomic_download_waits_for_the_target_lock() {
     let temp_dir = TempDir::new().unwrap();
-    let cache_dir = temp_dir.path().join("cache");
+    let cache = TempDir::new().unwrap();
+    let target_dir = temp_dir.path().join("avl");
+    let package_manager = package_manager(&chain(), &cache);
+
+    let held = FileLock::for_target(&target_dir, || {}).await.unwrap();
+    let download = tokio::spawn({
+        let target_dir = target_dir.clone();
+        async move {
+            package_manager
+                .download_package_atomic("gno.land/p/demo/avl", &target_dir)
+                .await
+        }
+    });
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(!download.is_finished());
+    assert!(!target_dir.exists());
+
+    drop(held);
+    download.await.unwrap().unwrap();
+    assert!(target_dir.join("node.gno").exists());
+}
 
-    let mock_server = MockRpcServer::new();
-    let package_manager = Arc::new(MockPackageManager::new(cache_dir, mock_server));
+#[tokio::test]
+async fn test_concurrent_atomic_downloads() {
+    let temp_dir = TempDir::new().unwrap();
+    let cache = TempDir::new().unwrap();
+    let package_manager = Arc::new(package_manager(&chain(), &cache));
 
-    // Test concurrent downloads to different directories
     let handles: Vec<_> = (0..3)
         .map(|i| {
             let pm = Arc::clone(&package_manager);
@@ -391,12 +199,9 @@ async fn test_concurrent_atomic_downloads() {
         })
         .collect();
 
-    // Wait for all downloads to complete
     let results: Vec<_> = futures::future::join_all(handles).await;
-
-    // All downloads should succeed
     for (i, result) in results.into_iter().enumerate() {
-        let download_result = result.unwrap(); // unwrap the JoinResult
+        let download_result = result.unwrap();
         assert!(
             download_result.is_ok(),
             "Concurrent download {} should succeed",
@@ -404,108 +209,207 @@ async fn test_concurrent_atomic_downloads() {
         );
 
         let target = temp_dir.path().join(format!("concurrent_{}", i));
-        assert!(target.exists(), "Target directory {} should exist", i);
         assert!(
             target.join("node.gno").exists(),
             "node.gno should exist in dir {}",
             i
         );
     }
+    assert_eq!(leftover_staging_dirs(temp_dir.path()), 0);
 }
 
 #[tokio::test]
 async fn test_atomic_download_validation_failure() {
     let temp_dir = TempDir::new().unwrap();
-    let cache_dir = temp_dir.path().join("cache");
+    let cache = TempDir::new().unwrap();
     let target_dir = temp_dir.path().join("invalid_package");
+    let package_manager = package_manager(&chain(), &cache);
 
-    let mock_server = MockRpcServer::new();
-    let package_manager = MockPackageManager::new(cache_dir, mock_server);
-
-    // Create a custom implementation that creates invalid package
-    struct InvalidMockPackageManager {
-        inner: MockPackageManager,
-    }
-
-    impl InvalidMockPackageManager {
-        async fn download_package_atomic_invalid(
-            &self,
-            _pkg_path: &str,
-            target_dir: &std::path::Path,
-        ) -> Result<(), PackageManagerError> {
-            use std::time::{SystemTime, UNIX_EPOCH};
-
-            let timestamp = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_nanos();
-            let temp_dir_name = format!(
-                "{}_tmp_{}",
-                target_dir
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("package"),
-                timestamp
-            );
-
-            let temp_dir = if let Some(parent) = target_dir.parent() {
-                parent.join(temp_dir_name)
-            } else {
-                PathBuf::from(temp_dir_name)
-            };
-
-            struct TempDirGuard(PathBuf);
-            impl Drop for TempDirGuard {
-                fn drop(&mut self) {
-                    if self.0.exists() {
-                        let _ = std::fs::remove_dir_all(&self.0);
-                    }
-                }
-            }
-            let _guard = TempDirGuard(temp_dir.clone());
-
-            // Create temp dir but no .gno files (will fail validation)
-            std::fs::create_dir_all(&temp_dir).map_err(PackageManagerError::Io)?;
-            std::fs::write(temp_dir.join("README.md"), "Not a gno file")
-                .map_err(PackageManagerError::Io)?;
-
-            // This should fail validation
-            self.inner.validate_package(&temp_dir).await?;
-
-            Ok(())
-        }
-    }
-
-    let invalid_manager = InvalidMockPackageManager {
-        inner: package_manager,
-    };
-
-    // Test download with validation failure
-    let result = invalid_manager
-        .download_package_atomic_invalid("gno.land/p/demo/invalid", &target_dir)
-        .await;
+    let err = package_manager
+        .download_package_atomic("gno.land/p/demo/invalid", &target_dir)
+        .await
+        .unwrap_err();
 
-    assert!(result.is_err(), "Download should fail validation");
+    assert_eq!(err.kind(), ErrorKind::Integrity, "{}", err);
     assert!(
         !target_dir.exists(),
         "Target directory should not exist after validation failure"
     );
+    assert_eq!(
+        leftover_staging_dirs(temp_dir.path()),
+        0,
+        "No temporary directories should remain after validation failure"
+    );
+}
+
+/// `avl` whose second file fails to download
+fn failing_chain() -> FakeChain {
+    let chain = FakeChain::new().with_package(
+        "gno.land/p/demo/avl",
+        &[
+            ("avl.gno", "package avl // v2"),
+            ("node.gno", "package avl"),
+        ],
+    );
+    chain.fail_package("gno.land/p/demo/avl/node.gno", 10, Failure::Status(500));
+    chain
+}
 
-    // Check that no temporary directories are left behind
-    let parent_dir = target_dir.parent().unwrap();
-    let entries: Vec<_> = std::fs::read_dir(parent_dir)
+fn leftover_staging_dirs(dir: &std::path::Path) -> usize {
+    std::fs::read_dir(dir)
         .unwrap()
         .filter_map(Result::ok)
-        .collect();
-
-    let temp_dirs: Vec<_> = entries
-        .iter()
         .filter(|entry| entry.file_name().to_string_lossy().contains("_tmp_"))
-        .collect();
+        .count()
+}
 
+#[tokio::test]
+async fn test_failed_download_leaves_the_target_untouched() {
+    let chain = failing_chain();
+    let url = chain.spawn();
+    let cache = TempDir::new().unwrap();
+    let root = TempDir::new().unwrap();
+    let target = root.path().join("avl");
+    std::fs::create_dir_all(&target).unwrap();
+    std::fs::write(target.join("avl.gno"), "package avl // v1").unwrap();
+
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+    pm.download_package("gno.land/p/demo/avl", &target)
+        .await
+        .unwrap_err();
     assert_eq!(
-        temp_dirs.len(),
-        0,
-        "No temporary directories should remain after validation failure"
+        std::fs::read_to_string(target.join("avl.gno")).unwrap(),
+        "package avl // v1"
+    );
+
+    let new_target = root.path().join("new");
+    pm.download_package("gno.land/p/demo/avl", &new_target)
+        .await
+        .unwrap_err();
+    assert!(!new_target.exists());
+    assert_eq!(leftover_staging_dirs(root.path()), 0);
+
+    // the deprecated direct path writes what it fetched before failing
+    let pm = pm.with_unsafe_direct(true);
+    pm.download_package("gno.land/p/demo/avl", &target)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        std::fs::read_to_string(target.join("avl.gno")).unwrap(),
+        "package avl // v2"
+    );
+}
+
+#[tokio::test]
+async fn test_download_into_existing_dir_keeps_other_files() {
+    let chain = FakeChain::new().with_package(
+        "gno.land/p/demo/avl",
+        &[
+            ("avl.gno", "package avl // v2"),
+            ("node.gno", "package avl"),
+        ],
+    );
+    let url = chain.spawn();
+    let cache = TempDir::new().unwrap();
+    let target = TempDir::new().unwrap();
+    std::fs::write(target.path().join("avl.gno"), "package avl // v1").unwrap();
+    std::fs::write(target.path().join("README.md"), "local notes").unwrap();
+
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+    pm.download_package("gno.land/p/demo/avl", target.path())
+        .await
+        .unwrap();
+    assert_eq!(
+        std::fs::read_to_string(target.path().join("avl.gno")).unwrap(),
+        "package avl // v2"
     );
+    assert!(target.path().join("node.gno").exists());
+    assert!(target.path().join("README.md").exists());
+    assert_eq!(leftover_staging_dirs(target.path().parent().unwrap()), 0);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_synced_downloads_normalize_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let chain = FakeChain::new().with_package(
+        "gno.land/p/demo/avl",
+        &[
+            ("avl.gno", "package avl // v2"),
+            ("node.gno", "package avl"),
+        ],
+    );
+    let cache = TempDir::new().unwrap();
+    let target = TempDir::new().unwrap();
+    let avl = target.path().join("avl.gno");
+    std::fs::write(&avl, "package avl // v1").unwrap();
+    std::fs::set_permissions(&avl, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let pm = package_manager(&chain, &cache).with_fsync(true);
+    pm.download_package("gno.land/p/demo/avl", target.path())
+        .await
+        .unwrap();
+    for file in ["avl.gno", "node.gno"] {
+        let mode = std::fs::metadata(target.path().join(file))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o644, "{}", file);
+    }
+    assert_eq!(std::fs::read_to_string(&avl).unwrap(), "package avl // v2");
+}
+
+/// A directory on another filesystem than the temp dir, if there is one
+#[cfg(unix)]
+fn other_filesystem(than: &std::path::Path) -> Option<TempDir> {
+    use std::os::unix::fs::MetadataExt;
+
+    let shm = std::path::Path::new("/dev/shm");
+    let other = std::fs::metadata(shm).ok()?.dev() != std::fs::metadata(than).ok()?.dev();
+    other.then(|| TempDir::new_in(shm).unwrap())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_staging_on_another_filesystem_copies_and_swaps() {
+    let root = TempDir::new().unwrap();
+    let Some(staging) = other_filesystem(root.path()) else {
+        eprintln!("skipping: no second filesystem to stage on");
+        return;
+    };
+    let chain = FakeChain::new().with_package(
+        "gno.land/p/demo/avl",
+        &[
+            ("avl.gno", "package avl // v2"),
+            ("node.gno", "package avl"),
+        ],
+    );
+    let url = chain.spawn();
+    let cache = TempDir::new().unwrap();
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf())
+        .with_quiet(true)
+        .with_staging_dir(staging.path());
+
+    // a new target is swapped in as a whole
+    let target = root.path().join("avl");
+    pm.download_package_atomic("gno.land/p/demo/avl", &target)
+        .await
+        .unwrap();
+    assert!(target.join("node.gno").exists());
+
+    // files of an existing one one by one
+    let existing = root.path().join("existing");
+    std::fs::create_dir_all(&existing).unwrap();
+    std::fs::write(existing.join("avl.gno"), "package avl // v1").unwrap();
+    pm.download_package("gno.land/p/demo/avl", &existing)
+        .await
+        .unwrap();
+    assert_eq!(
+        std::fs::read_to_string(existing.join("avl.gno")).unwrap(),
+        "package avl // v2"
+    );
+
+    assert_eq!(std::fs::read_dir(root.path()).unwrap().count(), 2);
+    assert_eq!(std::fs::read_dir(staging.path()).unwrap().count(), 0);
 }
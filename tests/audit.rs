@@ -0,0 +1,181 @@
+use gget::audit::{Audit, FindingKind};
+use gget::fetch::PackageManager;
+use gget::report::{render, ReportFormat};
+use gget::testing::FakeChain;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+fn write(root: &Path, dir: &str, file: &str, source: &str) {
+    let dir = root.join(dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join(file), source).unwrap();
+}
+
+fn listed(audit: &Audit, kind: FindingKind) -> Vec<(&str, &str)> {
+    audit
+        .of_kind(kind)
+        .map(|f| (f.package.as_str(), f.detail.as_str()))
+        .collect()
+}
+
+#[test]
+fn test_duplicate_package_paths() {
+    let dir = tempdir().unwrap();
+    write(
+        dir.path(),
+        "gno.land/p/demo/avl",
+        "avl.gno",
+        "package avl\n",
+    );
+    // a fork declaring the same path through its gno.mod
+    write(dir.path(), "fork/avl", "avl.gno", "package avl\n");
+    write(
+        dir.path(),
+        "fork/avl",
+        "gno.mod",
+        "module gno.land/p/demo/avl\n",
+    );
+
+    let audit = Audit::scan(dir.path()).unwrap();
+    assert_eq!(
+        listed(&audit, FindingKind::Duplicate),
+        [(
+            "gno.land/p/demo/avl",
+            "declared by fork/avl, gno.land/p/demo/avl"
+        )]
+    );
+    assert_eq!(audit.findings.len(), 1);
+}
+
+#[test]
+fn test_cycles_and_missing_imports() {
+    let dir = tempdir().unwrap();
+    write(
+        dir.path(),
+        "gno.land/p/demo/a",
+        "a.gno",
+        "package a\n\nimport (\n\t\"gno.land/p/demo/b\"\n\t\"gno.land/p/demo/ufmt\"\n)\n",
+    );
+    write(
+        dir.path(),
+        "gno.land/p/demo/b",
+        "b.gno",
+        "package b\n\nimport \"gno.land/p/demo/a\"\n",
+    );
+    write(
+        dir.path(),
+        "gno.land/r/demo/app",
+        "app_test.gno",
+        "package app\n\nimport \"gno.land/p/demo/ufmt\"\n",
+    );
+
+    let audit = Audit::scan(dir.path()).unwrap();
+    assert_eq!(
+        listed(&audit, FindingKind::Cycle),
+        [
+            (
+                "gno.land/p/demo/a",
+                "imports gno.land/p/demo/b, which import it back"
+            ),
+            (
+                "gno.land/p/demo/b",
+                "imports gno.land/p/demo/a, which import it back"
+            ),
+        ]
+    );
+    assert_eq!(
+        listed(&audit, FindingKind::Missing),
+        [(
+            "gno.land/p/demo/ufmt",
+            "imported by gno.land/p/demo/a, gno.land/r/demo/app"
+        )]
+    );
+
+    let human = render(&audit, ReportFormat::Human).unwrap();
+    assert!(human.contains("3 warnings under"), "{}", human);
+    assert!(human.contains("(not compared with the chain)"));
+    assert!(human.contains("  warning[missing] gno.land/p/demo/ufmt: imported by"));
+    let json = render(&audit, ReportFormat::Json).unwrap();
+    assert!(json.contains("\"kind\": \"cycle\""), "{}", json);
+}
+
+#[tokio::test]
+async fn test_local_copies_differing_from_the_chain() {
+    let chain = FakeChain::new()
+        .with_package(
+            "gno.land/p/demo/avl",
+            &[("avl.gno", "package avl\n"), ("node.gno", "package avl\n")],
+        )
+        .with_package("gno.land/p/demo/ufmt", &[("ufmt.gno", "package ufmt\n")]);
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+
+    let dir = tempdir().unwrap();
+    write(
+        dir.path(),
+        "gno.land/p/demo/avl",
+        "avl.gno",
+        "package avl\n\n// patched\n",
+    );
+    write(
+        dir.path(),
+        "gno.land/p/demo/avl",
+        "extra.gno",
+        "package avl\n",
+    );
+    write(
+        dir.path(),
+        "gno.land/p/demo/ufmt",
+        "ufmt.gno",
+        "package ufmt\n",
+    );
+    // not deployed yet
+    write(
+        dir.path(),
+        "gno.land/r/demo/app",
+        "app.gno",
+        "package app\n",
+    );
+
+    let mut audit = Audit::scan(dir.path()).unwrap();
+    assert!(audit.is_clean());
+    audit.compare_with_chain(&pm).await.unwrap();
+
+    assert!(audit.chain_checked);
+    assert_eq!(
+        listed(&audit, FindingKind::Shadowed),
+        [(
+            "gno.land/p/demo/avl",
+            "gno.land/p/demo/avl: avl.gno differs, extra.gno is not on chain, \
+             node.gno is only on chain"
+        )]
+    );
+    assert_eq!(audit.findings.len(), 1);
+}
+
+#[tokio::test]
+async fn test_clean_tree() {
+    let chain =
+        FakeChain::new().with_package("gno.land/p/demo/ufmt", &[("ufmt.gno", "package ufmt\n")]);
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+
+    let dir = tempdir().unwrap();
+    write(
+        dir.path(),
+        "gno.land/p/demo/ufmt",
+        "ufmt.gno",
+        "package ufmt\n",
+    );
+
+    let mut audit = Audit::scan(dir.path()).unwrap();
+    audit.compare_with_chain(&pm).await.unwrap();
+    assert!(audit.is_clean());
+    assert_eq!(
+        render(&audit, ReportFormat::Human).unwrap(),
+        format!("No problems found under {}\n", dir.path().display())
+    );
+}
@@ -0,0 +1,118 @@
+use async_trait::async_trait;
+use gget::fetch::PackageManager;
+use gget::query::{RpcParams, RpcRequest};
+use gget::rpc::{FailoverRpcClient, RpcClient, RpcClientError};
+use gget::testing::{Failure, FakeChain};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::tempdir;
+
+/// Answers after a short delay, or with 429 when `overloaded`, counting
+/// requests and the most it had in flight at once
+#[derive(Default)]
+struct Endpoint {
+    overloaded: bool,
+    calls: AtomicUsize,
+    in_flight: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+#[async_trait]
+impl RpcClient for Endpoint {
+    async fn send(&self, _request: &RpcRequest) -> Result<Vec<u8>, RpcClientError> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak.fetch_max(now, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        match self.overloaded {
+            true => Err(RpcClientError::TooManyRequests),
+            false => Ok(b"{}".to_vec()),
+        }
+    }
+}
+
+fn request() -> RpcRequest {
+    RpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: 1,
+        method: "abci_query".to_string(),
+        params: RpcParams {
+            path: "vm/qfile".to_string(),
+            data: "gno.land/p/demo/avl".to_string(),
+            height: None,
+        },
+    }
+}
+
+async fn send_all(client: &dyn RpcClient, requests: usize) {
+    let request = request();
+    let sends = (0..requests).map(|_| client.send(&request));
+    for result in futures::future::join_all(sends).await {
+        result.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_requests_overflow_once_an_endpoint_is_at_its_limit() {
+    let first = Arc::new(Endpoint::default());
+    let second = Arc::new(Endpoint::default());
+    let client = FailoverRpcClient::new(vec![first.clone(), second.clone()])
+        .with_max_in_flight(2)
+        .unwrap();
+
+    send_all(client.as_ref(), 10).await;
+    assert_eq!(first.peak.load(Ordering::SeqCst), 2);
+    assert_eq!(second.peak.load(Ordering::SeqCst), 2);
+    assert_eq!(
+        first.calls.load(Ordering::SeqCst) + second.calls.load(Ordering::SeqCst),
+        10
+    );
+}
+
+#[tokio::test]
+async fn test_overloaded_endpoint_sheds_load_to_the_next() {
+    let first = Arc::new(Endpoint {
+        overloaded: true,
+        ..Default::default()
+    });
+    let second = Arc::new(Endpoint::default());
+    let client = FailoverRpcClient::new(vec![first.clone(), second.clone()])
+        .with_max_in_flight(4)
+        .unwrap();
+
+    send_all(client.as_ref(), 20).await;
+    // every request ends up on the healthy endpoint, and the overloaded
+    // one only sees what it took before its share shrank
+    assert_eq!(second.calls.load(Ordering::SeqCst), 20);
+    assert!(first.calls.load(Ordering::SeqCst) < 20);
+    assert!(first.peak.load(Ordering::SeqCst) <= 4);
+}
+
+#[test]
+fn test_single_endpoints_have_no_limit_to_set() {
+    assert!(Endpoint::default().with_max_in_flight(2).is_none());
+}
+
+#[tokio::test]
+async fn test_too_many_requests_fails_over() {
+    let chain =
+        || FakeChain::new().with_package("gno.land/p/demo/avl", &[("avl.gno", "package avl\n")]);
+    let (busy, healthy) = (chain(), chain());
+    busy.fail_next(100, Failure::Status(429));
+    let cache = tempdir().unwrap();
+    let target = tempdir().unwrap();
+
+    let pm = PackageManager::builder()
+        .endpoints([busy.spawn(), healthy.spawn()])
+        .cache_dir(cache.path())
+        .build()
+        .unwrap()
+        .with_quiet(true);
+    pm.download_package("gno.land/p/demo/avl", target.path())
+        .await
+        .unwrap();
+    assert!(target.path().join("avl.gno").exists());
+    assert!(!busy.queries().is_empty());
+}
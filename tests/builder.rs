@@ -0,0 +1,322 @@
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
+use gget::cache::{AsyncStorage, CacheError, MemoryStorage, NoopCache};
+use gget::fetch::{PackageManager, PackageManagerError};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tempfile::tempdir;
+use warp::Filter;
+
+/// Serves `gno.land/p/demo/hello` as a single `hello.gno`, recording the
+/// user agent of every request
+fn spawn_node() -> (String, Arc<Mutex<Vec<Option<String>>>>) {
+    let agents = Arc::new(Mutex::new(Vec::new()));
+    let seen = agents.clone();
+    let route = warp::post()
+        .and(warp::header::optional::<String>("user-agent"))
+        .and(warp::body::json())
+        .map(move |agent: Option<String>, request: Value| {
+            seen.lock().unwrap().push(agent);
+            let data = general_purpose::STANDARD
+                .decode(request["params"]["data"].as_str().unwrap())
+                .unwrap();
+            let body = if String::from_utf8(data).unwrap().ends_with(".gno") {
+                "package hello"
+            } else {
+                "hello.gno\n"
+            };
+            warp::reply::json(&json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "result": { "response": { "ResponseBase": {
+                    "Error": null,
+                    "Data": general_purpose::STANDARD.encode(body),
+                    "Log": "",
+                } } }
+            }))
+        });
+    let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    (format!("http://{}", addr), agents)
+}
+
+#[tokio::test]
+async fn test_builder_configures_the_http_client() {
+    let (url, agents) = spawn_node();
+    let cache = tempdir().unwrap();
+    let target = tempdir().unwrap();
+
+    let pm = PackageManager::builder()
+        .endpoint(&url)
+        .cache_dir(cache.path())
+        .cache_ttl(Duration::from_secs(60))
+        .max_cache_entries(10)
+        .user_agent("gget-test/1.0")
+        .timeout(Duration::from_secs(5))
+        .build()
+        .unwrap()
+        .with_quiet(true);
+    assert_eq!(pm.rpc_endpoint(), url);
+
+    pm.download_package("gno.land/p/demo/hello", target.path())
+        .await
+        .unwrap();
+    let agents = agents.lock().unwrap();
+    assert!(!agents.is_empty());
+    assert!(agents
+        .iter()
+        .all(|agent| agent.as_deref() == Some("gget-test/1.0")));
+}
+
+#[tokio::test]
+async fn test_requests_go_through_the_proxy() {
+    let (proxy, agents) = spawn_node();
+    let cache = tempdir().unwrap();
+    let target = tempdir().unwrap();
+
+    // the endpoint only resolves through the proxy
+    let pm = PackageManager::builder()
+        .endpoint("http://rpc.gno.invalid:26657")
+        .cache_dir(cache.path())
+        .proxy(&proxy)
+        .pool_max_idle_per_host(4)
+        .pool_idle_timeout(Duration::from_secs(30))
+        .gzip(true)
+        .build()
+        .unwrap()
+        .with_quiet(true);
+
+    pm.download_package("gno.land/p/demo/hello", target.path())
+        .await
+        .unwrap();
+    assert!(!agents.lock().unwrap().is_empty());
+    assert!(target.path().join("hello.gno").exists());
+}
+
+#[tokio::test]
+async fn test_http2_without_upgrade() {
+    let (url, agents) = spawn_node();
+    let cache = tempdir().unwrap();
+    let target = tempdir().unwrap();
+
+    let pm = PackageManager::builder()
+        .endpoint(&url)
+        .cache_dir(cache.path())
+        .http2_prior_knowledge(true)
+        .keep_alive(None)
+        .build()
+        .unwrap()
+        .with_quiet(true);
+
+    pm.download_package("gno.land/p/demo/hello", target.path())
+        .await
+        .unwrap();
+    assert!(!agents.lock().unwrap().is_empty());
+}
+
+#[test]
+fn test_unusable_http_settings_fail_the_build() {
+    let dir = tempdir().unwrap();
+    let not_pem = dir.path().join("ca.pem");
+    fs::write(&not_pem, "not a certificate").unwrap();
+
+    let Err(err) = PackageManager::builder().root_certificate(&not_pem).build() else {
+        panic!("built with an invalid certificate");
+    };
+    assert!(err.to_string().contains("no PEM certificates"), "{}", err);
+    assert!(PackageManager::builder()
+        .root_certificate(dir.path().join("missing.pem"))
+        .build()
+        .is_err());
+    assert!(PackageManager::builder()
+        .proxy("not a url")
+        .build()
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_unreachable_endpoint_fails_over_to_the_next() {
+    let (url, _) = spawn_node();
+    let cache = tempdir().unwrap();
+    let target = tempdir().unwrap();
+
+    let pm = PackageManager::builder()
+        .endpoints(["http://127.0.0.1:1", url.as_str()])
+        .cache_dir(cache.path())
+        .connect_timeout(Duration::from_secs(1))
+        .build()
+        .unwrap()
+        .with_quiet(true);
+    assert_eq!(pm.rpc_endpoint(), "http://127.0.0.1:1");
+
+    pm.download_package("gno.land/p/demo/hello", target.path())
+        .await
+        .unwrap();
+    assert_eq!(
+        fs::read_to_string(target.path().join("hello.gno")).unwrap(),
+        "package hello"
+    );
+}
+
+#[tokio::test]
+async fn test_offline_serves_only_from_the_cache() {
+    let (url, agents) = spawn_node();
+    let cache = tempdir().unwrap();
+
+    let online = PackageManager::builder()
+        .endpoint(&url)
+        .cache_dir(cache.path())
+        .build()
+        .unwrap()
+        .with_quiet(true);
+    online
+        .download_package("gno.land/p/demo/hello", tempdir().unwrap().path())
+        .await
+        .unwrap();
+    let requests = agents.lock().unwrap().len();
+
+    let offline = PackageManager::builder()
+        .endpoint(&url)
+        .cache_dir(cache.path())
+        .offline(true)
+        .build()
+        .unwrap()
+        .with_quiet(true);
+    let target = tempdir().unwrap();
+    offline
+        .download_package("gno.land/p/demo/hello", target.path())
+        .await
+        .unwrap();
+    assert!(target.path().join("hello.gno").exists());
+    assert_eq!(agents.lock().unwrap().len(), requests);
+
+    let err = offline
+        .download_package("gno.land/p/demo/other", target.path())
+        .await
+        .unwrap_err();
+    match err {
+        PackageManagerError::Download { source, .. } => {
+            assert!(matches!(*source, PackageManagerError::Offline))
+        }
+        other => panic!("expected a download error, got {:?}", other),
+    }
+}
+
+/// Storage shared between package managers, standing in for a remote store
+#[derive(Clone, Default)]
+struct SharedStorage(Arc<Mutex<HashMap<String, String>>>);
+
+#[async_trait]
+impl AsyncStorage for SharedStorage {
+    async fn get(&self, key: &str) -> Result<Option<String>, CacheError> {
+        Ok(self.0.lock().unwrap().get(key).cloned())
+    }
+
+    async fn set(&self, key: &str, value: &str) -> Result<(), CacheError> {
+        self.0
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    async fn cleanup(&self) -> Result<(), CacheError> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_custom_storage_backs_the_cache() {
+    let (url, agents) = spawn_node();
+    let storage = SharedStorage::default();
+    let cache = tempdir().unwrap();
+
+    let pm = PackageManager::builder()
+        .endpoint(&url)
+        .cache_dir(cache.path())
+        .storage(storage.clone())
+        .build()
+        .unwrap()
+        .with_quiet(true);
+    pm.download_package("gno.land/p/demo/hello", tempdir().unwrap().path())
+        .await
+        .unwrap();
+    assert!(!storage.0.lock().unwrap().is_empty());
+    assert_eq!(fs::read_dir(cache.path()).unwrap().count(), 0);
+    let requests = agents.lock().unwrap().len();
+
+    // a fresh manager is served from the shared storage
+    let offline = PackageManager::builder()
+        .endpoint(&url)
+        .storage(storage)
+        .offline(true)
+        .build()
+        .unwrap()
+        .with_quiet(true);
+    let target = tempdir().unwrap();
+    offline
+        .download_package("gno.land/p/demo/hello", target.path())
+        .await
+        .unwrap();
+    assert!(target.path().join("hello.gno").exists());
+    assert_eq!(agents.lock().unwrap().len(), requests);
+}
+
+#[tokio::test]
+async fn test_noop_cache_persists_nothing() {
+    let (url, agents) = spawn_node();
+    let cache = tempdir().unwrap();
+
+    for _ in 0..2 {
+        let pm = PackageManager::builder()
+            .endpoint(&url)
+            .cache_dir(cache.path())
+            .storage(NoopCache)
+            .build()
+            .unwrap()
+            .with_quiet(true);
+        pm.download_package("gno.land/p/demo/hello", tempdir().unwrap().path())
+            .await
+            .unwrap();
+    }
+
+    // both managers went to the network, and nothing was written to disk
+    assert_eq!(agents.lock().unwrap().len(), 4);
+    assert_eq!(fs::read_dir(cache.path()).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn test_memory_storage_writes_nothing_to_disk() {
+    let (url, agents) = spawn_node();
+    let cache = tempdir().unwrap();
+    let storage = MemoryStorage::new(Duration::from_secs(60));
+
+    let pm = PackageManager::builder()
+        .endpoint(&url)
+        .cache_dir(cache.path().join("cache"))
+        .storage(storage.clone())
+        .build()
+        .unwrap()
+        .with_quiet(true);
+    pm.download_package("gno.land/p/demo/hello", tempdir().unwrap().path())
+        .await
+        .unwrap();
+    assert!(!cache.path().join("cache").exists());
+    let requests = agents.lock().unwrap().len();
+
+    let offline = PackageManager::builder()
+        .endpoint(&url)
+        .storage(storage)
+        .offline(true)
+        .build()
+        .unwrap()
+        .with_quiet(true);
+    offline
+        .download_package("gno.land/p/demo/hello", tempdir().unwrap().path())
+        .await
+        .unwrap();
+    assert_eq!(agents.lock().unwrap().len(), requests);
+}
@@ -0,0 +1,70 @@
+use gget::cache::{AsyncStorage, DiskStorage, QUARANTINE_DIR};
+use gget::fetch::PackageManager;
+use gget::testing::FakeChain;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use tempfile::tempdir;
+
+/// Cuts every cache entry under `dir` in half
+fn truncate_entries(dir: &Path) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            truncate_entries(&path);
+        } else if path.extension().is_some_and(|ext| ext == "json") {
+            let data = fs::read(&path).unwrap();
+            fs::write(&path, &data[..data.len() / 2]).unwrap();
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_corrupted_cache_entries_are_fetched_again() {
+    let chain =
+        FakeChain::new().with_package("gno.land/p/demo/avl", &[("avl.gno", "package avl\n")]);
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+
+    let pm = PackageManager::new(Some(url.clone()), cache.path().to_path_buf()).with_quiet(true);
+    let target = tempdir().unwrap();
+    pm.download_package("gno.land/p/demo/avl", target.path())
+        .await
+        .unwrap();
+    assert_eq!(chain.file_queries(), 2);
+
+    truncate_entries(cache.path());
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+    let target = tempdir().unwrap();
+    pm.download_package("gno.land/p/demo/avl", target.path())
+        .await
+        .unwrap();
+    assert_eq!(chain.file_queries(), 4);
+    // the broken entries are kept aside
+    let quarantined = fs::read_dir(cache.path().join(QUARANTINE_DIR)).unwrap();
+    assert!(quarantined.count() > 0);
+    assert_eq!(
+        fs::read_to_string(target.path().join("avl.gno")).unwrap(),
+        "package avl\n"
+    );
+}
+
+#[tokio::test]
+async fn test_cleanup_drops_old_quarantined_entries() {
+    let cache = tempdir().unwrap();
+    let storage = DiskStorage::new(cache.path().to_path_buf(), Duration::from_secs(3600));
+    let quarantine = cache.path().join(QUARANTINE_DIR);
+    fs::create_dir_all(&quarantine).unwrap();
+    fs::write(quarantine.join("old.json"), "{\"trunc").unwrap();
+    fs::write(quarantine.join("new.json"), "{\"trunc").unwrap();
+    fs::File::options()
+        .write(true)
+        .open(quarantine.join("old.json"))
+        .unwrap()
+        .set_modified(SystemTime::now() - Duration::from_secs(3 * 3600))
+        .unwrap();
+
+    storage.cleanup().await.unwrap();
+    assert!(!quarantine.join("old.json").exists());
+    assert!(quarantine.join("new.json").exists());
+}
@@ -0,0 +1,80 @@
+use gget::cache::HybridCache;
+use gget::fetch::PackageManager;
+use gget::testing::{download_options, FakeChain};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tempfile::tempdir;
+
+fn offline(url: &str, cache: &Path) -> PackageManager {
+    PackageManager::builder()
+        .endpoint(url)
+        .cache_dir(cache)
+        .offline(true)
+        .build()
+        .unwrap()
+        .with_quiet(true)
+}
+
+#[tokio::test]
+async fn test_seed_from_locked_tree() {
+    let chain = FakeChain::demo();
+    let url = chain.spawn();
+    let output = tempdir().unwrap();
+    let online_cache = tempdir().unwrap();
+    let pm =
+        PackageManager::new(Some(url.clone()), online_cache.path().to_path_buf()).with_quiet(true);
+    let options = download_options();
+    pm.download_with_deps_parallel("gno.land/r/demo/app", output.path(), options)
+        .await
+        .unwrap();
+    fs::write(
+        output.path().join("gno.land/r/demo/app/app.gno"),
+        "package app // edited\n",
+    )
+    .unwrap();
+
+    let cache = tempdir().unwrap();
+    let seeded = HybridCache::new(cache.path().to_path_buf(), Duration::from_secs(3600), 10)
+        .import_dir(output.path())
+        .await
+        .unwrap();
+    assert_eq!(seeded.packages, ["gno.land/p/demo/avl"]);
+    assert_eq!(seeded.files, 2);
+    assert_eq!(seeded.skipped, ["gno.land/r/demo/app"]);
+
+    let target = tempdir().unwrap();
+    offline(&url, cache.path())
+        .download_package("gno.land/p/demo/avl", target.path())
+        .await
+        .unwrap();
+    assert_eq!(
+        fs::read_to_string(target.path().join("node.gno")).unwrap(),
+        "package avl\n"
+    );
+}
+
+#[tokio::test]
+async fn test_seed_from_tree_without_lockfile() {
+    let vendor = tempdir().unwrap();
+    let avl = vendor.path().join("gno.land/p/demo/avl");
+    fs::create_dir_all(&avl).unwrap();
+    fs::write(avl.join("avl.gno"), "package avl\n").unwrap();
+    fs::write(avl.join("gno.mod"), "module gno.land/p/demo/avl\n").unwrap();
+    fs::write(vendor.path().join("gno.land/README.md"), "vendored\n").unwrap();
+
+    let cache = tempdir().unwrap();
+    let seeded = HybridCache::new(cache.path().to_path_buf(), Duration::from_secs(3600), 10)
+        .import_dir(vendor.path())
+        .await
+        .unwrap();
+    assert_eq!(seeded.packages, ["gno.land/p/demo/avl"]);
+    assert_eq!(seeded.files, 2);
+
+    let target = tempdir().unwrap();
+    offline("http://127.0.0.1:9", cache.path())
+        .download_package("gno.land/p/demo/avl", target.path())
+        .await
+        .unwrap();
+    assert!(target.path().join("gno.mod").exists());
+}
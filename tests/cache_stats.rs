@@ -0,0 +1,35 @@
+use gget::fetch::PackageManager;
+use gget::testing::{download_options, FakeChain};
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn test_download_summary_reports_cache_use() {
+    let chain = FakeChain::new().with_package(
+        "gno.land/p/demo/avl",
+        &[("avl.gno", "package avl\n"), ("node.gno", "package avl\n")],
+    );
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let options = download_options();
+
+    let pm = PackageManager::new(Some(url.clone()), cache.path().to_path_buf()).with_quiet(true);
+    let target = tempdir().unwrap();
+    let cold = pm
+        .download_with_deps_parallel("gno.land/p/demo/avl", target.path(), options.clone())
+        .await
+        .unwrap();
+    assert_eq!(cold.cache.hits(), 0);
+    assert!(cold.cache.misses > 0);
+    assert_eq!(cold.cache.writes, cold.cache.misses);
+
+    // a new process only has the disk cache to go on
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+    let target = tempdir().unwrap();
+    let warm = pm
+        .download_with_deps_parallel("gno.land/p/demo/avl", target.path(), options)
+        .await
+        .unwrap();
+    assert_eq!(warm.cache.misses, 0);
+    assert_eq!(warm.cache.storage_hits, cold.cache.misses);
+    assert!(warm.to_string().contains("100% hit rate"), "{}", warm);
+}
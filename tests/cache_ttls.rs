@@ -0,0 +1,63 @@
+use gget::cache::{CacheTtls, MemoryStorage};
+use gget::fetch::PackageManager;
+use gget::testing::FakeChain;
+use std::time::Duration;
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn test_file_lists_expire_before_contents() {
+    let chain = FakeChain::new().with_package(
+        "gno.land/p/demo/avl",
+        &[("avl.gno", "package avl\n"), ("node.gno", "package avl\n")],
+    );
+    let url = chain.spawn();
+    let storage = MemoryStorage::new(Duration::from_secs(3600));
+    let cache = tempdir().unwrap();
+    let ttls = CacheTtls {
+        files: Duration::from_millis(200),
+        ..CacheTtls::default()
+    };
+    let package_manager = || {
+        PackageManager::builder()
+            .endpoint(&url)
+            .cache_dir(cache.path())
+            .storage(storage.clone())
+            .cache_ttls(ttls)
+            .build()
+            .unwrap()
+            .with_quiet(true)
+    };
+
+    let target = tempdir().unwrap();
+    package_manager()
+        .download_package("gno.land/p/demo/avl", target.path())
+        .await
+        .unwrap();
+    assert_eq!(chain.file_queries(), 3);
+
+    // a new block, so the expired list can't be renewed as is
+    chain.advance();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    package_manager()
+        .download_package("gno.land/p/demo/avl", target.path())
+        .await
+        .unwrap();
+    assert_eq!(chain.file_queries(), 4);
+}
+
+#[tokio::test]
+async fn test_cache_ttl_leaves_the_negative_ttl_alone() {
+    let pm = PackageManager::builder()
+        .storage(MemoryStorage::new(Duration::from_secs(60)))
+        .negative_ttl(Duration::from_secs(7))
+        .cache_ttl(Duration::from_secs(60))
+        .build()
+        .unwrap();
+    assert_eq!(
+        *pm.cache().ttls(),
+        CacheTtls {
+            negative: Duration::from_secs(7),
+            ..CacheTtls::uniform(Duration::from_secs(60))
+        }
+    );
+}
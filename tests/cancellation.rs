@@ -0,0 +1,135 @@
+use gget::cancel::{Cancellation, Interrupted};
+use gget::fetch::{PackageManager, PackageManagerError};
+use gget::parallel::{DownloadError, DownloadManager, DownloadTask, PackageStats};
+use gget::testing::download_options;
+use gget::CancellationToken;
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tempfile::tempdir;
+use tokio::net::TcpListener;
+
+/// Starts an RPC endpoint that accepts connections but never answers
+async fn hanging_endpoint() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut open = Vec::new();
+        while let Ok((socket, _)) = listener.accept().await {
+            open.push(socket);
+        }
+    });
+    format!("http://{}", addr)
+}
+
+#[tokio::test]
+async fn test_cancellation_run() {
+    let cancellation = Cancellation::new();
+    assert_eq!(cancellation.run(async { 1 }).await, Ok(1));
+
+    let token = CancellationToken::new();
+    let cancellation = Cancellation::new().with_token(token.clone());
+    token.cancel();
+    assert_eq!(
+        cancellation.run(std::future::pending::<()>()).await,
+        Err(Interrupted::Cancelled)
+    );
+
+    let cancellation = Cancellation::new().with_timeout(Duration::from_millis(20));
+    assert_eq!(
+        cancellation.run(std::future::pending::<()>()).await,
+        Err(Interrupted::DeadlineExceeded)
+    );
+    assert_eq!(cancellation.check(), Err(Interrupted::DeadlineExceeded));
+}
+
+#[tokio::test]
+async fn test_deadline_stops_atomic_download_and_cleans_up() {
+    let endpoint = hanging_endpoint().await;
+    let cache = tempdir().unwrap();
+    let out = tempdir().unwrap();
+
+    let pm = PackageManager::new(Some(endpoint), cache.path().to_path_buf())
+        .with_quiet(true)
+        .with_deadline(Instant::now() + Duration::from_millis(200));
+
+    let started = Instant::now();
+    let result = pm
+        .download_package_atomic("gno.land/p/demo/avl", &out.path().join("avl"))
+        .await;
+
+    assert!(matches!(
+        result,
+        Err(PackageManagerError::Cancelled(
+            Interrupted::DeadlineExceeded
+        ))
+    ));
+    assert!(started.elapsed() < Duration::from_secs(5));
+    // neither the target nor the temporary directory is left behind
+    assert_eq!(fs::read_dir(out.path()).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn test_cancelled_dependency_download_writes_no_lockfile() {
+    let endpoint = hanging_endpoint().await;
+    let cache = tempdir().unwrap();
+    let out = tempdir().unwrap();
+
+    let token = CancellationToken::new();
+    let pm = PackageManager::new(Some(endpoint), cache.path().to_path_buf())
+        .with_quiet(true)
+        .with_cancellation(token.clone());
+
+    let canceller = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        token.cancel();
+    });
+
+    let result = pm
+        .download_with_deps_parallel("gno.land/r/demo/app", out.path(), download_options())
+        .await;
+    canceller.await.unwrap();
+
+    assert!(matches!(
+        result,
+        Err(PackageManagerError::Cancelled(Interrupted::Cancelled))
+    ));
+    assert!(!out.path().join("gget.lock").exists());
+}
+
+#[tokio::test]
+async fn test_cancelled_manager_starts_no_downloads() {
+    let token = CancellationToken::new();
+    token.cancel();
+    let manager = DownloadManager::new(2).with_cancellation(Cancellation::new().with_token(token));
+
+    for i in 0..3 {
+        manager
+            .queue_download(DownloadTask {
+                package_id: format!("pkg{}", i),
+                package_path: format!("gno.land/p/demo/pkg{}", i),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+    }
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let calls_clone = Arc::clone(&calls);
+    let download_fn = move |_task: DownloadTask| {
+        calls_clone.fetch_add(1, Ordering::SeqCst);
+        Box::pin(async { Ok(PackageStats::default()) })
+            as futures::future::BoxFuture<'static, Result<PackageStats, DownloadError>>
+    };
+
+    let summary = manager.process_queue(download_fn).await.unwrap();
+
+    assert_eq!(summary.successful, 0);
+    assert_eq!(summary.failed.len(), 3);
+    for failure in &summary.failed {
+        assert!(matches!(failure.error, DownloadError::Cancelled));
+        assert_eq!(failure.retry_count, 0);
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+}
@@ -0,0 +1,93 @@
+use gget::compat::{CompatChecker, GoConstruct};
+use gget::fetch::PackageManager;
+use gget::lint::{DiagnosticKind, Severity};
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn test_go_only_constructs_are_found_by_line() {
+    let mut checker = CompatChecker::new().unwrap();
+    let source = r#"package worker
+
+// #cgo LDFLAGS: -lm
+import "C"
+import "unsafe"
+
+//go:linkname now runtime.nanotime
+func now() int64
+
+func Run(jobs chan int) {
+	done := make(chan bool)
+	go func() {
+		jobs <- 1
+		done <- true
+	}()
+	select {
+	case <-done:
+	}
+}
+"#;
+
+    let found = checker.check_source(source).unwrap();
+    assert_eq!(
+        found,
+        vec![
+            (3, GoConstruct::Cgo),
+            (4, GoConstruct::Cgo),
+            (5, GoConstruct::Unsafe),
+            (7, GoConstruct::Directive),
+            (10, GoConstruct::Channel),
+            (11, GoConstruct::Channel),
+            (12, GoConstruct::Goroutine),
+            (13, GoConstruct::Channel),
+            (14, GoConstruct::Channel),
+            (16, GoConstruct::Select),
+            (17, GoConstruct::Channel),
+        ]
+    );
+}
+
+#[test]
+fn test_plain_gno_has_no_warnings() {
+    let mut checker = CompatChecker::new().unwrap();
+    let source = r#"package avl
+
+import "gno.land/p/demo/ufmt"
+
+// Go-style comments mentioning go: or channels are fine
+func Render(path string) string {
+	x := 1 - 2
+	return ufmt.Sprintf("%d <- %s", x, "C")
+}
+"#;
+    assert!(checker.check_source(source).unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_validation_reports_file_and_line() {
+    let dir = tempdir().unwrap();
+    let pkg = dir.path().join("gno.land/p/demo/worker");
+    fs::create_dir_all(&pkg).unwrap();
+    fs::write(pkg.join("ok.gno"), "package worker\n").unwrap();
+    fs::write(
+        pkg.join("spawn.gno"),
+        "package worker\n\nfunc Spawn(f func()) {\n\tgo f()\n}\n",
+    )
+    .unwrap();
+
+    let cache = tempdir().unwrap();
+    let report = PackageManager::new(None, cache.path().to_path_buf())
+        .validate_package(dir.path())
+        .await
+        .unwrap();
+
+    let warnings = &report.diagnostics;
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].file, pkg.join("spawn.gno"));
+    assert_eq!(warnings[0].line, 4);
+    assert_eq!(warnings[0].kind, DiagnosticKind::GoOnly);
+    assert_eq!(warnings[0].severity, Severity::Warning);
+    assert!(warnings[0].to_string().ends_with(
+        "spawn.gno:4: goroutine is not supported by Gno; the package will fail to deploy"
+    ));
+}
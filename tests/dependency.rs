@@ -1,4 +1,8 @@
-use gget::dependency::{DependencyResolver, PackageDependency};
+use gget::dependency::{
+    DependencyGraph, DependencyResolver, PackageDependency, SatResolver, TestFiles,
+};
+use gget::report::{render, ReportFormat};
+use gget::ErrorKind;
 use std::collections::{HashMap, HashSet};
 
 #[test]
@@ -12,7 +16,7 @@ fn test_dependency_resolver_creation() {
 
 #[test]
 fn test_extract_dependencies_simple() {
-    let mut resolver = DependencyResolver::new().unwrap();
+    let resolver = DependencyResolver::new().unwrap();
 
     let gno_source = r#"
         package main
@@ -38,7 +42,7 @@ fn test_extract_dependencies_simple() {
 
 #[test]
 fn test_extract_dependencies_with_aliases() {
-    let mut resolver = DependencyResolver::new().unwrap();
+    let resolver = DependencyResolver::new().unwrap();
 
     let gno_source = r#"
         package aliases
@@ -59,7 +63,7 @@ fn test_extract_dependencies_with_aliases() {
 
 #[test]
 fn test_extract_dependencies_blank_imports() {
-    let mut resolver = DependencyResolver::new().unwrap();
+    let resolver = DependencyResolver::new().unwrap();
 
     let gno_source = r#"
         package blank
@@ -78,9 +82,82 @@ fn test_extract_dependencies_blank_imports() {
     assert!(imports.contains("gno.land/p/demo/testutils"));
 }
 
+#[test]
+fn test_extract_dependencies_dot_imports() {
+    let resolver = DependencyResolver::new().unwrap();
+
+    let gno_source = r#"
+        package dotted
+        import . "gno.land/p/demo/testutils"
+        import (
+            . "gno.land/p/demo/avl"
+            "gno.land/p/demo/ufmt"
+        )
+    "#;
+
+    let (_, imports) = resolver.extract_dependencies(gno_source).unwrap();
+    assert_eq!(
+        imports,
+        HashSet::from([
+            "gno.land/p/demo/testutils".to_string(),
+            "gno.land/p/demo/avl".to_string(),
+            "gno.land/p/demo/ufmt".to_string(),
+        ])
+    );
+}
+
+#[test]
+fn test_extract_dependencies_raw_string_paths() {
+    let resolver = DependencyResolver::new().unwrap();
+
+    let gno_source = r#"
+        package raw
+        import `gno.land/p/demo/avl`
+        import (
+            u `gno.land/p/demo/ufmt`
+            . `gno.land/p/demo/testutils`
+            `strings`
+        )
+    "#;
+
+    let (_, imports) = resolver.extract_dependencies(gno_source).unwrap();
+    assert_eq!(
+        imports,
+        HashSet::from([
+            "gno.land/p/demo/avl".to_string(),
+            "gno.land/p/demo/ufmt".to_string(),
+            "gno.land/p/demo/testutils".to_string(),
+        ])
+    );
+}
+
+#[test]
+fn test_extract_dependencies_decodes_escapes() {
+    let resolver = DependencyResolver::new().unwrap();
+
+    let gno_source = r#"
+        package escaped
+        import (
+            "gno.land/p/demo/\x61vl"
+            "gno.land/p/demo/\u0075fmt"
+            "gno.land/p/demo/\164estutils"
+        )
+    "#;
+
+    let (_, imports) = resolver.extract_dependencies(gno_source).unwrap();
+    assert_eq!(
+        imports,
+        HashSet::from([
+            "gno.land/p/demo/avl".to_string(),
+            "gno.land/p/demo/ufmt".to_string(),
+            "gno.land/p/demo/testutils".to_string(),
+        ])
+    );
+}
+
 #[test]
 fn test_extract_dependencies_mixed_import_styles() {
-    let mut resolver = DependencyResolver::new().unwrap();
+    let resolver = DependencyResolver::new().unwrap();
 
     let gno_source = r#"
         package mixed
@@ -107,7 +184,7 @@ fn test_extract_dependencies_mixed_import_styles() {
 
 #[test]
 fn test_extract_dependencies_with_standard_library() {
-    let mut resolver = DependencyResolver::new().unwrap();
+    let resolver = DependencyResolver::new().unwrap();
 
     let gno_source = r#"
         package demo
@@ -129,7 +206,7 @@ fn test_extract_dependencies_with_standard_library() {
 
 #[test]
 fn test_extract_dependencies_single_import() {
-    let mut resolver = DependencyResolver::new().unwrap();
+    let resolver = DependencyResolver::new().unwrap();
 
     let gno_source = r#"
         package single
@@ -144,7 +221,7 @@ fn test_extract_dependencies_single_import() {
 
 #[test]
 fn test_extract_dependencies_no_imports() {
-    let mut resolver = DependencyResolver::new().unwrap();
+    let resolver = DependencyResolver::new().unwrap();
 
     let gno_source = r#"
         package standalone
@@ -173,6 +250,7 @@ fn test_deployment_order_simple_chain() {
                 set.insert("gno.land/p/demo/B".to_string());
                 set
             },
+            test_imports: HashSet::new(),
             instability: 0.0,
         },
     );
@@ -186,6 +264,7 @@ fn test_deployment_order_simple_chain() {
                 set.insert("gno.land/p/demo/C".to_string());
                 set
             },
+            test_imports: HashSet::new(),
             instability: 0.0,
         },
     );
@@ -195,12 +274,13 @@ fn test_deployment_order_simple_chain() {
         PackageDependency {
             name: "gno.land/p/demo/C".to_string(),
             imports: HashSet::new(),
+            test_imports: HashSet::new(),
             instability: 0.0,
         },
     );
 
     let resolver = DependencyResolver::new().unwrap();
-    let deployment_order = resolver.generate_deployment_order(&packages);
+    let deployment_order = resolver.generate_deployment_order(&packages).unwrap();
 
     assert_eq!(deployment_order.len(), 3);
 
@@ -243,6 +323,7 @@ fn test_deployment_order_complex_dependencies() {
                 set.insert("gno.land/p/demo/C".to_string());
                 set
             },
+            test_imports: HashSet::new(),
             instability: 0.0,
         },
     );
@@ -256,6 +337,7 @@ fn test_deployment_order_complex_dependencies() {
                 set.insert("gno.land/p/demo/D".to_string());
                 set
             },
+            test_imports: HashSet::new(),
             instability: 0.0,
         },
     );
@@ -269,6 +351,7 @@ fn test_deployment_order_complex_dependencies() {
                 set.insert("gno.land/p/demo/D".to_string());
                 set
             },
+            test_imports: HashSet::new(),
             instability: 0.0,
         },
     );
@@ -278,6 +361,7 @@ fn test_deployment_order_complex_dependencies() {
         PackageDependency {
             name: "gno.land/p/demo/D".to_string(),
             imports: HashSet::new(),
+            test_imports: HashSet::new(),
             instability: 0.0,
         },
     );
@@ -292,12 +376,13 @@ fn test_deployment_order_complex_dependencies() {
                 set.insert("gno.land/p/demo/D".to_string());
                 set
             },
+            test_imports: HashSet::new(),
             instability: 0.0,
         },
     );
 
     let resolver = DependencyResolver::new().unwrap();
-    let deployment_order = resolver.generate_deployment_order(&packages);
+    let deployment_order = resolver.generate_deployment_order(&packages).unwrap();
 
     assert_eq!(deployment_order.len(), 5);
 
@@ -351,6 +436,7 @@ fn test_deployment_order_cyclic_dependencies() {
                 set.insert("gno.land/p/demo/Y".to_string());
                 set
             },
+            test_imports: HashSet::new(),
             instability: 0.0,
         },
     );
@@ -364,12 +450,13 @@ fn test_deployment_order_cyclic_dependencies() {
                 set.insert("gno.land/p/demo/X".to_string());
                 set
             },
+            test_imports: HashSet::new(),
             instability: 0.0,
         },
     );
 
     let resolver = DependencyResolver::new().unwrap();
-    let deployment_order = resolver.generate_deployment_order(&packages);
+    let deployment_order = resolver.generate_deployment_order(&packages).unwrap();
 
     // Even with a cycle, should return all packages
     assert_eq!(deployment_order.len(), 2);
@@ -382,7 +469,7 @@ fn test_deployment_order_cyclic_dependencies() {
 
 #[test]
 fn test_parser_reuse_across_multiple_calls() {
-    let mut resolver = DependencyResolver::new().unwrap();
+    let resolver = DependencyResolver::new().unwrap();
 
     let source1 = r#"
         package pkg1
@@ -413,7 +500,7 @@ fn test_parser_reuse_across_multiple_calls() {
 
 #[test]
 fn test_invalid_gno_source() {
-    let mut resolver = DependencyResolver::new().unwrap();
+    let resolver = DependencyResolver::new().unwrap();
 
     // Completely invalid Go/Gno syntax
     let invalid_source = r#"
@@ -442,7 +529,7 @@ fn test_invalid_gno_source() {
 
 #[test]
 fn test_empty_source() {
-    let mut resolver = DependencyResolver::new().unwrap();
+    let resolver = DependencyResolver::new().unwrap();
 
     let result = resolver.extract_dependencies("");
     assert!(result.is_ok());
@@ -453,7 +540,7 @@ fn test_empty_source() {
 
 #[test]
 fn test_package_only_no_imports() {
-    let mut resolver = DependencyResolver::new().unwrap();
+    let resolver = DependencyResolver::new().unwrap();
 
     let source = r#"package mypackage"#;
 
@@ -463,3 +550,439 @@ fn test_package_only_no_imports() {
     assert_eq!(package_name, "mypackage");
     assert!(imports.is_empty());
 }
+
+#[test]
+fn test_closure_import_chain() {
+    use gget::dependency::DependencyClosure;
+
+    let mut closure = DependencyClosure::new("gno.land/r/demo/root");
+    closure.parents.insert(
+        "gno.land/p/demo/mid".to_string(),
+        "gno.land/r/demo/root".to_string(),
+    );
+    closure.parents.insert(
+        "gno.land/p/demo/leaf".to_string(),
+        "gno.land/p/demo/mid".to_string(),
+    );
+
+    assert_eq!(
+        closure.import_chain("gno.land/p/demo/leaf"),
+        vec!["gno.land/p/demo/mid", "gno.land/r/demo/root"]
+    );
+    assert!(closure.import_chain("gno.land/r/demo/root").is_empty());
+    assert_eq!(
+        closure.root_of("gno.land/p/demo/leaf"),
+        "gno.land/r/demo/root"
+    );
+}
+
+#[test]
+fn test_closure_dependent_counts() {
+    use gget::dependency::DependencyClosure;
+
+    // root -> a -> c, root -> b -> c, c -> d
+    let edges = [
+        ("root", vec!["a", "b"]),
+        ("a", vec!["c"]),
+        ("b", vec!["c"]),
+        ("c", vec!["d"]),
+        ("d", vec![]),
+    ];
+
+    let mut closure = DependencyClosure::new("root");
+    for (name, imports) in edges {
+        closure.packages.insert(
+            name.to_string(),
+            PackageDependency {
+                name: name.to_string(),
+                imports: imports.into_iter().map(String::from).collect(),
+                test_imports: HashSet::new(),
+                instability: 0.0,
+            },
+        );
+    }
+
+    let counts = closure.dependent_counts();
+    assert_eq!(counts["root"], 0);
+    assert_eq!(counts["a"], 1);
+    assert_eq!(counts["c"], 3);
+    assert_eq!(counts["d"], 4);
+    assert_eq!(closure.reverse_edges()["c"].len(), 2);
+}
+
+#[test]
+fn test_closure_deployment_waves() {
+    use gget::dependency::DependencyClosure;
+
+    // root -> a -> c, root -> b -> c, c -> d, plus a cycle x <-> y under root
+    let edges = [
+        ("root", vec!["a", "b", "x"]),
+        ("a", vec!["c"]),
+        ("b", vec!["c", "gno.land/p/outside"]),
+        ("c", vec!["d"]),
+        ("d", vec![]),
+        ("x", vec!["y"]),
+        ("y", vec!["x"]),
+    ];
+
+    let mut closure = DependencyClosure::new("root");
+    for (name, imports) in edges {
+        closure.packages.insert(
+            name.to_string(),
+            PackageDependency {
+                name: name.to_string(),
+                imports: imports.into_iter().map(String::from).collect(),
+                test_imports: HashSet::new(),
+                instability: 0.0,
+            },
+        );
+    }
+
+    assert_eq!(
+        closure.deployment_waves(),
+        vec![
+            vec!["d".to_string()],
+            vec!["c".to_string()],
+            vec!["a".to_string(), "b".to_string()],
+            // root waits on the cycle, so both end up in the last wave
+            vec!["root".to_string(), "x".to_string(), "y".to_string()],
+        ]
+    );
+}
+
+#[test]
+fn test_test_only_imports_are_kept_apart() {
+    let mut package = PackageDependency {
+        name: "gno.land/p/demo/app".to_string(),
+        imports: HashSet::new(),
+        test_imports: HashSet::new(),
+        instability: 0.0,
+    };
+    let set = |paths: &[&str]| paths.iter().map(|p| p.to_string()).collect::<HashSet<_>>();
+
+    package.add_imports(
+        "app_test.gno",
+        set(&["gno.land/p/demo/testutils", "gno.land/p/demo/avl"]),
+    );
+    package.add_imports("app_filetest.gno", set(&["gno.land/p/demo/urequire"]));
+    package.add_imports("app.gno", set(&["gno.land/p/demo/avl"]));
+
+    assert_eq!(package.imports, set(&["gno.land/p/demo/avl"]));
+    assert_eq!(
+        package.test_imports,
+        set(&["gno.land/p/demo/testutils", "gno.land/p/demo/urequire"])
+    );
+}
+
+#[test]
+fn test_directory_extraction_without_test_files() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("app.gno"),
+        "package app\n\nimport \"gno.land/p/demo/avl\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("app_test.gno"),
+        "package app\n\nimport \"gno.land/p/demo/testutils\"\n",
+    )
+    .unwrap();
+
+    let resolver = DependencyResolver::new().unwrap();
+    let packages = resolver
+        .extract_dependencies_from_directory(dir.path())
+        .unwrap();
+    assert!(packages["app"].imports.contains("gno.land/p/demo/avl"));
+    assert!(packages["app"]
+        .test_imports
+        .contains("gno.land/p/demo/testutils"));
+
+    let resolver = DependencyResolver::new()
+        .unwrap()
+        .with_test_files(TestFiles::Exclude);
+    let packages = resolver
+        .extract_dependencies_from_directory(dir.path())
+        .unwrap();
+    assert!(packages["app"].imports.contains("gno.land/p/demo/avl"));
+    assert!(packages["app"].test_imports.is_empty());
+}
+
+#[test]
+fn test_resolver_is_shared_across_threads() {
+    let resolver = DependencyResolver::new().unwrap();
+    let clone = resolver.clone();
+
+    std::thread::scope(|scope| {
+        for i in 0..8 {
+            let resolver = if i % 2 == 0 { &resolver } else { &clone };
+            scope.spawn(move || {
+                for j in 0..20 {
+                    let source = format!("package pkg{}\nimport \"gno.land/p/demo/dep{}\"\n", i, j);
+                    let (package, imports) = resolver.extract_dependencies(&source).unwrap();
+                    assert_eq!(package, format!("pkg{}", i));
+                    assert_eq!(
+                        imports,
+                        HashSet::from([format!("gno.land/p/demo/dep{}", j)])
+                    );
+                }
+            });
+        }
+    });
+}
+
+/// Packages importing each other, by path
+fn packages(edges: &[(&str, &[&str])]) -> HashMap<String, PackageDependency> {
+    edges
+        .iter()
+        .map(|(name, imports)| {
+            let package = PackageDependency {
+                name: name.to_string(),
+                imports: imports.iter().map(|i| i.to_string()).collect(),
+                test_imports: HashSet::new(),
+                instability: 0.0,
+            };
+            (name.to_string(), package)
+        })
+        .collect()
+}
+
+#[test]
+fn test_sat_strategy_orders_like_topological_sort() {
+    let packages = packages(&[
+        (
+            "gno.land/r/demo/app",
+            &["gno.land/p/demo/avl", "gno.land/p/demo/ufmt"],
+        ),
+        ("gno.land/p/demo/avl", &["gno.land/p/demo/ufmt"]),
+        ("gno.land/p/demo/ufmt", &[]),
+    ]);
+
+    let order = DependencyResolver::new()
+        .unwrap()
+        .with_strategy(SatResolver::new())
+        .generate_deployment_order(&packages)
+        .unwrap();
+    assert_eq!(
+        order,
+        [
+            "gno.land/p/demo/ufmt",
+            "gno.land/p/demo/avl",
+            "gno.land/r/demo/app"
+        ]
+    );
+}
+
+#[test]
+fn test_sat_strategy_drops_conflicting_optional_packages() {
+    let packages = packages(&[
+        ("gno.land/r/demo/app", &["gno.land/p/demo/avl"]),
+        ("gno.land/r/demo/plugin", &["gno.land/p/demo/avl2"]),
+        ("gno.land/p/demo/avl", &[]),
+        ("gno.land/p/demo/avl2", &[]),
+    ]);
+    let strategy = SatResolver::new()
+        .with_optional("gno.land/p/demo/avl2")
+        .with_optional("gno.land/r/demo/plugin")
+        .with_conflict("gno.land/p/demo/avl", "gno.land/p/demo/avl2");
+
+    let order = DependencyResolver::new()
+        .unwrap()
+        .with_strategy(strategy)
+        .generate_deployment_order(&packages)
+        .unwrap();
+    // the plugin needs what conflicts with a required package
+    assert_eq!(order, ["gno.land/p/demo/avl", "gno.land/r/demo/app"]);
+}
+
+#[test]
+fn test_sat_strategy_reports_required_conflicts() {
+    let packages = packages(&[
+        ("gno.land/r/demo/app", &["gno.land/p/demo/avl"]),
+        ("gno.land/r/demo/plugin", &["gno.land/p/demo/avl2"]),
+        ("gno.land/p/demo/avl", &[]),
+        ("gno.land/p/demo/avl2", &[]),
+    ]);
+    let strategy = SatResolver::new().with_conflict("gno.land/p/demo/avl", "gno.land/p/demo/avl2");
+
+    let err = DependencyResolver::new()
+        .unwrap()
+        .with_strategy(strategy)
+        .generate_deployment_order(&packages)
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Validation);
+    assert_eq!(
+        err.to_string(),
+        "No selection of packages meets the constraints: \
+         gno.land/p/demo/avl conflicts with gno.land/p/demo/avl2"
+    );
+}
+
+#[test]
+fn test_deployment_order_is_stable() {
+    let edges: &[(&str, &[&str])] = &[
+        ("gno.land/r/demo/zoo", &["gno.land/p/demo/avl"]),
+        (
+            "gno.land/r/demo/app",
+            &["gno.land/p/demo/avl", "gno.land/p/demo/ufmt"],
+        ),
+        ("gno.land/p/demo/ufmt", &[]),
+        ("gno.land/p/demo/avl", &[]),
+        ("gno.land/p/demo/json", &["gno.land/p/demo/ufmt"]),
+        // a cycle, left for last
+        ("gno.land/p/demo/b", &["gno.land/p/demo/a"]),
+        ("gno.land/p/demo/a", &["gno.land/p/demo/b"]),
+    ];
+    let expected = [
+        "gno.land/p/demo/avl",
+        "gno.land/p/demo/ufmt",
+        "gno.land/p/demo/json",
+        "gno.land/r/demo/app",
+        "gno.land/r/demo/zoo",
+        "gno.land/p/demo/a",
+        "gno.land/p/demo/b",
+    ];
+
+    let resolver = DependencyResolver::new().unwrap();
+    // every map iterates in its own order
+    for _ in 0..20 {
+        let order = resolver
+            .generate_deployment_order(&packages(edges))
+            .unwrap();
+        assert_eq!(order, expected);
+    }
+}
+
+#[test]
+fn test_reverse_edges_are_sorted() {
+    use gget::dependency::DependencyClosure;
+
+    for _ in 0..20 {
+        let mut closure = DependencyClosure::new("gno.land/r/demo/app");
+        closure.packages = packages(&[
+            ("gno.land/r/demo/c", &["gno.land/p/demo/avl"]),
+            ("gno.land/r/demo/a", &["gno.land/p/demo/avl"]),
+            ("gno.land/r/demo/b", &["gno.land/p/demo/avl"]),
+            ("gno.land/p/demo/avl", &[]),
+        ]);
+        assert_eq!(
+            closure.reverse_edges()["gno.land/p/demo/avl"],
+            [
+                "gno.land/r/demo/a",
+                "gno.land/r/demo/b",
+                "gno.land/r/demo/c"
+            ]
+        );
+    }
+}
+
+#[test]
+fn test_import_paths_shortest_first() {
+    // app imports ufmt directly, and through avl and pager; pager and list
+    // import each other
+    let packages = packages(&[
+        ("app", &["avl", "pager", "ufmt"]),
+        ("avl", &["ufmt"]),
+        ("pager", &["avl", "list"]),
+        ("list", &["pager", "ufmt"]),
+        ("ufmt", &[]),
+    ]);
+    let graph = DependencyGraph::from_packages(&packages, TestFiles::Exclude);
+
+    let paths = graph.import_paths("app", "ufmt", 100);
+    let joined: Vec<String> = paths.iter().map(|p| p.join(" ")).collect();
+    assert_eq!(
+        joined,
+        [
+            "app ufmt",
+            "app avl ufmt",
+            "app pager avl ufmt",
+            "app pager list ufmt",
+        ]
+    );
+
+    assert_eq!(graph.import_paths("app", "ufmt", 2).len(), 2);
+    assert!(graph.import_paths("ufmt", "app", 100).is_empty());
+    assert!(graph.import_paths("app", "missing", 100).is_empty());
+}
+
+#[test]
+fn test_import_paths_follow_test_imports_on_request() {
+    let mut packages = packages(&[("json", &[]), ("ufmt", &[])]);
+    packages
+        .get_mut("json")
+        .unwrap()
+        .test_imports
+        .insert("ufmt".to_string());
+
+    let without = DependencyGraph::from_packages(&packages, TestFiles::Exclude);
+    assert!(without.import_paths("json", "ufmt", 10).is_empty());
+    let with = DependencyGraph::from_packages(&packages, TestFiles::Include);
+    assert_eq!(with.import_paths("json", "ufmt", 10), [["json", "ufmt"]]);
+}
+
+#[test]
+fn test_graph_metrics() {
+    // app → {avl, pager}, pager → avl → ufmt, and list ⇄ tree on the side
+    let packages = packages(&[
+        ("app", &["avl", "pager", "gno.land/p/elsewhere"]),
+        ("pager", &["avl"]),
+        ("avl", &["ufmt"]),
+        ("ufmt", &[]),
+        ("list", &["tree"]),
+        ("tree", &["list", "ufmt"]),
+    ]);
+    let metrics = DependencyGraph::from_packages(&packages, TestFiles::Exclude).metrics();
+
+    assert_eq!(metrics.nodes, 6);
+    assert_eq!(metrics.edges, 7);
+    assert!((metrics.average_fan_out - 7.0 / 6.0).abs() < 1e-9);
+    assert_eq!(metrics.max_depth, 3);
+    assert_eq!(metrics.cycles, [["list", "tree"]]);
+
+    let depth: Vec<(&str, usize)> = metrics
+        .packages
+        .iter()
+        .map(|p| (p.package.as_str(), p.depth))
+        .collect();
+    assert_eq!(
+        depth,
+        [
+            ("app", 3),
+            ("avl", 1),
+            ("list", 1),
+            ("pager", 2),
+            ("tree", 1),
+            ("ufmt", 0)
+        ]
+    );
+
+    let most: Vec<(&str, usize)> = metrics
+        .most_depended_upon
+        .iter()
+        .map(|p| (p.package.as_str(), p.fan_in))
+        .collect();
+    assert_eq!(
+        most,
+        [
+            ("avl", 2),
+            ("ufmt", 2),
+            ("list", 1),
+            ("pager", 1),
+            ("tree", 1)
+        ]
+    );
+
+    let human = render(&metrics, ReportFormat::Human).unwrap();
+    assert!(human.starts_with("6 packages, 7 imports (1.17 per package), 3 deep\n"));
+    assert!(human.contains("Import cycles:\n  list, tree\n"));
+    assert!(human.contains("  avl (2 importers)\n"));
+}
+
+#[test]
+fn test_graph_metrics_of_nothing() {
+    let metrics = DependencyGraph::from_packages(&HashMap::new(), TestFiles::Exclude).metrics();
+    assert_eq!(metrics.nodes, 0);
+    assert_eq!(metrics.max_depth, 0);
+    assert_eq!(metrics.average_fan_out, 0.0);
+    assert!(metrics.cycles.is_empty());
+}
@@ -0,0 +1,173 @@
+use gget::dependency::SatResolver;
+use gget::deploy::{DeployError, DeployOptions, DeployPlan};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::tempdir;
+
+fn write_package(root: &Path, rel: &str, source: &str) {
+    let dir = root.join(rel);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("pkg.gno"), source).unwrap();
+}
+
+#[test]
+fn test_plan_follows_import_order() {
+    let dir = tempdir().unwrap();
+    write_package(
+        dir.path(),
+        "gno.land/r/demo/app",
+        "package app\n\nimport (\n\t\"gno.land/p/demo/avl\"\n\t\"gno.land/p/demo/avl/pager\"\n)\n",
+    );
+    write_package(
+        dir.path(),
+        "gno.land/p/demo/avl/pager",
+        "package pager\n\nimport \"gno.land/p/demo/avl\"\n",
+    );
+    write_package(
+        dir.path(),
+        "gno.land/p/demo/avl",
+        "package avl\n\nimport \"gno.land/p/demo/ufmt\"\n",
+    );
+
+    let plan = DeployPlan::from_dir(dir.path(), &DeployOptions::default()).unwrap();
+
+    let order: Vec<&str> = plan.steps.iter().map(|s| s.pkg_path.as_str()).collect();
+    assert_eq!(
+        order,
+        vec![
+            "gno.land/p/demo/avl",
+            "gno.land/p/demo/avl/pager",
+            "gno.land/r/demo/app"
+        ]
+    );
+    assert_eq!(
+        plan.steps[0].pkg_dir,
+        dir.path().join("gno.land/p/demo/avl")
+    );
+}
+
+#[test]
+fn test_gnomod_module_names_the_package() {
+    let dir = tempdir().unwrap();
+    write_package(dir.path(), "mylib", "package mylib\n");
+    fs::write(
+        dir.path().join("mylib/gno.mod"),
+        "module gno.land/p/me/mylib\n",
+    )
+    .unwrap();
+
+    let options = DeployOptions::default()
+        .with_key("my key")
+        .with_remote("https://rpc.test:443");
+    let plan = DeployPlan::from_dir(dir.path(), &options).unwrap();
+
+    assert_eq!(plan.steps.len(), 1);
+    assert_eq!(plan.steps[0].pkg_path, "gno.land/p/me/mylib");
+    assert_eq!(plan.steps[0].args.last().unwrap(), "my key");
+
+    let script = plan.to_script();
+    assert!(script.starts_with("#!/bin/sh\nset -e\n"));
+    assert!(script.contains("-pkgpath gno.land/p/me/mylib"));
+    assert!(script.contains("-remote https://rpc.test:443 'my key'"));
+}
+
+#[test]
+fn test_import_cycle_is_rejected() {
+    let dir = tempdir().unwrap();
+    write_package(
+        dir.path(),
+        "gno.land/p/demo/a",
+        "package a\n\nimport \"gno.land/p/demo/b\"\n",
+    );
+    write_package(
+        dir.path(),
+        "gno.land/p/demo/b",
+        "package b\n\nimport \"gno.land/p/demo/a\"\n",
+    );
+    write_package(dir.path(), "gno.land/p/demo/c", "package c\n");
+
+    match DeployPlan::from_dir(dir.path(), &DeployOptions::default()) {
+        Err(DeployError::ImportCycle(packages)) => {
+            assert_eq!(packages, vec!["gno.land/p/demo/a", "gno.land/p/demo/b"])
+        }
+        other => panic!("expected an import cycle, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_plan_with_sat_strategy() {
+    let dir = tempdir().unwrap();
+    write_package(
+        dir.path(),
+        "gno.land/r/demo/app",
+        "package app\n\nimport \"gno.land/p/demo/avl\"\n",
+    );
+    write_package(
+        dir.path(),
+        "gno.land/r/demo/legacy",
+        "package legacy\n\nimport \"gno.land/p/demo/avl/v0\"\n",
+    );
+    write_package(dir.path(), "gno.land/p/demo/avl", "package avl\n");
+    write_package(dir.path(), "gno.land/p/demo/avl/v0", "package avl\n");
+
+    let strategy = SatResolver::new()
+        .with_optional("gno.land/r/demo/legacy")
+        .with_optional("gno.land/p/demo/avl/v0")
+        .with_conflict("gno.land/p/demo/avl", "gno.land/p/demo/avl/v0");
+    let plan = DeployPlan::from_dir_with_strategy(dir.path(), &DeployOptions::default(), strategy)
+        .unwrap();
+
+    let order: Vec<&str> = plan.steps.iter().map(|s| s.pkg_path.as_str()).collect();
+    assert_eq!(order, ["gno.land/p/demo/avl", "gno.land/r/demo/app"]);
+    assert_eq!(
+        plan.steps[1].pkg_dir,
+        dir.path().join("gno.land/r/demo/app")
+    );
+}
+
+#[test]
+fn test_deploy_plan_resolver_flag() {
+    let dir = tempdir().unwrap();
+    write_package(
+        dir.path(),
+        "gno.land/r/demo/legacy",
+        "package legacy\n\nimport \"gno.land/p/demo/avl/v0\"\n",
+    );
+    write_package(dir.path(), "gno.land/p/demo/avl", "package avl\n");
+    write_package(dir.path(), "gno.land/p/demo/avl/v0", "package avl\n");
+    let deploy_plan = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_gget"))
+            .arg("deploy-plan")
+            .arg(dir.path())
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    let output = deploy_plan(&[
+        "--resolver",
+        "sat",
+        "--optional",
+        "gno.land/r/demo/legacy",
+        "--optional",
+        "gno.land/p/demo/avl/v0",
+        "--conflict",
+        "gno.land/p/demo/avl",
+        "gno.land/p/demo/avl/v0",
+    ]);
+    assert!(output.status.success());
+    let script = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        script.contains("-pkgpath gno.land/p/demo/avl "),
+        "{}",
+        script
+    );
+    assert!(!script.contains("legacy"), "{}", script);
+    assert!(!script.contains("avl/v0"), "{}", script);
+
+    // constraints mean nothing to a topological sort
+    let output = deploy_plan(&["--optional", "gno.land/r/demo/legacy"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--resolver sat"));
+}
@@ -0,0 +1,186 @@
+#![cfg(feature = "deploy")]
+
+use base64::{engine::general_purpose, Engine as _};
+use gget::deploy::broadcast::{BroadcastError, DeployStatus, Deployer, MemPackage};
+use gget::deploy::key::DeployKey;
+use gget::deploy::{DeployOptions, DeployPlan};
+use k256::ecdsa::signature::Verifier;
+use k256::ecdsa::{Signature, VerifyingKey};
+use serde_json::{json, Value};
+use std::fs;
+use std::sync::{Arc, Mutex};
+use tempfile::tempdir;
+use warp::Filter;
+
+const KEY_ONE: &str = "0000000000000000000000000000000000000000000000000000000000000001";
+
+/// Decoded transactions received by a fake node
+type Broadcasts = Arc<Mutex<Vec<Vec<u8>>>>;
+
+/// Serves just enough of a gno.land node's RPC to deploy packages:
+/// account lookups, `vm/qfile` for the `existing` packages, and commits
+/// that accept everything except transactions mentioning `reject`
+fn spawn_node(
+    existing: &'static [&'static str],
+    reject: Option<&'static str>,
+) -> (String, Broadcasts) {
+    let broadcasts = Broadcasts::default();
+    let received = broadcasts.clone();
+    let route = warp::post()
+        .and(warp::body::json())
+        .map(move |request: Value| {
+            let params = &request["params"];
+            let result = match request["method"].as_str().unwrap() {
+                "abci_query" if params["path"] == "vm/qfile" => {
+                    let data = general_purpose::STANDARD
+                        .decode(params["data"].as_str().unwrap())
+                        .unwrap();
+                    let path = String::from_utf8(data).unwrap();
+                    let error = if existing.contains(&path.as_str()) {
+                        Value::Null
+                    } else {
+                        json!({ "@type": "/vm.InvalidPkgPathError" })
+                    };
+                    json!({ "response": { "ResponseBase": { "Error": error, "Data": null } } })
+                }
+                "abci_query" => {
+                    let account = json!({
+                        "BaseAccount": { "account_number": "7", "sequence": "3" }
+                    });
+                    let data = general_purpose::STANDARD.encode(account.to_string());
+                    json!({ "response": { "ResponseBase": { "Error": null, "Data": data } } })
+                }
+                "broadcast_tx_commit" => {
+                    let tx = general_purpose::STANDARD
+                        .decode(params["tx"].as_str().unwrap())
+                        .unwrap();
+                    let rejected = reject.is_some_and(|r| contains(&tx, r.as_bytes()));
+                    received.lock().unwrap().push(tx);
+                    let deliver = if rejected {
+                        json!({ "Error": { "@type": "/std.UnauthorizedError" }, "Log": "unauthorized" })
+                    } else {
+                        json!({ "Error": null, "Log": "" })
+                    };
+                    json!({
+                        "check_tx": { "ResponseBase": { "Error": null } },
+                        "deliver_tx": { "ResponseBase": deliver },
+                        "hash": "aGFzaA==",
+                        "height": "42",
+                    })
+                }
+                method => panic!("unexpected method {}", method),
+            };
+            warp::reply::json(&json!({ "jsonrpc": "2.0", "id": 1, "result": result }))
+        });
+    let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    (format!("http://{}", addr), broadcasts)
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+fn write_tree(root: &std::path::Path) {
+    let pkg = |rel: &str, source: &str| {
+        let dir = root.join(rel);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("pkg.gno"), source).unwrap();
+    };
+    pkg("gno.land/p/demo/ufmt", "package ufmt\n");
+    pkg(
+        "gno.land/p/demo/avl",
+        "package avl\n\nimport \"gno.land/p/demo/ufmt\"\n",
+    );
+    pkg(
+        "gno.land/r/demo/app",
+        "package app\n\nimport \"gno.land/p/demo/avl\"\n",
+    );
+}
+
+#[test]
+fn test_key_address() {
+    let key = DeployKey::from_hex(KEY_ONE).unwrap();
+    assert_eq!(key.address(), "g1w508d6qejxtdg4y5r3zarvary0c5xw7kfptewu");
+    assert!(DeployKey::from_hex("abcd").is_err());
+    assert!(DeployKey::from_hex(&"0".repeat(64)).is_err());
+}
+
+#[test]
+fn test_signature_verifies() {
+    let key = DeployKey::from_hex(&format!("0x{}\n", KEY_ONE)).unwrap();
+    let signature = Signature::from_slice(&key.sign(b"sign me")).unwrap();
+    let verifying_key = VerifyingKey::from_sec1_bytes(&key.public_key()).unwrap();
+    assert!(verifying_key.verify(b"sign me", &signature).is_ok());
+}
+
+#[tokio::test]
+async fn test_deploy_in_order_skipping_existing_packages() {
+    let dir = tempdir().unwrap();
+    write_tree(dir.path());
+    let (url, broadcasts) = spawn_node(&["gno.land/p/demo/ufmt"], None);
+
+    let options = DeployOptions::default().with_remote(&url);
+    let plan = DeployPlan::from_dir(dir.path(), &options).unwrap();
+    let key = DeployKey::from_hex(KEY_ONE).unwrap();
+    let deployed = Deployer::new(key, options).deploy(&plan).await.unwrap();
+
+    let statuses: Vec<(&str, &DeployStatus)> = deployed
+        .iter()
+        .map(|d| (d.pkg_path.as_str(), &d.status))
+        .collect();
+    let committed = DeployStatus::Deployed {
+        height: 42,
+        hash: "aGFzaA==".to_string(),
+    };
+    assert_eq!(
+        statuses,
+        vec![
+            ("gno.land/p/demo/ufmt", &DeployStatus::AlreadyOnChain),
+            ("gno.land/p/demo/avl", &committed),
+            ("gno.land/r/demo/app", &committed),
+        ]
+    );
+
+    let broadcasts = broadcasts.lock().unwrap();
+    assert_eq!(broadcasts.len(), 2);
+    assert!(contains(&broadcasts[0], b"gno.land/p/demo/avl"));
+    assert!(contains(&broadcasts[1], b"gno.land/r/demo/app"));
+    assert!(contains(&broadcasts[0], b"/vm.m_addpkg"));
+}
+
+#[test]
+fn test_sign_bytes_track_the_account_sequence() {
+    let dir = tempdir().unwrap();
+    write_tree(dir.path());
+    let options = DeployOptions::default().with_chain_id("test-chain");
+    let plan = DeployPlan::from_dir(dir.path(), &options).unwrap();
+    let package = MemPackage::from_step(&plan.steps[0]).unwrap();
+    let deployer = Deployer::new(DeployKey::from_hex(KEY_ONE).unwrap(), options);
+
+    let sign_bytes = deployer.sign_bytes(&package, 7, 3);
+    assert!(sign_bytes.starts_with(r#"{"account_number":"7","chain_id":"test-chain","fee":"#));
+    assert!(sign_bytes.contains(r#""sequence":"3"}"#));
+    assert!(sign_bytes.contains(r#""creator":"g1w508d6qejxtdg4y5r3zarvary0c5xw7kfptewu""#));
+    assert!(sign_bytes.contains(r#""files":[{"body":"package ufmt\n","name":"pkg.gno"}]"#));
+}
+
+#[tokio::test]
+async fn test_rejected_transaction_stops_the_deploy() {
+    let dir = tempdir().unwrap();
+    write_tree(dir.path());
+    let (url, broadcasts) = spawn_node(&[], Some("gno.land/p/demo/avl"));
+
+    let options = DeployOptions::default().with_remote(&url);
+    let plan = DeployPlan::from_dir(dir.path(), &options).unwrap();
+    let key = DeployKey::from_hex(KEY_ONE).unwrap();
+    let err = Deployer::new(key, options).deploy(&plan).await.unwrap_err();
+
+    assert!(matches!(
+        err,
+        BroadcastError::Rejected { ref pkg_path, ref log }
+            if pkg_path == "gno.land/p/demo/avl" && log == "unauthorized"
+    ));
+    // ufmt went through, app was never sent
+    assert_eq!(broadcasts.lock().unwrap().len(), 2);
+}
@@ -0,0 +1,106 @@
+use gget::diff::{diff_package, unified_diff, Against, DiffError};
+use gget::fetch::PackageManager;
+use gget::testing::FakeChain;
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn test_unified_diff() {
+    let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+    let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\n";
+    assert_eq!(
+        unified_diff("a/x.gno", "b/x.gno", old, new),
+        "--- a/x.gno\n+++ b/x.gno\n\
+         @@ -1,5 +1,5 @@\n a\n-b\n+B\n c\n d\n e\n\
+         @@ -8,3 +8,4 @@\n h\n i\n j\n+k\n"
+    );
+    assert_eq!(unified_diff("a", "b", old, old), "");
+    assert_eq!(
+        unified_diff("a", "b", "", "x\n"),
+        "--- a\n+++ b\n@@ -0,0 +1 @@\n+x\n"
+    );
+}
+
+#[test]
+fn test_parse_against() {
+    assert_eq!("latest".parse(), Ok(Against::Latest));
+    assert_eq!("lockfile".parse(), Ok(Against::Lockfile));
+    assert_eq!("42".parse(), Ok(Against::Height(42)));
+    assert!("yesterday".parse::<Against>().is_err());
+}
+
+#[tokio::test]
+async fn test_diff_against_the_chain() {
+    let chain = FakeChain::new().with_package(
+        "gno.land/p/demo/avl",
+        &[
+            ("avl.gno", "package avl\n\nfunc A() {}\n"),
+            ("node.gno", "package avl\n"),
+        ],
+    );
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let output = tempdir().unwrap();
+    let dir = output.path().join("avl");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("avl.gno"), "package avl\n\nfunc B() {}\n").unwrap();
+    fs::write(dir.join("extra.gno"), "package avl\n").unwrap();
+
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+    let diff = diff_package(
+        &pm,
+        "gno.land/p/demo/avl",
+        &dir,
+        output.path(),
+        Against::Latest,
+    )
+    .await
+    .unwrap();
+    assert_eq!(diff.added, ["node.gno"]);
+    assert_eq!(diff.removed, ["extra.gno"]);
+    assert_eq!(diff.modified.len(), 1);
+    assert!(diff.modified[0]
+        .unified
+        .contains("-func B() {}\n+func A() {}\n"));
+
+    // an older height the local copy matches
+    let height = chain.height();
+    chain.advance();
+    chain.publish("gno.land/p/demo/avl", &[("avl.gno", "package avl // v2\n")]);
+    fs::write(dir.join("avl.gno"), "package avl\n\nfunc A() {}\n").unwrap();
+    fs::write(dir.join("node.gno"), "package avl\n").unwrap();
+    fs::remove_file(dir.join("extra.gno")).unwrap();
+    let diff = diff_package(
+        &pm,
+        "gno.land/p/demo/avl",
+        &dir,
+        output.path(),
+        Against::Height(height),
+    )
+    .await
+    .unwrap();
+    assert!(diff.is_empty(), "{:?}", diff);
+
+    let latest = diff_package(
+        &pm,
+        "gno.land/p/demo/avl",
+        &dir,
+        output.path(),
+        Against::Latest,
+    )
+    .await
+    .unwrap();
+    assert!(!latest.is_empty());
+
+    // without a lockfile there is no locked height
+    let err = diff_package(
+        &pm,
+        "gno.land/p/demo/avl",
+        &dir,
+        output.path(),
+        Against::Lockfile,
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(err, DiffError::NotLocked(_)), "{}", err);
+}
@@ -1,4 +1,5 @@
-use gget::dependency::DependencyResolver;
+use gget::dependency::{DependencyResolver, TestFiles};
+use gget::fetch::PackageManager;
 use std::fs;
 use tempfile::TempDir;
 
@@ -70,7 +71,7 @@ func Compute() int {
     fs::write(temp_path.join("readme.md"), "This is a readme").unwrap();
 
     // Now test reading all gno files and extracting dependencies
-    let mut resolver = DependencyResolver::new().unwrap();
+    let resolver = DependencyResolver::new().unwrap();
     let result = resolver.extract_dependencies_from_directory(temp_path);
 
     assert!(result.is_ok(), "Should successfully read directory");
@@ -109,7 +110,7 @@ func Compute() int {
 #[test]
 fn test_empty_directory() {
     let temp_dir = TempDir::new().unwrap();
-    let mut resolver = DependencyResolver::new().unwrap();
+    let resolver = DependencyResolver::new().unwrap();
     let result = resolver.extract_dependencies_from_directory(temp_dir.path());
 
     assert!(result.is_ok());
@@ -129,7 +130,7 @@ fn test_directory_with_no_gno_files() {
     fs::write(temp_dir.path().join("README.md"), "# README").unwrap();
     fs::write(temp_dir.path().join("config.json"), "{}").unwrap();
 
-    let mut resolver = DependencyResolver::new().unwrap();
+    let resolver = DependencyResolver::new().unwrap();
     let result = resolver.extract_dependencies_from_directory(temp_dir.path());
 
     assert!(result.is_ok());
@@ -195,7 +196,7 @@ func UseTree() {
     }
 
     // Test reading all files and extracting dependencies
-    let mut resolver = DependencyResolver::new().unwrap();
+    let resolver = DependencyResolver::new().unwrap();
     let result = resolver.extract_dependencies_from_directory(temp_path);
 
     assert!(result.is_ok(), "Should successfully read directory");
@@ -233,3 +234,113 @@ func UseTree() {
         panic!("myapp package not found");
     }
 }
+
+#[test]
+fn test_parallel_extraction_matches_sequential() {
+    let temp_dir = TempDir::new().unwrap();
+    for i in 0..40 {
+        let dir = temp_dir.path().join(format!("pkg{}", i % 5));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join(format!("file{}.gno", i)),
+            format!(
+                "package pkg{}\nimport (\n    \"gno.land/p/demo/dep{}\"\n    \"strings\"\n)\n",
+                i % 5,
+                i
+            ),
+        )
+        .unwrap();
+        fs::write(
+            dir.join(format!("file{}_test.gno", i)),
+            format!(
+                "package pkg{}\nimport \"gno.land/p/demo/testing{}\"\n",
+                i % 5,
+                i % 3
+            ),
+        )
+        .unwrap();
+    }
+
+    for test_files in [TestFiles::Include, TestFiles::Exclude] {
+        let resolver = DependencyResolver::new()
+            .unwrap()
+            .with_test_files(test_files);
+        let sequential = resolver
+            .extract_dependencies_from_directory(temp_dir.path())
+            .unwrap();
+        let parallel = resolver
+            .extract_dependencies_from_directory_parallel(temp_dir.path())
+            .unwrap();
+
+        assert_eq!(parallel.len(), 5);
+        for (name, package) in &sequential {
+            let other = &parallel[name];
+            assert_eq!(package.imports, other.imports, "{}", name);
+            assert_eq!(package.test_imports, other.test_imports, "{}", name);
+        }
+    }
+}
+
+#[test]
+fn test_parallel_extraction_reports_unreadable_files() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("ok.gno"), "package ok\n").unwrap();
+    fs::write(temp_dir.path().join("broken.gno"), [0xff, 0xfe, 0x00]).unwrap();
+
+    let resolver = DependencyResolver::new().unwrap();
+    assert!(resolver
+        .extract_dependencies_from_directory_parallel(temp_dir.path())
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_unchanged_files_are_not_parsed_again() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(
+        temp_dir.path().join("a.gno"),
+        "package app\nimport \"gno.land/p/demo/avl\"\n",
+    )
+    .unwrap();
+    fs::write(
+        temp_dir.path().join("b.gno"),
+        "package app\nimport \"gno.land/p/demo/ufmt\"\n",
+    )
+    .unwrap();
+    let cache = TempDir::new().unwrap();
+
+    let pm = PackageManager::new(None, cache.path().to_path_buf());
+    let cold = pm
+        .extract_dependencies_from_directory(temp_dir.path())
+        .await
+        .unwrap();
+    assert_eq!(pm.cache().stats().misses, 2);
+    assert_eq!(pm.cache().stats().writes, 2);
+    let expected = DependencyResolver::new()
+        .unwrap()
+        .extract_dependencies_from_directory(temp_dir.path())
+        .unwrap();
+    assert_eq!(cold["app"].imports, expected["app"].imports);
+
+    // a new process only has the disk cache to go on
+    let pm = PackageManager::new(None, cache.path().to_path_buf());
+    let warm = pm
+        .extract_dependencies_from_directory(temp_dir.path())
+        .await
+        .unwrap();
+    assert_eq!(pm.cache().stats().storage_hits, 2);
+    assert_eq!(pm.cache().stats().misses, 0);
+    assert_eq!(warm["app"].imports, cold["app"].imports);
+
+    fs::write(
+        temp_dir.path().join("b.gno"),
+        "package app\nimport \"gno.land/p/demo/json\"\n",
+    )
+    .unwrap();
+    let changed = pm
+        .extract_dependencies_from_directory(temp_dir.path())
+        .await
+        .unwrap();
+    assert_eq!(pm.cache().stats().misses, 1);
+    assert!(changed["app"].imports.contains("gno.land/p/demo/json"));
+    assert!(!changed["app"].imports.contains("gno.land/p/demo/ufmt"));
+}
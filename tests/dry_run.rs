@@ -0,0 +1,192 @@
+use gget::cache::imports_key;
+use gget::dependency::TestFiles;
+use gget::fetch::PackageManager;
+use gget::plan::{DownloadPlan, FileAction};
+use gget::testing::FakeChain;
+use std::fs;
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn test_plan_lists_what_would_be_written() {
+    let chain = FakeChain::demo();
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let output = tempdir().unwrap();
+    let avl_dir = output.path().join("gno.land/p/demo/avl");
+    fs::create_dir_all(&avl_dir).unwrap();
+    fs::write(avl_dir.join("avl.gno"), "package avl\n").unwrap();
+    fs::write(avl_dir.join("node.gno"), "package avl // edited\n").unwrap();
+
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+    let plan = pm
+        .plan_download(&["gno.land/r/demo/app"], output.path(), true)
+        .await
+        .unwrap();
+
+    let packages: Vec<&str> = plan.packages.iter().map(|p| p.package.as_str()).collect();
+    assert_eq!(packages, ["gno.land/p/demo/avl", "gno.land/r/demo/app"]);
+    assert_eq!(plan.packages[0].target_dir, avl_dir);
+    let actions: Vec<FileAction> = plan.packages[0].files.iter().map(|f| f.action).collect();
+    assert_eq!(actions, [FileAction::Unchanged, FileAction::Overwrite]);
+    assert_eq!(plan.count(FileAction::Create), 1);
+    assert_eq!(
+        plan.bytes_to_write(),
+        ("package avl\n".len() + "package app\n\nimport \"gno.land/p/demo/avl\"\n".len()) as u64
+    );
+
+    // nothing was written
+    assert!(!output.path().join("gno.land/r").exists());
+    assert_eq!(
+        fs::read_to_string(avl_dir.join("node.gno")).unwrap(),
+        "package avl // edited\n"
+    );
+}
+
+#[tokio::test]
+async fn test_plan_without_dependencies() {
+    let chain = FakeChain::demo();
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let output = tempdir().unwrap();
+
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+    let plan = pm
+        .plan_download(&["gno.land/r/demo/app"], output.path(), false)
+        .await
+        .unwrap();
+    assert_eq!(plan.packages.len(), 1);
+    assert_eq!(plan.count(FileAction::Create), 1);
+    assert_eq!(fs::read_dir(output.path()).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn test_plan_skips_test_only_dependencies() {
+    let chain = FakeChain::demo()
+        .with_package(
+            "gno.land/p/demo/testutils",
+            &[("testutils.gno", "package testutils\n")],
+        )
+        .with_package(
+            "gno.land/r/demo/app",
+            &[
+                ("app.gno", "package app\n\nimport \"gno.land/p/demo/avl\"\n"),
+                (
+                    "app_test.gno",
+                    "package app\n\nimport \"gno.land/p/demo/testutils\"\n",
+                ),
+            ],
+        );
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let output = tempdir().unwrap();
+
+    let plan = |test_files: TestFiles| {
+        let pm = PackageManager::new(Some(url.clone()), cache.path().to_path_buf())
+            .with_quiet(true)
+            .with_test_files(test_files);
+        let output = output.path().to_path_buf();
+        async move {
+            pm.plan_download(&["gno.land/r/demo/app"], &output, true)
+                .await
+                .unwrap()
+        }
+    };
+    let packages = |plan: &DownloadPlan| -> Vec<String> {
+        plan.packages.iter().map(|p| p.package.clone()).collect()
+    };
+
+    let with_tests = plan(TestFiles::Include).await;
+    assert_eq!(
+        packages(&with_tests),
+        [
+            "gno.land/p/demo/avl",
+            "gno.land/p/demo/testutils",
+            "gno.land/r/demo/app"
+        ]
+    );
+    let without_tests = plan(TestFiles::Exclude).await;
+    assert_eq!(
+        packages(&without_tests),
+        ["gno.land/p/demo/avl", "gno.land/r/demo/app"]
+    );
+    // the test file itself is still downloaded
+    let app = without_tests.packages.last().unwrap();
+    assert!(app.files.iter().any(|f| f.name == "app_test.gno"));
+}
+
+#[tokio::test]
+async fn test_cached_imports_skip_parsing_unless_refreshing() {
+    let chain = FakeChain::demo();
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let output = tempdir().unwrap();
+    let app = "gno.land/r/demo/app";
+
+    // an earlier run's result for these exact sources, made up so that
+    // using it shows
+    let sources = vec![(
+        "app.gno".to_string(),
+        "package app\n\nimport \"gno.land/p/demo/avl\"\n".to_string(),
+    )];
+    let pm = PackageManager::new(Some(url.clone()), cache.path().to_path_buf()).with_quiet(true);
+    pm.cache()
+        .set(&imports_key(app, &sources), "[[],[]]")
+        .await
+        .unwrap();
+
+    let plan = pm.plan_download(&[app], output.path(), true).await.unwrap();
+    let packages: Vec<&str> = plan.packages.iter().map(|p| p.package.as_str()).collect();
+    assert_eq!(packages, [app]);
+
+    let pm = PackageManager::new(Some(url.clone()), cache.path().to_path_buf())
+        .with_quiet(true)
+        .with_refresh(true);
+    let plan = pm.plan_download(&[app], output.path(), true).await.unwrap();
+    let packages: Vec<&str> = plan.packages.iter().map(|p| p.package.as_str()).collect();
+    assert_eq!(packages, ["gno.land/p/demo/avl", app]);
+
+    // refreshing stored what parsing found, for the next run to reuse
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+    let cached = pm
+        .cache()
+        .get(&imports_key(app, &sources))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(cached.contains("gno.land/p/demo/avl"), "{}", cached);
+}
+
+#[test]
+fn test_commands_that_would_write_anyway_reject_dry_run() {
+    let dir = tempdir().unwrap();
+    let gget = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_gget"))
+            .current_dir(dir.path())
+            .args([
+                "--rpc-endpoint",
+                "http://127.0.0.1:1",
+                "--cache-dir",
+                "cache",
+            ])
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    for args in [
+        &["--dry-run", "watch", "gno.land/p/demo/avl"][..],
+        &["prefetch", "gno.land/p/demo/avl", "--dry-run"][..],
+        &["cache", "seed", "vendor", "--dry-run"][..],
+    ] {
+        let output = gget(args);
+        assert!(!output.status.success(), "{:?}", args);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("doesn't support --dry-run"),
+            "{:?}: {}",
+            args,
+            stderr
+        );
+    }
+    assert!(!dir.path().join("cache").exists());
+}
@@ -0,0 +1,186 @@
+use gget::fetch::PackageManager;
+use gget::lock::{Lockfile, LOCKFILE_NAME};
+use gget::parallel::ParallelDownloadOptions;
+use gget::repro::repro_check;
+use gget::testing::{download_options, Failure, FakeChain};
+use gget::ErrorKind;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tempfile::tempdir;
+
+/// `gno.land/r/demo/app` importing `avl`, which imports `ufmt`
+fn universe() -> FakeChain {
+    FakeChain::new()
+        .with_package(
+            "gno.land/p/demo/ufmt",
+            &[("ufmt.gno", "package ufmt\n\nfunc Sprintf() string { return \"\" }\n")],
+        )
+        .with_package(
+            "gno.land/p/demo/avl",
+            &[(
+                "avl.gno",
+                "package avl\n\nimport \"gno.land/p/demo/ufmt\"\n\nvar _ = ufmt.Sprintf\n",
+            )],
+        )
+        .with_package(
+            "gno.land/r/demo/app",
+            &[
+                (
+                    "app.gno",
+                    "package app\n\nimport (\n\t\"gno.land/p/demo/avl\"\n\t\"gno.land/p/demo/ufmt\"\n)\n",
+                ),
+                ("app_test.gno", "package app\n"),
+            ],
+        )
+}
+
+fn manager(url: &str, cache: &Path) -> PackageManager {
+    PackageManager::new(Some(url.to_string()), cache.to_path_buf()).with_quiet(true)
+}
+
+#[tokio::test]
+async fn test_installs_the_dependency_closure() {
+    let chain = universe();
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let target = tempdir().unwrap();
+
+    let summary = manager(&url, cache.path())
+        .download_with_deps_parallel("gno.land/r/demo/app", target.path(), download_options())
+        .await
+        .unwrap();
+    assert!(summary.failed.is_empty(), "{}", summary);
+    assert_eq!(summary.successful, 3);
+    for package in ["r/demo/app", "p/demo/avl", "p/demo/ufmt"] {
+        assert!(target.path().join("gno.land").join(package).is_dir());
+    }
+
+    let lock = Lockfile::load(&target.path().join(LOCKFILE_NAME)).unwrap();
+    assert_eq!(lock.packages.len(), 3);
+
+    // a second install finds everything up to date
+    let summary = manager(&url, cache.path())
+        .download_with_deps_parallel("gno.land/r/demo/app", target.path(), download_options())
+        .await
+        .unwrap();
+    assert_eq!(summary.up_to_date.len(), 3);
+    assert_eq!(summary.successful, 0);
+}
+
+#[tokio::test]
+async fn test_updates_follow_the_chain_and_pins_hold() {
+    let chain = universe();
+    let url = chain.spawn();
+    let target = tempdir().unwrap();
+
+    manager(&url, tempdir().unwrap().path())
+        .download_with_deps_parallel("gno.land/p/demo/avl", target.path(), download_options())
+        .await
+        .unwrap();
+    let attestation = repro_check(&manager(&url, tempdir().unwrap().path()), target.path())
+        .await
+        .unwrap();
+    assert!(attestation.passed);
+
+    // ufmt changes at the next height
+    let old_height = chain.height();
+    chain.advance();
+    chain.set_file("gno.land/p/demo/ufmt", "ufmt.gno", "package ufmt // v2\n");
+
+    let attestation = repro_check(&manager(&url, tempdir().unwrap().path()), target.path())
+        .await
+        .unwrap();
+    assert!(!attestation.passed);
+    let failed: Vec<_> = attestation
+        .packages
+        .iter()
+        .filter(|check| !check.passed)
+        .map(|check| check.package.as_str())
+        .collect();
+    assert_eq!(failed, ["gno.land/p/demo/ufmt"]);
+
+    let pinned = manager(&url, tempdir().unwrap().path())
+        .with_pinned_height("gno.land/p/demo/ufmt", old_height);
+    let files = pinned
+        .fetch_remote_files("gno.land/p/demo/ufmt")
+        .await
+        .unwrap();
+    assert!(!files["ufmt.gno"].contains("v2"));
+
+    let summary = manager(&url, tempdir().unwrap().path())
+        .download_with_deps_parallel(
+            "gno.land/p/demo/avl",
+            target.path(),
+            ParallelDownloadOptions {
+                force: true,
+                ..download_options()
+            },
+        )
+        .await
+        .unwrap();
+    assert!(summary.failed.is_empty(), "{}", summary);
+    assert_eq!(
+        fs::read_to_string(target.path().join("gno.land/p/demo/ufmt/ufmt.gno")).unwrap(),
+        "package ufmt // v2\n"
+    );
+}
+
+#[tokio::test]
+async fn test_transient_failures_are_retried() {
+    let chain = universe();
+    let url = chain.spawn();
+    let target = tempdir().unwrap();
+    let pm = manager(&url, tempdir().unwrap().path());
+
+    chain.fail_package("gno.land/p/demo/ufmt", 1, Failure::Status(503));
+    chain.fail_package("gno.land/p/demo/ufmt/ufmt.gno", 1, Failure::Malformed);
+    let summary = pm
+        .download_packages_parallel(
+            vec!["gno.land/p/demo/ufmt"],
+            target.path(),
+            download_options(),
+        )
+        .await
+        .unwrap();
+    assert!(summary.failed.is_empty(), "{}", summary);
+    assert!(target.path().join("gno.land/p/demo/ufmt/ufmt.gno").exists());
+
+    // a package that keeps failing is reported once retries run out
+    chain.fail_package("gno.land/p/demo/avl", 10, Failure::Rpc("boom".to_string()));
+    let summary = pm
+        .download_packages_parallel(
+            vec!["gno.land/p/demo/avl"],
+            target.path(),
+            download_options(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(summary.failed.len(), 1);
+    assert_eq!(summary.failed[0].error_kind, ErrorKind::Network);
+    assert_eq!(summary.failed[0].retry_count, 2);
+}
+
+#[tokio::test]
+async fn test_missing_packages_are_not_found() {
+    let url = universe().spawn();
+    let err = manager(&url, tempdir().unwrap().path())
+        .download_package("gno.land/p/demo/missing", tempdir().unwrap().path())
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+}
+
+#[tokio::test]
+async fn test_slow_chain_hits_the_deadline() {
+    let chain = universe();
+    chain.set_latency(Duration::from_secs(2));
+    let url = chain.spawn();
+
+    let err = manager(&url, tempdir().unwrap().path())
+        .with_deadline(Instant::now() + Duration::from_millis(100))
+        .download_package("gno.land/p/demo/ufmt", tempdir().unwrap().path())
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Cancelled);
+}
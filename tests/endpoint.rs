@@ -0,0 +1,92 @@
+use gget::endpoint::{check_endpoints, query_height, query_node_status, select_endpoint};
+use gget::report::{render, ReportFormat};
+use gget::testing::FakeChain;
+use warp::Filter;
+
+/// Serves a tendermint-style `status` response reporting `height`
+fn spawn_node(height: u64) -> String {
+    let route = warp::post().map(move || {
+        warp::reply::json(&serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": { "sync_info": { "latest_block_height": height.to_string() } }
+        }))
+    });
+    let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    format!("http://{}", addr)
+}
+
+#[tokio::test]
+async fn test_query_height() {
+    let url = spawn_node(1234);
+    let height = query_height(&reqwest::Client::new(), &url).await.unwrap();
+    assert_eq!(height, 1234);
+}
+
+#[tokio::test]
+async fn test_lagging_endpoint_is_demoted() {
+    let lagging = spawn_node(900);
+    let fresh = spawn_node(1000);
+    let slightly_behind = spawn_node(995);
+    let down = "http://127.0.0.1:1".to_string();
+
+    let urls = vec![
+        down.clone(),
+        lagging.clone(),
+        slightly_behind.clone(),
+        fresh.clone(),
+    ];
+    let statuses = check_endpoints(&reqwest::Client::new(), &urls, 10).await;
+
+    assert_eq!(statuses[0].height, None);
+    assert!(statuses[0].error.is_some());
+    assert!(statuses[1].stale);
+    assert_eq!(statuses[1].lag, 100);
+    assert!(statuses[1].to_string().contains("[stale]"));
+    assert!(!statuses[2].stale);
+    assert_eq!(statuses[2].lag, 5);
+
+    // configured order wins among healthy endpoints
+    assert_eq!(select_endpoint(&statuses).unwrap().url, slightly_behind);
+}
+
+#[tokio::test]
+async fn test_node_status() {
+    let chain = FakeChain::new().with_chain_id("test5").with_catching_up();
+    chain.advance();
+    let url = chain.spawn();
+    let status = query_node_status(&reqwest::Client::new(), &url)
+        .await
+        .unwrap();
+
+    assert_eq!(status.chain_id.as_deref(), Some("test5"));
+    assert_eq!(status.version.as_deref(), Some("v0.1.0"));
+    assert_eq!(status.app_version.as_deref(), Some("fake"));
+    assert_eq!(status.latest_block_height, 2);
+    assert!(status.catching_up);
+    assert_eq!(status.abci_error, None);
+
+    let human = render(&status, ReportFormat::Human).unwrap();
+    assert!(human.contains("Chain:        test5"), "{}", human);
+    assert!(human.contains("Catching up:  yes"), "{}", human);
+    let json: serde_json::Value =
+        serde_json::from_str(&render(&status, ReportFormat::Json).unwrap()).unwrap();
+    assert_eq!(json["latest_block_height"], 2);
+    assert_eq!(json["catching_up"], true);
+}
+
+#[tokio::test]
+async fn test_node_status_without_abci_info() {
+    // answers `status` to everything, so `abci_info` can't be read
+    let url = spawn_node(7);
+    let status = query_node_status(&reqwest::Client::new(), &url)
+        .await
+        .unwrap();
+
+    assert_eq!(status.latest_block_height, 7);
+    assert_eq!(status.chain_id, None);
+    assert_eq!(status.app_version, None);
+    assert!(status.abci_error.is_some());
+    assert!(!status.catching_up);
+}
@@ -0,0 +1,406 @@
+use base64::{engine::general_purpose, Engine as _};
+use gget::cache::files_key;
+use gget::fetch::{PackageManager, PackageManagerError, ValidationError};
+use gget::parallel::DownloadError;
+use gget::query::{RpcErrorCode, RpcResponse};
+use gget::testing::{download_options, FakeChain};
+use gget::ErrorKind;
+use serde_json::{json, Value};
+use std::error::Error;
+use tempfile::tempdir;
+use warp::Filter;
+
+/// Node answering every request with `reply`, given the request's id
+/// unless it has one
+fn spawn_node(reply: Value) -> String {
+    let route = warp::post()
+        .and(warp::body::json())
+        .map(move |request: Value| {
+            let mut reply = reply.clone();
+            if reply.get("id").is_none() {
+                reply["id"] = request["id"].clone();
+            }
+            warp::reply::json(&reply)
+        });
+    let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    format!("http://{}", addr)
+}
+
+/// Node answering every query with an error
+fn spawn_failing_node() -> String {
+    spawn_node(json!({
+        "jsonrpc": "2.0",
+        "result": { "response": { "ResponseBase": {
+            "Error": { "msg": "package not found" },
+            "Data": "",
+            "Log": "",
+        } } }
+    }))
+}
+
+/// What `gget render` gets back from a node answering with `reply`
+async fn render_against(reply: Value) -> PackageManagerError {
+    let cache = tempdir().unwrap();
+    let pm = PackageManager::new(Some(spawn_node(reply)), cache.path().to_path_buf());
+    pm.render("gno.land/r/demo/boards", "").await.unwrap_err()
+}
+
+#[tokio::test]
+async fn test_download_error_keeps_its_source() {
+    let cache = tempdir().unwrap();
+    let target = tempdir().unwrap();
+    let pm = PackageManager::new(Some(spawn_failing_node()), cache.path().to_path_buf());
+
+    let err = pm
+        .download_package("gno.land/p/demo/missing", target.path())
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), "download");
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    match &err {
+        PackageManagerError::Download {
+            package,
+            file,
+            source,
+        } => {
+            assert_eq!(package, "gno.land/p/demo/missing");
+            assert_eq!(*file, None);
+            assert!(matches!(**source, PackageManagerError::Rpc(_)));
+        }
+        other => panic!("expected a download error, got {:?}", other),
+    }
+    assert!(err.source().unwrap().to_string().starts_with("RPC error"));
+}
+
+#[tokio::test]
+async fn test_resolution_error_names_the_package() {
+    let cache = tempdir().unwrap();
+    let target = tempdir().unwrap();
+    let pm = PackageManager::new(Some(spawn_failing_node()), cache.path().to_path_buf())
+        .with_quiet(true);
+
+    let err = pm
+        .download_with_deps_parallel("gno.land/p/demo/missing", target.path(), download_options())
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), "resolution");
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    assert!(matches!(
+        err,
+        PackageManagerError::Resolution { ref package, .. } if package == "gno.land/p/demo/missing"
+    ));
+}
+
+#[tokio::test]
+async fn test_validation_without_sources() {
+    let cache = tempdir().unwrap();
+    let pm = PackageManager::new(None, cache.path().to_path_buf());
+
+    let empty = tempdir().unwrap();
+    let report = pm.validate_package(empty.path()).await.unwrap();
+    assert_eq!(report.files_checked, 0);
+    let err = report.into_result().unwrap_err();
+    assert_eq!(err.code(), "validation");
+    assert_eq!(err.kind(), ErrorKind::Validation);
+    assert!(matches!(
+        err,
+        PackageManagerError::Validation {
+            source: ValidationError::NoSourceFiles,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn test_wrapped_errors_report_the_kind_of_their_cause() {
+    let offline = PackageManagerError::Offline;
+    assert_eq!(offline.kind(), ErrorKind::Network);
+
+    let queued = DownloadError::PackageManager(PackageManagerError::Download {
+        package: "gno.land/p/demo/avl".to_string(),
+        file: Some("avl.gno".to_string()),
+        source: Box::new(PackageManagerError::Cancelled(
+            gget::cancel::Interrupted::Cancelled,
+        )),
+    });
+    assert_eq!(queued.kind(), ErrorKind::Cancelled);
+    assert_eq!(DownloadError::ChecksumMismatch.kind(), ErrorKind::Integrity);
+    assert_eq!(ErrorKind::NotFound.to_string(), "not_found");
+    assert_eq!(
+        serde_json::to_value(ErrorKind::NotFound).unwrap(),
+        json!("not_found")
+    );
+}
+
+#[test]
+fn test_every_error_enum_has_a_kind() {
+    use gget::policy::PolicyError;
+    use gget::requirements::RequirementsError;
+    use gget::trash::TrashError;
+    use gget::verify::VerifyError;
+    use gget::watch::WatchError;
+
+    assert_eq!(
+        TrashError::NotFound("gno.land/p/demo/avl".to_string()).kind(),
+        ErrorKind::NotFound
+    );
+    let unparsable = RequirementsError::Parse {
+        line: 1,
+        reason: "empty package path".to_string(),
+    };
+    assert_eq!(unparsable.kind(), ErrorKind::Config);
+    assert_eq!(
+        VerifyError::Untrusted("gno.land/p/demo/avl".to_string()).kind(),
+        ErrorKind::Integrity
+    );
+    let disallowed = PolicyError::Disallowed {
+        package: "gno.land/r/demo/boards".to_string(),
+        reason: "realms are not allowed".to_string(),
+    };
+    assert_eq!(disallowed.kind(), ErrorKind::Config);
+    // wrapping variants report the kind of the error they wrap
+    let offline = WatchError::PackageManager(PackageManagerError::Offline);
+    assert_eq!(offline.kind(), ErrorKind::Network);
+}
+
+#[tokio::test]
+async fn test_json_rpc_error_objects_are_typed() {
+    let err = render_against(json!({
+        "jsonrpc": "2.0",
+        "error": { "code": -32601, "message": "Method not found", "data": "" },
+    }))
+    .await;
+    assert!(
+        matches!(
+            &err,
+            PackageManagerError::RpcProtocol { code: RpcErrorCode::MethodNotFound, message, data: None, .. }
+                if message == "Method not found"
+        ),
+        "{:?}",
+        err
+    );
+    assert_eq!(err.code(), "rpc_protocol");
+    assert_eq!(err.kind(), ErrorKind::Config);
+    let &PackageManagerError::RpcProtocol { request_id, .. } = &err else {
+        unreachable!()
+    };
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "JSON-RPC method not found (-32601) for request {}: Method not found",
+            request_id
+        )
+    );
+
+    // tm2 can't echo the id of a request it couldn't parse
+    let err = render_against(json!({
+        "jsonrpc": "2.0",
+        "id": "",
+        "error": { "code": -32700, "message": "Parse error. Invalid JSON", "data": "unexpected end" },
+    }))
+    .await;
+    assert_eq!(err.kind(), ErrorKind::Internal);
+    assert!(err.to_string().ends_with(": unexpected end"), "{}", err);
+
+    let err = render_against(json!({
+        "jsonrpc": "2.0",
+        "error": { "code": -32000, "message": "Server error" },
+    }))
+    .await;
+    assert!(matches!(
+        err,
+        PackageManagerError::RpcProtocol {
+            code: RpcErrorCode::ServerError(-32000),
+            ..
+        }
+    ));
+    assert_eq!(err.kind(), ErrorKind::Network);
+}
+
+#[tokio::test]
+async fn test_malformed_responses_are_json_errors() {
+    let err = render_against(json!({ "jsonrpc": "2.0" })).await;
+    assert!(matches!(err, PackageManagerError::Json(_)), "{:?}", err);
+    assert_eq!(err.kind(), ErrorKind::Integrity);
+
+    // Go encodes empty byte slices as null
+    let err = render_against(json!({
+        "jsonrpc": "2.0",
+        "result": { "response": { "ResponseBase": {
+            "Error": { "msg": "realm not found" },
+            "Data": null,
+            "Log": null,
+        } } },
+    }))
+    .await;
+    assert!(matches!(err, PackageManagerError::Rpc(_)), "{:?}", err);
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+}
+
+#[test]
+fn test_rpc_response_models_both_outcomes() {
+    let ok: RpcResponse = serde_json::from_value(json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "result": { "response": { "ResponseBase": {
+            "Error": null,
+            "Data": "aGk=",
+            "Log": "",
+        } } },
+    }))
+    .unwrap();
+    assert!(ok.error.is_none());
+    assert_eq!(ok.result.unwrap().response.response_base.data, "aGk=");
+
+    let rejected: RpcResponse = serde_json::from_value(json!({
+        "jsonrpc": "2.0",
+        "id": -1,
+        "error": { "code": -32602, "message": "Invalid params", "data": { "height": 0 } },
+    }))
+    .unwrap();
+    assert!(rejected.result.is_none());
+    let error = rejected.error.unwrap();
+    assert_eq!(RpcErrorCode::from(error.code), RpcErrorCode::InvalidParams);
+    assert_eq!(error.data_text().as_deref(), Some(r#"{"height":0}"#));
+    assert_eq!(RpcErrorCode::from(-1).code(), -1);
+}
+
+#[tokio::test]
+async fn test_requests_get_increasing_ids() {
+    let chain =
+        gget::testing::FakeChain::new().with_render("gno.land/r/demo/boards", "", "# Boards\n");
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf());
+    for _ in 0..3 {
+        pm.render("gno.land/r/demo/boards", "").await.unwrap();
+    }
+
+    let ids: Vec<u64> = chain.queries().iter().map(|query| query.id).collect();
+    assert_eq!(ids.len(), 3);
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", ids);
+}
+
+#[tokio::test]
+async fn test_responses_to_other_requests_are_rejected() {
+    let err = render_against(json!({
+        "jsonrpc": "2.0",
+        "id": "someone else's",
+        "result": { "response": { "ResponseBase": {
+            "Error": null,
+            "Data": "",
+            "Log": "",
+        } } },
+    }))
+    .await;
+    assert!(
+        matches!(&err, PackageManagerError::RpcIdMismatch { got, .. } if got == "someone else's"),
+        "{:?}",
+        err
+    );
+    assert_eq!(err.kind(), ErrorKind::Integrity);
+}
+
+#[tokio::test]
+async fn test_listings_that_cannot_be_whole_are_rejected() {
+    let listing_reply = |listing: &str| {
+        json!({
+            "jsonrpc": "2.0",
+            "result": { "response": { "ResponseBase": {
+                "Error": null,
+                "Data": general_purpose::STANDARD.encode(listing),
+                "Log": "",
+            } } }
+        })
+    };
+    for (listing, reason) in [
+        ("", "no files"),
+        (
+            "avl.gno\n../escape.gno\n",
+            "`../escape.gno` is not a file path",
+        ),
+        (
+            "avl.gno\n/etc/cron.d/evil.gno\n",
+            "`/etc/cron.d/evil.gno` is not a file path: it is absolute",
+        ),
+        (
+            "avl.gno\nC:evil.gno\n",
+            "`C:evil.gno` is not a file path: it starts with a drive letter",
+        ),
+        (
+            "avl.gno\n..\\..\\evil.gno\n",
+            "is not a file path: it contains a backslash",
+        ),
+        (
+            "avl.gno\nsub/../../evil.gno\n",
+            "`sub/../../evil.gno` is not a file path",
+        ),
+        ("avl.gno\nnode.gno\navl.gno", "`avl.gno` is listed twice"),
+        (
+            "README.md\nLICENSE",
+            "none of the 2 listed files is a .gno file",
+        ),
+    ] {
+        let cache = tempdir().unwrap();
+        let target = tempdir().unwrap();
+        let pm = PackageManager::new(
+            Some(spawn_node(listing_reply(listing))),
+            cache.path().to_path_buf(),
+        )
+        .with_quiet(true);
+
+        let err = pm
+            .download_package("gno.land/p/demo/avl", target.path())
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Integrity, "{}", err);
+        let PackageManagerError::Download { source, .. } = &err else {
+            panic!("expected a download error, got {:?}", err);
+        };
+        assert!(
+            matches!(&**source, PackageManagerError::IncompleteListing { package, .. }
+                if package == "gno.land/p/demo/avl"),
+            "{:?}",
+            source
+        );
+        assert_eq!(err.code(), "download");
+        assert!(err.to_string().contains(reason), "{}", err);
+        // nothing of the partial package is written
+        assert_eq!(std::fs::read_dir(target.path()).unwrap().count(), 0);
+    }
+}
+
+#[tokio::test]
+async fn test_cached_lists_cannot_escape_the_target() {
+    let chain =
+        FakeChain::new().with_package("gno.land/p/demo/avl", &[("avl.gno", "package avl\n")]);
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+    // a list planted in the cache, as if by an older gget or another user
+    pm.cache()
+        .set(
+            &files_key("gno.land/p/demo/avl", None),
+            r#"["avl.gno", "../../evil.gno"]"#,
+        )
+        .await
+        .unwrap();
+
+    let root = tempdir().unwrap();
+    let target = root.path().join("vendor/avl");
+    let err = pm
+        .download_package("gno.land/p/demo/avl", &target)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, PackageManagerError::UnsafePath { package, source }
+            if package == "gno.land/p/demo/avl" && source.name == "../../evil.gno"),
+        "{:?}",
+        err
+    );
+    assert_eq!(err.kind(), ErrorKind::Integrity);
+    assert_eq!(err.code(), "unsafe_path");
+    assert!(!root.path().join("evil.gno").exists());
+    assert!(!target.exists());
+}
@@ -0,0 +1,101 @@
+use gget::cache::{AsyncStorage, DiskStorage};
+use gget::filelock::FileLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn test_second_holder_waits() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("target.lock");
+    let first = FileLock::acquire(path.clone(), || panic!("nobody holds it yet"))
+        .await
+        .unwrap();
+
+    let waited = Arc::new(AtomicBool::new(false));
+    let second = tokio::spawn({
+        let waited = waited.clone();
+        async move {
+            FileLock::acquire(path, move || waited.store(true, Ordering::SeqCst))
+                .await
+                .unwrap()
+        }
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(waited.load(Ordering::SeqCst));
+    assert!(!second.is_finished());
+
+    drop(first);
+    let second = tokio::time::timeout(Duration::from_secs(5), second)
+        .await
+        .expect("the lock is released on drop")
+        .unwrap();
+    assert_eq!(second.path(), dir.path().join("target.lock"));
+}
+
+#[test]
+fn test_target_locks_stay_out_of_the_target() {
+    let dir = tempdir().unwrap();
+    let target = dir.path().join("gno");
+    let path = FileLock::target_path(&target).unwrap();
+    assert!(!path.starts_with(&target));
+    assert_eq!(path, FileLock::target_path(&target).unwrap());
+    assert_ne!(
+        path,
+        FileLock::target_path(&dir.path().join("other")).unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_disk_storage_entries_lock_across_instances() {
+    let dir = tempdir().unwrap();
+    let ours = DiskStorage::new(dir.path().to_path_buf(), Duration::from_secs(3600));
+    let theirs = DiskStorage::new(dir.path().to_path_buf(), Duration::from_secs(3600));
+
+    let guard = ours.lock("files:gno.land/p/demo/avl").await.unwrap();
+    assert!(guard.is_some());
+    let waiting = tokio::spawn(async move {
+        let _guard = theirs.lock("files:gno.land/p/demo/avl").await.unwrap();
+        theirs.get("files:gno.land/p/demo/avl").await.unwrap()
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!waiting.is_finished());
+
+    // the holder fills the entry, and the waiter finds it once let in
+    ours.set("files:gno.land/p/demo/avl", "[\"avl.gno\"]")
+        .await
+        .unwrap();
+    drop(guard);
+    assert_eq!(waiting.await.unwrap().as_deref(), Some("[\"avl.gno\"]"));
+}
+
+#[tokio::test]
+async fn test_concurrent_writes_of_one_entry_all_succeed() {
+    let dir = tempdir().unwrap();
+    let storage = DiskStorage::new(dir.path().to_path_buf(), Duration::from_secs(3600));
+
+    let writes = (0..32).map(|i| {
+        let storage = storage.clone();
+        tokio::spawn(async move {
+            storage
+                .set(
+                    "files:gno.land/p/demo/avl",
+                    &format!("[\"{}.gno\"]", i).repeat(1000),
+                )
+                .await
+        })
+    });
+    for write in futures::future::join_all(writes).await {
+        write.unwrap().unwrap();
+    }
+
+    // whichever write landed last, the entry is one of them, whole
+    let entry = storage
+        .get("files:gno.land/p/demo/avl")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(entry.len() % 1000, 0);
+    assert_eq!(entry, entry[..entry.len() / 1000].repeat(1000));
+}
@@ -1,9 +1,27 @@
-use blake3;
 use gget::fetch::{PackageManager, PackageManagerError};
+use gget::lock::LOCKFILE_NAME;
+use gget::testing::{download_options, FakeChain};
 use gget::DEFAULT_RPC_ENDPOINT;
 use std::fs;
+use std::path::Path;
 use tempfile::tempdir;
 
+/// A chain serving a cut-down `gno.land/p/demo/json`
+fn json_chain() -> FakeChain {
+    FakeChain::new().with_package(
+        "gno.land/p/demo/json",
+        &[
+            ("buffer.gno", "package json\n\ntype buffer struct{}\n"),
+            (
+                "escape.gno",
+                "package json\n\nfunc escape(s string) string { return s }\n",
+            ),
+            ("node.gno", "package json\n\ntype Node struct{}\n"),
+            ("path.gno", "package json\n\nfunc Path() {}\n"),
+        ],
+    )
+}
+
 #[tokio::test]
 async fn test_package_manager_creation() {
     let temp_dir = tempdir().expect("Failed to create temp directory");
@@ -15,18 +33,16 @@ async fn test_package_manager_creation() {
     assert_eq!(pm.rpc_endpoint(), custom_endpoint);
 }
 
-/// Test downloading a real package from gno.land
-/// This test requires network access and may be slow
-/// TODO: consider using a mock server for testing
+/// Test downloading a package from a fake chain
 #[tokio::test]
-#[ignore] // Use `cargo test -- --ignored` to run this test
 async fn test_package_manager_download_package() {
     // Create a temporary directory for testing
     let temp_dir = tempdir().expect("Failed to create temp directory");
     let temp_path = temp_dir.path();
 
     // Create a new package manager
-    let pm = PackageManager::new(None, temp_dir.path().to_path_buf());
+    let url = json_chain().spawn();
+    let pm = PackageManager::new(Some(url), temp_dir.path().join("cache"));
 
     // Test downloading a package
     let pkg_path = "gno.land/p/demo/json";
@@ -95,7 +111,7 @@ async fn test_package_manager_invalid_package() {
 
     // Verify error type
     match result {
-        Err(PackageManagerError::PackageFiles(_)) => {
+        Err(PackageManagerError::Download { file: None, .. }) => {
             // This is expected - package files retrieval should fail
         }
         Err(PackageManagerError::Rpc(_)) => {
@@ -134,10 +150,16 @@ async fn test_directory_creation() {
     // Verify directory doesn't exist initially
     assert!(!target_path.exists());
 
-    // Try to download (will fail due to network, but should create directory)
+    // Try to download (will fail due to network, before anything is written)
     let result = pm.download_package("test/package", &target_path).await;
+    assert!(result.is_err());
 
-    // Should create the directory even if download fails
+    // Failed downloads don't leave an empty directory behind
+    assert!(!target_path.exists(), "Target directory was created");
+
+    // unless writing in place
+    let pm = pm.with_unsafe_direct(true);
+    let result = pm.download_package("test/package", &target_path).await;
     assert!(target_path.exists(), "Target directory was not created");
     assert!(target_path.is_dir(), "Target path is not a directory");
 
@@ -165,7 +187,7 @@ async fn test_rpc_error_handling() {
         PackageManagerError::Http(_) => {
             // Expected
         }
-        PackageManagerError::PackageFiles(_) => {
+        PackageManagerError::Download { .. } => {
             // Also acceptable, as the HTTP error might be wrapped
         }
         other => {
@@ -187,7 +209,6 @@ async fn test_empty_package_path() {
 
 /// Test cache functionality
 #[tokio::test]
-#[ignore]
 async fn test_package_manager_cache() {
     // Create a temporary directory for testing
     let temp_dir = tempdir().expect("Failed to create temp directory");
@@ -195,7 +216,8 @@ async fn test_package_manager_cache() {
     let cache_dir = temp_dir.path().join("cache");
 
     // Create a new package manager with cache
-    let pm = PackageManager::new(None, cache_dir.clone());
+    let chain = json_chain();
+    let pm = PackageManager::new(Some(chain.spawn()), cache_dir.clone());
 
     // Test downloading a package for the first time
     let pkg_path = "gno.land/p/demo/json";
@@ -255,4 +277,403 @@ async fn test_package_manager_cache() {
         files_cache_mtime, new_files_cache_mtime,
         "Cache file was modified when it shouldn't have been"
     );
+
+    // Only the first download reached the chain: the listing and 4 files
+    assert_eq!(chain.file_queries(), 5);
+}
+
+/// Packages whose locked files are intact on disk are skipped without network access
+#[tokio::test]
+async fn test_parallel_download_skips_unchanged_packages() {
+    use gget::lock::{LockedPackage, Lockfile, LOCKFILE_NAME};
+    use gget::parallel::ParallelDownloadOptions;
+
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let out = temp_dir.path().join("out");
+    let pkg_dir = out.join("gno.land/p/demo/avl");
+    fs::create_dir_all(&pkg_dir).unwrap();
+    fs::write(pkg_dir.join("avl.gno"), "package avl\n").unwrap();
+
+    let mut lock = Lockfile::default();
+    lock.insert(LockedPackage::from_dir("gno.land/p/demo/avl", &pkg_dir, &[]).unwrap());
+    lock.save(&out.join(LOCKFILE_NAME)).unwrap();
+
+    // unreachable endpoint: any network access would make the download fail
+    let pm = PackageManager::new(
+        Some("http://127.0.0.1:1".to_string()),
+        temp_dir.path().join("cache"),
+    );
+    let options = download_options();
+
+    let summary = pm
+        .download_packages_parallel(vec!["gno.land/p/demo/avl"], &out, options.clone())
+        .await
+        .unwrap();
+    assert_eq!(summary.up_to_date, vec!["gno.land/p/demo/avl".to_string()]);
+    assert_eq!(summary.total_packages, 1);
+    assert!(summary.failed.is_empty());
+    assert!(summary.to_string().contains("1 up to date"));
+
+    // a local edit makes the package stale again
+    fs::write(pkg_dir.join("avl.gno"), "package avl // edited\n").unwrap();
+    let locked = lock.get("gno.land/p/demo/avl");
+    assert!(!pm
+        .is_unchanged("gno.land/p/demo/avl", &pkg_dir, locked)
+        .await
+        .unwrap());
+
+    // --force never skips
+    let forced = ParallelDownloadOptions {
+        force: true,
+        retry_config: gget::parallel::RetryConfig {
+            max_attempts: 1,
+            ..Default::default()
+        },
+        ..options
+    };
+    fs::write(pkg_dir.join("avl.gno"), "package avl\n").unwrap();
+    let summary = pm
+        .download_packages_parallel(vec!["gno.land/p/demo/avl"], &out, forced)
+        .await
+        .unwrap();
+    assert!(summary.up_to_date.is_empty());
+    assert_eq!(summary.failed.len(), 1);
+}
+
+#[tokio::test]
+async fn test_resolution_analyzes_each_level_concurrently() {
+    use gget::parallel::ParallelDownloadOptions;
+    use std::time::{Duration, Instant};
+
+    let leaves: Vec<String> = (0..10)
+        .map(|i| format!("gno.land/p/demo/leaf{}", i))
+        .collect();
+    let mut root = String::from("package app\n\nimport (\n");
+    for leaf in &leaves {
+        root.push_str(&format!("\t\"{}\"\n", leaf));
+    }
+    root.push_str(")\n");
+
+    let chain = FakeChain::new().with_package("gno.land/r/demo/app", &[("app.gno", &root)]);
+    for (i, leaf) in leaves.iter().enumerate() {
+        chain.publish(leaf, &[("leaf.gno", &format!("package leaf{}\n", i))]);
+    }
+    let url = chain.spawn();
+    // each leaf takes two queries, a listing and its file
+    chain.set_latency(Duration::from_millis(100));
+
+    let cache = tempdir().unwrap();
+    let target = tempdir().unwrap();
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+    let options = ParallelDownloadOptions {
+        max_concurrent: leaves.len(),
+        show_progress: false,
+        ..Default::default()
+    };
+
+    let started = Instant::now();
+    let summary = pm
+        .download_with_deps_parallel("gno.land/r/demo/app", target.path(), options)
+        .await
+        .unwrap();
+    let elapsed = started.elapsed();
+
+    assert_eq!(summary.total_packages, leaves.len() + 1);
+    assert!(summary.failed.is_empty());
+    // one leaf after another would take at least two seconds
+    assert!(
+        elapsed < Duration::from_millis(1500),
+        "resolution took {:?}",
+        elapsed
+    );
+}
+
+/// Listed packages download leaves first, whatever order they are given in
+#[tokio::test]
+async fn test_parallel_download_fetches_dependencies_first() {
+    use gget::parallel::ParallelDownloadOptions;
+
+    let chain = FakeChain::new()
+        .with_package(
+            "gno.land/r/demo/app",
+            &[("app.gno", "package app\n\nimport \"gno.land/p/demo/avl\"\n")],
+        )
+        .with_package(
+            "gno.land/p/demo/avl",
+            &[(
+                "avl.gno",
+                "package avl\n\nimport \"gno.land/p/demo/ufmt\"\n",
+            )],
+        )
+        .with_package("gno.land/p/demo/ufmt", &[("ufmt.gno", "package ufmt\n")]);
+    let temp_dir = tempdir().unwrap();
+    let pm =
+        PackageManager::new(Some(chain.spawn()), temp_dir.path().join("cache")).with_quiet(true);
+    let options = ParallelDownloadOptions {
+        max_concurrent: 1,
+        show_progress: false,
+        ..Default::default()
+    };
+
+    let summary = pm
+        .download_packages_parallel(
+            vec![
+                "gno.land/r/demo/app",
+                "gno.land/p/demo/avl",
+                "gno.land/p/demo/ufmt",
+            ],
+            &temp_dir.path().join("out"),
+            options,
+        )
+        .await
+        .unwrap();
+    assert_eq!(summary.successful, 3);
+
+    // the download fetches each file once more after analysis
+    let queries = chain.queries();
+    let downloaded = |file: &str| {
+        queries
+            .iter()
+            .rposition(|query| query.data == file)
+            .unwrap()
+    };
+    let ufmt = downloaded("gno.land/p/demo/ufmt/ufmt.gno");
+    let avl = downloaded("gno.land/p/demo/avl/avl.gno");
+    let app = downloaded("gno.land/r/demo/app/app.gno");
+    assert!(ufmt < avl && avl < app, "{} {} {}", ufmt, avl, app);
+}
+
+/// Packages download while the rest of the tree is still being resolved
+#[tokio::test]
+async fn test_downloads_start_before_resolution_finishes() {
+    use std::time::Duration;
+
+    let chain = FakeChain::new()
+        .with_package(
+            "gno.land/r/demo/app",
+            &[(
+                "app.gno",
+                "package app\n\nimport (\n\t\"gno.land/p/demo/leaf\"\n\t\"gno.land/p/demo/d1\"\n)\n",
+            )],
+        )
+        .with_package("gno.land/p/demo/leaf", &[("leaf.gno", "package leaf\n")])
+        .with_package(
+            "gno.land/p/demo/d1",
+            &[("d1.gno", "package d1\n\nimport \"gno.land/p/demo/d2\"\n")],
+        )
+        .with_package(
+            "gno.land/p/demo/d2",
+            &[("d2.gno", "package d2\n\nimport \"gno.land/p/demo/d3\"\n")],
+        )
+        .with_package(
+            "gno.land/p/demo/d3",
+            &[("d3.gno", "package d3\n\nimport \"gno.land/p/demo/d4\"\n")],
+        )
+        .with_package("gno.land/p/demo/d4", &[("d4.gno", "package d4\n")]);
+    chain.set_latency(Duration::from_millis(20));
+    let temp_dir = tempdir().unwrap();
+    let pm =
+        PackageManager::new(Some(chain.spawn()), temp_dir.path().join("cache")).with_quiet(true);
+    let out = temp_dir.path().join("out");
+
+    let summary = pm
+        .download_with_deps_parallel("gno.land/r/demo/app", &out, download_options())
+        .await
+        .unwrap();
+    assert_eq!(summary.successful, 6);
+    assert!(out.join("gget.lock").exists());
+
+    let queries = chain.queries();
+    let leaf_downloaded = queries
+        .iter()
+        .rposition(|query| query.data == "gno.land/p/demo/leaf/leaf.gno")
+        .unwrap();
+    let d4_analyzed = queries
+        .iter()
+        .position(|query| query.data == "gno.land/p/demo/d4/d4.gno")
+        .unwrap();
+    assert!(
+        leaf_downloaded < d4_analyzed,
+        "{} {}",
+        leaf_downloaded,
+        d4_analyzed
+    );
+
+    // the root still waits for everything it imports
+    let app_downloaded = queries
+        .iter()
+        .rposition(|query| query.data == "gno.land/r/demo/app/app.gno")
+        .unwrap();
+    let d4_downloaded = queries
+        .iter()
+        .rposition(|query| query.data == "gno.land/p/demo/d4/d4.gno")
+        .unwrap();
+    assert!(d4_downloaded < app_downloaded);
+}
+
+/// Runs the gget binary in `dir` against `url`, returning whether it
+/// succeeded and what it printed to stderr
+async fn gget(dir: &Path, url: &str, args: &[&str]) -> (bool, String) {
+    let output = tokio::process::Command::new(env!("CARGO_BIN_EXE_gget"))
+        .current_dir(dir)
+        .args(["--rpc-endpoint", url, "--cache-dir", "cache"])
+        .args(args)
+        .output()
+        .await
+        .unwrap();
+    (
+        output.status.success(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+#[tokio::test]
+async fn test_add_refuses_to_overwrite_an_edited_package() {
+    let url = json_chain().spawn();
+    let project = tempdir().unwrap();
+    let node = project.path().join("gno/gno.land/p/demo/json/node.gno");
+
+    let (ok, _) = gget(project.path(), &url, &["gno.land/p/demo/json"]).await;
+    assert!(ok);
+    // unchanged, so there's nothing to refuse
+    let (ok, _) = gget(project.path(), &url, &["gno.land/p/demo/json"]).await;
+    assert!(ok);
+
+    fs::write(&node, "package json // edited\n").unwrap();
+    let (ok, stderr) = gget(project.path(), &url, &["gno.land/p/demo/json"]).await;
+    assert!(!ok);
+    assert!(stderr.contains("--force"), "{}", stderr);
+    assert_eq!(
+        fs::read_to_string(&node).unwrap(),
+        "package json // edited\n"
+    );
+
+    let (ok, _) = gget(project.path(), &url, &["gno.land/p/demo/json", "--force"]).await;
+    assert!(ok);
+    assert_eq!(
+        fs::read_to_string(&node).unwrap(),
+        "package json\n\ntype Node struct{}\n"
+    );
+}
+
+#[tokio::test]
+async fn test_flags_before_a_subcommand_keep_it_a_subcommand() {
+    let chain = json_chain();
+    let url = chain.spawn();
+    let project = tempdir().unwrap();
+
+    for subcommand in ["status", "doctor"] {
+        let (ok, stderr) = gget(project.path(), &url, &[subcommand]).await;
+        assert!(ok, "{}: {}", subcommand, stderr);
+        assert!(!stderr.contains("download"), "{}: {}", subcommand, stderr);
+    }
+    assert!(chain.queries().iter().any(|query| query.method == "status"));
+    assert!(!project.path().join("gno").exists());
+
+    let (ok, stderr) = gget(project.path(), &url, &["gno.land/p/demo/json", "status"]).await;
+    assert!(!ok);
+    assert!(stderr.contains("can't be given together"), "{}", stderr);
+}
+
+#[tokio::test]
+async fn test_export_writes_the_archive_and_reports_it() {
+    let url = json_chain().spawn();
+    let project = tempdir().unwrap();
+    let (ok, stderr) = gget(
+        project.path(),
+        &url,
+        &["gno.land/p/demo/json", "--resolve-deps", "--parallel"],
+    )
+    .await;
+    assert!(ok, "{}", stderr);
+
+    let (ok, stderr) = gget(
+        project.path(),
+        &url,
+        &["export", "gno.land/p/demo/json", "--archive-format", "tar"],
+    )
+    .await;
+    assert!(ok, "{}", stderr);
+    assert!(project.path().join("json.tar").is_file());
+
+    let (ok, stderr) = gget(
+        project.path(),
+        &url,
+        &[
+            "export",
+            "gno.land/p/demo/json",
+            "--to",
+            "json.tgz",
+            "--json",
+        ],
+    )
+    .await;
+    assert!(ok, "{}", stderr);
+    assert!(project.path().join("json.tgz").is_file());
+}
+
+#[tokio::test]
+async fn test_validation_keeps_machine_readable_output_parseable() {
+    let url = json_chain().spawn();
+
+    for args in [
+        &["gno.land/p/demo/json", "--validate"][..],
+        &[
+            "gno.land/p/demo/json",
+            "--validate",
+            "--resolve-deps",
+            "--parallel",
+        ][..],
+    ] {
+        let project = tempdir().unwrap();
+        let output = tokio::process::Command::new(env!("CARGO_BIN_EXE_gget"))
+            .current_dir(project.path())
+            .args(["--rpc-endpoint", &url, "--cache-dir", "cache"])
+            .args(args)
+            .args(["--format", "json"])
+            .output()
+            .await
+            .unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success(), "{:?}: {}", args, stderr);
+
+        // the download summary, then the validation report
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let reports = serde_json::Deserializer::from_str(&stdout)
+            .into_iter::<serde_json::Value>()
+            .collect::<Result<Vec<_>, _>>();
+        assert_eq!(
+            reports.map(|r| r.len()).ok(),
+            Some(2),
+            "{:?}: {}",
+            args,
+            stdout
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_add_without_a_lock_entry_asks_the_chain_once_the_cache_is_gone() {
+    let url = json_chain().spawn();
+    let project = tempdir().unwrap();
+
+    let (ok, stderr) = gget(project.path(), &url, &["gno.land/p/demo/json"]).await;
+    assert!(ok, "{}", stderr);
+    // as if every cache entry had expired, with nothing locked
+    fs::remove_dir_all(project.path().join("cache")).unwrap();
+    let _ = fs::remove_file(project.path().join("gno").join(LOCKFILE_NAME));
+
+    let (ok, stderr) = gget(project.path(), &url, &["gno.land/p/demo/json"]).await;
+    assert!(ok, "{}", stderr);
+    assert!(!stderr.contains("differs"), "{}", stderr);
+
+    fs::write(
+        project.path().join("gno/gno.land/p/demo/json/node.gno"),
+        "package json // edited\n",
+    )
+    .unwrap();
+    fs::remove_dir_all(project.path().join("cache")).unwrap();
+    let (ok, stderr) = gget(project.path(), &url, &["gno.land/p/demo/json"]).await;
+    assert!(!ok);
+    assert!(stderr.contains("--force"), "{}", stderr);
 }
@@ -0,0 +1,184 @@
+use gget::fetch::PackageManager;
+use gget::lock::{Lockfile, LOCKFILE_NAME};
+use gget::source::{parse_git_source, GitSource, PackageSource, SourceError};
+use gget::testing::{download_options, FakeChain};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::tempdir;
+
+fn git(repo: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(["-c", "user.name=gget", "-c", "user.email=gget@example.com"])
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "git {:?} failed", args);
+    String::from_utf8(output.stdout).unwrap().trim().to_string()
+}
+
+/// Commits `files` to `repo` and returns the commit's hash
+fn commit(repo: &Path, files: &[(&str, &str)]) -> String {
+    for (name, content) in files {
+        let path = repo.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    git(repo, &["add", "-A"]);
+    git(repo, &["commit", "--quiet", "-m", "update"]);
+    git(repo, &["rev-parse", "HEAD"])
+}
+
+#[test]
+fn test_parse_git_source() {
+    let source = GitSource::parse("git@github.com:me/draft.git@v0.1.0#/pkg/draft/").unwrap();
+    assert_eq!(source.url, "git@github.com:me/draft.git");
+    assert_eq!(source.rev, "v0.1.0");
+    assert_eq!(source.subdir.as_deref(), Some("pkg/draft"));
+    assert_eq!(
+        source.to_string(),
+        "git@github.com:me/draft.git@v0.1.0#pkg/draft"
+    );
+    assert_eq!(GitSource::parse(&source.to_string()).unwrap(), source);
+    assert_eq!(GitSource::parse("./draft@main").unwrap().subdir, None);
+
+    for spec in [
+        "git@github.com:me/draft.git",
+        "https://example.com/draft",
+        "@v1",
+        "./draft@v1#../outside",
+        // options in disguise
+        "--upload-pack=touch pwned@v1",
+        "./draft@--output=pwned",
+    ] {
+        assert!(
+            matches!(GitSource::parse(spec), Err(SourceError::InvalidSpec { .. })),
+            "{}",
+            spec
+        );
+    }
+
+    let (pkg_path, source) = parse_git_source("gno.land/p/me/draft=./draft@v1").unwrap();
+    assert_eq!(pkg_path, "gno.land/p/me/draft");
+    assert_eq!(source.url, "./draft");
+    assert!(parse_git_source("./draft@v1").is_err());
+}
+
+#[tokio::test]
+async fn test_git_source_is_installed_at_its_revision() {
+    let repo = tempdir().unwrap();
+    git(repo.path(), &["init", "--quiet"]);
+    let pinned = commit(
+        repo.path(),
+        &[
+            ("README.md", "# drafts\n"),
+            (
+                "pkg/draft/draft.gno",
+                "package draft\n\nimport \"gno.land/p/demo/avl\"\n",
+            ),
+        ],
+    );
+    commit(
+        repo.path(),
+        &[("pkg/draft/draft.gno", "package draft // unreleased\n")],
+    );
+
+    let chain =
+        FakeChain::new().with_package("gno.land/p/demo/avl", &[("avl.gno", "package avl\n")]);
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let target = tempdir().unwrap();
+    let source =
+        GitSource::new(&repo.path().to_string_lossy(), &pinned, Some("pkg/draft")).unwrap();
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf())
+        .with_quiet(true)
+        .with_source("gno.land/p/me/draft", PackageSource::Git(source));
+
+    let summary = pm
+        .download_roots_with_deps_parallel(
+            &["gno.land/p/me/draft"],
+            target.path(),
+            download_options(),
+        )
+        .await
+        .unwrap();
+    assert!(summary.failed.is_empty());
+
+    let dir = target.path().join("gno.land/p/me/draft");
+    assert_eq!(
+        fs::read_to_string(dir.join("draft.gno")).unwrap(),
+        "package draft\n\nimport \"gno.land/p/demo/avl\"\n"
+    );
+    // only the package's directory is installed
+    assert!(!dir.join("README.md").exists());
+    assert!(target.path().join("gno.land/p/demo/avl/avl.gno").exists());
+    let lock = Lockfile::load(&target.path().join(LOCKFILE_NAME)).unwrap();
+    assert!(lock.packages.contains_key("gno.land/p/me/draft"));
+    assert!(chain
+        .queries()
+        .iter()
+        .all(|query| !query.data.contains("gno.land/p/me/draft")));
+    assert_eq!(fs::read_dir(cache.path().join("git")).unwrap().count(), 1);
+
+    // reused, unchanged, on the next run
+    let again = pm
+        .download_roots_with_deps_parallel(
+            &["gno.land/p/me/draft"],
+            target.path(),
+            download_options(),
+        )
+        .await
+        .unwrap();
+    assert!(again.failed.is_empty());
+    assert_eq!(fs::read_dir(cache.path().join("git")).unwrap().count(), 1);
+}
+
+#[tokio::test]
+async fn test_git_source_reports_unknown_revisions() {
+    let repo = tempdir().unwrap();
+    git(repo.path(), &["init", "--quiet"]);
+    commit(repo.path(), &[("draft.gno", "package draft\n")]);
+    let cache = tempdir().unwrap();
+
+    let source = GitSource::new(&repo.path().to_string_lossy(), "no-such-rev", None).unwrap();
+    let err = source.checkout(cache.path()).await.unwrap_err();
+    assert!(matches!(err, SourceError::Git { .. }), "{}", err);
+    // nothing half cloned is left behind
+    assert_eq!(fs::read_dir(cache.path()).unwrap().count(), 0);
+
+    let source = GitSource::new(&repo.path().to_string_lossy(), "HEAD", Some("missing")).unwrap();
+    let err = source.checkout(cache.path()).await.unwrap_err();
+    assert!(matches!(err, SourceError::MissingSubdir { .. }), "{}", err);
+}
+
+#[tokio::test]
+async fn test_git_source_follows_a_moving_branch() {
+    let repo = tempdir().unwrap();
+    git(repo.path(), &["init", "--quiet"]);
+    git(repo.path(), &["checkout", "--quiet", "-b", "drafts"]);
+    commit(repo.path(), &[("draft.gno", "package draft // v1\n")]);
+    let cache = tempdir().unwrap();
+    let source = GitSource::new(&repo.path().to_string_lossy(), "drafts", None).unwrap();
+
+    let dir = source.checkout(cache.path()).await.unwrap();
+    assert_eq!(
+        fs::read_to_string(dir.join("draft.gno")).unwrap(),
+        "package draft // v1\n"
+    );
+    assert_eq!(source.checkout(cache.path()).await.unwrap(), dir);
+
+    commit(repo.path(), &[("draft.gno", "package draft // v2\n")]);
+    let moved = source.checkout(cache.path()).await.unwrap();
+    assert_ne!(moved, dir);
+    assert_eq!(
+        fs::read_to_string(moved.join("draft.gno")).unwrap(),
+        "package draft // v2\n"
+    );
+
+    // an abbreviated hash of the first commit reuses its checkout
+    let first = git(repo.path(), &["rev-parse", "--short", "HEAD~1"]);
+    let source = GitSource::new(&repo.path().to_string_lossy(), &first, None).unwrap();
+    assert_eq!(source.checkout(cache.path()).await.unwrap(), dir);
+}
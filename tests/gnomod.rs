@@ -0,0 +1,231 @@
+use gget::gnomod::{
+    add_requires, add_requires_to_file, module_imports, remove_requires, GnoMod, GnoModError,
+    Replace, Require,
+};
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn test_parse_require_block_and_single_line() {
+    let content = r#"
+// app manifest
+module gno.land/r/demo/app
+
+gno 0.9
+
+require (
+	gno.land/p/demo/avl v0.0.0-latest // balanced tree
+	"gno.land/p/demo/ufmt" v0.0.0-latest
+)
+
+require gno.land/p/demo/json v0.0.0-latest
+
+replace gno.land/p/demo/avl => ../avl
+"#;
+
+    let gnomod = GnoMod::parse(content).unwrap();
+    assert_eq!(gnomod.module.as_deref(), Some("gno.land/r/demo/app"));
+    assert_eq!(
+        gnomod.require_paths(),
+        vec![
+            "gno.land/p/demo/avl",
+            "gno.land/p/demo/ufmt",
+            "gno.land/p/demo/json"
+        ]
+    );
+    assert_eq!(
+        gnomod.requires[0],
+        Require {
+            path: "gno.land/p/demo/avl".to_string(),
+            version: "v0.0.0-latest".to_string(),
+        }
+    );
+}
+
+#[test]
+fn test_parse_without_requires() {
+    let gnomod = GnoMod::parse("module gno.land/p/demo/leaf\n").unwrap();
+    assert!(gnomod.requires.is_empty());
+}
+
+#[test]
+fn test_parse_errors() {
+    match GnoMod::parse("module x\nrequire (\n\tgno.land/p/demo/avl\n)\n") {
+        Err(GnoModError::Parse { line, .. }) => assert_eq!(line, 3),
+        other => panic!("expected parse error, got {:?}", other),
+    }
+
+    assert!(matches!(
+        GnoMod::parse("require (\n\tgno.land/p/demo/avl v1\n"),
+        Err(GnoModError::Parse { .. })
+    ));
+}
+
+#[test]
+fn test_unused_requires_of_a_module() {
+    let project = tempdir().unwrap();
+    let root = project.path();
+    fs::write(
+        root.join("gno.mod"),
+        "module gno.land/r/demo/app\n\nrequire (\n\tgno.land/p/demo/avl v0.0.0-latest\n\tgno.land/p/demo/ufmt v0.0.0-latest\n\tgno.land/p/demo/json v0.0.0-latest\n\tgno.land/p/demo/uassert v0.0.0-latest\n)\n",
+    )
+    .unwrap();
+    fs::write(
+        root.join("app.gno"),
+        "package app\n\nimport \"gno.land/p/demo/avl\"\n",
+    )
+    .unwrap();
+    fs::create_dir(root.join("internal")).unwrap();
+    fs::write(
+        root.join("internal/app_test.gno"),
+        "package app\n\nimport \"gno.land/p/demo/uassert\"\n",
+    )
+    .unwrap();
+    // a module of its own
+    fs::create_dir(root.join("tool")).unwrap();
+    fs::write(root.join("tool/gno.mod"), "module gno.land/r/demo/tool\n").unwrap();
+    fs::write(
+        root.join("tool/tool.gno"),
+        "package tool\n\nimport \"gno.land/p/demo/ufmt\"\n",
+    )
+    .unwrap();
+    // vendored dependencies
+    fs::create_dir_all(root.join("gno/avl")).unwrap();
+    fs::write(
+        root.join("gno/avl/avl.gno"),
+        "package avl\n\nimport \"gno.land/p/demo/json\"\n",
+    )
+    .unwrap();
+
+    let gnomod = GnoMod::load(&root.join("gno.mod")).unwrap();
+    let imports = module_imports(root, &root.join("gno")).unwrap();
+    let unused: Vec<&str> = gnomod
+        .unused_requires(&imports)
+        .into_iter()
+        .map(|require| require.path.as_str())
+        .collect();
+    assert_eq!(unused, ["gno.land/p/demo/ufmt", "gno.land/p/demo/json"]);
+}
+
+#[test]
+fn test_add_requires_into_existing_block() {
+    let content = "// my realm\nmodule gno.land/r/demo/app\n\nrequire (\n    \"gno.land/p/demo/avl\" v0.0.0-latest // trees\n    gno.land/p/demo/ufmt v0.0.0-latest // indirect\n)\n";
+    let (updated, added) = add_requires(
+        content,
+        &["gno.land/p/demo/ufmt", "gno.land/p/demo/seqid"],
+        &[
+            "gno.land/p/demo/avl",
+            "gno.land/r/demo/app",
+            "gno.land/p/demo/bf",
+        ],
+    )
+    .unwrap();
+    assert_eq!(added, vec!["gno.land/p/demo/seqid", "gno.land/p/demo/bf"]);
+    assert_eq!(
+        updated,
+        "// my realm\nmodule gno.land/r/demo/app\n\nrequire (\n    \"gno.land/p/demo/avl\" v0.0.0-latest // trees\n    gno.land/p/demo/ufmt v0.0.0-latest\n    gno.land/p/demo/seqid v0.0.0-latest\n    gno.land/p/demo/bf v0.0.0-latest // indirect\n)\n"
+    );
+    assert_eq!(GnoMod::parse(&updated).unwrap().requires.len(), 4);
+}
+
+#[test]
+fn test_add_requires_without_a_block() {
+    let content = "module gno.land/r/demo/app\n\nrequire gno.land/p/demo/avl v0.0.0-latest\n";
+    let (updated, added) = add_requires(
+        content,
+        &["gno.land/p/demo/avl", "gno.land/p/demo/ufmt"],
+        &[],
+    )
+    .unwrap();
+    assert_eq!(added, vec!["gno.land/p/demo/ufmt"]);
+    assert_eq!(
+        updated,
+        "module gno.land/r/demo/app\n\nrequire gno.land/p/demo/avl v0.0.0-latest\n\nrequire (\n\tgno.land/p/demo/ufmt v0.0.0-latest\n)\n"
+    );
+
+    let (unchanged, added) = add_requires(&updated, &["gno.land/p/demo/ufmt"], &[]).unwrap();
+    assert!(added.is_empty());
+    assert_eq!(unchanged, updated);
+}
+
+#[test]
+fn test_add_requires_to_file() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("gno.mod");
+    fs::write(&path, "module gno.land/r/demo/app\n").unwrap();
+
+    let added = add_requires_to_file(&path, &["gno.land/p/demo/avl"], &[]).unwrap();
+    assert_eq!(added, vec!["gno.land/p/demo/avl"]);
+    let gnomod = GnoMod::parse(&fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(gnomod.requires[0].path, "gno.land/p/demo/avl");
+
+    fs::write(&path, "module (\n").unwrap();
+    assert!(add_requires_to_file(&path, &["gno.land/p/demo/avl"], &[]).is_err());
+}
+
+#[test]
+fn test_remove_requires() {
+    let content = "module gno.land/r/demo/app\n\nrequire gno.land/p/demo/bf v0.0.0-latest\n\n// deps\nrequire (\n\tgno.land/p/demo/avl v0.0.0-latest\n\tgno.land/p/demo/ufmt v0.0.0-latest // indirect\n)\n\nrequire (\n\tgno.land/p/demo/seqid v0.0.0-latest\n)\n";
+    let (updated, removed) = remove_requires(
+        content,
+        &[
+            "gno.land/p/demo/bf",
+            "gno.land/p/demo/ufmt",
+            "gno.land/p/demo/seqid",
+            "gno.land/p/demo/unknown",
+        ],
+    )
+    .unwrap();
+    assert_eq!(
+        removed,
+        vec![
+            "gno.land/p/demo/bf",
+            "gno.land/p/demo/ufmt",
+            "gno.land/p/demo/seqid"
+        ]
+    );
+    assert_eq!(
+        updated,
+        "module gno.land/r/demo/app\n\n\n// deps\nrequire (\n\tgno.land/p/demo/avl v0.0.0-latest\n)\n\n"
+    );
+}
+
+#[test]
+fn test_parse_replace_directives() {
+    let gnomod = GnoMod::parse(
+        "module gno.land/r/demo/app\n\nreplace gno.land/p/me/foo => ../foo\n\nreplace (\n\tgno.land/p/me/bar v0.0.0-latest => \"./bar\" // wip\n)\n",
+    )
+    .unwrap();
+    assert_eq!(
+        gnomod.replaces,
+        vec![
+            Replace {
+                path: "gno.land/p/me/foo".to_string(),
+                dir: "../foo".into(),
+            },
+            Replace {
+                path: "gno.land/p/me/bar".to_string(),
+                dir: "./bar".into(),
+            },
+        ]
+    );
+    assert_eq!(
+        gnomod.replacements(std::path::Path::new("/work/app")),
+        vec![
+            ("gno.land/p/me/foo".to_string(), "/work/app/../foo".into()),
+            ("gno.land/p/me/bar".to_string(), "/work/app/./bar".into()),
+        ]
+    );
+
+    for bad in [
+        "replace gno.land/p/me/foo ../foo\n",
+        "replace gno.land/p/me/foo => gno.land/p/other/foo\n",
+        "replace => ../foo\n",
+    ] {
+        assert!(
+            matches!(GnoMod::parse(bad), Err(GnoModError::Parse { line: 1, .. })),
+            "{}",
+            bad
+        );
+    }
+}
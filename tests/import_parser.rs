@@ -0,0 +1,229 @@
+//! Property tests for the import parser: generated sources mixing every
+//! import style with import-like text that isn't an import.
+
+use gget::dependency::DependencyResolver;
+use proptest::prelude::*;
+use std::collections::HashSet;
+
+const KEYWORDS: &[&str] = &[
+    "break",
+    "case",
+    "chan",
+    "const",
+    "continue",
+    "default",
+    "defer",
+    "else",
+    "fallthrough",
+    "for",
+    "func",
+    "go",
+    "goto",
+    "if",
+    "import",
+    "interface",
+    "map",
+    "package",
+    "range",
+    "return",
+    "select",
+    "struct",
+    "switch",
+    "type",
+    "var",
+];
+
+/// Go identifiers, unicode letters included
+fn ident() -> impl Strategy<Value = String> {
+    "[a-zA-Zαβγδπλ_][a-zA-Z0-9αβγδπλ_]{0,8}"
+        .prop_filter("keyword", |s| !KEYWORDS.contains(&s.as_str()) && s != "_")
+}
+
+fn segment() -> impl Strategy<Value = String> {
+    "[a-z][a-z0-9_]{0,8}"
+}
+
+/// `gno.land` package paths and standard library ones
+fn import_path() -> impl Strategy<Value = String> {
+    prop_oneof![
+        (
+            prop_oneof!["p", "r"],
+            prop::collection::vec(segment(), 1..4)
+        )
+            .prop_map(|(kind, segments)| format!(
+                "gno.land/{}/{}",
+                kind,
+                segments.join("/")
+            )),
+        prop::collection::vec(segment(), 1..3).prop_map(|segments| segments.join("/")),
+    ]
+}
+
+#[derive(Debug, Clone)]
+enum Name {
+    None,
+    Alias(String),
+    Dot,
+    Blank,
+}
+
+#[derive(Debug, Clone)]
+struct Spec {
+    name: Name,
+    path: String,
+    raw: bool,
+}
+
+impl Spec {
+    fn render(&self) -> String {
+        let name = match &self.name {
+            Name::None => String::new(),
+            Name::Alias(alias) => format!("{} ", alias),
+            Name::Dot => ". ".to_string(),
+            Name::Blank => "_ ".to_string(),
+        };
+        let path = if self.raw {
+            format!("`{}`", self.path)
+        } else {
+            format!("\"{}\"", self.path)
+        };
+        format!("{}{}", name, path)
+    }
+}
+
+fn spec() -> impl Strategy<Value = Spec> {
+    let name = prop_oneof![
+        Just(Name::None),
+        ident().prop_map(Name::Alias),
+        Just(Name::Dot),
+        Just(Name::Blank),
+    ];
+    (name, import_path(), any::<bool>()).prop_map(|(name, path, raw)| Spec { name, path, raw })
+}
+
+fn fake_path() -> impl Strategy<Value = String> {
+    segment().prop_map(|s| format!("gno.land/p/fake/{}", s))
+}
+
+/// Comments that look like imports
+fn comment() -> impl Strategy<Value = String> {
+    prop_oneof![
+        fake_path().prop_map(|path| format!("// import \"{}\"\n", path)),
+        fake_path().prop_map(|path| format!("/* import (\n\t\"{}\"\n) */\n", path)),
+    ]
+}
+
+/// Declarations that mention imports without being one
+fn decoy() -> impl Strategy<Value = String> {
+    prop_oneof![
+        comment(),
+        (ident(), fake_path())
+            .prop_map(|(name, path)| format!("var {} = \"import \\\"{}\\\"\"\n", name, path)),
+        (ident(), fake_path())
+            .prop_map(|(name, path)| format!("var {} = `import \"{}\"`\n", name, path)),
+        (ident(), ident()).prop_map(|(func, arg)| format!(
+            "func {}({} string) string {{ return {} + \"import\" }}\n",
+            func, arg, arg
+        )),
+    ]
+}
+
+/// A source file: its package name, import declarations (grouped unless
+/// they hold a single spec), and decoys before and after them
+#[derive(Debug, Clone)]
+struct Source {
+    package: String,
+    declarations: Vec<Vec<Spec>>,
+    leading: Vec<String>,
+    trailing: Vec<String>,
+}
+
+impl Source {
+    fn render(&self) -> String {
+        let mut out = format!("package {}\n\n", self.package);
+        for comment in &self.leading {
+            out.push_str(comment);
+        }
+        for declaration in &self.declarations {
+            match declaration.as_slice() {
+                [single] => out.push_str(&format!("import {}\n", single.render())),
+                specs => {
+                    out.push_str("import (\n");
+                    for spec in specs {
+                        out.push_str(&format!("\t{}\n", spec.render()));
+                    }
+                    out.push_str(")\n");
+                }
+            }
+        }
+        out.push('\n');
+        for decoy in &self.trailing {
+            out.push_str(decoy);
+        }
+        out
+    }
+
+    fn expected_imports(&self) -> HashSet<String> {
+        self.declarations
+            .iter()
+            .flatten()
+            .map(|spec| spec.path.clone())
+            .filter(|path| path.starts_with("gno.land/"))
+            .collect()
+    }
+}
+
+fn source() -> impl Strategy<Value = Source> {
+    (
+        ident(),
+        prop::collection::vec(prop::collection::vec(spec(), 0..4), 0..4),
+        // only comments may come before imports
+        prop::collection::vec(comment(), 0..3),
+        prop::collection::vec(decoy(), 0..4),
+    )
+        .prop_map(|(package, declarations, leading, trailing)| Source {
+            package,
+            declarations,
+            leading,
+            trailing,
+        })
+}
+
+proptest! {
+    #[test]
+    fn test_finds_exactly_the_gno_land_imports(source in source()) {
+        let resolver = DependencyResolver::new().unwrap();
+        let code = source.render();
+
+        let (package, imports) = resolver.extract_dependencies(&code).unwrap();
+        prop_assert_eq!(package, source.package.clone(), "{}", code);
+        prop_assert_eq!(imports, source.expected_imports(), "{}", code);
+    }
+
+    #[test]
+    fn test_never_panics_on_arbitrary_text(code in ".{0,300}") {
+        let resolver = DependencyResolver::new().unwrap();
+        if let Ok((_, imports)) = resolver.extract_dependencies(&code) {
+            for import in imports {
+                prop_assert!(code.contains(&import), "{:?} isn't in {:?}", import, code);
+            }
+        }
+    }
+
+    #[test]
+    fn test_never_panics_on_mangled_sources(source in source(), cut in any::<prop::sample::Index>()) {
+        let resolver = DependencyResolver::new().unwrap();
+        let code = source.render();
+        let mut end = cut.index(code.len() + 1);
+        while !code.is_char_boundary(end) {
+            end -= 1;
+        }
+        let code = &code[..end];
+
+        if let Ok((_, imports)) = resolver.extract_dependencies(code) {
+            for import in imports {
+                prop_assert!(code.contains(&import), "{:?} isn't in {:?}", import, code);
+            }
+        }
+    }
+}
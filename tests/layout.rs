@@ -0,0 +1,85 @@
+use gget::fetch::{PackageManager, PackageManagerError};
+use gget::layout::{Layout, LayoutError};
+use gget::lock::{Lockfile, LOCKFILE_NAME};
+use gget::testing::{download_options, FakeChain};
+use gget::ErrorKind;
+use std::path::Path;
+use tempfile::tempdir;
+
+#[test]
+fn test_package_dirs() {
+    let output = Path::new("gno");
+    let pkg = "gno.land/p/demo/avl";
+    assert_eq!(Layout::Flat.package_dir(output, pkg), Path::new("gno"));
+    assert_eq!(
+        Layout::ByName.package_dir(output, pkg),
+        Path::new("gno/avl")
+    );
+    assert_eq!(
+        Layout::ByImportPath.package_dir(output, pkg),
+        Path::new("gno/gno.land/p/demo/avl")
+    );
+
+    for layout in Layout::ALL {
+        assert_eq!(layout.to_string().parse::<Layout>(), Ok(layout));
+    }
+    assert!("nested".parse::<Layout>().is_err());
+}
+
+#[test]
+fn test_colliding_packages_are_refused() {
+    let output = Path::new("gno");
+    let packages = ["gno.land/p/demo/avl", "gno.land/p/other/avl"];
+    assert!(Layout::ByImportPath.check(output, packages).is_ok());
+    assert!(Layout::Flat.check(output, ["gno.land/p/demo/avl"]).is_ok());
+
+    let err = Layout::ByName.check(output, packages).unwrap_err();
+    let LayoutError::Collision { dir, packages, .. } = &err else {
+        panic!("unexpected error: {}", err);
+    };
+    assert_eq!(dir, Path::new("gno/avl"));
+    assert_eq!(packages.len(), 2);
+}
+
+#[tokio::test]
+async fn test_dependencies_follow_the_layout() {
+    let chain = FakeChain::demo();
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let output = tempdir().unwrap();
+
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf())
+        .with_quiet(true)
+        .with_layout(Layout::ByName);
+    let summary = pm
+        .download_with_deps_parallel("gno.land/r/demo/app", output.path(), download_options())
+        .await
+        .unwrap();
+    assert!(summary.failed.is_empty(), "{}", summary);
+    assert!(output.path().join("app/app.gno").exists());
+    assert!(output.path().join("avl/avl.gno").exists());
+
+    // the lockfile knows where the packages are
+    let lock = Lockfile::load(&output.path().join(LOCKFILE_NAME)).unwrap();
+    assert_eq!(lock.layout, Layout::ByName);
+    assert!(lock.is_up_to_date("gno.land/r/demo/app", output.path()));
+}
+
+#[tokio::test]
+async fn test_flat_layout_holds_a_single_package() {
+    let chain = FakeChain::demo();
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let output = tempdir().unwrap();
+
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf())
+        .with_quiet(true)
+        .with_layout(Layout::Flat);
+    let err = pm
+        .download_with_deps_parallel("gno.land/r/demo/app", output.path(), download_options())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, PackageManagerError::Layout(_)), "{}", err);
+    assert_eq!(err.kind(), ErrorKind::Config);
+    assert_eq!(std::fs::read_dir(output.path()).unwrap().count(), 0);
+}
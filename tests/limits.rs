@@ -0,0 +1,204 @@
+use gget::error::ErrorKind;
+use gget::fetch::{PackageManager, PackageManagerError};
+use gget::limits::{parse_size, DownloadLimits, Limit};
+use gget::query::{RpcParams, RpcRequest};
+use gget::rpc::{HttpRpcClient, RpcClient, RpcClientError};
+use gget::testing::FakeChain;
+use tempfile::tempdir;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+fn manager(chain: FakeChain, cache: &std::path::Path, limits: DownloadLimits) -> PackageManager {
+    PackageManager::new(Some(chain.spawn()), cache.to_path_buf())
+        .with_quiet(true)
+        .with_limits(limits)
+}
+
+fn exceeded(error: PackageManagerError) -> (String, Limit, Option<String>) {
+    assert_eq!(error.code(), "limit_exceeded");
+    assert_eq!(error.kind(), ErrorKind::Config);
+    let message = error.to_string();
+    match error {
+        PackageManagerError::LimitExceeded { source, .. } => (message, source.limit, source.file),
+        other => panic!("unexpected error: {}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_packages_with_too_many_files_are_refused() {
+    let chain = FakeChain::new().with_package(
+        "gno.land/p/demo/avl",
+        &[
+            ("avl.gno", "package avl\n"),
+            ("node.gno", "package avl\n"),
+            ("tree.gno", "package avl\n"),
+        ],
+    );
+    let cache = tempdir().unwrap();
+    let pm = manager(
+        chain,
+        cache.path(),
+        DownloadLimits::default().with_max_files(2),
+    );
+
+    let out = tempdir().unwrap();
+    let target = out.path().join("avl");
+    let error = pm
+        .download_package("gno.land/p/demo/avl", &target)
+        .await
+        .unwrap_err();
+    let (message, limit, _) = exceeded(error);
+    assert_eq!(limit, Limit::Files);
+    assert_eq!(
+        message,
+        "Refusing to download gno.land/p/demo/avl: 3 files, more than the 2 allowed by --max-files"
+    );
+    assert!(!target.exists());
+}
+
+#[tokio::test]
+async fn test_oversized_files_and_packages_are_refused() {
+    let chain = FakeChain::new().with_package(
+        "gno.land/p/demo/avl",
+        &[("avl.gno", "package avl\n"), ("node.gno", "package avl\n")],
+    );
+    let cache = tempdir().unwrap();
+    let out = tempdir().unwrap();
+
+    let pm = manager(
+        chain.clone(),
+        cache.path(),
+        DownloadLimits::default().with_max_file_size(8),
+    );
+    let error = pm
+        .download_package("gno.land/p/demo/avl", &out.path().join("a"))
+        .await
+        .unwrap_err();
+    let (message, limit, file) = exceeded(error);
+    assert_eq!(limit, Limit::FileSize);
+    assert_eq!(file.as_deref(), Some("avl.gno"));
+    assert!(
+        message.ends_with("avl.gno has 12 bytes, more than the 8 allowed by --max-file-size"),
+        "{}",
+        message
+    );
+
+    let pm = manager(
+        chain,
+        cache.path(),
+        DownloadLimits::default().with_max_package_size(20),
+    );
+    let error = pm
+        .download_package("gno.land/p/demo/avl", &out.path().join("b"))
+        .await
+        .unwrap_err();
+    let (_, limit, file) = exceeded(error);
+    assert_eq!(limit, Limit::PackageSize);
+    assert_eq!(file.as_deref(), Some("node.gno"));
+    assert!(!out.path().join("b").exists());
+}
+
+#[tokio::test]
+async fn test_total_size_counts_only_completed_downloads() {
+    let chain = FakeChain::new()
+        .with_package("gno.land/p/demo/a", &[("a.gno", "package a\n")])
+        .with_package(
+            "gno.land/p/demo/big",
+            &[("big.gno", "package big\n\n// big\n")],
+        )
+        .with_package("gno.land/p/demo/b", &[("b.gno", "package b\n")]);
+    let cache = tempdir().unwrap();
+    let pm = manager(
+        chain,
+        cache.path(),
+        DownloadLimits::default().with_max_total_size(25),
+    );
+    let out = tempdir().unwrap();
+
+    pm.download_package("gno.land/p/demo/a", &out.path().join("a"))
+        .await
+        .unwrap();
+    let error = pm
+        .download_package("gno.land/p/demo/big", &out.path().join("big"))
+        .await
+        .unwrap_err();
+    let (message, limit, _) = exceeded(error);
+    assert_eq!(limit, Limit::TotalSize);
+    assert!(message.contains("30 bytes in total"), "{}", message);
+
+    // the refused package doesn't count against the next one
+    pm.download_package("gno.land/p/demo/b", &out.path().join("b"))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_oversized_responses_are_refused_before_buffering() {
+    let limits = DownloadLimits::default().with_max_file_size(8);
+    let max = limits.max_response_size().unwrap();
+    let big = format!("package big\n\n// {}\n", "x".repeat(2 * max as usize));
+    let chain = FakeChain::new().with_package("gno.land/p/demo/big", &[("big.gno", &big)]);
+    let cache = tempdir().unwrap();
+    let out = tempdir().unwrap();
+    let error = manager(chain, cache.path(), limits)
+        .download_package("gno.land/p/demo/big", &out.path().join("big"))
+        .await
+        .unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Config);
+    match error {
+        PackageManagerError::Download { source, .. } => assert!(
+            matches!(
+                *source,
+                PackageManagerError::Transport(RpcClientError::ResponseTooLarge { max: m }) if m == max
+            ),
+            "{}",
+            source
+        ),
+        other => panic!("unexpected error: {}", other),
+    }
+
+    // without a length up front, reading stops at the cap
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = [0; 4096];
+        let _ = socket.read(&mut request).await;
+        let head = "HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n";
+        if socket.write_all(head.as_bytes()).await.is_err() {
+            return;
+        }
+        let chunk = format!("400\r\n{}\r\n", "x".repeat(0x400));
+        while socket.write_all(chunk.as_bytes()).await.is_ok() {}
+    });
+    let client = HttpRpcClient::new(&format!("http://{}", addr))
+        .with_max_response_size(Some(64 * 1024))
+        .unwrap();
+    let request = RpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: 1,
+        method: "abci_query".to_string(),
+        params: RpcParams {
+            path: "vm/qfile".to_string(),
+            data: "gno.land/p/demo/big".to_string(),
+            height: None,
+        },
+    };
+    let error = client.send(&request).await.unwrap_err();
+    assert!(
+        matches!(error, RpcClientError::ResponseTooLarge { max: 65536 }),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn test_sizes_accept_suffixes() {
+    assert_eq!(parse_size("512"), Ok(512));
+    assert_eq!(parse_size("512k"), Ok(512 * 1024));
+    assert_eq!(parse_size("2M"), Ok(2 * 1024 * 1024));
+    assert_eq!(parse_size("1g"), Ok(1 << 30));
+    assert!(parse_size("0").is_err());
+    assert!(parse_size("lots").is_err());
+    assert!(parse_size("99999999999999g").is_err());
+}
@@ -0,0 +1,176 @@
+use gget::fetch::{PackageManager, PackageManagerError, ValidationError};
+use gget::lint::{DiagnosticKind, Severity, SyntaxChecker};
+use gget::report::{render, ReportFormat};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+#[test]
+fn test_error_and_missing_nodes_are_found_by_line() {
+    let mut checker = SyntaxChecker::new().unwrap();
+    let source = "package broken\n\nfunc Render(path string) string {\n\treturn \"x\" +\n}\n\nvar x = [1, 2\n\n)))) nonsense\n";
+
+    let errors = checker
+        .check_source(Path::new("broken.gno"), source)
+        .unwrap();
+    // an error spanning the rest of the file hides what's nested in it
+    let found: Vec<(usize, &str)> = errors
+        .iter()
+        .map(|e| (e.line, e.snippet.as_str()))
+        .collect();
+    assert_eq!(found, [(4, "return \"x\" +"), (7, "var x = [1, 2")]);
+    assert!(errors.iter().all(|e| e.severity() == Severity::Error));
+    assert_eq!(
+        errors[0].to_string(),
+        "broken.gno:4:13: syntax error: return \"x\" +"
+    );
+}
+
+#[test]
+fn test_missing_token() {
+    let mut checker = SyntaxChecker::new().unwrap();
+    let errors = checker
+        .check_source(Path::new("a.gno"), "package a\n\nfunc F() {\n\tg(1, 2\n}\n")
+        .unwrap();
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert_eq!(errors[0].missing.as_deref(), Some(")"));
+    assert_eq!(errors[0].line, 4);
+    assert_eq!(
+        errors[0].to_string(),
+        "a.gno:4:8: syntax error, missing `)`: g(1, 2"
+    );
+}
+
+#[test]
+fn test_valid_source_has_no_errors() {
+    let mut checker = SyntaxChecker::new().unwrap();
+    let source = "package avl\n\nimport \"gno.land/p/demo/ufmt\"\n\nfunc Render(path string) string {\n\treturn ufmt.Sprintf(\"%s\", path)\n}\n";
+    assert!(checker
+        .check_source(Path::new("avl.gno"), source)
+        .unwrap()
+        .is_empty());
+}
+
+fn package_with_garbage() -> tempfile::TempDir {
+    let dir = tempdir().unwrap();
+    let pkg = dir.path().join("gno.land/p/demo/broken");
+    fs::create_dir_all(&pkg).unwrap();
+    fs::write(pkg.join("ok.gno"), "package broken\n").unwrap();
+    fs::write(pkg.join("bad.gno"), "package broken\n\nfunc {{{ oops\n").unwrap();
+    dir
+}
+
+#[tokio::test]
+async fn test_validation_reports_syntax_errors() {
+    let dir = package_with_garbage();
+    let cache = tempdir().unwrap();
+
+    let report = PackageManager::new(None, cache.path().to_path_buf())
+        .validate_package(dir.path())
+        .await
+        .unwrap();
+    assert_eq!(report.files_checked, 2);
+    assert!(report.errors().count() > 0);
+    assert!(report
+        .diagnostics
+        .iter()
+        .all(|d| d.kind == DiagnosticKind::Syntax
+            && d.file.ends_with("gno.land/p/demo/broken/bad.gno")));
+    assert_eq!(report.severity(), Some(Severity::Error));
+
+    // not strict, so still passing
+    let report = report.into_result().unwrap();
+    let human = render(&report, ReportFormat::Human).unwrap();
+    assert!(human.contains("error: "), "{}", human);
+    assert!(
+        human.ends_with("(use --strict to fail on errors)\n"),
+        "{}",
+        human
+    );
+}
+
+#[tokio::test]
+async fn test_strict_validation_fails_on_syntax_errors() {
+    let dir = package_with_garbage();
+    let cache = tempdir().unwrap();
+
+    let err = PackageManager::new(None, cache.path().to_path_buf())
+        .with_strict_validation(true)
+        .validate_package(dir.path())
+        .await
+        .unwrap()
+        .into_result()
+        .unwrap_err();
+    let PackageManagerError::Validation {
+        source: ValidationError::Syntax(errors),
+        ..
+    } = &err
+    else {
+        panic!("unexpected error: {}", err);
+    };
+    assert!(!errors.is_empty());
+    assert!(err.to_string().contains("bad.gno:3:"), "{}", err);
+    assert_eq!(err.code(), "validation");
+}
+
+#[tokio::test]
+async fn test_missing_gnomod_and_unexpected_files() {
+    let dir = tempdir().unwrap();
+    let avl = dir.path().join("gno.land/p/demo/avl");
+    let ufmt = dir.path().join("gno.land/p/demo/ufmt");
+    fs::create_dir_all(&avl).unwrap();
+    fs::create_dir_all(&ufmt).unwrap();
+    fs::write(avl.join("avl.gno"), "package avl\n").unwrap();
+    fs::write(avl.join("gno.mod"), "module gno.land/p/demo/avl\n").unwrap();
+    fs::write(avl.join("README.md"), "# avl\n").unwrap();
+    fs::write(ufmt.join("ufmt.gno"), "package ufmt\n").unwrap();
+    // not in a package directory
+    fs::write(dir.path().join("gget.lock"), "{}").unwrap();
+
+    let cache = tempdir().unwrap();
+    let report = PackageManager::new(None, cache.path().to_path_buf())
+        .validate_package(dir.path())
+        .await
+        .unwrap();
+    assert_eq!(report.files_checked, 2);
+    assert!(report.diagnostics.is_empty());
+    assert_eq!(report.missing_gnomod, [ufmt]);
+    assert_eq!(report.unexpected_files, [avl.join("README.md")]);
+    assert_eq!(report.warning_count(), 2);
+    assert_eq!(report.severity(), Some(Severity::Warning));
+
+    let json: serde_json::Value =
+        serde_json::from_str(&render(&report, ReportFormat::Json).unwrap()).unwrap();
+    assert_eq!(json["files_checked"], 2);
+    assert_eq!(
+        json["unexpected_files"][0],
+        avl.join("README.md").display().to_string()
+    );
+    let human = render(&report, ReportFormat::Human).unwrap();
+    assert!(human.contains("README.md is not deployed by gnokey\n"));
+    assert!(
+        human.ends_with("Checked 2 files: 0 errors, 2 warnings\n"),
+        "{}",
+        human
+    );
+}
+
+#[tokio::test]
+async fn test_clean_package() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("avl.gno"), "package avl\n").unwrap();
+    fs::write(dir.path().join("gno.mod"), "module gno.land/p/demo/avl\n").unwrap();
+
+    let cache = tempdir().unwrap();
+    let report = PackageManager::new(None, cache.path().to_path_buf())
+        .validate_package(dir.path())
+        .await
+        .unwrap()
+        .into_result()
+        .unwrap();
+    assert_eq!(report.severity(), None);
+    assert_eq!(
+        render(&report, ReportFormat::Human).unwrap(),
+        "All 1 files are valid\n"
+    );
+}
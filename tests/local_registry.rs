@@ -0,0 +1,139 @@
+use gget::fetch::PackageManager;
+use gget::lock::{Lockfile, LOCKFILE_NAME};
+use gget::testing::{download_options, FakeChain};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+/// A checkout of the gno repository's examples, in miniature
+fn registry(root: &Path) {
+    let avl = root.join("gno.land/p/demo/avl");
+    fs::create_dir_all(avl.join("pager")).unwrap();
+    fs::write(
+        avl.join("avl.gno"),
+        "package avl // local\n\nimport \"gno.land/p/demo/ufmt\"\n",
+    )
+    .unwrap();
+    fs::write(avl.join("gno.mod"), "module gno.land/p/demo/avl\n").unwrap();
+    // a package of its own
+    fs::write(avl.join("pager/pager.gno"), "package pager\n").unwrap();
+    fs::write(
+        avl.join("pager/gno.mod"),
+        "module gno.land/p/demo/avl/pager\n",
+    )
+    .unwrap();
+
+    let draft = root.join("gno.land/p/demo/draft");
+    fs::create_dir_all(&draft).unwrap();
+    fs::write(draft.join("draft.gno"), "package draft\n").unwrap();
+}
+
+fn chain() -> FakeChain {
+    FakeChain::new()
+        .with_package(
+            "gno.land/p/demo/avl",
+            &[(
+                "avl.gno",
+                "package avl\n\nimport \"gno.land/p/demo/ufmt\"\n",
+            )],
+        )
+        .with_package("gno.land/p/demo/ufmt", &[("ufmt.gno", "package ufmt\n")])
+}
+
+#[tokio::test]
+async fn test_prefer_local_skips_the_node() {
+    let chain = chain();
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let examples = tempdir().unwrap();
+    registry(examples.path());
+    let target = tempdir().unwrap();
+
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf())
+        .with_quiet(true)
+        .with_local_registry(examples.path())
+        .with_prefer_local(true);
+    assert!(pm.registry_package("gno.land/p/demo/avl").is_some());
+    assert!(pm.registry_package("gno.land/p/demo/ufmt").is_none());
+    assert!(pm.registry_package("gno.land/p/demo").is_none());
+
+    let summary = pm
+        .download_roots_with_deps_parallel(
+            &["gno.land/p/demo/avl"],
+            target.path(),
+            download_options(),
+        )
+        .await
+        .unwrap();
+    assert!(summary.failed.is_empty());
+
+    let avl = target.path().join("gno.land/p/demo/avl");
+    assert!(fs::read_to_string(avl.join("avl.gno"))
+        .unwrap()
+        .contains("// local"));
+    assert!(!avl.join("pager").exists());
+    // what it imports still comes from the chain
+    assert!(target.path().join("gno.land/p/demo/ufmt/ufmt.gno").exists());
+    assert!(chain
+        .queries()
+        .iter()
+        .all(|query| !query.data.contains("gno.land/p/demo/avl")));
+    let lock = Lockfile::load(&target.path().join(LOCKFILE_NAME)).unwrap();
+    assert_eq!(lock.packages.len(), 2);
+}
+
+#[tokio::test]
+async fn test_local_registry_serves_what_the_node_lacks() {
+    let chain = chain();
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let examples = tempdir().unwrap();
+    registry(examples.path());
+    let target = tempdir().unwrap();
+
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf())
+        .with_quiet(true)
+        .with_local_registry(examples.path());
+    let summary = pm
+        .download_roots_with_deps_parallel(
+            &["gno.land/p/demo/avl", "gno.land/p/demo/draft"],
+            target.path(),
+            download_options(),
+        )
+        .await
+        .unwrap();
+    assert!(summary.failed.is_empty());
+
+    // the node's copy wins when it has one
+    assert_eq!(
+        fs::read_to_string(target.path().join("gno.land/p/demo/avl/avl.gno")).unwrap(),
+        "package avl\n\nimport \"gno.land/p/demo/ufmt\"\n"
+    );
+    assert_eq!(
+        fs::read_to_string(target.path().join("gno.land/p/demo/draft/draft.gno")).unwrap(),
+        "package draft\n"
+    );
+
+    // and without a node at all
+    let offline_target = tempdir().unwrap();
+    let offline = PackageManager::builder()
+        .cache_dir(tempdir().unwrap().path())
+        .offline(true)
+        .build()
+        .unwrap()
+        .with_quiet(true)
+        .with_local_registry(examples.path());
+    let summary = offline
+        .download_roots_with_deps_parallel(
+            &["gno.land/p/demo/draft"],
+            offline_target.path(),
+            download_options(),
+        )
+        .await
+        .unwrap();
+    assert!(summary.failed.is_empty());
+    assert!(offline_target
+        .path()
+        .join("gno.land/p/demo/draft/draft.gno")
+        .exists());
+}
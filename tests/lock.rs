@@ -0,0 +1,397 @@
+use gget::fetch::PackageManager;
+use gget::lock::{
+    hash_content, hash_dir, hash_files, select_heights, LockError, LockedPackage, Lockfile, Pin,
+    LOCKFILE_NAME,
+};
+use gget::testing::{download_options, FakeChain};
+use std::collections::BTreeMap;
+use std::fs;
+use tempfile::tempdir;
+
+fn pin(root: &str, path: &str, height: u64, hash: &str) -> Pin {
+    Pin {
+        root: root.to_string(),
+        path: path.to_string(),
+        height,
+        hash: hash.to_string(),
+    }
+}
+
+fn locked(path: &str, height: u64, hash: &str) -> LockedPackage {
+    LockedPackage {
+        path: path.to_string(),
+        height: Some(height),
+        hash: hash.to_string(),
+        files: BTreeMap::new(),
+        required_by: Vec::new(),
+    }
+}
+
+#[test]
+fn test_highest_pinned_height_wins() {
+    let pins = vec![
+        pin("app", "gno.land/p/demo/avl", 100, "aaa"),
+        pin("tool", "gno.land/p/demo/avl", 250, "bbb"),
+        pin("tool", "gno.land/p/demo/ufmt", 90, "ccc"),
+    ];
+
+    let selections = select_heights(&pins).unwrap();
+    assert_eq!(selections.len(), 2);
+
+    let avl = &selections[0];
+    assert_eq!(avl.path, "gno.land/p/demo/avl");
+    assert_eq!(avl.height, 250);
+    assert_eq!(avl.hash, "bbb");
+    assert_eq!(avl.roots, vec!["tool".to_string()]);
+    assert_eq!(avl.overridden, vec![("app".to_string(), 100)]);
+    assert!(avl.to_string().contains("overrides 100 from app"));
+}
+
+#[test]
+fn test_selection_is_order_independent() {
+    let mut pins = vec![
+        pin("b", "gno.land/p/demo/avl", 10, "x"),
+        pin("a", "gno.land/p/demo/avl", 10, "x"),
+        pin("c", "gno.land/p/demo/json", 3, "y"),
+    ];
+    let first = select_heights(&pins).unwrap();
+    pins.reverse();
+    let second = select_heights(&pins).unwrap();
+
+    assert_eq!(first, second);
+    assert_eq!(first[0].roots, vec!["a".to_string(), "b".to_string()]);
+}
+
+#[test]
+fn test_conflicting_hashes_at_selected_height() {
+    let pins = vec![
+        pin("app", "gno.land/p/demo/avl", 300, "aaa"),
+        pin("tool", "gno.land/p/demo/avl", 300, "bbb"),
+        // lower heights never conflict with the winner
+        pin("old", "gno.land/p/demo/avl", 1, "zzz"),
+    ];
+
+    match select_heights(&pins) {
+        Err(LockError::HashConflict { path, height, pins }) => {
+            assert_eq!(path, "gno.land/p/demo/avl");
+            assert_eq!(height, 300);
+            assert_eq!(pins.len(), 2);
+        }
+        other => panic!("expected hash conflict, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_merge_roots_records_requirers() {
+    let mut app = Lockfile::default();
+    app.insert(locked("gno.land/p/demo/avl", 100, "aaa"));
+    let mut tool = Lockfile::default();
+    tool.insert(locked("gno.land/p/demo/avl", 200, "bbb"));
+    tool.insert(locked("gno.land/p/demo/ufmt", 5, "ccc"));
+
+    let (merged, selections) =
+        Lockfile::merge_roots(&[("app".to_string(), app), ("tool".to_string(), tool)]).unwrap();
+
+    assert_eq!(selections.len(), 2);
+    let avl = merged.get("gno.land/p/demo/avl").unwrap();
+    assert_eq!(avl.height, Some(200));
+    assert_eq!(avl.hash, "bbb");
+    assert_eq!(avl.required_by, vec!["tool".to_string()]);
+}
+
+#[test]
+fn test_lockfile_roundtrip_and_dir_hash() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("a.gno"), "package a\n").unwrap();
+    fs::create_dir(dir.path().join("sub")).unwrap();
+    fs::write(dir.path().join("sub").join("b.gno"), "package b\n").unwrap();
+
+    let (hash, files) = hash_dir(dir.path()).unwrap();
+    assert_eq!(files.len(), 2);
+    assert_eq!(files["a.gno"], hash_content(b"package a\n"));
+    assert!(files.contains_key("sub/b.gno"));
+
+    let mut lock = Lockfile::default();
+    lock.insert(LockedPackage {
+        files,
+        ..locked("gno.land/p/demo/a", 1, &hash)
+    });
+    let lock_path = dir.path().join(LOCKFILE_NAME);
+    lock.save(&lock_path).unwrap();
+
+    // the lockfile itself must not affect the package hash
+    let (rehash, _) = hash_dir(dir.path()).unwrap();
+    assert_eq!(hash, rehash);
+    assert_eq!(Lockfile::load(&lock_path).unwrap(), lock);
+}
+
+#[test]
+fn test_up_to_date_detection() {
+    let dir = tempdir().unwrap();
+    let avl_dir = dir.path().join("gno.land/p/demo/avl");
+    let pager_dir = avl_dir.join("pager");
+    fs::create_dir_all(&pager_dir).unwrap();
+    fs::write(avl_dir.join("avl.gno"), "package avl\n").unwrap();
+    fs::write(pager_dir.join("pager.gno"), "package pager\n").unwrap();
+
+    let nested = vec![pager_dir.clone()];
+    let avl = LockedPackage::from_dir("gno.land/p/demo/avl", &avl_dir, &nested).unwrap();
+    assert_eq!(avl.files.len(), 1, "nested package must be excluded");
+
+    let mut lock = Lockfile {
+        roots: vec!["gno.land/p/demo/avl".to_string()],
+        ..Default::default()
+    };
+    lock.insert(avl);
+    lock.insert(LockedPackage::from_dir("gno.land/p/demo/avl/pager", &pager_dir, &[]).unwrap());
+
+    assert!(lock.is_up_to_date("gno.land/p/demo/avl", dir.path()));
+    assert!(!lock.is_up_to_date("gno.land/p/demo/other", dir.path()));
+
+    // editing a subpackage only invalidates the subpackage
+    fs::write(pager_dir.join("pager.gno"), "package pager // edited\n").unwrap();
+    assert!(lock.get("gno.land/p/demo/avl").unwrap().is_intact(&avl_dir));
+    assert_eq!(
+        lock.get("gno.land/p/demo/avl/pager")
+            .unwrap()
+            .changed_files(&pager_dir),
+        vec!["pager.gno".to_string()]
+    );
+    assert!(!lock.is_up_to_date("gno.land/p/demo/avl", dir.path()));
+
+    fs::remove_dir_all(&pager_dir).unwrap();
+    assert!(!lock.is_up_to_date("gno.land/p/demo/avl", dir.path()));
+}
+
+#[test]
+fn test_merge3_unions_independent_changes() {
+    let mut base = Lockfile {
+        roots: vec!["app".to_string(), "old".to_string()],
+        ..Default::default()
+    };
+    base.insert(locked("gno.land/p/demo/avl", 1, "a1"));
+    base.insert(locked("gno.land/p/demo/gone", 1, "g1"));
+
+    // ours bumps avl and drops the old root
+    let mut ours = base.clone();
+    ours.roots.retain(|r| r != "old");
+    ours.insert(locked("gno.land/p/demo/avl", 2, "a2"));
+    ours.packages.remove("gno.land/p/demo/gone");
+
+    // theirs adds a new root with its own package
+    let mut theirs = base.clone();
+    theirs.roots.push("tool".to_string());
+    theirs.insert(locked("gno.land/p/demo/json", 7, "j7"));
+
+    let (merged, conflicts) = Lockfile::merge3(&base, &ours, &theirs);
+    assert!(conflicts.is_empty());
+    assert_eq!(merged.roots, vec!["app".to_string(), "tool".to_string()]);
+    assert_eq!(merged.get("gno.land/p/demo/avl").unwrap().hash, "a2");
+    assert!(merged.get("gno.land/p/demo/json").is_some());
+    assert!(merged.get("gno.land/p/demo/gone").is_none());
+}
+
+#[test]
+fn test_merge3_same_hash_is_not_a_conflict() {
+    let base = Lockfile::default();
+    let mut ours = Lockfile::default();
+    ours.insert(LockedPackage {
+        required_by: vec!["app".to_string()],
+        ..locked("gno.land/p/demo/avl", 3, "same")
+    });
+    let mut theirs = Lockfile::default();
+    theirs.insert(LockedPackage {
+        required_by: vec!["tool".to_string()],
+        ..locked("gno.land/p/demo/avl", 5, "same")
+    });
+
+    let (merged, conflicts) = Lockfile::merge3(&base, &ours, &theirs);
+    assert!(conflicts.is_empty());
+    let avl = merged.get("gno.land/p/demo/avl").unwrap();
+    assert_eq!(avl.height, Some(5));
+    assert_eq!(avl.required_by, vec!["app".to_string(), "tool".to_string()]);
+}
+
+#[test]
+fn test_merge3_reports_hash_divergence() {
+    let mut base = Lockfile::default();
+    base.insert(locked("gno.land/p/demo/avl", 1, "base"));
+    let mut ours = base.clone();
+    ours.insert(locked("gno.land/p/demo/avl", 2, "ours"));
+    let mut theirs = base.clone();
+    theirs.insert(locked("gno.land/p/demo/avl", 2, "theirs"));
+
+    let (merged, conflicts) = Lockfile::merge3(&base, &ours, &theirs);
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].path, "gno.land/p/demo/avl");
+    assert_eq!(conflicts[0].theirs, "theirs");
+    assert_eq!(merged.get("gno.land/p/demo/avl").unwrap().hash, "ours");
+}
+
+#[test]
+fn test_load_or_default_accepts_empty_base() {
+    let dir = tempdir().unwrap();
+    let empty = dir.path().join("base.lock");
+    fs::write(&empty, "").unwrap();
+
+    assert_eq!(
+        Lockfile::load_or_default(&empty).unwrap(),
+        Lockfile::default()
+    );
+    assert_eq!(
+        Lockfile::load_or_default(&dir.path().join("missing.lock")).unwrap(),
+        Lockfile::default()
+    );
+}
+
+#[test]
+fn test_diff_lists_added_removed_and_changed() {
+    let mut old = Lockfile::default();
+    old.insert(locked("gno.land/p/demo/avl", 10, "aaa"));
+    old.insert(locked("gno.land/p/demo/ufmt", 10, "bbb"));
+    old.insert(locked("gno.land/p/demo/gone", 10, "ccc"));
+
+    let mut new = Lockfile::default();
+    new.insert(locked("gno.land/p/demo/avl", 10, "aaa"));
+    new.insert(locked("gno.land/p/demo/ufmt", 12, "bbb2"));
+    new.insert(locked("gno.land/p/demo/seqid", 12, "ddd"));
+
+    let diff = old.diff(&new);
+    assert_eq!(diff.added, vec!["gno.land/p/demo/seqid"]);
+    assert_eq!(diff.removed, vec!["gno.land/p/demo/gone"]);
+    assert_eq!(diff.changed.len(), 1);
+    assert_eq!(diff.changed[0].path, "gno.land/p/demo/ufmt");
+    assert_eq!(diff.changed[0].old_height, Some(10));
+    assert_eq!(diff.changed[0].new_hash, "bbb2");
+
+    assert!(new.diff(&new).is_empty());
+}
+
+fn locked_files(path: &str, files: &[(&str, &str)]) -> LockedPackage {
+    let files: BTreeMap<String, String> = files
+        .iter()
+        .map(|(name, content)| (name.to_string(), hash_content(content.as_bytes())))
+        .collect();
+    LockedPackage {
+        path: path.to_string(),
+        height: Some(10),
+        hash: hash_files(&files),
+        files,
+        required_by: Vec::new(),
+    }
+}
+
+#[test]
+fn test_diff_detects_moved_packages_and_renamed_files() {
+    let mut old = Lockfile::default();
+    old.insert(locked_files(
+        "gno.land/p/demo/tree",
+        &[
+            ("tree.gno", "package tree"),
+            ("node.gno", "node"),
+            ("util.gno", "util"),
+        ],
+    ));
+    old.insert(locked_files(
+        "gno.land/p/demo/gone",
+        &[("gone.gno", "gone")],
+    ));
+    old.insert(locked_files(
+        "gno.land/p/demo/ufmt",
+        &[("ufmt.gno", "fmt"), ("helpers.gno", "helpers")],
+    ));
+
+    let mut new = Lockfile::default();
+    // same files except one, one of them renamed
+    new.insert(locked_files(
+        "gno.land/p/moul/tree",
+        &[
+            ("tree.gno", "package tree"),
+            ("nodes.gno", "node"),
+            ("util.gno", "util v2"),
+        ],
+    ));
+    new.insert(locked_files(
+        "gno.land/p/demo/fresh",
+        &[("fresh.gno", "fresh")],
+    ));
+    new.insert(locked_files(
+        "gno.land/p/demo/ufmt",
+        &[("ufmt.gno", "fmt"), ("internal.gno", "helpers")],
+    ));
+
+    let diff = old.diff(&new);
+    assert_eq!(diff.added, vec!["gno.land/p/demo/fresh"]);
+    assert_eq!(diff.removed, vec!["gno.land/p/demo/gone"]);
+
+    assert_eq!(diff.moved.len(), 1);
+    let moved = &diff.moved[0];
+    assert_eq!(moved.from, "gno.land/p/demo/tree");
+    assert_eq!(moved.to, "gno.land/p/moul/tree");
+    assert_eq!(moved.similarity, 66);
+    assert_eq!(moved.renamed_files.len(), 1);
+    assert_eq!(moved.renamed_files[0].from, "node.gno");
+    assert_eq!(moved.renamed_files[0].to, "nodes.gno");
+
+    assert_eq!(diff.changed.len(), 1);
+    assert_eq!(diff.changed[0].renamed_files[0].from, "helpers.gno");
+    assert_eq!(diff.changed[0].renamed_files[0].to, "internal.gno");
+}
+
+#[test]
+fn test_diff_keeps_dissimilar_packages_apart() {
+    let mut old = Lockfile::default();
+    old.insert(locked_files(
+        "gno.land/p/demo/a",
+        &[("a.gno", "a"), ("b.gno", "b"), ("c.gno", "c")],
+    ));
+    let mut new = Lockfile::default();
+    new.insert(locked_files(
+        "gno.land/p/demo/z",
+        &[("a.gno", "a"), ("y.gno", "y"), ("z.gno", "z")],
+    ));
+
+    let diff = old.diff(&new);
+    assert!(diff.moved.is_empty());
+    assert_eq!(diff.added, vec!["gno.land/p/demo/z"]);
+    assert_eq!(diff.removed, vec!["gno.land/p/demo/a"]);
+}
+
+#[tokio::test]
+async fn test_selected_heights_are_fetched_planned_and_locked() {
+    let chain =
+        FakeChain::new().with_package("gno.land/p/demo/avl", &[("avl.gno", "package avl\n")]);
+    chain.advance();
+    chain.set_file("gno.land/p/demo/avl", "avl.gno", "package avl // 2\n");
+    chain.advance();
+    let url = chain.spawn();
+    let mut app = Lockfile::default();
+    app.insert(locked("gno.land/p/demo/avl", 1, "aaa"));
+    let mut tool = Lockfile::default();
+    tool.insert(locked("gno.land/p/demo/avl", 2, "bbb"));
+    let (_, selections) =
+        Lockfile::merge_roots(&[("app".to_string(), app), ("tool".to_string(), tool)]).unwrap();
+
+    let cache = tempdir().unwrap();
+    let target = tempdir().unwrap();
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf())
+        .with_quiet(true)
+        .with_selections(selections.clone());
+    let plan = pm
+        .plan_download(&["gno.land/p/demo/avl"], target.path(), true)
+        .await
+        .unwrap();
+    assert_eq!(plan.selections, selections);
+
+    let options = download_options();
+    pm.download_roots_with_deps_parallel(&["gno.land/p/demo/avl"], target.path(), options)
+        .await
+        .unwrap();
+    assert_eq!(
+        fs::read_to_string(target.path().join("gno.land/p/demo/avl/avl.gno")).unwrap(),
+        "package avl // 2\n"
+    );
+    let lock = Lockfile::load(&target.path().join(LOCKFILE_NAME)).unwrap();
+    assert_eq!(lock.packages["gno.land/p/demo/avl"].height, Some(2));
+    assert_eq!(lock.selections, selections);
+}
@@ -0,0 +1,83 @@
+use gget::fetch::PackageManager;
+use gget::testing::{download_options, FakeChain};
+use std::time::Duration;
+use tempfile::tempdir;
+
+/// `gno.land/r/demo/app` importing a package that doesn't exist
+fn chain() -> FakeChain {
+    FakeChain::new().with_package(
+        "gno.land/r/demo/app",
+        &[(
+            "app.gno",
+            "package app\n\nimport \"gno.land/p/demo/missing\"\n",
+        )],
+    )
+}
+
+fn missing_queries(chain: &FakeChain) -> usize {
+    chain
+        .queries()
+        .iter()
+        .filter(|query| query.data == "gno.land/p/demo/missing")
+        .count()
+}
+
+#[tokio::test]
+async fn test_missing_packages_are_remembered() {
+    let chain = chain();
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let target = tempdir().unwrap();
+    let options = download_options();
+
+    for _ in 0..3 {
+        let pm =
+            PackageManager::new(Some(url.clone()), cache.path().to_path_buf()).with_quiet(true);
+        let err = pm
+            .download_with_deps_parallel("gno.land/r/demo/app", target.path(), options.clone())
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("gno.land/p/demo/missing"),
+            "{}",
+            err
+        );
+    }
+    assert_eq!(missing_queries(&chain), 1);
+
+    // the node is asked again when refreshing, and once it's published
+    chain.advance();
+    chain.publish(
+        "gno.land/p/demo/missing",
+        &[("missing.gno", "package missing")],
+    );
+    let pm = PackageManager::new(Some(url.clone()), cache.path().to_path_buf())
+        .with_quiet(true)
+        .with_refresh(true);
+    let summary = pm
+        .download_with_deps_parallel("gno.land/r/demo/app", target.path(), options)
+        .await
+        .unwrap();
+    assert!(summary.failed.is_empty(), "{}", summary);
+    assert!(missing_queries(&chain) > 1);
+}
+
+#[tokio::test]
+async fn test_negative_caching_can_be_disabled() {
+    let chain = chain();
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+
+    let pm = PackageManager::builder()
+        .endpoint(&url)
+        .cache_dir(cache.path())
+        .negative_ttl(Duration::ZERO)
+        .build()
+        .unwrap();
+    for _ in 0..2 {
+        pm.download_package("gno.land/p/demo/missing", tempdir().unwrap().path())
+            .await
+            .unwrap_err();
+    }
+    assert_eq!(missing_queries(&chain), 2);
+}
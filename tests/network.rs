@@ -0,0 +1,90 @@
+use gget::endpoint::EndpointError;
+use gget::fetch::{PackageManager, PackageManagerError};
+use gget::network::{Network, NetworkError, NETWORKS};
+use gget::testing::FakeChain;
+use gget::ErrorKind;
+use tempfile::tempdir;
+
+#[test]
+fn test_networks_are_found_by_name() {
+    let test5 = Network::find("test5").unwrap();
+    assert_eq!(test5.chain_id, "test5");
+    assert_eq!(Network::find("local").unwrap().chain_id, "dev");
+    assert_eq!(Network::names().len(), NETWORKS.len());
+
+    let err = Network::find("mainnet").unwrap_err();
+    assert!(matches!(&err, NetworkError::Unknown(name) if name == "mainnet"));
+    assert_eq!(err.kind(), ErrorKind::Config);
+    assert!(err.to_string().contains("portal-loop"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_check_compares_chain_ids() {
+    let client = reqwest::Client::new();
+    let url = FakeChain::new().with_chain_id("test5").spawn();
+    let test5 = Network::find("test5").unwrap();
+    test5.check(&client, &url).await.unwrap();
+
+    let err = Network::find("local")
+        .unwrap()
+        .check(&client, &url)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, NetworkError::ChainIdMismatch { expected, actual, .. }
+            if expected == "dev" && actual == "test5"),
+        "{}",
+        err
+    );
+    assert_eq!(err.kind(), ErrorKind::Config);
+
+    let silent = FakeChain::new().spawn();
+    let err = test5.check(&client, &silent).await.unwrap_err();
+    assert!(matches!(
+        err,
+        NetworkError::Status {
+            source: EndpointError::MissingChainId,
+            ..
+        }
+    ));
+}
+
+#[tokio::test]
+async fn test_cache_namespaces_are_kept_apart() {
+    let chain = FakeChain::new().with_package("gno.land/p/demo/avl", &[("avl.gno", "package avl")]);
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let manager = |namespace: &str, offline: bool| {
+        PackageManager::builder()
+            .endpoint(&url)
+            .cache_dir(cache.path())
+            .cache_namespace(namespace)
+            .offline(offline)
+            .build()
+            .unwrap()
+            .with_quiet(true)
+    };
+
+    let target = tempdir().unwrap();
+    manager("test5", false)
+        .download_package("gno.land/p/demo/avl", target.path())
+        .await
+        .unwrap();
+
+    // same cache directory, other network
+    let target = tempdir().unwrap();
+    let err = manager("staging", true)
+        .download_package("gno.land/p/demo/avl", target.path())
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, PackageManagerError::Download { source, .. }
+            if matches!(**source, PackageManagerError::Offline)),
+        "{}",
+        err
+    );
+    manager("test5", true)
+        .download_package("gno.land/p/demo/avl", target.path())
+        .await
+        .unwrap();
+}
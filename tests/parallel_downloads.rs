@@ -1,4 +1,8 @@
-use gget::parallel::{DownloadError, DownloadManager, DownloadTask, ProgressUpdate, RetryConfig};
+use gget::parallel::{
+    DownloadError, DownloadManager, DownloadOutcome, DownloadState, DownloadSummary, DownloadTask,
+    PackageStats, ProgressUpdate, RetryConfig,
+};
+use gget::ErrorKind;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
@@ -17,6 +21,7 @@ async fn test_download_manager_basic() {
             target_dir: PathBuf::from(format!("/tmp/pkg{}", i)),
             priority: i as u8,
             retry_config: RetryConfig::default(),
+            ..Default::default()
         };
 
         manager.queue_download(task).await.unwrap();
@@ -32,8 +37,8 @@ async fn test_download_manager_basic() {
             // Simulate download
             sleep(Duration::from_millis(100)).await;
             count.fetch_add(1, Ordering::SeqCst);
-            Ok(())
-        }) as futures::future::BoxFuture<'static, Result<(), DownloadError>>
+            Ok(PackageStats::default())
+        }) as futures::future::BoxFuture<'static, Result<PackageStats, DownloadError>>
     };
 
     let summary = manager.process_queue(download_fn).await.unwrap();
@@ -59,6 +64,7 @@ async fn test_download_manager_with_failures() {
                 max_attempts: 1,
                 ..Default::default()
             },
+            ..Default::default()
         };
 
         manager.queue_download(task).await.unwrap();
@@ -70,9 +76,9 @@ async fn test_download_manager_with_failures() {
             if task.package_id.ends_with("0") || task.package_id.ends_with("2") {
                 Err(DownloadError::Network("Simulated failure".to_string()))
             } else {
-                Ok(())
+                Ok(PackageStats::default())
             }
-        }) as futures::future::BoxFuture<'static, Result<(), DownloadError>>
+        }) as futures::future::BoxFuture<'static, Result<PackageStats, DownloadError>>
     };
 
     let summary = manager.process_queue(download_fn).await.unwrap();
@@ -98,6 +104,7 @@ async fn test_download_manager_priority() {
             target_dir: PathBuf::from(format!("/tmp/{}", name)),
             priority,
             retry_config: RetryConfig::default(),
+            ..Default::default()
         };
 
         manager.queue_download(task).await.unwrap();
@@ -108,8 +115,8 @@ async fn test_download_manager_priority() {
         let order = Arc::clone(&order_clone);
         Box::pin(async move {
             order.lock().await.push(task.package_id);
-            Ok(())
-        }) as futures::future::BoxFuture<'static, Result<(), DownloadError>>
+            Ok(PackageStats::default())
+        }) as futures::future::BoxFuture<'static, Result<PackageStats, DownloadError>>
     };
 
     manager.process_queue(download_fn).await.unwrap();
@@ -138,7 +145,9 @@ async fn test_download_manager_retry() {
             initial_backoff: Duration::from_millis(10),
             max_backoff: Duration::from_millis(100),
             multiplier: 2.0,
+            jitter: 0.0,
         },
+        ..Default::default()
     };
 
     manager.queue_download(task).await.unwrap();
@@ -151,9 +160,9 @@ async fn test_download_manager_retry() {
             if attempts < 3 {
                 Err(DownloadError::Network("Retry me".to_string()))
             } else {
-                Ok(())
+                Ok(PackageStats::default())
             }
-        }) as futures::future::BoxFuture<'static, Result<(), DownloadError>>
+        }) as futures::future::BoxFuture<'static, Result<PackageStats, DownloadError>>
     };
 
     let summary = manager.process_queue(download_fn).await.unwrap();
@@ -169,7 +178,7 @@ async fn test_progress_tracker() {
     let progress = manager.progress();
 
     // Get update receiver
-    let update_rx = progress.get_update_receiver();
+    let mut update_rx = progress.get_update_receiver();
 
     // Queue a task
     let task = DownloadTask {
@@ -178,6 +187,7 @@ async fn test_progress_tracker() {
         target_dir: PathBuf::from("/tmp/progress"),
         priority: 1,
         retry_config: RetryConfig::default(),
+        ..Default::default()
     };
 
     manager.queue_download(task).await.unwrap();
@@ -185,11 +195,9 @@ async fn test_progress_tracker() {
     // Spawn a task to collect progress updates
     let updates = Arc::new(tokio::sync::Mutex::new(Vec::new()));
     let updates_clone = Arc::clone(&updates);
-    let update_rx_clone = Arc::clone(&update_rx);
 
     let collector_task = tokio::spawn(async move {
-        let mut rx = update_rx_clone.lock().await;
-        while let Some(update) = rx.recv().await {
+        while let Ok(update) = update_rx.recv().await {
             updates_clone.lock().await.push(update);
         }
     });
@@ -197,8 +205,8 @@ async fn test_progress_tracker() {
     let download_fn = move |_task: DownloadTask| {
         Box::pin(async move {
             sleep(Duration::from_millis(10)).await;
-            Ok(())
-        }) as futures::future::BoxFuture<'static, Result<(), DownloadError>>
+            Ok(PackageStats::default())
+        }) as futures::future::BoxFuture<'static, Result<PackageStats, DownloadError>>
     };
 
     let _ = manager.process_queue(download_fn).await.unwrap();
@@ -227,6 +235,12 @@ async fn test_progress_tracker() {
     assert!(collected_updates
         .iter()
         .any(|u| matches!(u, ProgressUpdate::Completed { .. })));
+
+    let packages = progress.get_progress().await;
+    assert!(matches!(
+        packages["progress_test"].state,
+        DownloadState::Completed
+    ));
 }
 
 #[tokio::test]
@@ -245,6 +259,7 @@ async fn test_concurrent_downloads() {
             target_dir: PathBuf::from(format!("/tmp/concurrent{}", i)),
             priority: 0,
             retry_config: RetryConfig::default(),
+            ..Default::default()
         };
 
         manager.queue_download(task).await.unwrap();
@@ -277,8 +292,8 @@ async fn test_concurrent_downloads() {
             // Decrement concurrent count
             concurrent.fetch_sub(1, Ordering::SeqCst);
 
-            Ok(())
-        }) as futures::future::BoxFuture<'static, Result<(), DownloadError>>
+            Ok(PackageStats::default())
+        }) as futures::future::BoxFuture<'static, Result<PackageStats, DownloadError>>
     };
 
     let summary = manager.process_queue(download_fn).await.unwrap();
@@ -293,3 +308,667 @@ async fn test_concurrent_downloads() {
     // Should have had at most 4 concurrent downloads
     assert!(max_concurrent.load(Ordering::SeqCst) <= 4);
 }
+
+#[tokio::test]
+async fn test_failed_download_reports_import_chain() {
+    let manager = DownloadManager::new(1);
+
+    let task = DownloadTask {
+        package_id: "gno.land/p/demo/leaf".to_string(),
+        package_path: "gno.land/p/demo/leaf".to_string(),
+        target_dir: PathBuf::from("/tmp/leaf"),
+        priority: 1,
+        retry_config: RetryConfig {
+            max_attempts: 1,
+            ..Default::default()
+        },
+        required_by: vec![
+            "gno.land/p/demo/mid".to_string(),
+            "gno.land/r/demo/root".to_string(),
+        ],
+        ..Default::default()
+    };
+    manager.queue_download(task).await.unwrap();
+
+    let download_fn = move |_task: DownloadTask| {
+        Box::pin(async move { Err(DownloadError::Network("unreachable".to_string())) })
+            as futures::future::BoxFuture<'static, Result<PackageStats, DownloadError>>
+    };
+
+    let summary = manager.process_queue(download_fn).await.unwrap();
+
+    assert_eq!(summary.failed.len(), 1);
+    assert_eq!(summary.failed[0].required_by.len(), 2);
+    // the last attempt's error is kept as the cause
+    let last = std::error::Error::source(&summary.failed[0].error).unwrap();
+    assert_eq!(last.to_string(), "Network error: unreachable");
+    let report = summary.to_string();
+    assert!(
+        report.contains("needed by gno.land/p/demo/mid ← gno.land/r/demo/root"),
+        "unexpected report: {}",
+        report
+    );
+    assert!(
+        report.contains("unreachable"),
+        "unexpected report: {}",
+        report
+    );
+}
+
+#[test]
+fn test_retry_config_for_blocker() {
+    let base = RetryConfig {
+        max_attempts: 3,
+        initial_backoff: Duration::from_millis(800),
+        ..Default::default()
+    };
+
+    let leaf = base.for_blocker(0);
+    assert_eq!(leaf.max_attempts, 3);
+    assert_eq!(leaf.initial_backoff, Duration::from_millis(800));
+
+    let small = base.for_blocker(1);
+    assert_eq!(small.max_attempts, 4);
+    assert_eq!(small.initial_backoff, Duration::from_millis(400));
+
+    let big = base.for_blocker(1000);
+    assert_eq!(big.max_attempts, 6, "boost must be capped");
+    assert_eq!(big.initial_backoff, Duration::from_millis(200));
+    assert_eq!(big.max_backoff, base.max_backoff);
+}
+
+#[tokio::test]
+async fn test_summary_reports_per_package_stats() {
+    let manager = DownloadManager::new(2);
+
+    for i in 0..2 {
+        let task = DownloadTask {
+            package_id: format!("pkg{}", i),
+            package_path: format!("gno.land/p/demo/pkg{}", i),
+            target_dir: PathBuf::from(format!("/tmp/pkg{}", i)),
+            priority: 0,
+            retry_config: RetryConfig {
+                max_attempts: 3,
+                initial_backoff: Duration::from_millis(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        manager.queue_download(task).await.unwrap();
+    }
+
+    // pkg1 fails once before succeeding
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let download_fn = move |task: DownloadTask| {
+        let attempts = Arc::clone(&attempts);
+        Box::pin(async move {
+            if task.package_id == "pkg1" && attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                return Err(DownloadError::Network("flaky".to_string()));
+            }
+            Ok(PackageStats {
+                files: 2,
+                bytes: 128,
+                cache_hits: 1,
+                network_fetches: 2,
+            })
+        }) as futures::future::BoxFuture<'static, Result<PackageStats, DownloadError>>
+    };
+
+    let summary = manager.process_queue(download_fn).await.unwrap();
+    assert_eq!(summary.packages.len(), 2);
+
+    let flaky = summary
+        .packages
+        .iter()
+        .find(|p| p.package == "pkg1")
+        .unwrap();
+    assert_eq!(flaky.retries, 1);
+    assert_eq!(flaky.stats.bytes, 128);
+
+    let json = serde_json::to_value(&summary).unwrap();
+    assert_eq!(json["successful"], 2);
+    assert!(json["duration_ms"].is_u64());
+    let entry = &json["packages"][0];
+    assert_eq!(entry["files"], 2);
+    assert_eq!(entry["cache_hits"], 1);
+    assert_eq!(entry["network_fetches"], 2);
+    assert!(entry["duration_ms"].is_u64());
+}
+
+#[tokio::test]
+async fn test_retry_count_and_retry_events() {
+    let manager = DownloadManager::new(1);
+    let mut update_rx = manager.progress().get_update_receiver();
+
+    let task = DownloadTask {
+        package_id: "always_fails".to_string(),
+        package_path: "gno.land/p/demo/fails".to_string(),
+        target_dir: PathBuf::from("/tmp/fails"),
+        priority: 0,
+        retry_config: RetryConfig {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(1),
+            jitter: 0.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    manager.queue_download(task).await.unwrap();
+
+    let download_fn = move |_task: DownloadTask| {
+        Box::pin(async move { Err(DownloadError::Network("down".to_string())) })
+            as futures::future::BoxFuture<'static, Result<PackageStats, DownloadError>>
+    };
+
+    let summary = manager.process_queue(download_fn).await.unwrap();
+    assert_eq!(summary.failed.len(), 1);
+    assert_eq!(summary.failed[0].retry_count, 2);
+
+    let mut retries = Vec::new();
+    while let Ok(update) = update_rx.try_recv() {
+        if let ProgressUpdate::Retrying {
+            package_id,
+            attempt,
+            backoff,
+        } = update
+        {
+            assert_eq!(package_id, "always_fails");
+            retries.push((attempt, backoff));
+        }
+    }
+    assert_eq!(
+        retries,
+        vec![(1, Duration::from_millis(1)), (2, Duration::from_millis(2)),]
+    );
+}
+
+#[tokio::test]
+async fn test_timeouts_are_retried_and_reported() {
+    let manager = DownloadManager::new(2).with_timeout(Duration::from_millis(20));
+
+    let retry_config = RetryConfig {
+        max_attempts: 2,
+        initial_backoff: Duration::from_millis(1),
+        ..Default::default()
+    };
+    manager
+        .queue_download(DownloadTask {
+            package_id: "slow".to_string(),
+            package_path: "gno.land/p/demo/slow".to_string(),
+            retry_config: retry_config.clone(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    // a generous per-task override lets the same download finish
+    manager
+        .queue_download(DownloadTask {
+            package_id: "patient".to_string(),
+            package_path: "gno.land/p/demo/patient".to_string(),
+            retry_config,
+            timeout: Some(Duration::from_secs(5)),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let attempts_clone = Arc::clone(&attempts);
+    let download_fn = move |task: DownloadTask| {
+        let attempts = Arc::clone(&attempts_clone);
+        Box::pin(async move {
+            if task.package_id == "slow" {
+                attempts.fetch_add(1, Ordering::SeqCst);
+            }
+            sleep(Duration::from_millis(100)).await;
+            Ok(PackageStats::default())
+        }) as futures::future::BoxFuture<'static, Result<PackageStats, DownloadError>>
+    };
+
+    let summary = manager.process_queue(download_fn).await.unwrap();
+
+    assert_eq!(summary.successful, 1);
+    assert_eq!(summary.packages[0].package, "patient");
+    assert_eq!(summary.failed.len(), 1);
+    // exhausted like any other failure, with the timeout as the last error
+    let DownloadError::MaxRetriesExceeded(last) = &summary.failed[0].error else {
+        panic!("{}", summary.failed[0].error);
+    };
+    assert!(matches!(
+        **last,
+        DownloadError::Timeout(limit) if limit == Duration::from_millis(20)
+    ));
+    assert_eq!(summary.failed[0].error.kind(), ErrorKind::Network);
+    assert_eq!(summary.failed[0].retry_count, 1);
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_duplicate_tasks_are_coalesced() {
+    let manager = DownloadManager::new(1);
+
+    let task = |id: &str, path: &str, priority: u8| DownloadTask {
+        package_id: id.to_string(),
+        package_path: path.to_string(),
+        priority,
+        ..Default::default()
+    };
+
+    manager
+        .queue_download(task("other", "gno.land/p/demo/other", 5))
+        .await
+        .unwrap();
+    let first = manager
+        .queue_download(task("avl", "gno.land/p/demo/avl", 1))
+        .await
+        .unwrap();
+    // the same package reached through another root, with a higher priority
+    let second = manager
+        .queue_download(task("avl-again", "gno.land/p/demo/avl", 9))
+        .await
+        .unwrap();
+
+    let order = Arc::new(tokio::sync::Mutex::new(Vec::new()));
+    let order_clone = Arc::clone(&order);
+    let download_fn = move |task: DownloadTask| {
+        let order = Arc::clone(&order_clone);
+        Box::pin(async move {
+            order.lock().await.push(task.package_path);
+            Ok(PackageStats::default())
+        }) as futures::future::BoxFuture<'static, Result<PackageStats, DownloadError>>
+    };
+
+    let summary = manager.process_queue(download_fn).await.unwrap();
+
+    assert_eq!(summary.total_packages, 2);
+    assert_eq!(
+        *order.lock().await,
+        vec!["gno.land/p/demo/avl", "gno.land/p/demo/other"],
+        "coalesced task should keep the higher priority"
+    );
+
+    for waiter in [first, second] {
+        match waiter.wait().await {
+            Some(DownloadOutcome::Completed(report)) => assert_eq!(report.package, "avl"),
+            other => panic!("expected a completed download, got {:?}", other),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_waves_run_in_order_and_skip_failed_dependents() {
+    let manager = DownloadManager::new(4);
+
+    let task = |name: &str, depends_on: &[&str]| DownloadTask {
+        package_id: name.to_string(),
+        package_path: format!("gno.land/p/demo/{}", name),
+        depends_on: depends_on
+            .iter()
+            .map(|d| format!("gno.land/p/demo/{}", d))
+            .collect(),
+        retry_config: RetryConfig {
+            max_attempts: 1,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let waves = vec![
+        vec![task("base", &[]), task("broken", &[])],
+        vec![task("mid", &["base"]), task("blocked", &["broken"])],
+        vec![task("top", &["mid", "blocked"])],
+    ];
+
+    let order = Arc::new(tokio::sync::Mutex::new(Vec::new()));
+    let order_clone = Arc::clone(&order);
+    let download_fn = move |task: DownloadTask| {
+        let order = Arc::clone(&order_clone);
+        Box::pin(async move {
+            if task.package_id == "broken" {
+                return Err(DownloadError::Network("unreachable".to_string()));
+            }
+            // slow first wave to show the next one waits for it
+            if task.package_id == "base" {
+                sleep(Duration::from_millis(50)).await;
+            }
+            order.lock().await.push(task.package_id);
+            Ok(PackageStats::default())
+        }) as futures::future::BoxFuture<'static, Result<PackageStats, DownloadError>>
+    };
+
+    let summary = manager.process_waves(waves, download_fn).await.unwrap();
+
+    assert_eq!(*order.lock().await, vec!["base", "mid"]);
+    assert_eq!(summary.total_packages, 5);
+    assert_eq!(summary.successful, 2);
+
+    let mut failed: Vec<_> = summary
+        .failed
+        .iter()
+        .map(|f| (f.package.as_str(), f.error.to_string()))
+        .collect();
+    failed.sort();
+    assert_eq!(failed[0].0, "blocked");
+    assert_eq!(failed[0].1, "Dependency gno.land/p/demo/broken failed");
+    assert_eq!(failed[1].0, "broken");
+    assert_eq!(failed[2].0, "top");
+    assert!(failed[2].1.contains("gno.land/p/demo/blocked"));
+}
+
+/// Runs a task that always fails and returns the delays of its retries
+async fn retry_delays(seed: u64, package_path: &str) -> Vec<Duration> {
+    let manager = DownloadManager::new(1).with_seed(seed);
+    let mut update_rx = manager.progress().get_update_receiver();
+
+    manager
+        .queue_download(DownloadTask {
+            package_id: package_path.to_string(),
+            package_path: package_path.to_string(),
+            retry_config: RetryConfig {
+                max_attempts: 4,
+                initial_backoff: Duration::from_millis(4),
+                jitter: 0.5,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    let download_fn = move |_task: DownloadTask| {
+        Box::pin(async move { Err(DownloadError::Network("down".to_string())) })
+            as futures::future::BoxFuture<'static, Result<PackageStats, DownloadError>>
+    };
+    manager.process_queue(download_fn).await.unwrap();
+
+    let mut delays = Vec::new();
+    while let Ok(update) = update_rx.try_recv() {
+        if let ProgressUpdate::Retrying { backoff, .. } = update {
+            delays.push(backoff);
+        }
+    }
+    delays
+}
+
+#[tokio::test]
+async fn test_seeded_jitter_is_reproducible() {
+    let first = retry_delays(42, "gno.land/p/demo/avl").await;
+    let second = retry_delays(42, "gno.land/p/demo/avl").await;
+    assert_eq!(first.len(), 3);
+    assert_eq!(first, second);
+
+    // jitter only ever shortens the exponential backoff, by at most half
+    for (delay, full) in first.iter().zip([4, 8, 16]) {
+        let full = Duration::from_millis(full);
+        assert!(
+            *delay <= full && *delay >= full / 2,
+            "{:?} vs {:?}",
+            delay,
+            full
+        );
+    }
+
+    assert_ne!(first, retry_delays(43, "gno.land/p/demo/avl").await);
+    assert_ne!(first, retry_delays(42, "gno.land/p/demo/ufmt").await);
+}
+
+#[tokio::test]
+async fn test_summary_lists_packages_by_path() {
+    let manager = DownloadManager::new(4);
+    for name in ["zeta", "alpha", "mu", "beta"] {
+        let task = DownloadTask {
+            package_id: format!("gno.land/p/demo/{}", name),
+            package_path: format!("gno.land/p/demo/{}", name),
+            retry_config: RetryConfig {
+                max_attempts: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        manager.queue_download(task).await.unwrap();
+    }
+
+    // the first queued finish last, and two fail
+    let download_fn = |task: DownloadTask| {
+        Box::pin(async move {
+            let delay = match task.package_path.as_str() {
+                "gno.land/p/demo/zeta" => 60,
+                "gno.land/p/demo/alpha" => 40,
+                _ => 0,
+            };
+            sleep(Duration::from_millis(delay)).await;
+            if task.package_path.ends_with("mu") || task.package_path.ends_with("zeta") {
+                Err(DownloadError::Network("unreachable".to_string()))
+            } else {
+                Ok(PackageStats::default())
+            }
+        }) as futures::future::BoxFuture<'static, Result<PackageStats, DownloadError>>
+    };
+
+    let summary = manager.process_queue(download_fn).await.unwrap();
+    let packages: Vec<&str> = summary
+        .packages
+        .iter()
+        .map(|p| p.package.as_str())
+        .collect();
+    assert_eq!(packages, ["gno.land/p/demo/alpha", "gno.land/p/demo/beta"]);
+    let failed: Vec<&str> = summary.failed.iter().map(|f| f.package.as_str()).collect();
+    assert_eq!(failed, ["gno.land/p/demo/mu", "gno.land/p/demo/zeta"]);
+}
+
+#[tokio::test]
+async fn test_nested_targets_take_turns() {
+    let manager = DownloadManager::new(4);
+    for (path, target) in [
+        ("gno.land/p/demo/avl", "/tmp/gno.land/p/demo/avl"),
+        (
+            "gno.land/p/demo/avl/pager",
+            "/tmp/gno.land/p/demo/avl/pager",
+        ),
+        ("gno.land/p/demo/ufmt", "/tmp/gno.land/p/demo/ufmt"),
+    ] {
+        manager
+            .queue_download(DownloadTask {
+                package_id: path.to_string(),
+                package_path: path.to_string(),
+                target_dir: PathBuf::from(target),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+    }
+
+    let writing: Arc<std::sync::Mutex<Vec<PathBuf>>> = Arc::default();
+    let overlaps = Arc::new(AtomicUsize::new(0));
+    let max_concurrent = Arc::new(AtomicUsize::new(0));
+    let (writing_clone, overlaps_clone, max_clone) = (
+        Arc::clone(&writing),
+        Arc::clone(&overlaps),
+        Arc::clone(&max_concurrent),
+    );
+    let download_fn = move |task: DownloadTask| {
+        let writing = Arc::clone(&writing_clone);
+        let overlaps = Arc::clone(&overlaps_clone);
+        let max = Arc::clone(&max_clone);
+        Box::pin(async move {
+            {
+                let mut writing = writing.lock().unwrap();
+                if writing
+                    .iter()
+                    .any(|t| t.starts_with(&task.target_dir) || task.target_dir.starts_with(t))
+                {
+                    overlaps.fetch_add(1, Ordering::SeqCst);
+                }
+                writing.push(task.target_dir.clone());
+                max.fetch_max(writing.len(), Ordering::SeqCst);
+            }
+            sleep(Duration::from_millis(50)).await;
+            writing.lock().unwrap().retain(|t| *t != task.target_dir);
+            Ok(PackageStats::default())
+        }) as futures::future::BoxFuture<'static, Result<PackageStats, DownloadError>>
+    };
+
+    let summary = manager.process_queue(download_fn).await.unwrap();
+    assert_eq!(summary.successful, 3);
+    assert_eq!(overlaps.load(Ordering::SeqCst), 0);
+    // the unrelated package still ran alongside one of the others
+    assert_eq!(max_concurrent.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_tasks_queued_while_processing_are_downloaded() {
+    let manager = DownloadManager::new(1);
+    let task = |path: &str| DownloadTask {
+        package_id: path.to_string(),
+        package_path: path.to_string(),
+        ..Default::default()
+    };
+    manager
+        .queue_download(task("gno.land/p/demo/avl"))
+        .await
+        .unwrap();
+    assert_eq!(manager.queue_depth().await, 1);
+
+    let download_fn = |_task: DownloadTask| {
+        Box::pin(async move {
+            sleep(Duration::from_millis(50)).await;
+            Ok(PackageStats::default())
+        }) as futures::future::BoxFuture<'static, Result<PackageStats, DownloadError>>
+    };
+    let enqueue = async {
+        sleep(Duration::from_millis(10)).await;
+        // the only worker is busy with the first package
+        assert_eq!(manager.in_flight(), 1);
+        for path in ["gno.land/p/demo/ufmt", "gno.land/p/demo/seqid"] {
+            manager.queue_download(task(path)).await.unwrap();
+        }
+        assert_eq!(manager.queue_depth().await, 2);
+    };
+
+    let (summary, ()) = tokio::join!(manager.process_queue(download_fn), enqueue);
+    let summary = summary.unwrap();
+    assert_eq!(summary.total_packages, 3);
+    assert_eq!(summary.successful, 3);
+    assert_eq!(manager.queue_depth().await, 0);
+    assert_eq!(manager.in_flight(), 0);
+}
+
+#[tokio::test]
+async fn test_held_queue_waits_for_dependencies_queued_later() {
+    let manager = DownloadManager::new(4);
+    let task = |path: &str, depends_on: &[&str]| DownloadTask {
+        package_id: path.to_string(),
+        package_path: path.to_string(),
+        depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+        retry_config: RetryConfig {
+            max_attempts: 1,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let hold = manager.hold();
+    manager
+        .queue_download(task("gno.land/r/demo/app", &["gno.land/p/demo/avl"]))
+        .await
+        .unwrap();
+    manager
+        .queue_download(task("gno.land/r/demo/other", &["gno.land/p/demo/broken"]))
+        .await
+        .unwrap();
+
+    let order: Arc<std::sync::Mutex<Vec<String>>> = Arc::default();
+    let order_clone = Arc::clone(&order);
+    let download_fn = move |task: DownloadTask| {
+        let order = Arc::clone(&order_clone);
+        Box::pin(async move {
+            order.lock().unwrap().push(task.package_path.clone());
+            if task.package_path.ends_with("broken") {
+                return Err(DownloadError::Network("unreachable".to_string()));
+            }
+            Ok(PackageStats::default())
+        }) as futures::future::BoxFuture<'static, Result<PackageStats, DownloadError>>
+    };
+    let produce = async {
+        sleep(Duration::from_millis(20)).await;
+        // nothing could start yet: both dependencies may still be queued
+        assert_eq!(manager.in_flight(), 0);
+        assert_eq!(manager.queue_depth().await, 2);
+        for path in ["gno.land/p/demo/avl", "gno.land/p/demo/broken"] {
+            manager.queue_download(task(path, &[])).await.unwrap();
+        }
+        drop(hold);
+    };
+
+    let (summary, ()) = tokio::join!(manager.process_queue(download_fn), produce);
+    let summary = summary.unwrap();
+    assert_eq!(summary.total_packages, 4);
+    assert_eq!(summary.successful, 2);
+
+    let order = order.lock().unwrap().clone();
+    let app = order
+        .iter()
+        .position(|p| p == "gno.land/r/demo/app")
+        .unwrap();
+    let avl = order
+        .iter()
+        .position(|p| p == "gno.land/p/demo/avl")
+        .unwrap();
+    assert!(avl < app, "{:?}", order);
+    assert!(!order.contains(&"gno.land/r/demo/other".to_string()));
+    let other = summary
+        .failed
+        .iter()
+        .find(|f| f.package == "gno.land/r/demo/other")
+        .unwrap();
+    assert_eq!(other.error_code, "dependency_failed");
+}
+
+#[tokio::test]
+async fn test_unread_progress_does_not_stall_downloads() {
+    let manager = DownloadManager::new(4);
+    for i in 0..200 {
+        let path = format!("gno.land/p/demo/pkg{}", i);
+        manager
+            .queue_download(DownloadTask {
+                package_id: path.clone(),
+                package_path: path,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+    }
+
+    let download_fn = |_task: DownloadTask| {
+        Box::pin(async move { Ok(PackageStats::default()) })
+            as futures::future::BoxFuture<'static, Result<PackageStats, DownloadError>>
+    };
+    let summary = tokio::time::timeout(Duration::from_secs(10), manager.process_queue(download_fn))
+        .await
+        .expect("downloads stalled")
+        .unwrap();
+    assert_eq!(summary.successful, 200);
+
+    // nothing is kept for a reader coming late, but the progress of every
+    // package still says it finished
+    let mut receiver = manager.progress().get_update_receiver();
+    assert!(receiver.try_recv().is_err());
+    let packages = manager.progress().get_progress().await;
+    assert_eq!(packages.len(), 200);
+    assert!(packages
+        .values()
+        .all(|package| matches!(package.state, DownloadState::Completed)));
+}
+
+#[test]
+fn test_merged_summaries_keep_skipped_packages() {
+    let skipped = |path: &str| DownloadSummary {
+        skipped: [(path.to_string(), format!("{} is denied", path))].into(),
+        ..Default::default()
+    };
+    let mut summary = skipped("gno.land/p/demo/a");
+    summary.merge(skipped("gno.land/p/demo/b"));
+    assert_eq!(
+        summary.skipped.keys().collect::<Vec<_>>(),
+        ["gno.land/p/demo/a", "gno.land/p/demo/b"]
+    );
+}
@@ -0,0 +1,88 @@
+use gget::paths::{cache_dir_from, check_relative, safe_join, CACHE_DIR_ENV};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// Environment holding only `vars`
+fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<OsString> + 'a {
+    move |name| {
+        vars.iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| OsString::from(value))
+    }
+}
+
+#[test]
+fn test_override_wins() {
+    let dir = cache_dir_from(env(&[
+        (CACHE_DIR_ENV, "/srv/gget-cache"),
+        ("XDG_CACHE_HOME", "/home/me/.xdg"),
+        ("HOME", "/home/me"),
+        ("LOCALAPPDATA", "/home/me/AppData/Local"),
+    ]));
+    assert_eq!(dir, Path::new("/srv/gget-cache"));
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+#[test]
+fn test_xdg_cache_home() {
+    let dir = cache_dir_from(env(&[
+        ("XDG_CACHE_HOME", "/home/me/.xdg"),
+        ("HOME", "/home/me"),
+    ]));
+    assert_eq!(dir, Path::new("/home/me/.xdg/gget/packages"));
+
+    // relative values are ignored
+    let dir = cache_dir_from(env(&[("XDG_CACHE_HOME", "xdg"), ("HOME", "/home/me")]));
+    assert_eq!(dir, Path::new("/home/me/.cache/gget/packages"));
+}
+
+#[test]
+fn test_fallback_without_home() {
+    assert_eq!(cache_dir_from(env(&[])), PathBuf::from("cache"));
+    assert_eq!(
+        cache_dir_from(env(&[(CACHE_DIR_ENV, "relative")])),
+        PathBuf::from("cache")
+    );
+}
+
+#[test]
+fn test_relative_names_are_joined() {
+    for name in [
+        "avl.gno",
+        "gno.mod",
+        "sub/node.gno",
+        "a/b/c_test.gno",
+        ".hidden.gno",
+    ] {
+        assert!(check_relative(name).is_ok(), "{}", name);
+    }
+    assert_eq!(
+        safe_join(Path::new("vendor/avl"), "sub/node.gno").unwrap(),
+        Path::new("vendor/avl/sub/node.gno")
+    );
+}
+
+#[test]
+fn test_escaping_names_are_rejected() {
+    for (name, reason) in [
+        ("", "it is empty"),
+        ("/etc/passwd", "it is absolute"),
+        ("C:evil.gno", "it starts with a drive letter"),
+        ("c:/windows/evil.gno", "it starts with a drive letter"),
+        ("..\\evil.gno", "it contains a backslash"),
+        ("avl.gno:stream", "it contains a colon"),
+        ("evil\n.gno", "it contains a control character"),
+        ("../evil.gno", "it has an empty, `.` or `..` component"),
+        (
+            "sub/../../evil.gno",
+            "it has an empty, `.` or `..` component",
+        ),
+        ("./avl.gno", "it has an empty, `.` or `..` component"),
+        ("sub//avl.gno", "it has an empty, `.` or `..` component"),
+        ("sub/", "it has an empty, `.` or `..` component"),
+    ] {
+        let err = safe_join(Path::new("vendor"), name).unwrap_err();
+        assert_eq!(err.reason, reason, "{:?}", name);
+        assert_eq!(err.name, name);
+    }
+}
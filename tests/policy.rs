@@ -0,0 +1,116 @@
+use gget::fetch::{PackageManager, PackageManagerError};
+use gget::parallel::ParallelDownloadOptions;
+use gget::policy::{Policy, PolicyError, PolicyMode};
+use gget::testing::{download_options, FakeChain};
+use std::fs;
+use tempfile::tempdir;
+
+fn policy(allow: &[&str], deny: &[&str]) -> Policy {
+    Policy {
+        allow: allow.iter().map(|s| s.to_string()).collect(),
+        deny: deny.iter().map(|s| s.to_string()).collect(),
+        mode: PolicyMode::Fail,
+    }
+}
+
+#[test]
+fn test_empty_policy_allows_everything() {
+    let policy = Policy::default();
+    assert!(policy.is_empty());
+    assert!(policy.check("gno.land/r/demo/boards").is_ok());
+}
+
+#[test]
+fn test_deny_takes_precedence_over_allow() {
+    let policy = policy(&["gno.land/*"], &["gno.land/r/*"]);
+    assert!(policy.check("gno.land/p/demo/avl").is_ok());
+    match policy.check("gno.land/r/demo/boards") {
+        Err(PolicyError::Disallowed { package, reason }) => {
+            assert_eq!(package, "gno.land/r/demo/boards");
+            assert!(reason.contains("gno.land/r/*"));
+        }
+        other => panic!("expected denial, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_allowlist_rejects_unmatched() {
+    let policy = policy(&["gno.land/p/demo/*"], &[]);
+    assert!(policy.check("gno.land/p/demo/ufmt").is_ok());
+    assert!(policy.check("gno.land/p/nt/avl").is_err());
+}
+
+#[test]
+fn test_load_policy_file() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("policy.json");
+    fs::write(&path, r#"{ "deny": ["gno.land/r/*"], "mode": "warn" }"#).unwrap();
+
+    let policy = Policy::load(&path).unwrap();
+    assert_eq!(policy.mode, PolicyMode::Warn);
+    assert!(policy.allow.is_empty());
+    assert!(policy.check("gno.land/r/demo/users").is_err());
+}
+
+#[tokio::test]
+async fn test_denied_root_fails_before_network() {
+    let temp_dir = tempdir().unwrap();
+    let pm = PackageManager::new(
+        Some("http://127.0.0.1:1".to_string()),
+        temp_dir.path().join("cache"),
+    )
+    .with_policy(Policy {
+        mode: PolicyMode::Warn,
+        ..policy(&[], &["gno.land/r/*"])
+    });
+
+    let result = pm
+        .download_with_deps_parallel(
+            "gno.land/r/demo/boards",
+            temp_dir.path(),
+            ParallelDownloadOptions::default(),
+        )
+        .await;
+
+    assert!(matches!(result, Err(PackageManagerError::Policy(_))));
+}
+
+#[tokio::test]
+async fn test_warn_mode_reports_skipped_dependencies() {
+    let chain = FakeChain::new()
+        .with_package(
+            "gno.land/p/demo/app",
+            &[(
+                "app.gno",
+                "package app\n\nimport \"gno.land/r/demo/users\"\n",
+            )],
+        )
+        .with_package("gno.land/r/demo/users", &[("users.gno", "package users\n")]);
+    let url = chain.spawn();
+    let temp_dir = tempdir().unwrap();
+    let pm = PackageManager::new(Some(url), temp_dir.path().join("cache"))
+        .with_quiet(true)
+        .with_policy(Policy {
+            mode: PolicyMode::Warn,
+            ..policy(&[], &["gno.land/r/*"])
+        });
+
+    let summary = pm
+        .download_with_deps_parallel(
+            "gno.land/p/demo/app",
+            &temp_dir.path().join("gno"),
+            download_options(),
+        )
+        .await
+        .unwrap();
+
+    assert!(summary.failed.is_empty());
+    assert_eq!(
+        summary.skipped.keys().collect::<Vec<_>>(),
+        ["gno.land/r/demo/users"]
+    );
+    assert!(summary
+        .to_string()
+        .contains("skipped: Package gno.land/r/demo/users is not allowed"));
+    assert!(!temp_dir.path().join("gno/gno.land/r").exists());
+}
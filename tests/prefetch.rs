@@ -0,0 +1,71 @@
+use gget::fetch::PackageManager;
+use gget::testing::{download_options, FakeChain};
+use tempfile::tempdir;
+
+/// The demo chain, with a gno.mod for the app
+fn chain() -> FakeChain {
+    let chain = FakeChain::demo();
+    chain.set_file(
+        "gno.land/r/demo/app",
+        "gno.mod",
+        "module gno.land/r/demo/app\n",
+    );
+    chain
+}
+
+#[tokio::test]
+async fn test_prefetch_fills_the_cache_for_offline_downloads() {
+    let chain = chain();
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+
+    let pm = PackageManager::new(Some(url.clone()), cache.path().to_path_buf()).with_quiet(true);
+    let summary = pm.prefetch(&["gno.land/r/demo/app"]).await.unwrap();
+    let packages: Vec<_> = summary
+        .packages
+        .iter()
+        .map(|p| p.package.as_str())
+        .collect();
+    assert_eq!(packages, ["gno.land/p/demo/avl", "gno.land/r/demo/app"]);
+    assert_eq!(summary.packages[1].files, 2);
+    assert!(summary.packages.iter().all(|p| p.cached == 0));
+
+    // everything is cached now, nothing is fetched again
+    let again = pm.prefetch(&["gno.land/r/demo/app"]).await.unwrap();
+    assert!(again.packages.iter().all(|p| p.fetched == 0));
+
+    let queries = chain.queries().len();
+    let offline = PackageManager::builder()
+        .endpoint(&url)
+        .cache_dir(cache.path())
+        .offline(true)
+        .build()
+        .unwrap()
+        .with_quiet(true);
+    let target = tempdir().unwrap();
+    let options = download_options();
+    offline
+        .download_with_deps_parallel("gno.land/r/demo/app", target.path(), options)
+        .await
+        .unwrap();
+    assert!(target.path().join("gno.land/r/demo/app/gno.mod").exists());
+    assert!(target.path().join("gno.land/p/demo/avl/avl.gno").exists());
+    assert_eq!(chain.queries().len(), queries);
+}
+
+#[tokio::test]
+async fn test_offline_resolution_needs_the_sources_cached() {
+    let chain = chain();
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+
+    let offline = PackageManager::builder()
+        .endpoint(&url)
+        .cache_dir(cache.path())
+        .offline(true)
+        .build()
+        .unwrap()
+        .with_quiet(true);
+    assert!(offline.prefetch(&["gno.land/r/demo/app"]).await.is_err());
+    assert!(chain.queries().is_empty());
+}
@@ -0,0 +1,47 @@
+use gget::ratelimit::{RateLimit, RateLimiter};
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_parse_rate_limit() {
+    assert_eq!(
+        "10".parse::<RateLimit>().unwrap(),
+        RateLimit::requests_per_second(10.0)
+    );
+    assert_eq!(
+        "2.5,512k".parse::<RateLimit>().unwrap(),
+        RateLimit::requests_per_second(2.5).with_bytes_per_second(512 * 1024)
+    );
+    assert!("0".parse::<RateLimit>().is_err());
+    assert!("fast".parse::<RateLimit>().is_err());
+    assert!("5,lots".parse::<RateLimit>().is_err());
+}
+
+#[tokio::test]
+async fn test_requests_are_throttled_after_burst() {
+    let limiter = RateLimiter::new(RateLimit::requests_per_second(50.0));
+
+    // a full bucket allows one second worth of requests right away
+    let start = Instant::now();
+    for _ in 0..50 {
+        limiter.acquire_request().await;
+    }
+    assert!(start.elapsed() < Duration::from_millis(100));
+
+    // the next ten have to wait for refills at 50/s
+    let start = Instant::now();
+    for _ in 0..10 {
+        limiter.acquire_request().await;
+    }
+    assert!(start.elapsed() >= Duration::from_millis(150));
+}
+
+#[tokio::test]
+async fn test_byte_budget_delays_after_overdraw() {
+    let limiter =
+        RateLimiter::new(RateLimit::requests_per_second(100.0).with_bytes_per_second(1000));
+
+    let start = Instant::now();
+    // 1000 bytes of burst, then 200 bytes of debt at 1000 B/s
+    limiter.record_bytes(1200).await;
+    assert!(start.elapsed() >= Duration::from_millis(150));
+}
@@ -0,0 +1,179 @@
+use gget::lock::{LockedPackage, Lockfile, LOCKFILE_NAME};
+use gget::rdeps::{ImportPaths, ReverseDependencies};
+use gget::report::{render, ReportFormat};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+/// ufmt ← avl ← {app, pager}, with ufmt also used by json's tests
+fn vendor_tree(root: &Path) {
+    let packages = [
+        ("gno.land/p/demo/ufmt", "ufmt.gno", "package ufmt\n"),
+        (
+            "gno.land/p/demo/avl",
+            "avl.gno",
+            "package avl\n\nimport \"gno.land/p/demo/ufmt\"\n",
+        ),
+        (
+            "gno.land/p/demo/pager",
+            "pager.gno",
+            "package pager\n\nimport \"gno.land/p/demo/avl\"\n",
+        ),
+        (
+            "gno.land/r/demo/app",
+            "app.gno",
+            "package app\n\nimport (\n\t\"gno.land/p/demo/avl\"\n\t\"gno.land/p/demo/pager\"\n)\n",
+        ),
+        ("gno.land/p/demo/json", "json.gno", "package json\n"),
+        (
+            "gno.land/p/demo/json",
+            "json_test.gno",
+            "package json\n\nimport \"gno.land/p/demo/ufmt\"\n",
+        ),
+    ];
+    for (path, file, source) in packages {
+        let dir = root.join(path);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(file), source).unwrap();
+    }
+}
+
+fn lock(root: &Path) {
+    let mut lock = Lockfile::default();
+    for path in [
+        "gno.land/p/demo/ufmt",
+        "gno.land/p/demo/avl",
+        "gno.land/p/demo/pager",
+        "gno.land/r/demo/app",
+        "gno.land/p/demo/json",
+    ] {
+        lock.insert(LockedPackage::from_dir(path, &root.join(path), &[]).unwrap());
+    }
+    lock.save(&root.join(LOCKFILE_NAME)).unwrap();
+}
+
+fn listed(rdeps: &ReverseDependencies) -> Vec<(&str, usize, &str)> {
+    rdeps
+        .dependents
+        .iter()
+        .map(|d| (d.package.as_str(), d.depth, d.via.as_str()))
+        .collect()
+}
+
+#[test]
+fn test_direct_and_transitive_dependents() {
+    let dir = tempdir().unwrap();
+    vendor_tree(dir.path());
+    lock(dir.path());
+
+    let rdeps = ReverseDependencies::from_dir(dir.path(), "gno.land/p/demo/ufmt").unwrap();
+    assert_eq!(
+        listed(&rdeps),
+        [
+            ("gno.land/p/demo/avl", 1, "gno.land/p/demo/ufmt"),
+            ("gno.land/p/demo/json", 1, "gno.land/p/demo/ufmt"),
+            ("gno.land/p/demo/pager", 2, "gno.land/p/demo/avl"),
+            ("gno.land/r/demo/app", 2, "gno.land/p/demo/avl"),
+        ]
+    );
+    assert_eq!(rdeps.direct().count(), 2);
+
+    let human = render(&rdeps, ReportFormat::Human).unwrap();
+    assert!(human.starts_with("4 packages import gno.land/p/demo/ufmt (2 directly)\n"));
+    assert!(human.contains("  gno.land/r/demo/app (via gno.land/p/demo/avl)\n"));
+    let csv = render(&rdeps, ReportFormat::Csv).unwrap();
+    assert!(csv.contains("gno.land/p/demo/pager,2,gno.land/p/demo/avl"));
+}
+
+#[test]
+fn test_only_locked_packages_count() {
+    let dir = tempdir().unwrap();
+    vendor_tree(dir.path());
+    lock(dir.path());
+    // a stray copy the lockfile doesn't know about
+    let stray = dir.path().join("gno.land/r/demo/stray");
+    fs::create_dir_all(&stray).unwrap();
+    fs::write(
+        stray.join("stray.gno"),
+        "package stray\n\nimport \"gno.land/r/demo/app\"\n",
+    )
+    .unwrap();
+
+    let rdeps = ReverseDependencies::from_dir(dir.path(), "gno.land/r/demo/app").unwrap();
+    assert!(rdeps.dependents.is_empty());
+    assert_eq!(
+        render(&rdeps, ReportFormat::Human).unwrap(),
+        "Nothing imports gno.land/r/demo/app\n"
+    );
+}
+
+#[test]
+fn test_tree_without_lockfile_is_scanned() {
+    let dir = tempdir().unwrap();
+    vendor_tree(dir.path());
+
+    let rdeps = ReverseDependencies::from_dir(dir.path(), "gno.land/p/demo/pager").unwrap();
+    assert_eq!(
+        listed(&rdeps),
+        [("gno.land/r/demo/app", 1, "gno.land/p/demo/pager")]
+    );
+}
+
+#[test]
+fn test_why_lists_every_import_chain() {
+    let dir = tempdir().unwrap();
+    vendor_tree(dir.path());
+    lock(dir.path());
+
+    let why = ImportPaths::from_dir(
+        dir.path(),
+        "gno.land/r/demo/app",
+        "gno.land/p/demo/ufmt",
+        100,
+    )
+    .unwrap();
+    assert_eq!(
+        why.paths,
+        [
+            vec![
+                "gno.land/r/demo/app",
+                "gno.land/p/demo/avl",
+                "gno.land/p/demo/ufmt"
+            ],
+            vec![
+                "gno.land/r/demo/app",
+                "gno.land/p/demo/pager",
+                "gno.land/p/demo/avl",
+                "gno.land/p/demo/ufmt"
+            ],
+        ]
+    );
+
+    let human = render(&why, ReportFormat::Human).unwrap();
+    assert!(human
+        .starts_with("gno.land/r/demo/app reaches gno.land/p/demo/ufmt through 2 import chains\n"));
+    assert!(
+        human.contains("  gno.land/r/demo/app -> gno.land/p/demo/avl -> gno.land/p/demo/ufmt\n")
+    );
+    let csv = render(&why, ReportFormat::Csv).unwrap();
+    assert!(csv.contains("3,gno.land/r/demo/app -> gno.land/p/demo/pager"));
+}
+
+#[test]
+fn test_why_without_a_chain() {
+    let dir = tempdir().unwrap();
+    vendor_tree(dir.path());
+
+    let why = ImportPaths::from_dir(
+        dir.path(),
+        "gno.land/p/demo/ufmt",
+        "gno.land/r/demo/app",
+        100,
+    )
+    .unwrap();
+    assert!(why.paths.is_empty());
+    assert_eq!(
+        render(&why, ReportFormat::Human).unwrap(),
+        "gno.land/p/demo/ufmt does not import gno.land/r/demo/app\n"
+    );
+}
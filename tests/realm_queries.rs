@@ -0,0 +1,111 @@
+use gget::fetch::{PackageManager, PackageManagerError};
+use gget::testing::FakeChain;
+use serde_json::json;
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn test_render_queries_the_realm() {
+    let chain = FakeChain::new()
+        .with_render("gno.land/r/demo/boards", "", "# Boards\n")
+        .with_render("gno.land/r/demo/boards", "gnolang/1", "## Hello\n");
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+
+    assert_eq!(
+        pm.render("gno.land/r/demo/boards", "gnolang/1")
+            .await
+            .unwrap(),
+        "## Hello\n"
+    );
+    assert_eq!(
+        pm.render("gno.land/r/demo/boards", "").await.unwrap(),
+        "# Boards\n"
+    );
+    let query = chain.queries().pop().unwrap();
+    assert_eq!(query.path, "vm/qrender");
+    assert_eq!(query.data, "gno.land/r/demo/boards:");
+
+    // rendered pages aren't cached
+    pm.render("gno.land/r/demo/boards", "").await.unwrap();
+    assert_eq!(chain.queries().len(), 3);
+
+    let err = pm.render("gno.land/r/demo/missing", "").await.unwrap_err();
+    assert!(matches!(err, PackageManagerError::Rpc(_)), "{}", err);
+}
+
+#[tokio::test]
+async fn test_eval_queries_the_realm() {
+    let chain = FakeChain::new().with_eval(
+        "gno.land/r/demo/boards",
+        "GetBoardIDFromName(\"gnolang\")",
+        "(1 gno.land/r/demo/boards.BoardID)",
+    );
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+
+    assert_eq!(
+        pm.eval("gno.land/r/demo/boards", "GetBoardIDFromName(\"gnolang\")")
+            .await
+            .unwrap(),
+        "(1 gno.land/r/demo/boards.BoardID)"
+    );
+    let query = chain.queries().pop().unwrap();
+    assert_eq!(query.path, "vm/qeval");
+    assert_eq!(
+        query.data,
+        "gno.land/r/demo/boards.GetBoardIDFromName(\"gnolang\")"
+    );
+
+    let err = pm
+        .eval("gno.land/r/demo/boards", "Missing()")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, PackageManagerError::Rpc(_)), "{}", err);
+}
+
+#[tokio::test]
+async fn test_funcs_lists_signatures() {
+    let chain = FakeChain::new().with_funcs(
+        "gno.land/r/demo/boards",
+        json!([
+            {
+                "FuncName": "Render",
+                "Params": [{"Name": "path", "Type": "string", "Value": ""}],
+                "Results": [{"Name": "_", "Type": "string", "Value": ""}],
+            },
+            {
+                "FuncName": "CreateBoard",
+                "Params": [{"Name": "name", "Type": "string", "Value": ""}],
+                "Results": [{"Name": "bid", "Type": "BoardID", "Value": ""}],
+            },
+            {"FuncName": "Reset", "Params": null, "Results": null},
+            {
+                "FuncName": "Lookup",
+                "Params": [],
+                "Results": [
+                    {"Name": "", "Type": "string", "Value": ""},
+                    {"Name": "", "Type": "bool", "Value": ""},
+                ],
+            },
+        ]),
+    );
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+
+    let funcs = pm.funcs("gno.land/r/demo/boards").await.unwrap();
+    let signatures: Vec<String> = funcs.iter().map(|f| f.to_string()).collect();
+    assert_eq!(
+        signatures,
+        [
+            "func Render(path string) string",
+            "func CreateBoard(name string) (bid BoardID)",
+            "func Reset()",
+            "func Lookup() (string, bool)",
+        ]
+    );
+    assert_eq!(chain.queries().pop().unwrap().path, "vm/qfuncs");
+    assert!(pm.funcs("gno.land/r/demo/missing").await.is_err());
+}
@@ -0,0 +1,128 @@
+#![cfg(feature = "redis")]
+
+use gget::cache::redis::RedisStorage;
+use gget::cache::AsyncStorage;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+/// Commands received by the fake server, in order
+type Commands = Arc<Mutex<Vec<Vec<String>>>>;
+
+/// Speaks just enough RESP to serve GET and SETEX, answering OK to anything
+/// else (e.g. the CLIENT SETINFO sent on connect)
+async fn spawn_redis() -> (String, Commands) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("redis://{}", listener.local_addr().unwrap());
+    let commands = Commands::default();
+    let store = Arc::new(Mutex::new(HashMap::<String, String>::new()));
+
+    let seen = commands.clone();
+    tokio::spawn(async move {
+        loop {
+            let (socket, _) = listener.accept().await.unwrap();
+            let (seen, store) = (seen.clone(), store.clone());
+            tokio::spawn(async move {
+                let (read, mut write) = socket.into_split();
+                let mut read = BufReader::new(read);
+                while let Some(command) = read_command(&mut read).await {
+                    let reply = match command[0].to_uppercase().as_str() {
+                        "GET" => match store.lock().unwrap().get(&command[1]) {
+                            Some(value) => format!("${}\r\n{}\r\n", value.len(), value),
+                            None => "$-1\r\n".to_string(),
+                        },
+                        "SETEX" => {
+                            store
+                                .lock()
+                                .unwrap()
+                                .insert(command[1].clone(), command[3].clone());
+                            "+OK\r\n".to_string()
+                        }
+                        _ => "+OK\r\n".to_string(),
+                    };
+                    seen.lock().unwrap().push(command);
+                    if write.write_all(reply.as_bytes()).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+    (url, commands)
+}
+
+async fn read_command<R: AsyncBufReadExt + Unpin>(read: &mut R) -> Option<Vec<String>> {
+    let mut line = String::new();
+    if read.read_line(&mut line).await.ok()? == 0 {
+        return None;
+    }
+    let count: usize = line.trim_end().strip_prefix('*')?.parse().ok()?;
+    let mut args = Vec::with_capacity(count);
+    for _ in 0..count {
+        line.clear();
+        read.read_line(&mut line).await.ok()?;
+        let len: usize = line.trim_end().strip_prefix('$')?.parse().ok()?;
+        let mut arg = vec![0; len + 2];
+        read.read_exact(&mut arg).await.ok()?;
+        arg.truncate(len);
+        args.push(String::from_utf8(arg).ok()?);
+    }
+    Some(args)
+}
+
+#[tokio::test]
+async fn test_entries_are_namespaced_and_expire_in_redis() {
+    let (url, commands) = spawn_redis().await;
+    let storage = RedisStorage::builder(&url)
+        .namespace("ci")
+        .ttl(Duration::from_millis(1500))
+        .pool_size(2)
+        .connect()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        storage.get("files:gno.land/p/demo/avl").await.unwrap(),
+        None
+    );
+    storage
+        .set("files:gno.land/p/demo/avl", "avl.gno")
+        .await
+        .unwrap();
+    assert_eq!(
+        storage
+            .get("files:gno.land/p/demo/avl")
+            .await
+            .unwrap()
+            .as_deref(),
+        Some("avl.gno")
+    );
+
+    let commands = commands.lock().unwrap();
+    let set = commands
+        .iter()
+        .find(|command| command[0] == "SETEX")
+        .expect("a SETEX command");
+    assert_eq!(
+        set,
+        &["SETEX", "ci:files:gno.land/p/demo/avl", "2", "avl.gno"]
+    );
+}
+
+#[tokio::test]
+async fn test_storages_share_entries_through_the_server() {
+    let (url, _) = spawn_redis().await;
+    let first = RedisStorage::builder(&url).connect().await.unwrap();
+    let second = RedisStorage::builder(&url).connect().await.unwrap();
+    let other = RedisStorage::builder(&url)
+        .namespace("other")
+        .connect()
+        .await
+        .unwrap();
+
+    first.set("key", "value").await.unwrap();
+    assert_eq!(second.get("key").await.unwrap().as_deref(), Some("value"));
+    assert_eq!(other.get("key").await.unwrap(), None);
+}
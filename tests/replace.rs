@@ -0,0 +1,105 @@
+use gget::fetch::PackageManager;
+use gget::testing::{download_options, FakeChain};
+use std::fs;
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn test_replaced_packages_come_from_their_directory() {
+    // foo isn't published, but its own imports are
+    let chain = FakeChain::new()
+        .with_package(
+            "gno.land/r/demo/app",
+            &[("app.gno", "package app\n\nimport \"gno.land/p/me/foo\"\n")],
+        )
+        .with_package("gno.land/p/demo/avl", &[("avl.gno", "package avl\n")]);
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let local = tempdir().unwrap();
+    fs::write(
+        local.path().join("foo.gno"),
+        "package foo\n\nimport \"gno.land/p/demo/avl\"\n",
+    )
+    .unwrap();
+    fs::create_dir_all(local.path().join("internal")).unwrap();
+    fs::write(local.path().join("internal/x.gno"), "package foo\n").unwrap();
+    // neither hidden entries nor nested packages are part of it
+    fs::write(local.path().join(".notes"), "todo\n").unwrap();
+    fs::create_dir_all(local.path().join("sub")).unwrap();
+    fs::write(
+        local.path().join("sub/gno.mod"),
+        "module gno.land/p/me/foo/sub\n",
+    )
+    .unwrap();
+    fs::write(local.path().join("sub/sub.gno"), "package sub\n").unwrap();
+
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf())
+        .with_quiet(true)
+        .with_replacement("gno.land/p/me/foo", local.path());
+    let target = tempdir().unwrap();
+    let summary = pm
+        .download_with_deps_parallel("gno.land/r/demo/app", target.path(), download_options())
+        .await
+        .unwrap();
+    assert!(summary.failed.is_empty());
+
+    let foo = target.path().join("gno.land/p/me/foo");
+    assert!(foo.join("foo.gno").exists());
+    assert!(foo.join("internal/x.gno").exists());
+    assert!(!foo.join(".notes").exists());
+    assert!(!foo.join("sub").exists());
+    assert!(target.path().join("gno.land/p/demo/avl/avl.gno").exists());
+    assert!(chain
+        .queries()
+        .iter()
+        .all(|query| !query.data.contains("gno.land/p/me/foo")));
+
+    // edits to the directory are picked up by the next run
+    fs::write(
+        local.path().join("foo.gno"),
+        "package foo // edited\n\nimport \"gno.land/p/demo/avl\"\n",
+    )
+    .unwrap();
+    pm.download_with_deps_parallel("gno.land/r/demo/app", target.path(), download_options())
+        .await
+        .unwrap();
+    assert_eq!(
+        fs::read_to_string(foo.join("foo.gno")).unwrap(),
+        "package foo // edited\n\nimport \"gno.land/p/demo/avl\"\n"
+    );
+}
+
+#[tokio::test]
+async fn test_replacement_ignores_cached_chain_copies() {
+    let chain = FakeChain::new().with_package(
+        "gno.land/p/me/foo",
+        &[("foo.gno", "package foo // chain\n")],
+    );
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let target = tempdir().unwrap();
+
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+    pm.download_package("gno.land/p/me/foo", target.path())
+        .await
+        .unwrap();
+
+    let local = tempdir().unwrap();
+    fs::write(local.path().join("foo.gno"), "package foo // local\n").unwrap();
+    let replaced = pm.with_replacement("gno.land/p/me/foo", local.path());
+    assert!(!replaced
+        .is_unchanged("gno.land/p/me/foo", target.path(), None)
+        .await
+        .unwrap());
+    replaced
+        .download_package("gno.land/p/me/foo", target.path())
+        .await
+        .unwrap();
+    assert_eq!(
+        fs::read_to_string(target.path().join("foo.gno")).unwrap(),
+        "package foo // local\n"
+    );
+    assert!(replaced
+        .is_unchanged("gno.land/p/me/foo", target.path(), None)
+        .await
+        .unwrap());
+}
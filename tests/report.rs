@@ -0,0 +1,92 @@
+use gget::cache::CacheStats;
+use gget::parallel::{DownloadSummary, PackageReport, PackageStats};
+use gget::report::{render, to_yaml, ReportFormat, Table};
+use std::time::Duration;
+
+fn summary() -> DownloadSummary {
+    DownloadSummary {
+        total_packages: 2,
+        successful: 1,
+        failed: Vec::new(),
+        up_to_date: vec!["gno.land/p/demo/ufmt".to_string()],
+        skipped: Default::default(),
+        packages: vec![PackageReport {
+            package: "gno.land/p/demo/avl".to_string(),
+            duration: Duration::from_millis(42),
+            stats: PackageStats {
+                files: 3,
+                bytes: 2048,
+                cache_hits: 1,
+                network_fetches: 3,
+            },
+            retries: 0,
+        }],
+        duration: Duration::from_millis(50),
+        cache: CacheStats::default(),
+    }
+}
+
+#[test]
+fn test_format_names_parse() {
+    for name in ReportFormat::NAMES {
+        assert!(name.parse::<ReportFormat>().is_ok(), "{}", name);
+    }
+    assert!("xml".parse::<ReportFormat>().is_err());
+}
+
+#[test]
+fn test_summary_in_every_format() {
+    let summary = summary();
+
+    let human = render(&summary, ReportFormat::Human).unwrap();
+    assert!(human.starts_with("Downloaded 2 packages"));
+
+    let json: serde_json::Value =
+        serde_json::from_str(&render(&summary, ReportFormat::Json).unwrap()).unwrap();
+    assert_eq!(json["packages"][0]["bytes"], 2048);
+
+    let csv = render(&summary, ReportFormat::Csv).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("package,status,duration_ms"));
+    assert!(lines[1].starts_with("gno.land/p/demo/avl,downloaded,42,3,2048"));
+    assert!(lines[2].starts_with("gno.land/p/demo/ufmt,up to date"));
+
+    let markdown = render(&summary, ReportFormat::Markdown).unwrap();
+    assert!(markdown.contains("| package | status |"));
+    assert!(markdown.contains("|---|---|"));
+
+    let yaml = render(&summary, ReportFormat::Yaml).unwrap();
+    assert!(yaml.contains("total_packages: 2\n"));
+    assert!(yaml.contains("up_to_date:\n  - gno.land/p/demo/ufmt\n"));
+    assert!(yaml.contains("packages:\n  - package: gno.land/p/demo/avl\n    duration_ms: 42\n"));
+    assert!(yaml.contains("failed: []\n"));
+}
+
+#[test]
+fn test_csv_and_markdown_escaping() {
+    let mut table = Table::new("", &["name", "note"]);
+    table.push_row(["a,b", "say \"hi\""]);
+    table.push_row(["pipe", "x | y"]);
+
+    let csv = table.to_csv();
+    assert!(csv.contains("\"a,b\",\"say \"\"hi\"\"\"\n"));
+    assert!(table.to_markdown().contains("x \\| y"));
+}
+
+#[test]
+fn test_yaml_quotes_ambiguous_strings() {
+    let value = serde_json::json!({
+        "plain": "gno.land/p/demo/avl",
+        "boolish": "true",
+        "numeric": "123",
+        "colon": "a: b",
+        "empty": "",
+    });
+    let yaml = to_yaml(&value);
+    assert!(yaml.contains("plain: gno.land/p/demo/avl\n"));
+    assert!(yaml.contains("boolish: \"true\"\n"));
+    assert!(yaml.contains("numeric: \"123\"\n"));
+    assert!(yaml.contains("colon: \"a: b\"\n"));
+    assert!(yaml.contains("empty: \"\"\n"));
+}
@@ -0,0 +1,100 @@
+use base64::{engine::general_purpose, Engine as _};
+use gget::fetch::PackageManager;
+use gget::report::{render, ReportFormat};
+use gget::repro::repro_check;
+use gget::testing::download_options;
+use serde_json::{json, Value};
+use std::fs;
+use std::sync::{Arc, Mutex};
+use tempfile::tempdir;
+use warp::Filter;
+
+/// Serves `gno.land/p/demo/hello` as a single `hello.gno` whose content the
+/// test can change
+fn spawn_node() -> (String, Arc<Mutex<String>>) {
+    let content = Arc::new(Mutex::new("package hello".to_string()));
+    let served = content.clone();
+    let route = warp::post()
+        .and(warp::body::json())
+        .map(move |request: Value| {
+            let data = general_purpose::STANDARD
+                .decode(request["params"]["data"].as_str().unwrap())
+                .unwrap();
+            let body = if String::from_utf8(data).unwrap().ends_with(".gno") {
+                served.lock().unwrap().clone()
+            } else {
+                "hello.gno\n".to_string()
+            };
+            warp::reply::json(&json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "result": { "response": { "ResponseBase": {
+                    "Error": null,
+                    "Data": general_purpose::STANDARD.encode(body),
+                    "Log": "",
+                } } }
+            }))
+        });
+    let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    (format!("http://{}", addr), content)
+}
+
+async fn install(pm: &PackageManager, target: &std::path::Path) {
+    let summary = pm
+        .download_with_deps_parallel("gno.land/p/demo/hello", target, download_options())
+        .await
+        .unwrap();
+    assert!(summary.failed.is_empty());
+}
+
+#[tokio::test]
+async fn test_matching_tree_passes() {
+    let (url, _) = spawn_node();
+    let cache = tempdir().unwrap();
+    let target = tempdir().unwrap();
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+    install(&pm, target.path()).await;
+
+    let attestation = repro_check(&pm, target.path()).await.unwrap();
+    assert!(attestation.passed);
+    assert_eq!(attestation.packages.len(), 1);
+    assert!(attestation.packages[0].divergent.is_empty());
+    assert!(render(&attestation, ReportFormat::Human)
+        .unwrap()
+        .starts_with("PASS: 1 packages"));
+}
+
+#[tokio::test]
+async fn test_reports_divergent_files() {
+    let (url, content) = spawn_node();
+    let cache = tempdir().unwrap();
+    let target = tempdir().unwrap();
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+    install(&pm, target.path()).await;
+
+    // a local edit to the vendor tree
+    let vendored = target.path().join("gno.land/p/demo/hello/hello.gno");
+    fs::write(&vendored, "package hello // patched").unwrap();
+    let attestation = repro_check(&pm, target.path()).await.unwrap();
+    assert!(!attestation.passed);
+    let check = &attestation.packages[0];
+    assert_eq!(check.fetched.as_ref(), Some(&check.locked));
+    assert_ne!(check.installed.as_ref(), Some(&check.locked));
+    assert_eq!(check.divergent.len(), 1);
+    assert_eq!(check.divergent[0].file, "hello.gno");
+    assert_eq!(check.divergent[0].locked, check.divergent[0].fetched);
+
+    // the chain serving something else than what was locked
+    fs::write(&vendored, "package hello").unwrap();
+    *content.lock().unwrap() = "package hello // upgraded".to_string();
+    let attestation = repro_check(&pm, target.path()).await.unwrap();
+    assert!(!attestation.passed);
+    let file = &attestation.packages[0].divergent[0];
+    assert_eq!(file.locked, file.installed);
+    assert_ne!(file.locked, file.fetched);
+
+    let human = render(&attestation, ReportFormat::Human).unwrap();
+    assert!(human.starts_with("FAIL: 1 of 1 packages"));
+    assert!(human.contains("    hello.gno: locked "));
+}
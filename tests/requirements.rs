@@ -0,0 +1,192 @@
+use base64::{engine::general_purpose, Engine as _};
+use gget::fetch::PackageManager;
+use gget::lock::{Lockfile, LOCKFILE_NAME};
+use gget::requirements::{Requirement, Requirements, RequirementsError};
+use gget::testing::{download_options, FakeChain};
+use serde_json::{json, Value};
+use std::fs;
+use std::sync::{Arc, Mutex};
+use tempfile::tempdir;
+use warp::Filter;
+
+#[test]
+fn test_parse_paths_and_options() {
+    let content = "
+# shared helpers
+gno.land/p/demo/avl @height=123 pin=true layout=import-path
+gno.land/p/demo/ufmt   # latest
+";
+
+    let requirements = Requirements::parse(content).unwrap();
+    assert_eq!(
+        requirements.entries,
+        vec![
+            Requirement {
+                path: "gno.land/p/demo/avl".to_string(),
+                height: Some(123),
+                pin: true,
+                layout: Some("import-path".to_string()),
+                source: None,
+            },
+            Requirement {
+                path: "gno.land/p/demo/ufmt".to_string(),
+                height: None,
+                pin: false,
+                layout: None,
+                source: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_parse_git_source_options() {
+    let requirements = Requirements::parse(
+        "gno.land/p/me/draft git=https://github.com/me/draft rev=v0.1.0 dir=pkg/draft\n",
+    )
+    .unwrap();
+    let source = requirements.entries[0].source.as_ref().unwrap();
+    assert_eq!(
+        source.to_string(),
+        "https://github.com/me/draft@v0.1.0#pkg/draft"
+    );
+}
+
+#[test]
+fn test_parse_errors() {
+    let line_of = |content: &str| match Requirements::parse(content) {
+        Err(RequirementsError::Parse { line, .. }) => line,
+        other => panic!("expected parse error, got {:?}", other),
+    };
+
+    assert_eq!(
+        line_of("gno.land/p/demo/avl\ngno.land/p/demo/ufmt @height=x\n"),
+        2
+    );
+    assert_eq!(line_of("gno.land/p/demo/avl pin=yes\n"), 1);
+    assert_eq!(line_of("gno.land/p/demo/avl version=1\n"), 1);
+    assert_eq!(line_of("gno.land/p/demo/avl pin\n"), 1);
+    assert_eq!(line_of("pin=true\n"), 1);
+    assert_eq!(line_of("gno.land/p/demo/avl\n\ngno.land/p/demo/avl\n"), 3);
+    assert_eq!(line_of("gno.land/p/me/draft git=./draft\n"), 1);
+    assert_eq!(line_of("gno.land/p/me/draft rev=v1\n"), 1);
+    assert_eq!(
+        line_of("gno.land/p/me/draft git=./draft rev=v1 dir=../x\n"),
+        1
+    );
+    assert_eq!(
+        line_of("gno.land/p/me/draft git=./draft rev=v1 @height=3\n"),
+        1
+    );
+}
+
+/// Serves `gno.land/p/demo/avl` as a single `avl.gno` and records the
+/// height of every query
+fn spawn_node() -> (String, Arc<Mutex<Vec<Option<String>>>>) {
+    let heights = Arc::new(Mutex::new(Vec::new()));
+    let seen = heights.clone();
+    let route = warp::post()
+        .and(warp::body::json())
+        .map(move |request: Value| {
+            seen.lock()
+                .unwrap()
+                .push(request["params"]["height"].as_str().map(String::from));
+            let data = general_purpose::STANDARD
+                .decode(request["params"]["data"].as_str().unwrap())
+                .unwrap();
+            let body = if String::from_utf8(data).unwrap().ends_with(".gno") {
+                "package avl"
+            } else {
+                "avl.gno\n"
+            };
+            warp::reply::json(&json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "result": { "response": { "ResponseBase": {
+                    "Error": null,
+                    "Data": general_purpose::STANDARD.encode(body),
+                    "Log": "",
+                } } }
+            }))
+        });
+    let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    (format!("http://{}", addr), heights)
+}
+
+#[tokio::test]
+async fn test_pinned_package_is_queried_and_locked_at_its_height() {
+    let (url, heights) = spawn_node();
+    let cache = tempdir().unwrap();
+    let target = tempdir().unwrap();
+
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf())
+        .with_quiet(true)
+        .with_pinned_height("gno.land/p/demo/avl", 123);
+    let summary = pm
+        .download_roots_with_deps_parallel(
+            &["gno.land/p/demo/avl"],
+            target.path(),
+            download_options(),
+        )
+        .await
+        .unwrap();
+    assert!(summary.failed.is_empty());
+
+    let heights = heights.lock().unwrap();
+    assert!(!heights.is_empty());
+    assert!(heights.iter().all(|h| h.as_deref() == Some("123")));
+
+    let lock = Lockfile::load(&target.path().join(LOCKFILE_NAME)).unwrap();
+    assert_eq!(lock.packages["gno.land/p/demo/avl"].height, Some(123));
+    assert_eq!(
+        fs::read_to_string(target.path().join("gno.land/p/demo/avl/avl.gno")).unwrap(),
+        "package avl"
+    );
+}
+
+#[tokio::test]
+async fn test_unpinned_queries_omit_height() {
+    let (url, heights) = spawn_node();
+    let cache = tempdir().unwrap();
+    let target = tempdir().unwrap();
+
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+    pm.download_package("gno.land/p/demo/avl", target.path())
+        .await
+        .unwrap();
+
+    assert!(heights.lock().unwrap().iter().all(Option::is_none));
+}
+
+#[tokio::test]
+async fn test_repinning_at_another_height_refetches_the_package() {
+    let chain =
+        FakeChain::new().with_package("gno.land/p/demo/avl", &[("avl.gno", "package avl // 1\n")]);
+    chain.advance();
+    chain.set_file("gno.land/p/demo/avl", "avl.gno", "package avl // 2\n");
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let target = tempdir().unwrap();
+    let avl = target.path().join("gno.land/p/demo/avl/avl.gno");
+
+    for height in [1, 2] {
+        let summary = PackageManager::new(Some(url.clone()), cache.path().to_path_buf())
+            .with_quiet(true)
+            .with_pinned_height("gno.land/p/demo/avl", height)
+            .download_roots_with_deps_parallel(
+                &["gno.land/p/demo/avl"],
+                target.path(),
+                download_options(),
+            )
+            .await
+            .unwrap();
+        assert!(summary.up_to_date.is_empty(), "{}", summary);
+        assert_eq!(
+            fs::read_to_string(&avl).unwrap(),
+            format!("package avl // {}\n", height)
+        );
+        let lock = Lockfile::load(&target.path().join(LOCKFILE_NAME)).unwrap();
+        assert_eq!(lock.packages["gno.land/p/demo/avl"].height, Some(height));
+    }
+}
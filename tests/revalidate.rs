@@ -0,0 +1,155 @@
+use async_trait::async_trait;
+use gget::cache::{AsyncStorage, CacheError};
+use gget::fetch::PackageManager;
+use gget::testing::FakeChain;
+use std::collections::HashMap;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tempfile::tempdir;
+
+/// Storage whose entries only expire when told to, so renewal doesn't
+/// race the clock
+#[derive(Clone, Default)]
+struct ExpiringStorage {
+    /// Content of each entry and whether it has expired
+    entries: Arc<Mutex<HashMap<String, (String, bool)>>>,
+}
+
+impl ExpiringStorage {
+    /// Expires every entry stored so far
+    fn expire(&self) {
+        for (_, expired) in self.entries.lock().unwrap().values_mut() {
+            *expired = true;
+        }
+    }
+}
+
+#[async_trait]
+impl AsyncStorage for ExpiringStorage {
+    async fn get(&self, key: &str) -> Result<Option<String>, CacheError> {
+        Ok(match self.entries.lock().unwrap().get(key) {
+            Some((content, false)) => Some(content.clone()),
+            _ => None,
+        })
+    }
+
+    async fn get_stale(&self, key: &str) -> Result<Option<String>, CacheError> {
+        Ok(self
+            .entries
+            .lock()
+            .unwrap()
+            .get(key)
+            .map(|(content, _)| content.clone()))
+    }
+
+    async fn set(&self, key: &str, value: &str) -> Result<(), CacheError> {
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), (value.to_string(), false));
+        Ok(())
+    }
+
+    async fn cleanup(&self) -> Result<(), CacheError> {
+        Ok(())
+    }
+}
+
+fn package_manager(
+    url: &str,
+    storage: &ExpiringStorage,
+    cache: &std::path::Path,
+) -> PackageManager {
+    PackageManager::builder()
+        .endpoint(url)
+        .cache_dir(cache)
+        .storage(storage.clone())
+        .cache_ttl(Duration::from_secs(3600))
+        .build()
+        .unwrap()
+        .with_quiet(true)
+}
+
+#[tokio::test]
+async fn test_expired_entries_are_renewed_while_the_chain_is_unchanged() {
+    let chain = FakeChain::new().with_package(
+        "gno.land/p/demo/avl",
+        &[("avl.gno", "package avl\n"), ("node.gno", "package avl\n")],
+    );
+    let url = chain.spawn();
+    let storage = ExpiringStorage::default();
+    let cache = tempdir().unwrap();
+    let target = tempdir().unwrap();
+
+    let pm = package_manager(&url, &storage, cache.path());
+    pm.download_package("gno.land/p/demo/avl", target.path())
+        .await
+        .unwrap();
+    assert_eq!(chain.file_queries(), 3);
+
+    storage.expire();
+    let pm = package_manager(&url, &storage, cache.path());
+    pm.download_package("gno.land/p/demo/avl", target.path())
+        .await
+        .unwrap();
+    // only the file list was asked for again
+    assert_eq!(chain.file_queries(), 4);
+    assert_eq!(
+        fs::read_to_string(target.path().join("avl.gno")).unwrap(),
+        "package avl\n"
+    );
+}
+
+#[tokio::test]
+async fn test_expired_entries_are_refetched_once_the_chain_moved_on() {
+    let chain =
+        FakeChain::new().with_package("gno.land/p/demo/avl", &[("avl.gno", "package avl\n")]);
+    let url = chain.spawn();
+    let storage = ExpiringStorage::default();
+    let cache = tempdir().unwrap();
+    let target = tempdir().unwrap();
+
+    let pm = package_manager(&url, &storage, cache.path());
+    pm.download_package("gno.land/p/demo/avl", target.path())
+        .await
+        .unwrap();
+
+    chain.advance();
+    chain.set_file("gno.land/p/demo/avl", "avl.gno", "package avl // v2\n");
+    storage.expire();
+    let pm = package_manager(&url, &storage, cache.path());
+    pm.download_package("gno.land/p/demo/avl", target.path())
+        .await
+        .unwrap();
+    assert_eq!(chain.file_queries(), 4);
+    assert_eq!(
+        fs::read_to_string(target.path().join("avl.gno")).unwrap(),
+        "package avl // v2\n"
+    );
+}
+
+#[tokio::test]
+async fn test_pinned_packages_are_renewed_without_a_query() {
+    let chain =
+        FakeChain::new().with_package("gno.land/p/demo/avl", &[("avl.gno", "package avl\n")]);
+    let url = chain.spawn();
+    chain.advance();
+    let storage = ExpiringStorage::default();
+    let cache = tempdir().unwrap();
+    let target = tempdir().unwrap();
+
+    let pinned = |pm: PackageManager| pm.with_pinned_height("gno.land/p/demo/avl", 1);
+    pinned(package_manager(&url, &storage, cache.path()))
+        .download_package("gno.land/p/demo/avl", target.path())
+        .await
+        .unwrap();
+    assert_eq!(chain.file_queries(), 2);
+
+    storage.expire();
+    pinned(package_manager(&url, &storage, cache.path()))
+        .download_package("gno.land/p/demo/avl", target.path())
+        .await
+        .unwrap();
+    assert_eq!(chain.file_queries(), 2);
+}
@@ -0,0 +1,103 @@
+use gget::lock::{LockedPackage, Lockfile};
+use gget::policy::Policy;
+use gget::report::{render, ReportFormat};
+use gget::review::{DependencyReview, FindingKind};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+fn write_package(root: &Path, path: &str, source: &str) -> LockedPackage {
+    let dir = root.join(path);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("pkg.gno"), source).unwrap();
+    LockedPackage::from_dir(path, &dir, &[]).unwrap()
+}
+
+fn sample(root: &Path) -> Lockfile {
+    let mut lock = Lockfile {
+        roots: vec!["gno.land/r/demo/app".to_string()],
+        ..Default::default()
+    };
+    lock.insert(write_package(
+        root,
+        "gno.land/p/demo/ufmt",
+        "package ufmt\n",
+    ));
+    lock.insert(write_package(
+        root,
+        "gno.land/p/demo/avl",
+        "package avl\n\nimport \"gno.land/p/demo/ufmt\"\n",
+    ));
+    lock.insert(write_package(
+        root,
+        "gno.land/r/demo/app",
+        "package app\n\nimport (\n\t\"gno.land/p/demo/avl\"\n\t\"gno.land/p/demo/ufmt\"\n\t\"strings\"\n)\n",
+    ));
+    lock
+}
+
+#[test]
+fn test_review_tree_and_sizes() {
+    let dir = tempdir().unwrap();
+    let lock = sample(dir.path());
+
+    let review = DependencyReview::new(&lock, dir.path(), &Policy::default()).unwrap();
+
+    assert!(review.findings.is_empty());
+    assert_eq!(review.packages.len(), 3);
+    assert_eq!(review.packages[1].bytes, Some(13));
+    assert_eq!(
+        review.tree(),
+        vec![
+            (0, "gno.land/r/demo/app", false),
+            (1, "gno.land/p/demo/avl", false),
+            (2, "gno.land/p/demo/ufmt", false),
+            (1, "gno.land/p/demo/ufmt", true),
+        ]
+    );
+}
+
+#[test]
+fn test_review_findings_and_markdown() {
+    let dir = tempdir().unwrap();
+    let lock = sample(dir.path());
+    fs::write(dir.path().join("gno.land/p/demo/avl/pkg.gno"), "tampered").unwrap();
+
+    let mut previous = lock.clone();
+    previous.packages.remove("gno.land/p/demo/ufmt");
+
+    let policy = Policy {
+        deny: vec!["gno.land/r/*".to_string()],
+        ..Default::default()
+    };
+    let review = DependencyReview::new(&lock, dir.path(), &policy)
+        .unwrap()
+        .with_lock_diff(previous.diff(&lock));
+
+    let kinds: Vec<_> = review
+        .findings
+        .iter()
+        .map(|f| (f.package.as_str(), f.kind))
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            ("gno.land/p/demo/avl", FindingKind::Integrity),
+            ("gno.land/r/demo/app", FindingKind::Policy),
+        ]
+    );
+
+    let markdown = render(&review, ReportFormat::Markdown).unwrap();
+    assert!(markdown.starts_with("## Dependency review\n"));
+    for section in [
+        "### Packages",
+        "### Dependency tree",
+        "### Findings",
+        "### Lockfile changes",
+    ] {
+        assert!(markdown.contains(section), "missing {}", section);
+    }
+    assert!(markdown.contains("  - `gno.land/p/demo/avl`\n"));
+    assert!(markdown.contains("| added | `gno.land/p/demo/ufmt` |"));
+    assert!(markdown.contains("- **policy** `gno.land/r/demo/app`"));
+}
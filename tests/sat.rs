@@ -0,0 +1,85 @@
+use gget::sat::{Formula, Lit};
+use proptest::prelude::*;
+
+fn satisfies(clauses: &[Vec<Lit>], values: &[bool]) -> bool {
+    clauses.iter().all(|clause| {
+        clause
+            .iter()
+            .any(|lit| values[lit.var()] == lit.is_positive())
+    })
+}
+
+#[test]
+fn test_implications_propagate() {
+    // 0 → 1 → 2, with 0 required
+    let mut formula = Formula::new(3);
+    formula.add_clause([Lit::neg(0), Lit::pos(1)]);
+    formula.add_clause([Lit::neg(1), Lit::pos(2)]);
+    formula.add_clause([Lit::pos(0)]);
+
+    assert_eq!(formula.solve(), Some(vec![true, true, true]));
+}
+
+#[test]
+fn test_contradictions_have_no_solution() {
+    let mut formula = Formula::new(2);
+    formula.add_clause([Lit::pos(0), Lit::pos(1)]);
+    formula.add_clause([Lit::neg(0), Lit::pos(1)]);
+    formula.add_clause([Lit::pos(0), Lit::neg(1)]);
+    formula.add_clause([Lit::neg(0), Lit::neg(1)]);
+    assert_eq!(formula.solve(), None);
+
+    let mut formula = Formula::new(1);
+    formula.add_clause([]);
+    assert_eq!(formula.solve(), None);
+
+    let formula = Formula::new(1);
+    assert_eq!(formula.solve_with(&[Lit::pos(0), Lit::neg(0)]), None);
+}
+
+#[test]
+fn test_earlier_soft_literals_win() {
+    // at most one of 0, 1, 2
+    let mut formula = Formula::new(3);
+    formula.add_clause([Lit::neg(0), Lit::neg(1)]);
+    formula.add_clause([Lit::neg(0), Lit::neg(2)]);
+    formula.add_clause([Lit::neg(1), Lit::neg(2)]);
+
+    let soft = [Lit::pos(1), Lit::pos(0), Lit::pos(2)];
+    assert_eq!(formula.maximize(&soft), Some(vec![false, true, false]));
+}
+
+fn formula() -> impl Strategy<Value = (usize, Vec<Vec<Lit>>)> {
+    (1usize..7).prop_flat_map(|vars| {
+        let lit = (0..vars, any::<bool>()).prop_map(|(var, positive)| {
+            if positive {
+                Lit::pos(var)
+            } else {
+                Lit::neg(var)
+            }
+        });
+        (
+            Just(vars),
+            prop::collection::vec(prop::collection::vec(lit, 1..4), 0..20),
+        )
+    })
+}
+
+proptest! {
+    #[test]
+    fn test_agrees_with_brute_force((vars, clauses) in formula()) {
+        let mut formula = Formula::new(vars);
+        for clause in &clauses {
+            formula.add_clause(clause.iter().copied());
+        }
+
+        let exists = (0..1u32 << vars).any(|bits| {
+            let values: Vec<bool> = (0..vars).map(|var| bits & (1 << var) != 0).collect();
+            satisfies(&clauses, &values)
+        });
+        match formula.solve() {
+            Some(values) => prop_assert!(satisfies(&clauses, &values)),
+            None => prop_assert!(!exists),
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use base64::{engine::general_purpose, Engine as _};
+use gget::fetch::PackageManager;
+use gget::session::{parse_headers, SessionError};
+use serde_json::{json, Value};
+use std::fs;
+use std::sync::{Arc, Mutex};
+use tempfile::tempdir;
+use warp::http::StatusCode;
+use warp::Filter;
+
+/// Cookie header of each request, in order
+type SeenCookies = Arc<Mutex<Vec<Option<String>>>>;
+
+/// Serves `gno.land/p/demo/hello` as a single `hello.gno` to requests whose
+/// `x-session` header holds the current token, setting a `sid` cookie on
+/// every response. Records the cookie each request came with.
+fn spawn_gateway(token: &str) -> (String, Arc<Mutex<String>>, SeenCookies) {
+    let token = Arc::new(Mutex::new(token.to_string()));
+    let cookies = Arc::new(Mutex::new(Vec::new()));
+    let (expected, seen) = (token.clone(), cookies.clone());
+    let route = warp::post()
+        .and(warp::header::optional::<String>("x-session"))
+        .and(warp::header::optional::<String>("cookie"))
+        .and(warp::body::json())
+        .map(
+            move |session: Option<String>, cookie: Option<String>, request: Value| {
+                seen.lock().unwrap().push(cookie);
+                if session.as_deref() != Some(expected.lock().unwrap().as_str()) {
+                    return warp::reply::with_status(
+                        warp::reply::json(&json!({ "error": "unauthorized" })),
+                        StatusCode::UNAUTHORIZED,
+                    );
+                }
+                let data = general_purpose::STANDARD
+                    .decode(request["params"]["data"].as_str().unwrap())
+                    .unwrap();
+                let body = if String::from_utf8(data).unwrap().ends_with(".gno") {
+                    "package hello"
+                } else {
+                    "hello.gno\n"
+                };
+                warp::reply::with_status(
+                    warp::reply::json(&json!({
+                        "jsonrpc": "2.0",
+                        "id": request["id"],
+                        "result": { "response": { "ResponseBase": {
+                            "Error": null,
+                            "Data": general_purpose::STANDARD.encode(body),
+                            "Log": "",
+                        } } }
+                    })),
+                    StatusCode::OK,
+                )
+            },
+        )
+        .map(|reply| warp::reply::with_header(reply, "set-cookie", "sid=abc; Path=/"));
+    let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    (format!("http://{}", addr), token, cookies)
+}
+
+#[test]
+fn test_parse_headers() {
+    let headers = parse_headers("# session\nX-Session: abc\n\nAuthorization: Bearer t\n").unwrap();
+    assert_eq!(headers["x-session"], "abc");
+    assert_eq!(headers["authorization"], "Bearer t");
+
+    assert!(matches!(
+        parse_headers("not a header"),
+        Err(SessionError::InvalidHeader(_))
+    ));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_login_command_authenticates_and_refreshes() {
+    let (url, token, _) = spawn_gateway("first");
+    let dir = tempdir().unwrap();
+    let cache = tempdir().unwrap();
+    let token_file = dir.path().join("token");
+    let log = dir.path().join("log");
+    fs::write(&token_file, "first").unwrap();
+    let command = format!(
+        "echo \"$GGET_ENDPOINT\" >> {log}; echo \"X-Session: $(cat {token})\"",
+        log = log.display(),
+        token = token_file.display()
+    );
+
+    let pm = PackageManager::builder()
+        .endpoint(&url)
+        .cache_dir(cache.path())
+        .login_command(command)
+        .build()
+        .unwrap()
+        .with_quiet(true);
+    let target = tempdir().unwrap();
+    pm.download_package("gno.land/p/demo/hello", target.path())
+        .await
+        .unwrap();
+    assert!(target.path().join("hello.gno").exists());
+    assert_eq!(fs::read_to_string(&log).unwrap(), format!("{}\n", url));
+
+    // the session expires: the next rejected request logs in again
+    *token.lock().unwrap() = "second".to_string();
+    fs::write(&token_file, "second").unwrap();
+    pm.download_package("gno.land/p/demo/other", target.path())
+        .await
+        .unwrap();
+    assert_eq!(fs::read_to_string(&log).unwrap().lines().count(), 2);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_failing_login_command_fails_the_download() {
+    let (url, _, _) = spawn_gateway("token");
+    let cache = tempdir().unwrap();
+    let pm = PackageManager::builder()
+        .endpoint(&url)
+        .cache_dir(cache.path())
+        .login_command("echo denied >&2; exit 3")
+        .build()
+        .unwrap()
+        .with_quiet(true);
+
+    let err = pm
+        .download_package("gno.land/p/demo/hello", tempdir().unwrap().path())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("denied"), "{}", err);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_cookie_jar_sends_session_cookies_back() {
+    let (url, _, cookies) = spawn_gateway("token");
+    let cache = tempdir().unwrap();
+    let pm = PackageManager::builder()
+        .endpoint(&url)
+        .cache_dir(cache.path())
+        .login_command("echo 'X-Session: token'")
+        .cookies(true)
+        .build()
+        .unwrap()
+        .with_quiet(true);
+    pm.download_package("gno.land/p/demo/hello", tempdir().unwrap().path())
+        .await
+        .unwrap();
+
+    let cookies = cookies.lock().unwrap();
+    assert!(cookies.len() > 1);
+    assert_eq!(cookies[0], None);
+    assert!(cookies[1..]
+        .iter()
+        .all(|cookie| cookie.as_deref() == Some("sid=abc")));
+}
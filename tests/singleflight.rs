@@ -0,0 +1,81 @@
+use futures::future::join_all;
+use gget::error::ErrorKind;
+use gget::fetch::PackageManager;
+use gget::singleflight::SingleFlight;
+use gget::testing::{Failure, FakeChain};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tempfile::tempdir;
+
+fn chain() -> FakeChain {
+    let chain = FakeChain::new().with_package(
+        "gno.land/p/demo/avl",
+        &[("avl.gno", "package avl"), ("node.gno", "package avl")],
+    );
+    chain.set_latency(Duration::from_millis(100));
+    chain
+}
+
+#[tokio::test]
+async fn test_single_flight_runs_once_per_key() {
+    let flight = SingleFlight::<u32>::new();
+    let calls = AtomicUsize::new(0);
+    let call = || async {
+        calls.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        7
+    };
+
+    let outputs = join_all((0..4).map(|_| flight.run("a", call(), |v| *v, |v| v))).await;
+    assert_eq!(outputs, vec![7; 4]);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    assert!(flight.is_empty());
+
+    // finished flights aren't reused
+    flight.run("a", call(), |v| *v, |v| v).await;
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_concurrent_downloads_share_queries() {
+    let chain = chain();
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+
+    let targets: Vec<_> = (0..4).map(|_| tempdir().unwrap()).collect();
+    let results = join_all(
+        targets
+            .iter()
+            .map(|target| pm.download_package("gno.land/p/demo/avl", target.path())),
+    )
+    .await;
+    for result in results {
+        result.unwrap();
+    }
+    for target in &targets {
+        assert!(target.path().join("node.gno").exists());
+    }
+
+    // one file list and two files, however many tasks asked for them
+    assert_eq!(chain.file_queries(), 3);
+}
+
+#[tokio::test]
+async fn test_waiting_tasks_share_the_failure() {
+    let chain = chain();
+    chain.fail_next(1, Failure::Rpc("package not found".to_string()));
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+
+    let target = tempdir().unwrap();
+    let results =
+        join_all((0..3).map(|_| pm.download_package("gno.land/p/demo/avl", target.path()))).await;
+    assert_eq!(chain.file_queries(), 1);
+    for result in results {
+        let err = result.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert!(err.to_string().contains("package not found"), "{}", err);
+    }
+}
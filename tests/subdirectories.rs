@@ -0,0 +1,106 @@
+use gget::fetch::PackageManager;
+use gget::testing::{download_options, FakeChain};
+use std::time::Duration;
+use tempfile::tempdir;
+
+/// A package with files two directories deep, and a subdirectory that is a
+/// package of its own
+fn chain() -> FakeChain {
+    FakeChain::new().with_package(
+        "gno.land/r/demo/app",
+        &[
+            ("app.gno", "package app\n"),
+            ("static/README.md", "# app\n"),
+            ("internal/util/util.gno", "package util\n"),
+            ("internal/util/testdata/case.txt", "case\n"),
+            ("plugin/gno.mod", "module gno.land/r/demo/app/plugin\n"),
+            ("plugin/plugin.gno", "package plugin\n"),
+        ],
+    )
+}
+
+/// Directories listed, relative to the package
+fn listed_dirs(chain: &FakeChain) -> Vec<String> {
+    chain
+        .queries()
+        .iter()
+        .filter(|query| query.path == "vm/qfile")
+        .filter_map(|query| query.data.strip_prefix("gno.land/r/demo/app/"))
+        .filter(|target| !target.contains('.'))
+        .map(str::to_string)
+        .collect()
+}
+
+#[tokio::test]
+async fn test_subdirectories_are_downloaded() {
+    let chain = chain();
+    let cache = tempdir().unwrap();
+    let target = tempdir().unwrap();
+    let pm = PackageManager::new(Some(chain.spawn()), cache.path().to_path_buf()).with_quiet(true);
+
+    let stats = pm
+        .download_package("gno.land/r/demo/app", target.path())
+        .await
+        .unwrap();
+    assert_eq!(stats.files, 4);
+    for file in [
+        "app.gno",
+        "static/README.md",
+        "internal/util/util.gno",
+        "internal/util/testdata/case.txt",
+    ] {
+        assert!(target.path().join(file).is_file(), "{} is missing", file);
+    }
+    // a package of its own, downloaded when something requires it
+    assert!(!target.path().join("plugin").exists());
+    assert_eq!(
+        listed_dirs(&chain),
+        [
+            "internal",
+            "plugin",
+            "static",
+            "internal/util",
+            "internal/util/testdata"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_nested_packages_download_concurrently() {
+    let chain = FakeChain::new()
+        .with_package(
+            "gno.land/p/demo/avl",
+            &[("avl.gno", "package avl\n"), ("tree.gno", "package avl\n")],
+        )
+        .with_package(
+            "gno.land/p/demo/avl/pager",
+            &[
+                ("gno.mod", "module gno.land/p/demo/avl/pager\n"),
+                ("pager.gno", "package pager\n"),
+            ],
+        );
+    chain.set_latency(Duration::from_millis(10));
+    let url = chain.spawn();
+
+    for _ in 0..3 {
+        let cache = tempdir().unwrap();
+        let target = tempdir().unwrap();
+        let pm =
+            PackageManager::new(Some(url.clone()), cache.path().to_path_buf()).with_quiet(true);
+        let options = download_options();
+        let summary = pm
+            .download_packages_parallel(
+                vec!["gno.land/p/demo/avl/pager", "gno.land/p/demo/avl"],
+                target.path(),
+                options,
+            )
+            .await
+            .unwrap();
+        assert!(summary.failed.is_empty(), "{:?}", summary.failed);
+
+        let avl = target.path().join("gno.land/p/demo/avl");
+        for file in ["avl.gno", "tree.gno", "pager/gno.mod", "pager/pager.gno"] {
+            assert!(avl.join(file).is_file(), "{} is missing", file);
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use gget::fetch::PackageManager;
+use gget::gnomod::GnoMod;
+use gget::lock::{LockError, Lockfile, LOCKFILE_NAME};
+use gget::testing::{download_options, FakeChain};
+use gget::tidy::{tidy, TidyAction, TidyError, TidyOptions, TidyReport};
+use gget::trash::Trash;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::tempdir;
+
+fn chain() -> FakeChain {
+    FakeChain::new()
+        .with_package(
+            "gno.land/p/demo/foo",
+            &[("foo.gno", "package foo\n\nimport \"gno.land/p/demo/avl\"\n")],
+        )
+        .with_package("gno.land/p/demo/avl", &[("avl.gno", "package avl\n")])
+}
+
+fn options(check: bool) -> TidyOptions {
+    TidyOptions {
+        check,
+        download: download_options(),
+    }
+}
+
+fn packages(report: &TidyReport, action: TidyAction) -> Vec<&str> {
+    report.packages(action).collect()
+}
+
+fn write_module(dir: &Path, imports: &[&str]) {
+    let imports: String = imports
+        .iter()
+        .map(|import| format!("import \"{}\"\n", import))
+        .collect();
+    fs::write(dir.join("app.gno"), format!("package app\n\n{}", imports)).unwrap();
+}
+
+#[tokio::test]
+async fn test_tidy_reconciles_gnomod_lockfile_and_vendor_dir() {
+    let chain = chain();
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let project = tempdir().unwrap();
+    let gnomod = project.path().join("gno.mod");
+    let target = project.path().join("gno");
+    fs::write(
+        &gnomod,
+        "module gno.land/r/me/app\n\n// kept as written\nrequire (\n\tgno.land/p/demo/old v0.0.0-latest\n)\n",
+    )
+    .unwrap();
+    write_module(
+        project.path(),
+        &["gno.land/p/demo/foo", "gno.land/r/me/app/sub", "strings"],
+    );
+    let trash = tempdir().unwrap();
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf())
+        .with_quiet(true)
+        .with_trash(Trash::new(trash.path()));
+
+    let check = tidy(&pm, &gnomod, &target, options(true)).await.unwrap();
+    assert!(!check.is_tidy());
+    assert!(!check.applied);
+    assert_eq!(
+        packages(&check, TidyAction::Require),
+        ["gno.land/p/demo/avl", "gno.land/p/demo/foo"]
+    );
+    assert_eq!(
+        packages(&check, TidyAction::Unrequire),
+        ["gno.land/p/demo/old"]
+    );
+    assert_eq!(
+        packages(&check, TidyAction::Download),
+        ["gno.land/p/demo/avl", "gno.land/p/demo/foo"]
+    );
+    assert!(check.lockfile_stale);
+    // checking changes nothing
+    assert!(fs::read_to_string(&gnomod).unwrap().contains("demo/old"));
+    assert!(!target.exists());
+
+    let applied = tidy(&pm, &gnomod, &target, options(false)).await.unwrap();
+    assert!(applied.applied);
+    assert_eq!(
+        fs::read_to_string(&gnomod).unwrap(),
+        "module gno.land/r/me/app\n\n// kept as written\n\nrequire (\n\tgno.land/p/demo/foo v0.0.0-latest\n\tgno.land/p/demo/avl v0.0.0-latest // indirect\n)\n"
+    );
+    assert!(target.join("gno.land/p/demo/foo/foo.gno").exists());
+    assert!(target.join("gno.land/p/demo/avl/avl.gno").exists());
+    let lock = Lockfile::load(&target.join(LOCKFILE_NAME)).unwrap();
+    assert_eq!(lock.roots, ["gno.land/p/demo/foo"]);
+    assert_eq!(lock.packages.len(), 2);
+
+    let again = tidy(&pm, &gnomod, &target, options(true)).await.unwrap();
+    assert!(again.is_tidy(), "{:?}", again);
+
+    // foo goes out of use, avl is now imported directly
+    write_module(project.path(), &["gno.land/p/demo/avl"]);
+    let pruned = tidy(&pm, &gnomod, &target, options(false)).await.unwrap();
+    assert_eq!(
+        packages(&pruned, TidyAction::Unrequire),
+        ["gno.land/p/demo/foo"]
+    );
+    assert_eq!(
+        packages(&pruned, TidyAction::Prune),
+        ["gno.land/p/demo/foo"]
+    );
+    let requires = GnoMod::load(&gnomod).unwrap().requires;
+    assert_eq!(requires.len(), 1);
+    assert_eq!(requires[0].path, "gno.land/p/demo/avl");
+    assert!(!fs::read_to_string(&gnomod).unwrap().contains("indirect"));
+    assert!(!target.join("gno.land/p/demo/foo").exists());
+    assert!(target.join("gno.land/p/demo/avl/avl.gno").exists());
+    assert_eq!(Trash::new(trash.path()).entries().unwrap().len(), 1);
+    let lock = Lockfile::load(&target.join(LOCKFILE_NAME)).unwrap();
+    assert_eq!(lock.roots, ["gno.land/p/demo/avl"]);
+    assert_eq!(lock.packages.len(), 1);
+
+    assert!(tidy(&pm, &gnomod, &target, options(true))
+        .await
+        .unwrap()
+        .is_tidy());
+}
+
+#[tokio::test]
+async fn test_tidy_check_reports_modified_vendored_packages() {
+    let chain = chain();
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let project = tempdir().unwrap();
+    let gnomod = project.path().join("gno.mod");
+    let target = project.path().join("gno");
+    fs::write(&gnomod, "module gno.land/r/me/app\n").unwrap();
+    write_module(project.path(), &["gno.land/p/demo/avl"]);
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+
+    tidy(&pm, &gnomod, &target, options(false)).await.unwrap();
+    fs::write(
+        target.join("gno.land/p/demo/avl/avl.gno"),
+        "package avl // edited\n",
+    )
+    .unwrap();
+
+    let check = tidy(&pm, &gnomod, &target, options(true)).await.unwrap();
+    assert!(!check.is_tidy());
+    assert_eq!(
+        packages(&check, TidyAction::Download),
+        ["gno.land/p/demo/avl"]
+    );
+    assert!(!check.gnomod_stale);
+
+    tidy(&pm, &gnomod, &target, options(false)).await.unwrap();
+    assert_eq!(
+        fs::read_to_string(target.join("gno.land/p/demo/avl/avl.gno")).unwrap(),
+        "package avl\n"
+    );
+}
+
+#[tokio::test]
+async fn test_tidy_refuses_a_lockfile_reaching_outside_the_target() {
+    let project = tempdir().unwrap();
+    let gnomod = project.path().join("gno.mod");
+    let target = project.path().join("gno");
+    fs::create_dir_all(&target).unwrap();
+    fs::write(&gnomod, "module gno.land/r/me/app\n").unwrap();
+    write_module(project.path(), &[]);
+    let victim = project.path().join("victim.txt");
+    fs::write(&victim, "keep me\n").unwrap();
+    let pm = PackageManager::new(None, tempdir().unwrap().path().to_path_buf()).with_quiet(true);
+
+    for (package, file) in [
+        ("../victim.txt", "x.gno"),
+        ("gno.land/p/demo/avl", "../../../../victim.txt"),
+    ] {
+        fs::write(
+            target.join(LOCKFILE_NAME),
+            format!(
+                r#"{{"version": 1, "packages": {{"{package}": {{"path": "{package}", "hash": "", "files": {{"{file}": ""}}}}}}}}"#
+            ),
+        )
+        .unwrap();
+        assert!(matches!(
+            Lockfile::load(&target.join(LOCKFILE_NAME)),
+            Err(LockError::UnsafePath(_))
+        ));
+        let result = tidy(&pm, &gnomod, &target, options(false)).await;
+        assert!(
+            matches!(result, Err(TidyError::Lock(LockError::UnsafePath(_)))),
+            "{:?}",
+            result
+        );
+        assert_eq!(fs::read_to_string(&victim).unwrap(), "keep me\n");
+    }
+}
+
+/// Every file under `dir` with its content
+fn snapshot(dir: &Path) -> BTreeMap<PathBuf, Vec<u8>> {
+    let mut files = BTreeMap::new();
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            files.extend(snapshot(&path));
+        } else {
+            files.insert(path.clone(), fs::read(&path).unwrap());
+        }
+    }
+    files
+}
+
+#[tokio::test]
+async fn test_dry_run_changes_nothing() {
+    let chain = chain();
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let project = tempdir().unwrap();
+    let gnomod = project.path().join("gno.mod");
+    let target = project.path().join("gno");
+    fs::write(&gnomod, "module gno.land/r/me/app\n").unwrap();
+    write_module(project.path(), &["gno.land/p/demo/foo"]);
+    let pm = PackageManager::new(Some(url.clone()), cache.path().to_path_buf()).with_quiet(true);
+    tidy(&pm, &gnomod, &target, options(false)).await.unwrap();
+
+    // tidying now would unrequire and prune foo, and require avl
+    write_module(project.path(), &["gno.land/p/demo/avl"]);
+    let before = snapshot(project.path());
+    let output = tokio::process::Command::new(env!("CARGO_BIN_EXE_gget"))
+        .current_dir(project.path())
+        .args(["--rpc-endpoint", &url, "--cache-dir"])
+        .arg(cache.path())
+        .args(["tidy", "--dry-run"])
+        .output()
+        .await
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("gno.land/p/demo/foo"));
+    assert_eq!(snapshot(project.path()), before);
+}
@@ -0,0 +1,39 @@
+use gget::timings::Timings;
+use std::thread::sleep;
+use std::time::Duration;
+use tracing_subscriber::layer::SubscriberExt;
+
+#[test]
+fn test_nested_phases_are_not_double_counted() {
+    let timings = Timings::new();
+    let subscriber = tracing_subscriber::registry().with(timings.layer());
+
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info_span!("resolve").in_scope(|| {
+            for _ in 0..2 {
+                tracing::info_span!("rpc").in_scope(|| sleep(Duration::from_millis(20)));
+            }
+            sleep(Duration::from_millis(5));
+        });
+        // spans outside the known phases are ignored
+        tracing::info_span!("unrelated").in_scope(|| sleep(Duration::from_millis(5)));
+    });
+
+    let rpc = timings.get("rpc").unwrap();
+    assert_eq!(rpc.count, 2);
+    assert!(rpc.busy >= Duration::from_millis(40));
+
+    let resolve = timings.get("resolve").unwrap();
+    assert_eq!(resolve.count, 1);
+    assert!(resolve.busy >= Duration::from_millis(5));
+    assert!(
+        resolve.busy < Duration::from_millis(40),
+        "nested rpc time leaked into resolve: {:?}",
+        resolve.busy
+    );
+
+    assert!(timings.get("unrelated").is_none());
+    let report = timings.report(Duration::from_millis(60)).to_string();
+    assert!(report.contains("rpc"));
+    assert!(report.contains("validate"));
+}
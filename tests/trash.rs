@@ -0,0 +1,203 @@
+use base64::{engine::general_purpose, Engine as _};
+use gget::fetch::PackageManager;
+use gget::trash::{Trash, TrashError};
+use serde_json::{json, Value};
+use std::fs;
+use tempfile::tempdir;
+use warp::Filter;
+
+#[test]
+fn test_discard_and_restore_directory() {
+    let root = tempdir().unwrap();
+    let trash = Trash::new(root.path().join(".gget-trash"));
+    let pkg = root.path().join("avl");
+    fs::create_dir_all(&pkg).unwrap();
+    fs::write(pkg.join("avl.gno"), "package avl // v1").unwrap();
+
+    let entry = trash
+        .discard("gno.land/p/demo/avl", &pkg)
+        .unwrap()
+        .expect("trashed");
+    assert!(!pkg.exists());
+    assert_eq!(entry.package, "gno.land/p/demo/avl");
+    assert_eq!(entry.size, "package avl // v1".len() as u64);
+    assert_eq!(trash.entries().unwrap(), vec![entry]);
+
+    // a newer version took its place
+    fs::create_dir_all(&pkg).unwrap();
+    fs::write(pkg.join("avl.gno"), "package avl // v2").unwrap();
+
+    trash.restore("gno.land/p/demo/avl", None).unwrap();
+    assert_eq!(
+        fs::read_to_string(pkg.join("avl.gno")).unwrap(),
+        "package avl // v1"
+    );
+
+    // the replaced version can be restored in turn
+    let entries = trash.entries().unwrap();
+    assert_eq!(entries.len(), 1);
+    trash.restore("gno.land/p/demo/avl", None).unwrap();
+    assert_eq!(
+        fs::read_to_string(pkg.join("avl.gno")).unwrap(),
+        "package avl // v2"
+    );
+
+    assert!(matches!(
+        trash.restore("gno.land/p/demo/missing", None),
+        Err(TrashError::NotFound(_))
+    ));
+}
+
+#[test]
+fn test_failed_restore_keeps_the_entry() {
+    let root = tempdir().unwrap();
+    let trash = Trash::new(root.path().join(".gget-trash"));
+    let blocker = root.path().join("not-a-dir");
+    fs::write(&blocker, "").unwrap();
+
+    let pkg = root.path().join("avl");
+    fs::create_dir_all(&pkg).unwrap();
+    fs::write(pkg.join("avl.gno"), "package avl // v1").unwrap();
+    trash.discard("gno.land/p/demo/avl", &pkg).unwrap();
+    let hello = root.path().join("hello");
+    fs::create_dir_all(&hello).unwrap();
+    fs::write(hello.join("hello.gno"), "package hello // v1").unwrap();
+    trash
+        .save_files("gno.land/p/demo/hello", &hello, &["hello.gno".to_string()])
+        .unwrap();
+    let entries = trash.entries().unwrap();
+
+    // neither a whole directory nor saved files can go under a file
+    for package in ["gno.land/p/demo/avl", "gno.land/p/demo/hello"] {
+        let to = blocker.join("pkg");
+        assert!(trash.restore(package, Some(&to)).is_err());
+        assert_eq!(trash.entries().unwrap(), entries);
+    }
+
+    trash.restore("gno.land/p/demo/avl", None).unwrap();
+    assert_eq!(
+        fs::read_to_string(pkg.join("avl.gno")).unwrap(),
+        "package avl // v1"
+    );
+}
+
+#[test]
+fn test_prune_drops_oldest_but_keeps_newest() {
+    let root = tempdir().unwrap();
+    let trash = Trash::new(root.path().join(".gget-trash")).with_limit(15);
+
+    for version in 1..=3 {
+        let pkg = root.path().join("pkg");
+        fs::create_dir_all(&pkg).unwrap();
+        fs::write(pkg.join("a.gno"), format!("package a // v{}", version)).unwrap();
+        trash.discard("gno.land/p/demo/a", &pkg).unwrap();
+    }
+
+    // each entry is 16 bytes, over the limit on its own
+    let entries = trash.entries().unwrap();
+    assert_eq!(entries.len(), 1);
+    let restored = root.path().join("restored");
+    trash.restore("gno.land/p/demo/a", Some(&restored)).unwrap();
+    assert_eq!(
+        fs::read_to_string(restored.join("a.gno")).unwrap(),
+        "package a // v3"
+    );
+}
+
+#[test]
+fn test_refuses_to_trash_its_own_parent() {
+    let root = tempdir().unwrap();
+    let trash = Trash::new(root.path().join(".gget-trash"));
+    fs::write(root.path().join("a.gno"), "package a").unwrap();
+
+    assert!(matches!(
+        trash.discard("gno.land/p/demo/a", root.path()),
+        Err(TrashError::ContainsTrash(_))
+    ));
+    assert!(root.path().join("a.gno").exists());
+}
+
+/// Serves `gno.land/p/demo/hello` as a single `hello.gno`
+fn spawn_node() -> String {
+    let route = warp::post().and(warp::body::json()).map(|request: Value| {
+        let data = general_purpose::STANDARD
+            .decode(request["params"]["data"].as_str().unwrap())
+            .unwrap();
+        let body = if String::from_utf8(data).unwrap().ends_with(".gno") {
+            "package hello // on chain"
+        } else {
+            "hello.gno\n"
+        };
+        warp::reply::json(&json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "result": { "response": { "ResponseBase": {
+                "Error": null,
+                "Data": general_purpose::STANDARD.encode(body),
+                "Log": "",
+            } } }
+        }))
+    });
+    let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    format!("http://{}", addr)
+}
+
+#[tokio::test]
+async fn test_overwritten_files_can_be_restored() {
+    let root = tempdir().unwrap();
+    let cache = tempdir().unwrap();
+    let target = root.path().join("hello");
+    fs::create_dir_all(&target).unwrap();
+    fs::write(target.join("hello.gno"), "package hello // local edit").unwrap();
+    fs::write(target.join("notes.txt"), "kept").unwrap();
+
+    let trash = Trash::new(root.path().join(".gget-trash"));
+    let pm = PackageManager::new(Some(spawn_node()), cache.path().to_path_buf())
+        .with_quiet(true)
+        .with_trash(trash.clone());
+    pm.download_package("gno.land/p/demo/hello", &target)
+        .await
+        .unwrap();
+    assert_eq!(
+        fs::read_to_string(target.join("hello.gno")).unwrap(),
+        "package hello // on chain"
+    );
+
+    let entries = trash.entries().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].files, Some(vec!["hello.gno".to_string()]));
+
+    trash.restore("gno.land/p/demo/hello", None).unwrap();
+    assert_eq!(
+        fs::read_to_string(target.join("hello.gno")).unwrap(),
+        "package hello // local edit"
+    );
+    assert!(target.join("notes.txt").exists());
+}
+
+#[tokio::test]
+async fn test_atomic_replacement_trashes_the_old_copy() {
+    let root = tempdir().unwrap();
+    let cache = tempdir().unwrap();
+    let target = root.path().join("hello");
+    fs::create_dir_all(&target).unwrap();
+    fs::write(target.join("hello.gno"), "package hello // old").unwrap();
+
+    let trash = Trash::new(root.path().join(".gget-trash"));
+    let pm = PackageManager::new(Some(spawn_node()), cache.path().to_path_buf())
+        .with_quiet(true)
+        .with_trash(trash.clone());
+    pm.download_package_atomic("gno.land/p/demo/hello", &target)
+        .await
+        .unwrap();
+
+    let entries = trash.entries().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].files, None);
+    trash.restore("gno.land/p/demo/hello", None).unwrap();
+    assert_eq!(
+        fs::read_to_string(target.join("hello.gno")).unwrap(),
+        "package hello // old"
+    );
+}
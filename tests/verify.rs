@@ -0,0 +1,131 @@
+use gget::lock::{hash_dir, nested_dirs, LockedPackage, Lockfile, LOCKFILE_NAME};
+use gget::verify::{verify_tree, ChecksumManifest, FileStatus, TreeFinding, Verifier, VerifyError};
+use std::fs;
+use tempfile::tempdir;
+
+fn package_dir() -> tempfile::TempDir {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("avl.gno"), "package avl\n").unwrap();
+    dir
+}
+
+#[test]
+fn test_manifest_accepts_matching_package() {
+    let dir = package_dir();
+    let (hash, _) = hash_dir(dir.path()).unwrap();
+
+    let manifest =
+        ChecksumManifest::parse(&format!("# trusted\n\n{}  gno.land/p/demo/avl\n", hash)).unwrap();
+    assert!(manifest.verify("gno.land/p/demo/avl", dir.path()).is_ok());
+}
+
+#[test]
+fn test_manifest_rejects_tampered_package() {
+    let dir = package_dir();
+    let (hash, _) = hash_dir(dir.path()).unwrap();
+    let manifest = ChecksumManifest::parse(&format!("{} gno.land/p/demo/avl", hash)).unwrap();
+
+    fs::write(dir.path().join("avl.gno"), "package avl\n// evil\n").unwrap();
+
+    match manifest.verify("gno.land/p/demo/avl", dir.path()) {
+        Err(VerifyError::Mismatch { package, .. }) => assert_eq!(package, "gno.land/p/demo/avl"),
+        other => panic!("expected mismatch, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_manifest_unlisted_packages() {
+    let dir = package_dir();
+    let manifest = ChecksumManifest::parse("").unwrap();
+    assert!(matches!(
+        manifest.verify("gno.land/p/demo/avl", dir.path()),
+        Err(VerifyError::Untrusted(_))
+    ));
+
+    let lenient = ChecksumManifest::parse("").unwrap().allow_unlisted();
+    assert!(lenient.verify("gno.land/p/demo/avl", dir.path()).is_ok());
+}
+
+#[test]
+fn test_malformed_manifest() {
+    assert!(matches!(
+        ChecksumManifest::parse("not-a-hash gno.land/p/demo/avl"),
+        Err(VerifyError::Manifest { line: 1, .. })
+    ));
+    assert!(matches!(
+        ChecksumManifest::parse("\nonlyonefield"),
+        Err(VerifyError::Manifest { line: 2, .. })
+    ));
+}
+
+/// An output directory holding `avl` and its subpackage `avl/list`, locked
+fn locked_tree() -> tempfile::TempDir {
+    let output = tempdir().unwrap();
+    let avl = output.path().join("gno.land/p/demo/avl");
+    fs::create_dir_all(avl.join("list")).unwrap();
+    fs::write(avl.join("avl.gno"), "package avl\n").unwrap();
+    fs::write(avl.join("node.gno"), "package avl\n\ntype Node struct{}\n").unwrap();
+    fs::write(avl.join("list/list.gno"), "package list\n").unwrap();
+
+    let packages = [
+        "gno.land/p/demo/avl".to_string(),
+        "gno.land/p/demo/avl/list".to_string(),
+    ];
+    let mut lock = Lockfile::default();
+    for pkg in &packages {
+        let nested = nested_dirs(lock.layout, pkg, &packages, output.path());
+        let dir = lock.package_dir(output.path(), pkg);
+        lock.insert(LockedPackage::from_dir(pkg, &dir, &nested).unwrap());
+    }
+    lock.save(&output.path().join(LOCKFILE_NAME)).unwrap();
+    output
+}
+
+#[test]
+fn test_verify_tree_passes_untouched_tree() {
+    let output = locked_tree();
+    let verification = verify_tree(output.path()).unwrap();
+    assert!(verification.passed(), "{:?}", verification.findings);
+    assert_eq!(verification.packages, 2);
+    assert_eq!(verification.files, 3);
+}
+
+#[test]
+fn test_verify_tree_reports_every_discrepancy() {
+    let output = locked_tree();
+    let avl = output.path().join("gno.land/p/demo/avl");
+    fs::write(avl.join("avl.gno"), "package avl\n// local edit\n").unwrap();
+    fs::remove_file(avl.join("node.gno")).unwrap();
+    fs::write(avl.join("list/extra.gno"), "package list\n").unwrap();
+    fs::write(output.path().join("gno.land/notes.txt"), "todo\n").unwrap();
+
+    let verification = verify_tree(output.path()).unwrap();
+    assert!(!verification.passed());
+    let finding = |package: Option<&str>, file: &str, status| TreeFinding {
+        package: package.map(str::to_string),
+        file: file.to_string(),
+        status,
+    };
+    assert_eq!(
+        verification.findings,
+        vec![
+            finding(Some("gno.land/p/demo/avl"), "avl.gno", FileStatus::Modified),
+            finding(Some("gno.land/p/demo/avl"), "node.gno", FileStatus::Missing),
+            finding(
+                Some("gno.land/p/demo/avl/list"),
+                "extra.gno",
+                FileStatus::Extraneous
+            ),
+            finding(None, "gno.land/notes.txt", FileStatus::Extraneous),
+        ]
+    );
+}
+
+#[test]
+fn test_verify_tree_requires_lockfile() {
+    let output = tempdir().unwrap();
+    assert!(matches!(
+        verify_tree(output.path()),
+        Err(VerifyError::MissingLockfile(_))
+    ));
+}
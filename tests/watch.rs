@@ -0,0 +1,109 @@
+use base64::{engine::general_purpose, Engine as _};
+use gget::fetch::PackageManager;
+use gget::watch::{WatchError, Watcher};
+use serde_json::{json, Value};
+use std::fs;
+use std::sync::{Arc, Mutex};
+use tempfile::tempdir;
+use warp::Filter;
+
+/// Serves `gno.land/p/demo/hello` as a single `hello.gno` whose content the
+/// test can change
+fn spawn_node() -> (String, Arc<Mutex<String>>) {
+    let content = Arc::new(Mutex::new("package hello // v1".to_string()));
+    let served = content.clone();
+    let route = warp::post()
+        .and(warp::body::json())
+        .map(move |request: Value| {
+            let data = general_purpose::STANDARD
+                .decode(request["params"]["data"].as_str().unwrap())
+                .unwrap();
+            let body = if String::from_utf8(data).unwrap().ends_with(".gno") {
+                served.lock().unwrap().clone()
+            } else {
+                "hello.gno\n".to_string()
+            };
+            warp::reply::json(&json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "result": { "response": { "ResponseBase": {
+                    "Error": null,
+                    "Data": general_purpose::STANDARD.encode(body),
+                    "Log": "",
+                } } }
+            }))
+        });
+    let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    (format!("http://{}", addr), content)
+}
+
+#[tokio::test]
+async fn test_watch_redownloads_on_change_and_runs_hook() {
+    let (url, content) = spawn_node();
+    let cache = tempdir().unwrap();
+    let out = tempdir().unwrap();
+    let target = out.path().join("hello");
+    let log = out.path().join("hook.log");
+
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+    let mut watcher = Watcher::new(pm, "gno.land/p/demo/hello", &target).with_hook(&format!(
+        "echo \"$GGET_PACKAGE $GGET_OLD_HASH\" >> {}",
+        log.display()
+    ));
+
+    let first = watcher.check().await.unwrap().expect("initial download");
+    assert_eq!(first.old_hash, None);
+    assert_eq!(
+        fs::read_to_string(target.join("hello.gno")).unwrap(),
+        "package hello // v1"
+    );
+
+    assert!(watcher.check().await.unwrap().is_none());
+
+    *content.lock().unwrap() = "package hello // v2".to_string();
+    let second = watcher.check().await.unwrap().expect("change detected");
+    assert_eq!(second.old_hash.as_deref(), Some(first.new_hash.as_str()));
+    assert_ne!(second.new_hash, first.new_hash);
+    assert_eq!(
+        fs::read_to_string(target.join("hello.gno")).unwrap(),
+        "package hello // v2"
+    );
+
+    let log = fs::read_to_string(log).unwrap();
+    let lines: Vec<&str> = log.lines().collect();
+    assert_eq!(
+        lines,
+        vec![
+            "gno.land/p/demo/hello ".to_string(),
+            format!("gno.land/p/demo/hello {}", first.new_hash),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_existing_copy_is_not_redownloaded() {
+    let (url, _) = spawn_node();
+    let cache = tempdir().unwrap();
+    let target = tempdir().unwrap();
+    fs::write(target.path().join("hello.gno"), "package hello // v1").unwrap();
+
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+    let mut watcher = Watcher::new(pm, "gno.land/p/demo/hello", target.path());
+    assert!(watcher.check().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_failing_hook_is_reported() {
+    let (url, _) = spawn_node();
+    let cache = tempdir().unwrap();
+    let target = tempdir().unwrap();
+
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+    let mut watcher =
+        Watcher::new(pm, "gno.land/p/demo/hello", &target.path().join("hello")).with_hook("exit 3");
+    let err = watcher.check().await.unwrap_err();
+    assert!(matches!(err, WatchError::Hook { .. }));
+    // the update itself went through
+    assert!(watcher.check().await.unwrap().is_none());
+}
@@ -0,0 +1,245 @@
+use gget::fetch::PackageManager;
+use gget::lock::{LockError, LockedPackage, Lockfile, LOCKFILE_NAME};
+use gget::report::Report;
+use gget::testing::{download_options, FakeChain};
+use gget::workspace::{
+    find_modules, format_workspace, init_workspace, parse_workspace, Workspace, WorkspaceError,
+    WORKSPACE_NAME,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::tempdir;
+
+fn write_module(dir: &Path, module: &str, source: &str) {
+    fs::create_dir_all(dir).unwrap();
+    fs::write(dir.join("gno.mod"), format!("module {}\n", module)).unwrap();
+    fs::write(dir.join("main.gno"), source).unwrap();
+}
+
+/// A realm importing a package of the workspace and one from the chain,
+/// which itself imports the workspace package
+fn workspace(root: &Path) -> Workspace {
+    write_module(
+        &root.join("r/app"),
+        "gno.land/r/me/app",
+        "package app\n\nimport (\n\t\"gno.land/p/me/util\"\n\t\"gno.land/p/demo/avl\"\n\t\"gno.land/r/me/app/sub\"\n)\n",
+    );
+    write_module(
+        &root.join("p/util"),
+        "gno.land/p/me/util",
+        "package util\n\nimport \"gno.land/p/demo/ufmt\"\n",
+    );
+    fs::write(
+        root.join(WORKSPACE_NAME),
+        "// my monorepo\nuse (\n\t./r/app\n\t\"./p/util\"\n)\n",
+    )
+    .unwrap();
+    Workspace::load(&root.join(WORKSPACE_NAME)).unwrap()
+}
+
+#[test]
+fn test_parse_workspace() {
+    let dirs =
+        parse_workspace("use ./a // realm\n\nuse (\n\t./b\n\n\t\"./c\"\n)\ngo 1.0\n").unwrap();
+    assert_eq!(
+        dirs,
+        [
+            PathBuf::from("./a"),
+            PathBuf::from("./b"),
+            PathBuf::from("./c")
+        ]
+    );
+    assert_eq!(parse_workspace(&format_workspace(&dirs)).unwrap(), dirs);
+
+    assert!(matches!(
+        parse_workspace("use\n"),
+        Err(WorkspaceError::Parse { line: 1, .. })
+    ));
+    assert!(matches!(
+        parse_workspace("use (\n\t./a\n"),
+        Err(WorkspaceError::Parse { .. })
+    ));
+}
+
+#[test]
+fn test_workspace_imports_split_local_and_external() {
+    let root = tempdir().unwrap();
+    let workspace = workspace(root.path());
+    assert_eq!(workspace.members.len(), 2);
+    assert_eq!(
+        workspace.member_of("gno.land/r/me/app/sub").unwrap().module,
+        "gno.land/r/me/app"
+    );
+    assert!(workspace.member_of("gno.land/r/me/application").is_none());
+
+    let imports = workspace.imports(&root.path().join("gno")).unwrap();
+    assert_eq!(
+        imports.external.into_iter().collect::<Vec<_>>(),
+        ["gno.land/p/demo/avl", "gno.land/p/demo/ufmt"]
+    );
+    assert_eq!(
+        imports.local["gno.land/r/me/app"]
+            .iter()
+            .collect::<Vec<_>>(),
+        ["gno.land/p/me/util"]
+    );
+    assert!(!imports.local.contains_key("gno.land/p/me/util"));
+}
+
+#[test]
+fn test_workspace_rejects_duplicate_modules() {
+    let root = tempdir().unwrap();
+    write_module(
+        &root.path().join("a"),
+        "gno.land/p/me/util",
+        "package util\n",
+    );
+    write_module(
+        &root.path().join("b"),
+        "gno.land/p/me/util",
+        "package util\n",
+    );
+    let result = Workspace::from_dirs(
+        root.path().to_path_buf(),
+        &[PathBuf::from("a"), PathBuf::from("b")],
+    );
+    assert!(matches!(
+        result,
+        Err(WorkspaceError::DuplicateModule { .. })
+    ));
+}
+
+#[test]
+fn test_init_workspace_from_found_modules() {
+    let root = tempdir().unwrap();
+    workspace(root.path());
+    fs::remove_file(root.path().join(WORKSPACE_NAME)).unwrap();
+    // vendored packages aren't members
+    write_module(
+        &root.path().join("gno/gno.land/p/demo/avl"),
+        "gno.land/p/demo/avl",
+        "package avl\n",
+    );
+
+    let dirs = find_modules(root.path(), &root.path().join("gno")).unwrap();
+    assert_eq!(dirs, [PathBuf::from("p/util"), PathBuf::from("r/app")]);
+    let path = root.path().join(WORKSPACE_NAME);
+    init_workspace(&path, &dirs).unwrap();
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "use (\n\t./p/util\n\t./r/app\n)\n"
+    );
+    assert_eq!(Workspace::load(&path).unwrap().members.len(), 2);
+    assert!(init_workspace(&path, &dirs).is_err());
+}
+
+#[tokio::test]
+async fn test_sync_fetches_external_imports_only() {
+    let chain = FakeChain::new()
+        .with_package(
+            "gno.land/p/demo/avl",
+            &[("avl.gno", "package avl\n\nimport \"gno.land/p/me/util\"\n")],
+        )
+        .with_package("gno.land/p/demo/ufmt", &[("ufmt.gno", "package ufmt\n")]);
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let root = tempdir().unwrap();
+    let workspace = workspace(root.path());
+    let target = root.path().join("gno");
+
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+    let options = download_options();
+    let (imports, summary) = workspace.sync(&pm, &target, options).await.unwrap();
+    assert_eq!(imports.external.len(), 2);
+    assert!(summary.failed.is_empty());
+
+    assert!(target.join("gno.land/p/demo/avl/avl.gno").exists());
+    assert!(target.join("gno.land/p/demo/ufmt/ufmt.gno").exists());
+    assert!(!target.join("gno.land/p/me").exists());
+    let lock = Lockfile::load(&target.join(LOCKFILE_NAME)).unwrap();
+    assert_eq!(lock.roots, ["gno.land/p/demo/avl", "gno.land/p/demo/ufmt"]);
+    assert_eq!(
+        lock.packages.keys().collect::<Vec<_>>(),
+        ["gno.land/p/demo/avl", "gno.land/p/demo/ufmt"]
+    );
+    // members were never asked for
+    assert!(chain
+        .queries()
+        .iter()
+        .all(|query| !query.data.contains("gno.land/p/me")));
+}
+
+/// A lockfile pinning each of `pins` at a height, with a made up hash
+fn pin_lock(dir: &Path, pins: &[(&str, u64, &str)]) {
+    let mut lock = Lockfile::default();
+    for (path, height, hash) in pins {
+        lock.insert(LockedPackage {
+            path: path.to_string(),
+            height: Some(*height),
+            hash: hash.to_string(),
+            files: Default::default(),
+            required_by: Vec::new(),
+        });
+    }
+    lock.save(&dir.join(LOCKFILE_NAME)).unwrap();
+}
+
+#[tokio::test]
+async fn test_sync_selects_the_highest_height_members_pinned() {
+    let chain = FakeChain::new()
+        .with_package("gno.land/p/demo/avl", &[("avl.gno", "package avl\n")])
+        .with_package("gno.land/p/demo/ufmt", &[("ufmt.gno", "package ufmt\n")]);
+    let url = chain.spawn();
+    let cache = tempdir().unwrap();
+    let root = tempdir().unwrap();
+    let workspace = workspace(root.path());
+    pin_lock(
+        &root.path().join("r/app"),
+        &[("gno.land/p/demo/avl", 100, "a")],
+    );
+    pin_lock(
+        &root.path().join("p/util"),
+        &[
+            ("gno.land/p/demo/avl", 250, "b"),
+            ("gno.land/p/demo/ufmt", 7, "c"),
+        ],
+    );
+    let target = root.path().join("gno");
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+
+    let plan = workspace.plan(&pm, &target).await.unwrap();
+    assert!(!target.exists());
+    assert_eq!(plan.selections.len(), 2);
+    assert!(
+        plan.human()
+            .contains("gno.land/p/demo/avl @ 250 (pinned by gno.land/p/me/util); overrides 100 from gno.land/r/me/app"),
+        "{}",
+        plan.human()
+    );
+
+    let options = download_options();
+    let (_, summary) = workspace.sync(&pm, &target, options).await.unwrap();
+    assert!(summary.failed.is_empty());
+    let heights: std::collections::BTreeSet<_> = chain
+        .queries()
+        .iter()
+        .filter(|query| query.data.starts_with("gno.land/p/demo/avl"))
+        .map(|query| query.height)
+        .collect();
+    assert_eq!(heights.into_iter().collect::<Vec<_>>(), [Some(250)]);
+
+    let lock = Lockfile::load(&target.join(LOCKFILE_NAME)).unwrap();
+    assert_eq!(lock.packages["gno.land/p/demo/avl"].height, Some(250));
+    assert_eq!(lock.packages["gno.land/p/demo/ufmt"].height, Some(7));
+    assert_eq!(lock.selections, plan.selections);
+
+    // members pinning the winning height must agree on what it holds
+    pin_lock(
+        &root.path().join("r/app"),
+        &[("gno.land/p/demo/avl", 250, "a")],
+    );
+    assert!(matches!(
+        workspace.select_heights(&target),
+        Err(WorkspaceError::Lock(LockError::HashConflict { .. }))
+    ));
+}
@@ -0,0 +1,140 @@
+use base64::{engine::general_purpose, Engine as _};
+use futures::{SinkExt, StreamExt};
+use gget::fetch::PackageManager;
+use gget::query::{RpcParams, RpcRequest, RpcResponse};
+use gget::rpc::{RpcClient, RpcClientError, WsRpcClient};
+use serde_json::{json, Value};
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tempfile::tempdir;
+use warp::ws::{Message, WebSocket, Ws};
+use warp::Filter;
+
+/// Answers `vm/qfile` with `<path> contents`, or a file listing for paths
+/// without an extension
+fn qfile_response(request: &Value) -> Value {
+    let data = general_purpose::STANDARD
+        .decode(request["params"]["data"].as_str().unwrap())
+        .unwrap();
+    let path = String::from_utf8(data).unwrap();
+    let body = if path.ends_with(".gno") {
+        format!("package {} // contents", path.rsplit('/').nth(1).unwrap())
+    } else {
+        "hello.gno\n".to_string()
+    };
+    json!({
+        "jsonrpc": "2.0",
+        "id": request["id"],
+        "result": { "response": { "ResponseBase": {
+            "Error": null,
+            "Data": general_purpose::STANDARD.encode(body),
+            "Log": "",
+        } } }
+    })
+}
+
+/// Serves tm2's `/websocket` endpoint. Requests are answered in batches of
+/// `batch`, last request first, so callers only get the right response if
+/// ids are matched. Counts the connections it accepts.
+fn spawn_ws_node(batch: usize) -> (String, Arc<AtomicUsize>) {
+    let connections = Arc::new(AtomicUsize::new(0));
+    let accepted = connections.clone();
+    let route = warp::path("websocket").and(warp::ws()).map(move |ws: Ws| {
+        accepted.fetch_add(1, Ordering::SeqCst);
+        ws.on_upgrade(move |socket: WebSocket| async move {
+            let (mut tx, mut rx) = socket.split();
+            let mut waiting = Vec::new();
+            while let Some(Ok(message)) = rx.next().await {
+                let Ok(text) = message.to_str() else {
+                    continue;
+                };
+                waiting.push(serde_json::from_str::<Value>(text).unwrap());
+                if waiting.len() == batch {
+                    for request in waiting.drain(..).rev() {
+                        let reply = qfile_response(&request).to_string();
+                        tx.send(Message::text(reply)).await.unwrap();
+                    }
+                }
+            }
+        })
+    });
+    let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    (format!("ws://{}/websocket", addr), connections)
+}
+
+fn qfile(path: &str) -> RpcRequest {
+    RpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: 1,
+        method: "abci_query".to_string(),
+        params: RpcParams {
+            path: "vm/qfile".to_string(),
+            data: general_purpose::STANDARD.encode(path),
+            height: None,
+        },
+    }
+}
+
+fn decoded_data(body: &[u8]) -> String {
+    let response: RpcResponse = serde_json::from_slice(body).unwrap();
+    let data = general_purpose::STANDARD
+        .decode(response.result.unwrap().response.response_base.data)
+        .unwrap();
+    String::from_utf8(data).unwrap()
+}
+
+#[tokio::test]
+async fn test_concurrent_requests_share_one_connection() {
+    let (url, connections) = spawn_ws_node(3);
+    let client = WsRpcClient::new(&url);
+
+    let requests = ["a", "b", "c"].map(|name| qfile(&format!("gno.land/p/demo/{0}/{0}.gno", name)));
+    let (a, b, c) = tokio::join!(
+        client.send(&requests[0]),
+        client.send(&requests[1]),
+        client.send(&requests[2]),
+    );
+
+    assert_eq!(decoded_data(&a.unwrap()), "package a // contents");
+    assert_eq!(decoded_data(&b.unwrap()), "package b // contents");
+    assert_eq!(decoded_data(&c.unwrap()), "package c // contents");
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_package_manager_downloads_over_websocket() {
+    let (url, connections) = spawn_ws_node(1);
+    let cache = tempdir().unwrap();
+    let target = tempdir().unwrap();
+
+    let pm = PackageManager::new(Some(url), cache.path().to_path_buf()).with_quiet(true);
+    pm.download_package("gno.land/p/demo/hello", target.path())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        fs::read_to_string(target.path().join("hello.gno")).unwrap(),
+        "package hello // contents"
+    );
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_dropped_connection_fails_waiting_requests() {
+    // accepts the connection, then hangs up on the first request
+    let route = warp::path("websocket").and(warp::ws()).map(|ws: Ws| {
+        ws.on_upgrade(|socket: WebSocket| async move {
+            let (mut tx, mut rx) = socket.split();
+            rx.next().await;
+            let _ = tx.send(Message::close()).await;
+        })
+    });
+    let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+
+    let client = WsRpcClient::new(&format!("ws://{}/websocket", addr));
+    let result = client.send(&qfile("gno.land/p/demo/a")).await;
+    assert!(matches!(result, Err(RpcClientError::ConnectionClosed)));
+}